@@ -1,12 +1,17 @@
 fn main() {
-    println!("cargo:rustc-link-search=framework=/System/Library/Frameworks");
-    println!("cargo:rustc-link-lib=framework=MetalPerformanceShadersGraph");
-    println!("cargo:rustc-link-lib=framework=MetalPerformanceShaders");
-    println!("cargo:rustc-link-lib=framework=Metal");
-    println!("cargo:rustc-link-lib=framework=Foundation");
+    // The build script runs on the host, so check the target through Cargo's environment
+    let target_vendor = std::env::var("CARGO_CFG_TARGET_VENDOR").unwrap_or_default();
 
-    // Link with system library for Block_copy support
-    println!("cargo:rustc-link-lib=System");
+    if target_vendor == "apple" {
+        println!("cargo:rustc-link-search=framework=/System/Library/Frameworks");
+        println!("cargo:rustc-link-lib=framework=MetalPerformanceShadersGraph");
+        println!("cargo:rustc-link-lib=framework=MetalPerformanceShaders");
+        println!("cargo:rustc-link-lib=framework=Metal");
+        println!("cargo:rustc-link-lib=framework=Foundation");
+
+        // Link with system library for Block_copy support
+        println!("cargo:rustc-link-lib=System");
+    }
 
     // Disable doctests to avoid issues with Metal device requirements
     println!("cargo:rustc-cfg=mpsgraph_skip_doctests");
@@ -1,12 +1,18 @@
+#[cfg(target_vendor = "apple")]
 use mpsgraph::{
     core::MPSDataType, executable::MPSGraphExecutionDescriptor, graph::MPSGraph, shape::MPSShape,
     tensor_data::MPSGraphTensorData,
 };
+#[cfg(target_vendor = "apple")]
 use std::collections::HashMap;
+#[cfg(target_vendor = "apple")]
 use std::sync::{Arc, Mutex};
+#[cfg(target_vendor = "apple")]
 use std::thread;
+#[cfg(target_vendor = "apple")]
 use std::time::Duration;
 
+#[cfg(target_vendor = "apple")]
 fn main() {
     // Create the graph and device
     let graph = MPSGraph::new();
@@ -83,3 +89,8 @@ fn main() {
 
     println!("Callback test completed!");
 }
+
+#[cfg(not(target_vendor = "apple"))]
+fn main() {
+    eprintln!("This example needs Metal and only runs on Apple platforms");
+}
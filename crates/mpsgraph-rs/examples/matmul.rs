@@ -1,17 +1,22 @@
+#[cfg(target_vendor = "apple")]
 use metal::{Buffer, Device, MTLResourceOptions};
+#[cfg(target_vendor = "apple")]
 use mpsgraph::{
     core::MPSDataType, executable::MPSGraphExecutionDescriptor, graph::MPSGraph, shape::MPSShape,
     tensor_data::MPSGraphTensorData,
 };
+#[cfg(target_vendor = "apple")]
 use std::collections::HashMap;
 
 // A struct that pairs an MTLBuffer with its MPSGraphTensorData
+#[cfg(target_vendor = "apple")]
 #[derive(Clone)]
 struct TensorBuffer {
     buffer: Buffer,
     tensor_data: MPSGraphTensorData,
 }
 
+#[cfg(target_vendor = "apple")]
 impl TensorBuffer {
     // Create a new TensorBuffer from a vector of f32 data
     fn new(device: &Device, data: &[f32], shape: &MPSShape, data_type: MPSDataType) -> Self {
@@ -58,6 +63,7 @@ impl TensorBuffer {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 fn main() {
     // Get Metal device
     let device = Device::system_default().expect("No Metal device found");
@@ -140,3 +146,8 @@ fn main() {
         println!("  {:?}", row);
     }
 }
+
+#[cfg(not(target_vendor = "apple"))]
+fn main() {
+    eprintln!("This example needs Metal and only runs on Apple platforms");
+}
@@ -1,8 +1,11 @@
+#[cfg(target_vendor = "apple")]
 use metal::{Device, MTLResourceOptions};
+#[cfg(target_vendor = "apple")]
 use mpsgraph::{
     MPSCommandBuffer, MPSDataType, MPSGraph, MPSGraphExecutionDescriptor, MPSGraphTensorData,
     MPSShape,
 };
+#[cfg(target_vendor = "apple")]
 use std::collections::HashMap;
 
 /// A simple example demonstrating how to use MPSGraph with MPSCommandBuffer
@@ -14,6 +17,7 @@ use std::collections::HashMap;
 /// 4. Creating an MPSCommandBuffer and encoding the graph
 /// 5. Committing and waiting for the command buffer
 /// 6. Reading results directly from the Metal buffers
+#[cfg(target_vendor = "apple")]
 fn main() {
     println!("MPSGraph with MPSCommandBuffer example\n");
 
@@ -207,3 +211,8 @@ fn main() {
 
     println!("\nExecution complete!");
 }
+
+#[cfg(not(target_vendor = "apple"))]
+fn main() {
+    eprintln!("This example needs Metal and only runs on Apple platforms");
+}
@@ -1,9 +1,11 @@
+#[cfg(target_vendor = "apple")]
 use mpsgraph::{
     core::MPSDataType,
     data_types::{MPSGraphShapedType, MPSGraphType},
     shape::MPSShape,
 };
 
+#[cfg(target_vendor = "apple")]
 fn main() {
     // Create a basic type
     let graph_type = MPSGraphType::new();
@@ -30,3 +32,8 @@ fn main() {
 
     println!("Type system test completed successfully!");
 }
+
+#[cfg(not(target_vendor = "apple"))]
+fn main() {
+    eprintln!("This example needs Metal and only runs on Apple platforms");
+}
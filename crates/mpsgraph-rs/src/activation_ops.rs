@@ -22,9 +22,10 @@ impl MPSGraph {
             ];
 
             if !tensor.is_null() {
-                MPSGraphTensor(tensor)
+                let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+                MPSGraphTensor::from_retained(tensor)
             } else {
-                MPSGraphTensor(std::ptr::null_mut())
+                MPSGraphTensor::from_retained(std::ptr::null_mut())
             }
         }
     }
@@ -50,7 +51,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -69,10 +70,11 @@ impl MPSGraph {
             ];
 
             if !tensor.is_null() {
-                MPSGraphTensor(tensor)
+                let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+                MPSGraphTensor::from_retained(tensor)
             } else {
                 // Return null tensor if the operation failed
-                MPSGraphTensor(std::ptr::null_mut())
+                MPSGraphTensor::from_retained(std::ptr::null_mut())
             }
         }
     }
@@ -96,7 +98,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -111,9 +113,10 @@ impl MPSGraph {
             let tensor: *mut AnyObject = msg_send![self.0, tanhWithTensor: x.0, name: name_obj];
 
             if !tensor.is_null() {
-                MPSGraphTensor(tensor)
+                let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+                MPSGraphTensor::from_retained(tensor)
             } else {
-                MPSGraphTensor(std::ptr::null_mut())
+                MPSGraphTensor::from_retained(std::ptr::null_mut())
             }
         }
     }
@@ -133,9 +136,10 @@ impl MPSGraph {
             ];
 
             if !tensor.is_null() {
-                MPSGraphTensor(tensor)
+                let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+                MPSGraphTensor::from_retained(tensor)
             } else {
-                MPSGraphTensor(std::ptr::null_mut())
+                MPSGraphTensor::from_retained(std::ptr::null_mut())
             }
         }
     }
@@ -161,7 +165,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -179,9 +183,10 @@ impl MPSGraph {
             ];
 
             if !tensor.is_null() {
-                MPSGraphTensor(tensor)
+                let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+                MPSGraphTensor::from_retained(tensor)
             } else {
-                MPSGraphTensor(std::ptr::null_mut())
+                MPSGraphTensor::from_retained(std::ptr::null_mut())
             }
         }
     }
@@ -205,7 +210,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -232,7 +237,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 }
@@ -23,7 +23,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -42,7 +42,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -61,7 +61,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -80,7 +80,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -99,7 +99,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -118,7 +118,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -137,7 +137,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -156,7 +156,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -175,7 +175,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -194,7 +194,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -213,7 +213,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -232,7 +232,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -251,7 +251,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -270,7 +270,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -290,7 +290,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -309,7 +309,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -328,7 +328,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -347,7 +347,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -366,7 +366,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -385,7 +385,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -404,7 +404,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -423,7 +423,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -442,7 +442,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -461,7 +461,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -480,7 +480,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -502,7 +502,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -521,7 +521,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -540,7 +540,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -558,7 +558,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -577,7 +577,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -596,7 +596,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -615,7 +615,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -634,7 +634,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -653,7 +653,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -672,7 +672,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -691,7 +691,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -710,7 +710,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -729,7 +729,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -752,7 +752,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -771,7 +771,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -795,7 +795,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -817,7 +817,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -839,7 +839,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -861,7 +861,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -883,7 +883,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -905,7 +905,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -927,7 +927,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -949,7 +949,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -971,7 +971,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -993,7 +993,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1015,7 +1015,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1037,7 +1037,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1059,7 +1059,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1081,7 +1081,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1103,7 +1103,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1125,7 +1125,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1147,7 +1147,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1169,7 +1169,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1191,7 +1191,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1213,7 +1213,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1235,7 +1235,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1257,7 +1257,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1279,7 +1279,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1301,7 +1301,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1323,7 +1323,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1345,7 +1345,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1367,7 +1367,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1389,7 +1389,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1406,12 +1406,12 @@ impl MPSGraph {
         };
 
         unsafe {
-            let tensor: *mut AnyObject = msg_send![self.0, leftShiftWithPrimaryTensor: primary.0,
+            let tensor: *mut AnyObject = msg_send![self.0, bitwiseLeftShiftWithPrimaryTensor: primary.0,
                                                   secondaryTensor: secondary.0,
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1428,12 +1428,12 @@ impl MPSGraph {
         };
 
         unsafe {
-            let tensor: *mut AnyObject = msg_send![self.0, rightShiftWithPrimaryTensor: primary.0,
+            let tensor: *mut AnyObject = msg_send![self.0, bitwiseRightShiftWithPrimaryTensor: primary.0,
                                                   secondaryTensor: secondary.0,
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1459,7 +1459,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1483,7 +1483,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1504,7 +1504,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1523,7 +1523,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1545,7 +1545,7 @@ impl MPSGraph {
                                                   name: name_obj];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1564,7 +1564,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 }
@@ -0,0 +1,271 @@
+//! Awaitable execution of graphs and executables.
+//!
+//! [`MPSGraph::run`] and [`MPSGraphExecutable::run`] encode the work on a command queue and
+//! return a future that is resolved by the Metal completion handler, so GPU results can be
+//! awaited from any executor without blocking a thread on `waitUntilCompleted`.
+//!
+//! Cancellation follows the usual rules for Rust futures: nothing is encoded until the
+//! future is first polled. Once the work is submitted, dropping the future doesn't stop the
+//! GPU; the feeds stay retained until the work finishes and the results are then discarded.
+
+use crate::error::{MPSGraphError, Result};
+use crate::executable::{
+    MPSGraphExecutable, MPSGraphExecutableExecutionDescriptor, MPSGraphExecutionDescriptor,
+};
+use crate::graph::{checked_results, MPSGraph};
+use crate::tensor::MPSGraphTensor;
+use crate::tensor_data::MPSGraphTensorData;
+use block2::RcBlock;
+use metal::foreign_types::ForeignType;
+use objc2::msg_send;
+use objc2::runtime::AnyObject;
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, Waker};
+
+/// Completion handler shared by `MPSGraphExecutionHandler` and
+/// `MPSGraphExecutableCompletionHandler`: results and an optional `NSError`
+type CompletionFn = dyn Fn(*mut AnyObject, *mut AnyObject);
+
+struct State<T> {
+    result: Option<Result<T>>,
+    waker: Option<Waker>,
+    finished: bool,
+}
+
+/// Future resolved from an Objective-C completion handler
+struct Completion<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+/// The completion handler's end of a [`Completion`]
+struct Completer<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+fn completion<T>() -> (Completion<T>, Completer<T>) {
+    let state = Arc::new(Mutex::new(State {
+        result: None,
+        waker: None,
+        finished: false,
+    }));
+    (
+        Completion {
+            state: state.clone(),
+        },
+        Completer { state },
+    )
+}
+
+impl<T> Completer<T> {
+    /// Stores the result of `produce` and wakes the task awaiting it
+    ///
+    /// Runs on a Metal thread inside an Objective-C block, so a panic is turned into an
+    /// error instead of unwinding into Objective-C.
+    fn complete(&self, produce: impl FnOnce() -> Result<T>) {
+        let result = catch_unwind(AssertUnwindSafe(produce)).unwrap_or_else(|_| {
+            Err(MPSGraphError::ExecutionFailed(String::from(
+                "converting the results panicked",
+            )))
+        });
+        let waker = {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            if state.result.is_some() || state.finished {
+                return;
+            }
+            state.result = Some(result);
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Future for Completion<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(result) = state.result.take() {
+            state.finished = true;
+            return Poll::Ready(result);
+        }
+        assert!(!state.finished, "execution future polled after completion");
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// An Objective-C object retained until the completion handler is released
+struct KeepAlive(*mut AnyObject);
+
+impl Drop for KeepAlive {
+    fn drop(&mut self) {
+        unsafe { objc2::ffi::objc_release(self.0 as *mut _) };
+    }
+}
+
+/// Converts an `NSDictionary<MPSGraphTensor, MPSGraphTensorData>` without taking ownership
+unsafe fn results_from_dictionary(
+    dictionary: *mut AnyObject,
+) -> HashMap<MPSGraphTensor, MPSGraphTensorData> {
+    let keys: *mut AnyObject = msg_send![dictionary, allKeys];
+    let count: usize = msg_send![keys, count];
+    let mut results = HashMap::with_capacity(count);
+    for i in 0..count {
+        let key: *mut AnyObject = msg_send![keys, objectAtIndex: i];
+        let value: *mut AnyObject = msg_send![dictionary, objectForKey: key];
+        let tensor = MPSGraphTensor::from_retained(objc2::ffi::objc_retain(key));
+        results.insert(tensor, MPSGraphTensorData(objc2::ffi::objc_retain(value)));
+    }
+    results
+}
+
+/// Turns the arguments of a completion handler into results or an error
+unsafe fn completed<T>(
+    results: *mut AnyObject,
+    error: *mut AnyObject,
+    convert: impl FnOnce(*mut AnyObject) -> T,
+) -> Result<T> {
+    if !error.is_null() {
+        return Err(MPSGraphError::from_ns_error(error));
+    }
+    if results.is_null() {
+        return Err(MPSGraphError::ExecutionFailed(String::from(
+            "MPSGraph completed without results",
+        )));
+    }
+    Ok(convert(results))
+}
+
+impl MPSGraph {
+    /// Runs the graph on `command_queue`, resolving once the GPU has finished
+    ///
+    /// Feeds are checked with [`MPSGraph::validate_feeds`] first; execution errors reported
+    /// by Metal become [`MPSGraphError::CommandBuffer`]. See the [module
+    /// documentation](crate::async_run) for what dropping the future does.
+    pub async fn run(
+        &self,
+        command_queue: &metal::CommandQueue,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+    ) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        self.validate_feeds(feeds, targets)?;
+        let results = self.submit(command_queue, feeds, targets).await?;
+        checked_results(results, targets)
+    }
+
+    fn submit(
+        &self,
+        command_queue: &metal::CommandQueue,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+    ) -> Completion<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        let (future, completer) = completion();
+        unsafe {
+            let (keys, values): (Vec<_>, Vec<_>) = feeds
+                .iter()
+                .map(|(tensor, data)| (tensor.0, data.0))
+                .unzip();
+            let feed_dict = KeepAlive(crate::core::create_ns_dictionary_from_pointers(
+                &keys, &values,
+            ));
+            let targets_raw: Vec<*mut AnyObject> = targets.iter().map(|t| t.0).collect();
+            let targets_array = crate::core::create_ns_array_from_pointers(&targets_raw);
+            let queue = command_queue.as_ptr() as *mut AnyObject;
+
+            let descriptor = MPSGraphExecutionDescriptor::new();
+            let feed_dict_ptr = feed_dict.0;
+            let on_completion = move |results: *mut AnyObject, error: *mut AnyObject| {
+                let _feeds = &feed_dict;
+                completer.complete(|| completed(results, error, |d| results_from_dictionary(d)));
+            };
+            let handler: RcBlock<CompletionFn> = RcBlock::new(on_completion);
+            let _: () = msg_send![descriptor.0, setCompletionHandler: &*handler];
+            let _: *mut AnyObject = msg_send![
+                self.0,
+                runAsyncWithMTLCommandQueue: queue,
+                feeds: feed_dict_ptr,
+                targetTensors: targets_array,
+                targetOperations: std::ptr::null_mut::<AnyObject>(),
+                executionDescriptor: descriptor.0
+            ];
+            objc2::ffi::objc_release(targets_array as *mut _);
+        }
+        future
+    }
+}
+
+impl MPSGraphExecutable {
+    /// Runs the executable on `command_queue`, resolving once the GPU has finished
+    ///
+    /// `feeds` must supply exactly the executable's [`feed_tensors`](Self::feed_tensors), as
+    /// checked by [`validate_feeds`](Self::validate_feeds); the results are keyed by its
+    /// [`target_tensors`](Self::target_tensors).
+    pub async fn run(
+        &self,
+        command_queue: &metal::CommandQueue,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+    ) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        self.validate_feeds(feeds)?;
+        let targets = self.target_tensors();
+        // Raw pointers aren't Send, so the inputs must not be held across the await
+        let completion = {
+            let values: Vec<*mut AnyObject> = self
+                .feed_tensors()
+                .iter()
+                .map(|tensor| feeds[tensor].0)
+                .collect();
+            self.submit(command_queue, &values, targets.clone())
+        };
+        let results = completion.await?;
+        checked_results(results, &targets)
+    }
+
+    fn submit(
+        &self,
+        command_queue: &metal::CommandQueue,
+        inputs: &[*mut AnyObject],
+        targets: Vec<MPSGraphTensor>,
+    ) -> Completion<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        let (future, completer) = completion();
+        unsafe {
+            let inputs_array = KeepAlive(crate::core::create_ns_array_from_pointers(inputs));
+            let queue = command_queue.as_ptr() as *mut AnyObject;
+
+            let descriptor = MPSGraphExecutableExecutionDescriptor::new();
+            let inputs_ptr = inputs_array.0;
+            let on_completion = move |results: *mut AnyObject, error: *mut AnyObject| {
+                let _inputs = &inputs_array;
+                completer.complete(|| {
+                    completed(results, error, |array| {
+                        let count: usize = msg_send![array, count];
+                        let mut map = HashMap::with_capacity(count);
+                        for (i, tensor) in targets.iter().take(count).enumerate() {
+                            let data: *mut AnyObject = msg_send![array, objectAtIndex: i];
+                            map.insert(
+                                tensor.clone(),
+                                MPSGraphTensorData(objc2::ffi::objc_retain(data)),
+                            );
+                        }
+                        map
+                    })
+                });
+            };
+            let handler: RcBlock<CompletionFn> = RcBlock::new(on_completion);
+            let _: () = msg_send![descriptor.0, setCompletionHandler: &*handler];
+            let _: *mut AnyObject = msg_send![
+                self.0,
+                runAsyncWithMTLCommandQueue: queue,
+                inputsArray: inputs_ptr,
+                resultsArray: std::ptr::null_mut::<AnyObject>(),
+                executionDescriptor: descriptor.0
+            ];
+        }
+        future
+    }
+}
@@ -92,7 +92,7 @@ impl MPSGraph {
                     let tensor_ptr: *mut AnyObject =
                         obj as *const objc2::runtime::AnyObject as *mut AnyObject;
                     let tensor = objc2::ffi::objc_retain(tensor_ptr as *mut _);
-                    results.push(MPSGraphTensor(tensor));
+                    results.push(MPSGraphTensor::from_retained(tensor));
                 }
             }
 
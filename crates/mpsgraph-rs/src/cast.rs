@@ -0,0 +1,131 @@
+//! Host-side conversions between floating-point data types.
+//!
+//! These helpers convert little-endian host buffers without going through a graph, for
+//! example to store Float32 weights as Float16 while loading them. The half-precision
+//! conversions round to nearest, ties to even, and don't need the `half` feature.
+
+use crate::core::MPSDataType;
+
+/// Converts IEEE 754 half-precision bits to `f32`
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        0 => {
+            // Subnormal: mantissa * 2^-24
+            let value = mantissa as f32 / (1u32 << 24) as f32;
+            return if sign != 0 { -value } else { value };
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+/// Rounds an `f32` to IEEE 754 half-precision bits
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // Infinity stays infinite, NaN stays a quiet NaN
+        let nan = if mantissa != 0 {
+            0x200 | (mantissa >> 13) as u16
+        } else {
+            0
+        };
+        return sign | 0x7c00 | nan;
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if half_exponent <= 0 {
+        // Subnormal or zero in half precision
+        if half_exponent < -10 {
+            return sign;
+        }
+        let full = mantissa | 0x80_0000;
+        let shift = (14 - half_exponent) as u32;
+        let mut half = full >> shift;
+        let rest = full & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        if rest > halfway || (rest == halfway && half & 1 == 1) {
+            half += 1;
+        }
+        return sign | half as u16;
+    }
+
+    // A carry out of the mantissa correctly bumps the exponent, up to infinity
+    let mut half = ((half_exponent as u32) << 10) | (mantissa >> 13);
+    let rest = mantissa & 0x1fff;
+    if rest > 0x1000 || (rest == 0x1000 && half & 1 == 1) {
+        half += 1;
+    }
+    sign | half as u16
+}
+
+/// Converts bfloat16 bits to `f32`
+pub fn bf16_to_f32(bits: u16) -> f32 {
+    f32::from_bits((bits as u32) << 16)
+}
+
+/// Rounds an `f32` to bfloat16 bits
+pub fn f32_to_bf16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    if value.is_nan() {
+        return ((bits >> 16) | 0x40) as u16;
+    }
+    let rounding = 0x7fff + ((bits >> 16) & 1);
+    (bits.wrapping_add(rounding) >> 16) as u16
+}
+
+/// Converts little-endian elements between `Float16`, `BFloat16`, `Float32` and `Float64`
+///
+/// Returns `None` if either type isn't one of those, or if `data` isn't a whole number of
+/// elements. Conversions to and from the 16-bit types go through `f32`.
+pub fn cast(data: &[u8], from: MPSDataType, to: MPSDataType) -> Option<Vec<u8>> {
+    let from_size = float_size(from)?;
+    let to_size = float_size(to)?;
+    if !data.chunks_exact(from_size).remainder().is_empty() {
+        return None;
+    }
+    if from == to {
+        return Some(data.to_vec());
+    }
+
+    let mut out = Vec::with_capacity(data.len() / from_size * to_size);
+    for element in data.chunks_exact(from_size) {
+        let value = match from {
+            MPSDataType::Float16 => f16_to_f32(u16::from_le_bytes([element[0], element[1]])) as f64,
+            MPSDataType::BFloat16 => {
+                bf16_to_f32(u16::from_le_bytes([element[0], element[1]])) as f64
+            }
+            MPSDataType::Float32 => f32::from_le_bytes(element.try_into().unwrap()) as f64,
+            _ => f64::from_le_bytes(element.try_into().unwrap()),
+        };
+        match to {
+            MPSDataType::Float16 => out.extend_from_slice(&f32_to_f16(value as f32).to_le_bytes()),
+            MPSDataType::BFloat16 => {
+                out.extend_from_slice(&f32_to_bf16(value as f32).to_le_bytes())
+            }
+            MPSDataType::Float32 => out.extend_from_slice(&(value as f32).to_le_bytes()),
+            _ => out.extend_from_slice(&value.to_le_bytes()),
+        }
+    }
+    Some(out)
+}
+
+fn float_size(data_type: MPSDataType) -> Option<usize> {
+    match data_type {
+        MPSDataType::Float16 | MPSDataType::BFloat16 => Some(2),
+        MPSDataType::Float32 => Some(4),
+        MPSDataType::Float64 => Some(8),
+        _ => None,
+    }
+}
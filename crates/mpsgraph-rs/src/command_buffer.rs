@@ -1,4 +1,5 @@
 use crate::core::AsRawObject;
+use crate::error::{MPSGraphError, Result};
 use metal::foreign_types::ForeignType;
 use metal::{BlitCommandEncoder, CommandBuffer, CommandQueue, ComputeCommandEncoder, Device};
 use objc2::msg_send;
@@ -276,6 +277,31 @@ impl MPSCommandBuffer {
         }
     }
 
+    /// Returns the command buffer error as an [`MPSGraphError::CommandBuffer`]
+    ///
+    /// Returns `Ok(())` when the command buffer has not failed.
+    pub fn check_error(&self) -> Result<()> {
+        unsafe {
+            let cmd_buf_ptr: *mut AnyObject = msg_send![self.0, commandBuffer];
+            if cmd_buf_ptr.is_null() {
+                return Ok(());
+            }
+
+            let error_ptr: *mut AnyObject = msg_send![cmd_buf_ptr, error];
+            if error_ptr.is_null() {
+                Ok(())
+            } else {
+                Err(MPSGraphError::from_ns_error(error_ptr))
+            }
+        }
+    }
+
+    /// Waits until this command buffer has completed execution and reports any execution error
+    pub fn try_wait_until_completed(&self) -> Result<()> {
+        self.wait_until_completed();
+        self.check_error()
+    }
+
     /// Creates a compute command encoder to encode into this command buffer
     pub fn new_compute_command_encoder(&self) -> Option<ComputeCommandEncoder> {
         unsafe {
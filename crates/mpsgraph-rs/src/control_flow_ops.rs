@@ -1,15 +1,158 @@
-use objc2::runtime::AnyObject;
-use std::ops::Fn;
-use std::ffi::c_void;
+use crate::core::{create_ns_array_from_pointers, AsRawObject, MPSDataType, NSString};
+use crate::executable::tensors_from_array;
 use crate::graph::MPSGraph;
-use crate::tensor::MPSGraphTensor;
 use crate::operation::MPSGraphOperation;
-use objc2_foundation::NSString;
-use crate::core::AsRawObject;
+use crate::tensor::MPSGraphTensor;
+use block2::RcBlock;
 use objc2::msg_send;
+use objc2::runtime::AnyObject;
+use std::any::Any;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
-// Import the block_kit crate for Objective-C blocks
-use block_kit::{Block, RcBlock};
+type NullaryFn = dyn Fn() -> *mut AnyObject;
+type UnaryFn = dyn Fn(*mut AnyObject) -> *mut AnyObject;
+type BinaryFn = dyn Fn(*mut AnyObject, *mut AnyObject) -> *mut AnyObject;
+
+/// State shared by the blocks passed to one control flow call
+///
+/// MPSGraph runs the blocks while the call that received them builds the graph, but it may
+/// keep them alive afterwards. The blocks are therefore `'static` and reach the borrowed
+/// closures through pointers that are only followed while the scope is open; once the call
+/// returns, a late invocation returns nil without touching them. `MPSGraph` is `Send`, so
+/// the blocks may be released or invoked on another thread, and the state is atomic.
+///
+/// A panic in a closure is caught before it can unwind into Objective-C. The closures that
+/// are still to run are skipped, the blocks return their inputs unchanged (or, for `if_op`,
+/// the other branch's results) so that MPSGraph can finish, and the panic resumes once the
+/// call has returned.
+struct BlockScope {
+    open: AtomicBool,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+    /// Objects returned to MPSGraph, released when the call returns
+    returned: Mutex<Vec<*mut AnyObject>>,
+}
+
+// SAFETY: the raw pointers are retained Objective-C objects, only touched under the lock
+unsafe impl Send for BlockScope {}
+unsafe impl Sync for BlockScope {}
+
+impl BlockScope {
+    fn new() -> Arc<Self> {
+        Arc::new(BlockScope {
+            open: AtomicBool::new(true),
+            panic: Mutex::new(None),
+            returned: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn is_open(&self) -> bool {
+        self.open.load(Ordering::Acquire)
+    }
+
+    /// Runs a user closure unless an earlier one panicked, catching its panic
+    fn catch<R>(&self, f: impl FnOnce() -> R) -> Option<R> {
+        if self.panic.lock().unwrap().is_some() {
+            return None;
+        }
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(value) => Some(value),
+            Err(payload) => {
+                *self.panic.lock().unwrap() = Some(payload);
+                None
+            }
+        }
+    }
+
+    /// Takes a +1 reference to an object returned to MPSGraph and releases it once the
+    /// call returns
+    fn keep(&self, object: *mut AnyObject) -> *mut AnyObject {
+        self.returned.lock().unwrap().push(object);
+        object
+    }
+
+    /// Returns an array of `tensors` that stays alive until the call returns
+    fn return_tensors(&self, tensors: &[MPSGraphTensor]) -> *mut AnyObject {
+        let pointers: Vec<*mut AnyObject> = tensors.iter().map(|t| t.0).collect();
+        self.keep(create_ns_array_from_pointers(&pointers))
+    }
+
+    fn nullary_block(
+        self: &Arc<Self>,
+        f: &(dyn Fn() -> *mut AnyObject + '_),
+    ) -> RcBlock<NullaryFn> {
+        // SAFETY: only the lifetime changes, and `run` closes the scope before `f` goes
+        // out of scope, after which the pointer is never followed
+        let f: *const NullaryFn = unsafe { mem::transmute(f) };
+        let scope = Arc::clone(self);
+        RcBlock::new(move || {
+            if scope.is_open() {
+                unsafe { (*f)() }
+            } else {
+                ptr::null_mut()
+            }
+        })
+    }
+
+    fn unary_block(
+        self: &Arc<Self>,
+        f: &(dyn Fn(*mut AnyObject) -> *mut AnyObject + '_),
+    ) -> RcBlock<UnaryFn> {
+        // SAFETY: as in `nullary_block`
+        let f: *const UnaryFn = unsafe { mem::transmute(f) };
+        let scope = Arc::clone(self);
+        RcBlock::new(move |a| {
+            if scope.is_open() {
+                unsafe { (*f)(a) }
+            } else {
+                ptr::null_mut()
+            }
+        })
+    }
+
+    fn binary_block(
+        self: &Arc<Self>,
+        f: &(dyn Fn(*mut AnyObject, *mut AnyObject) -> *mut AnyObject + '_),
+    ) -> RcBlock<BinaryFn> {
+        // SAFETY: as in `nullary_block`
+        let f: *const BinaryFn = unsafe { mem::transmute(f) };
+        let scope = Arc::clone(self);
+        RcBlock::new(move |a, b| {
+            if scope.is_open() {
+                unsafe { (*f)(a, b) }
+            } else {
+                ptr::null_mut()
+            }
+        })
+    }
+
+    /// Makes the MPSGraph call, closes the scope and resumes a panic caught in a closure
+    fn run<T>(&self, call: impl FnOnce() -> T) -> T {
+        struct Close<'a>(&'a BlockScope);
+
+        impl Drop for Close<'_> {
+            fn drop(&mut self) {
+                self.0.open.store(false, Ordering::Release);
+                for object in self.0.returned.lock().unwrap().drain(..) {
+                    unsafe { objc2::ffi::objc_release(object as *mut _) };
+                }
+            }
+        }
+
+        let result = {
+            let _close = Close(self);
+            call()
+        };
+        let payload = self.panic.lock().unwrap().take();
+        if let Some(payload) = payload {
+            panic::resume_unwind(payload);
+        }
+        result
+    }
+}
 
 /// Control flow operations for MPSGraph
 ///
@@ -18,6 +161,10 @@ use block_kit::{Block, RcBlock};
 /// - Conditional execution (if-then-else)
 /// - While loops
 /// - For loops
+///
+/// The closures build the operations of each region. MPSGraph calls them before the method
+/// returns, and they may borrow from the caller, for example to capture the graph. A panic
+/// in a closure never unwinds through MPSGraph: it is resumed once MPSGraph returns.
 impl MPSGraph {
     /// Creates a control dependency between operations.
     ///
@@ -33,56 +180,47 @@ impl MPSGraph {
     /// # Returns
     ///
     /// A vector of tensors that are the result of the dependent_block
-    pub fn control_dependency<F>(&self, 
-                              operations:  &[&MPSGraphOperation],
-                              dependent_block:  F,
-                              name:  Option<&str>) -> Vec<MPSGraphTensor>
+    pub fn control_dependency<F>(
+        &self,
+        operations: &[&MPSGraphOperation],
+        dependent_block: F,
+        name: Option<&str>,
+    ) -> Vec<MPSGraphTensor>
     where
-        F:  Fn() -> Vec<MPSGraphTensor>
+        F: Fn() -> Vec<MPSGraphTensor>,
     {
-        unsafe {
-            let name_obj = match name {
-                Some(s) => NSString::from_str(s).as_raw_object(),
-                None => std::ptr::null_mut(),
-            };
-            
-            // Convert operations to NSArray
-            let operations_ptr: Vec<*mut AnyObject> = operations.iter().map(|op| op.0).collect();
-            let operations_array = crate::core::create_ns_array_from_pointers(&operations_ptr);
-            
-            // Create the block callback
-            let block = RcBlock::new(move || {
-                // Call the user's block
-                let tensors = dependent_block();
-                // Convert Vec<MPSGraphTensor> to NSArray of pointers
-                let tensor_ptrs: Vec<*mut AnyObject> = tensors.iter().map(|t| t.0).collect();
-                let tensor_array = crate::core::create_ns_array_from_pointers(&tensor_ptrs);
-                // Return the NSArray pointer
-                tensor_array
-            });
-            
-            // Call the Objective-C method
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+        let operation_ptrs: Vec<*mut AnyObject> = operations.iter().map(|op| op.0).collect();
+        let operations_array = create_ns_array_from_pointers(&operation_ptrs);
+
+        let scope = BlockScope::new();
+        let dependent = || {
+            let tensors = scope.catch(&dependent_block).unwrap_or_default();
+            scope.return_tensors(&tensors)
+        };
+        let dependent = scope.nullary_block(&dependent);
+
+        let result = scope.run(|| unsafe {
             let result_array: *mut AnyObject = msg_send![
-                self.0, controlDependencyWithOperations: operations_array.0,
-                dependentBlock: &*block,
+                self.0, controlDependencyWithOperations: operations_array,
+                dependentBlock: &*dependent,
                 name: name_obj,
             ];
-            
-            // Get the count of result tensors
-            let count: usize = msg_send![result_array, count];
-            
-            // Convert NSArray to Vec<MPSGraphTensor>
-            let mut result = Vec::with_capacity(count);
-            for i in 0..count {
-                let tensor: *mut AnyObject = msg_send![result_array, objectAtIndex: i,,,,,,,];
-                let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-                result.push(MPSGraphTensor(tensor));
+            tensors_from_array(result_array)
+        });
+
+        unsafe {
+            objc2::ffi::objc_release(operations_array as *mut _);
+            if !name_obj.is_null() {
+                objc2::ffi::objc_release(name_obj as *mut _);
             }
-            
-            result
         }
+        result
     }
-    
+
     /// Creates an if-then-else operation.
     ///
     /// This allows for conditional execution of operations based on a predicate tensor.
@@ -91,78 +229,70 @@ impl MPSGraph {
     ///
     /// * `predicate` - A scalar tensor that determines which branch to execute
     /// * `then_block` - A closure that returns tensors for the "then" branch
-    /// * `else_block` - An optional closure that returns tensors for the "else" branch
+    /// * `else_block` - An optional closure that returns tensors for the "else" branch.
+    ///   Without one, the "then" branch must not return any tensors.
     /// * `name` - Optional name for the operation
     ///
     /// # Returns
     ///
     /// A vector of tensors that are the result of either the then_block or else_block,
     /// depending on the value of the predicate tensor
-    pub fn if_op<F, G>(&self,
-                    predicate:  &MPSGraphTensor,
-                    then_block:  F,
-                    else_block:  Option<G>,
-                    name:  Option<&str>) -> Vec<MPSGraphTensor>
+    pub fn if_op<F, G>(
+        &self,
+        predicate: &MPSGraphTensor,
+        then_block: F,
+        else_block: Option<G>,
+        name: Option<&str>,
+    ) -> Vec<MPSGraphTensor>
     where
-        F:  Fn() -> Vec<MPSGraphTensor>,
-        G:  Fn() -> Vec<MPSGraphTensor>
+        F: Fn() -> Vec<MPSGraphTensor>,
+        G: Fn() -> Vec<MPSGraphTensor>,
     {
-        unsafe {
-            let name_obj = match name {
-                Some(s) => NSString::from_str(s).as_raw_object(),
-                None => std::ptr::null_mut(),
-            };
-            
-            // Create the then block callback
-            let then_callback = RcBlock::new(move || {
-                // Call the user's block
-                let tensors = then_block();
-                // Convert Vec<MPSGraphTensor> to NSArray of pointers
-                let tensor_ptrs: Vec<*mut AnyObject> = tensors.iter().map(|t| t.0).collect();
-                let tensor_array = crate::core::create_ns_array_from_pointers(&tensor_ptrs);
-                // Return the NSArray pointer
-                tensor_array
-            });
-            
-            // Create the else block callback if provided
-            let else_callback = if let Some(else_fn) = else_block {
-                let block = RcBlock::new(move || {
-                    // Call the user's block
-                    let tensors = else_fn();
-                    // Convert Vec<MPSGraphTensor> to NSArray of pointers
-                    let tensor_ptrs: Vec<*mut AnyObject> = tensors.iter().map(|t| t.0).collect();
-                    let tensor_array = crate::core::create_ns_array_from_pointers(&tensor_ptrs);
-                    // Return the NSArray pointer
-                    tensor_array
-                });
-                Some(block)
-            } else {
-                None
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+
+        let scope = BlockScope::new();
+        // A branch that panics, or is skipped after the other one panicked, returns the other
+        // branch's results so that MPSGraph sees matching branches and returns normally
+        let last_results = Mutex::new(Vec::new());
+        let branch = |block: &dyn Fn() -> Vec<MPSGraphTensor>| {
+            let tensors = match scope.catch(block) {
+                Some(tensors) => {
+                    *last_results.lock().unwrap() = tensors.clone();
+                    tensors
+                }
+                None => last_results.lock().unwrap().clone(),
             };
-            
-            // Call the Objective-C method
+            scope.return_tensors(&tensors)
+        };
+        let then_branch = || branch(&then_block);
+        let then_branch = scope.nullary_block(&then_branch);
+        let else_branch = else_block.as_ref().map(|else_block| {
+            let branch = &branch;
+            move || branch(else_block)
+        });
+        let else_branch = else_branch
+            .as_ref()
+            .map(|else_branch| scope.nullary_block(else_branch));
+
+        let result = scope.run(|| unsafe {
             let result_array: *mut AnyObject = msg_send![
                 self.0, ifWithPredicateTensor: predicate.0,
-                thenBlock: &*then_callback,
-                elseBlock: else_callback,
+                thenBlock: &*then_branch,
+                elseBlock: else_branch.as_deref(),
                 name: name_obj,
             ];
-            
-            // Get the count of result tensors
-            let count: usize = msg_send![result_array, count];
-            
-            // Convert NSArray to Vec<MPSGraphTensor>
-            let mut result = Vec::with_capacity(count);
-            for i in 0..count {
-                let tensor: *mut AnyObject = msg_send![result_array, objectAtIndex: i,,];
-                let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-                result.push(MPSGraphTensor(tensor));
-            }
-            
-            result
+            tensors_from_array(result_array)
+        });
+
+        if !name_obj.is_null() {
+            unsafe { objc2::ffi::objc_release(name_obj as *mut _) };
         }
+        result
     }
-    
+
     /// Creates a while loop operation.
     ///
     /// This allows for iterative execution of operations until a condition is met.
@@ -170,105 +300,75 @@ impl MPSGraph {
     /// # Parameters
     ///
     /// * `initial_inputs` - Initial tensors passed to the loop
-    /// * `before_block` - A closure that evaluates the condition and produces intermediate tensors
-    /// * `after_block` - A closure that executes the loop body
+    /// * `before_block` - A closure that receives the loop inputs, pushes the tensors to
+    ///   pass on (to the body, or out of the loop) and returns a scalar Bool condition
+    /// * `after_block` - A closure that executes the loop body and returns the next inputs
     /// * `name` - Optional name for the operation
     ///
     /// # Returns
     ///
     /// A vector of tensors that are the final result of the while loop
-    pub fn while_loop<F, G>(&self,
-                         initial_inputs:  &[&MPSGraphTensor],
-                         before_block:  F,
-                         after_block:  G,
-                         name:  Option<&str>) -> Vec<MPSGraphTensor>
+    pub fn while_loop<F, G>(
+        &self,
+        initial_inputs: &[&MPSGraphTensor],
+        before_block: F,
+        after_block: G,
+        name: Option<&str>,
+    ) -> Vec<MPSGraphTensor>
     where
-        F:  Fn(&[MPSGraphTensor], &mut Vec<MPSGraphTensor>) -> MPSGraphTensor,
-        G:  Fn(&[MPSGraphTensor]) -> Vec<MPSGraphTensor>
+        F: Fn(&[MPSGraphTensor], &mut Vec<MPSGraphTensor>) -> MPSGraphTensor,
+        G: Fn(&[MPSGraphTensor]) -> Vec<MPSGraphTensor>,
     {
-        unsafe {
-            let name_obj = match name {
-                Some(s) => NSString::from_str(s).as_raw_object(),
-                None => std::ptr::null_mut(),
-            };
-            
-            // Convert initial inputs to NSArray
-            let initial_inputs_array = crate::core::NSArray::from_objects(
-                &initial_inputs.iter().map(|t| t.0).collect::<Vec<_>>()
-            );
-            
-            // Create the before block callback
-            let before_callback = ConcreteBlock::new(move |inputs: *mut AnyObject, results: *mut AnyObject| -> *mut AnyObject {
-                // Convert NSArray to Vec<MPSGraphTensor>
-                let inputs_count: usize = msg_send![inputs, count];
-                let mut input_tensors = Vec::with_capacity(inputs_count);
-                for i in 0..inputs_count {
-                    let tensor: *mut AnyObject = msg_send![inputs, objectAtIndex: i];
-                    input_tensors.push(MPSGraphTensor(tensor));
-                }
-                
-                // Create a mutable Vec to hold result tensors
-                let mut result_tensors = Vec::new();
-                
-                // Call the user's block
-                let condition = before_block(&input_tensors, &mut result_tensors);
-                
-                // Set results array
-                if !result_tensors.is_empty() {
-                    let result_ptrs: Vec<*mut Object> = result_tensors.iter().map(|t| t.0).collect();
-                    for i in 0..result_ptrs.len() {
-                        let _: () = msg_send![results, addObject: result_ptrs[i]];
-                    }
-                }
-                
-                // Return the condition tensor
-                condition.0
-            }).copy();
-            
-            // Create the after block callback
-            let after_callback = ConcreteBlock::new(move |body_args: *mut AnyObject| -> *mut AnyObject {
-                // Convert NSArray to Vec<MPSGraphTensor>
-                let args_count: usize = msg_send![body_args, count];
-                let mut body_arguments = Vec::with_capacity(args_count);
-                for i in 0..args_count {
-                    let tensor: *mut AnyObject = msg_send![body_args, objectAtIndex: i];
-                    body_arguments.push(MPSGraphTensor(tensor));
-                }
-                
-                // Call the user's block
-                let result_tensors = after_block(&body_arguments);
-                
-                // Convert Vec<MPSGraphTensor> to NSArray
-                let result_ptrs: Vec<*mut Object> = result_tensors.iter().map(|t| t.0).collect();
-                let result_array = crate::core::NSArray::from_objects(&result_ptrs);
-                
-                // Return the NSArray
-                result_array.0
-            }).copy();
-            
-            // Call the Objective-C method
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+        let input_ptrs: Vec<*mut AnyObject> = initial_inputs.iter().map(|t| t.0).collect();
+        let inputs_array = create_ns_array_from_pointers(&input_ptrs);
+
+        let scope = BlockScope::new();
+        let before = |inputs: *mut AnyObject, results: *mut AnyObject| {
+            let inputs = unsafe { tensors_from_array(inputs) };
+            let mut passed = Vec::new();
+            let condition = scope
+                .catch(|| before_block(&inputs, &mut passed))
+                .unwrap_or_else(|| {
+                    // Leave the loop with the inputs unchanged
+                    passed = inputs.clone();
+                    self.constant_scalar(0.0, MPSDataType::Bool)
+                });
+            for tensor in &passed {
+                let _: () = unsafe { msg_send![results, addObject: tensor.0] };
+            }
+            scope.keep(unsafe { objc2::ffi::objc_retain(condition.0) })
+        };
+        let before = scope.binary_block(&before);
+        let after = |arguments: *mut AnyObject| {
+            let arguments = unsafe { tensors_from_array(arguments) };
+            let tensors = scope.catch(|| after_block(&arguments)).unwrap_or(arguments);
+            scope.return_tensors(&tensors)
+        };
+        let after = scope.unary_block(&after);
+
+        let result = scope.run(|| unsafe {
             let result_array: *mut AnyObject = msg_send![
-                self.0, whileWithInitialInputs: initial_inputs_array.0,
-                before: &*before_callback,
-                after: &*after_callback,
+                self.0, whileWithInitialInputs: inputs_array,
+                before: &*before,
+                after: &*after,
                 name: name_obj,
             ];
-            
-            // Get the count of result tensors
-            let count: usize = msg_send![result_array, count];
-            
-            // Convert NSArray to Vec<MPSGraphTensor>
-            let mut result = Vec::with_capacity(count);
-            for i in 0..count {
-                let tensor: *mut AnyObject = msg_send![result_array, objectAtIndex: i,,,,,,,];
-                let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-                result.push(MPSGraphTensor(tensor));
+            tensors_from_array(result_array)
+        });
+
+        unsafe {
+            objc2::ffi::objc_release(inputs_array as *mut _);
+            if !name_obj.is_null() {
+                objc2::ffi::objc_release(name_obj as *mut _);
             }
-            
-            result
         }
+        result
     }
-    
+
     /// Creates a for loop operation.
     ///
     /// This allows for iterative execution of operations for a specified range.
@@ -285,76 +385,54 @@ impl MPSGraph {
     /// # Returns
     ///
     /// A vector of tensors that are the final result of the for loop
-    pub fn for_loop<F>(&self, 
-                    lower_bound:  &MPSGraphTensor,
-                    upper_bound:  &MPSGraphTensor,
-                    step:  &MPSGraphTensor,
-                    initial_body_arguments:  &[&MPSGraphTensor],
-                    body_block:  F,
-                    name:  Option<&str>) -> Vec<MPSGraphTensor>
+    pub fn for_loop<F>(
+        &self,
+        lower_bound: &MPSGraphTensor,
+        upper_bound: &MPSGraphTensor,
+        step: &MPSGraphTensor,
+        initial_body_arguments: &[&MPSGraphTensor],
+        body_block: F,
+        name: Option<&str>,
+    ) -> Vec<MPSGraphTensor>
     where
-        F:  Fn(&MPSGraphTensor, &[MPSGraphTensor]) -> Vec<MPSGraphTensor>
+        F: Fn(&MPSGraphTensor, &[MPSGraphTensor]) -> Vec<MPSGraphTensor>,
     {
-        unsafe {
-            let name_obj = match name {
-                Some(s) => NSString::from_str(s).as_raw_object(),
-                None => std::ptr::null_mut(),
-            };
-            
-            // Convert initial body arguments to NSArray
-            let initial_args_array = crate::core::NSArray::from_objects(
-                &initial_body_arguments.iter().map(|t| t.0).collect::<Vec<_>>()
-            );
-            
-            // Create the body block callback
-            let body_callback = ConcreteBlock::new(move |index: *mut AnyObject, args: *mut AnyObject| -> *mut AnyObject {
-                // Convert to MPSGraphTensor
-                let index_tensor = MPSGraphTensor(index);
-                
-                // Convert NSArray to Vec<MPSGraphTensor>
-                let args_count: usize = msg_send![args, count];
-                let mut body_arguments = Vec::with_capacity(args_count);
-                for i in 0..args_count {
-                    let tensor: *mut AnyObject = msg_send![args, objectAtIndex: i];
-                    body_arguments.push(MPSGraphTensor(tensor));
-                }
-                
-                // Call the user's block
-                let result_tensors = body_block(&index_tensor, &body_arguments);
-                
-                // Convert Vec<MPSGraphTensor> to NSArray
-                let result_ptrs: Vec<*mut Object> = result_tensors.iter().map(|t| t.0).collect();
-                let result_array = crate::core::NSArray::from_objects(&result_ptrs);
-                
-                // Return the NSArray
-                result_array.0
-            }).copy();
-            
-            // Call the Objective-C method
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+        let argument_ptrs: Vec<*mut AnyObject> =
+            initial_body_arguments.iter().map(|t| t.0).collect();
+        let arguments_array = create_ns_array_from_pointers(&argument_ptrs);
+
+        let scope = BlockScope::new();
+        let body = |index: *mut AnyObject, arguments: *mut AnyObject| {
+            let body_result = unsafe { for_body(index, arguments, &scope, &body_block) };
+            scope.return_tensors(&body_result)
+        };
+        let body = scope.binary_block(&body);
+
+        let result = scope.run(|| unsafe {
             let result_array: *mut AnyObject = msg_send![
                 self.0, forLoopWithLowerBound: lower_bound.0,
                 upperBound: upper_bound.0,
                 step: step.0,
-                initialBodyArguments: initial_args_array.0,
-                body: &*body_callback,
+                initialBodyArguments: arguments_array,
+                body: &*body,
                 name: name_obj,
             ];
-            
-            // Get the count of result tensors
-            let count: usize = msg_send![result_array, count];
-            
-            // Convert NSArray to Vec<MPSGraphTensor>
-            let mut result = Vec::with_capacity(count);
-            for i in 0..count {
-                let tensor: *mut AnyObject = msg_send![result_array, objectAtIndex: i,,];
-                let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-                result.push(MPSGraphTensor(tensor));
+            tensors_from_array(result_array)
+        });
+
+        unsafe {
+            objc2::ffi::objc_release(arguments_array as *mut _);
+            if !name_obj.is_null() {
+                objc2::ffi::objc_release(name_obj as *mut _);
             }
-            
-            result
         }
+        result
     }
-    
+
     /// Creates a for loop operation with a specific number of iterations.
     ///
     /// This is a more direct version of the for loop that just specifies the total number of iterations.
@@ -369,168 +447,68 @@ impl MPSGraph {
     /// # Returns
     ///
     /// A vector of tensors that are the final result of the for loop
-    pub fn for_loop_with_iterations<F>(&self,
-                                    num_iterations:  &MPSGraphTensor,
-                                    initial_body_arguments:  &[&MPSGraphTensor],
-                                    body_block:  F,
-                                    name:  Option<&str>) -> Vec<MPSGraphTensor>
+    pub fn for_loop_with_iterations<F>(
+        &self,
+        num_iterations: &MPSGraphTensor,
+        initial_body_arguments: &[&MPSGraphTensor],
+        body_block: F,
+        name: Option<&str>,
+    ) -> Vec<MPSGraphTensor>
     where
-        F:  Fn(&MPSGraphTensor, &[MPSGraphTensor]) -> Vec<MPSGraphTensor>
+        F: Fn(&MPSGraphTensor, &[MPSGraphTensor]) -> Vec<MPSGraphTensor>,
     {
-        unsafe {
-            let name_obj = match name {
-                Some(s) => NSString::from_str(s).as_raw_object(),
-                None => std::ptr::null_mut(),
-            };
-            
-            // Convert initial body arguments to NSArray
-            let initial_args_array = crate::core::NSArray::from_objects(
-                &initial_body_arguments.iter().map(|t| t.0).collect::<Vec<_>>()
-            );
-            
-            // Create the body block callback
-            let body_callback = ConcreteBlock::new(move |index: *mut AnyObject, args: *mut AnyObject| -> *mut AnyObject {
-                // Convert to MPSGraphTensor
-                let index_tensor = MPSGraphTensor(index);
-                
-                // Convert NSArray to Vec<MPSGraphTensor>
-                let args_count: usize = msg_send![args, count];
-                let mut body_arguments = Vec::with_capacity(args_count);
-                for i in 0..args_count {
-                    let tensor: *mut AnyObject = msg_send![args, objectAtIndex: i];
-                    body_arguments.push(MPSGraphTensor(tensor));
-                }
-                
-                // Call the user's block
-                let result_tensors = body_block(&index_tensor, &body_arguments);
-                
-                // Convert Vec<MPSGraphTensor> to NSArray
-                let result_ptrs: Vec<*mut Object> = result_tensors.iter().map(|t| t.0).collect();
-                let result_array = crate::core::NSArray::from_objects(&result_ptrs);
-                
-                // Return the NSArray
-                result_array.0
-            }).copy();
-            
-            // Call the Objective-C method
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+        let argument_ptrs: Vec<*mut AnyObject> =
+            initial_body_arguments.iter().map(|t| t.0).collect();
+        let arguments_array = create_ns_array_from_pointers(&argument_ptrs);
+
+        let scope = BlockScope::new();
+        let body = |index: *mut AnyObject, arguments: *mut AnyObject| {
+            let body_result = unsafe { for_body(index, arguments, &scope, &body_block) };
+            scope.return_tensors(&body_result)
+        };
+        let body = scope.binary_block(&body);
+
+        let result = scope.run(|| unsafe {
             let result_array: *mut AnyObject = msg_send![
                 self.0, forLoopWithNumberOfIterations: num_iterations.0,
-                initialBodyArguments: initial_args_array.0,
-                body: &*body_callback,
+                initialBodyArguments: arguments_array,
+                body: &*body,
                 name: name_obj,
             ];
-            
-            // Get the count of result tensors
-            let count: usize = msg_send![result_array, count];
-            
-            // Convert NSArray to Vec<MPSGraphTensor>
-            let mut result = Vec::with_capacity(count);
-            for i in 0..count {
-                let tensor: *mut AnyObject = msg_send![result_array, objectAtIndex: i,,,,,,,];
-                let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-                result.push(MPSGraphTensor(tensor));
+            tensors_from_array(result_array)
+        });
+
+        unsafe {
+            objc2::ffi::objc_release(arguments_array as *mut _);
+            if !name_obj.is_null() {
+                objc2::ffi::objc_release(name_obj as *mut _);
             }
-            
-            result
         }
+        result
     }
 }
 
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::core::MPSDataType;
-    use crate::device::MPSGraphDevice;
-    use std::collections::HashMap;
-    
-    #[test]
-    fn test_if_op() {
-        // This test will be skipped in environments without Metal
-        if crate::tests::should_skip_test("test_if_op") {
-            return;
-        }
-        
-        let graph = MPSGraph::new();
-        let device = MPSGraphDevice::new();
-        
-        // Create a predicate tensor (true)
-        let predicate = graph.constant_scalar_value(1, MPSDataType::Bool, Some("predicate"));
-        
-        // Create tensors for the then branch
-        let then_value = graph.constant_scalar_value(42.0f32, MPSDataType::Float32, Some("then_value"));
-        
-        // Create tensors for the else branch
-        let else_value = graph.constant_scalar_value(-42.0f32, MPSDataType::Float32, Some("else_value"));
-        
-        // Create the if-then-else operation
-        let result = graph.if_op(
-            &predicate,
-            || vec![then_value.clone()],
-            Some(|| vec![else_value.clone()]),
-            Some("if_op")
-        );
-        
-        // Verify that we got one result
-        assert_eq!(result.len(), 1, "If operation should return 1 tensor");
-        
-        // Run the graph
-        let results = graph.run_graph(&result, &device, None);
-        
-        // Get the result
-        let output = results.get(&result[0]).unwrap().to_vec::<f32>();
-        
-        // Since predicate is true, the result should be the then_value
-        assert_eq!(output[0], 42.0f32, "Result should be the then_value");
-    }
-    
-    #[test]
-    fn test_for_loop() {
-        // This test will be skipped in environments without Metal
-        if crate::tests::should_skip_test("test_for_loop") {
-            return;
-        }
-        
-        let graph = MPSGraph::new();
-        let device = MPSGraphDevice::new();
-        
-        // Create bounds and step for the loop
-        let lower_bound = graph.constant_scalar_value(0i32, MPSDataType::Int32, Some("lower_bound"));
-        let upper_bound = graph.constant_scalar_value(5i32, MPSDataType::Int32, Some("upper_bound"));
-        let step = graph.constant_scalar_value(1i32, MPSDataType::Int32, Some("step"));
-        
-        // Create initial value for accumulator (0.0)
-        let initial_value = graph.constant_scalar_value(0.0f32, MPSDataType::Float32, Some("initial"));
-        
-        // Create the for loop to sum numbers from 0 to 4
-        let result = graph.for_loop(
-            &lower_bound,
-            &upper_bound,
-            &step,
-            &[&initial_value],
-            |index, args| {
-                // Convert index to float
-                let index_float = graph.cast(index, MPSDataType::Float32, None);
-                
-                // Add the current index to the accumulator
-                let new_acc = graph.add(&args[0], &index_float, None);
-                
-                // Return the updated accumulator
-                vec![new_acc]
-            },
-            Some("for_loop")
-        );
-        
-        // Verify that we got one result
-        assert_eq!(result.len(), 1, "For loop should return 1 tensor");
-        
-        // Run the graph
-        let results = graph.run_graph(&result, &device, None);
-        
-        // Get the result
-        let output = results.get(&result[0]).unwrap().to_vec::<f32>();
-        
-        // The sum of 0 + 1 + 2 + 3 + 4 = 10
-        assert_eq!(output[0], 10.0f32, "Result should be the sum 0+1+2+3+4=10");
-    }
-}
\ No newline at end of file
+/// Runs a for loop body, passing the iteration arguments through if it panics
+///
+/// # Safety
+///
+/// `index` must be a valid tensor and `arguments` a valid `NSArray` of tensors.
+unsafe fn for_body<F>(
+    index: *mut AnyObject,
+    arguments: *mut AnyObject,
+    scope: &BlockScope,
+    body_block: &F,
+) -> Vec<MPSGraphTensor>
+where
+    F: Fn(&MPSGraphTensor, &[MPSGraphTensor]) -> Vec<MPSGraphTensor>,
+{
+    let index = MPSGraphTensor::from_retained(objc2::ffi::objc_retain(index as *mut _));
+    let arguments = tensors_from_array(arguments);
+    scope
+        .catch(|| body_block(&index, &arguments))
+        .unwrap_or(arguments)
+}
@@ -1,47 +1,67 @@
+#[cfg(target_vendor = "apple")]
+use crate::core::{AsRawObject, NSString};
+#[cfg(target_vendor = "apple")]
+use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
+use crate::shape::MPSShape;
+#[cfg(target_vendor = "apple")]
+use crate::tensor::MPSGraphTensor;
+#[cfg(target_vendor = "apple")]
 use objc2::msg_send;
+#[cfg(target_vendor = "apple")]
 use objc2::runtime::AnyObject;
+#[cfg(target_vendor = "apple")]
 use std::ptr;
 
 /// Convolution padding mode
 #[repr(u64)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MPSGraphPaddingMode {
+    /// Explicit padding - user-specified padding values
+    Explicit = 0,
     /// Valid padding - no padding
-    Valid = 0,
+    Valid = 1,
     /// Same padding - pad to maintain same size
-    Same = 1,
-    /// Explicit padding - user-specified padding values
-    Explicit = 2,
+    Same = 2,
 }
 
 /// Dataflow direction for convolution
 #[repr(u64)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MPSGraphConvolutionDataLayout {
-    /// Data is arranged as NHWC (batch, height, width, channels)
-    NHWC = 0,
     /// Data is arranged as NCHW (batch, channels, height, width)
-    NCHW = 1,
+    NCHW = 0,
+    /// Data is arranged as NHWC (batch, height, width, channels)
+    NHWC = 1,
+    /// Data is arranged as NCDHW (batch, channels, depth, height, width)
+    NCDHW = 7,
+    /// Data is arranged as NDHWC (batch, depth, height, width, channels)
+    NDHWC = 8,
 }
 
 /// Weight layout for convolution
 #[repr(u64)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MPSGraphWeightsLayout {
+    /// Weights arranged as OIHW (output channels, input channels, height, width)
+    OIHW = 2,
     /// Weights arranged as HWIO (height, width, input channels, output channels)
-    HWIO = 0,
-    /// Weights arranged as OHWI (output channels, height, width, input channels)
-    OHWI = 1,
-    /// Weights arranged as IHWO (input channels, height, width, output channels)
-    IHWO = 2,
+    HWIO = 3,
+    /// Weights arranged as OIDHW (output channels, input channels, depth, height, width)
+    OIDHW = 9,
+    /// Weights arranged as DHWIO (depth, height, width, input channels, output channels)
+    DHWIO = 10,
 }
 
 /// Descriptor for 2D convolution operations
+#[cfg(target_vendor = "apple")]
 pub struct MPSGraphConvolution2DOpDescriptor(pub(crate) *mut AnyObject);
 
 /// Descriptor for 3D convolution operations
+#[cfg(target_vendor = "apple")]
 pub struct MPSGraphConvolution3DOpDescriptor(pub(crate) *mut AnyObject);
 
+#[cfg(target_vendor = "apple")]
 impl Drop for MPSGraphConvolution2DOpDescriptor {
     fn drop(&mut self) {
         unsafe {
@@ -52,6 +72,7 @@ impl Drop for MPSGraphConvolution2DOpDescriptor {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl Clone for MPSGraphConvolution2DOpDescriptor {
     fn clone(&self) -> Self {
         unsafe {
@@ -65,6 +86,7 @@ impl Clone for MPSGraphConvolution2DOpDescriptor {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl Drop for MPSGraphConvolution3DOpDescriptor {
     fn drop(&mut self) {
         unsafe {
@@ -75,6 +97,7 @@ impl Drop for MPSGraphConvolution3DOpDescriptor {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl Clone for MPSGraphConvolution3DOpDescriptor {
     fn clone(&self) -> Self {
         unsafe {
@@ -88,12 +111,14 @@ impl Clone for MPSGraphConvolution3DOpDescriptor {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl Default for MPSGraphConvolution2DOpDescriptor {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl MPSGraphConvolution2DOpDescriptor {
     /// Creates a new descriptor with default parameters
     pub fn new() -> Self {
@@ -103,14 +128,116 @@ impl MPSGraphConvolution2DOpDescriptor {
             MPSGraphConvolution2DOpDescriptor(objc2::ffi::objc_retain(desc as *mut _))
         }
     }
+
+    /// Sets the stride in X dimension
+    pub fn set_stride_in_x(&self, stride: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setStrideInX: stride];
+        }
+    }
+
+    /// Sets the stride in Y dimension
+    pub fn set_stride_in_y(&self, stride: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setStrideInY: stride];
+        }
+    }
+
+    /// Sets the dilation rate in X dimension
+    pub fn set_dilation_rate_in_x(&self, rate: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setDilationRateInX: rate];
+        }
+    }
+
+    /// Sets the dilation rate in Y dimension
+    pub fn set_dilation_rate_in_y(&self, rate: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setDilationRateInY: rate];
+        }
+    }
+
+    /// Sets the padding on the left
+    pub fn set_padding_left(&self, padding: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setPaddingLeft: padding];
+        }
+    }
+
+    /// Sets the padding on the right
+    pub fn set_padding_right(&self, padding: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setPaddingRight: padding];
+        }
+    }
+
+    /// Sets the padding on the top
+    pub fn set_padding_top(&self, padding: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setPaddingTop: padding];
+        }
+    }
+
+    /// Sets the padding on the bottom
+    pub fn set_padding_bottom(&self, padding: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setPaddingBottom: padding];
+        }
+    }
+
+    /// Sets all four explicit padding values at once
+    ///
+    /// Only used when the padding style is `MPSGraphPaddingMode::Explicit`.
+    pub fn set_explicit_padding(&self, left: usize, right: usize, top: usize, bottom: usize) {
+        unsafe {
+            let _: () = msg_send![
+                self.0, setExplicitPaddingWithPaddingLeft: left,
+                paddingRight: right,
+                paddingTop: top,
+                paddingBottom: bottom
+            ];
+        }
+    }
+
+    /// Sets the padding style
+    pub fn set_padding_style(&self, style: MPSGraphPaddingMode) {
+        unsafe {
+            let _: () = msg_send![self.0, setPaddingStyle: style as u64];
+        }
+    }
+
+    /// Sets the data layout of the source and result tensors
+    pub fn set_data_layout(&self, layout: MPSGraphConvolutionDataLayout) {
+        unsafe {
+            let _: () = msg_send![self.0, setDataLayout: layout as u64];
+        }
+    }
+
+    /// Sets the layout of the weights tensor
+    pub fn set_weights_layout(&self, layout: MPSGraphWeightsLayout) {
+        unsafe {
+            let _: () = msg_send![self.0, setWeightsLayout: layout as u64];
+        }
+    }
+
+    /// Sets the number of groups
+    ///
+    /// Input and output channels are split into `groups` groups that are convolved independently.
+    pub fn set_groups(&self, groups: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setGroups: groups];
+        }
+    }
 }
 
+#[cfg(target_vendor = "apple")]
 impl Default for MPSGraphConvolution3DOpDescriptor {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl MPSGraphConvolution3DOpDescriptor {
     /// Creates a new descriptor with default parameters
     pub fn new() -> Self {
@@ -120,4 +247,538 @@ impl MPSGraphConvolution3DOpDescriptor {
             MPSGraphConvolution3DOpDescriptor(objc2::ffi::objc_retain(desc as *mut _))
         }
     }
+
+    /// Sets the stride in X dimension
+    pub fn set_stride_in_x(&self, stride: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setStrideInX: stride];
+        }
+    }
+
+    /// Sets the stride in Y dimension
+    pub fn set_stride_in_y(&self, stride: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setStrideInY: stride];
+        }
+    }
+
+    /// Sets the stride in Z dimension
+    pub fn set_stride_in_z(&self, stride: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setStrideInZ: stride];
+        }
+    }
+
+    /// Sets the dilation rate in X dimension
+    pub fn set_dilation_rate_in_x(&self, rate: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setDilationRateInX: rate];
+        }
+    }
+
+    /// Sets the dilation rate in Y dimension
+    pub fn set_dilation_rate_in_y(&self, rate: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setDilationRateInY: rate];
+        }
+    }
+
+    /// Sets the dilation rate in Z dimension
+    pub fn set_dilation_rate_in_z(&self, rate: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setDilationRateInZ: rate];
+        }
+    }
+
+    /// Sets the padding on the left
+    pub fn set_padding_left(&self, padding: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setPaddingLeft: padding];
+        }
+    }
+
+    /// Sets the padding on the right
+    pub fn set_padding_right(&self, padding: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setPaddingRight: padding];
+        }
+    }
+
+    /// Sets the padding on the top
+    pub fn set_padding_top(&self, padding: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setPaddingTop: padding];
+        }
+    }
+
+    /// Sets the padding on the bottom
+    pub fn set_padding_bottom(&self, padding: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setPaddingBottom: padding];
+        }
+    }
+
+    /// Sets the padding on the front
+    pub fn set_padding_front(&self, padding: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setPaddingFront: padding];
+        }
+    }
+
+    /// Sets the padding on the back
+    pub fn set_padding_back(&self, padding: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setPaddingBack: padding];
+        }
+    }
+
+    /// Sets all six explicit padding values at once
+    ///
+    /// Only used when the padding style is `MPSGraphPaddingMode::Explicit`.
+    pub fn set_explicit_padding(
+        &self,
+        left: usize,
+        right: usize,
+        top: usize,
+        bottom: usize,
+        front: usize,
+        back: usize,
+    ) {
+        unsafe {
+            let _: () = msg_send![
+                self.0, setExplicitPaddingWithPaddingLeft: left,
+                paddingRight: right,
+                paddingTop: top,
+                paddingBottom: bottom,
+                paddingFront: front,
+                paddingBack: back
+            ];
+        }
+    }
+
+    /// Sets the padding style
+    pub fn set_padding_style(&self, style: MPSGraphPaddingMode) {
+        unsafe {
+            let _: () = msg_send![self.0, setPaddingStyle: style as u64];
+        }
+    }
+
+    /// Sets the data layout of the source and result tensors
+    pub fn set_data_layout(&self, layout: MPSGraphConvolutionDataLayout) {
+        unsafe {
+            let _: () = msg_send![self.0, setDataLayout: layout as u64];
+        }
+    }
+
+    /// Sets the layout of the weights tensor
+    pub fn set_weights_layout(&self, layout: MPSGraphWeightsLayout) {
+        unsafe {
+            let _: () = msg_send![self.0, setWeightsLayout: layout as u64];
+        }
+    }
+
+    /// Sets the number of groups
+    pub fn set_groups(&self, groups: usize) {
+        unsafe {
+            let _: () = msg_send![self.0, setGroups: groups];
+        }
+    }
+}
+
+/// Convolution operations for MPSGraph
+#[cfg(target_vendor = "apple")]
+impl MPSGraph {
+    /// Creates a 2D convolution operation and returns the result tensor.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source tensor, laid out as described by the descriptor's data layout
+    /// * `weights` - Weights tensor, laid out as described by the descriptor's weights layout
+    /// * `descriptor` - Specifies strides, dilation rates, paddings, groups and layouts
+    /// * `name` - Name for the operation
+    ///
+    /// # Returns
+    ///
+    /// A new MPSGraphTensor containing the result
+    pub fn convolution_2d(
+        &self,
+        source: &MPSGraphTensor,
+        weights: &MPSGraphTensor,
+        descriptor: &MPSGraphConvolution2DOpDescriptor,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+
+        unsafe {
+            let tensor: *mut AnyObject = msg_send![
+                self.0, convolution2DWithSourceTensor: source.0,
+                weightsTensor: weights.0,
+                descriptor: descriptor.0,
+                name: name_obj,
+            ];
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a 2D convolution gradient operation with respect to the source tensor.
+    ///
+    /// # Arguments
+    ///
+    /// * `incoming_gradient` - Gradient with respect to the result of the forward convolution
+    /// * `weights` - Weights tensor of the forward convolution
+    /// * `output_shape` - Shape of the source image
+    /// * `descriptor` - Descriptor of the forward convolution
+    /// * `name` - Name for the operation
+    ///
+    /// # Returns
+    ///
+    /// A new MPSGraphTensor containing the gradient
+    pub fn convolution_2d_data_gradient(
+        &self,
+        incoming_gradient: &MPSGraphTensor,
+        weights: &MPSGraphTensor,
+        output_shape: &MPSShape,
+        descriptor: &MPSGraphConvolution2DOpDescriptor,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+
+        unsafe {
+            let tensor: *mut AnyObject = msg_send![
+                self.0, convolution2DDataGradientWithIncomingGradientTensor: incoming_gradient.0,
+                weightsTensor: weights.0,
+                outputShape: output_shape.0,
+                forwardConvolutionDescriptor: descriptor.0,
+                name: name_obj,
+            ];
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a 2D convolution gradient operation with respect to the source tensor and a tensor output shape.
+    ///
+    /// # Arguments
+    ///
+    /// * `incoming_gradient` - Gradient with respect to the result of the forward convolution
+    /// * `weights` - Weights tensor of the forward convolution
+    /// * `output_shape_tensor` - 1D Int32 or Int64 tensor with the shape of the source image
+    /// * `descriptor` - Descriptor of the forward convolution
+    /// * `name` - Name for the operation
+    ///
+    /// # Returns
+    ///
+    /// A new MPSGraphTensor containing the gradient
+    pub fn convolution_2d_data_gradient_with_tensor_shape(
+        &self,
+        incoming_gradient: &MPSGraphTensor,
+        weights: &MPSGraphTensor,
+        output_shape_tensor: &MPSGraphTensor,
+        descriptor: &MPSGraphConvolution2DOpDescriptor,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+
+        unsafe {
+            let tensor: *mut AnyObject = msg_send![
+                self.0, convolution2DDataGradientWithIncomingGradientTensor: incoming_gradient.0,
+                weightsTensor: weights.0,
+                outputShapeTensor: output_shape_tensor.0,
+                forwardConvolutionDescriptor: descriptor.0,
+                name: name_obj,
+            ];
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a 2D convolution gradient operation with respect to the weights tensor.
+    ///
+    /// # Arguments
+    ///
+    /// * `incoming_gradient` - Gradient with respect to the result of the forward convolution
+    /// * `source` - Source tensor of the forward convolution
+    /// * `output_shape` - Shape of the weights
+    /// * `descriptor` - Descriptor of the forward convolution
+    /// * `name` - Name for the operation
+    ///
+    /// # Returns
+    ///
+    /// A new MPSGraphTensor containing the gradient
+    pub fn convolution_2d_weights_gradient(
+        &self,
+        incoming_gradient: &MPSGraphTensor,
+        source: &MPSGraphTensor,
+        output_shape: &MPSShape,
+        descriptor: &MPSGraphConvolution2DOpDescriptor,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+
+        unsafe {
+            let tensor: *mut AnyObject = msg_send![
+                self.0, convolution2DWeightsGradientWithIncomingGradientTensor: incoming_gradient.0,
+                sourceTensor: source.0,
+                outputShape: output_shape.0,
+                forwardConvolutionDescriptor: descriptor.0,
+                name: name_obj,
+            ];
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a 2D convolution gradient operation with respect to the weights tensor and a tensor output shape.
+    ///
+    /// # Arguments
+    ///
+    /// * `incoming_gradient` - Gradient with respect to the result of the forward convolution
+    /// * `source` - Source tensor of the forward convolution
+    /// * `output_shape_tensor` - 1D Int32 or Int64 tensor with the shape of the weights
+    /// * `descriptor` - Descriptor of the forward convolution
+    /// * `name` - Name for the operation
+    ///
+    /// # Returns
+    ///
+    /// A new MPSGraphTensor containing the gradient
+    pub fn convolution_2d_weights_gradient_with_tensor_shape(
+        &self,
+        incoming_gradient: &MPSGraphTensor,
+        source: &MPSGraphTensor,
+        output_shape_tensor: &MPSGraphTensor,
+        descriptor: &MPSGraphConvolution2DOpDescriptor,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+
+        unsafe {
+            let tensor: *mut AnyObject = msg_send![
+                self.0, convolution2DWeightsGradientWithIncomingGradientTensor: incoming_gradient.0,
+                sourceTensor: source.0,
+                outputShapeTensor: output_shape_tensor.0,
+                forwardConvolutionDescriptor: descriptor.0,
+                name: name_obj,
+            ];
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a 3D convolution operation and returns the result tensor.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source tensor, laid out as described by the descriptor's data layout
+    /// * `weights` - Weights tensor, laid out as described by the descriptor's weights layout
+    /// * `descriptor` - Specifies strides, dilation rates, paddings, groups and layouts
+    /// * `name` - Name for the operation
+    ///
+    /// # Returns
+    ///
+    /// A new MPSGraphTensor containing the result
+    pub fn convolution_3d(
+        &self,
+        source: &MPSGraphTensor,
+        weights: &MPSGraphTensor,
+        descriptor: &MPSGraphConvolution3DOpDescriptor,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+
+        unsafe {
+            let tensor: *mut AnyObject = msg_send![
+                self.0, convolution3DWithSourceTensor: source.0,
+                weightsTensor: weights.0,
+                descriptor: descriptor.0,
+                name: name_obj,
+            ];
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a 3D convolution gradient operation with respect to the source tensor.
+    ///
+    /// # Arguments
+    ///
+    /// * `incoming_gradient` - Gradient with respect to the result of the forward convolution
+    /// * `weights` - Weights tensor of the forward convolution
+    /// * `output_shape` - Shape of the source volume
+    /// * `descriptor` - Descriptor of the forward convolution
+    /// * `name` - Name for the operation
+    ///
+    /// # Returns
+    ///
+    /// A new MPSGraphTensor containing the gradient
+    pub fn convolution_3d_data_gradient(
+        &self,
+        incoming_gradient: &MPSGraphTensor,
+        weights: &MPSGraphTensor,
+        output_shape: &MPSShape,
+        descriptor: &MPSGraphConvolution3DOpDescriptor,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+
+        unsafe {
+            let tensor: *mut AnyObject = msg_send![
+                self.0, convolution3DDataGradientWithIncomingGradientTensor: incoming_gradient.0,
+                weightsTensor: weights.0,
+                outputShape: output_shape.0,
+                forwardConvolutionDescriptor: descriptor.0,
+                name: name_obj,
+            ];
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a 3D convolution gradient operation with respect to the source tensor and a tensor output shape.
+    ///
+    /// # Arguments
+    ///
+    /// * `incoming_gradient` - Gradient with respect to the result of the forward convolution
+    /// * `weights` - Weights tensor of the forward convolution
+    /// * `output_shape_tensor` - 1D Int32 or Int64 tensor with the shape of the source volume
+    /// * `descriptor` - Descriptor of the forward convolution
+    /// * `name` - Name for the operation
+    ///
+    /// # Returns
+    ///
+    /// A new MPSGraphTensor containing the gradient
+    pub fn convolution_3d_data_gradient_with_tensor_shape(
+        &self,
+        incoming_gradient: &MPSGraphTensor,
+        weights: &MPSGraphTensor,
+        output_shape_tensor: &MPSGraphTensor,
+        descriptor: &MPSGraphConvolution3DOpDescriptor,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+
+        unsafe {
+            let tensor: *mut AnyObject = msg_send![
+                self.0, convolution3DDataGradientWithIncomingGradientTensor: incoming_gradient.0,
+                weightsTensor: weights.0,
+                outputShapeTensor: output_shape_tensor.0,
+                forwardConvolutionDescriptor: descriptor.0,
+                name: name_obj,
+            ];
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a 3D convolution gradient operation with respect to the weights tensor.
+    ///
+    /// # Arguments
+    ///
+    /// * `incoming_gradient` - Gradient with respect to the result of the forward convolution
+    /// * `source` - Source tensor of the forward convolution
+    /// * `output_shape` - Shape of the weights
+    /// * `descriptor` - Descriptor of the forward convolution
+    /// * `name` - Name for the operation
+    ///
+    /// # Returns
+    ///
+    /// A new MPSGraphTensor containing the gradient
+    pub fn convolution_3d_weights_gradient(
+        &self,
+        incoming_gradient: &MPSGraphTensor,
+        source: &MPSGraphTensor,
+        output_shape: &MPSShape,
+        descriptor: &MPSGraphConvolution3DOpDescriptor,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+
+        unsafe {
+            let tensor: *mut AnyObject = msg_send![
+                self.0, convolution3DWeightsGradientWithIncomingGradientTensor: incoming_gradient.0,
+                sourceTensor: source.0,
+                outputShape: output_shape.0,
+                forwardConvolutionDescriptor: descriptor.0,
+                name: name_obj,
+            ];
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a 3D convolution gradient operation with respect to the weights tensor and a tensor output shape.
+    ///
+    /// # Arguments
+    ///
+    /// * `incoming_gradient` - Gradient with respect to the result of the forward convolution
+    /// * `source` - Source tensor of the forward convolution
+    /// * `output_shape_tensor` - 1D Int32 or Int64 tensor with the shape of the weights
+    /// * `descriptor` - Descriptor of the forward convolution
+    /// * `name` - Name for the operation
+    ///
+    /// # Returns
+    ///
+    /// A new MPSGraphTensor containing the gradient
+    pub fn convolution_3d_weights_gradient_with_tensor_shape(
+        &self,
+        incoming_gradient: &MPSGraphTensor,
+        source: &MPSGraphTensor,
+        output_shape_tensor: &MPSGraphTensor,
+        descriptor: &MPSGraphConvolution3DOpDescriptor,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        let name_obj = match name {
+            Some(s) => NSString::from_str(s).as_raw_object(),
+            None => ptr::null_mut(),
+        };
+
+        unsafe {
+            let tensor: *mut AnyObject = msg_send![
+                self.0, convolution3DWeightsGradientWithIncomingGradientTensor: incoming_gradient.0,
+                sourceTensor: source.0,
+                outputShapeTensor: output_shape_tensor.0,
+                forwardConvolutionDescriptor: descriptor.0,
+                name: name_obj,
+            ];
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
 }
@@ -1,16 +1,22 @@
+use crate::error::MPSGraphError;
+#[cfg(target_vendor = "apple")]
 use objc2::msg_send;
+#[cfg(target_vendor = "apple")]
 use objc2::runtime::AnyObject;
 
 // Import and re-export Foundation types for use in other modules
+#[cfg(target_vendor = "apple")]
 pub use objc2_foundation::{NSArray, NSData, NSDictionary, NSError, NSNumber, NSString};
 
 // Helper extension trait to get raw AnyObject pointer from various types
 // Returns a raw pointer with +1 retain count (caller responsible for releasing)
+#[cfg(target_vendor = "apple")]
 pub trait AsRawObject {
     fn as_raw_object(&self) -> *mut AnyObject;
 }
 
 // Generic implementation for all Retained<T> where T: Message
+#[cfg(target_vendor = "apple")]
 impl<T: objc2::Message> AsRawObject for objc2::rc::Retained<T> {
     fn as_raw_object(&self) -> *mut AnyObject {
         unsafe {
@@ -26,6 +32,7 @@ impl<T: objc2::Message> AsRawObject for objc2::rc::Retained<T> {
 }
 
 // Helper function to create NSArray from AnyObject pointers
+#[cfg(target_vendor = "apple")]
 pub fn create_ns_array_from_pointers(objects: &[*mut AnyObject]) -> *mut AnyObject {
     unsafe {
         // Convert raw pointers to references
@@ -45,6 +52,7 @@ pub fn create_ns_array_from_pointers(objects: &[*mut AnyObject]) -> *mut AnyObje
 }
 
 // Helper function to create NSArray from i64 slice
+#[cfg(target_vendor = "apple")]
 pub fn create_ns_array_from_i64_slice(values: &[i64]) -> *mut AnyObject {
     unsafe {
         // Create NSNumber objects for each value using objc2-foundation's NSNumber
@@ -70,6 +78,7 @@ pub fn create_ns_array_from_i64_slice(values: &[i64]) -> *mut AnyObject {
 }
 
 // Helper function to create NSDictionary from keys and objects pointers
+#[cfg(target_vendor = "apple")]
 pub fn create_ns_dictionary_from_pointers(
     keys: &[*mut AnyObject],
     objects: &[*mut AnyObject],
@@ -109,86 +118,225 @@ pub fn create_ns_dictionary_from_pointers(
 // We might need these later
 // use std::ops::Deref;
 
+// Bit fields of an `MPSDataType` code
+const FLOAT_BIT: u32 = 0x1000_0000;
+const COMPLEX_BIT: u32 = 0x0100_0000;
+const SIGNED_BIT: u32 = 0x2000_0000;
+const NORMALIZED_BIT: u32 = 0x4000_0000;
+const ALTERNATE_ENCODING_BIT: u32 = 0x8000_0000;
+const BIT_WIDTH_MASK: u32 = 0xFFFF;
+
 /// MPS Graph data types
+///
+/// The discriminants follow `MPSDataType` in `MPSCoreTypes.h`: the low 16 bits hold the
+/// bit width and the high bits flag floating point, complex, signed, normalized and
+/// alternate encodings. `Float64` and `Complex64` aren't defined by Apple; they're kept
+/// for host-side data and are rejected by MPSGraph itself.
 #[repr(u32)] // Changed from u64 to u32 to match Objective-C's NSUInteger on 32-bit platforms
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum MPSDataType {
     Invalid = 0,
 
     // Floating point types
-    Float32 = 0x10000000 | 32,
-    Float16 = 0x10000000 | 16,
-    Float64 = 0x10000000 | 64,
+    Float32 = FLOAT_BIT | 32,
+    Float16 = FLOAT_BIT | 16,
+    Float64 = FLOAT_BIT | 64,
+    BFloat16 = ALTERNATE_ENCODING_BIT | FLOAT_BIT | 16,
 
     // Signed integer types
-    Int8 = 0x20000000 | 8,
-    Int16 = 0x20000000 | 16,
-    Int32 = 0x20000000 | 32,
-    Int64 = 0x20000000 | 64,
+    Int4 = SIGNED_BIT | 4,
+    Int8 = SIGNED_BIT | 8,
+    Int16 = SIGNED_BIT | 16,
+    Int32 = SIGNED_BIT | 32,
+    Int64 = SIGNED_BIT | 64,
 
     // Unsigned integer types
+    UInt4 = 4,
     UInt8 = 8,
     UInt16 = 16,
     UInt32 = 32,
     UInt64 = 64,
 
     // Boolean type
-    Bool = 0x40000000 | 8,
+    Bool = ALTERNATE_ENCODING_BIT | 8,
+
+    // Complex types, named after the width of each component
+    Complex16 = FLOAT_BIT | COMPLEX_BIT | 32,
+    Complex32 = FLOAT_BIT | COMPLEX_BIT | 64,
+    Complex64 = FLOAT_BIT | COMPLEX_BIT | 128,
 
-    // Complex types
-    Complex32 = 0x10000000 | 0x80000000 | 32,
-    Complex64 = 0x10000000 | 0x80000000 | 64,
+    // Normalized unsigned types, mapping the integer range onto [0, 1]
+    Unorm1 = NORMALIZED_BIT | 1,
+    Unorm8 = NORMALIZED_BIT | 8,
 }
 
 impl MPSDataType {
+    /// Every valid data type, in declaration order
+    pub const ALL: [MPSDataType; 20] = [
+        MPSDataType::Float32,
+        MPSDataType::Float16,
+        MPSDataType::Float64,
+        MPSDataType::BFloat16,
+        MPSDataType::Int4,
+        MPSDataType::Int8,
+        MPSDataType::Int16,
+        MPSDataType::Int32,
+        MPSDataType::Int64,
+        MPSDataType::UInt4,
+        MPSDataType::UInt8,
+        MPSDataType::UInt16,
+        MPSDataType::UInt32,
+        MPSDataType::UInt64,
+        MPSDataType::Bool,
+        MPSDataType::Complex16,
+        MPSDataType::Complex32,
+        MPSDataType::Complex64,
+        MPSDataType::Unorm1,
+        MPSDataType::Unorm8,
+    ];
+
     /// Converts a u32 value to an MPSDataType
+    #[deprecated(note = "use `MPSDataType::try_from`, which reports unknown codes")]
     pub fn from_u32(value: u32) -> Self {
-        match value {
-            // Since we're given the exact value from ObjC, check for exact enum values
-            val if val == MPSDataType::Float32 as u32 => MPSDataType::Float32,
-            val if val == MPSDataType::Float16 as u32 => MPSDataType::Float16,
-            val if val == MPSDataType::Float64 as u32 => MPSDataType::Float64,
-            val if val == MPSDataType::Int8 as u32 => MPSDataType::Int8,
-            val if val == MPSDataType::Int16 as u32 => MPSDataType::Int16,
-            val if val == MPSDataType::Int32 as u32 => MPSDataType::Int32,
-            val if val == MPSDataType::Int64 as u32 => MPSDataType::Int64,
-            val if val == MPSDataType::UInt8 as u32 => MPSDataType::UInt8,
-            val if val == MPSDataType::UInt16 as u32 => MPSDataType::UInt16,
-            val if val == MPSDataType::UInt32 as u32 => MPSDataType::UInt32,
-            val if val == MPSDataType::UInt64 as u32 => MPSDataType::UInt64,
-            val if val == MPSDataType::Bool as u32 => MPSDataType::Bool,
-            val if val == MPSDataType::Complex32 as u32 => MPSDataType::Complex32,
-            val if val == MPSDataType::Complex64 as u32 => MPSDataType::Complex64,
-            _ => MPSDataType::Invalid,
-        }
+        MPSDataType::try_from(value).unwrap_or(MPSDataType::Invalid)
     }
 
     /// Returns the u32 representation of this data type
     pub fn as_u32(&self) -> u32 {
         *self as u32
     }
+
+    /// Returns the number of bits per element, or per complex value for complex types
+    pub fn bit_width(&self) -> usize {
+        (self.as_u32() & BIT_WIDTH_MASK) as usize
+    }
+
     /// Returns the size in bytes for this data type
+    ///
+    /// Sub-byte types such as `Int4` and `Unorm1` round up to one byte; use
+    /// [`packed_size_in_bytes`](Self::packed_size_in_bytes) for buffers of them.
     pub fn size_in_bytes(&self) -> usize {
-        match self {
-            MPSDataType::Float16 => 2,
-            MPSDataType::Float32 => 4,
-            MPSDataType::Float64 => 8,
-            MPSDataType::Int8 => 1,
-            MPSDataType::Int16 => 2,
-            MPSDataType::Int32 => 4,
-            MPSDataType::Int64 => 8,
-            MPSDataType::UInt8 => 1,
-            MPSDataType::UInt16 => 2,
-            MPSDataType::UInt32 => 4,
-            MPSDataType::UInt64 => 8,
-            MPSDataType::Bool => 1,
-            MPSDataType::Complex32 => 8,  // 2 * Float32
-            MPSDataType::Complex64 => 16, // 2 * Float64
-            MPSDataType::Invalid => 0,
+        self.bit_width().div_ceil(8)
+    }
+
+    /// Returns the number of bytes needed to store `count` packed elements
+    ///
+    /// Sub-byte types share bytes, so `count` `Int4` elements take `count / 2` bytes
+    /// rounded up. Returns `None` if the size overflows `usize`.
+    pub fn packed_size_in_bytes(&self, count: usize) -> Option<usize> {
+        Some(count.checked_mul(self.bit_width())?.div_ceil(8))
+    }
+
+    /// Returns true for real floating point types
+    pub fn is_float(&self) -> bool {
+        self.as_u32() & (FLOAT_BIT | COMPLEX_BIT) == FLOAT_BIT
+    }
+
+    /// Returns true for signed and unsigned integer types, excluding `Bool`
+    pub fn is_integer(&self) -> bool {
+        *self != MPSDataType::Invalid && self.as_u32() & !(SIGNED_BIT | BIT_WIDTH_MASK) == 0
+    }
+
+    /// Returns true for types that can hold negative values
+    pub fn is_signed(&self) -> bool {
+        self.as_u32() & (SIGNED_BIT | FLOAT_BIT) != 0
+    }
+
+    /// Returns true for complex types
+    pub fn is_complex(&self) -> bool {
+        self.as_u32() & COMPLEX_BIT != 0
+    }
+
+    /// Returns the smallest type both operands convert to without losing range
+    ///
+    /// `Bool` promotes to anything, integers promote to floats and floats to complex
+    /// types, each widened as needed. Mixing signed and unsigned integers picks a signed
+    /// type wide enough for both. Returns `None` when there is no such type, as for
+    /// `Int64` and `UInt64`, and for `Invalid` or normalized operands.
+    pub fn promote(a: MPSDataType, b: MPSDataType) -> Option<MPSDataType> {
+        use MPSDataType::*;
+
+        if a == b {
+            return (a != Invalid).then_some(a);
+        }
+        let supported =
+            |t: MPSDataType| t == Bool || t.is_integer() || t.is_float() || t.is_complex();
+        if !supported(a) || !supported(b) {
+            return None;
+        }
+        if a == Bool {
+            return Some(b);
+        }
+        if b == Bool {
+            return Some(a);
+        }
+
+        if a.is_complex() || b.is_complex() {
+            let component = |t: MPSDataType| match t {
+                Complex16 => Float16,
+                Complex32 => Float32,
+                Complex64 => Float64,
+                t => t,
+            };
+            let complex = |t: MPSDataType| match t {
+                Float16 => Some(Complex16),
+                Float32 => Some(Complex32),
+                Float64 => Some(Complex64),
+                _ => None,
+            };
+            return complex(Self::promote(component(a), component(b))?);
+        }
+
+        if a.is_float() || b.is_float() {
+            return match (a.is_float(), b.is_float()) {
+                // Float16 and BFloat16 only share Float32
+                (true, true) if a.bit_width() == b.bit_width() => Some(Float32),
+                (true, true) => Some(if a.bit_width() > b.bit_width() { a } else { b }),
+                (true, false) => Some(a),
+                _ => Some(b),
+            };
+        }
+
+        let signed = |bits: usize| match bits {
+            0..=4 => Some(Int4),
+            5..=8 => Some(Int8),
+            9..=16 => Some(Int16),
+            17..=32 => Some(Int32),
+            33..=64 => Some(Int64),
+            _ => None,
+        };
+        let unsigned = |bits: usize| match bits {
+            0..=4 => Some(UInt4),
+            5..=8 => Some(UInt8),
+            9..=16 => Some(UInt16),
+            17..=32 => Some(UInt32),
+            33..=64 => Some(UInt64),
+            _ => None,
+        };
+        match (a.is_signed(), b.is_signed()) {
+            (true, true) => signed(a.bit_width().max(b.bit_width())),
+            (false, false) => unsigned(a.bit_width().max(b.bit_width())),
+            (true, false) => signed(a.bit_width().max(b.bit_width() * 2)),
+            (false, true) => signed(b.bit_width().max(a.bit_width() * 2)),
         }
     }
 }
 
+impl TryFrom<u32> for MPSDataType {
+    type Error = MPSGraphError;
+
+    /// Converts a raw `MPSDataType` code, rejecting codes this crate doesn't know
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        if value == MPSDataType::Invalid as u32 {
+            return Ok(MPSDataType::Invalid);
+        }
+        MPSDataType::ALL
+            .into_iter()
+            .find(|data_type| data_type.as_u32() == value)
+            .ok_or(MPSGraphError::UnknownDataType(value))
+    }
+}
+
 /// Options for MPSGraph execution
 #[repr(u64)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -205,7 +353,7 @@ pub enum MPSGraphOptions {
 
 /// Optimization levels for MPSGraph compilation
 #[repr(u64)]
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum MPSGraphOptimization {
     /// Graph performs core optimizations only
     Level0 = 0,
@@ -215,7 +363,7 @@ pub enum MPSGraphOptimization {
 
 /// Optimization profile for MPSGraph
 #[repr(u64)]
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum MPSGraphOptimizationProfile {
     /// Default, graph optimized for performance
     Performance = 0,
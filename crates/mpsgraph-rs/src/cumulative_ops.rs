@@ -48,7 +48,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -91,7 +91,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -125,7 +125,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -168,7 +168,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -211,7 +211,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -254,7 +254,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -297,7 +297,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -331,7 +331,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -374,7 +374,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -408,7 +408,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -451,7 +451,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -485,7 +485,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 }
@@ -1,4 +1,5 @@
 use crate::core::MPSDataType;
+use crate::error::MPSGraphError;
 use crate::shape::MPSShape;
 use objc2::msg_send;
 use objc2::runtime::AnyObject;
@@ -124,11 +125,19 @@ impl MPSGraphShapedType {
     }
 
     /// Returns the data type of this type
+    ///
+    /// Panics if MPSGraph reports a data type this crate doesn't know; see
+    /// [`try_data_type`](Self::try_data_type).
     pub fn data_type(&self) -> MPSDataType {
-        unsafe {
-            let data_type_val: u32 = msg_send![self.0, dataType];
-            std::mem::transmute(data_type_val)
-        }
+        self.try_data_type()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Returns the data type of this type, or [`MPSGraphError::UnknownDataType`] for a code this
+    /// crate doesn't know
+    pub fn try_data_type(&self) -> Result<MPSDataType, MPSGraphError> {
+        let data_type_val: u32 = unsafe { msg_send![self.0, dataType] };
+        MPSDataType::try_from(data_type_val)
     }
 
     /// Returns the rank of this type (calculated from shape)
@@ -346,7 +346,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -386,7 +386,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -426,7 +426,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -463,7 +463,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -503,7 +503,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -543,7 +543,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 }
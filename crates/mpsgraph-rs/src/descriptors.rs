@@ -0,0 +1,1182 @@
+//! Plain-Rust descriptors for convolution, pooling, stencil and im2col operations.
+//!
+//! The `MPSGraph*OpDescriptor` types wrap Objective-C objects that are configured through
+//! setters, so they can't be compared, hashed or checked before MPSGraph sees them. The
+//! descriptors here are ordinary values built with `with_*` methods. [`validate`] rejects
+//! combinations MPSGraph can't execute, such as zero strides, padding larger than the
+//! window, or explicit padding values combined with a padding style that ignores them.
+//! On Apple targets `build` validates and creates the Objective-C descriptor, and the
+//! `try_*` operation methods on `MPSGraph` do both when the operation is created.
+//!
+//! The conventions are those of the recorded IR: 2D spatial pairs are `[height, width]`
+//! (`[y, x]` in `MPSGraph` terms) and 2D padding is `[top, bottom, left, right]`. Descriptors
+//! with more dimensions have one value per dimension, in tensor order, and a
+//! `[before, after]` padding pair per dimension.
+//!
+//! [`validate`]: Convolution2dDescriptor::validate
+
+use crate::convolution_ops::{MPSGraphConvolutionDataLayout, MPSGraphWeightsLayout};
+use crate::core::MPSDataType;
+use crate::pooling_ops::{
+    MPSGraphPaddingStyle, MPSGraphPoolingReturnIndicesMode, MPSGraphTensorNamedDataLayout,
+};
+use crate::sample_grid_ops::MPSGraphPaddingMode;
+use crate::stencil_ops::MPSGraphReductionMode;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+#[cfg(target_vendor = "apple")]
+use crate::convolution_ops::{
+    MPSGraphConvolution2DOpDescriptor, MPSGraphConvolution3DOpDescriptor,
+    MPSGraphPaddingMode as MPSGraphConvolutionPaddingMode,
+};
+#[cfg(target_vendor = "apple")]
+use crate::convolution_transpose_ops::{PaddingStyle, TensorNamedDataLayout};
+#[cfg(target_vendor = "apple")]
+use crate::core::create_ns_array_from_i64_slice;
+#[cfg(target_vendor = "apple")]
+use crate::depthwise_convolution_ops::{
+    MPSGraphDepthwiseConvolution2DOpDescriptor, MPSGraphDepthwiseConvolution3DOpDescriptor,
+};
+#[cfg(target_vendor = "apple")]
+use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
+use crate::im2col_ops::MPSGraphImToColOpDescriptor;
+#[cfg(target_vendor = "apple")]
+use crate::pooling_ops::{MPSGraphPooling2DOpDescriptor, MPSGraphPooling4DOpDescriptor};
+#[cfg(target_vendor = "apple")]
+use crate::shape::MPSShape;
+#[cfg(target_vendor = "apple")]
+use crate::stencil_ops::MPSGraphStencilOpDescriptor;
+#[cfg(target_vendor = "apple")]
+use crate::tensor::MPSGraphTensor;
+
+/// Errors reported when validating a descriptor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DescriptorError {
+    /// A kernel size, stride, dilation rate or group count is zero
+    Zero {
+        /// The field holding the zero
+        field: &'static str,
+    },
+    /// Padding on one side of an axis is larger than the dilated window along it
+    PaddingExceedsWindow {
+        /// Spatial axis, in the order the descriptor lists them
+        axis: usize,
+        /// The offending padding value
+        padding: usize,
+        /// Extent of the dilated window along the axis
+        window: usize,
+    },
+    /// Padding values are set but the padding style computes its own padding
+    PaddingWithStyle(MPSGraphPaddingStyle),
+    /// The layout can't be used with this kind of operation
+    UnsupportedLayout {
+        /// The field holding the layout
+        field: &'static str,
+        /// The layout, as printed by `Debug`
+        layout: String,
+    },
+}
+
+impl fmt::Display for DescriptorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DescriptorError::Zero { field } => write!(f, "{} must be at least 1", field),
+            DescriptorError::PaddingExceedsWindow {
+                axis,
+                padding,
+                window,
+            } => write!(
+                f,
+                "padding {} on axis {} is larger than the window extent {}",
+                padding, axis, window
+            ),
+            DescriptorError::PaddingWithStyle(style) => write!(
+                f,
+                "padding values are only used with explicit padding, not {:?}",
+                style
+            ),
+            DescriptorError::UnsupportedLayout { field, layout } => {
+                write!(f, "{} {} is not supported here", field, layout)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DescriptorError {}
+
+/// Result type returned by descriptor validation
+pub type Result<T> = std::result::Result<T, DescriptorError>;
+
+fn check_nonzero(field: &'static str, values: &[usize]) -> Result<()> {
+    if values.contains(&0) {
+        return Err(DescriptorError::Zero { field });
+    }
+    Ok(())
+}
+
+fn check_padding_style(style: MPSGraphPaddingStyle, padding: &[usize]) -> Result<()> {
+    if style != MPSGraphPaddingStyle::Explicit && padding.iter().any(|&p| p != 0) {
+        return Err(DescriptorError::PaddingWithStyle(style));
+    }
+    Ok(())
+}
+
+/// Checks `[before, after]` padding pairs against the dilated window of each axis
+fn check_window(kernel: &[usize], dilations: &[usize], padding: &[usize]) -> Result<()> {
+    check_nonzero("kernel size", kernel)?;
+    for (axis, ((&size, &dilation), pair)) in kernel
+        .iter()
+        .zip(dilations)
+        .zip(padding.chunks_exact(2))
+        .enumerate()
+    {
+        let window = (size - 1) * dilation + 1;
+        if let Some(&padding) = pair.iter().find(|&&p| p > window) {
+            return Err(DescriptorError::PaddingExceedsWindow {
+                axis,
+                padding,
+                window,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn unsupported_layout(field: &'static str, layout: impl fmt::Debug) -> DescriptorError {
+    DescriptorError::UnsupportedLayout {
+        field,
+        layout: format!("{:?}", layout),
+    }
+}
+
+/// A 2D convolution
+///
+/// The kernel size comes from the weights tensor, so [`validate`](Self::validate) can't
+/// check the padding against it; [`validate_kernel`](Self::validate_kernel) does.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Convolution2dDescriptor {
+    pub strides: [usize; 2],
+    pub dilations: [usize; 2],
+    pub padding: [usize; 4],
+    pub padding_style: MPSGraphPaddingStyle,
+    pub data_layout: MPSGraphTensorNamedDataLayout,
+    pub weights_layout: MPSGraphWeightsLayout,
+    pub groups: usize,
+}
+
+impl Default for Convolution2dDescriptor {
+    fn default() -> Self {
+        Convolution2dDescriptor {
+            strides: [1, 1],
+            dilations: [1, 1],
+            padding: [0; 4],
+            padding_style: MPSGraphPaddingStyle::Explicit,
+            data_layout: MPSGraphTensorNamedDataLayout::NCHW,
+            weights_layout: MPSGraphWeightsLayout::OIHW,
+            groups: 1,
+        }
+    }
+}
+
+impl Convolution2dDescriptor {
+    /// Creates an NCHW/OIHW convolution with unit strides and dilations and no padding
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_strides(mut self, strides: [usize; 2]) -> Self {
+        self.strides = strides;
+        self
+    }
+
+    pub fn with_dilations(mut self, dilations: [usize; 2]) -> Self {
+        self.dilations = dilations;
+        self
+    }
+
+    /// Sets the `[top, bottom, left, right]` padding
+    pub fn with_padding(mut self, padding: [usize; 4]) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_padding_style(mut self, padding_style: MPSGraphPaddingStyle) -> Self {
+        self.padding_style = padding_style;
+        self
+    }
+
+    pub fn with_data_layout(mut self, data_layout: MPSGraphTensorNamedDataLayout) -> Self {
+        self.data_layout = data_layout;
+        self
+    }
+
+    pub fn with_weights_layout(mut self, weights_layout: MPSGraphWeightsLayout) -> Self {
+        self.weights_layout = weights_layout;
+        self
+    }
+
+    pub fn with_groups(mut self, groups: usize) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    /// Checks the strides, dilations, groups, padding style and weights layout
+    pub fn validate(&self) -> Result<()> {
+        check_nonzero("stride", &self.strides)?;
+        check_nonzero("dilation rate", &self.dilations)?;
+        check_nonzero("groups", &[self.groups])?;
+        check_padding_style(self.padding_style, &self.padding)?;
+        match self.weights_layout {
+            MPSGraphWeightsLayout::OIHW | MPSGraphWeightsLayout::HWIO => Ok(()),
+            layout => Err(unsupported_layout("weights layout", layout)),
+        }
+    }
+
+    /// Like [`validate`](Self::validate), and also checks the padding against a
+    /// `[height, width]` kernel
+    pub fn validate_kernel(&self, kernel: [usize; 2]) -> Result<()> {
+        self.validate()?;
+        check_window(&kernel, &self.dilations, &self.padding)
+    }
+
+    /// Validates the descriptor and creates the Objective-C descriptor
+    #[cfg(target_vendor = "apple")]
+    pub fn build(&self) -> Result<MPSGraphConvolution2DOpDescriptor> {
+        self.validate()?;
+        let descriptor = MPSGraphConvolution2DOpDescriptor::new();
+        descriptor.set_stride_in_y(self.strides[0]);
+        descriptor.set_stride_in_x(self.strides[1]);
+        descriptor.set_dilation_rate_in_y(self.dilations[0]);
+        descriptor.set_dilation_rate_in_x(self.dilations[1]);
+        let [top, bottom, left, right] = self.padding;
+        descriptor.set_explicit_padding(left, right, top, bottom);
+        descriptor.set_padding_style(convolution_padding_mode(self.padding_style));
+        descriptor.set_data_layout(match self.data_layout {
+            MPSGraphTensorNamedDataLayout::NCHW => MPSGraphConvolutionDataLayout::NCHW,
+            MPSGraphTensorNamedDataLayout::NHWC => MPSGraphConvolutionDataLayout::NHWC,
+        });
+        descriptor.set_weights_layout(self.weights_layout);
+        descriptor.set_groups(self.groups);
+        Ok(descriptor)
+    }
+}
+
+/// A 3D convolution
+///
+/// Strides and dilations are `[depth, height, width]` and padding is
+/// `[front, back, top, bottom, left, right]`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Convolution3dDescriptor {
+    pub strides: [usize; 3],
+    pub dilations: [usize; 3],
+    pub padding: [usize; 6],
+    pub padding_style: MPSGraphPaddingStyle,
+    pub data_layout: MPSGraphConvolutionDataLayout,
+    pub weights_layout: MPSGraphWeightsLayout,
+    pub groups: usize,
+}
+
+impl Default for Convolution3dDescriptor {
+    fn default() -> Self {
+        Convolution3dDescriptor {
+            strides: [1; 3],
+            dilations: [1; 3],
+            padding: [0; 6],
+            padding_style: MPSGraphPaddingStyle::Explicit,
+            data_layout: MPSGraphConvolutionDataLayout::NCDHW,
+            weights_layout: MPSGraphWeightsLayout::OIDHW,
+            groups: 1,
+        }
+    }
+}
+
+impl Convolution3dDescriptor {
+    /// Creates an NCDHW/OIDHW convolution with unit strides and dilations and no padding
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_strides(mut self, strides: [usize; 3]) -> Self {
+        self.strides = strides;
+        self
+    }
+
+    pub fn with_dilations(mut self, dilations: [usize; 3]) -> Self {
+        self.dilations = dilations;
+        self
+    }
+
+    /// Sets the `[front, back, top, bottom, left, right]` padding
+    pub fn with_padding(mut self, padding: [usize; 6]) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_padding_style(mut self, padding_style: MPSGraphPaddingStyle) -> Self {
+        self.padding_style = padding_style;
+        self
+    }
+
+    pub fn with_data_layout(mut self, data_layout: MPSGraphConvolutionDataLayout) -> Self {
+        self.data_layout = data_layout;
+        self
+    }
+
+    pub fn with_weights_layout(mut self, weights_layout: MPSGraphWeightsLayout) -> Self {
+        self.weights_layout = weights_layout;
+        self
+    }
+
+    pub fn with_groups(mut self, groups: usize) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    /// Checks the strides, dilations, groups, padding style and both layouts
+    pub fn validate(&self) -> Result<()> {
+        check_nonzero("stride", &self.strides)?;
+        check_nonzero("dilation rate", &self.dilations)?;
+        check_nonzero("groups", &[self.groups])?;
+        check_padding_style(self.padding_style, &self.padding)?;
+        match self.data_layout {
+            MPSGraphConvolutionDataLayout::NCDHW | MPSGraphConvolutionDataLayout::NDHWC => {}
+            layout => return Err(unsupported_layout("data layout", layout)),
+        }
+        match self.weights_layout {
+            MPSGraphWeightsLayout::OIDHW | MPSGraphWeightsLayout::DHWIO => Ok(()),
+            layout => Err(unsupported_layout("weights layout", layout)),
+        }
+    }
+
+    /// Like [`validate`](Self::validate), and also checks the padding against a
+    /// `[depth, height, width]` kernel
+    pub fn validate_kernel(&self, kernel: [usize; 3]) -> Result<()> {
+        self.validate()?;
+        check_window(&kernel, &self.dilations, &self.padding)
+    }
+
+    /// Validates the descriptor and creates the Objective-C descriptor
+    #[cfg(target_vendor = "apple")]
+    pub fn build(&self) -> Result<MPSGraphConvolution3DOpDescriptor> {
+        self.validate()?;
+        let descriptor = MPSGraphConvolution3DOpDescriptor::new();
+        descriptor.set_stride_in_z(self.strides[0]);
+        descriptor.set_stride_in_y(self.strides[1]);
+        descriptor.set_stride_in_x(self.strides[2]);
+        descriptor.set_dilation_rate_in_z(self.dilations[0]);
+        descriptor.set_dilation_rate_in_y(self.dilations[1]);
+        descriptor.set_dilation_rate_in_x(self.dilations[2]);
+        let [front, back, top, bottom, left, right] = self.padding;
+        descriptor.set_explicit_padding(left, right, top, bottom, front, back);
+        descriptor.set_padding_style(convolution_padding_mode(self.padding_style));
+        descriptor.set_data_layout(self.data_layout);
+        descriptor.set_weights_layout(self.weights_layout);
+        descriptor.set_groups(self.groups);
+        Ok(descriptor)
+    }
+}
+
+/// A 2D depthwise convolution
+///
+/// The weights use MPSGraph's default layout.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DepthwiseConvolution2dDescriptor {
+    pub strides: [usize; 2],
+    pub dilations: [usize; 2],
+    pub padding: [usize; 4],
+    pub padding_style: MPSGraphPaddingStyle,
+    pub data_layout: MPSGraphTensorNamedDataLayout,
+}
+
+impl Default for DepthwiseConvolution2dDescriptor {
+    fn default() -> Self {
+        DepthwiseConvolution2dDescriptor {
+            strides: [1, 1],
+            dilations: [1, 1],
+            padding: [0; 4],
+            padding_style: MPSGraphPaddingStyle::Explicit,
+            data_layout: MPSGraphTensorNamedDataLayout::NCHW,
+        }
+    }
+}
+
+impl DepthwiseConvolution2dDescriptor {
+    /// Creates an NCHW convolution with unit strides and dilations and no padding
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_strides(mut self, strides: [usize; 2]) -> Self {
+        self.strides = strides;
+        self
+    }
+
+    pub fn with_dilations(mut self, dilations: [usize; 2]) -> Self {
+        self.dilations = dilations;
+        self
+    }
+
+    /// Sets the `[top, bottom, left, right]` padding
+    pub fn with_padding(mut self, padding: [usize; 4]) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_padding_style(mut self, padding_style: MPSGraphPaddingStyle) -> Self {
+        self.padding_style = padding_style;
+        self
+    }
+
+    pub fn with_data_layout(mut self, data_layout: MPSGraphTensorNamedDataLayout) -> Self {
+        self.data_layout = data_layout;
+        self
+    }
+
+    /// Checks the strides, dilations and padding style
+    pub fn validate(&self) -> Result<()> {
+        check_nonzero("stride", &self.strides)?;
+        check_nonzero("dilation rate", &self.dilations)?;
+        check_padding_style(self.padding_style, &self.padding)
+    }
+
+    /// Like [`validate`](Self::validate), and also checks the padding against a
+    /// `[height, width]` kernel
+    pub fn validate_kernel(&self, kernel: [usize; 2]) -> Result<()> {
+        self.validate()?;
+        check_window(&kernel, &self.dilations, &self.padding)
+    }
+
+    /// Validates the descriptor and creates the Objective-C descriptor
+    #[cfg(target_vendor = "apple")]
+    pub fn build(&self) -> Result<MPSGraphDepthwiseConvolution2DOpDescriptor> {
+        self.validate()?;
+        let descriptor = MPSGraphDepthwiseConvolution2DOpDescriptor::new();
+        descriptor.set_stride_in_y(self.strides[0]);
+        descriptor.set_stride_in_x(self.strides[1]);
+        descriptor.set_dilation_rate_in_y(self.dilations[0]);
+        descriptor.set_dilation_rate_in_x(self.dilations[1]);
+        let [top, bottom, left, right] = self.padding;
+        descriptor.set_explicit_padding(left, right, top, bottom);
+        descriptor.set_padding_style(padding_style(self.padding_style));
+        descriptor.set_data_layout(named_layout(self.data_layout));
+        Ok(descriptor)
+    }
+}
+
+/// A 3D depthwise convolution
+///
+/// Strides and dilations are `[depth, height, width]` and padding is
+/// `[front, back, top, bottom, left, right]`. The channel dimension index is counted from
+/// the end when negative, like MPSGraph's default of `-4`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DepthwiseConvolution3dDescriptor {
+    pub strides: [usize; 3],
+    pub dilations: [usize; 3],
+    pub padding: [usize; 6],
+    pub padding_style: MPSGraphPaddingStyle,
+    pub channel_dimension_index: isize,
+}
+
+impl Default for DepthwiseConvolution3dDescriptor {
+    fn default() -> Self {
+        DepthwiseConvolution3dDescriptor {
+            strides: [1; 3],
+            dilations: [1; 3],
+            padding: [0; 6],
+            padding_style: MPSGraphPaddingStyle::Explicit,
+            channel_dimension_index: -4,
+        }
+    }
+}
+
+impl DepthwiseConvolution3dDescriptor {
+    /// Creates a convolution with unit strides and dilations, no padding and channels at `-4`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_strides(mut self, strides: [usize; 3]) -> Self {
+        self.strides = strides;
+        self
+    }
+
+    pub fn with_dilations(mut self, dilations: [usize; 3]) -> Self {
+        self.dilations = dilations;
+        self
+    }
+
+    /// Sets the `[front, back, top, bottom, left, right]` padding
+    pub fn with_padding(mut self, padding: [usize; 6]) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_padding_style(mut self, padding_style: MPSGraphPaddingStyle) -> Self {
+        self.padding_style = padding_style;
+        self
+    }
+
+    pub fn with_channel_dimension_index(mut self, index: isize) -> Self {
+        self.channel_dimension_index = index;
+        self
+    }
+
+    /// Checks the strides, dilations and padding style
+    pub fn validate(&self) -> Result<()> {
+        check_nonzero("stride", &self.strides)?;
+        check_nonzero("dilation rate", &self.dilations)?;
+        check_padding_style(self.padding_style, &self.padding)
+    }
+
+    /// Like [`validate`](Self::validate), and also checks the padding against a
+    /// `[depth, height, width]` kernel
+    pub fn validate_kernel(&self, kernel: [usize; 3]) -> Result<()> {
+        self.validate()?;
+        check_window(&kernel, &self.dilations, &self.padding)
+    }
+
+    /// Validates the descriptor and creates the Objective-C descriptor
+    #[cfg(target_vendor = "apple")]
+    pub fn build(&self) -> Result<MPSGraphDepthwiseConvolution3DOpDescriptor> {
+        self.validate()?;
+        let descriptor = MPSGraphDepthwiseConvolution3DOpDescriptor::new_with_values(
+            &self.strides,
+            &self.dilations,
+            &self.padding,
+            padding_style(self.padding_style),
+        );
+        descriptor.set_channel_dimension_index(self.channel_dimension_index);
+        Ok(descriptor)
+    }
+}
+
+/// A 2D pooling window
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Pooling2dDescriptor {
+    pub kernel: [usize; 2],
+    pub strides: [usize; 2],
+    pub dilations: [usize; 2],
+    pub padding: [usize; 4],
+    pub padding_style: MPSGraphPaddingStyle,
+    pub data_layout: MPSGraphTensorNamedDataLayout,
+    pub return_indices_mode: MPSGraphPoolingReturnIndicesMode,
+    pub return_indices_data_type: MPSDataType,
+    /// Rounds the output size up instead of down
+    pub ceil_mode: bool,
+    /// Counts padded elements when averaging
+    pub include_zero_pad_to_average: bool,
+}
+
+impl Pooling2dDescriptor {
+    /// Creates an NCHW window with unit strides and dilations and no padding
+    pub fn new(kernel: [usize; 2]) -> Self {
+        Pooling2dDescriptor {
+            kernel,
+            strides: [1, 1],
+            dilations: [1, 1],
+            padding: [0; 4],
+            padding_style: MPSGraphPaddingStyle::Explicit,
+            data_layout: MPSGraphTensorNamedDataLayout::NCHW,
+            return_indices_mode: MPSGraphPoolingReturnIndicesMode::None,
+            return_indices_data_type: MPSDataType::Int32,
+            ceil_mode: false,
+            include_zero_pad_to_average: false,
+        }
+    }
+
+    pub fn with_strides(mut self, strides: [usize; 2]) -> Self {
+        self.strides = strides;
+        self
+    }
+
+    pub fn with_dilations(mut self, dilations: [usize; 2]) -> Self {
+        self.dilations = dilations;
+        self
+    }
+
+    /// Sets the `[top, bottom, left, right]` padding
+    pub fn with_padding(mut self, padding: [usize; 4]) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_padding_style(mut self, padding_style: MPSGraphPaddingStyle) -> Self {
+        self.padding_style = padding_style;
+        self
+    }
+
+    pub fn with_data_layout(mut self, data_layout: MPSGraphTensorNamedDataLayout) -> Self {
+        self.data_layout = data_layout;
+        self
+    }
+
+    /// Sets how max pooling with indices reports the position of each maximum
+    pub fn with_return_indices(
+        mut self,
+        mode: MPSGraphPoolingReturnIndicesMode,
+        data_type: MPSDataType,
+    ) -> Self {
+        self.return_indices_mode = mode;
+        self.return_indices_data_type = data_type;
+        self
+    }
+
+    pub fn with_ceil_mode(mut self, ceil_mode: bool) -> Self {
+        self.ceil_mode = ceil_mode;
+        self
+    }
+
+    pub fn with_include_zero_pad_to_average(mut self, include: bool) -> Self {
+        self.include_zero_pad_to_average = include;
+        self
+    }
+
+    /// Checks the window, strides and padding
+    pub fn validate(&self) -> Result<()> {
+        check_nonzero("stride", &self.strides)?;
+        check_nonzero("dilation rate", &self.dilations)?;
+        check_padding_style(self.padding_style, &self.padding)?;
+        check_window(&self.kernel, &self.dilations, &self.padding)
+    }
+
+    /// Validates the descriptor and creates the Objective-C descriptor
+    #[cfg(target_vendor = "apple")]
+    pub fn build(&self) -> Result<MPSGraphPooling2DOpDescriptor> {
+        self.validate()?;
+        let descriptor = MPSGraphPooling2DOpDescriptor::new(
+            self.kernel[1],
+            self.kernel[0],
+            self.strides[1],
+            self.strides[0],
+            self.dilations[1],
+            self.dilations[0],
+            self.padding[2],
+            self.padding[3],
+            self.padding[0],
+            self.padding[1],
+            self.padding_style,
+            self.data_layout,
+        );
+        descriptor.set_return_indices_mode(self.return_indices_mode);
+        descriptor.set_return_indices_data_type(self.return_indices_data_type);
+        descriptor.set_ceil_mode(self.ceil_mode);
+        descriptor.set_include_zero_pad_to_average(self.include_zero_pad_to_average);
+        Ok(descriptor)
+    }
+}
+
+/// A pooling window over the four innermost dimensions
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Pooling4dDescriptor {
+    pub kernel: [usize; 4],
+    pub strides: [usize; 4],
+    pub dilations: [usize; 4],
+    pub padding: [usize; 8],
+    pub padding_style: MPSGraphPaddingStyle,
+    pub return_indices_mode: MPSGraphPoolingReturnIndicesMode,
+    pub return_indices_data_type: MPSDataType,
+    /// Rounds the output size up instead of down
+    pub ceil_mode: bool,
+    /// Counts padded elements when averaging
+    pub include_zero_pad_to_average: bool,
+}
+
+impl Pooling4dDescriptor {
+    /// Creates a window with unit strides and dilations and no padding
+    pub fn new(kernel: [usize; 4]) -> Self {
+        Pooling4dDescriptor {
+            kernel,
+            strides: [1; 4],
+            dilations: [1; 4],
+            padding: [0; 8],
+            padding_style: MPSGraphPaddingStyle::Explicit,
+            return_indices_mode: MPSGraphPoolingReturnIndicesMode::None,
+            return_indices_data_type: MPSDataType::Int32,
+            ceil_mode: false,
+            include_zero_pad_to_average: false,
+        }
+    }
+
+    pub fn with_strides(mut self, strides: [usize; 4]) -> Self {
+        self.strides = strides;
+        self
+    }
+
+    pub fn with_dilations(mut self, dilations: [usize; 4]) -> Self {
+        self.dilations = dilations;
+        self
+    }
+
+    /// Sets the `[before, after]` padding of each dimension
+    pub fn with_padding(mut self, padding: [usize; 8]) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_padding_style(mut self, padding_style: MPSGraphPaddingStyle) -> Self {
+        self.padding_style = padding_style;
+        self
+    }
+
+    /// Sets how max pooling with indices reports the position of each maximum
+    pub fn with_return_indices(
+        mut self,
+        mode: MPSGraphPoolingReturnIndicesMode,
+        data_type: MPSDataType,
+    ) -> Self {
+        self.return_indices_mode = mode;
+        self.return_indices_data_type = data_type;
+        self
+    }
+
+    pub fn with_ceil_mode(mut self, ceil_mode: bool) -> Self {
+        self.ceil_mode = ceil_mode;
+        self
+    }
+
+    pub fn with_include_zero_pad_to_average(mut self, include: bool) -> Self {
+        self.include_zero_pad_to_average = include;
+        self
+    }
+
+    /// Checks the window, strides and padding
+    pub fn validate(&self) -> Result<()> {
+        check_nonzero("stride", &self.strides)?;
+        check_nonzero("dilation rate", &self.dilations)?;
+        check_padding_style(self.padding_style, &self.padding)?;
+        check_window(&self.kernel, &self.dilations, &self.padding)
+    }
+
+    /// Validates the descriptor and creates the Objective-C descriptor
+    #[cfg(target_vendor = "apple")]
+    pub fn build(&self) -> Result<MPSGraphPooling4DOpDescriptor> {
+        self.validate()?;
+        let descriptor = MPSGraphPooling4DOpDescriptor::new(
+            &self.kernel,
+            &self.strides,
+            &self.dilations,
+            &self.padding,
+            self.padding_style,
+        );
+        descriptor.set_return_indices_mode(self.return_indices_mode);
+        descriptor.set_return_indices_data_type(self.return_indices_data_type);
+        descriptor.set_ceil_mode(self.ceil_mode);
+        descriptor.set_include_zero_pad_to_average(self.include_zero_pad_to_average);
+        Ok(descriptor)
+    }
+}
+
+/// A stencil over the four innermost dimensions
+///
+/// The window is the shape of the weights tensor, so [`validate`](Self::validate) can't
+/// check the padding against it; [`validate_kernel`](Self::validate_kernel) does.
+#[derive(Clone, Debug)]
+pub struct StencilDescriptor {
+    pub reduction_mode: MPSGraphReductionMode,
+    /// Offset of the window origin in each dimension
+    pub offsets: [i64; 4],
+    pub strides: [usize; 4],
+    pub dilations: [usize; 4],
+    pub padding: [usize; 8],
+    /// How values outside the source are produced
+    pub boundary_mode: MPSGraphPaddingMode,
+    pub padding_style: MPSGraphPaddingStyle,
+    /// Value of padded elements with [`MPSGraphPaddingMode::Constant`]
+    pub padding_constant: f32,
+}
+
+impl StencilDescriptor {
+    /// The fields compared and hashed, with the padding constant as bits
+    ///
+    /// Adding zero turns -0.0 into 0.0 first, so `Eq` and `Hash` agree with each other. NaN
+    /// constants are equal when their bits are.
+    #[allow(clippy::type_complexity)]
+    fn key(
+        &self,
+    ) -> (
+        MPSGraphReductionMode,
+        [i64; 4],
+        [usize; 4],
+        [usize; 4],
+        [usize; 8],
+        MPSGraphPaddingMode,
+        MPSGraphPaddingStyle,
+        u32,
+    ) {
+        (
+            self.reduction_mode,
+            self.offsets,
+            self.strides,
+            self.dilations,
+            self.padding,
+            self.boundary_mode,
+            self.padding_style,
+            (self.padding_constant + 0.0).to_bits(),
+        )
+    }
+}
+
+impl PartialEq for StencilDescriptor {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for StencilDescriptor {}
+
+impl Hash for StencilDescriptor {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl Default for StencilDescriptor {
+    fn default() -> Self {
+        StencilDescriptor {
+            reduction_mode: MPSGraphReductionMode::Sum,
+            offsets: [0; 4],
+            strides: [1; 4],
+            dilations: [1; 4],
+            padding: [0; 8],
+            boundary_mode: MPSGraphPaddingMode::Zero,
+            padding_style: MPSGraphPaddingStyle::Explicit,
+            padding_constant: 0.0,
+        }
+    }
+}
+
+impl StencilDescriptor {
+    /// Creates a sum stencil with zero boundaries, unit strides and dilations and no padding
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_reduction_mode(mut self, reduction_mode: MPSGraphReductionMode) -> Self {
+        self.reduction_mode = reduction_mode;
+        self
+    }
+
+    pub fn with_offsets(mut self, offsets: [i64; 4]) -> Self {
+        self.offsets = offsets;
+        self
+    }
+
+    pub fn with_strides(mut self, strides: [usize; 4]) -> Self {
+        self.strides = strides;
+        self
+    }
+
+    pub fn with_dilations(mut self, dilations: [usize; 4]) -> Self {
+        self.dilations = dilations;
+        self
+    }
+
+    /// Sets the `[before, after]` padding of each dimension
+    pub fn with_padding(mut self, padding: [usize; 8]) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_boundary_mode(mut self, boundary_mode: MPSGraphPaddingMode) -> Self {
+        self.boundary_mode = boundary_mode;
+        self
+    }
+
+    pub fn with_padding_style(mut self, padding_style: MPSGraphPaddingStyle) -> Self {
+        self.padding_style = padding_style;
+        self
+    }
+
+    pub fn with_padding_constant(mut self, padding_constant: f32) -> Self {
+        self.padding_constant = padding_constant;
+        self
+    }
+
+    /// Checks the strides, dilations and padding style
+    pub fn validate(&self) -> Result<()> {
+        check_nonzero("stride", &self.strides)?;
+        check_nonzero("dilation rate", &self.dilations)?;
+        check_padding_style(self.padding_style, &self.padding)
+    }
+
+    /// Like [`validate`](Self::validate), and also checks the padding against the shape of
+    /// the weights
+    pub fn validate_kernel(&self, kernel: [usize; 4]) -> Result<()> {
+        self.validate()?;
+        check_window(&kernel, &self.dilations, &self.padding)
+    }
+
+    /// Validates the descriptor and creates the Objective-C descriptor
+    #[cfg(target_vendor = "apple")]
+    pub fn build(&self) -> Result<MPSGraphStencilOpDescriptor> {
+        self.validate()?;
+        Ok(MPSGraphStencilOpDescriptor::with_all_params(
+            self.reduction_mode,
+            &MPSShape(create_ns_array_from_i64_slice(&self.offsets)),
+            &MPSShape::from_slice(&self.strides),
+            &MPSShape::from_slice(&self.dilations),
+            &MPSShape::from_slice(&self.padding),
+            self.boundary_mode,
+            padding_style(self.padding_style),
+            self.padding_constant,
+        ))
+    }
+}
+
+/// An image-to-column window
+///
+/// Im2col only supports explicit padding.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ImToColDescriptor {
+    pub kernel: [usize; 2],
+    pub strides: [usize; 2],
+    pub dilations: [usize; 2],
+    pub padding: [usize; 4],
+    pub data_layout: MPSGraphTensorNamedDataLayout,
+}
+
+impl ImToColDescriptor {
+    /// Creates an NCHW window with unit strides and dilations and no padding
+    pub fn new(kernel: [usize; 2]) -> Self {
+        ImToColDescriptor {
+            kernel,
+            strides: [1, 1],
+            dilations: [1, 1],
+            padding: [0; 4],
+            data_layout: MPSGraphTensorNamedDataLayout::NCHW,
+        }
+    }
+
+    pub fn with_strides(mut self, strides: [usize; 2]) -> Self {
+        self.strides = strides;
+        self
+    }
+
+    pub fn with_dilations(mut self, dilations: [usize; 2]) -> Self {
+        self.dilations = dilations;
+        self
+    }
+
+    /// Sets the `[top, bottom, left, right]` padding
+    pub fn with_padding(mut self, padding: [usize; 4]) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_data_layout(mut self, data_layout: MPSGraphTensorNamedDataLayout) -> Self {
+        self.data_layout = data_layout;
+        self
+    }
+
+    /// Checks the window, strides and padding
+    pub fn validate(&self) -> Result<()> {
+        check_nonzero("stride", &self.strides)?;
+        check_nonzero("dilation rate", &self.dilations)?;
+        check_window(&self.kernel, &self.dilations, &self.padding)
+    }
+
+    /// Validates the descriptor and creates the Objective-C descriptor
+    #[cfg(target_vendor = "apple")]
+    pub fn build(&self) -> Result<MPSGraphImToColOpDescriptor> {
+        self.validate()?;
+        Ok(
+            MPSGraphImToColOpDescriptor::descriptor_with_kernel_dimensions(
+                self.kernel[1],
+                self.kernel[0],
+                self.strides[1],
+                self.strides[0],
+                self.dilations[1],
+                self.dilations[0],
+                self.padding[2],
+                self.padding[3],
+                self.padding[0],
+                self.padding[1],
+                named_layout(self.data_layout),
+            ),
+        )
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+fn convolution_padding_mode(style: MPSGraphPaddingStyle) -> MPSGraphConvolutionPaddingMode {
+    match style {
+        MPSGraphPaddingStyle::Explicit => MPSGraphConvolutionPaddingMode::Explicit,
+        MPSGraphPaddingStyle::TfValid => MPSGraphConvolutionPaddingMode::Valid,
+        MPSGraphPaddingStyle::TfSame => MPSGraphConvolutionPaddingMode::Same,
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+fn padding_style(style: MPSGraphPaddingStyle) -> PaddingStyle {
+    match style {
+        MPSGraphPaddingStyle::Explicit => PaddingStyle::Explicit,
+        MPSGraphPaddingStyle::TfValid => PaddingStyle::TfValid,
+        MPSGraphPaddingStyle::TfSame => PaddingStyle::TfSame,
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+fn named_layout(layout: MPSGraphTensorNamedDataLayout) -> TensorNamedDataLayout {
+    match layout {
+        MPSGraphTensorNamedDataLayout::NCHW => TensorNamedDataLayout::NCHW,
+        MPSGraphTensorNamedDataLayout::NHWC => TensorNamedDataLayout::NHWC,
+    }
+}
+
+/// Returns the dimensions of a tensor whose rank and every dimension are known
+#[cfg(target_vendor = "apple")]
+fn static_dims<const N: usize>(tensor: &MPSGraphTensor) -> Option<[usize; N]> {
+    tensor.shape().to_shape().to_static()?.try_into().ok()
+}
+
+/// Operations created from validated descriptors
+#[cfg(target_vendor = "apple")]
+impl MPSGraph {
+    /// Creates a 2D convolution from a [`Convolution2dDescriptor`]
+    ///
+    /// When the shape of `weights` is static, the padding is also checked against its kernel.
+    pub fn try_convolution_2d(
+        &self,
+        source: &MPSGraphTensor,
+        weights: &MPSGraphTensor,
+        descriptor: &Convolution2dDescriptor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        if let Some(dims) = static_dims::<4>(weights) {
+            descriptor.validate_kernel(match descriptor.weights_layout {
+                MPSGraphWeightsLayout::HWIO => [dims[0], dims[1]],
+                _ => [dims[2], dims[3]],
+            })?;
+        }
+        Ok(self.convolution_2d(source, weights, &descriptor.build()?, name))
+    }
+
+    /// Creates a 3D convolution from a [`Convolution3dDescriptor`]
+    ///
+    /// When the shape of `weights` is static, the padding is also checked against its kernel.
+    pub fn try_convolution_3d(
+        &self,
+        source: &MPSGraphTensor,
+        weights: &MPSGraphTensor,
+        descriptor: &Convolution3dDescriptor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        if let Some(dims) = static_dims::<5>(weights) {
+            descriptor.validate_kernel(match descriptor.weights_layout {
+                MPSGraphWeightsLayout::DHWIO => [dims[0], dims[1], dims[2]],
+                _ => [dims[2], dims[3], dims[4]],
+            })?;
+        }
+        Ok(self.convolution_3d(source, weights, &descriptor.build()?, name))
+    }
+
+    /// Creates a 2D depthwise convolution from a [`DepthwiseConvolution2dDescriptor`]
+    pub fn try_depthwise_convolution_2d(
+        &self,
+        source: &MPSGraphTensor,
+        weights: &MPSGraphTensor,
+        descriptor: &DepthwiseConvolution2dDescriptor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        Ok(self.depthwise_convolution_2d(source, weights, &descriptor.build()?, name))
+    }
+
+    /// Creates a 3D depthwise convolution from a [`DepthwiseConvolution3dDescriptor`]
+    pub fn try_depthwise_convolution_3d(
+        &self,
+        source: &MPSGraphTensor,
+        weights: &MPSGraphTensor,
+        descriptor: &DepthwiseConvolution3dDescriptor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        Ok(self.depthwise_convolution_3d(source, weights, &descriptor.build()?, name))
+    }
+
+    /// Creates a 2D max pooling from a [`Pooling2dDescriptor`]
+    pub fn try_max_pooling_2d(
+        &self,
+        source: &MPSGraphTensor,
+        descriptor: &Pooling2dDescriptor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        Ok(self.max_pooling_2d(source, &descriptor.build()?, name))
+    }
+
+    /// Creates a 2D average pooling from a [`Pooling2dDescriptor`]
+    pub fn try_avg_pooling_2d(
+        &self,
+        source: &MPSGraphTensor,
+        descriptor: &Pooling2dDescriptor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        Ok(self.avg_pooling_2d(source, &descriptor.build()?, name))
+    }
+
+    /// Creates a 2D L2-norm pooling from a [`Pooling2dDescriptor`]
+    pub fn try_l2_norm_pooling_2d(
+        &self,
+        source: &MPSGraphTensor,
+        descriptor: &Pooling2dDescriptor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        Ok(self.l2_norm_pooling_2d(source, &descriptor.build()?, name))
+    }
+
+    /// Creates a 4D max pooling from a [`Pooling4dDescriptor`]
+    pub fn try_max_pooling_4d(
+        &self,
+        source: &MPSGraphTensor,
+        descriptor: &Pooling4dDescriptor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        Ok(self.max_pooling_4d(source, &descriptor.build()?, name))
+    }
+
+    /// Creates a 4D average pooling from a [`Pooling4dDescriptor`]
+    pub fn try_avg_pooling_4d(
+        &self,
+        source: &MPSGraphTensor,
+        descriptor: &Pooling4dDescriptor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        Ok(self.avg_pooling_4d(source, &descriptor.build()?, name))
+    }
+
+    /// Creates a 4D L2-norm pooling from a [`Pooling4dDescriptor`]
+    pub fn try_l2_norm_pooling_4d(
+        &self,
+        source: &MPSGraphTensor,
+        descriptor: &Pooling4dDescriptor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        Ok(self.l2_norm_pooling_4d(source, &descriptor.build()?, name))
+    }
+
+    /// Creates a stencil from a [`StencilDescriptor`]
+    ///
+    /// When the shape of `weights` is static, the padding is also checked against it.
+    pub fn try_stencil(
+        &self,
+        source: &MPSGraphTensor,
+        weights: &MPSGraphTensor,
+        descriptor: &StencilDescriptor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        if let Some(kernel) = static_dims::<4>(weights) {
+            descriptor.validate_kernel(kernel)?;
+        }
+        Ok(self.stencil(source, weights, &descriptor.build()?, name))
+    }
+
+    /// Creates an image-to-column operation from an [`ImToColDescriptor`]
+    pub fn try_im_to_col(
+        &self,
+        source: &MPSGraphTensor,
+        descriptor: &ImToColDescriptor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        Ok(self.im_to_col(source, &descriptor.build()?, name))
+    }
+}
@@ -0,0 +1,365 @@
+//! Pure-Rust tensor shapes with dynamic dimensions.
+//!
+//! [`Shape`] is a plain value type that can be inspected and transformed without touching
+//! Objective-C. It is converted into an [`MPSShape`](crate::shape::MPSShape) only when it is
+//! passed to MPSGraph.
+
+use std::fmt;
+use std::ops::Index;
+
+/// A single tensor dimension
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dim {
+    /// A dimension with a size known when the graph is built
+    Static(usize),
+    /// A dimension whose size is only known at run time (`-1` in MPSGraph)
+    Dynamic,
+}
+
+impl Dim {
+    /// Creates a dimension from an MPSGraph shape value, where negative values are dynamic
+    pub fn from_i64(value: i64) -> Self {
+        if value < 0 {
+            Dim::Dynamic
+        } else {
+            Dim::Static(value as usize)
+        }
+    }
+
+    /// Returns the MPSGraph shape value, `-1` for dynamic dimensions
+    pub fn to_i64(self) -> i64 {
+        match self {
+            Dim::Static(size) => size as i64,
+            Dim::Dynamic => -1,
+        }
+    }
+
+    /// Returns the size if the dimension is static
+    pub fn size(self) -> Option<usize> {
+        match self {
+            Dim::Static(size) => Some(size),
+            Dim::Dynamic => None,
+        }
+    }
+
+    /// Returns true if the size of the dimension is known
+    pub fn is_static(self) -> bool {
+        matches!(self, Dim::Static(_))
+    }
+}
+
+impl From<usize> for Dim {
+    fn from(size: usize) -> Self {
+        Dim::Static(size)
+    }
+}
+
+impl fmt::Display for Dim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dim::Static(size) => write!(f, "{}", size),
+            Dim::Dynamic => write!(f, "?"),
+        }
+    }
+}
+
+/// Errors reported by [`Shape`] transformations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShapeError {
+    /// Two dimensions can't be broadcast against each other
+    IncompatibleBroadcast {
+        /// Left-hand shape
+        lhs: Shape,
+        /// Right-hand shape
+        rhs: Shape,
+        /// Axis of the broadcast result where the dimensions disagree
+        axis: usize,
+    },
+    /// An axis is outside `-rank..rank`
+    AxisOutOfRange {
+        /// The requested axis
+        axis: i64,
+        /// Rank of the shape
+        rank: usize,
+    },
+    /// An axis appears more than once
+    DuplicateAxis {
+        /// The normalized axis
+        axis: usize,
+    },
+    /// A permutation doesn't list every axis exactly once
+    InvalidPermutation {
+        /// The requested permutation
+        permutation: Vec<i64>,
+        /// Rank of the shape
+        rank: usize,
+    },
+    /// A reshape target is malformed or doesn't preserve the element count
+    InvalidReshape {
+        /// Source shape
+        shape: Shape,
+        /// Requested target shape, with `-1` for the inferred dimension
+        target: Vec<i64>,
+        /// Why the reshape was rejected
+        reason: String,
+    },
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeError::IncompatibleBroadcast { lhs, rhs, axis } => write!(
+                f,
+                "shapes {} and {} can't be broadcast: dimensions differ at axis {}",
+                lhs, rhs, axis
+            ),
+            ShapeError::AxisOutOfRange { axis, rank } => {
+                write!(f, "axis {} is out of range for rank {}", axis, rank)
+            }
+            ShapeError::DuplicateAxis { axis } => write!(f, "axis {} is repeated", axis),
+            ShapeError::InvalidPermutation { permutation, rank } => write!(
+                f,
+                "{:?} is not a permutation of the {} axes",
+                permutation, rank
+            ),
+            ShapeError::InvalidReshape {
+                shape,
+                target,
+                reason,
+            } => write!(f, "can't reshape {} to {:?}: {}", shape, target, reason),
+        }
+    }
+}
+
+impl std::error::Error for ShapeError {}
+
+/// A tensor shape whose dimensions may be dynamic
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Shape(Vec<Dim>);
+
+impl Shape {
+    /// Creates a shape from its dimensions
+    pub fn new(dims: Vec<Dim>) -> Self {
+        Shape(dims)
+    }
+
+    /// Creates a rank-0 shape
+    pub fn scalar() -> Self {
+        Shape(Vec::new())
+    }
+
+    /// Creates a fully static shape
+    pub fn from_static(dims: &[usize]) -> Self {
+        Shape(dims.iter().map(|&size| Dim::Static(size)).collect())
+    }
+
+    /// Creates a shape from MPSGraph shape values, where negative values are dynamic
+    pub fn from_i64(dims: &[i64]) -> Self {
+        Shape(dims.iter().map(|&value| Dim::from_i64(value)).collect())
+    }
+
+    /// Returns the MPSGraph shape values, `-1` for dynamic dimensions
+    pub fn to_i64(&self) -> Vec<i64> {
+        self.0.iter().map(|dim| dim.to_i64()).collect()
+    }
+
+    /// Returns the sizes if every dimension is static
+    pub fn to_static(&self) -> Option<Vec<usize>> {
+        self.0.iter().map(|dim| dim.size()).collect()
+    }
+
+    /// Returns the dimensions
+    pub fn dims(&self) -> &[Dim] {
+        &self.0
+    }
+
+    /// Returns the number of dimensions
+    pub fn rank(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if every dimension is static
+    pub fn is_static(&self) -> bool {
+        self.0.iter().all(|dim| dim.is_static())
+    }
+
+    /// Returns the number of elements if every dimension is static and the count fits in a
+    /// `usize`
+    pub fn element_count(&self) -> Option<usize> {
+        self.0
+            .iter()
+            .try_fold(1usize, |count, dim| count.checked_mul(dim.size()?))
+    }
+
+    /// Converts a possibly negative axis into an index in `0..rank`
+    pub fn normalize_axis(&self, axis: i64) -> Result<usize, ShapeError> {
+        normalize_axis(axis, self.rank())
+    }
+
+    /// Normalizes a list of axes, rejecting out-of-range and repeated axes
+    pub fn normalize_axes(&self, axes: &[i64]) -> Result<Vec<usize>, ShapeError> {
+        let mut normalized = Vec::with_capacity(axes.len());
+        for &axis in axes {
+            let axis = self.normalize_axis(axis)?;
+            if normalized.contains(&axis) {
+                return Err(ShapeError::DuplicateAxis { axis });
+            }
+            normalized.push(axis);
+        }
+        Ok(normalized)
+    }
+
+    /// Computes the NumPy-style broadcast of two shapes
+    ///
+    /// Shapes are aligned at their trailing dimensions. A static `1` stretches to the other
+    /// dimension. A dynamic dimension broadcast against a static size other than `1` is
+    /// assumed to match it.
+    pub fn broadcast(&self, other: &Shape) -> Result<Shape, ShapeError> {
+        let rank = self.rank().max(other.rank());
+        let mut dims = Vec::with_capacity(rank);
+
+        for axis in 0..rank {
+            // Missing leading dimensions behave like 1
+            let lhs = (axis + self.rank())
+                .checked_sub(rank)
+                .map_or(Dim::Static(1), |i| self.0[i]);
+            let rhs = (axis + other.rank())
+                .checked_sub(rank)
+                .map_or(Dim::Static(1), |i| other.0[i]);
+
+            let dim = match (lhs, rhs) {
+                (Dim::Static(1), dim) | (dim, Dim::Static(1)) => dim,
+                (Dim::Static(a), Dim::Static(b)) if a == b => lhs,
+                (Dim::Static(_), Dim::Static(_)) => {
+                    return Err(ShapeError::IncompatibleBroadcast {
+                        lhs: self.clone(),
+                        rhs: other.clone(),
+                        axis,
+                    })
+                }
+                (Dim::Static(size), Dim::Dynamic) | (Dim::Dynamic, Dim::Static(size)) => {
+                    Dim::Static(size)
+                }
+                (Dim::Dynamic, Dim::Dynamic) => Dim::Dynamic,
+            };
+            dims.push(dim);
+        }
+
+        Ok(Shape(dims))
+    }
+
+    /// Computes the result of reshaping to `target`
+    ///
+    /// At most one entry of `target` may be `-1`; its size is inferred from the element count.
+    /// If the source shape has dynamic dimensions the inferred dimension is dynamic too.
+    pub fn reshape(&self, target: &[i64]) -> Result<Shape, ShapeError> {
+        let invalid = |reason: String| ShapeError::InvalidReshape {
+            shape: self.clone(),
+            target: target.to_vec(),
+            reason,
+        };
+
+        let mut inferred = None;
+        for (axis, &size) in target.iter().enumerate() {
+            if size == -1 {
+                if inferred.is_some() {
+                    return Err(invalid(String::from("more than one dimension is -1")));
+                }
+                inferred = Some(axis);
+            } else if size < 0 {
+                return Err(invalid(format!("dimension {} has size {}", axis, size)));
+            }
+        }
+
+        let known = target
+            .iter()
+            .filter(|&&size| size >= 0)
+            .try_fold(1usize, |count, &size| count.checked_mul(size as usize))
+            .ok_or_else(|| invalid(String::from("the target element count overflows")))?;
+        let mut dims: Vec<Dim> = target.iter().map(|&size| Dim::from_i64(size)).collect();
+
+        let count = self.element_count();
+        if count.is_none() && self.is_static() {
+            return Err(invalid(String::from("the source element count overflows")));
+        }
+        match (count, inferred) {
+            (Some(count), Some(axis)) => {
+                if known == 0 || count % known != 0 {
+                    return Err(invalid(format!(
+                        "{} elements can't be split into chunks of {}",
+                        count, known
+                    )));
+                }
+                dims[axis] = Dim::Static(count / known);
+            }
+            (Some(count), None) if count != known => {
+                return Err(invalid(format!(
+                    "element count changes from {} to {}",
+                    count, known
+                )));
+            }
+            _ => {}
+        }
+
+        Ok(Shape(dims))
+    }
+
+    /// Computes the result of transposing with `permutation`
+    ///
+    /// Result dimension `i` is source dimension `permutation[i]`. Negative axes are allowed.
+    pub fn permute(&self, permutation: &[i64]) -> Result<Shape, ShapeError> {
+        let invalid = || ShapeError::InvalidPermutation {
+            permutation: permutation.to_vec(),
+            rank: self.rank(),
+        };
+
+        if permutation.len() != self.rank() {
+            return Err(invalid());
+        }
+        let axes = self.normalize_axes(permutation).map_err(|_| invalid())?;
+
+        Ok(Shape(axes.iter().map(|&axis| self.0[axis]).collect()))
+    }
+}
+
+/// Converts a possibly negative axis into an index in `0..rank`
+pub fn normalize_axis(axis: i64, rank: usize) -> Result<usize, ShapeError> {
+    let normalized = if axis < 0 { axis + rank as i64 } else { axis };
+    if normalized < 0 || normalized >= rank as i64 {
+        return Err(ShapeError::AxisOutOfRange { axis, rank });
+    }
+    Ok(normalized as usize)
+}
+
+impl Index<usize> for Shape {
+    type Output = Dim;
+
+    fn index(&self, index: usize) -> &Dim {
+        &self.0[index]
+    }
+}
+
+impl From<Vec<Dim>> for Shape {
+    fn from(dims: Vec<Dim>) -> Self {
+        Shape(dims)
+    }
+}
+
+impl From<&[usize]> for Shape {
+    fn from(dims: &[usize]) -> Self {
+        Shape::from_static(dims)
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, dim) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", dim)?;
+        }
+        write!(f, "]")
+    }
+}
@@ -0,0 +1,595 @@
+//! DLPack tensor exchange.
+//!
+//! The `#[repr(C)]` types mirror `dlpack.h`, so a [`DLManagedTensor`] can be passed to and
+//! received from any library that speaks DLPack. [`ManagedTensor`] owns one and calls its
+//! deleter when dropped.
+//!
+//! Exports are CPU tensors and don't copy: a [`HostTensor`] or [`DynHostTensor`] hands its
+//! storage (and, for a host tensor, its strides) to the DLPack tensor, and on Apple targets
+//! [`MPSGraphTensorData::to_dlpack`] points into the backing buffer when it is CPU-visible,
+//! keeping it retained until the consumer calls the deleter. Imports borrow compact CPU data
+//! through [`ManagedTensor::as_bytes`] and copy everything else into host tensors, honouring
+//! the producer's strides and byte offset.
+
+use crate::core::MPSDataType;
+use crate::error::MPSGraphError;
+use crate::host::{contiguous_strides, DynHostTensor, HostElement, HostTensor};
+use std::any::Any;
+use std::ffi::c_void;
+use std::fmt;
+use std::ptr::NonNull;
+
+#[cfg(target_vendor = "apple")]
+use crate::tensor_data::MPSGraphTensorData;
+#[cfg(target_vendor = "apple")]
+use objc2::msg_send;
+#[cfg(target_vendor = "apple")]
+use objc2::runtime::AnyObject;
+
+/// Kind of device a DLPack tensor's data lives on (`DLDeviceType`)
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DLDeviceType(pub i32);
+
+impl DLDeviceType {
+    /// Host memory
+    pub const CPU: Self = DLDeviceType(1);
+    /// CUDA device memory
+    pub const CUDA: Self = DLDeviceType(2);
+    /// Pinned CUDA host memory
+    pub const CUDA_HOST: Self = DLDeviceType(3);
+    /// OpenCL buffers
+    pub const OPENCL: Self = DLDeviceType(4);
+    /// Vulkan buffers
+    pub const VULKAN: Self = DLDeviceType(7);
+    /// Metal buffers; `data` is an `id<MTLBuffer>`
+    pub const METAL: Self = DLDeviceType(8);
+    /// ROCm device memory
+    pub const ROCM: Self = DLDeviceType(10);
+}
+
+/// Device a DLPack tensor's data lives on (`DLDevice`)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DLDevice {
+    /// Kind of device
+    pub device_type: DLDeviceType,
+    /// Index of the device among those of its kind
+    pub device_id: i32,
+}
+
+impl DLDevice {
+    /// Host memory
+    pub const CPU: Self = DLDevice {
+        device_type: DLDeviceType::CPU,
+        device_id: 0,
+    };
+}
+
+/// Type code of a [`DLDataType`] (`DLDataTypeCode`)
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DLDataTypeCode(pub u8);
+
+impl DLDataTypeCode {
+    /// Signed integers
+    pub const INT: Self = DLDataTypeCode(0);
+    /// Unsigned integers
+    pub const UINT: Self = DLDataTypeCode(1);
+    /// IEEE floating point
+    pub const FLOAT: Self = DLDataTypeCode(2);
+    /// Opaque handles
+    pub const OPAQUE_HANDLE: Self = DLDataTypeCode(3);
+    /// bfloat16
+    pub const BFLOAT: Self = DLDataTypeCode(4);
+    /// Complex numbers; `bits` covers both components
+    pub const COMPLEX: Self = DLDataTypeCode(5);
+    /// Booleans, one byte each
+    pub const BOOL: Self = DLDataTypeCode(6);
+}
+
+/// Element type of a DLPack tensor (`DLDataType`)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DLDataType {
+    /// Kind of number
+    pub code: DLDataTypeCode,
+    /// Width of one lane in bits
+    pub bits: u8,
+    /// Number of lanes; 1 for scalars
+    pub lanes: u16,
+}
+
+impl DLDataType {
+    const fn scalar(code: DLDataTypeCode, bits: u8) -> Self {
+        DLDataType {
+            code,
+            bits,
+            lanes: 1,
+        }
+    }
+}
+
+impl TryFrom<MPSDataType> for DLDataType {
+    type Error = DlpackError;
+
+    fn try_from(data_type: MPSDataType) -> Result<Self> {
+        let (code, bits) = match data_type {
+            MPSDataType::Float16 => (DLDataTypeCode::FLOAT, 16),
+            MPSDataType::Float32 => (DLDataTypeCode::FLOAT, 32),
+            MPSDataType::Float64 => (DLDataTypeCode::FLOAT, 64),
+            MPSDataType::BFloat16 => (DLDataTypeCode::BFLOAT, 16),
+            MPSDataType::Int8 => (DLDataTypeCode::INT, 8),
+            MPSDataType::Int16 => (DLDataTypeCode::INT, 16),
+            MPSDataType::Int32 => (DLDataTypeCode::INT, 32),
+            MPSDataType::Int64 => (DLDataTypeCode::INT, 64),
+            MPSDataType::UInt8 => (DLDataTypeCode::UINT, 8),
+            MPSDataType::UInt16 => (DLDataTypeCode::UINT, 16),
+            MPSDataType::UInt32 => (DLDataTypeCode::UINT, 32),
+            MPSDataType::UInt64 => (DLDataTypeCode::UINT, 64),
+            MPSDataType::Bool => (DLDataTypeCode::BOOL, 8),
+            MPSDataType::Complex16 => (DLDataTypeCode::COMPLEX, 32),
+            MPSDataType::Complex32 => (DLDataTypeCode::COMPLEX, 64),
+            MPSDataType::Complex64 => (DLDataTypeCode::COMPLEX, 128),
+            _ => return Err(DlpackError::UnsupportedDataType(data_type)),
+        };
+        Ok(DLDataType::scalar(code, bits))
+    }
+}
+
+impl TryFrom<DLDataType> for MPSDataType {
+    type Error = DlpackError;
+
+    fn try_from(dtype: DLDataType) -> Result<Self> {
+        let unsupported = || DlpackError::UnsupportedDlDataType(dtype);
+        if dtype.lanes != 1 {
+            return Err(unsupported());
+        }
+        Ok(match (dtype.code, dtype.bits) {
+            (DLDataTypeCode::FLOAT, 16) => MPSDataType::Float16,
+            (DLDataTypeCode::FLOAT, 32) => MPSDataType::Float32,
+            (DLDataTypeCode::FLOAT, 64) => MPSDataType::Float64,
+            (DLDataTypeCode::BFLOAT, 16) => MPSDataType::BFloat16,
+            (DLDataTypeCode::INT, 8) => MPSDataType::Int8,
+            (DLDataTypeCode::INT, 16) => MPSDataType::Int16,
+            (DLDataTypeCode::INT, 32) => MPSDataType::Int32,
+            (DLDataTypeCode::INT, 64) => MPSDataType::Int64,
+            (DLDataTypeCode::UINT, 8) => MPSDataType::UInt8,
+            (DLDataTypeCode::UINT, 16) => MPSDataType::UInt16,
+            (DLDataTypeCode::UINT, 32) => MPSDataType::UInt32,
+            (DLDataTypeCode::UINT, 64) => MPSDataType::UInt64,
+            (DLDataTypeCode::BOOL, 8) => MPSDataType::Bool,
+            (DLDataTypeCode::COMPLEX, 32) => MPSDataType::Complex16,
+            (DLDataTypeCode::COMPLEX, 64) => MPSDataType::Complex32,
+            (DLDataTypeCode::COMPLEX, 128) => MPSDataType::Complex64,
+            _ => return Err(unsupported()),
+        })
+    }
+}
+
+/// A strided view of memory (`DLTensor`)
+#[repr(C)]
+#[derive(Debug)]
+pub struct DLTensor {
+    /// Start of the allocation; the first element is `byte_offset` bytes further
+    pub data: *mut c_void,
+    /// Device the data lives on
+    pub device: DLDevice,
+    /// Number of dimensions
+    pub ndim: i32,
+    /// Element type
+    pub dtype: DLDataType,
+    /// `ndim` sizes
+    pub shape: *mut i64,
+    /// `ndim` strides in elements, or null for a compact row-major layout
+    pub strides: *mut i64,
+    /// Offset of the first element from `data`, in bytes
+    pub byte_offset: u64,
+}
+
+/// A [`DLTensor`] with the means to release it (`DLManagedTensor`)
+#[repr(C)]
+#[derive(Debug)]
+pub struct DLManagedTensor {
+    /// The tensor
+    pub dl_tensor: DLTensor,
+    /// Producer state for the deleter
+    pub manager_ctx: *mut c_void,
+    /// Called by the consumer, once, when it no longer needs the tensor
+    pub deleter: Option<unsafe extern "C" fn(*mut DLManagedTensor)>,
+}
+
+/// Errors reported while exchanging DLPack tensors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DlpackError {
+    /// The data type has no DLPack counterpart
+    UnsupportedDataType(MPSDataType),
+    /// The DLPack data type has no `MPSDataType` counterpart
+    UnsupportedDlDataType(DLDataType),
+    /// The data isn't in host memory
+    UnsupportedDevice(DLDevice),
+    /// The tensor is null or its fields are inconsistent
+    InvalidTensor(String),
+    /// The elements aren't stored compactly in row-major order, so they can't be borrowed
+    NotCompact,
+    /// The tensor holds elements of a different type than requested
+    DataTypeMismatch {
+        /// Data type of the tensor
+        expected: MPSDataType,
+        /// Data type that was asked for
+        actual: MPSDataType,
+    },
+    /// Reading tensor data back failed
+    Graph(MPSGraphError),
+}
+
+impl fmt::Display for DlpackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DlpackError::UnsupportedDataType(data_type) => {
+                write!(f, "{:?} has no DLPack data type", data_type)
+            }
+            DlpackError::UnsupportedDlDataType(dtype) => write!(
+                f,
+                "DLPack data type (code {}, {} bits, {} lanes) has no MPSDataType counterpart",
+                dtype.code.0, dtype.bits, dtype.lanes
+            ),
+            DlpackError::UnsupportedDevice(device) => write!(
+                f,
+                "DLPack device type {} is not host memory",
+                device.device_type.0
+            ),
+            DlpackError::InvalidTensor(reason) => write!(f, "invalid DLPack tensor: {}", reason),
+            DlpackError::NotCompact => f.write_str("DLPack tensor is not compact and row-major"),
+            DlpackError::DataTypeMismatch { expected, actual } => {
+                write!(f, "tensor holds {:?} elements, not {:?}", expected, actual)
+            }
+            DlpackError::Graph(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for DlpackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DlpackError::Graph(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<MPSGraphError> for DlpackError {
+    fn from(error: MPSGraphError) -> Self {
+        DlpackError::Graph(error)
+    }
+}
+
+/// Result type for DLPack exchange
+pub type Result<T> = std::result::Result<T, DlpackError>;
+
+/// Everything an exported tensor points at, freed by [`delete_export`]
+///
+/// `managed` comes first so a pointer to it is a pointer to the whole export.
+#[repr(C)]
+struct Export {
+    managed: DLManagedTensor,
+    shape: Vec<i64>,
+    strides: Vec<i64>,
+    _storage: Box<dyn Any>,
+}
+
+unsafe extern "C" fn delete_export(tensor: *mut DLManagedTensor) {
+    if !tensor.is_null() {
+        drop(Box::from_raw(tensor as *mut Export));
+    }
+}
+
+/// Wraps CPU memory owned by `storage` as a DLPack tensor
+fn export(
+    data: *mut c_void,
+    storage: Box<dyn Any>,
+    shape: &[usize],
+    strides: &[usize],
+    dtype: DLDataType,
+) -> ManagedTensor {
+    let mut export = Box::new(Export {
+        managed: DLManagedTensor {
+            dl_tensor: DLTensor {
+                data,
+                device: DLDevice::CPU,
+                ndim: shape.len() as i32,
+                dtype,
+                shape: std::ptr::null_mut(),
+                strides: std::ptr::null_mut(),
+                byte_offset: 0,
+            },
+            manager_ctx: std::ptr::null_mut(),
+            deleter: Some(delete_export),
+        },
+        shape: shape.iter().map(|&size| size as i64).collect(),
+        strides: strides.iter().map(|&stride| stride as i64).collect(),
+        _storage: storage,
+    });
+    // The vectors' heap buffers don't move when the box does
+    export.managed.dl_tensor.shape = export.shape.as_mut_ptr();
+    export.managed.dl_tensor.strides = export.strides.as_mut_ptr();
+    let export = Box::into_raw(export);
+    unsafe {
+        (*export).managed.manager_ctx = export as *mut c_void;
+        ManagedTensor(NonNull::new_unchecked(export as *mut DLManagedTensor))
+    }
+}
+
+/// An owned [`DLManagedTensor`], deleted when dropped
+pub struct ManagedTensor(NonNull<DLManagedTensor>);
+
+impl ManagedTensor {
+    /// Exports a host tensor without copying, keeping its strides
+    pub fn from_host<T: HostElement>(tensor: HostTensor<T>) -> Self {
+        let dtype = DLDataType::try_from(T::DATA_TYPE)
+            .expect("every host element type has a DLPack data type");
+        let (shape, strides, mut data) = tensor.into_parts();
+        let pointer = data.as_mut_ptr() as *mut c_void;
+        export(pointer, Box::new(data), &shape, &strides, dtype)
+    }
+
+    /// Exports an untyped host tensor without copying
+    pub fn from_dyn_host(tensor: DynHostTensor) -> Result<Self> {
+        let dtype = DLDataType::try_from(tensor.data_type())?;
+        let shape = tensor.shape().to_vec();
+        let mut bytes = tensor.into_bytes();
+        let pointer = bytes.as_mut_ptr() as *mut c_void;
+        let strides = contiguous_strides(&shape);
+        Ok(export(pointer, Box::new(bytes), &shape, &strides, dtype))
+    }
+
+    /// Takes ownership of a tensor from another DLPack producer
+    ///
+    /// A tensor that fails validation is still owned, and deleted, by the time the error is
+    /// returned.
+    ///
+    /// # Safety
+    ///
+    /// `tensor` must point to a valid `DLManagedTensor` that nobody else will delete, and its
+    /// data must stay valid until the deleter runs.
+    pub unsafe fn from_raw(tensor: *mut DLManagedTensor) -> Result<Self> {
+        let tensor = NonNull::new(tensor)
+            .map(ManagedTensor)
+            .ok_or_else(|| DlpackError::InvalidTensor(String::from("the tensor is null")))?;
+        let dl_tensor = tensor.dl_tensor();
+        if dl_tensor.ndim < 0 {
+            return Err(DlpackError::InvalidTensor(format!(
+                "ndim is {}",
+                dl_tensor.ndim
+            )));
+        }
+        if dl_tensor.ndim > 0 && dl_tensor.shape.is_null() {
+            return Err(DlpackError::InvalidTensor(String::from(
+                "the shape is null",
+            )));
+        }
+        if let Some(size) = tensor.shape().iter().find(|&&size| size < 0) {
+            return Err(DlpackError::InvalidTensor(format!(
+                "the shape has a dimension of size {}",
+                size
+            )));
+        }
+        Ok(tensor)
+    }
+
+    /// Releases ownership, for handing the tensor to a consumer that will call its deleter
+    pub fn into_raw(self) -> *mut DLManagedTensor {
+        let tensor = self.0.as_ptr();
+        std::mem::forget(self);
+        tensor
+    }
+
+    /// Returns the underlying `DLTensor`
+    pub fn dl_tensor(&self) -> &DLTensor {
+        unsafe { &self.0.as_ref().dl_tensor }
+    }
+
+    /// Returns the device the data lives on
+    pub fn device(&self) -> DLDevice {
+        self.dl_tensor().device
+    }
+
+    /// Returns the element type
+    pub fn data_type(&self) -> Result<MPSDataType> {
+        MPSDataType::try_from(self.dl_tensor().dtype)
+    }
+
+    /// Returns the size of each dimension
+    pub fn shape(&self) -> &[i64] {
+        let tensor = self.dl_tensor();
+        if tensor.ndim == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(tensor.shape, tensor.ndim as usize) }
+    }
+
+    /// Returns the strides in elements, or `None` for a compact row-major layout
+    pub fn strides(&self) -> Option<&[i64]> {
+        let tensor = self.dl_tensor();
+        if tensor.strides.is_null() {
+            return None;
+        }
+        if tensor.ndim == 0 {
+            return Some(&[]);
+        }
+        Some(unsafe { std::slice::from_raw_parts(tensor.strides, tensor.ndim as usize) })
+    }
+
+    /// Returns the number of elements
+    pub fn element_count(&self) -> usize {
+        self.shape().iter().map(|&size| size as usize).product()
+    }
+
+    /// Returns whether the elements are stored compactly in row-major order
+    pub fn is_compact(&self) -> bool {
+        let Some(strides) = self.strides() else {
+            return true;
+        };
+        let mut expected = 1;
+        for (&size, &stride) in self.shape().iter().zip(strides).rev() {
+            if size > 1 && stride != expected {
+                return false;
+            }
+            expected *= size;
+        }
+        true
+    }
+
+    /// Returns the address of the first element, checking that the data is in host memory
+    fn first_element(&self) -> Result<*const u8> {
+        let tensor = self.dl_tensor();
+        if tensor.device.device_type != DLDeviceType::CPU {
+            return Err(DlpackError::UnsupportedDevice(tensor.device));
+        }
+        if tensor.data.is_null() && self.element_count() > 0 {
+            return Err(DlpackError::InvalidTensor(String::from("the data is null")));
+        }
+        Ok((tensor.data as *const u8).wrapping_add(tensor.byte_offset as usize))
+    }
+
+    /// Borrows the elements of a compact CPU tensor without copying
+    pub fn as_bytes(&self) -> Result<&[u8]> {
+        let size = self.data_type()?.size_in_bytes();
+        let first = self.first_element()?;
+        if !self.is_compact() {
+            return Err(DlpackError::NotCompact);
+        }
+        let len = self.element_count() * size;
+        if len == 0 {
+            return Ok(&[]);
+        }
+        Ok(unsafe { std::slice::from_raw_parts(first, len) })
+    }
+
+    /// Copies the elements into an untyped host tensor in C order
+    pub fn to_dyn_host(&self) -> Result<DynHostTensor> {
+        let data_type = self.data_type()?;
+        let shape: Vec<usize> = self.shape().iter().map(|&size| size as usize).collect();
+        let bytes = match self.as_bytes() {
+            Ok(bytes) => bytes.to_vec(),
+            Err(DlpackError::NotCompact) => self.gather(data_type.size_in_bytes())?,
+            Err(error) => return Err(error),
+        };
+        Ok(DynHostTensor::new(&shape, data_type, bytes)?)
+    }
+
+    /// Copies the elements into a host tensor of `T`, which must match the data type
+    pub fn to_host<T: HostElement>(&self) -> Result<HostTensor<T>> {
+        let data_type = self.data_type()?;
+        if T::DATA_TYPE != data_type {
+            return Err(DlpackError::DataTypeMismatch {
+                expected: data_type,
+                actual: T::DATA_TYPE,
+            });
+        }
+        Ok(self.to_dyn_host()?.to_typed()?)
+    }
+
+    /// Copies the elements of a strided tensor in C order
+    fn gather(&self, size: usize) -> Result<Vec<u8>> {
+        let first = self.first_element()?;
+        let shape = self.shape();
+        let strides = self.strides().unwrap_or_default();
+        let count = self.element_count();
+        let mut bytes = Vec::with_capacity(count * size);
+        let mut index = vec![0i64; shape.len()];
+        for _ in 0..count {
+            let offset: i64 = index.iter().zip(strides).map(|(&i, &s)| i * s).sum();
+            let element = first.wrapping_offset(offset as isize * size as isize);
+            bytes.extend_from_slice(unsafe { std::slice::from_raw_parts(element, size) });
+            for axis in (0..index.len()).rev() {
+                index[axis] += 1;
+                if index[axis] < shape[axis] {
+                    break;
+                }
+                index[axis] = 0;
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+impl Drop for ManagedTensor {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(deleter) = self.0.as_ref().deleter {
+                deleter(self.0.as_ptr());
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ManagedTensor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ManagedTensor")
+            .field("device", &self.device())
+            .field("dtype", &self.dl_tensor().dtype)
+            .field("shape", &self.shape())
+            .field("strides", &self.strides())
+            .finish()
+    }
+}
+
+/// Releases an Objective-C object when an export is deleted
+#[cfg(target_vendor = "apple")]
+struct Released(*mut AnyObject);
+
+#[cfg(target_vendor = "apple")]
+impl Drop for Released {
+    fn drop(&mut self) {
+        unsafe { objc2::ffi::objc_release(self.0 as *mut _) };
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl ManagedTensor {
+    /// Copies the elements into new tensor data
+    pub fn to_tensor_data(&self) -> Result<MPSGraphTensorData> {
+        Ok(self.to_dyn_host()?.to_tensor_data())
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl MPSGraphTensorData {
+    /// Exports the data as a CPU DLPack tensor
+    ///
+    /// When the backing buffer is CPU-visible, as with shared storage on Apple silicon, and
+    /// provably holds just the elements in C order, the DLPack tensor points straight into it
+    /// and keeps it retained until the deleter runs. MPSNDArray doesn't report its offset or
+    /// strides, so that proof is an array that isn't a view of another and a buffer exactly
+    /// as long as the elements. Anything else is copied with
+    /// [`read_bytes`](MPSGraphTensorData::read_bytes).
+    pub fn to_dlpack(&self) -> Result<ManagedTensor> {
+        let data_type = self.try_data_type()?;
+        let dtype = DLDataType::try_from(data_type)?;
+        let shape = self.shape().dimensions();
+        let needed = shape
+            .iter()
+            .try_fold(1usize, |count, &size| count.checked_mul(size))
+            .and_then(|count| data_type.packed_size_in_bytes(count));
+        unsafe {
+            self.synchronize();
+            let ndarray = self.mpsndarray();
+            if !ndarray.is_null() {
+                let parent: *mut AnyObject = msg_send![ndarray, parent];
+                let buffer: *mut AnyObject = msg_send![ndarray, mtlBuffer];
+                if parent.is_null() && !buffer.is_null() {
+                    let contents: *mut c_void = msg_send![buffer, contents];
+                    let length: usize = msg_send![buffer, length];
+                    if !contents.is_null() && Some(length) == needed {
+                        let strides = contiguous_strides(&shape);
+                        let owner = Box::new(Released(ndarray));
+                        return Ok(export(contents, owner, &shape, &strides, dtype));
+                    }
+                }
+                objc2::ffi::objc_release(ndarray as *mut _);
+            }
+        }
+        ManagedTensor::from_dyn_host(DynHostTensor::from_tensor_data(self)?)
+    }
+}
@@ -0,0 +1,267 @@
+//! Graphviz DOT export.
+//!
+//! [`Graph::to_dot`](crate::ir::Graph::to_dot) and, on Apple targets, [`MPSGraph::to_dot`]
+//! walk back from a set of target tensors through the operations producing them and render
+//! the reachable subgraph: one box per operation, data edges labelled with the shape and data
+//! type of the tensor they carry, and dashed edges for control dependencies. Render the
+//! result with `dot -Tsvg graph.dot -o graph.svg`.
+
+use std::fmt::Write;
+
+#[cfg(target_vendor = "apple")]
+use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
+use crate::operation::MPSGraphOperation;
+#[cfg(target_vendor = "apple")]
+use crate::tensor::MPSGraphTensor;
+#[cfg(target_vendor = "apple")]
+use std::collections::HashMap;
+
+/// Options for DOT export
+#[derive(Debug, Clone, Default)]
+pub struct DotOptions {
+    cluster_separator: Option<String>,
+}
+
+impl DotOptions {
+    /// Renders every operation at the top level
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Groups operations into nested clusters by the prefixes of their names
+    ///
+    /// With `"/"`, an operation named `encoder/layer0/matmul` is drawn inside a
+    /// `layer0` cluster inside an `encoder` cluster.
+    pub fn with_clusters(mut self, separator: &str) -> Self {
+        self.cluster_separator = (!separator.is_empty()).then(|| separator.to_string());
+        self
+    }
+}
+
+/// A node of a [`DotGraph`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DotNode {
+    /// Text drawn in the node
+    pub(crate) label: String,
+    /// Name used for clustering; output nodes have none and stay at the top level
+    pub(crate) name: Option<String>,
+    /// Targets are drawn as ellipses, operations as boxes
+    pub(crate) is_output: bool,
+}
+
+/// An edge of a [`DotGraph`] between node indices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DotEdge {
+    pub(crate) from: usize,
+    pub(crate) to: usize,
+    /// Shape and data type of the tensor; `None` for control dependencies
+    pub(crate) label: Option<String>,
+}
+
+/// Backend-independent graph handed to [`DotGraph::render`]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DotGraph {
+    pub(crate) nodes: Vec<DotNode>,
+    pub(crate) edges: Vec<DotEdge>,
+}
+
+/// Operations and nested clusters sharing one name prefix
+#[derive(Default)]
+struct Cluster {
+    nodes: Vec<usize>,
+    children: Vec<(String, Cluster)>,
+}
+
+impl Cluster {
+    fn insert(&mut self, path: &[&str], node: usize) {
+        let Some((first, rest)) = path.split_first() else {
+            self.nodes.push(node);
+            return;
+        };
+        let index = match self.children.iter().position(|(name, _)| name == first) {
+            Some(index) => index,
+            None => {
+                self.children.push((first.to_string(), Cluster::default()));
+                self.children.len() - 1
+            }
+        };
+        self.children[index].1.insert(rest, node);
+    }
+}
+
+/// Quotes a string as a DOT identifier
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl DotGraph {
+    /// Adds a node and returns its index
+    pub(crate) fn add_node(
+        &mut self,
+        label: String,
+        name: Option<String>,
+        is_output: bool,
+    ) -> usize {
+        self.nodes.push(DotNode {
+            label,
+            name,
+            is_output,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Renders the graph in the DOT language
+    pub(crate) fn render(&self, options: &DotOptions) -> String {
+        let mut root = Cluster::default();
+        for (index, node) in self.nodes.iter().enumerate() {
+            let path: Vec<&str> = match (&options.cluster_separator, &node.name) {
+                (Some(separator), Some(name)) if !node.is_output => {
+                    let mut parts: Vec<&str> = name.split(separator.as_str()).collect();
+                    parts.pop();
+                    parts
+                }
+                _ => Vec::new(),
+            };
+            root.insert(&path, index);
+        }
+
+        let mut out = String::from("digraph {\n    node [shape=box];\n");
+        let mut clusters = 0;
+        self.render_cluster(&root, 1, &mut clusters, &mut out);
+        for edge in &self.edges {
+            let attributes = match &edge.label {
+                Some(label) => format!("label={}", quote(label)),
+                None => "style=dashed".to_string(),
+            };
+            writeln!(out, "    n{} -> n{} [{}];", edge.from, edge.to, attributes).unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn render_cluster(
+        &self,
+        cluster: &Cluster,
+        depth: usize,
+        clusters: &mut usize,
+        out: &mut String,
+    ) {
+        let indent = "    ".repeat(depth);
+        for &index in &cluster.nodes {
+            let node = &self.nodes[index];
+            let shape = if node.is_output {
+                ", shape=ellipse"
+            } else {
+                ""
+            };
+            writeln!(
+                out,
+                "{}n{} [label={}{}];",
+                indent,
+                index,
+                quote(&node.label),
+                shape
+            )
+            .unwrap();
+        }
+        for (name, child) in &cluster.children {
+            writeln!(out, "{}subgraph cluster_{} {{", indent, clusters).unwrap();
+            writeln!(out, "{}    label={};", indent, quote(name)).unwrap();
+            *clusters += 1;
+            self.render_cluster(child, depth + 1, clusters, out);
+            writeln!(out, "{}}}", indent).unwrap();
+        }
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl MPSGraph {
+    /// Renders the operations `targets` depend on as a Graphviz DOT graph
+    ///
+    /// Operations are labelled with their names, data edges with the dimensions and data
+    /// type of the tensor they carry, and control dependencies are drawn dashed. Each
+    /// target is drawn as an ellipse fed by its producer.
+    pub fn to_dot(&self, targets: &[MPSGraphTensor], options: &DotOptions) -> String {
+        fn edge_label(tensor: &MPSGraphTensor) -> String {
+            match tensor.try_data_type() {
+                Ok(data_type) => format!("{:?} {:?}", tensor.dimensions(), data_type),
+                Err(error) => format!("{:?} ({})", tensor.dimensions(), error),
+            }
+        }
+
+        // Post-order walk so producers are emitted before their consumers
+        fn visit(
+            op: MPSGraphOperation,
+            dot: &mut DotGraph,
+            nodes: &mut HashMap<usize, usize>,
+            retained: &mut Vec<MPSGraphOperation>,
+        ) -> usize {
+            let key = op.0 as usize;
+            if let Some(&node) = nodes.get(&key) {
+                return node;
+            }
+            let inputs: Vec<(usize, String)> = op
+                .input_tensors()
+                .iter()
+                .map(|tensor| {
+                    (
+                        visit(tensor.operation(), dot, nodes, retained),
+                        edge_label(tensor),
+                    )
+                })
+                .collect();
+            let dependencies: Vec<usize> = op
+                .control_dependencies()
+                .into_iter()
+                .map(|dependency| visit(dependency, dot, nodes, retained))
+                .collect();
+
+            let name = op.name();
+            let node = dot.add_node(name.clone(), Some(name), false);
+            nodes.insert(key, node);
+            for (from, label) in inputs {
+                dot.edges.push(DotEdge {
+                    from,
+                    to: node,
+                    label: Some(label),
+                });
+            }
+            for from in dependencies {
+                dot.edges.push(DotEdge {
+                    from,
+                    to: node,
+                    label: None,
+                });
+            }
+            retained.push(op);
+            node
+        }
+
+        let mut dot = DotGraph::default();
+        let mut nodes = HashMap::new();
+        // Keeps the visited operations retained so their addresses stay unique
+        let mut retained = Vec::new();
+        for tensor in targets {
+            let producer = visit(tensor.operation(), &mut dot, &mut nodes, &mut retained);
+            let output = dot.add_node(tensor.name(), None, true);
+            dot.edges.push(DotEdge {
+                from: producer,
+                to: output,
+                label: Some(edge_label(tensor)),
+            });
+        }
+        dot.render(options)
+    }
+}
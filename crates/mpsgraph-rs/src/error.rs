@@ -0,0 +1,322 @@
+use crate::core::MPSDataType;
+use crate::dims::ShapeError;
+#[cfg(target_vendor = "apple")]
+use objc2::runtime::AnyObject;
+#[cfg(target_vendor = "apple")]
+use objc2_foundation::NSError;
+use std::fmt;
+
+/// Errors reported by the fallible (`try_*`) graph, executable and command buffer APIs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MPSGraphError {
+    /// A feed is null, has no matching placeholder or has the wrong data type
+    InvalidFeed {
+        /// Name of the fed tensor
+        tensor: String,
+        /// Why the feed was rejected
+        reason: String,
+    },
+    /// The shape of the fed data does not match the shape of the placeholder
+    ShapeMismatch {
+        /// Name of the fed tensor
+        tensor: String,
+        /// Placeholder shape, with `-1` for dynamic dimensions
+        expected: Vec<i64>,
+        /// Shape of the supplied tensor data
+        actual: Vec<usize>,
+    },
+    /// Pre-flight validation found problems with the feeds, listed in full
+    InvalidFeeds(Vec<FeedProblem>),
+    /// MPSGraph did not produce an executable
+    ///
+    /// MPSGraph's compile and specialize calls don't report an `NSError`, so the reason is
+    /// this crate's description of the failure.
+    CompilationFailed(String),
+    /// Running or encoding the graph did not produce the requested results
+    ExecutionFailed(String),
+    /// A serialized MPSGraph package or CoreML package could not be loaded
+    PackageLoadFailed {
+        /// URL of the package
+        url: String,
+        /// Why loading failed; MPSGraph reports no `NSError` for packages it rejects, so that
+        /// case has a fixed reason
+        reason: String,
+    },
+    /// The running OS does not implement the requested MPSGraph API
+    UnsupportedOsApi {
+        /// The Objective-C selector that is missing
+        api: &'static str,
+        /// The first OS releases that provide it
+        available_since: &'static str,
+    },
+    /// MPSGraph reported a data type code this crate doesn't know
+    UnknownDataType(u32),
+    /// No tensor was created with the given name
+    UnknownTensorName(String),
+    /// More than one tensor answers to the given name, or a name was requested twice
+    DuplicateTensorName(String),
+    /// Host data holds elements of a different type than requested
+    DataTypeMismatch {
+        /// Data type of the stored elements
+        expected: MPSDataType,
+        /// Data type that was asked for
+        actual: MPSDataType,
+    },
+    /// A host buffer's length doesn't match its shape and data type
+    SizeMismatch {
+        /// Bytes needed by the shape and data type, or `usize::MAX` if that overflows
+        expected: usize,
+        /// Bytes supplied
+        actual: usize,
+    },
+    /// Strides don't give one step per axis of the shape
+    InvalidStrides {
+        /// Shape of the tensor
+        shape: Vec<usize>,
+        /// Strides supplied, in elements
+        strides: Vec<usize>,
+    },
+    /// A host-side reshape or permutation was rejected
+    Shape(ShapeError),
+    /// The command buffer finished with an error
+    CommandBuffer {
+        /// `NSError` domain
+        domain: String,
+        /// `NSError` code
+        code: i64,
+        /// `NSError` localized description
+        description: String,
+    },
+}
+
+/// One problem found while checking feeds against the tensors the targets depend on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeedProblem {
+    /// A placeholder the targets depend on has no feed
+    Missing {
+        /// Name of the placeholder
+        tensor: String,
+    },
+    /// The fed tensor or its data is null
+    Null {
+        /// Name of the fed tensor
+        tensor: String,
+    },
+    /// The fed data type differs from the tensor's
+    DataType {
+        /// Name of the fed tensor
+        tensor: String,
+        /// Data type of the tensor
+        expected: MPSDataType,
+        /// Data type of the supplied tensor data
+        actual: MPSDataType,
+    },
+    /// The fed shape doesn't fit the tensor's shape
+    Shape {
+        /// Name of the fed tensor
+        tensor: String,
+        /// Shape of the tensor, with `-1` for dynamic dimensions
+        expected: Vec<i64>,
+        /// Shape of the supplied tensor data
+        actual: Vec<usize>,
+    },
+    /// The targets don't depend on the fed tensor
+    Unused {
+        /// Name of the fed tensor
+        tensor: String,
+    },
+}
+
+impl FeedProblem {
+    /// Compares a feed with the tensor it feeds
+    ///
+    /// `expected_shape` is `None` for unranked tensors, which accept any shape, and `-1`
+    /// marks a dynamic dimension. Returns the data type and shape problems, if any.
+    pub fn check(
+        tensor: &str,
+        expected_shape: Option<&[i64]>,
+        expected_type: MPSDataType,
+        actual_shape: &[usize],
+        actual_type: MPSDataType,
+    ) -> Vec<FeedProblem> {
+        let mut problems = Vec::new();
+        if expected_type != actual_type {
+            problems.push(FeedProblem::DataType {
+                tensor: tensor.to_string(),
+                expected: expected_type,
+                actual: actual_type,
+            });
+        }
+        if let Some(expected) = expected_shape {
+            let fits = expected.len() == actual_shape.len()
+                && expected
+                    .iter()
+                    .zip(actual_shape)
+                    .all(|(&e, &a)| e < 0 || e as usize == a);
+            if !fits {
+                problems.push(FeedProblem::Shape {
+                    tensor: tensor.to_string(),
+                    expected: expected.to_vec(),
+                    actual: actual_shape.to_vec(),
+                });
+            }
+        }
+        problems
+    }
+
+    /// Name of the tensor the problem is about
+    pub fn tensor(&self) -> &str {
+        match self {
+            FeedProblem::Missing { tensor }
+            | FeedProblem::Null { tensor }
+            | FeedProblem::DataType { tensor, .. }
+            | FeedProblem::Shape { tensor, .. }
+            | FeedProblem::Unused { tensor } => tensor,
+        }
+    }
+}
+
+impl fmt::Display for FeedProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedProblem::Missing { tensor } => write!(f, "placeholder '{}' is not fed", tensor),
+            FeedProblem::Null { tensor } => {
+                write!(f, "feed for '{}' has a null tensor or data", tensor)
+            }
+            FeedProblem::DataType {
+                tensor,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "'{}' expects {:?} but the feed has {:?}",
+                tensor, expected, actual
+            ),
+            FeedProblem::Shape {
+                tensor,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "'{}' expects shape {:?} but the feed has {:?}",
+                tensor, expected, actual
+            ),
+            FeedProblem::Unused { tensor } => {
+                write!(f, "'{}' is fed but the targets don't use it", tensor)
+            }
+        }
+    }
+}
+
+/// Result type returned by the fallible MPSGraph APIs
+pub type Result<T> = std::result::Result<T, MPSGraphError>;
+
+#[cfg(target_vendor = "apple")]
+impl MPSGraphError {
+    /// Builds a `CommandBuffer` error from an `NSError` pointer
+    ///
+    /// # Safety
+    ///
+    /// `error` must be a valid, non-null `NSError` pointer.
+    pub(crate) unsafe fn from_ns_error(error: *mut AnyObject) -> Self {
+        let error = &*(error as *const NSError);
+        MPSGraphError::CommandBuffer {
+            domain: error.domain().to_string(),
+            code: error.code() as i64,
+            description: error.localizedDescription().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for MPSGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MPSGraphError::InvalidFeed { tensor, reason } => {
+                write!(f, "invalid feed for tensor '{}': {}", tensor, reason)
+            }
+            MPSGraphError::ShapeMismatch {
+                tensor,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "shape mismatch for tensor '{}': placeholder expects {:?}, feed has {:?}",
+                tensor, expected, actual
+            ),
+            MPSGraphError::InvalidFeeds(problems) => {
+                write!(f, "{} invalid feed(s): ", problems.len())?;
+                for (i, problem) in problems.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("; ")?;
+                    }
+                    write!(f, "{}", problem)?;
+                }
+                Ok(())
+            }
+            MPSGraphError::CompilationFailed(reason) => {
+                write!(f, "graph compilation failed: {}", reason)
+            }
+            MPSGraphError::ExecutionFailed(reason) => {
+                write!(f, "graph execution failed: {}", reason)
+            }
+            MPSGraphError::PackageLoadFailed { url, reason } => {
+                write!(f, "failed to load package at '{}': {}", url, reason)
+            }
+            MPSGraphError::UnsupportedOsApi {
+                api,
+                available_since,
+            } => write!(
+                f,
+                "{} is not available on this OS (requires {})",
+                api, available_since
+            ),
+            MPSGraphError::UnknownDataType(code) => {
+                write!(f, "unknown MPSDataType code {:#x}", code)
+            }
+            MPSGraphError::UnknownTensorName(name) => write!(f, "no tensor is named '{}'", name),
+            MPSGraphError::DuplicateTensorName(name) => {
+                write!(f, "the name '{}' refers to more than one tensor", name)
+            }
+            MPSGraphError::DataTypeMismatch { expected, actual } => {
+                write!(f, "data holds {:?} elements, not {:?}", expected, actual)
+            }
+            MPSGraphError::SizeMismatch { expected, actual } => write!(
+                f,
+                "buffer holds {} bytes but the shape and data type need {}",
+                actual, expected
+            ),
+            MPSGraphError::InvalidStrides { shape, strides } => write!(
+                f,
+                "strides {:?} don't match the {} axes of shape {:?}",
+                strides,
+                shape.len(),
+                shape
+            ),
+            MPSGraphError::Shape(error) => write!(f, "{}", error),
+            MPSGraphError::CommandBuffer {
+                domain,
+                code,
+                description,
+            } => write!(
+                f,
+                "command buffer failed ({} code {}): {}",
+                domain, code, description
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MPSGraphError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MPSGraphError::Shape(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ShapeError> for MPSGraphError {
+    fn from(error: ShapeError) -> Self {
+        MPSGraphError::Shape(error)
+    }
+}
@@ -2,6 +2,8 @@ use crate::command_buffer::MPSCommandBuffer;
 use crate::core::{
     AsRawObject, MPSDataType, MPSGraphOptimization, MPSGraphOptimizationProfile, NSString,
 };
+use crate::error::{FeedProblem, MPSGraphError, Result};
+use crate::graph::{check_feed, checked_results, feed_problems_result};
 use crate::tensor::MPSGraphTensor;
 use crate::tensor_data::MPSGraphTensorData;
 use metal::foreign_types::ForeignType;
@@ -703,11 +705,14 @@ impl MPSGraphExecutable {
                 objc2::ffi::objc_retain(key as *mut _);
 
                 // Create Tensor wrapper
-                let tensor = MPSGraphTensor(key);
+                let tensor = MPSGraphTensor::from_retained(key);
 
                 // Extract data type from NSNumber
                 let data_type_value: u64 = msg_send![value, unsignedIntegerValue];
-                let data_type = MPSDataType::from_u32(data_type_value as u32);
+                let Ok(data_type) = MPSDataType::try_from(data_type_value as u32) else {
+                    objc2::ffi::objc_release(output_types_dict as *mut _);
+                    return None;
+                };
 
                 // Add to the result HashMap
                 result.insert(tensor, data_type);
@@ -720,6 +725,53 @@ impl MPSGraphExecutable {
         }
     }
 
+    /// Returns the tensors the executable is fed, in the order of its inputs arrays
+    pub fn feed_tensors(&self) -> Vec<MPSGraphTensor> {
+        unsafe {
+            let tensors: *mut AnyObject = msg_send![self.0, feedTensors];
+            tensors_from_array(tensors)
+        }
+    }
+
+    /// Returns the tensors the executable computes, in the order of its results arrays
+    pub fn target_tensors(&self) -> Vec<MPSGraphTensor> {
+        unsafe {
+            let tensors: *mut AnyObject = msg_send![self.0, targetTensors];
+            tensors_from_array(tensors)
+        }
+    }
+
+    /// Checks `feeds` against the executable's [`feed_tensors`](Self::feed_tensors)
+    ///
+    /// Like [`MPSGraph::validate_feeds`](crate::graph::MPSGraph::validate_feeds), every
+    /// problem is reported at once as [`MPSGraphError::InvalidFeeds`]: inputs without a feed,
+    /// feeds whose data type or shape doesn't fit the input, and feeds of tensors the
+    /// executable doesn't take.
+    pub fn validate_feeds(
+        &self,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+    ) -> Result<()> {
+        let inputs = self.feed_tensors();
+        let problems = inputs
+            .iter()
+            .filter(|tensor| !feeds.contains_key(*tensor))
+            .map(|tensor| FeedProblem::Missing {
+                tensor: tensor.name(),
+            })
+            .collect();
+        let mut feed_problems = Vec::new();
+        for (tensor, data) in feeds {
+            if data.0.is_null() || tensor.0.is_null() || inputs.contains(tensor) {
+                feed_problems.extend(check_feed(tensor, data)?);
+            } else {
+                feed_problems.push(FeedProblem::Unused {
+                    tensor: tensor.name(),
+                });
+            }
+        }
+        feed_problems_result(problems, feed_problems)
+    }
+
     /// Serializes the executable to a file URL
     ///
     /// - Parameters:
@@ -754,6 +806,306 @@ impl MPSGraphExecutable {
     }
 }
 
+// Result-returning variants of the load, run and encode entry points
+impl MPSGraphExecutable {
+    /// Loads an executable from a serialized package, reporting why loading failed
+    ///
+    /// `initWithMPSGraphPackageAtURL:compilationDescriptor:` has no `NSError` out-parameter,
+    /// so only a bad URL or a missing file gets a specific reason; a package MPSGraph rejects
+    /// is reported with a fixed one.
+    ///
+    /// - Parameters:
+    ///   - url_string: The URL string where the package is stored (file:// URL)
+    ///   - compilation_descriptor: Optional compilation descriptor for specialization
+    pub fn try_from_serialized_package(
+        url_string: &str,
+        compilation_descriptor: Option<&MPSGraphCompilationDescriptor>,
+    ) -> Result<Self> {
+        check_package_url(url_string)?;
+        Self::from_serialized_package(url_string, compilation_descriptor).ok_or_else(|| {
+            MPSGraphError::PackageLoadFailed {
+                url: url_string.to_string(),
+                reason: String::from(
+                    "MPSGraph could not read the package (no NSError is available)",
+                ),
+            }
+        })
+    }
+
+    /// Loads an executable from a CoreML model package, reporting why loading failed
+    ///
+    /// Returns [`MPSGraphError::UnsupportedOsApi`] before iOS 18/macOS 15. As with
+    /// [`try_from_serialized_package`](Self::try_from_serialized_package), MPSGraph gives no
+    /// `NSError` when conversion fails, so that case has a fixed reason.
+    pub fn try_from_coreml_package(
+        url_string: &str,
+        compilation_descriptor: Option<&MPSGraphCompilationDescriptor>,
+    ) -> Result<Self> {
+        let supported = objc2::runtime::AnyClass::get(c"MPSGraphExecutable")
+            .and_then(|cls| {
+                cls.instance_method(sel!(initWithCoreMLPackageAtURL:compilationDescriptor:))
+            })
+            .is_some();
+        if !supported {
+            return Err(MPSGraphError::UnsupportedOsApi {
+                api: "-[MPSGraphExecutable initWithCoreMLPackageAtURL:compilationDescriptor:]",
+                available_since: "macOS 15.0 / iOS 18.0",
+            });
+        }
+
+        check_package_url(url_string)?;
+        Self::from_coreml_package(url_string, compilation_descriptor).ok_or_else(|| {
+            MPSGraphError::PackageLoadFailed {
+                url: url_string.to_string(),
+                reason: String::from(
+                    "MPSGraph could not convert the CoreML package (no NSError is available)",
+                ),
+            }
+        })
+    }
+
+    /// Executes the graph, returning an error instead of an incomplete result dictionary
+    pub fn try_run_with_feeds(
+        &self,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        output_tensors: &[MPSGraphTensor],
+    ) -> Result<MPSGraphExecutionResult> {
+        self.validate_feeds(feeds)?;
+        checked_results(self.run_with_feeds(feeds, output_tensors), output_tensors)
+    }
+
+    /// Executes the graph with an execution descriptor
+    pub fn try_run_with_feeds_and_descriptor(
+        &self,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        output_tensors: &[MPSGraphTensor],
+        execution_descriptor: &MPSGraphExecutionDescriptor,
+    ) -> Result<MPSGraphExecutionResult> {
+        self.validate_feeds(feeds)?;
+        checked_results(
+            self.run_with_feeds_and_descriptor(feeds, output_tensors, execution_descriptor),
+            output_tensors,
+        )
+    }
+
+    /// Executes the graph asynchronously on a command queue
+    pub fn try_run_async_with_command_queue<F>(
+        &self,
+        command_queue: &metal::CommandQueue,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        output_tensors: &[MPSGraphTensor],
+        execution_descriptor: &MPSGraphExecutionDescriptor,
+        completion_handler: Option<F>,
+    ) -> Result<MPSGraphExecutionResult>
+    where
+        F: FnOnce(MPSGraphExecutionResult) + 'static,
+    {
+        self.validate_feeds(feeds)?;
+        checked_results(
+            self.run_async_with_command_queue(
+                command_queue,
+                feeds,
+                output_tensors,
+                execution_descriptor,
+                completion_handler,
+            ),
+            output_tensors,
+        )
+    }
+
+    /// Executes the executable with array-based inputs and outputs
+    ///
+    /// Mismatched input arrays are reported as [`MPSGraphError::InvalidFeed`] and inputs that
+    /// don't fit as [`MPSGraphError::InvalidFeeds`] instead of panicking.
+    pub fn try_run_with_inputs_outputs(
+        &self,
+        input_tensors: &[MPSGraphTensor],
+        input_values: &[MPSGraphTensorData],
+        output_tensors: &[MPSGraphTensor],
+        execution_descriptor: Option<&MPSGraphExecutableExecutionDescriptor>,
+    ) -> Result<Vec<MPSGraphTensorData>> {
+        if input_tensors.len() != input_values.len() {
+            return Err(MPSGraphError::InvalidFeed {
+                tensor: String::from("<inputs>"),
+                reason: format!(
+                    "{} input tensors but {} input values",
+                    input_tensors.len(),
+                    input_values.len()
+                ),
+            });
+        }
+        let feeds: HashMap<MPSGraphTensor, MPSGraphTensorData> = input_tensors
+            .iter()
+            .cloned()
+            .zip(input_values.iter().cloned())
+            .collect();
+        self.validate_feeds(&feeds)?;
+
+        let results = self.run_with_inputs_outputs(
+            input_tensors,
+            input_values,
+            output_tensors,
+            execution_descriptor,
+        );
+        checked_result_count(results, output_tensors.len())
+    }
+
+    /// Runs the executable on a command queue with explicit input and output operations
+    pub fn try_run_with_operations_on_command_queue(
+        &self,
+        command_queue: &metal::CommandQueue,
+        input_operations: &[crate::operation::MPSGraphOperation],
+        input_data: &[MPSGraphTensorData],
+        output_operations: &[crate::operation::MPSGraphOperation],
+        execution_descriptor: Option<&MPSGraphExecutableExecutionDescriptor>,
+    ) -> Result<Vec<MPSGraphTensorData>> {
+        if input_operations.len() != input_data.len() {
+            return Err(MPSGraphError::InvalidFeed {
+                tensor: String::from("<inputs>"),
+                reason: format!(
+                    "{} input operations but {} input values",
+                    input_operations.len(),
+                    input_data.len()
+                ),
+            });
+        }
+        if let Some(index) = input_data.iter().position(|d| d.0.is_null()) {
+            return Err(MPSGraphError::InvalidFeed {
+                tensor: format!("<input {}>", index),
+                reason: String::from("the tensor data is null"),
+            });
+        }
+
+        let results = self.run_with_operations_on_command_queue(
+            command_queue,
+            input_operations,
+            input_data,
+            output_operations,
+            execution_descriptor,
+        );
+        checked_result_count(results, output_operations.len())
+    }
+
+    /// Encodes the executable to a Metal command buffer
+    ///
+    /// Errors raised while the command buffer executes are reported by
+    /// [`MPSCommandBuffer::try_wait_until_completed`].
+    pub fn try_encode_to_metal_command_buffer(
+        &self,
+        command_buffer: &metal::CommandBuffer,
+        inputs: &[MPSGraphTensorData],
+        results: Option<&[MPSGraphTensorData]>,
+        execution_descriptor: Option<&MPSGraphExecutableExecutionDescriptor>,
+    ) -> Result<Vec<MPSGraphTensorData>> {
+        let mps_command_buffer = MPSCommandBuffer::from_command_buffer(command_buffer);
+        self.try_encode_to_command_buffer(
+            &mps_command_buffer,
+            inputs,
+            results,
+            execution_descriptor,
+        )
+    }
+
+    /// Encodes the executable to an MPSCommandBuffer
+    ///
+    /// Errors raised while the command buffer executes are reported by
+    /// [`MPSCommandBuffer::try_wait_until_completed`].
+    pub fn try_encode_to_command_buffer(
+        &self,
+        command_buffer: &MPSCommandBuffer,
+        inputs: &[MPSGraphTensorData],
+        results: Option<&[MPSGraphTensorData]>,
+        execution_descriptor: Option<&MPSGraphExecutableExecutionDescriptor>,
+    ) -> Result<Vec<MPSGraphTensorData>> {
+        if let Some(index) = inputs.iter().position(|d| d.0.is_null()) {
+            return Err(MPSGraphError::InvalidFeed {
+                tensor: format!("<input {}>", index),
+                reason: String::from("the tensor data is null"),
+            });
+        }
+
+        let encoded =
+            self.encode_to_command_buffer(command_buffer, inputs, results, execution_descriptor);
+        match results {
+            Some(results) => checked_result_count(encoded, results.len()),
+            None => Ok(encoded),
+        }
+    }
+
+    /// Specializes the executable, reporting a failed specialization as a compilation error
+    ///
+    /// `specializeWithDevice:inputTypes:compilationDescriptor:` has no `NSError`
+    /// out-parameter, so the error carries a fixed reason.
+    pub fn try_specialize_with_device(
+        &self,
+        device: &crate::device::MPSGraphDevice,
+        tensor_shapes: &HashMap<MPSGraphTensor, crate::shape::MPSShape>,
+        tensor_data_types: &HashMap<MPSGraphTensor, MPSDataType>,
+    ) -> Result<Self> {
+        self.specialize_with_device(device, tensor_shapes, tensor_data_types)
+            .ok_or_else(|| {
+                MPSGraphError::CompilationFailed(String::from(
+                    "MPSGraph could not specialize the executable (no NSError is available)",
+                ))
+            })
+    }
+}
+
+/// Checks that a package URL parses and, for file URLs, that the package exists
+fn check_package_url(url_string: &str) -> Result<()> {
+    unsafe {
+        let nsurl_class = objc2::runtime::AnyClass::get(c"NSURL").unwrap();
+        let url_str = NSString::from_str(url_string);
+        let nsurl: *mut AnyObject = msg_send![nsurl_class, URLWithString: &*url_str];
+        if nsurl.is_null() {
+            return Err(MPSGraphError::PackageLoadFailed {
+                url: url_string.to_string(),
+                reason: String::from("not a valid URL"),
+            });
+        }
+    }
+
+    if let Some(path) = url_string.strip_prefix("file://") {
+        if !std::path::Path::new(path).exists() {
+            return Err(MPSGraphError::PackageLoadFailed {
+                url: url_string.to_string(),
+                reason: String::from("no such file or directory"),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the results if there is one for every requested output
+fn checked_result_count(
+    results: Vec<MPSGraphTensorData>,
+    expected: usize,
+) -> Result<Vec<MPSGraphTensorData>> {
+    if results.len() != expected {
+        return Err(MPSGraphError::ExecutionFailed(format!(
+            "expected {} results but got {}",
+            expected,
+            results.len()
+        )));
+    }
+    Ok(results)
+}
+
+/// Wraps the tensors of a possibly nil `NSArray<MPSGraphTensor>`
+pub(crate) unsafe fn tensors_from_array(array: *mut AnyObject) -> Vec<MPSGraphTensor> {
+    if array.is_null() {
+        return Vec::new();
+    }
+    let count: usize = msg_send![array, count];
+    (0..count)
+        .map(|i| {
+            let tensor: *mut AnyObject = msg_send![array, objectAtIndex: i];
+            MPSGraphTensor::from_retained(objc2::ffi::objc_retain(tensor))
+        })
+        .collect()
+}
+
 /// Helper function to convert an NSDictionary to a Rust HashMap
 fn convert_dictionary_to_hash_map(
     dictionary: *mut AnyObject,
@@ -776,7 +1128,7 @@ fn convert_dictionary_to_hash_map(
             objc2::ffi::objc_retain(value as *mut _);
 
             // Create Rust wrappers
-            let tensor = MPSGraphTensor(key);
+            let tensor = MPSGraphTensor::from_retained(key);
             let tensor_data = MPSGraphTensorData(value);
 
             // Add to the result HashMap
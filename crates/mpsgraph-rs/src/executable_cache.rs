@@ -0,0 +1,898 @@
+//! Caching of compiled executables.
+//!
+//! Compiling a graph for a new set of feed shapes is expensive, so [`ExecutableCache`] keeps
+//! the executables it has compiled, keyed by a [`CacheKey`]: a [`Fingerprint`] of the graph
+//! and one of its [`FeedSignature`], the shapes and data types of the fed tensors.
+//! Fingerprints use FNV-1a, so they are stable across runs and can name files on disk.
+//!
+//! The cache keeps a bounded number of executables in memory and, optionally, serializes them
+//! as MPSGraph packages into a directory. That directory is described by a [`Manifest`], a
+//! small text file listing the cached keys in least-recently-used order together with a
+//! fingerprint of the [`CompilationOptions`] they were compiled with. Packages compiled with
+//! other options are discarded when the cache is opened, and the least recently used
+//! packages are evicted once the directory is full.
+//!
+//! Fingerprints, feed signatures and manifests are plain values available on every target;
+//! [`ExecutableCache`] itself needs Metal.
+//!
+//! [`ir::Graph::fingerprint`](crate::ir::Graph::fingerprint) covers the operations and the
+//! constant data of a recorded graph. MPSGraph doesn't expose the values of its constants, so
+//! an `MPSGraph` can't be fingerprinted reliably and the caller provides the key, see
+//! [`ExecutableCache::get_or_compile_with_key`].
+
+use crate::core::{MPSDataType, MPSGraphOptimization, MPSGraphOptimizationProfile};
+use crate::error::MPSGraphError;
+use crate::ir::Graph;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+#[cfg(target_vendor = "apple")]
+use crate::device::MPSGraphDevice;
+#[cfg(target_vendor = "apple")]
+use crate::executable::{
+    MPSGraphCompilationDescriptor, MPSGraphExecutable, MPSGraphExecutableSerializationDescriptor,
+};
+#[cfg(target_vendor = "apple")]
+use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
+use crate::operation::MPSGraphOperation;
+#[cfg(target_vendor = "apple")]
+use crate::tensor::MPSGraphTensor;
+#[cfg(target_vendor = "apple")]
+use crate::tensor_data::MPSGraphTensorData;
+#[cfg(target_vendor = "apple")]
+use std::collections::HashSet;
+#[cfg(target_vendor = "apple")]
+use std::fs;
+#[cfg(target_vendor = "apple")]
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest inside a cache directory
+pub const MANIFEST_FILE: &str = "manifest.txt";
+
+/// Extension of the serialized packages inside a cache directory
+pub const PACKAGE_EXTENSION: &str = "mpsgraphpackage";
+
+const MANIFEST_HEADER: &str = "mpsgraph-executable-cache";
+const MANIFEST_VERSION: u32 = 1;
+
+/// Errors reported by the executable cache
+#[derive(Debug)]
+pub enum CacheError {
+    /// Reading or writing the cache directory failed
+    Io(io::Error),
+    /// The manifest is malformed
+    Manifest {
+        /// 1-based line number
+        line: usize,
+        /// What is wrong with the line
+        message: String,
+    },
+    /// Compiling the graph failed
+    Graph(MPSGraphError),
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::Io(error) => write!(f, "executable cache i/o error: {}", error),
+            CacheError::Manifest { line, message } => {
+                write!(f, "invalid cache manifest, line {}: {}", line, message)
+            }
+            CacheError::Graph(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CacheError::Io(error) => Some(error),
+            CacheError::Graph(error) => Some(error),
+            CacheError::Manifest { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for CacheError {
+    fn from(error: io::Error) -> Self {
+        CacheError::Io(error)
+    }
+}
+
+impl From<MPSGraphError> for CacheError {
+    fn from(error: MPSGraphError) -> Self {
+        CacheError::Graph(error)
+    }
+}
+
+/// Result type returned by the executable cache
+pub type Result<T> = std::result::Result<T, CacheError>;
+
+/// A 64-bit hash that is stable across runs, printed as 16 hex digits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+    /// Wraps a raw hash value
+    pub fn from_u64(value: u64) -> Self {
+        Fingerprint(value)
+    }
+
+    /// Returns the raw hash value
+    pub fn to_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        if s.len() != 16 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!("'{}' is not a 16-digit hex fingerprint", s));
+        }
+        u64::from_str_radix(s, 16)
+            .map(Fingerprint)
+            .map_err(|error| error.to_string())
+    }
+}
+
+/// Incremental FNV-1a hasher producing [`Fingerprint`]s
+///
+/// Unlike `std::collections::hash_map::DefaultHasher` the result doesn't depend on the
+/// process, so it can be stored. Integers are hashed little-endian and strings are prefixed
+/// with their length, so `("ab", "c")` and `("a", "bc")` hash differently.
+#[derive(Debug, Clone)]
+pub struct Fingerprinter(u64);
+
+impl Fingerprinter {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    /// Starts an empty hash
+    pub fn new() -> Self {
+        Fingerprinter(Self::OFFSET_BASIS)
+    }
+
+    /// Hashes raw bytes
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// Hashes an integer
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Hashes a signed integer
+    pub fn write_i64(&mut self, value: i64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Hashes a string together with its length
+    pub fn write_str(&mut self, text: &str) {
+        self.write_u64(text.len() as u64);
+        self.write_bytes(text.as_bytes());
+    }
+
+    /// Hashes another fingerprint
+    pub fn write_fingerprint(&mut self, fingerprint: Fingerprint) {
+        self.write_u64(fingerprint.0);
+    }
+
+    /// Returns the hash of everything written so far
+    pub fn finish(&self) -> Fingerprint {
+        Fingerprint(self.0)
+    }
+}
+
+impl Default for Fingerprinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Write for Fingerprinter {
+    /// Hashes formatted text without the length prefix of [`Fingerprinter::write_str`], so
+    /// the result doesn't depend on how the text is split into pieces
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.write_bytes(text.as_bytes());
+        Ok(())
+    }
+}
+
+impl Graph {
+    /// Fingerprints the operations, attributes, names and constant data of the graph
+    ///
+    /// Two graphs have the same fingerprint when they print the same text form.
+    pub fn fingerprint(&self) -> Fingerprint {
+        let mut fingerprinter = Fingerprinter::new();
+        fmt::write(&mut fingerprinter, format_args!("{}", self)).expect("hashing never fails");
+        fingerprinter.finish()
+    }
+}
+
+/// Shapes and data types of the tensors fed to a graph
+///
+/// Each feed is identified by the index of the fed tensor, such as the position of the
+/// placeholder in the graph. The order in which feeds are pushed doesn't matter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct FeedSignature {
+    entries: Vec<(usize, Vec<usize>, MPSDataType)>,
+}
+
+impl FeedSignature {
+    /// Creates a signature without feeds
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the shape and data type fed to the tensor with index `input`
+    pub fn push(&mut self, input: usize, shape: &[usize], data_type: MPSDataType) {
+        let entry = (input, shape.to_vec(), data_type);
+        let position = self
+            .entries
+            .partition_point(|(other, other_shape, other_type)| {
+                (*other, other_shape, *other_type as u32) < (input, &entry.1, data_type as u32)
+            });
+        self.entries.insert(position, entry);
+    }
+
+    /// Adds a feed and returns the signature
+    pub fn with(mut self, input: usize, shape: &[usize], data_type: MPSDataType) -> Self {
+        self.push(input, shape, data_type);
+        self
+    }
+
+    /// Number of feeds
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the signature has no feeds
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Hashes the feeds
+    pub fn fingerprint(&self) -> Fingerprint {
+        let mut fingerprinter = Fingerprinter::new();
+        fingerprinter.write_u64(self.entries.len() as u64);
+        for (input, shape, data_type) in &self.entries {
+            fingerprinter.write_u64(*input as u64);
+            fingerprinter.write_u64(shape.len() as u64);
+            for &dim in shape {
+                fingerprinter.write_u64(dim as u64);
+            }
+            fingerprinter.write_u64(*data_type as u32 as u64);
+        }
+        fingerprinter.finish()
+    }
+}
+
+/// Identifies one compiled executable: a graph fingerprint and a feed signature fingerprint
+///
+/// Printed as `<graph>-<feeds>`, which is also the stem of the package file on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CacheKey {
+    /// Fingerprint of the graph and its targets
+    pub graph: Fingerprint,
+    /// Fingerprint of the feed signature
+    pub feeds: Fingerprint,
+}
+
+impl CacheKey {
+    /// Combines a graph fingerprint with the signature of the feeds
+    pub fn new(graph: Fingerprint, feeds: &FeedSignature) -> Self {
+        CacheKey {
+            graph,
+            feeds: feeds.fingerprint(),
+        }
+    }
+
+    /// Name of the package file holding the executable
+    pub fn file_name(&self) -> String {
+        format!("{}.{}", self, PACKAGE_EXTENSION)
+    }
+
+    /// Parses a name returned by [`CacheKey::file_name`]
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        name.strip_suffix(PACKAGE_EXTENSION)?
+            .strip_suffix('.')?
+            .parse()
+            .ok()
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.graph, self.feeds)
+    }
+}
+
+impl FromStr for CacheKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        let (graph, feeds) = s
+            .split_once('-')
+            .ok_or_else(|| format!("'{}' is not a cache key", s))?;
+        Ok(CacheKey {
+            graph: graph.parse()?,
+            feeds: feeds.parse()?,
+        })
+    }
+}
+
+/// Compilation settings, as a value that can be compared and fingerprinted
+///
+/// The Objective-C compilation descriptor can't be inspected, so the cache is configured with
+/// this type and builds the descriptor from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CompilationOptions {
+    /// Optimization level
+    pub optimization_level: MPSGraphOptimization,
+    /// Optimization profile
+    pub optimization_profile: MPSGraphOptimizationProfile,
+    /// Whether to compile for debugging
+    pub debug_compile: bool,
+}
+
+impl Default for CompilationOptions {
+    /// The MPSGraph defaults: level 1, optimized for performance, no debug compile
+    fn default() -> Self {
+        CompilationOptions {
+            optimization_level: MPSGraphOptimization::Level1,
+            optimization_profile: MPSGraphOptimizationProfile::Performance,
+            debug_compile: false,
+        }
+    }
+}
+
+impl CompilationOptions {
+    /// Sets the optimization level
+    pub fn with_optimization_level(mut self, level: MPSGraphOptimization) -> Self {
+        self.optimization_level = level;
+        self
+    }
+
+    /// Sets the optimization profile
+    pub fn with_optimization_profile(mut self, profile: MPSGraphOptimizationProfile) -> Self {
+        self.optimization_profile = profile;
+        self
+    }
+
+    /// Sets whether to compile for debugging
+    pub fn with_debug_compile(mut self, debug_compile: bool) -> Self {
+        self.debug_compile = debug_compile;
+        self
+    }
+
+    /// Hashes the settings
+    pub fn fingerprint(&self) -> Fingerprint {
+        let mut fingerprinter = Fingerprinter::new();
+        fingerprinter.write_u64(self.optimization_level as u64);
+        fingerprinter.write_u64(self.optimization_profile as u64);
+        fingerprinter.write_u64(self.debug_compile as u64);
+        fingerprinter.finish()
+    }
+
+    /// Builds the Objective-C compilation descriptor
+    #[cfg(target_vendor = "apple")]
+    pub fn build(&self) -> MPSGraphCompilationDescriptor {
+        let descriptor = MPSGraphCompilationDescriptor::new();
+        descriptor.set_optimization_level(self.optimization_level);
+        descriptor.set_optimization_profile(self.optimization_profile);
+        descriptor.set_debug_compile(self.debug_compile);
+        descriptor
+    }
+}
+
+/// Index of a cache directory
+///
+/// Records the fingerprint of the compilation options and, for each cached key, when it was
+/// last used. Its text form is
+///
+/// ```text
+/// mpsgraph-executable-cache 1
+/// options 5f2c7be0a9d3e641
+/// entry 0c1d2e3f40516273-8899aabbccddeeff 4
+/// ```
+///
+/// where the number after each key is a use counter; the entry with the smallest counter is
+/// evicted first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    options: Fingerprint,
+    clock: u64,
+    entries: HashMap<CacheKey, u64>,
+}
+
+impl Manifest {
+    /// Creates an empty manifest for executables compiled with `options`
+    pub fn new(options: Fingerprint) -> Self {
+        Manifest {
+            options,
+            clock: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Parses the text form
+    pub fn parse(text: &str) -> Result<Self> {
+        let error = |line: usize, message: String| CacheError::Manifest { line, message };
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+
+        let (line, header) = lines
+            .next()
+            .ok_or_else(|| error(1, String::from("the manifest is empty")))?;
+        match header.split_once(' ') {
+            Some((MANIFEST_HEADER, version)) => {
+                if version.parse() != Ok(MANIFEST_VERSION) {
+                    return Err(error(
+                        line,
+                        format!("unsupported manifest version '{}'", version),
+                    ));
+                }
+            }
+            _ => return Err(error(line, format!("expected '{}'", MANIFEST_HEADER))),
+        }
+
+        let mut options = None;
+        let mut manifest = Manifest::new(Fingerprint(0));
+        for (line, text) in lines {
+            let fields: Vec<&str> = text.split_whitespace().collect();
+            match fields.as_slice() {
+                ["options", fingerprint] if options.is_none() => {
+                    options = Some(fingerprint.parse().map_err(|e| error(line, e))?);
+                }
+                ["entry", key, used] => {
+                    let key: CacheKey = key.parse().map_err(|e| error(line, e))?;
+                    let used: u64 = used
+                        .parse()
+                        .map_err(|_| error(line, format!("'{}' is not a use counter", used)))?;
+                    if manifest.entries.insert(key, used).is_some() {
+                        return Err(error(line, format!("{} is listed twice", key)));
+                    }
+                    manifest.clock = manifest.clock.max(used);
+                }
+                _ => return Err(error(line, format!("unexpected line '{}'", text))),
+            }
+        }
+        manifest.options = options
+            .ok_or_else(|| error(text.lines().count(), String::from("missing 'options' line")))?;
+        Ok(manifest)
+    }
+
+    /// Fingerprint of the compilation options the cached executables were compiled with
+    pub fn options(&self) -> Fingerprint {
+        self.options
+    }
+
+    /// Number of cached keys
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no key is cached
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether `key` is cached
+    pub fn contains(&self, key: &CacheKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// The cached keys, least recently used first
+    pub fn keys(&self) -> Vec<CacheKey> {
+        let mut entries: Vec<(u64, CacheKey)> = self
+            .entries
+            .iter()
+            .map(|(key, used)| (*used, *key))
+            .collect();
+        entries.sort();
+        entries.into_iter().map(|(_, key)| key).collect()
+    }
+
+    /// Marks `key` as most recently used; returns false if it isn't cached
+    pub fn touch(&mut self, key: &CacheKey) -> bool {
+        let Some(used) = self.entries.get_mut(key) else {
+            return false;
+        };
+        self.clock += 1;
+        *used = self.clock;
+        true
+    }
+
+    /// Adds or touches `key` and evicts the least recently used keys beyond `capacity`
+    ///
+    /// Returns the evicted keys, whose packages should be deleted. With a capacity of zero
+    /// every key is evicted, `key` included.
+    pub fn insert(&mut self, key: CacheKey, capacity: usize) -> Vec<CacheKey> {
+        self.clock += 1;
+        self.entries.insert(key, self.clock);
+        self.shrink_to(capacity)
+    }
+
+    /// Removes `key`; returns false if it wasn't cached
+    pub fn remove(&mut self, key: &CacheKey) -> bool {
+        self.entries.remove(key).is_some()
+    }
+
+    /// Evicts the least recently used keys until at most `capacity` remain
+    pub fn shrink_to(&mut self, capacity: usize) -> Vec<CacheKey> {
+        let excess = self.entries.len().saturating_sub(capacity);
+        let evicted: Vec<CacheKey> = self.keys().into_iter().take(excess).collect();
+        for key in &evicted {
+            self.entries.remove(key);
+        }
+        evicted
+    }
+
+    /// Switches to other compilation options, dropping every key if they changed
+    ///
+    /// Returns the dropped keys.
+    pub fn retarget(&mut self, options: Fingerprint) -> Vec<CacheKey> {
+        if options == self.options {
+            return Vec::new();
+        }
+        self.options = options;
+        self.shrink_to(0)
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", MANIFEST_HEADER, MANIFEST_VERSION)?;
+        writeln!(f, "options {}", self.options)?;
+        for key in self.keys() {
+            writeln!(f, "entry {} {}", key, self.entries[&key])?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Manifest {
+    type Err = CacheError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Manifest::parse(s)
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl MPSGraph {
+    /// Numbers the tensors `targets` depend on, visiting the operations in post-order
+    ///
+    /// The numbering only depends on how the graph is built, not on names or object
+    /// addresses, so rebuilding the same graph numbers its tensors the same way.
+    fn number_tensors(&self, targets: &[MPSGraphTensor]) -> Walk {
+        fn visit(op: MPSGraphOperation, walk: &mut Walk, visited: &mut HashSet<usize>) {
+            if !visited.insert(op.0 as usize) {
+                return;
+            }
+            for tensor in op.input_tensors() {
+                visit(tensor.operation(), walk, visited);
+            }
+            for dependency in op.control_dependencies() {
+                visit(dependency, walk, visited);
+            }
+            for tensor in op.output_tensors() {
+                let index = walk.tensors.len();
+                walk.tensors.insert(tensor.0 as usize, index);
+                walk.retained_tensors.push(tensor);
+            }
+            walk.retained_operations.push(op);
+        }
+
+        let mut walk = Walk::default();
+        let mut visited = HashSet::new();
+        for tensor in targets {
+            visit(tensor.operation(), &mut walk, &mut visited);
+        }
+        walk
+    }
+
+    /// Describes `feeds`, identifying each fed tensor by its position in a post-order walk
+    /// from `targets`
+    ///
+    /// Feeds the targets don't depend on are all identified as `usize::MAX`.
+    pub fn feed_signature(
+        &self,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+    ) -> FeedSignature {
+        self.number_tensors(targets).feed_signature(feeds)
+    }
+}
+
+/// Tensor numbering of a walk from the targets
+#[cfg(target_vendor = "apple")]
+#[derive(Default)]
+struct Walk {
+    tensors: HashMap<usize, usize>,
+    // Keep the visited objects retained so their addresses stay unique
+    retained_tensors: Vec<MPSGraphTensor>,
+    retained_operations: Vec<MPSGraphOperation>,
+}
+
+#[cfg(target_vendor = "apple")]
+impl Walk {
+    fn feed_signature(&self, feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>) -> FeedSignature {
+        let mut signature = FeedSignature::new();
+        for (tensor, data) in feeds {
+            let input = self
+                .tensors
+                .get(&(tensor.0 as usize))
+                .copied()
+                .unwrap_or(usize::MAX);
+            // Unknown data types are reported by `validate_feeds` before anything is compiled
+            let data_type = data.try_data_type().unwrap_or(MPSDataType::Invalid);
+            signature.push(input, &data.shape().dimensions(), data_type);
+        }
+        signature
+    }
+}
+
+/// The on-disk tier of an [`ExecutableCache`]
+#[cfg(target_vendor = "apple")]
+struct DiskCache {
+    directory: PathBuf,
+    capacity: usize,
+    manifest: Manifest,
+}
+
+#[cfg(target_vendor = "apple")]
+impl DiskCache {
+    fn url(&self, key: &CacheKey) -> String {
+        format!("file://{}", self.directory.join(key.file_name()).display())
+    }
+
+    fn remove_package(&self, key: &CacheKey) -> io::Result<()> {
+        let path = self.directory.join(key.file_name());
+        let result = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        match result {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    fn remove_packages(&self, keys: &[CacheKey]) -> io::Result<()> {
+        keys.iter().try_for_each(|key| self.remove_package(key))
+    }
+
+    /// Writes the manifest through a temporary file so it is never left half-written
+    fn save(&self) -> io::Result<()> {
+        let temporary = self.directory.join(format!("{}.tmp", MANIFEST_FILE));
+        fs::write(&temporary, self.manifest.to_string())?;
+        fs::rename(&temporary, self.directory.join(MANIFEST_FILE))
+    }
+}
+
+/// Compiled executables keyed by graph fingerprint and feed signature
+///
+/// Executables are kept in memory up to a capacity and, after
+/// [`ExecutableCache::with_directory`], also serialized into a directory that outlives the
+/// process. Both tiers evict the least recently used executable first.
+///
+/// An executable returned for a key may have been compiled from another, structurally
+/// identical graph, or loaded from a package, so it is run positionally with
+/// [`MPSGraphExecutable::encode_to_command_buffer`] or
+/// [`MPSGraphExecutable::run_with_inputs_outputs`] rather than with a dictionary keyed by
+/// the caller's tensors.
+#[cfg(target_vendor = "apple")]
+pub struct ExecutableCache {
+    options: CompilationOptions,
+    descriptor: MPSGraphCompilationDescriptor,
+    capacity: usize,
+    // Least recently used first
+    memory: Vec<(CacheKey, MPSGraphExecutable)>,
+    disk: Option<DiskCache>,
+}
+
+#[cfg(target_vendor = "apple")]
+impl ExecutableCache {
+    /// Creates an in-memory cache holding up to `capacity` executables
+    pub fn new(options: CompilationOptions, capacity: usize) -> Self {
+        ExecutableCache {
+            options,
+            descriptor: options.build(),
+            capacity,
+            memory: Vec::new(),
+            disk: None,
+        }
+    }
+
+    /// Also keeps up to `capacity` serialized executables in `directory`
+    ///
+    /// The directory is created if needed. Packages compiled with other options, packages
+    /// the manifest doesn't list and, if the manifest can't be read, every package are
+    /// deleted.
+    pub fn with_directory(mut self, directory: impl AsRef<Path>, capacity: usize) -> Result<Self> {
+        let directory = directory.as_ref();
+        fs::create_dir_all(directory)?;
+        let directory = directory.canonicalize()?;
+
+        let options = self.options.fingerprint();
+        let mut manifest = match fs::read_to_string(directory.join(MANIFEST_FILE)) {
+            Ok(text) => Manifest::parse(&text).unwrap_or_else(|_| Manifest::new(options)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Manifest::new(options),
+            Err(error) => return Err(error.into()),
+        };
+        manifest.retarget(options);
+        manifest.shrink_to(capacity);
+
+        let disk = DiskCache {
+            directory,
+            capacity,
+            manifest,
+        };
+        let mut orphans = Vec::new();
+        for entry in fs::read_dir(&disk.directory)? {
+            let name = entry?.file_name();
+            if let Some(key) = name.to_str().and_then(CacheKey::from_file_name) {
+                if !disk.manifest.contains(&key) {
+                    orphans.push(key);
+                }
+            }
+        }
+        disk.remove_packages(&orphans)?;
+        disk.save()?;
+        self.disk = Some(disk);
+        Ok(self)
+    }
+
+    /// The options executables are compiled with
+    pub fn options(&self) -> CompilationOptions {
+        self.options
+    }
+
+    /// Changes the compilation options, dropping every cached executable if they differ
+    pub fn set_options(&mut self, options: CompilationOptions) -> Result<()> {
+        if options == self.options {
+            return Ok(());
+        }
+        self.options = options;
+        self.descriptor = options.build();
+        self.memory.clear();
+        if let Some(disk) = &mut self.disk {
+            let dropped = disk.manifest.retarget(options.fingerprint());
+            disk.remove_packages(&dropped)?;
+            disk.save()?;
+        }
+        Ok(())
+    }
+
+    /// Number of executables held in memory
+    pub fn len(&self) -> usize {
+        self.memory.len()
+    }
+
+    /// Whether no executable is held in memory
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_empty()
+    }
+
+    /// Returns the executable cached for `key`, loading it from disk if needed
+    ///
+    /// A package that can no longer be loaded, for example after an OS update, is dropped.
+    pub fn get(&mut self, key: &CacheKey) -> Result<Option<MPSGraphExecutable>> {
+        if let Some(position) = self.memory.iter().position(|(other, _)| other == key) {
+            let entry = self.memory.remove(position);
+            let executable = entry.1.clone();
+            self.memory.push(entry);
+            return Ok(Some(executable));
+        }
+
+        let Some(disk) = &mut self.disk else {
+            return Ok(None);
+        };
+        if !disk.manifest.contains(key) {
+            return Ok(None);
+        }
+        let loaded =
+            MPSGraphExecutable::try_from_serialized_package(&disk.url(key), Some(&self.descriptor));
+        match loaded {
+            Ok(executable) => {
+                disk.manifest.touch(key);
+                disk.save()?;
+                self.remember(*key, executable.clone());
+                Ok(Some(executable))
+            }
+            Err(_) => {
+                disk.manifest.remove(key);
+                disk.remove_package(key)?;
+                disk.save()?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Caches `executable` under `key`
+    ///
+    /// If the executable can't be serialized it is only kept in memory.
+    pub fn insert(&mut self, key: CacheKey, executable: MPSGraphExecutable) -> Result<()> {
+        if let Some(disk) = &mut self.disk {
+            disk.remove_package(&key)?;
+            let serialized = executable.serialize_to_url(
+                &disk.url(&key),
+                &MPSGraphExecutableSerializationDescriptor::new(),
+            );
+            if serialized {
+                let evicted = disk.manifest.insert(key, disk.capacity);
+                disk.remove_packages(&evicted)?;
+            } else {
+                disk.manifest.remove(&key);
+            }
+            disk.save()?;
+        }
+        self.memory.retain(|(other, _)| *other != key);
+        self.remember(key, executable);
+        Ok(())
+    }
+
+    /// Returns the executable cached under `key`, compiling `graph` for `feeds` and `targets`
+    /// on a miss
+    ///
+    /// Before compiling, the feeds are checked with [`MPSGraph::validate_feeds`].
+    ///
+    /// The key must cover everything the executable depends on. MPSGraph doesn't expose the
+    /// values of its constants, so the graph half can't be derived from an `MPSGraph`: use
+    /// [`ir::Graph::fingerprint`](crate::ir::Graph::fingerprint) for recorded graphs, or hash
+    /// the model and its weights with a [`Fingerprinter`]. The feed half comes from
+    /// [`MPSGraph::feed_signature`].
+    pub fn get_or_compile_with_key(
+        &mut self,
+        key: CacheKey,
+        graph: &MPSGraph,
+        device: &MPSGraphDevice,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+    ) -> Result<MPSGraphExecutable> {
+        if let Some(executable) = self.get(&key)? {
+            return Ok(executable);
+        }
+        graph.validate_feeds(feeds, targets)?;
+        let executable = graph.try_compile(device, feeds, targets, Some(&self.descriptor))?;
+        self.insert(key, executable.clone())?;
+        Ok(executable)
+    }
+
+    /// Drops every cached executable, in memory and on disk
+    pub fn clear(&mut self) -> Result<()> {
+        self.memory.clear();
+        if let Some(disk) = &mut self.disk {
+            let dropped = disk.manifest.shrink_to(0);
+            disk.remove_packages(&dropped)?;
+            disk.save()?;
+        }
+        Ok(())
+    }
+
+    fn remember(&mut self, key: CacheKey, executable: MPSGraphExecutable) {
+        self.memory.push((key, executable));
+        let excess = self.memory.len().saturating_sub(self.capacity);
+        self.memory.drain(..excess);
+    }
+}
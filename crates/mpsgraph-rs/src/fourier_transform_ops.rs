@@ -155,7 +155,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -196,7 +196,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -245,7 +245,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -286,7 +286,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -335,7 +335,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -376,7 +376,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -430,7 +430,7 @@ impl MPSGraph {
             let real_output = objc2::ffi::objc_retain(real_output as *mut _);
             let imag_output = objc2::ffi::objc_retain(imag_output as *mut _);
 
-            (MPSGraphTensor(real_output), MPSGraphTensor(imag_output))
+            (MPSGraphTensor::from_retained(real_output), MPSGraphTensor::from_retained(imag_output))
         }
     }
 
@@ -486,7 +486,7 @@ impl MPSGraph {
             let real_output = objc2::ffi::objc_retain(real_output as *mut _);
             let imag_output = objc2::ffi::objc_retain(imag_output as *mut _);
 
-            (MPSGraphTensor(real_output), MPSGraphTensor(imag_output))
+            (MPSGraphTensor::from_retained(real_output), MPSGraphTensor::from_retained(imag_output))
         }
     }
 
@@ -535,7 +535,7 @@ impl MPSGraph {
             let real_output = objc2::ffi::objc_retain(real_output as *mut _);
             let imag_output = objc2::ffi::objc_retain(imag_output as *mut _);
 
-            (MPSGraphTensor(real_output), MPSGraphTensor(imag_output))
+            (MPSGraphTensor::from_retained(real_output), MPSGraphTensor::from_retained(imag_output))
         }
     }
 
@@ -577,7 +577,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 }
@@ -0,0 +1,1211 @@
+//! GGUF model files and reference dequantization of ggml block formats.
+//!
+//! [`GgufFile`] memory-maps a `.gguf` file (or takes ownership of its bytes) and parses the
+//! metadata key-value pairs and tensor infos. Each tensor keeps its ggml storage type:
+//! [`dequantize`] is a pure-Rust reference that expands a block format to `f32` exactly
+//! like ggml does, and [`unpack`] splits the blocks on the host into the operands of an
+//! `MPSGraph` dequantize operation:
+//!
+//! - Q4_0, Q5_0, Q8_0 and Q6_K become one value per byte, a scale per block and a shared
+//!   zero point, for `dequantize_with_tensors`.
+//! - Q4_1 and Q4_K, whose blocks add a minimum, become packed UInt4 indices into a
+//!   16-entry lookup table per block, for `dequantize_with_lut`.
+//!
+//! [`Unpacked::dequantize`] evaluates the same formula on the host, so the unpacking can be
+//! checked bit for bit against [`dequantize`] without Metal. On Apple targets
+//! [`GgufTensor::load_into`] records the constants and the dequantize operation.
+//! Vector lookup tables (`dequantize_with_lut_axis`) have no GGUF counterpart.
+
+use crate::cast;
+use crate::core::MPSDataType;
+use memmap2::Mmap;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+#[cfg(target_vendor = "apple")]
+use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
+use crate::shape::MPSShape;
+#[cfg(target_vendor = "apple")]
+use crate::tensor::MPSGraphTensor;
+#[cfg(target_vendor = "apple")]
+use std::collections::HashMap;
+
+const MAGIC: &[u8; 4] = b"GGUF";
+
+/// Alignment of the tensor data when the file doesn't set `general.alignment`
+pub const DEFAULT_ALIGNMENT: usize = 32;
+
+/// Errors reported while reading, writing or dequantizing GGUF data
+#[derive(Debug)]
+pub enum GgufError {
+    /// Reading or writing the underlying file failed
+    Io(io::Error),
+    /// The header, metadata or tensor infos are malformed
+    Format(String),
+    /// Only GGUF versions 2 and 3 are supported
+    UnsupportedVersion(u32),
+    /// The ggml type code is unknown
+    UnknownType(u32),
+    /// The ggml type has no reference dequantizer or graph mapping
+    UnsupportedType(GgmlType),
+    /// The data type can't hold dequantized values
+    UnsupportedDataType(MPSDataType),
+    /// The file has no tensor with this name
+    MissingTensor(String),
+    /// The buffer size doesn't match the shape and ggml type
+    SizeMismatch {
+        /// Bytes the shape and type require
+        expected: usize,
+        /// Bytes supplied
+        actual: usize,
+    },
+}
+
+impl fmt::Display for GgufError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GgufError::Io(error) => write!(f, "gguf i/o error: {}", error),
+            GgufError::Format(reason) => write!(f, "invalid gguf file: {}", reason),
+            GgufError::UnsupportedVersion(version) => {
+                write!(f, "gguf version {} is not supported", version)
+            }
+            GgufError::UnknownType(code) => write!(f, "unknown ggml type {}", code),
+            GgufError::UnsupportedType(ggml_type) => {
+                write!(f, "ggml type {:?} can't be dequantized", ggml_type)
+            }
+            GgufError::UnsupportedDataType(data_type) => {
+                write!(f, "can't dequantize to {:?}", data_type)
+            }
+            GgufError::MissingTensor(name) => write!(f, "no tensor named '{}'", name),
+            GgufError::SizeMismatch { expected, actual } => write!(
+                f,
+                "buffer holds {} bytes but the shape and type need {}",
+                actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GgufError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GgufError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GgufError {
+    fn from(error: io::Error) -> Self {
+        GgufError::Io(error)
+    }
+}
+
+/// Result type returned by the GGUF functions
+pub type Result<T> = std::result::Result<T, GgufError>;
+
+/// Storage types of ggml tensors
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum GgmlType {
+    F32 = 0,
+    F16 = 1,
+    Q4_0 = 2,
+    Q4_1 = 3,
+    Q5_0 = 6,
+    Q5_1 = 7,
+    Q8_0 = 8,
+    Q8_1 = 9,
+    Q2_K = 10,
+    Q3_K = 11,
+    Q4_K = 12,
+    Q5_K = 13,
+    Q6_K = 14,
+    Q8_K = 15,
+    IQ2_XXS = 16,
+    IQ2_XS = 17,
+    IQ3_XXS = 18,
+    IQ1_S = 19,
+    IQ4_NL = 20,
+    IQ3_S = 21,
+    IQ2_S = 22,
+    IQ4_XS = 23,
+    I8 = 24,
+    I16 = 25,
+    I32 = 26,
+    I64 = 27,
+    F64 = 28,
+    IQ1_M = 29,
+    BF16 = 30,
+}
+
+impl TryFrom<u32> for GgmlType {
+    type Error = GgufError;
+
+    fn try_from(code: u32) -> Result<Self> {
+        use GgmlType::*;
+        Ok(match code {
+            0 => F32,
+            1 => F16,
+            2 => Q4_0,
+            3 => Q4_1,
+            6 => Q5_0,
+            7 => Q5_1,
+            8 => Q8_0,
+            9 => Q8_1,
+            10 => Q2_K,
+            11 => Q3_K,
+            12 => Q4_K,
+            13 => Q5_K,
+            14 => Q6_K,
+            15 => Q8_K,
+            16 => IQ2_XXS,
+            17 => IQ2_XS,
+            18 => IQ3_XXS,
+            19 => IQ1_S,
+            20 => IQ4_NL,
+            21 => IQ3_S,
+            22 => IQ2_S,
+            23 => IQ4_XS,
+            24 => I8,
+            25 => I16,
+            26 => I32,
+            27 => I64,
+            28 => F64,
+            29 => IQ1_M,
+            30 => BF16,
+            code => return Err(GgufError::UnknownType(code)),
+        })
+    }
+}
+
+impl GgmlType {
+    /// Returns the number of elements in one block; 1 for plain types
+    pub fn block_size(self) -> usize {
+        use GgmlType::*;
+        match self {
+            F32 | F16 | BF16 | F64 | I8 | I16 | I32 | I64 => 1,
+            Q4_0 | Q4_1 | Q5_0 | Q5_1 | Q8_0 | Q8_1 | IQ4_NL => 32,
+            _ => 256,
+        }
+    }
+
+    /// Returns the number of bytes in one block
+    pub fn block_bytes(self) -> usize {
+        use GgmlType::*;
+        match self {
+            I8 => 1,
+            F16 | BF16 | I16 => 2,
+            F32 | I32 => 4,
+            F64 | I64 => 8,
+            Q4_0 => 18,
+            Q4_1 => 20,
+            Q5_0 => 22,
+            Q5_1 => 24,
+            Q8_0 => 34,
+            Q8_1 => 36,
+            Q2_K => 84,
+            Q3_K => 110,
+            Q4_K => 144,
+            Q5_K => 176,
+            Q6_K => 210,
+            Q8_K => 292,
+            IQ2_XXS => 66,
+            IQ2_XS => 74,
+            IQ3_XXS => 98,
+            IQ1_S => 50,
+            IQ4_NL => 18,
+            IQ3_S => 110,
+            IQ2_S => 82,
+            IQ4_XS => 136,
+            IQ1_M => 56,
+        }
+    }
+
+    /// Returns the data type of plain, non-quantized types
+    pub fn data_type(self) -> Option<MPSDataType> {
+        Some(match self {
+            GgmlType::F32 => MPSDataType::Float32,
+            GgmlType::F16 => MPSDataType::Float16,
+            GgmlType::BF16 => MPSDataType::BFloat16,
+            GgmlType::F64 => MPSDataType::Float64,
+            GgmlType::I8 => MPSDataType::Int8,
+            GgmlType::I16 => MPSDataType::Int16,
+            GgmlType::I32 => MPSDataType::Int32,
+            GgmlType::I64 => MPSDataType::Int64,
+            _ => return None,
+        })
+    }
+
+    /// Returns the number of bytes `count` elements occupy
+    ///
+    /// Returns `None` if `count` isn't a whole number of blocks or the size overflows.
+    pub fn size_in_bytes(self, count: usize) -> Option<usize> {
+        let blocks = count / self.block_size();
+        if blocks * self.block_size() != count {
+            return None;
+        }
+        blocks.checked_mul(self.block_bytes())
+    }
+}
+
+/// A metadata value
+#[derive(Debug, Clone, PartialEq)]
+pub enum GgufValue {
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+    Bool(bool),
+    String(String),
+    /// Elements of one type; an empty array is written with U8 elements
+    Array(Vec<GgufValue>),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+}
+
+impl GgufValue {
+    fn type_code(&self) -> u32 {
+        match self {
+            GgufValue::U8(_) => 0,
+            GgufValue::I8(_) => 1,
+            GgufValue::U16(_) => 2,
+            GgufValue::I16(_) => 3,
+            GgufValue::U32(_) => 4,
+            GgufValue::I32(_) => 5,
+            GgufValue::F32(_) => 6,
+            GgufValue::Bool(_) => 7,
+            GgufValue::String(_) => 8,
+            GgufValue::Array(_) => 9,
+            GgufValue::U64(_) => 10,
+            GgufValue::I64(_) => 11,
+            GgufValue::F64(_) => 12,
+        }
+    }
+
+    /// Returns integer values as `i64`, if they fit
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            GgufValue::U8(v) => Some(v as i64),
+            GgufValue::I8(v) => Some(v as i64),
+            GgufValue::U16(v) => Some(v as i64),
+            GgufValue::I16(v) => Some(v as i64),
+            GgufValue::U32(v) => Some(v as i64),
+            GgufValue::I32(v) => Some(v as i64),
+            GgufValue::U64(v) => i64::try_from(v).ok(),
+            GgufValue::I64(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns numeric values as `f64`
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            GgufValue::F32(v) => Some(v as f64),
+            GgufValue::F64(v) => Some(v),
+            GgufValue::U64(v) => Some(v as f64),
+            _ => self.as_i64().map(|v| v as f64),
+        }
+    }
+
+    /// Returns the value of a Bool
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            GgufValue::Bool(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a String
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            GgufValue::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements of an Array
+    pub fn as_array(&self) -> Option<&[GgufValue]> {
+        match self {
+            GgufValue::Array(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// A tensor listed in a GGUF file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GgufTensorInfo {
+    /// Name of the tensor
+    pub name: String,
+    /// Shape, outermost dimension first; GGUF itself lists the innermost first
+    pub shape: Vec<usize>,
+    /// Storage type
+    pub ggml_type: GgmlType,
+    /// Offset of the data from the start of the data section
+    pub offset: u64,
+}
+
+/// Borrowed tensor data in a ggml storage type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GgufTensor<'a> {
+    ggml_type: GgmlType,
+    shape: &'a [usize],
+    data: &'a [u8],
+}
+
+impl<'a> GgufTensor<'a> {
+    /// Creates a tensor, checking that the buffer matches the shape and type
+    ///
+    /// Blocks run along the innermost dimension, which must be a whole number of blocks.
+    pub fn new(ggml_type: GgmlType, shape: &'a [usize], data: &'a [u8]) -> Result<Self> {
+        let innermost = shape.last().copied().unwrap_or(1);
+        if innermost % ggml_type.block_size() != 0 {
+            return Err(GgufError::Format(format!(
+                "innermost dimension {} is not a multiple of the {:?} block size {}",
+                innermost,
+                ggml_type,
+                ggml_type.block_size()
+            )));
+        }
+        let expected = shape
+            .iter()
+            .try_fold(1usize, |count, &size| count.checked_mul(size))
+            .and_then(|count| ggml_type.size_in_bytes(count))
+            .ok_or_else(|| {
+                GgufError::Format(format!("shape {:?} holds more bytes than fit", shape))
+            })?;
+        if data.len() != expected {
+            return Err(GgufError::SizeMismatch {
+                expected,
+                actual: data.len(),
+            });
+        }
+        Ok(GgufTensor {
+            ggml_type,
+            shape,
+            data,
+        })
+    }
+
+    /// Returns the storage type
+    pub fn ggml_type(&self) -> GgmlType {
+        self.ggml_type
+    }
+
+    /// Returns the shape, outermost dimension first
+    pub fn shape(&self) -> &'a [usize] {
+        self.shape
+    }
+
+    /// Returns the stored bytes
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Expands the tensor to `f32` with the reference dequantizer
+    pub fn dequantize(&self) -> Result<Vec<f32>> {
+        dequantize(self.ggml_type, self.data)
+    }
+
+    /// Splits the tensor into the operands of a dequantize operation
+    pub fn unpack(&self) -> Result<Unpacked> {
+        unpack(self.ggml_type, self.data)
+    }
+
+    /// Records the tensor as constants and a dequantize operation producing `data_type`
+    ///
+    /// `data_type` must be Float32, Float16 or BFloat16; scales and lookup tables are
+    /// converted to it on the host. Plain integer tensors keep their type.
+    #[cfg(target_vendor = "apple")]
+    pub fn load_into(
+        &self,
+        graph: &MPSGraph,
+        data_type: MPSDataType,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        if !matches!(
+            data_type,
+            MPSDataType::Float32 | MPSDataType::Float16 | MPSDataType::BFloat16
+        ) {
+            return Err(GgufError::UnsupportedDataType(data_type));
+        }
+        let floats = |values: &[f32]| {
+            let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+            cast::cast(&bytes, MPSDataType::Float32, data_type).expect("float types")
+        };
+        let shape: Vec<i64> = self.shape.iter().map(|&size| size as i64).collect();
+
+        let tensor = match self.unpack()? {
+            Unpacked::Plain {
+                data_type: stored,
+                data,
+            } => {
+                let shape = MPSShape::from_slice(self.shape);
+                return Ok(match cast::cast(&data, stored, data_type) {
+                    Some(data) => graph.constant(&data, &shape, data_type),
+                    None => graph.constant(&data, &shape, stored),
+                });
+            }
+            Unpacked::Affine {
+                values,
+                data_type: values_type,
+                scales,
+                zero_point,
+                block_size,
+            } => {
+                // One row per block, so the per-block scales run along axis 0
+                let blocks = scales.len();
+                let values = graph.constant(
+                    &values,
+                    &MPSShape::from_slice(&[blocks, block_size]),
+                    values_type,
+                );
+                let block_shape = MPSShape::from_slice(&[blocks]);
+                let scales = graph.constant(&floats(&scales), &block_shape, data_type);
+                let zero_points =
+                    graph.constant(&vec![zero_point; blocks], &block_shape, values_type);
+                graph.dequantize_with_tensors(&values, &scales, &zero_points, data_type, 0, None)
+            }
+            Unpacked::Lut {
+                indices,
+                lut,
+                block_size,
+            } => {
+                let blocks = lut.len() / LUT_ENTRIES;
+                let indices = graph.constant(
+                    &indices,
+                    &MPSShape::from_slice(&[blocks, block_size]),
+                    MPSDataType::UInt4,
+                );
+                let lut = graph.constant(
+                    &floats(&lut),
+                    &MPSShape::from_slice(&[blocks, 1, LUT_ENTRIES]),
+                    data_type,
+                );
+                graph.dequantize_with_lut(&indices, &lut, None)
+            }
+        };
+        Ok(graph.reshape(&tensor, &shape, name))
+    }
+}
+
+/// Entries in the lookup table of each [`Unpacked::Lut`] block
+pub const LUT_ENTRIES: usize = 16;
+
+/// A tensor split into the operands of an `MPSGraph` dequantize operation
+///
+/// Blocks run along the innermost dimension, so block `b` covers elements
+/// `b * block_size..(b + 1) * block_size` in row-major order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Unpacked {
+    /// Plain element bytes that need no dequantization
+    Plain {
+        /// Type of the elements
+        data_type: MPSDataType,
+        /// Little-endian element bytes
+        data: Vec<u8>,
+    },
+    /// `scales[block] * (value - zero_point)`, for `dequantize_with_tensors`
+    Affine {
+        /// One value per element and byte
+        values: Vec<u8>,
+        /// Int8 for Q8_0 values, UInt8 otherwise
+        data_type: MPSDataType,
+        /// One scale per block
+        scales: Vec<f32>,
+        /// Zero point shared by every block
+        zero_point: u8,
+        /// Elements per block
+        block_size: usize,
+    },
+    /// `lut[block * LUT_ENTRIES + index]`, for `dequantize_with_lut`
+    Lut {
+        /// UInt4 indices packed two per byte, the earlier element in the low nibble
+        indices: Vec<u8>,
+        /// [`LUT_ENTRIES`] values per block
+        lut: Vec<f32>,
+        /// Elements per block
+        block_size: usize,
+    },
+}
+
+impl Unpacked {
+    /// Evaluates the dequantize operation on the host
+    ///
+    /// Plain integer elements are converted to `f32`.
+    pub fn dequantize(&self) -> Vec<f32> {
+        match self {
+            Unpacked::Plain { data_type, data } => plain_to_f32(*data_type, data),
+            Unpacked::Affine {
+                values,
+                data_type,
+                scales,
+                zero_point,
+                block_size,
+            } => values
+                .iter()
+                .enumerate()
+                .map(|(index, &value)| {
+                    let value = match data_type {
+                        MPSDataType::Int8 => value as i8 as i32,
+                        _ => value as i32,
+                    };
+                    ((value - *zero_point as i32) as f32) * scales[index / block_size]
+                })
+                .collect(),
+            Unpacked::Lut {
+                indices,
+                lut,
+                block_size,
+            } => indices
+                .iter()
+                .flat_map(|&pair| [pair & 0x0f, pair >> 4])
+                .enumerate()
+                .map(|(index, entry)| lut[index / block_size * LUT_ENTRIES + entry as usize])
+                .collect(),
+        }
+    }
+}
+
+fn f16(bytes: &[u8]) -> f32 {
+    cast::f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn plain_to_f32(data_type: MPSDataType, data: &[u8]) -> Vec<f32> {
+    let size = data_type.size_in_bytes();
+    data.chunks_exact(size)
+        .map(|e| match data_type {
+            MPSDataType::Float16 => f16(e),
+            MPSDataType::BFloat16 => cast::bf16_to_f32(u16::from_le_bytes([e[0], e[1]])),
+            MPSDataType::Float32 => f32::from_le_bytes(e.try_into().unwrap()),
+            MPSDataType::Float64 => f64::from_le_bytes(e.try_into().unwrap()) as f32,
+            MPSDataType::Int8 => e[0] as i8 as f32,
+            MPSDataType::Int16 => i16::from_le_bytes([e[0], e[1]]) as f32,
+            MPSDataType::Int32 => i32::from_le_bytes(e.try_into().unwrap()) as f32,
+            _ => i64::from_le_bytes(e.try_into().unwrap()) as f32,
+        })
+        .collect()
+}
+
+/// Returns the blocks of `data`, checking that it is a whole number of them
+fn blocks(ggml_type: GgmlType, data: &[u8]) -> Result<std::slice::ChunksExact<'_, u8>> {
+    let blocks = data.chunks_exact(ggml_type.block_bytes());
+    if !blocks.remainder().is_empty() {
+        let expected = data.len() / ggml_type.block_bytes() * ggml_type.block_bytes();
+        return Err(GgufError::SizeMismatch {
+            expected,
+            actual: data.len(),
+        });
+    }
+    Ok(blocks)
+}
+
+/// Unpacks the 6-bit scale and minimum of sub-block `j` of a Q4_K block
+fn scale_min_k4(j: usize, scales: &[u8]) -> (u8, u8) {
+    if j < 4 {
+        (scales[j] & 63, scales[j + 4] & 63)
+    } else {
+        (
+            (scales[j + 4] & 0x0f) | ((scales[j - 4] >> 6) << 4),
+            (scales[j + 4] >> 4) | ((scales[j] >> 6) << 4),
+        )
+    }
+}
+
+/// Expands ggml data to `f32` exactly as ggml's reference implementation does
+///
+/// Supports the plain types and Q4_0, Q4_1, Q5_0, Q8_0, Q4_K and Q6_K.
+pub fn dequantize(ggml_type: GgmlType, data: &[u8]) -> Result<Vec<f32>> {
+    let blocks = blocks(ggml_type, data)?;
+    if let Some(data_type) = ggml_type.data_type() {
+        return Ok(plain_to_f32(data_type, data));
+    }
+
+    let mut out = Vec::with_capacity(blocks.len() * ggml_type.block_size());
+    for block in blocks {
+        match ggml_type {
+            GgmlType::Q4_0 => {
+                let d = f16(block);
+                let qs = &block[2..18];
+                out.extend(qs.iter().map(|q| ((q & 0x0f) as i32 - 8) as f32 * d));
+                out.extend(qs.iter().map(|q| ((q >> 4) as i32 - 8) as f32 * d));
+            }
+            GgmlType::Q4_1 => {
+                let (d, m) = (f16(block), f16(&block[2..]));
+                let qs = &block[4..20];
+                out.extend(qs.iter().map(|q| (q & 0x0f) as f32 * d + m));
+                out.extend(qs.iter().map(|q| (q >> 4) as f32 * d + m));
+            }
+            GgmlType::Q5_0 => {
+                let d = f16(block);
+                let qh = u32::from_le_bytes(block[2..6].try_into().unwrap());
+                let qs = &block[6..22];
+                let low = (0..16).map(|j| {
+                    let high = ((qh >> j) << 4) & 0x10;
+                    (((qs[j] & 0x0f) as u32 | high) as i32 - 16) as f32 * d
+                });
+                out.extend(low.collect::<Vec<_>>());
+                let high = (0..16).map(|j| {
+                    let high = (qh >> (j + 12)) & 0x10;
+                    (((qs[j] >> 4) as u32 | high) as i32 - 16) as f32 * d
+                });
+                out.extend(high.collect::<Vec<_>>());
+            }
+            GgmlType::Q8_0 => {
+                let d = f16(block);
+                out.extend(block[2..34].iter().map(|&q| (q as i8) as f32 * d));
+            }
+            GgmlType::Q4_K => {
+                let (d, min) = (f16(block), f16(&block[2..]));
+                let scales = &block[4..16];
+                for (chunk, qs) in block[16..144].chunks_exact(32).enumerate() {
+                    let (sc, m) = scale_min_k4(2 * chunk, scales);
+                    let (d1, m1) = (d * sc as f32, min * m as f32);
+                    out.extend(qs.iter().map(|q| d1 * (q & 0x0f) as f32 - m1));
+                    let (sc, m) = scale_min_k4(2 * chunk + 1, scales);
+                    let (d2, m2) = (d * sc as f32, min * m as f32);
+                    out.extend(qs.iter().map(|q| d2 * (q >> 4) as f32 - m2));
+                }
+            }
+            GgmlType::Q6_K => {
+                let (ql, qh) = (&block[..128], &block[128..192]);
+                let scales = &block[192..208];
+                let d = f16(&block[208..]);
+                for half in 0..2 {
+                    let (ql, qh) = (&ql[64 * half..], &qh[32 * half..]);
+                    let sc = &scales[8 * half..];
+                    let mut y = [0f32; 128];
+                    for l in 0..32 {
+                        let is = l / 16;
+                        let q = [
+                            (ql[l] & 0x0f) | ((qh[l] & 3) << 4),
+                            (ql[l + 32] & 0x0f) | (((qh[l] >> 2) & 3) << 4),
+                            (ql[l] >> 4) | (((qh[l] >> 4) & 3) << 4),
+                            (ql[l + 32] >> 4) | (((qh[l] >> 6) & 3) << 4),
+                        ];
+                        for (group, q) in q.into_iter().enumerate() {
+                            let scale = sc[is + 2 * group] as i8 as f32;
+                            y[l + 32 * group] = d * scale * (q as i32 - 32) as f32;
+                        }
+                    }
+                    out.extend_from_slice(&y);
+                }
+            }
+            other => return Err(GgufError::UnsupportedType(other)),
+        }
+    }
+    Ok(out)
+}
+
+/// Splits ggml data into the operands of an `MPSGraph` dequantize operation
+///
+/// Supports the same types as [`dequantize`], and [`Unpacked::dequantize`] reproduces its
+/// results bit for bit.
+pub fn unpack(ggml_type: GgmlType, data: &[u8]) -> Result<Unpacked> {
+    let blocks = blocks(ggml_type, data)?;
+    if let Some(data_type) = ggml_type.data_type() {
+        return Ok(Unpacked::Plain {
+            data_type,
+            data: data.to_vec(),
+        });
+    }
+
+    let count = blocks.len() * ggml_type.block_size();
+    match ggml_type {
+        GgmlType::Q4_0 | GgmlType::Q5_0 | GgmlType::Q8_0 => {
+            let mut values = Vec::with_capacity(count);
+            let mut scales = Vec::with_capacity(blocks.len());
+            for block in blocks {
+                scales.push(f16(block));
+                match ggml_type {
+                    GgmlType::Q4_0 => {
+                        let qs = &block[2..18];
+                        values.extend(qs.iter().map(|q| q & 0x0f));
+                        values.extend(qs.iter().map(|q| q >> 4));
+                    }
+                    GgmlType::Q5_0 => {
+                        let qh = u32::from_le_bytes(block[2..6].try_into().unwrap());
+                        let qs = &block[6..22];
+                        values
+                            .extend((0..16).map(|j| (qs[j] & 0x0f) | (((qh >> j) & 1) << 4) as u8));
+                        values.extend(
+                            (0..16).map(|j| (qs[j] >> 4) | (((qh >> (j + 16)) & 1) << 4) as u8),
+                        );
+                    }
+                    _ => values.extend_from_slice(&block[2..34]),
+                }
+            }
+            let (data_type, zero_point) = match ggml_type {
+                GgmlType::Q4_0 => (MPSDataType::UInt8, 8),
+                GgmlType::Q5_0 => (MPSDataType::UInt8, 16),
+                _ => (MPSDataType::Int8, 0),
+            };
+            Ok(Unpacked::Affine {
+                values,
+                data_type,
+                scales,
+                zero_point,
+                block_size: 32,
+            })
+        }
+        GgmlType::Q6_K => {
+            // Sixteen sub-blocks of 16 elements, each with its own scale
+            let mut values = Vec::with_capacity(count);
+            let mut scales = Vec::with_capacity(blocks.len() * 16);
+            for block in blocks {
+                let (ql, qh) = (&block[..128], &block[128..192]);
+                let d = f16(&block[208..]);
+                scales.extend(block[192..208].iter().map(|&sc| d * (sc as i8) as f32));
+                for half in 0..2 {
+                    let (ql, qh) = (&ql[64 * half..], &qh[32 * half..]);
+                    let mut y = [0u8; 128];
+                    for l in 0..32 {
+                        y[l] = (ql[l] & 0x0f) | ((qh[l] & 3) << 4);
+                        y[l + 32] = (ql[l + 32] & 0x0f) | (((qh[l] >> 2) & 3) << 4);
+                        y[l + 64] = (ql[l] >> 4) | (((qh[l] >> 4) & 3) << 4);
+                        y[l + 96] = (ql[l + 32] >> 4) | (((qh[l] >> 6) & 3) << 4);
+                    }
+                    values.extend_from_slice(&y);
+                }
+            }
+            Ok(Unpacked::Affine {
+                values,
+                data_type: MPSDataType::UInt8,
+                scales,
+                zero_point: 32,
+                block_size: 16,
+            })
+        }
+        GgmlType::Q4_1 | GgmlType::Q4_K => {
+            // Every 32 elements share one table, so both types use 32-element blocks
+            let mut indices = Vec::with_capacity(count / 2);
+            let mut lut = Vec::with_capacity(count / 32 * LUT_ENTRIES);
+            let mut push = |nibbles: &[u8], table: [f32; LUT_ENTRIES]| {
+                indices.extend(nibbles.chunks_exact(2).map(|pair| pair[0] | (pair[1] << 4)));
+                lut.extend_from_slice(&table);
+            };
+            for block in blocks {
+                let (d, min) = (f16(block), f16(&block[2..]));
+                if ggml_type == GgmlType::Q4_1 {
+                    let qs = &block[4..20];
+                    let nibbles: Vec<u8> = qs
+                        .iter()
+                        .map(|q| q & 0x0f)
+                        .chain(qs.iter().map(|q| q >> 4))
+                        .collect();
+                    push(&nibbles, std::array::from_fn(|i| i as f32 * d + min));
+                    continue;
+                }
+                let scales = &block[4..16];
+                for (chunk, qs) in block[16..144].chunks_exact(32).enumerate() {
+                    for (sub, shift) in [(2 * chunk, 0), (2 * chunk + 1, 4)] {
+                        let (sc, m) = scale_min_k4(sub, scales);
+                        let (d1, m1) = (d * sc as f32, min * m as f32);
+                        let nibbles: Vec<u8> = qs.iter().map(|q| (q >> shift) & 0x0f).collect();
+                        push(&nibbles, std::array::from_fn(|i| d1 * i as f32 - m1));
+                    }
+                }
+            }
+            Ok(Unpacked::Lut {
+                indices,
+                lut,
+                block_size: 32,
+            })
+        }
+        other => Err(GgufError::UnsupportedType(other)),
+    }
+}
+
+enum Storage {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Storage {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Storage::Mapped(map) => map,
+            Storage::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// Little-endian cursor over the header of a GGUF file
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| GgufError::Format("unexpected end of file".into()))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    /// Reads a count and checks that at least `min_size` bytes per item remain
+    fn count(&mut self, min_size: usize) -> Result<usize> {
+        let count = self.u64()?;
+        let remaining = (self.bytes.len() - self.position) as u64;
+        if count.saturating_mul(min_size as u64) > remaining {
+            return Err(GgufError::Format(format!(
+                "count {} exceeds the remaining {} bytes",
+                count, remaining
+            )));
+        }
+        Ok(count as usize)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let length = self.count(1)?;
+        String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| GgufError::Format("string is not valid UTF-8".into()))
+    }
+
+    fn value(&mut self, type_code: u32) -> Result<GgufValue> {
+        Ok(match type_code {
+            0 => GgufValue::U8(self.array::<1>()?[0]),
+            1 => GgufValue::I8(self.array::<1>()?[0] as i8),
+            2 => GgufValue::U16(u16::from_le_bytes(self.array()?)),
+            3 => GgufValue::I16(i16::from_le_bytes(self.array()?)),
+            4 => GgufValue::U32(self.u32()?),
+            5 => GgufValue::I32(i32::from_le_bytes(self.array()?)),
+            6 => GgufValue::F32(f32::from_le_bytes(self.array()?)),
+            7 => match self.array::<1>()?[0] {
+                0 => GgufValue::Bool(false),
+                1 => GgufValue::Bool(true),
+                other => return Err(GgufError::Format(format!("invalid bool {}", other))),
+            },
+            8 => GgufValue::String(self.string()?),
+            9 => {
+                let element_type = self.u32()?;
+                if element_type == 9 {
+                    return Err(GgufError::Format("nested arrays are not supported".into()));
+                }
+                let count = self.count(1)?;
+                let values = (0..count)
+                    .map(|_| self.value(element_type))
+                    .collect::<Result<_>>()?;
+                GgufValue::Array(values)
+            }
+            10 => GgufValue::U64(self.u64()?),
+            11 => GgufValue::I64(i64::from_le_bytes(self.array()?)),
+            12 => GgufValue::F64(f64::from_le_bytes(self.array()?)),
+            other => return Err(GgufError::Format(format!("unknown value type {}", other))),
+        })
+    }
+}
+
+/// A parsed GGUF file
+///
+/// The tensor data stays in the mapped or owned buffer; [`GgufFile::tensor`] borrows it.
+pub struct GgufFile {
+    storage: Storage,
+    version: u32,
+    metadata: Vec<(String, GgufValue)>,
+    tensors: Vec<GgufTensorInfo>,
+    data_offset: usize,
+}
+
+impl GgufFile {
+    /// Memory-maps and parses a GGUF file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the map is read-only and kept alive alongside the tensors borrowing it.
+        // Like every mmap reader we rely on the file not being truncated while it's open.
+        let map = unsafe { Mmap::map(&file)? };
+        Self::parse(Storage::Mapped(map))
+    }
+
+    /// Parses a GGUF file held in memory
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        Self::parse(Storage::Owned(bytes))
+    }
+
+    fn parse(storage: Storage) -> Result<Self> {
+        let mut reader = Reader {
+            bytes: storage.bytes(),
+            position: 0,
+        };
+        if reader.take(4).ok() != Some(&MAGIC[..]) {
+            return Err(GgufError::Format("missing GGUF magic".into()));
+        }
+        let version = reader.u32()?;
+        if !(2..=3).contains(&version) {
+            return Err(GgufError::UnsupportedVersion(version));
+        }
+        // A tensor info needs at least 24 bytes and a key-value pair 13
+        let tensor_count = reader.count(24)?;
+        let kv_count = reader.count(13)?;
+
+        let mut metadata = Vec::with_capacity(kv_count);
+        for _ in 0..kv_count {
+            let key = reader.string()?;
+            let type_code = reader.u32()?;
+            metadata.push((key, reader.value(type_code)?));
+        }
+
+        let mut tensors = Vec::with_capacity(tensor_count);
+        for _ in 0..tensor_count {
+            let name = reader.string()?;
+            let rank = reader.u32()? as usize;
+            if rank > 8 {
+                return Err(GgufError::Format(format!(
+                    "tensor '{}' has {} dimensions",
+                    name, rank
+                )));
+            }
+            let mut shape = (0..rank)
+                .map(|_| reader.u64().map(|size| size as usize))
+                .collect::<Result<Vec<_>>>()?;
+            shape.reverse();
+            let ggml_type = GgmlType::try_from(reader.u32()?)?;
+            let offset = reader.u64()?;
+            tensors.push(GgufTensorInfo {
+                name,
+                shape,
+                ggml_type,
+                offset,
+            });
+        }
+
+        let alignment = alignment(&metadata)?;
+        let data_offset = reader.position.next_multiple_of(alignment);
+        let position = reader.position;
+        let file = GgufFile {
+            storage,
+            version,
+            metadata,
+            tensors,
+            data_offset,
+        };
+        if data_offset > file.storage.bytes().len() && !file.tensors.is_empty() {
+            return Err(GgufError::Format(format!(
+                "data section at {} is past the end of the file",
+                position
+            )));
+        }
+        for info in &file.tensors {
+            if info.offset % alignment as u64 != 0 {
+                return Err(GgufError::Format(format!(
+                    "tensor '{}' is not aligned to {} bytes",
+                    info.name, alignment
+                )));
+            }
+            file.tensor_of(info)?;
+        }
+        Ok(file)
+    }
+
+    /// Returns true if the file is memory-mapped rather than held in an owned buffer
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.storage, Storage::Mapped(_))
+    }
+
+    /// Returns the GGUF version, 2 or 3
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the metadata key-value pairs in file order
+    pub fn metadata(&self) -> &[(String, GgufValue)] {
+        &self.metadata
+    }
+
+    /// Returns the metadata value for `key`
+    pub fn get(&self, key: &str) -> Option<&GgufValue> {
+        self.metadata
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the alignment of the tensor data
+    pub fn alignment(&self) -> usize {
+        alignment(&self.metadata).expect("validated on parse")
+    }
+
+    /// Returns the tensor infos in file order
+    pub fn tensors(&self) -> &[GgufTensorInfo] {
+        &self.tensors
+    }
+
+    /// Returns the tensor named `name`
+    pub fn tensor(&self, name: &str) -> Result<GgufTensor<'_>> {
+        let info = self
+            .tensors
+            .iter()
+            .find(|info| info.name == name)
+            .ok_or_else(|| GgufError::MissingTensor(name.to_string()))?;
+        self.tensor_of(info)
+    }
+
+    fn tensor_of<'a>(&'a self, info: &'a GgufTensorInfo) -> Result<GgufTensor<'a>> {
+        let count = info
+            .shape
+            .iter()
+            .try_fold(1usize, |count, &size| count.checked_mul(size));
+        let size = count.and_then(|count| info.ggml_type.size_in_bytes(count));
+        let bytes = self.storage.bytes();
+        let start = usize::try_from(info.offset)
+            .ok()
+            .and_then(|offset| offset.checked_add(self.data_offset));
+        let data = match (start, size) {
+            (Some(start), Some(size)) if start.saturating_add(size) <= bytes.len() => {
+                &bytes[start..start + size]
+            }
+            _ => {
+                return Err(GgufError::Format(format!(
+                    "data of tensor '{}' is out of bounds",
+                    info.name
+                )))
+            }
+        };
+        GgufTensor::new(info.ggml_type, &info.shape, data)
+    }
+
+    /// Records every tensor in the graph as a constant or dequantize operation
+    ///
+    /// Tensors are named after their GGUF names; see [`GgufTensor::load_into`].
+    #[cfg(target_vendor = "apple")]
+    pub fn load_into(
+        &self,
+        graph: &MPSGraph,
+        data_type: MPSDataType,
+    ) -> Result<HashMap<String, MPSGraphTensor>> {
+        self.tensors
+            .iter()
+            .map(|info| {
+                let tensor = self.tensor_of(info)?;
+                let loaded = tensor.load_into(graph, data_type, Some(&info.name))?;
+                Ok((info.name.clone(), loaded))
+            })
+            .collect()
+    }
+}
+
+fn alignment(metadata: &[(String, GgufValue)]) -> Result<usize> {
+    let Some((_, value)) = metadata.iter().find(|(key, _)| key == "general.alignment") else {
+        return Ok(DEFAULT_ALIGNMENT);
+    };
+    match value.as_i64() {
+        Some(alignment) if alignment > 0 && (alignment as u64).is_power_of_two() => {
+            Ok(alignment as usize)
+        }
+        _ => Err(GgufError::Format(format!(
+            "general.alignment {:?} is not a power of two",
+            value
+        ))),
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn write_value(out: &mut Vec<u8>, value: &GgufValue) -> Result<()> {
+    match value {
+        GgufValue::U8(v) => out.push(*v),
+        GgufValue::I8(v) => out.push(*v as u8),
+        GgufValue::U16(v) => out.extend_from_slice(&v.to_le_bytes()),
+        GgufValue::I16(v) => out.extend_from_slice(&v.to_le_bytes()),
+        GgufValue::U32(v) => out.extend_from_slice(&v.to_le_bytes()),
+        GgufValue::I32(v) => out.extend_from_slice(&v.to_le_bytes()),
+        GgufValue::F32(v) => out.extend_from_slice(&v.to_le_bytes()),
+        GgufValue::Bool(v) => out.push(*v as u8),
+        GgufValue::String(v) => write_string(out, v),
+        GgufValue::Array(values) => {
+            let element_type = values.first().map_or(0, GgufValue::type_code);
+            if element_type == 9 || values.iter().any(|v| v.type_code() != element_type) {
+                return Err(GgufError::Format(
+                    "array elements must share one non-array type".into(),
+                ));
+            }
+            out.extend_from_slice(&element_type.to_le_bytes());
+            out.extend_from_slice(&(values.len() as u64).to_le_bytes());
+            for value in values {
+                write_value(out, value)?;
+            }
+        }
+        GgufValue::U64(v) => out.extend_from_slice(&v.to_le_bytes()),
+        GgufValue::I64(v) => out.extend_from_slice(&v.to_le_bytes()),
+        GgufValue::F64(v) => out.extend_from_slice(&v.to_le_bytes()),
+    }
+    Ok(())
+}
+
+/// Serializes metadata and tensors into a version 3 GGUF file
+///
+/// Tensor data is aligned to `general.alignment` if the metadata sets it, and to
+/// [`DEFAULT_ALIGNMENT`] otherwise.
+pub fn serialize(
+    metadata: &[(String, GgufValue)],
+    tensors: &[(&str, GgufTensor<'_>)],
+) -> Result<Vec<u8>> {
+    let alignment = alignment(metadata)?;
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&3u32.to_le_bytes());
+    out.extend_from_slice(&(tensors.len() as u64).to_le_bytes());
+    out.extend_from_slice(&(metadata.len() as u64).to_le_bytes());
+    for (key, value) in metadata {
+        write_string(&mut out, key);
+        out.extend_from_slice(&value.type_code().to_le_bytes());
+        write_value(&mut out, value)?;
+    }
+
+    let mut offset = 0;
+    for (name, tensor) in tensors {
+        write_string(&mut out, name);
+        out.extend_from_slice(&(tensor.shape.len() as u32).to_le_bytes());
+        for &size in tensor.shape.iter().rev() {
+            out.extend_from_slice(&(size as u64).to_le_bytes());
+        }
+        out.extend_from_slice(&(tensor.ggml_type as u32).to_le_bytes());
+        out.extend_from_slice(&(offset as u64).to_le_bytes());
+        offset = (offset + tensor.data.len()).next_multiple_of(alignment);
+    }
+
+    for (_, tensor) in tensors {
+        out.resize(out.len().next_multiple_of(alignment), 0);
+        out.extend_from_slice(tensor.data);
+    }
+    Ok(out)
+}
+
+/// Writes metadata and tensors to a version 3 GGUF file; see [`serialize`]
+pub fn save<P: AsRef<Path>>(
+    path: P,
+    metadata: &[(String, GgufValue)],
+    tensors: &[(&str, GgufTensor<'_>)],
+) -> Result<()> {
+    std::fs::write(path, serialize(metadata, tensors)?)?;
+    Ok(())
+}
@@ -3,6 +3,7 @@
 use crate::command_buffer::MPSCommandBuffer;
 use crate::core::{AsRawObject, MPSDataType, MPSGraphOptions};
 use crate::device::MPSGraphDevice;
+use crate::error::{FeedProblem, MPSGraphError, Result};
 use crate::executable::{
     MPSGraphCompilationDescriptor, MPSGraphExecutable, MPSGraphExecutionDescriptor,
 };
@@ -17,7 +18,7 @@ use objc2::rc::Retained;
 use objc2::runtime::AnyObject;
 use objc2_foundation::NSData;
 use objc2_foundation::NSString;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
 /// Trait for scalar types that can be used in MPSGraph operations
@@ -121,6 +122,81 @@ impl MPSTensorDataScalar for u64 {
     }
 }
 
+macro_rules! impl_tensor_data_scalar {
+    ($($ty:ty),*) => {
+        $(
+            impl MPSTensorDataScalar for $ty {
+                fn to_f64(&self) -> f64 {
+                    *self as f64
+                }
+
+                fn to_nsdata(values: &[Self]) -> Retained<NSData> {
+                    unsafe {
+                        NSData::with_bytes(std::slice::from_raw_parts(
+                            values.as_ptr() as *const u8,
+                            std::mem::size_of_val(values),
+                        ))
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_tensor_data_scalar!(i8, u8, i16, u16);
+
+impl MPSTensorDataScalar for bool {
+    fn to_f64(&self) -> f64 {
+        if *self {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    fn to_nsdata(values: &[Self]) -> Retained<NSData> {
+        // Rust guarantees bools are one byte holding 0 or 1, which is MPSDataType::Bool
+        unsafe {
+            NSData::with_bytes(std::slice::from_raw_parts(
+                values.as_ptr() as *const u8,
+                values.len(),
+            ))
+        }
+    }
+}
+
+#[cfg(feature = "half")]
+impl MPSTensorDataScalar for half::f16 {
+    fn to_f64(&self) -> f64 {
+        f64::from(*self)
+    }
+
+    fn to_nsdata(values: &[Self]) -> Retained<NSData> {
+        unsafe {
+            NSData::with_bytes(std::slice::from_raw_parts(
+                values.as_ptr() as *const u8,
+                std::mem::size_of_val(values),
+            ))
+        }
+    }
+}
+
+#[cfg(feature = "half")]
+impl MPSTensorDataScalar for half::bf16 {
+    fn to_f64(&self) -> f64 {
+        f64::from(*self)
+    }
+
+    fn to_nsdata(values: &[Self]) -> Retained<NSData> {
+        unsafe {
+            NSData::with_bytes(std::slice::from_raw_parts(
+                values.as_ptr() as *const u8,
+                std::mem::size_of_val(values),
+            ))
+        }
+    }
+}
+
 #[link(name = "MetalPerformanceShadersGraph", kind = "framework")]
 extern "C" {
     #[allow(dead_code)]
@@ -149,10 +225,22 @@ impl MPSGraph {
             let cls = objc2::runtime::AnyClass::get(class_name).unwrap();
             let obj: *mut AnyObject = msg_send![cls, alloc];
             let graph: *mut AnyObject = msg_send![obj, init];
-            MPSGraph(graph)
+            MPSGraph::from_retained(graph)
         }
     }
 
+    /// Wraps a graph the caller already holds a +1 retain on, taking over that retain
+    ///
+    /// # Safety
+    ///
+    /// `graph` must be null or point to a live `MPSGraph` retained by the caller.
+    pub(crate) unsafe fn from_retained(graph: *mut AnyObject) -> Self {
+        if !graph.is_null() {
+            tensor_names::hold(graph);
+        }
+        MPSGraph(graph)
+    }
+
     /// Sets the options for this graph
     pub fn set_options(&self, options: MPSGraphOptions) {
         unsafe {
@@ -190,7 +278,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -231,7 +319,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -249,7 +337,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -269,7 +357,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -301,7 +389,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -333,7 +421,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -1383,9 +1471,10 @@ impl MPSGraph {
 
                     println!("DEBUG: Got value: {:p}", value);
 
-                    // Create Rust wrappers WITHOUT retaining the objects, since we're in an autorelease pool
-                    // and we've adjusted the tensor implementation to not release
-                    let tensor = MPSGraphTensor(key);
+                    // The tensor wrapper releases its object when dropped, so retain the key.
+                    // Tensor data still doesn't release, so its value is wrapped as is
+                    let tensor =
+                        MPSGraphTensor::from_retained(objc2::ffi::objc_retain(key as *mut _));
                     let tensor_data = MPSGraphTensorData(value);
 
                     println!(
@@ -1408,6 +1497,336 @@ impl MPSGraph {
     }
 }
 
+// Result-returning variants of the compile, run and encode entry points
+impl MPSGraph {
+    /// Compiles the graph against a given set of feeds and targets
+    ///
+    /// Unlike [`MPSGraph::compile`], the feeds are checked with [`MPSGraph::validate_feeds`]
+    /// first and a missing executable is reported as [`MPSGraphError::CompilationFailed`].
+    /// `compileWithDevice:feeds:targetTensors:targetOperations:compilationDescriptor:` has no
+    /// `NSError` out-parameter, so that error carries a fixed reason rather than MPSGraph's.
+    pub fn try_compile(
+        &self,
+        device: &MPSGraphDevice,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+        descriptor: Option<&MPSGraphCompilationDescriptor>,
+    ) -> Result<MPSGraphExecutable> {
+        self.validate_feeds(feeds, targets)?;
+        checked_executable(self.compile(device, feeds, targets, descriptor))
+    }
+
+    /// Compiles the graph against a given set of feeds, targets, and target operations
+    pub fn try_compile_with_targets_and_ops(
+        &self,
+        device: &MPSGraphDevice,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+        target_ops: &[MPSGraphOperation],
+        descriptor: Option<&MPSGraphCompilationDescriptor>,
+    ) -> Result<MPSGraphExecutable> {
+        self.validate_feeds_with_ops(feeds, targets, target_ops)?;
+        checked_executable(
+            self.compile_with_targets_and_ops(device, feeds, targets, target_ops, descriptor),
+        )
+    }
+
+    /// Runs the graph synchronously, returning an error instead of an incomplete result dictionary
+    pub fn try_run_with_feeds(
+        &self,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+    ) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        self.validate_feeds(feeds, targets)?;
+        checked_results(self.run_with_feeds(feeds, targets), targets)
+    }
+
+    /// Runs the graph synchronously with target operations specified
+    pub fn try_run_with_feeds_and_ops(
+        &self,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+        target_ops: &[MPSGraphOperation],
+    ) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        self.validate_feeds_with_ops(feeds, targets, target_ops)?;
+        checked_results(
+            self.run_with_feeds_and_ops(feeds, targets, target_ops),
+            targets,
+        )
+    }
+
+    /// Runs the graph synchronously against a given device
+    pub fn try_run_with_feeds_on_device(
+        &self,
+        device: &MPSGraphDevice,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+    ) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        self.validate_feeds(feeds, targets)?;
+        checked_results(
+            self.run_with_feeds_on_device(device, feeds, targets),
+            targets,
+        )
+    }
+
+    /// Runs the graph synchronously on a device with both target tensors and operations
+    pub fn try_run_with_feeds_and_ops_on_device(
+        &self,
+        device: &MPSGraphDevice,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+        target_ops: &[MPSGraphOperation],
+    ) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        self.validate_feeds_with_ops(feeds, targets, target_ops)?;
+        checked_results(
+            self.run_with_feeds_and_ops_on_device(device, feeds, targets, target_ops),
+            targets,
+        )
+    }
+
+    /// Runs the graph asynchronously with feeds and returns the target tensor values
+    pub fn try_run_async_with_feeds(
+        &self,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        target_tensors: &[MPSGraphTensor],
+        target_operations: Option<&[MPSGraphOperation]>,
+        execution_descriptor: Option<&MPSGraphExecutionDescriptor>,
+    ) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        self.validate_feeds_with_ops(feeds, target_tensors, target_operations.unwrap_or(&[]))?;
+        checked_results(
+            self.run_async_with_feeds(
+                feeds,
+                target_tensors,
+                target_operations,
+                execution_descriptor,
+            ),
+            target_tensors,
+        )
+    }
+
+    /// Runs the graph asynchronously on a command queue
+    pub fn try_run_async_with_command_queue(
+        &self,
+        command_queue: &CommandQueue,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        target_tensors: &[MPSGraphTensor],
+        target_operations: Option<&[MPSGraphOperation]>,
+        execution_descriptor: Option<&MPSGraphExecutionDescriptor>,
+    ) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        self.validate_feeds_with_ops(feeds, target_tensors, target_operations.unwrap_or(&[]))?;
+        checked_results(
+            self.run_async_with_command_queue(
+                command_queue,
+                feeds,
+                target_tensors,
+                target_operations,
+                execution_descriptor,
+            ),
+            target_tensors,
+        )
+    }
+
+    /// Runs the graph asynchronously with a command queue and results dictionary
+    pub fn try_run_async_with_command_queue_results_dict(
+        &self,
+        command_queue: &CommandQueue,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        target_operations: Option<&[MPSGraphOperation]>,
+        results_dict: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        execution_descriptor: Option<&MPSGraphExecutionDescriptor>,
+    ) -> Result<()> {
+        validate_results_dict(results_dict)?;
+        let targets: Vec<MPSGraphTensor> = results_dict.keys().cloned().collect();
+        self.validate_feeds_with_ops(feeds, &targets, target_operations.unwrap_or(&[]))?;
+        self.run_async_with_command_queue_results_dict(
+            command_queue,
+            feeds,
+            target_operations,
+            results_dict,
+            execution_descriptor,
+        );
+        Ok(())
+    }
+
+    /// Runs the graph with a command queue, feeds and outputs specified
+    pub fn try_run_with_command_queue_feeds_outputs(
+        &self,
+        command_queue: &CommandQueue,
+        feeds: HashMap<&MPSGraphTensor, &MPSGraphTensorData>,
+        results_dict: HashMap<&MPSGraphTensor, &MPSGraphTensorData>,
+        execution_descriptor: Option<&MPSGraphExecutionDescriptor>,
+    ) -> Result<()> {
+        for (tensor, data) in results_dict.iter() {
+            validate_result_entry(tensor, data)?;
+        }
+        let owned_feeds: HashMap<MPSGraphTensor, MPSGraphTensorData> = feeds
+            .iter()
+            .map(|(&tensor, &data)| (tensor.clone(), data.clone()))
+            .collect();
+        let targets: Vec<MPSGraphTensor> = results_dict.keys().map(|&t| t.clone()).collect();
+        self.validate_feeds(&owned_feeds, &targets)?;
+        self.run_with_command_queue_feeds_outputs(
+            command_queue,
+            feeds,
+            results_dict,
+            execution_descriptor,
+        );
+        Ok(())
+    }
+
+    /// Encodes the graph to a Metal command buffer for execution
+    ///
+    /// Errors raised while the command buffer executes are reported by
+    /// [`MPSCommandBuffer::try_wait_until_completed`].
+    pub fn try_encode_to_metal_command_buffer(
+        &self,
+        command_buffer: &CommandBuffer,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        target_tensors: &[MPSGraphTensor],
+        target_operations: Option<&[MPSGraphOperation]>,
+        execution_descriptor: Option<&MPSGraphExecutionDescriptor>,
+    ) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        let mps_command_buffer = MPSCommandBuffer::from_command_buffer(command_buffer);
+        self.try_encode_to_command_buffer(
+            &mps_command_buffer,
+            feeds,
+            target_tensors,
+            target_operations,
+            execution_descriptor,
+        )
+    }
+
+    /// Encodes the graph to a Metal command buffer with a results dictionary
+    pub fn try_encode_to_metal_command_buffer_with_results(
+        &self,
+        command_buffer: &CommandBuffer,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        target_operations: Option<&[MPSGraphOperation]>,
+        results_dict: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        execution_descriptor: Option<&MPSGraphExecutionDescriptor>,
+    ) -> Result<()> {
+        let mps_command_buffer = MPSCommandBuffer::from_command_buffer(command_buffer);
+        self.try_encode_to_command_buffer_with_results(
+            &mps_command_buffer,
+            feeds,
+            target_operations,
+            results_dict,
+            execution_descriptor,
+        )
+    }
+
+    /// Encodes the graph to a MPSCommandBuffer for execution
+    ///
+    /// Errors raised while the command buffer executes are reported by
+    /// [`MPSCommandBuffer::try_wait_until_completed`].
+    pub fn try_encode_to_command_buffer(
+        &self,
+        command_buffer: &MPSCommandBuffer,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        target_tensors: &[MPSGraphTensor],
+        target_operations: Option<&[MPSGraphOperation]>,
+        execution_descriptor: Option<&MPSGraphExecutionDescriptor>,
+    ) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        self.validate_feeds_with_ops(feeds, target_tensors, target_operations.unwrap_or(&[]))?;
+        checked_results(
+            self.encode_to_command_buffer(
+                command_buffer,
+                feeds,
+                target_tensors,
+                target_operations,
+                execution_descriptor,
+            ),
+            target_tensors,
+        )
+    }
+
+    /// Encodes the graph to a MPSCommandBuffer with a results dictionary
+    pub fn try_encode_to_command_buffer_with_results(
+        &self,
+        command_buffer: &MPSCommandBuffer,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        target_operations: Option<&[MPSGraphOperation]>,
+        results_dict: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        execution_descriptor: Option<&MPSGraphExecutionDescriptor>,
+    ) -> Result<()> {
+        validate_results_dict(results_dict)?;
+        let targets: Vec<MPSGraphTensor> = results_dict.keys().cloned().collect();
+        self.validate_feeds_with_ops(feeds, &targets, target_operations.unwrap_or(&[]))?;
+        self.encode_to_command_buffer_with_results(
+            command_buffer,
+            feeds,
+            target_operations,
+            results_dict,
+            execution_descriptor,
+        );
+        Ok(())
+    }
+
+    /// Enqueues a graph run on a command queue
+    pub fn try_encode_to_command_queue(
+        &self,
+        device: &MPSGraphDevice,
+        command_queue: &CommandQueue,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+        execution_descriptor: Option<&MPSGraphExecutionDescriptor>,
+    ) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        self.validate_feeds(feeds, targets)?;
+        checked_results(
+            self.encode_to_command_queue(
+                device,
+                command_queue,
+                feeds,
+                targets,
+                execution_descriptor,
+            ),
+            targets,
+        )
+    }
+
+    /// Encodes and runs a graph on a command buffer (legacy method)
+    pub fn try_encode_to_command_buffer_legacy(
+        &self,
+        command_buffer: &CommandBuffer,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+        execution_descriptor: Option<&MPSGraphExecutionDescriptor>,
+    ) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        self.validate_feeds(feeds, targets)?;
+        checked_results(
+            self.encode_to_command_buffer_legacy(
+                command_buffer,
+                feeds,
+                targets,
+                execution_descriptor,
+            ),
+            targets,
+        )
+    }
+
+    /// Encodes and runs a graph on a command buffer with a completed event
+    pub fn try_encode_to_command_buffer_with_event(
+        &self,
+        command_buffer: &CommandBuffer,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+        execution_descriptor: Option<&MPSGraphExecutionDescriptor>,
+        event: Option<&SharedEvent>,
+    ) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+        self.validate_feeds(feeds, targets)?;
+        checked_results(
+            self.encode_to_command_buffer_with_event(
+                command_buffer,
+                feeds,
+                targets,
+                execution_descriptor,
+                event,
+            ),
+            targets,
+        )
+    }
+}
+
 // Concatenation operations extension
 impl MPSGraph {
     /// Creates a concatenation operation
@@ -1436,7 +1855,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 }
@@ -1468,7 +1887,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 }
@@ -1478,6 +1897,7 @@ impl Drop for MPSGraph {
         unsafe {
             // Convert to NSObject and release
             if !self.0.is_null() {
+                tensor_names::release(self.0);
                 objc2::ffi::objc_release(self.0 as *mut _);
             }
         }
@@ -1490,7 +1910,7 @@ impl Clone for MPSGraph {
             // Retain and return new instance
             if !self.0.is_null() {
                 let obj = objc2::ffi::objc_retain(self.0 as *mut _);
-                MPSGraph(obj)
+                MPSGraph::from_retained(obj)
             } else {
                 MPSGraph(std::ptr::null_mut::<AnyObject>())
             }
@@ -1498,6 +1918,204 @@ impl Clone for MPSGraph {
     }
 }
 
+/// A retained reference to the graph a tensor or operation handle points into
+///
+/// MPSGraph doesn't keep a graph alive for the tensors and operations inside it, so every
+/// handle holds one of these. That way a tensor cloned out of a graph can't outlive it.
+pub(crate) struct GraphOwner(*mut AnyObject);
+
+impl GraphOwner {
+    /// Retains the graph that owns `operation`, or holds nothing for a null operation
+    ///
+    /// # Safety
+    ///
+    /// `operation` must be null or point to a live `MPSGraphOperation`.
+    pub(crate) unsafe fn of_operation(operation: *mut AnyObject) -> Self {
+        if operation.is_null() {
+            return GraphOwner(std::ptr::null_mut());
+        }
+        let graph: *mut AnyObject = msg_send![operation, graph];
+        Self::retain(graph)
+    }
+
+    /// Retains the graph that owns `tensor`, or holds nothing for a null tensor
+    ///
+    /// # Safety
+    ///
+    /// `tensor` must be null or point to a live `MPSGraphTensor`.
+    pub(crate) unsafe fn of_tensor(tensor: *mut AnyObject) -> Self {
+        if tensor.is_null() {
+            return GraphOwner(std::ptr::null_mut());
+        }
+        let operation: *mut AnyObject = msg_send![tensor, operation];
+        Self::of_operation(operation)
+    }
+
+    unsafe fn retain(graph: *mut AnyObject) -> Self {
+        if !graph.is_null() {
+            objc2::ffi::objc_retain(graph as *mut _);
+            tensor_names::hold(graph);
+            #[cfg(any(test, feature = "leak-tracking"))]
+            leak_tracking::track(graph);
+        }
+        GraphOwner(graph)
+    }
+
+    /// Adds `tensor` to its graph's name index
+    pub(crate) fn record_tensor(&self, tensor: *mut AnyObject) {
+        if !self.0.is_null() && !tensor.is_null() {
+            tensor_names::record(self.0, tensor);
+        }
+    }
+}
+
+/// Returns the name the operation producing `tensor` was created with
+///
+/// # Safety
+///
+/// `tensor` must point to a live `MPSGraphTensor`.
+pub(crate) unsafe fn operation_name(tensor: *mut AnyObject) -> Option<String> {
+    let operation: *mut AnyObject = msg_send![tensor, operation];
+    if operation.is_null() {
+        return None;
+    }
+    let name: *mut AnyObject = msg_send![operation, name];
+    if name.is_null() {
+        return None;
+    }
+    let utf8: *const std::ffi::c_char = msg_send![name, UTF8String];
+    (!utf8.is_null()).then(|| {
+        std::ffi::CStr::from_ptr(utf8)
+            .to_string_lossy()
+            .into_owned()
+    })
+}
+
+impl Clone for GraphOwner {
+    fn clone(&self) -> Self {
+        unsafe { Self::retain(self.0) }
+    }
+}
+
+impl Drop for GraphOwner {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            #[cfg(any(test, feature = "leak-tracking"))]
+            leak_tracking::untrack(self.0);
+            tensor_names::release(self.0);
+            unsafe { objc2::ffi::objc_release(self.0 as *mut _) };
+        }
+    }
+}
+
+/// Counts live tensor and operation handles per graph
+#[cfg(any(test, feature = "leak-tracking"))]
+mod leak_tracking {
+    use objc2::runtime::AnyObject;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    static LIVE_HANDLES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+    pub(super) fn track(graph: *mut AnyObject) {
+        let mut live = LIVE_HANDLES.lock().unwrap_or_else(|e| e.into_inner());
+        *live.entry(graph as usize).or_insert(0) += 1;
+    }
+
+    pub(super) fn untrack(graph: *mut AnyObject) {
+        let mut live = LIVE_HANDLES.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = live.get_mut(&(graph as usize)) {
+            *count -= 1;
+            if *count == 0 {
+                live.remove(&(graph as usize));
+            }
+        }
+    }
+
+    pub(super) fn live(graph: *mut AnyObject) -> usize {
+        let live = LIVE_HANDLES.lock().unwrap_or_else(|e| e.into_inner());
+        live.get(&(graph as usize)).copied().unwrap_or(0)
+    }
+}
+
+/// Tensors of each graph that handles were created for, by graph address
+///
+/// MPSGraph lists its placeholders but no other tensors, so lookups by name go through this
+/// index. Every tensor handle adds its tensor when it's created, and names are only read
+/// when a lookup needs them. A graph owns its tensors, so the stored addresses stay valid
+/// for as long as the graph does. Every [`MPSGraph`] and [`GraphOwner`] counts as a holder
+/// of its graph, and the graph's entry is dropped together with its last holder.
+pub(crate) mod tensor_names {
+    use super::operation_name;
+    use objc2::runtime::AnyObject;
+    use std::collections::{BTreeMap, HashSet};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Entry {
+        holders: usize,
+        tensors: Vec<usize>,
+        seen: HashSet<usize>,
+    }
+
+    static INDEX: Mutex<BTreeMap<usize, Entry>> = Mutex::new(BTreeMap::new());
+
+    pub(super) fn hold(graph: *mut AnyObject) {
+        let mut index = INDEX.lock().unwrap_or_else(|e| e.into_inner());
+        index.entry(graph as usize).or_default().holders += 1;
+    }
+
+    pub(super) fn release(graph: *mut AnyObject) {
+        let mut index = INDEX.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = index.get_mut(&(graph as usize)) {
+            entry.holders -= 1;
+            if entry.holders == 0 {
+                index.remove(&(graph as usize));
+            }
+        }
+    }
+
+    pub(super) fn record(graph: *mut AnyObject, tensor: *mut AnyObject) {
+        let mut index = INDEX.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = index.get_mut(&(graph as usize)) {
+            if entry.seen.insert(tensor as usize) {
+                entry.tensors.push(tensor as usize);
+            }
+        }
+    }
+
+    /// Returns the tensors of `graph` named `name`, in creation order
+    ///
+    /// # Safety
+    ///
+    /// `graph` must point to a live `MPSGraph`.
+    pub(crate) unsafe fn lookup(graph: *mut AnyObject, name: &str) -> Vec<*mut AnyObject> {
+        let tensors = {
+            let index = INDEX.lock().unwrap_or_else(|e| e.into_inner());
+            index
+                .get(&(graph as usize))
+                .map(|entry| entry.tensors.clone())
+                .unwrap_or_default()
+        };
+        tensors
+            .into_iter()
+            .map(|tensor| tensor as *mut AnyObject)
+            .filter(|&tensor| operation_name(tensor).as_deref() == Some(name))
+            .collect()
+    }
+}
+
+#[cfg(any(test, feature = "leak-tracking"))]
+impl MPSGraph {
+    /// Returns how many tensor and operation handles into this graph are still alive
+    ///
+    /// Only available in tests and with the `leak-tracking` feature. Each handle holds
+    /// exactly one retain on its object, so zero means nothing built from this graph leaked.
+    pub fn live_handles(&self) -> usize {
+        leak_tracking::live(self.0)
+    }
+}
+
 impl fmt::Debug for MPSGraph {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("MPSGraph").finish()
@@ -1510,3 +2128,157 @@ fn create_dimensions(dimensions: &[usize]) -> *mut AnyObject {
     let shape = MPSShape::from_slice(dimensions);
     shape.0
 }
+
+impl MPSGraph {
+    /// Checks `feeds` against what `targets` need before running or compiling
+    ///
+    /// Walks back from the targets, stopping at fed tensors, and reports every problem at
+    /// once as [`MPSGraphError::InvalidFeeds`]: placeholders that are reached but not fed,
+    /// feeds whose data type or shape doesn't fit the tensor (dynamic dimensions accept any
+    /// size), and feeds the targets don't depend on.
+    pub fn validate_feeds(
+        &self,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+    ) -> Result<()> {
+        self.validate_feeds_with_ops(feeds, targets, &[])
+    }
+
+    /// Like [`MPSGraph::validate_feeds`], also walking back from `target_ops`
+    pub(crate) fn validate_feeds_with_ops(
+        &self,
+        feeds: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+        targets: &[MPSGraphTensor],
+        target_ops: &[MPSGraphOperation],
+    ) -> Result<()> {
+        let mut problems = Vec::new();
+        let mut used = HashSet::new();
+        let mut visited = HashSet::new();
+        // Keeps the visited operations retained so their addresses stay unique
+        let mut retained = Vec::new();
+        let mut tensors: Vec<MPSGraphTensor> = targets.to_vec();
+        let mut operations: Vec<MPSGraphOperation> = target_ops.to_vec();
+        loop {
+            if let Some(tensor) = tensors.pop() {
+                if feeds.contains_key(&tensor) {
+                    used.insert(tensor.0 as usize);
+                } else {
+                    operations.push(tensor.operation());
+                }
+                continue;
+            }
+            let Some(operation) = operations.pop() else {
+                break;
+            };
+            if !visited.insert(operation.0 as usize) {
+                continue;
+            }
+            let class = unsafe { (*operation.0).class().name() };
+            if class == c"MPSGraphPlaceholderOp" {
+                for output in operation.output_tensors() {
+                    problems.push(FeedProblem::Missing {
+                        tensor: output.name(),
+                    });
+                }
+            }
+            tensors.extend(operation.input_tensors());
+            operations.extend(operation.control_dependencies());
+            retained.push(operation);
+        }
+
+        let mut feed_problems = Vec::new();
+        for (tensor, data) in feeds {
+            if data.0.is_null() || tensor.0.is_null() || used.contains(&(tensor.0 as usize)) {
+                feed_problems.extend(check_feed(tensor, data)?);
+            } else {
+                feed_problems.push(FeedProblem::Unused {
+                    tensor: tensor.name(),
+                });
+            }
+        }
+        feed_problems_result(problems, feed_problems)
+    }
+}
+
+/// Compares a feed with the tensor it feeds, reporting a null tensor or data as
+/// [`FeedProblem::Null`]
+pub(crate) fn check_feed(
+    tensor: &MPSGraphTensor,
+    data: &MPSGraphTensorData,
+) -> Result<Vec<FeedProblem>> {
+    if tensor.0.is_null() || data.0.is_null() {
+        let name = if tensor.0.is_null() {
+            String::from("<null>")
+        } else {
+            tensor.name()
+        };
+        return Ok(vec![FeedProblem::Null { tensor: name }]);
+    }
+    // Unranked tensors have no shape
+    let shape: *mut AnyObject = unsafe { msg_send![tensor.0, shape] };
+    let expected = (!shape.is_null()).then(|| tensor.shape().to_shape().to_i64());
+    Ok(FeedProblem::check(
+        &tensor.name(),
+        expected.as_deref(),
+        tensor.try_data_type()?,
+        &data.shape().dimensions(),
+        data.try_data_type()?,
+    ))
+}
+
+/// Appends the problems found with individual feeds and reports them all
+pub(crate) fn feed_problems_result(
+    mut problems: Vec<FeedProblem>,
+    mut feed_problems: Vec<FeedProblem>,
+) -> Result<()> {
+    // Feeds come from a hash map; sort them so the report is stable
+    feed_problems.sort_by(|a, b| a.tensor().cmp(b.tensor()));
+    problems.extend(feed_problems);
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(MPSGraphError::InvalidFeeds(problems))
+    }
+}
+
+/// Checks that a caller-provided result entry can receive data
+fn validate_result_entry(tensor: &MPSGraphTensor, data: &MPSGraphTensorData) -> Result<()> {
+    if tensor.0.is_null() || data.0.is_null() {
+        return Err(MPSGraphError::ExecutionFailed(String::from(
+            "the results dictionary contains a null tensor or tensor data",
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that every entry of a caller-provided results dictionary can receive data
+fn validate_results_dict(results: &HashMap<MPSGraphTensor, MPSGraphTensorData>) -> Result<()> {
+    for (tensor, data) in results {
+        validate_result_entry(tensor, data)?;
+    }
+    Ok(())
+}
+
+/// Returns the results if every target produced a value
+pub(crate) fn checked_results(
+    results: HashMap<MPSGraphTensor, MPSGraphTensorData>,
+    targets: &[MPSGraphTensor],
+) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+    if let Some(missing) = targets.iter().find(|t| !results.contains_key(*t)) {
+        return Err(MPSGraphError::ExecutionFailed(format!(
+            "no result was produced for tensor '{}'",
+            missing.name()
+        )));
+    }
+    Ok(results)
+}
+
+/// Turns a null executable into a compilation error
+fn checked_executable(executable: MPSGraphExecutable) -> Result<MPSGraphExecutable> {
+    if executable.0.is_null() {
+        return Err(MPSGraphError::CompilationFailed(String::from(
+            "MPSGraph returned a nil executable (no NSError is available)",
+        )));
+    }
+    Ok(executable)
+}
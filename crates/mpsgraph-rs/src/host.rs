@@ -0,0 +1,469 @@
+//! Host-side tensors.
+//!
+//! [`HostTensor<T>`] owns typed elements together with a shape and strides, so it can be
+//! indexed, reshaped and permuted on the host; permuting only reorders the strides. On Apple
+//! targets [`MPSGraphTensorData::to_host`] reads tensor data back into one after checking
+//! the element type, and [`MPSGraphTensorData::from_host`] goes the other way.
+//!
+//! [`DynHostTensor`] is the untyped counterpart: C-order bytes tagged with a shape and
+//! [`MPSDataType`], so values of any element type can share one map. It is what the
+//! name-based execution API ([`MPSGraph::run_named`](crate::graph::MPSGraph::run_named))
+//! takes and returns.
+
+use crate::core::MPSDataType;
+use crate::dims::Shape;
+use crate::error::{MPSGraphError, Result};
+use std::ops::{Index, IndexMut};
+
+#[cfg(target_vendor = "apple")]
+use crate::tensor_data::MPSGraphTensorData;
+
+/// Element types that can be stored in a [`HostTensor`] or [`DynHostTensor`]
+pub trait HostElement: Copy + 'static {
+    /// The data type of the element
+    const DATA_TYPE: MPSDataType;
+
+    /// Reads an element from its native-endian bytes
+    fn from_ne_bytes(bytes: &[u8]) -> Self;
+
+    /// Appends the native-endian bytes of the element
+    fn extend_ne_bytes(self, out: &mut Vec<u8>);
+}
+
+macro_rules! impl_host_element {
+    ($($ty:ty => $data_type:ident),* $(,)?) => {
+        $(
+            impl HostElement for $ty {
+                const DATA_TYPE: MPSDataType = MPSDataType::$data_type;
+
+                fn from_ne_bytes(bytes: &[u8]) -> Self {
+                    <$ty>::from_ne_bytes(bytes.try_into().unwrap())
+                }
+
+                fn extend_ne_bytes(self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_ne_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_host_element!(
+    f32 => Float32,
+    f64 => Float64,
+    i8 => Int8,
+    i16 => Int16,
+    i32 => Int32,
+    i64 => Int64,
+    u8 => UInt8,
+    u16 => UInt16,
+    u32 => UInt32,
+    u64 => UInt64,
+);
+
+#[cfg(feature = "half")]
+impl_host_element!(half::f16 => Float16, half::bf16 => BFloat16);
+
+impl HostElement for bool {
+    const DATA_TYPE: MPSDataType = MPSDataType::Bool;
+
+    fn from_ne_bytes(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+
+    fn extend_ne_bytes(self, out: &mut Vec<u8>) {
+        out.push(self as u8);
+    }
+}
+
+/// Strides of a C-order layout, in elements
+pub(crate) fn contiguous_strides(shape: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1usize; shape.len()];
+    for axis in (1..shape.len()).rev() {
+        strides[axis - 1] = strides[axis].saturating_mul(shape[axis]);
+    }
+    strides
+}
+
+/// An owned, typed host tensor with a shape and strides
+///
+/// Strides are in elements. Tensors built with [`HostTensor::new`] are in C order; other
+/// layouts come from [`HostTensor::from_strided`] or [`HostTensor::permute`]. Equality
+/// compares shapes and elements, not layouts.
+#[derive(Debug, Clone)]
+pub struct HostTensor<T> {
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+    data: Vec<T>,
+}
+
+impl<T: HostElement> HostTensor<T> {
+    /// Creates a tensor from elements in C order
+    pub fn new(shape: &[usize], data: Vec<T>) -> Result<Self> {
+        let strides = contiguous_strides(shape);
+        Self::from_strided(shape, &strides, data)
+    }
+
+    /// Creates a tensor over `data` laid out with `strides`
+    ///
+    /// Every index inside `shape` must land inside `data`.
+    pub fn from_strided(shape: &[usize], strides: &[usize], data: Vec<T>) -> Result<Self> {
+        if strides.len() != shape.len() {
+            return Err(MPSGraphError::InvalidStrides {
+                shape: shape.to_vec(),
+                strides: strides.to_vec(),
+            });
+        }
+        let size = T::DATA_TYPE.size_in_bytes();
+        let needed = if shape.contains(&0) {
+            Some(0)
+        } else {
+            shape
+                .iter()
+                .zip(strides)
+                .try_fold(1usize, |needed, (&size, &stride)| {
+                    needed.checked_add((size - 1).checked_mul(stride)?)
+                })
+        };
+        let Some(needed) = needed else {
+            return Err(MPSGraphError::SizeMismatch {
+                expected: usize::MAX,
+                actual: data.len() * size,
+            });
+        };
+        let contiguous = strides == contiguous_strides(shape).as_slice();
+        if data.len() < needed || (contiguous && data.len() != needed) {
+            return Err(MPSGraphError::SizeMismatch {
+                expected: needed.saturating_mul(size),
+                actual: data.len() * size,
+            });
+        }
+        Ok(HostTensor {
+            shape: shape.to_vec(),
+            strides: strides.to_vec(),
+            data,
+        })
+    }
+
+    /// Creates a rank-0 tensor holding `value`
+    pub fn scalar(value: T) -> Self {
+        HostTensor {
+            shape: Vec::new(),
+            strides: Vec::new(),
+            data: vec![value],
+        }
+    }
+
+    /// Returns the shape of the tensor
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    /// Returns the distance between consecutive indices along each axis, in elements
+    pub fn strides(&self) -> &[usize] {
+        &self.strides
+    }
+
+    /// Returns the data type tag of the elements
+    pub fn data_type(&self) -> MPSDataType {
+        T::DATA_TYPE
+    }
+
+    /// Returns the number of elements
+    pub fn element_count(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// Returns whether the elements are stored in C order without gaps
+    pub fn is_contiguous(&self) -> bool {
+        self.strides == contiguous_strides(&self.shape)
+    }
+
+    /// Returns the underlying storage, in layout order
+    pub fn storage(&self) -> &[T] {
+        &self.data
+    }
+
+    /// Splits the tensor into its shape, strides and storage, the inverse of
+    /// [`HostTensor::from_strided`]
+    pub fn into_parts(self) -> (Vec<usize>, Vec<usize>, Vec<T>) {
+        (self.shape, self.strides, self.data)
+    }
+
+    fn offset(&self, index: &[usize]) -> Option<usize> {
+        if index.len() != self.shape.len() {
+            return None;
+        }
+        let mut offset = 0;
+        for ((&i, &size), &stride) in index.iter().zip(&self.shape).zip(&self.strides) {
+            if i >= size {
+                return None;
+            }
+            offset += i * stride;
+        }
+        Some(offset)
+    }
+
+    /// Returns the element at `index`, or `None` if it's out of bounds
+    pub fn get(&self, index: &[usize]) -> Option<&T> {
+        self.offset(index).map(|offset| &self.data[offset])
+    }
+
+    /// Returns the element at `index` mutably, or `None` if it's out of bounds
+    pub fn get_mut(&mut self, index: &[usize]) -> Option<&mut T> {
+        self.offset(index).map(|offset| &mut self.data[offset])
+    }
+
+    /// Iterates over the elements in C order of the logical shape
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        let count = self.element_count();
+        let mut index = vec![0; self.shape.len()];
+        (0..count).map(move |_| {
+            let element = &self.data[self.offset(&index).unwrap()];
+            for axis in (0..index.len()).rev() {
+                index[axis] += 1;
+                if index[axis] < self.shape[axis] {
+                    break;
+                }
+                index[axis] = 0;
+            }
+            element
+        })
+    }
+
+    /// Copies the elements out in C order
+    pub fn to_vec(&self) -> Vec<T> {
+        if self.is_contiguous() {
+            self.data.clone()
+        } else {
+            self.iter().copied().collect()
+        }
+    }
+
+    /// Returns the elements in C order, copying only if the layout isn't contiguous
+    pub fn into_vec(self) -> Vec<T> {
+        if self.is_contiguous() {
+            self.data
+        } else {
+            self.to_vec()
+        }
+    }
+
+    /// Returns a tensor with the same elements stored in C order
+    pub fn to_contiguous(&self) -> Self {
+        HostTensor {
+            shape: self.shape.clone(),
+            strides: contiguous_strides(&self.shape),
+            data: self.to_vec(),
+        }
+    }
+
+    /// Reshapes to `shape`, with at most one `-1` for an inferred dimension
+    ///
+    /// Elements keep their C order; a non-contiguous tensor is copied first.
+    pub fn reshape(self, shape: &[i64]) -> Result<Self> {
+        let target = Shape::from_static(&self.shape).reshape(shape)?;
+        let target = target.to_static().unwrap_or_default();
+        Self::new(&target, self.into_vec())
+    }
+
+    /// Reorders the axes so that result axis `i` is source axis `axes[i]`
+    ///
+    /// Only the shape and strides change; the storage isn't copied. Negative axes are
+    /// allowed.
+    pub fn permute(self, axes: &[i64]) -> Result<Self> {
+        let source = Shape::from_static(&self.shape);
+        source.permute(axes)?;
+        let axes = source.normalize_axes(axes)?;
+        Ok(HostTensor {
+            shape: axes.iter().map(|&axis| self.shape[axis]).collect(),
+            strides: axes.iter().map(|&axis| self.strides[axis]).collect(),
+            data: self.data,
+        })
+    }
+}
+
+impl<T: HostElement + PartialEq> PartialEq for HostTensor<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.shape == other.shape && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: HostElement> Index<&[usize]> for HostTensor<T> {
+    type Output = T;
+
+    fn index(&self, index: &[usize]) -> &T {
+        match self.get(index) {
+            Some(element) => element,
+            None => panic!(
+                "index {:?} is out of bounds for shape {:?}",
+                index, self.shape
+            ),
+        }
+    }
+}
+
+impl<T: HostElement> IndexMut<&[usize]> for HostTensor<T> {
+    fn index_mut(&mut self, index: &[usize]) -> &mut T {
+        let shape = self.shape.clone();
+        match self.get_mut(index) {
+            Some(element) => element,
+            None => panic!("index {:?} is out of bounds for shape {:?}", index, shape),
+        }
+    }
+}
+
+impl<T: HostElement> From<HostTensor<T>> for DynHostTensor {
+    fn from(tensor: HostTensor<T>) -> Self {
+        let shape = tensor.shape.clone();
+        let mut bytes = Vec::with_capacity(tensor.element_count() * T::DATA_TYPE.size_in_bytes());
+        for value in tensor.into_vec() {
+            value.extend_ne_bytes(&mut bytes);
+        }
+        DynHostTensor {
+            shape,
+            data_type: T::DATA_TYPE,
+            bytes,
+        }
+    }
+}
+
+/// An owned host buffer in C order with its shape and data type
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynHostTensor {
+    shape: Vec<usize>,
+    data_type: MPSDataType,
+    bytes: Vec<u8>,
+}
+
+impl DynHostTensor {
+    /// Creates a tensor from native-endian bytes in C order
+    pub fn new(shape: &[usize], data_type: MPSDataType, bytes: Vec<u8>) -> Result<Self> {
+        let expected = shape
+            .iter()
+            .try_fold(1usize, |count, &size| count.checked_mul(size))
+            .and_then(|count| data_type.packed_size_in_bytes(count))
+            .unwrap_or(usize::MAX);
+        if bytes.len() != expected {
+            return Err(MPSGraphError::SizeMismatch {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        Ok(DynHostTensor {
+            shape: shape.to_vec(),
+            data_type,
+            bytes,
+        })
+    }
+
+    /// Creates a tensor from typed values in C order
+    pub fn from_slice<T: HostElement>(shape: &[usize], values: &[T]) -> Result<Self> {
+        let mut bytes = Vec::with_capacity(std::mem::size_of_val(values));
+        for &value in values {
+            value.extend_ne_bytes(&mut bytes);
+        }
+        Self::new(shape, T::DATA_TYPE, bytes)
+    }
+
+    /// Creates a rank-0 tensor holding `value`
+    pub fn scalar<T: HostElement>(value: T) -> Self {
+        let mut bytes = Vec::with_capacity(std::mem::size_of::<T>());
+        value.extend_ne_bytes(&mut bytes);
+        DynHostTensor {
+            shape: Vec::new(),
+            data_type: T::DATA_TYPE,
+            bytes,
+        }
+    }
+
+    /// Returns the shape of the tensor
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    /// Returns the data type of the tensor
+    pub fn data_type(&self) -> MPSDataType {
+        self.data_type
+    }
+
+    /// Returns the native-endian bytes of the tensor in C order
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the bytes, dropping the shape and data type
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Returns the number of elements
+    pub fn element_count(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// Copies the elements out as `T`, which must match the data type
+    pub fn to_vec<T: HostElement>(&self) -> Result<Vec<T>> {
+        if T::DATA_TYPE != self.data_type {
+            return Err(MPSGraphError::DataTypeMismatch {
+                expected: self.data_type,
+                actual: T::DATA_TYPE,
+            });
+        }
+        Ok(self
+            .bytes
+            .chunks_exact(self.data_type.size_in_bytes())
+            .map(T::from_ne_bytes)
+            .collect())
+    }
+
+    /// Converts to a typed tensor, checking `T` against the data type
+    pub fn to_typed<T: HostElement>(&self) -> Result<HostTensor<T>> {
+        HostTensor::new(&self.shape, self.to_vec()?)
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl DynHostTensor {
+    /// Copies the tensor into new tensor data
+    pub fn to_tensor_data(&self) -> MPSGraphTensorData {
+        MPSGraphTensorData::new(&self.bytes, &self.shape, self.data_type)
+    }
+
+    /// Copies tensor data back to the host
+    pub fn from_tensor_data(tensor_data: &MPSGraphTensorData) -> Result<Self> {
+        let shape = tensor_data.shape().dimensions();
+        Self::new(
+            &shape,
+            tensor_data.try_data_type()?,
+            tensor_data.read_bytes()?,
+        )
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl MPSGraphTensorData {
+    /// Copies the data to the host as `T`, which must match [`data_type`](Self::data_type)
+    pub fn to_host<T: HostElement>(&self) -> Result<HostTensor<T>> {
+        let data_type = self.try_data_type()?;
+        if T::DATA_TYPE != data_type {
+            return Err(MPSGraphError::DataTypeMismatch {
+                expected: data_type,
+                actual: T::DATA_TYPE,
+            });
+        }
+        let shape = self.shape().dimensions();
+        let data = self
+            .read_bytes()?
+            .chunks_exact(data_type.size_in_bytes())
+            .map(T::from_ne_bytes)
+            .collect();
+        HostTensor::new(&shape, data)
+    }
+
+    /// Copies a host tensor into new tensor data, in C order
+    pub fn from_host<T: HostElement>(tensor: &HostTensor<T>) -> Self {
+        let data = tensor.to_vec();
+        MPSGraphTensorData::new(&data, tensor.shape(), T::DATA_TYPE)
+    }
+}
@@ -0,0 +1,1070 @@
+//! Builder methods that mirror the `MPSGraph` op surface.
+//!
+//! Method names and argument order follow the corresponding `MPSGraph` methods, with
+//! [`TensorId`]s in place of `MPSGraphTensor`s. Each `try_` method records one operation and
+//! returns an [`IrError`] describing why the operands don't fit it; the method without the
+//! prefix panics with that description instead.
+
+use super::{
+    BinaryOp, Convolution2d, Graph, IrError, OpKind, PoolingOp, ReductionOp, TensorId, UnaryOp,
+    Window2d,
+};
+use crate::core::MPSDataType;
+use crate::dims::Shape;
+use crate::loss_ops::MPSGraphLossReductionType;
+use crate::non_maximum_suppression_ops::MPSGraphNonMaximumSuppressionCoordinateMode;
+use crate::pooling_ops::MPSGraphTensorNamedDataLayout;
+use crate::resize_ops::MPSGraphResizeMode;
+use crate::scatter_nd_ops::MPSGraphScatterMode;
+use crate::tensor_shape_ops::{try_expand_slice_ellipsis, MPSGraphSliceMasks};
+
+type Result<T> = std::result::Result<T, IrError>;
+
+/// Unwraps the result of a `try_` builder, panicking with the error's description
+fn expect<T>(result: Result<T>) -> T {
+    result.unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// Defines panicking builders that forward to their `try_` counterparts
+macro_rules! panicking {
+    ($($method:ident => $try_method:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty;)*) => {
+        $(
+            #[doc = concat!(
+                "Like [`", stringify!($try_method), "`](Self::", stringify!($try_method),
+                "), but panics with a descriptive message if the operands don't fit"
+            )]
+            pub fn $method(&mut self, $($arg: $ty),*) -> $ret {
+                expect(self.$try_method($($arg),*))
+            }
+        )*
+    };
+}
+
+macro_rules! unary_ops {
+    ($($method:ident, $try_method:ident => $op:ident),* $(,)?) => {
+        $(
+            #[doc = concat!("Records an elementwise `", stringify!($method), "` operation")]
+            pub fn $try_method(&mut self, x: TensorId, name: Option<&str>) -> Result<TensorId> {
+                self.try_record(OpKind::Unary(UnaryOp::$op), &[x], name)
+            }
+
+            panicking! {
+                $method => $try_method(x: TensorId, name: Option<&str>) -> TensorId;
+            }
+        )*
+    };
+}
+
+macro_rules! binary_ops {
+    ($($method:ident, $try_method:ident => $op:ident),* $(,)?) => {
+        $(
+            #[doc = concat!("Records an elementwise `", stringify!($method), "` operation with broadcasting")]
+            pub fn $try_method(
+                &mut self,
+                primary: TensorId,
+                secondary: TensorId,
+                name: Option<&str>,
+            ) -> Result<TensorId> {
+                self.try_record(OpKind::Binary(BinaryOp::$op), &[primary, secondary], name)
+            }
+
+            panicking! {
+                $method => $try_method(
+                    primary: TensorId,
+                    secondary: TensorId,
+                    name: Option<&str>,
+                ) -> TensorId;
+            }
+        )*
+    };
+}
+
+macro_rules! reduction_ops {
+    ($($axis_method:ident, $try_axis_method:ident, $axes_method:ident, $try_axes_method:ident => $op:ident),* $(,)?) => {
+        $(
+            #[doc = concat!("Records a `", stringify!($op), "` reduction over one axis")]
+            pub fn $try_axis_method(
+                &mut self,
+                tensor: TensorId,
+                axis: i64,
+                name: Option<&str>,
+            ) -> Result<TensorId> {
+                self.try_reduction(ReductionOp::$op, tensor, Some(&[axis]), name)
+            }
+
+            #[doc = concat!("Records a `", stringify!($op), "` reduction over `axes`, or every axis if `None`")]
+            pub fn $try_axes_method(
+                &mut self,
+                tensor: TensorId,
+                axes: Option<&[i64]>,
+                name: Option<&str>,
+            ) -> Result<TensorId> {
+                self.try_reduction(ReductionOp::$op, tensor, axes, name)
+            }
+
+            panicking! {
+                $axis_method => $try_axis_method(
+                    tensor: TensorId,
+                    axis: i64,
+                    name: Option<&str>,
+                ) -> TensorId;
+                $axes_method => $try_axes_method(
+                    tensor: TensorId,
+                    axes: Option<&[i64]>,
+                    name: Option<&str>,
+                ) -> TensorId;
+            }
+        )*
+    };
+}
+
+impl Graph {
+    /// Records a placeholder that is fed at run time
+    pub fn try_placeholder(
+        &mut self,
+        shape: &Shape,
+        data_type: MPSDataType,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::Placeholder {
+            shape: shape.clone(),
+            data_type,
+        };
+        self.try_record(kind, &[], name)
+    }
+
+    /// Records a constant with the given values and static shape
+    ///
+    /// The values are stored as raw bytes; their size must match `data_type`.
+    pub fn try_constant<T: Copy>(
+        &mut self,
+        values: &[T],
+        shape: &[usize],
+        data_type: MPSDataType,
+    ) -> Result<TensorId> {
+        let data = unsafe {
+            std::slice::from_raw_parts(values.as_ptr() as *const u8, std::mem::size_of_val(values))
+        };
+        let kind = OpKind::Constant {
+            data: data.to_vec(),
+            shape: Shape::from_static(shape),
+            data_type,
+        };
+        self.try_record(kind, &[], None)
+    }
+
+    /// Like [`try_constant`](Self::try_constant), but panics with a descriptive message if
+    /// the values don't fit the shape and data type
+    pub fn constant<T: Copy>(
+        &mut self,
+        values: &[T],
+        shape: &[usize],
+        data_type: MPSDataType,
+    ) -> TensorId {
+        expect(self.try_constant(values, shape, data_type))
+    }
+
+    /// Records a rank-0 constant
+    pub fn try_constant_scalar(&mut self, value: f64, data_type: MPSDataType) -> Result<TensorId> {
+        self.try_constant_scalar_with_shape(value, &Shape::scalar(), data_type)
+    }
+
+    /// Records a constant filled with `value`
+    pub fn try_constant_scalar_with_shape(
+        &mut self,
+        value: f64,
+        shape: &Shape,
+        data_type: MPSDataType,
+    ) -> Result<TensorId> {
+        let kind = OpKind::ConstantScalar {
+            value,
+            shape: shape.clone(),
+            data_type,
+        };
+        self.try_record(kind, &[], None)
+    }
+
+    panicking! {
+        placeholder => try_placeholder(
+            shape: &Shape,
+            data_type: MPSDataType,
+            name: Option<&str>,
+        ) -> TensorId;
+        constant_scalar => try_constant_scalar(value: f64, data_type: MPSDataType) -> TensorId;
+        constant_scalar_with_shape => try_constant_scalar_with_shape(
+            value: f64,
+            shape: &Shape,
+            data_type: MPSDataType,
+        ) -> TensorId;
+    }
+
+    unary_ops! {
+        identity, try_identity => Identity,
+        exp, try_exp => Exp,
+        exp2, try_exp2 => Exp2,
+        exp10, try_exp10 => Exp10,
+        log, try_log => Log,
+        log2, try_log2 => Log2,
+        log10, try_log10 => Log10,
+        square, try_square => Square,
+        sqrt, try_sqrt => Sqrt,
+        rsqrt, try_rsqrt => Rsqrt,
+        reciprocal, try_reciprocal => Reciprocal,
+        abs, try_abs => Abs,
+        negative, try_negative => Negative,
+        sign, try_sign => Sign,
+        ceil, try_ceil => Ceil,
+        floor, try_floor => Floor,
+        round, try_round => Round,
+        rint, try_rint => Rint,
+        truncate, try_truncate => Truncate,
+        sin, try_sin => Sin,
+        cos, try_cos => Cos,
+        tan, try_tan => Tan,
+        sinh, try_sinh => Sinh,
+        cosh, try_cosh => Cosh,
+        tanh, try_tanh => Tanh,
+        asin, try_asin => Asin,
+        acos, try_acos => Acos,
+        atan, try_atan => Atan,
+        asinh, try_asinh => Asinh,
+        acosh, try_acosh => Acosh,
+        atanh, try_atanh => Atanh,
+        erf, try_erf => Erf,
+        logical_not, try_logical_not => LogicalNot,
+        is_nan, try_is_nan => IsNan,
+        is_infinite, try_is_infinite => IsInfinite,
+        is_finite, try_is_finite => IsFinite,
+        relu, try_relu => Relu,
+        sigmoid, try_sigmoid => Sigmoid,
+        abs_square, try_abs_square => AbsSquare,
+        signbit, try_signbit => Signbit,
+        bitwise_not, try_bitwise_not => BitwiseNot,
+        bitwise_population_count, try_bitwise_population_count => BitwisePopulationCount,
+    }
+
+    binary_ops! {
+        add, try_add => Add,
+        subtract, try_subtract => Subtract,
+        multiply, try_multiply => Multiply,
+        divide, try_divide => Divide,
+        division_no_nan, try_division_no_nan => DivisionNoNan,
+        modulo, try_modulo => Modulo,
+        floor_modulo, try_floor_modulo => FloorModulo,
+        power, try_power => Power,
+        minimum, try_minimum => Minimum,
+        maximum, try_maximum => Maximum,
+        minimum_with_nan_propagation, try_minimum_with_nan_propagation => MinimumWithNanPropagation,
+        maximum_with_nan_propagation, try_maximum_with_nan_propagation => MaximumWithNanPropagation,
+        atan2, try_atan2 => Atan2,
+        equal, try_equal => Equal,
+        not_equal, try_not_equal => NotEqual,
+        less_than, try_less_than => LessThan,
+        less_than_or_equal_to, try_less_than_or_equal_to => LessThanOrEqualTo,
+        greater_than, try_greater_than => GreaterThan,
+        greater_than_or_equal_to, try_greater_than_or_equal_to => GreaterThanOrEqualTo,
+        logical_and, try_logical_and => LogicalAnd,
+        logical_or, try_logical_or => LogicalOr,
+        logical_xor, try_logical_xor => LogicalXor,
+        logical_nand, try_logical_nand => LogicalNand,
+        logical_nor, try_logical_nor => LogicalNor,
+        logical_xnor, try_logical_xnor => LogicalXnor,
+        bitwise_and, try_bitwise_and => BitwiseAnd,
+        bitwise_or, try_bitwise_or => BitwiseOr,
+        bitwise_xor, try_bitwise_xor => BitwiseXor,
+        left_shift, try_left_shift => LeftShift,
+        right_shift, try_right_shift => RightShift,
+    }
+
+    /// Records a select operation which chooses values from the true or false tensor
+    pub fn try_select(
+        &mut self,
+        predicate: TensorId,
+        true_tensor: TensorId,
+        false_tensor: TensorId,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_record(
+            OpKind::Select,
+            &[predicate, true_tensor, false_tensor],
+            name,
+        )
+    }
+
+    /// Records a clamp operation
+    pub fn try_clamp(
+        &mut self,
+        tensor: TensorId,
+        min_tensor: TensorId,
+        max_tensor: TensorId,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_record(OpKind::Clamp, &[tensor, min_tensor, max_tensor], name)
+    }
+
+    /// Records a cast to `data_type`
+    pub fn try_cast(
+        &mut self,
+        x: TensorId,
+        data_type: MPSDataType,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_record(OpKind::Cast { data_type }, &[x], name)
+    }
+
+    /// Records a matrix multiplication
+    ///
+    /// Rank-1 operands are treated as a row (primary) or column (secondary) vector and the
+    /// corresponding dimension is dropped from the result. Batch dimensions broadcast.
+    pub fn try_matmul(
+        &mut self,
+        primary: TensorId,
+        secondary: TensorId,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_record(OpKind::MatMul, &[primary, secondary], name)
+    }
+
+    /// Records a batched matrix multiplication
+    ///
+    /// `MPSGraph` uses the same operation for both, so this is an alias of
+    /// [`Graph::try_matmul`].
+    pub fn try_batch_matmul(
+        &mut self,
+        primary: TensorId,
+        secondary: TensorId,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_matmul(primary, secondary, name)
+    }
+
+    panicking! {
+        select => try_select(
+            predicate: TensorId,
+            true_tensor: TensorId,
+            false_tensor: TensorId,
+            name: Option<&str>,
+        ) -> TensorId;
+        clamp => try_clamp(
+            tensor: TensorId,
+            min_tensor: TensorId,
+            max_tensor: TensorId,
+            name: Option<&str>,
+        ) -> TensorId;
+        cast => try_cast(x: TensorId, data_type: MPSDataType, name: Option<&str>) -> TensorId;
+        matmul => try_matmul(primary: TensorId, secondary: TensorId, name: Option<&str>) -> TensorId;
+        batch_matmul => try_batch_matmul(
+            primary: TensorId,
+            secondary: TensorId,
+            name: Option<&str>,
+        ) -> TensorId;
+    }
+
+    reduction_ops! {
+        reduction_sum_with_tensor_axis, try_reduction_sum_with_tensor_axis,
+        reduction_sum_with_tensor_axes, try_reduction_sum_with_tensor_axes => Sum,
+        reduction_maximum_with_tensor_axis, try_reduction_maximum_with_tensor_axis,
+        reduction_maximum_with_tensor_axes, try_reduction_maximum_with_tensor_axes => Maximum,
+        reduction_minimum_with_tensor_axis, try_reduction_minimum_with_tensor_axis,
+        reduction_minimum_with_tensor_axes, try_reduction_minimum_with_tensor_axes => Minimum,
+        reduction_maximum_propagate_nan_with_tensor_axis,
+        try_reduction_maximum_propagate_nan_with_tensor_axis,
+        reduction_maximum_propagate_nan_with_tensor_axes,
+        try_reduction_maximum_propagate_nan_with_tensor_axes => MaximumPropagateNan,
+        reduction_minimum_propagate_nan_with_tensor_axis,
+        try_reduction_minimum_propagate_nan_with_tensor_axis,
+        reduction_minimum_propagate_nan_with_tensor_axes,
+        try_reduction_minimum_propagate_nan_with_tensor_axes => MinimumPropagateNan,
+        reduction_product_with_tensor_axis, try_reduction_product_with_tensor_axis,
+        reduction_product_with_tensor_axes, try_reduction_product_with_tensor_axes => Product,
+        reduction_and_with_tensor_axis, try_reduction_and_with_tensor_axis,
+        reduction_and_with_tensor_axes, try_reduction_and_with_tensor_axes => And,
+        reduction_or_with_tensor_axis, try_reduction_or_with_tensor_axis,
+        reduction_or_with_tensor_axes, try_reduction_or_with_tensor_axes => Or,
+        reduction_xor_with_tensor_axis, try_reduction_xor_with_tensor_axis,
+        reduction_xor_with_tensor_axes, try_reduction_xor_with_tensor_axes => Xor,
+    }
+
+    /// Records the mean over `axes`; reduced axes are kept with size 1
+    pub fn try_mean(
+        &mut self,
+        tensor: TensorId,
+        axes: &[i64],
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_reduction(ReductionOp::Mean, tensor, Some(axes), name)
+    }
+
+    /// Records the index of the maximum along `axis`, as Int32
+    pub fn try_reduction_arg_maximum_with_tensor_axis(
+        &mut self,
+        tensor: TensorId,
+        axis: i64,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_reduction(ReductionOp::ArgMaximum, tensor, Some(&[axis]), name)
+    }
+
+    /// Records the index of the minimum along `axis`, as Int32
+    pub fn try_reduction_arg_minimum_with_tensor_axis(
+        &mut self,
+        tensor: TensorId,
+        axis: i64,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_reduction(ReductionOp::ArgMinimum, tensor, Some(&[axis]), name)
+    }
+
+    /// Records a reshape; at most one entry of `shape` may be `-1`
+    pub fn try_reshape(
+        &mut self,
+        x: TensorId,
+        shape: &[i64],
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::Reshape {
+            shape: shape.to_vec(),
+        };
+        self.try_record(kind, &[x], name)
+    }
+
+    /// Records a transpose where result dimension `i` is source dimension `dimensions[i]`
+    pub fn try_transpose(
+        &mut self,
+        x: TensorId,
+        dimensions: &[usize],
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::Transpose {
+            permutation: dimensions.to_vec(),
+        };
+        self.try_record(kind, &[x], name)
+    }
+
+    /// Records a broadcast to `shape`
+    pub fn try_broadcast(
+        &mut self,
+        x: TensorId,
+        shape: &[i64],
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::Broadcast {
+            shape: shape.to_vec(),
+        };
+        self.try_record(kind, &[x], name)
+    }
+
+    /// Records a concatenation along `dimension`
+    pub fn try_concatenate(
+        &mut self,
+        tensors: &[TensorId],
+        dimension: i64,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_record(OpKind::Concat { axis: dimension }, tensors, name)
+    }
+
+    /// Records a stack along a new `axis`
+    pub fn try_stack(
+        &mut self,
+        tensors: &[TensorId],
+        axis: i64,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_record(OpKind::Stack { axis }, tensors, name)
+    }
+
+    /// Records a split into `num_splits` equal parts along `axis`
+    ///
+    /// `num_splits` must be positive and divide the axis.
+    pub fn try_split(
+        &mut self,
+        x: TensorId,
+        num_splits: i64,
+        axis: i64,
+        name: Option<&str>,
+    ) -> Result<Vec<TensorId>> {
+        if num_splits <= 0 {
+            return Err(IrError::Invalid {
+                op: "split",
+                reason: format!("num_splits must be positive, got {}", num_splits),
+            });
+        }
+        let kind = OpKind::Split {
+            num_splits: num_splits as usize,
+            axis,
+        };
+        self.try_add_operation(kind, &[x], name)
+    }
+
+    /// Records a squeeze of `axes`, or of every size-1 axis if `axes` is empty
+    pub fn try_squeeze(
+        &mut self,
+        x: TensorId,
+        axes: &[i64],
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::Squeeze {
+            axes: axes.to_vec(),
+        };
+        self.try_record(kind, &[x], name)
+    }
+
+    /// Records the insertion of size-1 axes at `axes` of the result
+    pub fn try_expand_dims(
+        &mut self,
+        x: TensorId,
+        axes: &[i64],
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::ExpandDims {
+            axes: axes.to_vec(),
+        };
+        self.try_record(kind, &[x], name)
+    }
+
+    /// Records a tile with one multiple per dimension
+    pub fn try_tile(
+        &mut self,
+        x: TensorId,
+        multiples: &[i64],
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::Tile {
+            multiples: multiples.to_vec(),
+        };
+        self.try_record(kind, &[x], name)
+    }
+
+    /// Records a reversal of `axes`
+    pub fn try_reverse(
+        &mut self,
+        x: TensorId,
+        axes: &[i64],
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::Reverse {
+            axes: axes.to_vec(),
+        };
+        self.try_record(kind, &[x], name)
+    }
+
+    /// Records a flatten into two dimensions around `axis`
+    pub fn try_flatten2d(
+        &mut self,
+        x: TensorId,
+        axis: i64,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_record(OpKind::Flatten2d { axis }, &[x], name)
+    }
+
+    /// Records a slice of `length` elements starting at `start` along `dimension`
+    pub fn try_slice(
+        &mut self,
+        x: TensorId,
+        dimension: usize,
+        start: i64,
+        length: i64,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::Slice {
+            dimension,
+            start,
+            length,
+        };
+        self.try_record(kind, &[x], name)
+    }
+
+    /// Records a strided slice
+    ///
+    /// The ellipsis mask is expanded against the rank of `x` when the operation is recorded,
+    /// the same way [`MPSGraph::strided_slice`](crate::graph::MPSGraph) does it, and a slice
+    /// the `MPSGraph` method would reject is reported as [`IrError::Invalid`].
+    pub fn try_strided_slice(
+        &mut self,
+        x: TensorId,
+        starts: &[i64],
+        ends: &[i64],
+        strides: &[i64],
+        masks: &MPSGraphSliceMasks,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let rank = self.try_tensor(x)?.shape.rank();
+        let slice =
+            try_expand_slice_ellipsis(rank, starts, ends, strides, masks).map_err(|reason| {
+                IrError::Invalid {
+                    op: "strided_slice",
+                    reason,
+                }
+            })?;
+        let kind = OpKind::StridedSlice {
+            starts: slice.starts,
+            ends: slice.ends,
+            strides: slice.strides,
+            masks: slice.masks,
+        };
+        self.try_record(kind, &[x], name)
+    }
+
+    panicking! {
+        reduction_arg_maximum_with_tensor_axis => try_reduction_arg_maximum_with_tensor_axis(
+            tensor: TensorId,
+            axis: i64,
+            name: Option<&str>,
+        ) -> TensorId;
+        reduction_arg_minimum_with_tensor_axis => try_reduction_arg_minimum_with_tensor_axis(
+            tensor: TensorId,
+            axis: i64,
+            name: Option<&str>,
+        ) -> TensorId;
+        mean => try_mean(tensor: TensorId, axes: &[i64], name: Option<&str>) -> TensorId;
+        reshape => try_reshape(x: TensorId, shape: &[i64], name: Option<&str>) -> TensorId;
+        transpose => try_transpose(
+            x: TensorId,
+            dimensions: &[usize],
+            name: Option<&str>,
+        ) -> TensorId;
+        broadcast => try_broadcast(x: TensorId, shape: &[i64], name: Option<&str>) -> TensorId;
+        concatenate => try_concatenate(
+            tensors: &[TensorId],
+            dimension: i64,
+            name: Option<&str>,
+        ) -> TensorId;
+        stack => try_stack(tensors: &[TensorId], axis: i64, name: Option<&str>) -> TensorId;
+        split => try_split(
+            x: TensorId,
+            num_splits: i64,
+            axis: i64,
+            name: Option<&str>,
+        ) -> Vec<TensorId>;
+        squeeze => try_squeeze(x: TensorId, axes: &[i64], name: Option<&str>) -> TensorId;
+        expand_dims => try_expand_dims(x: TensorId, axes: &[i64], name: Option<&str>) -> TensorId;
+        tile => try_tile(x: TensorId, multiples: &[i64], name: Option<&str>) -> TensorId;
+        reverse => try_reverse(x: TensorId, axes: &[i64], name: Option<&str>) -> TensorId;
+        flatten2d => try_flatten2d(x: TensorId, axis: i64, name: Option<&str>) -> TensorId;
+        slice => try_slice(
+            x: TensorId,
+            dimension: usize,
+            start: i64,
+            length: i64,
+            name: Option<&str>,
+        ) -> TensorId;
+        strided_slice => try_strided_slice(
+            x: TensorId,
+            starts: &[i64],
+            ends: &[i64],
+            strides: &[i64],
+            masks: &MPSGraphSliceMasks,
+            name: Option<&str>,
+        ) -> TensorId;
+    }
+
+    /// Records a gather of slices of `params_tensor` along `axis`
+    pub fn try_gather(
+        &mut self,
+        params_tensor: TensorId,
+        indices_tensor: TensorId,
+        axis: usize,
+        batch_dimensions: usize,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::Gather {
+            axis,
+            batch_dimensions,
+        };
+        self.try_record(kind, &[params_tensor, indices_tensor], name)
+    }
+
+    /// Records a gather of individual elements along `axis`
+    pub fn try_gather_along_axis(
+        &mut self,
+        axis: isize,
+        params_tensor: TensorId,
+        indices_tensor: TensorId,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::GatherAlongAxis { axis };
+        self.try_record(kind, &[params_tensor, indices_tensor], name)
+    }
+
+    /// Records a scatter of slices into a new tensor of `shape`
+    pub fn try_scatter(
+        &mut self,
+        updates_tensor: TensorId,
+        indices_tensor: TensorId,
+        shape: &Shape,
+        axis: isize,
+        mode: MPSGraphScatterMode,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::Scatter {
+            shape: shape.clone(),
+            axis,
+            mode,
+        };
+        self.try_record(kind, &[updates_tensor, indices_tensor], name)
+    }
+
+    /// Records a scatter of individual elements along `axis` into a new tensor of `shape`
+    pub fn try_scatter_along_axis(
+        &mut self,
+        axis: isize,
+        updates_tensor: TensorId,
+        indices_tensor: TensorId,
+        shape: &Shape,
+        mode: MPSGraphScatterMode,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::ScatterAlongAxis {
+            shape: shape.clone(),
+            axis,
+            mode,
+        };
+        self.try_record(kind, &[updates_tensor, indices_tensor], name)
+    }
+
+    /// Records a softmax along `axis`
+    pub fn try_softmax(&mut self, x: TensorId, axis: i64, name: Option<&str>) -> Result<TensorId> {
+        self.try_record(OpKind::Softmax { axis }, &[x], name)
+    }
+
+    /// Records a softmax cross entropy loss
+    pub fn try_softmax_cross_entropy(
+        &mut self,
+        source_tensor: TensorId,
+        labels_tensor: TensorId,
+        axis: i64,
+        reduction_type: MPSGraphLossReductionType,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::SoftmaxCrossEntropy {
+            axis,
+            reduction: reduction_type,
+        };
+        self.try_record(kind, &[source_tensor, labels_tensor], name)
+    }
+
+    /// Records the gradient of a softmax cross entropy loss with respect to the logits
+    pub fn try_softmax_cross_entropy_gradient(
+        &mut self,
+        gradient_tensor: TensorId,
+        source_tensor: TensorId,
+        labels_tensor: TensorId,
+        axis: i64,
+        reduction_type: MPSGraphLossReductionType,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::SoftmaxCrossEntropyGradient {
+            axis,
+            reduction: reduction_type,
+        };
+        self.try_record(kind, &[gradient_tensor, source_tensor, labels_tensor], name)
+    }
+
+    panicking! {
+        gather => try_gather(
+            params_tensor: TensorId,
+            indices_tensor: TensorId,
+            axis: usize,
+            batch_dimensions: usize,
+            name: Option<&str>,
+        ) -> TensorId;
+        gather_along_axis => try_gather_along_axis(
+            axis: isize,
+            params_tensor: TensorId,
+            indices_tensor: TensorId,
+            name: Option<&str>,
+        ) -> TensorId;
+        scatter => try_scatter(
+            updates_tensor: TensorId,
+            indices_tensor: TensorId,
+            shape: &Shape,
+            axis: isize,
+            mode: MPSGraphScatterMode,
+            name: Option<&str>,
+        ) -> TensorId;
+        scatter_along_axis => try_scatter_along_axis(
+            axis: isize,
+            updates_tensor: TensorId,
+            indices_tensor: TensorId,
+            shape: &Shape,
+            mode: MPSGraphScatterMode,
+            name: Option<&str>,
+        ) -> TensorId;
+        softmax => try_softmax(x: TensorId, axis: i64, name: Option<&str>) -> TensorId;
+        softmax_cross_entropy => try_softmax_cross_entropy(
+            source_tensor: TensorId,
+            labels_tensor: TensorId,
+            axis: i64,
+            reduction_type: MPSGraphLossReductionType,
+            name: Option<&str>,
+        ) -> TensorId;
+        softmax_cross_entropy_gradient => try_softmax_cross_entropy_gradient(
+            gradient_tensor: TensorId,
+            source_tensor: TensorId,
+            labels_tensor: TensorId,
+            axis: i64,
+            reduction_type: MPSGraphLossReductionType,
+            name: Option<&str>,
+        ) -> TensorId;
+    }
+
+    /// Records a 2D convolution
+    pub fn try_convolution_2d(
+        &mut self,
+        source: TensorId,
+        weights: TensorId,
+        descriptor: &Convolution2d,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_record(OpKind::Convolution2d(*descriptor), &[source, weights], name)
+    }
+
+    /// Records a 2D max pooling
+    pub fn try_max_pooling_2d(
+        &mut self,
+        source: TensorId,
+        descriptor: &Window2d,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_pooling_2d(PoolingOp::Max, source, descriptor, name)
+    }
+
+    /// Records a 2D average pooling
+    pub fn try_avg_pooling_2d(
+        &mut self,
+        source: TensorId,
+        descriptor: &Window2d,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_pooling_2d(PoolingOp::Average, source, descriptor, name)
+    }
+
+    /// Records a 2D L2 norm pooling
+    pub fn try_l2_norm_pooling_2d(
+        &mut self,
+        source: TensorId,
+        descriptor: &Window2d,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_pooling_2d(PoolingOp::L2Norm, source, descriptor, name)
+    }
+
+    /// Records an image to column transformation
+    ///
+    /// Every window of a `[N, C, H, W]` source becomes a column of a `[N, C*kH*kW, L]`
+    /// result, where `L` is the number of windows. An NHWC source gives `[N, L, kH*kW*C]`.
+    pub fn try_im_to_col(
+        &mut self,
+        source: TensorId,
+        descriptor: &Window2d,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.try_record(OpKind::ImToCol(*descriptor), &[source], name)
+    }
+
+    /// Records a resize of the spatial dimensions to `size` (`[height, width]`)
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_resize(
+        &mut self,
+        images_tensor: TensorId,
+        size: [usize; 2],
+        mode: MPSGraphResizeMode,
+        center_result: bool,
+        align_corners: bool,
+        layout: MPSGraphTensorNamedDataLayout,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::Resize {
+            size,
+            mode,
+            center_result,
+            align_corners,
+            data_layout: layout,
+        };
+        self.try_record(kind, &[images_tensor], name)
+    }
+
+    /// Records the `k` largest values along `axis` and their Int32 indices
+    pub fn try_top_k_axis(
+        &mut self,
+        source: TensorId,
+        axis: i64,
+        k: usize,
+        name: Option<&str>,
+    ) -> Result<(TensorId, TensorId)> {
+        self.try_top_k(source, axis, k, true, name)
+    }
+
+    /// Records the `k` smallest values along `axis` and their Int32 indices
+    pub fn try_bottom_k_axis(
+        &mut self,
+        source: TensorId,
+        axis: i64,
+        k: usize,
+        name: Option<&str>,
+    ) -> Result<(TensorId, TensorId)> {
+        self.try_top_k(source, axis, k, false, name)
+    }
+
+    /// Records a non-maximum suppression of `[N, B, 4]` boxes with `[N, B, K]` scores
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_non_maximum_suppression(
+        &mut self,
+        boxes_tensor: TensorId,
+        scores_tensor: TensorId,
+        iou_threshold: f32,
+        score_threshold: f32,
+        per_class_suppression: bool,
+        coordinate_mode: MPSGraphNonMaximumSuppressionCoordinateMode,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::NonMaximumSuppression {
+            iou_threshold,
+            score_threshold,
+            per_class_suppression,
+            coordinate_mode,
+        };
+        self.try_record(kind, &[boxes_tensor, scores_tensor], name)
+    }
+
+    panicking! {
+        convolution_2d => try_convolution_2d(
+            source: TensorId,
+            weights: TensorId,
+            descriptor: &Convolution2d,
+            name: Option<&str>,
+        ) -> TensorId;
+        max_pooling_2d => try_max_pooling_2d(
+            source: TensorId,
+            descriptor: &Window2d,
+            name: Option<&str>,
+        ) -> TensorId;
+        avg_pooling_2d => try_avg_pooling_2d(
+            source: TensorId,
+            descriptor: &Window2d,
+            name: Option<&str>,
+        ) -> TensorId;
+        l2_norm_pooling_2d => try_l2_norm_pooling_2d(
+            source: TensorId,
+            descriptor: &Window2d,
+            name: Option<&str>,
+        ) -> TensorId;
+        im_to_col => try_im_to_col(
+            source: TensorId,
+            descriptor: &Window2d,
+            name: Option<&str>,
+        ) -> TensorId;
+        top_k_axis => try_top_k_axis(
+            source: TensorId,
+            axis: i64,
+            k: usize,
+            name: Option<&str>,
+        ) -> (TensorId, TensorId);
+        bottom_k_axis => try_bottom_k_axis(
+            source: TensorId,
+            axis: i64,
+            k: usize,
+            name: Option<&str>,
+        ) -> (TensorId, TensorId);
+    }
+
+    /// Like [`try_resize`](Self::try_resize), but panics with a descriptive message if the
+    /// operands don't fit
+    #[allow(clippy::too_many_arguments)]
+    pub fn resize(
+        &mut self,
+        images_tensor: TensorId,
+        size: [usize; 2],
+        mode: MPSGraphResizeMode,
+        center_result: bool,
+        align_corners: bool,
+        layout: MPSGraphTensorNamedDataLayout,
+        name: Option<&str>,
+    ) -> TensorId {
+        expect(self.try_resize(
+            images_tensor,
+            size,
+            mode,
+            center_result,
+            align_corners,
+            layout,
+            name,
+        ))
+    }
+
+    /// Like [`try_non_maximum_suppression`](Self::try_non_maximum_suppression), but panics
+    /// with a descriptive message if the operands don't fit
+    #[allow(clippy::too_many_arguments)]
+    pub fn non_maximum_suppression(
+        &mut self,
+        boxes_tensor: TensorId,
+        scores_tensor: TensorId,
+        iou_threshold: f32,
+        score_threshold: f32,
+        per_class_suppression: bool,
+        coordinate_mode: MPSGraphNonMaximumSuppressionCoordinateMode,
+        name: Option<&str>,
+    ) -> TensorId {
+        expect(self.try_non_maximum_suppression(
+            boxes_tensor,
+            scores_tensor,
+            iou_threshold,
+            score_threshold,
+            per_class_suppression,
+            coordinate_mode,
+            name,
+        ))
+    }
+
+    fn try_top_k(
+        &mut self,
+        source: TensorId,
+        axis: i64,
+        k: usize,
+        largest: bool,
+        name: Option<&str>,
+    ) -> Result<(TensorId, TensorId)> {
+        let axis = self
+            .try_tensor(source)?
+            .shape
+            .normalize_axis(axis)
+            .map_err(|error| IrError::Shape { op: "top_k", error })?;
+        let results = self.try_add_operation(OpKind::TopK { axis, k, largest }, &[source], name)?;
+        Ok((results[0], results[1]))
+    }
+
+    fn try_pooling_2d(
+        &mut self,
+        op: PoolingOp,
+        source: TensorId,
+        descriptor: &Window2d,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let kind = OpKind::Pooling2d {
+            op,
+            window: *descriptor,
+        };
+        self.try_record(kind, &[source], name)
+    }
+
+    /// Normalizes reduction axes against the operand and records the reduction
+    fn try_reduction(
+        &mut self,
+        op: ReductionOp,
+        tensor: TensorId,
+        axes: Option<&[i64]>,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let shape = &self.try_tensor(tensor)?.shape;
+        let axes = match axes {
+            Some(axes) => shape.normalize_axes(axes).map_err(|error| IrError::Shape {
+                op: "reduction",
+                error,
+            })?,
+            None => (0..shape.rank()).collect(),
+        };
+        self.try_record(OpKind::Reduction { op, axes }, &[tensor], name)
+    }
+}
@@ -0,0 +1,472 @@
+//! Shape-checked `MPSGraph` builders.
+//!
+//! Each `try_` method here runs the inference rule the recorded [`Graph`](super::Graph) uses
+//! on the shapes and data types of its `MPSGraphTensor` operands, and returns an [`IrError`]
+//! naming the operation, the operands and the offending dimensions instead of letting
+//! MPSGraph assert. When the operands fit, it creates the operation with the method of the
+//! same name without the prefix. Operands of unknown rank can't be checked and are passed
+//! straight to MPSGraph.
+
+use super::infer::infer_results;
+use super::{BinaryOp, IrError, OpId, OpKind, ReductionOp, TensorInfo};
+use crate::graph::MPSGraph;
+use crate::loss_ops::MPSGraphLossReductionType;
+use crate::tensor::MPSGraphTensor;
+use objc2::msg_send;
+use objc2::runtime::AnyObject;
+
+type Result<T> = std::result::Result<T, IrError>;
+
+/// Describes the operands for inference, or returns `None` if one of them is unranked
+fn operand_infos(
+    op: &'static str,
+    operands: &[&MPSGraphTensor],
+) -> Result<Option<Vec<TensorInfo>>> {
+    let mut infos = Vec::with_capacity(operands.len());
+    for tensor in operands {
+        let shape: *mut AnyObject = unsafe { msg_send![tensor.0, shape] };
+        if shape.is_null() {
+            return Ok(None);
+        }
+        let data_type = tensor.try_data_type().map_err(|error| IrError::Invalid {
+            op,
+            reason: error.to_string(),
+        })?;
+        infos.push(TensorInfo {
+            data_type,
+            shape: tensor.shape().to_shape(),
+            name: None,
+            producer: OpId(0),
+        });
+    }
+    Ok(Some(infos))
+}
+
+/// Checks that `kind` can be applied to `operands`
+fn check(kind: &OpKind, operands: &[&MPSGraphTensor]) -> Result<()> {
+    if let Some(infos) = operand_infos(kind.name(), operands)? {
+        let infos: Vec<&TensorInfo> = infos.iter().collect();
+        infer_results(kind, &infos)?;
+    }
+    Ok(())
+}
+
+/// Checks a reduction over `axes`, or every axis if `None`
+fn check_reduction(op: ReductionOp, tensor: &MPSGraphTensor, axes: Option<&[i64]>) -> Result<()> {
+    let Some(infos) = operand_infos("reduction", &[tensor])? else {
+        return Ok(());
+    };
+    let shape = &infos[0].shape;
+    let axes = match axes {
+        Some(axes) => shape.normalize_axes(axes).map_err(|error| IrError::Shape {
+            op: "reduction",
+            error,
+        })?,
+        None => (0..shape.rank()).collect(),
+    };
+    infer_results(&OpKind::Reduction { op, axes }, &[&infos[0]])?;
+    Ok(())
+}
+
+macro_rules! binary_ops {
+    ($($method:ident, $try_method:ident => $op:ident),* $(,)?) => {
+        $(
+            #[doc = concat!(
+                "Like [`", stringify!($method), "`](Self::", stringify!($method),
+                "), but checks that the operands broadcast and share a data type"
+            )]
+            pub fn $try_method(
+                &self,
+                primary: &MPSGraphTensor,
+                secondary: &MPSGraphTensor,
+                name: Option<&str>,
+            ) -> Result<MPSGraphTensor> {
+                check(&OpKind::Binary(BinaryOp::$op), &[primary, secondary])?;
+                Ok(self.$method(primary, secondary, name))
+            }
+        )*
+    };
+}
+
+macro_rules! reduction_ops {
+    ($($axis_method:ident, $try_axis_method:ident, $axes_method:ident, $try_axes_method:ident => $op:ident),* $(,)?) => {
+        $(
+            #[doc = concat!(
+                "Like [`", stringify!($axis_method), "`](Self::", stringify!($axis_method),
+                "), but checks the axis against the rank of `tensor`"
+            )]
+            pub fn $try_axis_method(
+                &self,
+                tensor: &MPSGraphTensor,
+                axis: i64,
+                name: Option<&str>,
+            ) -> Result<MPSGraphTensor> {
+                check_reduction(ReductionOp::$op, tensor, Some(&[axis]))?;
+                Ok(self.$axis_method(tensor, axis, name))
+            }
+
+            #[doc = concat!(
+                "Like [`", stringify!($axes_method), "`](Self::", stringify!($axes_method),
+                "), but checks the axes against the rank of `tensor`"
+            )]
+            pub fn $try_axes_method(
+                &self,
+                tensor: &MPSGraphTensor,
+                axes: Option<&[i64]>,
+                name: Option<&str>,
+            ) -> Result<MPSGraphTensor> {
+                check_reduction(ReductionOp::$op, tensor, axes)?;
+                Ok(self.$axes_method(tensor, axes, name))
+            }
+        )*
+    };
+}
+
+/// Shape-checked builders
+impl MPSGraph {
+    binary_ops! {
+        add, try_add => Add,
+        subtract, try_subtract => Subtract,
+        multiply, try_multiply => Multiply,
+        divide, try_divide => Divide,
+        division_no_nan, try_division_no_nan => DivisionNoNan,
+        modulo, try_modulo => Modulo,
+        floor_modulo, try_floor_modulo => FloorModulo,
+        power, try_power => Power,
+        minimum, try_minimum => Minimum,
+        maximum, try_maximum => Maximum,
+        minimum_with_nan_propagation, try_minimum_with_nan_propagation => MinimumWithNanPropagation,
+        maximum_with_nan_propagation, try_maximum_with_nan_propagation => MaximumWithNanPropagation,
+        atan2, try_atan2 => Atan2,
+        equal, try_equal => Equal,
+        not_equal, try_not_equal => NotEqual,
+        less_than, try_less_than => LessThan,
+        less_than_or_equal_to, try_less_than_or_equal_to => LessThanOrEqualTo,
+        greater_than, try_greater_than => GreaterThan,
+        greater_than_or_equal_to, try_greater_than_or_equal_to => GreaterThanOrEqualTo,
+        logical_and, try_logical_and => LogicalAnd,
+        logical_or, try_logical_or => LogicalOr,
+        logical_xor, try_logical_xor => LogicalXor,
+        logical_nand, try_logical_nand => LogicalNand,
+        logical_nor, try_logical_nor => LogicalNor,
+        logical_xnor, try_logical_xnor => LogicalXnor,
+        bitwise_and, try_bitwise_and => BitwiseAnd,
+        bitwise_or, try_bitwise_or => BitwiseOr,
+        bitwise_xor, try_bitwise_xor => BitwiseXor,
+        left_shift, try_left_shift => LeftShift,
+        right_shift, try_right_shift => RightShift,
+    }
+
+    /// Like [`select`](Self::select), but checks that the predicate is Bool and the
+    /// operands broadcast
+    pub fn try_select(
+        &self,
+        predicate: &MPSGraphTensor,
+        true_tensor: &MPSGraphTensor,
+        false_tensor: &MPSGraphTensor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        check(&OpKind::Select, &[predicate, true_tensor, false_tensor])?;
+        Ok(self.select(predicate, true_tensor, false_tensor, name))
+    }
+
+    /// Like [`clamp`](Self::clamp), but checks that the operands broadcast and share a
+    /// data type
+    pub fn try_clamp(
+        &self,
+        tensor: &MPSGraphTensor,
+        min_tensor: &MPSGraphTensor,
+        max_tensor: &MPSGraphTensor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        check(&OpKind::Clamp, &[tensor, min_tensor, max_tensor])?;
+        Ok(self.clamp(tensor, min_tensor, max_tensor, name))
+    }
+
+    /// Like [`matmul`](Self::matmul), but checks the inner and batch dimensions
+    pub fn try_matmul(
+        &self,
+        primary: &MPSGraphTensor,
+        secondary: &MPSGraphTensor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        check(&OpKind::MatMul, &[primary, secondary])?;
+        Ok(self.matmul(primary, secondary, name))
+    }
+
+    reduction_ops! {
+        reduction_sum_with_tensor_axis, try_reduction_sum_with_tensor_axis,
+        reduction_sum_with_tensor_axes, try_reduction_sum_with_tensor_axes => Sum,
+        reduction_maximum_with_tensor_axis, try_reduction_maximum_with_tensor_axis,
+        reduction_maximum_with_tensor_axes, try_reduction_maximum_with_tensor_axes => Maximum,
+        reduction_minimum_with_tensor_axis, try_reduction_minimum_with_tensor_axis,
+        reduction_minimum_with_tensor_axes, try_reduction_minimum_with_tensor_axes => Minimum,
+        reduction_maximum_propagate_nan_with_tensor_axis,
+        try_reduction_maximum_propagate_nan_with_tensor_axis,
+        reduction_maximum_propagate_nan_with_tensor_axes,
+        try_reduction_maximum_propagate_nan_with_tensor_axes => MaximumPropagateNan,
+        reduction_minimum_propagate_nan_with_tensor_axis,
+        try_reduction_minimum_propagate_nan_with_tensor_axis,
+        reduction_minimum_propagate_nan_with_tensor_axes,
+        try_reduction_minimum_propagate_nan_with_tensor_axes => MinimumPropagateNan,
+        reduction_product_with_tensor_axis, try_reduction_product_with_tensor_axis,
+        reduction_product_with_tensor_axes, try_reduction_product_with_tensor_axes => Product,
+        reduction_and_with_tensor_axis, try_reduction_and_with_tensor_axis,
+        reduction_and_with_tensor_axes, try_reduction_and_with_tensor_axes => And,
+        reduction_or_with_tensor_axis, try_reduction_or_with_tensor_axis,
+        reduction_or_with_tensor_axes, try_reduction_or_with_tensor_axes => Or,
+        reduction_xor_with_tensor_axis, try_reduction_xor_with_tensor_axis,
+        reduction_xor_with_tensor_axes, try_reduction_xor_with_tensor_axes => Xor,
+    }
+
+    /// Like [`mean`](Self::mean), but checks the axes against the rank of `tensor`
+    pub fn try_mean(
+        &self,
+        tensor: &MPSGraphTensor,
+        axes: &[i64],
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        check_reduction(ReductionOp::Mean, tensor, Some(axes))?;
+        Ok(self.mean(tensor, axes, name))
+    }
+
+    /// Like [`reduction_arg_maximum_with_tensor_axis`](Self::reduction_arg_maximum_with_tensor_axis),
+    /// but checks the axis against the rank of `tensor`
+    pub fn try_reduction_arg_maximum_with_tensor_axis(
+        &self,
+        tensor: &MPSGraphTensor,
+        axis: i64,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        check_reduction(ReductionOp::ArgMaximum, tensor, Some(&[axis]))?;
+        Ok(self.reduction_arg_maximum_with_tensor_axis(tensor, axis, name))
+    }
+
+    /// Like [`reduction_arg_minimum_with_tensor_axis`](Self::reduction_arg_minimum_with_tensor_axis),
+    /// but checks the axis against the rank of `tensor`
+    pub fn try_reduction_arg_minimum_with_tensor_axis(
+        &self,
+        tensor: &MPSGraphTensor,
+        axis: i64,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        check_reduction(ReductionOp::ArgMinimum, tensor, Some(&[axis]))?;
+        Ok(self.reduction_arg_minimum_with_tensor_axis(tensor, axis, name))
+    }
+
+    /// Like [`reshape`](Self::reshape), but checks that the element count is preserved
+    pub fn try_reshape(
+        &self,
+        x: &MPSGraphTensor,
+        shape: &[i64],
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        let kind = OpKind::Reshape {
+            shape: shape.to_vec(),
+        };
+        check(&kind, &[x])?;
+        Ok(self.reshape(x, shape, name))
+    }
+
+    /// Like [`transpose`](Self::transpose), but checks that `dimensions` is a permutation of
+    /// the axes of `x`
+    pub fn try_transpose(
+        &self,
+        x: &MPSGraphTensor,
+        dimensions: &[usize],
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        let kind = OpKind::Transpose {
+            permutation: dimensions.to_vec(),
+        };
+        check(&kind, &[x])?;
+        Ok(self.transpose(x, dimensions, name))
+    }
+
+    /// Like [`broadcast`](Self::broadcast), but checks that `x` broadcasts to `shape`
+    pub fn try_broadcast(
+        &self,
+        x: &MPSGraphTensor,
+        shape: &[i64],
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        let kind = OpKind::Broadcast {
+            shape: shape.to_vec(),
+        };
+        check(&kind, &[x])?;
+        Ok(self.broadcast(x, shape, name))
+    }
+
+    /// Like [`concatenate`](Self::concatenate), but checks that the tensors agree outside
+    /// `dimension`
+    pub fn try_concatenate(
+        &self,
+        tensors: &[MPSGraphTensor],
+        dimension: i64,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        let operands: Vec<&MPSGraphTensor> = tensors.iter().collect();
+        check(&OpKind::Concat { axis: dimension }, &operands)?;
+        Ok(self.concatenate(tensors, dimension, name))
+    }
+
+    /// Like [`stack`](Self::stack), but checks that the tensors have the same shape
+    pub fn try_stack(
+        &self,
+        tensors: &[MPSGraphTensor],
+        axis: i64,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        let operands: Vec<&MPSGraphTensor> = tensors.iter().collect();
+        check(&OpKind::Stack { axis }, &operands)?;
+        Ok(self.stack(tensors, axis, name))
+    }
+
+    /// Like [`squeeze`](Self::squeeze), but checks that the squeezed axes have size 1
+    pub fn try_squeeze(
+        &self,
+        x: &MPSGraphTensor,
+        axes: &[i64],
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        let kind = OpKind::Squeeze {
+            axes: axes.to_vec(),
+        };
+        check(&kind, &[x])?;
+        Ok(self.squeeze(x, axes, name))
+    }
+
+    /// Like [`expand_dims`](Self::expand_dims), but checks the axes against the result rank
+    pub fn try_expand_dims(
+        &self,
+        x: &MPSGraphTensor,
+        axes: &[i64],
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        let kind = OpKind::ExpandDims {
+            axes: axes.to_vec(),
+        };
+        check(&kind, &[x])?;
+        Ok(self.expand_dims(x, axes, name))
+    }
+
+    /// Like [`tile`](Self::tile), but checks that there is one multiple per axis
+    pub fn try_tile(
+        &self,
+        x: &MPSGraphTensor,
+        multiples: &[i64],
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        let kind = OpKind::Tile {
+            multiples: multiples.to_vec(),
+        };
+        check(&kind, &[x])?;
+        Ok(self.tile(x, multiples, name))
+    }
+
+    /// Like [`slice`](Self::slice), but checks the range against the size of `dimension`
+    pub fn try_slice(
+        &self,
+        x: &MPSGraphTensor,
+        dimension: usize,
+        start: i64,
+        length: i64,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        let kind = OpKind::Slice {
+            dimension,
+            start,
+            length,
+        };
+        check(&kind, &[x])?;
+        Ok(self.slice(x, dimension, start, length, name))
+    }
+
+    /// Like [`gather`](Self::gather), but checks the axis, batch dimensions and index type
+    pub fn try_gather(
+        &self,
+        updates_tensor: &MPSGraphTensor,
+        indices_tensor: &MPSGraphTensor,
+        axis: usize,
+        batch_dimensions: usize,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        let kind = OpKind::Gather {
+            axis,
+            batch_dimensions,
+        };
+        check(&kind, &[updates_tensor, indices_tensor])?;
+        Ok(self.gather(updates_tensor, indices_tensor, axis, batch_dimensions, name))
+    }
+
+    /// Like [`gather_along_axis`](Self::gather_along_axis), but checks the axis, ranks and
+    /// index type
+    pub fn try_gather_along_axis(
+        &self,
+        axis: isize,
+        updates_tensor: &MPSGraphTensor,
+        indices_tensor: &MPSGraphTensor,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        check(
+            &OpKind::GatherAlongAxis { axis },
+            &[updates_tensor, indices_tensor],
+        )?;
+        Ok(self.gather_along_axis(axis, updates_tensor, indices_tensor, name))
+    }
+
+    /// Like [`softmax`](Self::softmax), but checks the axis against the rank of `x`
+    pub fn try_softmax(
+        &self,
+        x: &MPSGraphTensor,
+        axis: i64,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        check(&OpKind::Softmax { axis }, &[x])?;
+        Ok(self.softmax(x, axis, name))
+    }
+
+    /// Like [`softmax_cross_entropy`](Self::softmax_cross_entropy), but checks that the
+    /// logits and labels broadcast and share a data type
+    pub fn try_softmax_cross_entropy(
+        &self,
+        source_tensor: &MPSGraphTensor,
+        labels_tensor: &MPSGraphTensor,
+        axis: i64,
+        reduction_type: MPSGraphLossReductionType,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        let kind = OpKind::SoftmaxCrossEntropy {
+            axis,
+            reduction: reduction_type,
+        };
+        check(&kind, &[source_tensor, labels_tensor])?;
+        Ok(self.softmax_cross_entropy(source_tensor, labels_tensor, axis, reduction_type, name))
+    }
+
+    /// Like [`softmax_cross_entropy_gradient`](Self::softmax_cross_entropy_gradient), but
+    /// checks that the gradient, logits and labels broadcast and share a data type
+    pub fn try_softmax_cross_entropy_gradient(
+        &self,
+        gradient_tensor: &MPSGraphTensor,
+        source_tensor: &MPSGraphTensor,
+        labels_tensor: &MPSGraphTensor,
+        axis: i64,
+        reduction_type: MPSGraphLossReductionType,
+        name: Option<&str>,
+    ) -> Result<MPSGraphTensor> {
+        let kind = OpKind::SoftmaxCrossEntropyGradient {
+            axis,
+            reduction: reduction_type,
+        };
+        check(&kind, &[gradient_tensor, source_tensor, labels_tensor])?;
+        Ok(self.softmax_cross_entropy_gradient(
+            gradient_tensor,
+            source_tensor,
+            labels_tensor,
+            axis,
+            reduction_type,
+            name,
+        ))
+    }
+}
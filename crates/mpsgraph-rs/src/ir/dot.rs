@@ -0,0 +1,70 @@
+//! Graphviz DOT export of recorded graphs.
+
+use super::{Graph, OpKind, TensorId};
+use crate::dot::{DotEdge, DotGraph, DotOptions};
+
+impl Graph {
+    /// Renders the operations `targets` depend on as a Graphviz DOT graph
+    ///
+    /// Operations are labelled with their name and kind (the function, for elementwise
+    /// operations), and data edges with the shape and data type of the tensor they carry.
+    /// Each target is drawn as an ellipse fed by its producer. Recorded graphs have no
+    /// control dependencies.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a target was not recorded in this graph.
+    pub fn to_dot(&self, targets: &[TensorId], options: &DotOptions) -> String {
+        let edge_label = |id: TensorId| format!("{} {:?}", self.shape(id), self.data_type(id));
+
+        // Mark the operations the targets depend on
+        let mut needed = vec![false; self.operations.len()];
+        let mut pending = targets.to_vec();
+        while let Some(id) = pending.pop() {
+            let op = self.tensor(id).producer;
+            if !needed[op.0] {
+                needed[op.0] = true;
+                pending.extend(&self.operations[op.0].inputs);
+            }
+        }
+
+        // Recording order is topological, so producers get their nodes first
+        let mut dot = DotGraph::default();
+        let mut nodes = vec![None; self.operations.len()];
+        for (index, op) in self.operations.iter().enumerate() {
+            if !needed[index] {
+                continue;
+            }
+            let kind = match &op.kind {
+                OpKind::Unary(unary) => format!("{:?}", unary),
+                OpKind::Binary(binary) => format!("{:?}", binary),
+                OpKind::Reduction { op, .. } => format!("reduction {:?}", op),
+                kind => kind.name().to_string(),
+            };
+            let label = match &op.name {
+                Some(name) => format!("{}\n{}", name, kind),
+                None => kind,
+            };
+            let node = dot.add_node(label, op.name.clone(), false);
+            nodes[index] = Some(node);
+            for &input in &op.inputs {
+                dot.edges.push(DotEdge {
+                    from: nodes[self.tensor(input).producer.0].expect("producer recorded first"),
+                    to: node,
+                    label: Some(edge_label(input)),
+                });
+            }
+        }
+
+        for &target in targets {
+            let label = self.tensor(target).name.clone();
+            let output = dot.add_node(label.unwrap_or_else(|| target.to_string()), None, true);
+            dot.edges.push(DotEdge {
+                from: nodes[self.tensor(target).producer.0].expect("target producer is needed"),
+                to: output,
+                label: Some(edge_label(target)),
+            });
+        }
+        dot.render(options)
+    }
+}
@@ -0,0 +1,1122 @@
+//! Result type inference for recorded operations.
+
+use super::{OpKind, ReductionOp, TensorId, TensorInfo};
+use crate::convolution_ops::MPSGraphWeightsLayout;
+use crate::core::MPSDataType;
+use crate::dims::{normalize_axis, Dim, Shape, ShapeError};
+use crate::loss_ops::MPSGraphLossReductionType;
+use crate::pooling_ops::{MPSGraphPaddingStyle, MPSGraphTensorNamedDataLayout};
+use std::fmt;
+
+/// Errors reported when an operation can't be recorded
+#[derive(Debug, Clone, PartialEq)]
+pub enum IrError {
+    /// An operand was not recorded in this graph
+    UnknownTensor(TensorId),
+    /// The operation got the wrong number of operands
+    WrongOperandCount {
+        /// Operation kind
+        op: &'static str,
+        /// Number of operands the operation takes
+        expected: usize,
+        /// Number of operands supplied
+        actual: usize,
+    },
+    /// Operands that must share a data type don't
+    DataTypeMismatch {
+        /// Operation kind
+        op: &'static str,
+        /// Data type of the first operand
+        expected: MPSDataType,
+        /// Data type of the offending operand
+        actual: MPSDataType,
+    },
+    /// Operand shapes don't fit the operation
+    Shape {
+        /// Operation kind
+        op: &'static str,
+        /// The underlying shape error
+        error: ShapeError,
+    },
+    /// A dimension of an operand doesn't fit the operation
+    Dimension {
+        /// Operation kind
+        op: &'static str,
+        /// Role of the operand, such as `"source"` or `"weights"`
+        operand: &'static str,
+        /// Axis of the offending dimension
+        axis: usize,
+        /// Size of the offending dimension
+        size: usize,
+        /// What the dimension had to match
+        reason: String,
+    },
+    /// An attribute or operand is invalid for the operation
+    Invalid {
+        /// Operation kind
+        op: &'static str,
+        /// What is wrong
+        reason: String,
+    },
+}
+
+impl fmt::Display for IrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IrError::UnknownTensor(id) => write!(f, "tensor {} is not part of this graph", id),
+            IrError::WrongOperandCount {
+                op,
+                expected,
+                actual,
+            } => write!(f, "{} takes {} operands, got {}", op, expected, actual),
+            IrError::DataTypeMismatch {
+                op,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} operands must have the same data type, got {:?} and {:?}",
+                op, expected, actual
+            ),
+            IrError::Shape { op, error } => write!(f, "{}: {}", op, error),
+            IrError::Dimension {
+                op,
+                operand,
+                axis,
+                size,
+                reason,
+            } => write!(
+                f,
+                "{}: {} dimension {} has size {}, {}",
+                op, operand, axis, size, reason
+            ),
+            IrError::Invalid { op, reason } => write!(f, "{}: {}", op, reason),
+        }
+    }
+}
+
+impl std::error::Error for IrError {}
+
+/// Result data type and shape of each output
+type Results = Vec<(MPSDataType, Shape)>;
+
+/// Infers the results of `kind` applied to `operands`
+pub(crate) fn infer_results(kind: &OpKind, operands: &[&TensorInfo]) -> Result<Results, IrError> {
+    let op = kind.name();
+    let shape_error = |error| IrError::Shape { op, error };
+    let invalid = |reason: String| IrError::Invalid { op, reason };
+
+    match kind {
+        OpKind::Placeholder { shape, data_type } => {
+            expect_operands(op, operands, 0)?;
+            Ok(vec![(*data_type, shape.clone())])
+        }
+        OpKind::Constant {
+            data,
+            shape,
+            data_type,
+        } => {
+            expect_operands(op, operands, 0)?;
+            let too_large = || invalid(format!("constant shape {} is too large", shape));
+            let count = match shape.element_count() {
+                Some(count) => count,
+                None if shape.is_static() => return Err(too_large()),
+                None => return Err(invalid(format!("constant shape {} must be static", shape))),
+            };
+            let expected = data_type
+                .packed_size_in_bytes(count)
+                .ok_or_else(too_large)?;
+            if data.len() != expected {
+                return Err(invalid(format!(
+                    "{} {:?} elements need {} bytes, got {}",
+                    count,
+                    data_type,
+                    expected,
+                    data.len()
+                )));
+            }
+            Ok(vec![(*data_type, shape.clone())])
+        }
+        OpKind::ConstantScalar {
+            shape, data_type, ..
+        } => {
+            expect_operands(op, operands, 0)?;
+            Ok(vec![(*data_type, shape.clone())])
+        }
+        OpKind::Unary(unary) => {
+            expect_operands(op, operands, 1)?;
+            if unary.is_bitwise() {
+                expect_bitwise(op, operands[0].data_type)?;
+            }
+            let data_type = if unary.is_predicate() {
+                MPSDataType::Bool
+            } else {
+                operands[0].data_type
+            };
+            Ok(vec![(data_type, operands[0].shape.clone())])
+        }
+        OpKind::Binary(binary) => {
+            expect_operands(op, operands, 2)?;
+            same_data_type(op, operands)?;
+            if binary.is_bitwise() {
+                expect_bitwise(op, operands[0].data_type)?;
+            }
+            let shape = operands[0]
+                .shape
+                .broadcast(&operands[1].shape)
+                .map_err(shape_error)?;
+            let data_type = if binary.is_predicate() {
+                MPSDataType::Bool
+            } else {
+                operands[0].data_type
+            };
+            Ok(vec![(data_type, shape)])
+        }
+        OpKind::Select => {
+            expect_operands(op, operands, 3)?;
+            if operands[0].data_type != MPSDataType::Bool {
+                return Err(invalid(format!(
+                    "predicate must be Bool, got {:?}",
+                    operands[0].data_type
+                )));
+            }
+            same_data_type(op, &operands[1..])?;
+            let shape = broadcast_all(operands).map_err(shape_error)?;
+            Ok(vec![(operands[1].data_type, shape)])
+        }
+        OpKind::Clamp => {
+            expect_operands(op, operands, 3)?;
+            same_data_type(op, operands)?;
+            let shape = broadcast_all(operands).map_err(shape_error)?;
+            Ok(vec![(operands[0].data_type, shape)])
+        }
+        OpKind::Cast { data_type } => {
+            expect_operands(op, operands, 1)?;
+            Ok(vec![(*data_type, operands[0].shape.clone())])
+        }
+        OpKind::MatMul => {
+            expect_operands(op, operands, 2)?;
+            same_data_type(op, operands)?;
+            let shape = matmul_shape(op, &operands[0].shape, &operands[1].shape)?;
+            Ok(vec![(operands[0].data_type, shape)])
+        }
+        OpKind::Reduction {
+            op: reduction,
+            axes,
+        } => {
+            expect_operands(op, operands, 1)?;
+            let shape = &operands[0].shape;
+            let mut dims = shape.dims().to_vec();
+            for &axis in axes {
+                if axis >= dims.len() {
+                    return Err(shape_error(ShapeError::AxisOutOfRange {
+                        axis: axis as i64,
+                        rank: dims.len(),
+                    }));
+                }
+                dims[axis] = Dim::Static(1);
+            }
+            let data_type = match reduction {
+                ReductionOp::ArgMaximum | ReductionOp::ArgMinimum => {
+                    if axes.len() != 1 {
+                        return Err(invalid(format!(
+                            "arg reductions take exactly one axis, got {}",
+                            axes.len()
+                        )));
+                    }
+                    MPSDataType::Int32
+                }
+                _ => operands[0].data_type,
+            };
+            Ok(vec![(data_type, Shape::new(dims))])
+        }
+        OpKind::Reshape { shape } => {
+            expect_operands(op, operands, 1)?;
+            let shape = operands[0].shape.reshape(shape).map_err(shape_error)?;
+            Ok(vec![(operands[0].data_type, shape)])
+        }
+        OpKind::Transpose { permutation } => {
+            expect_operands(op, operands, 1)?;
+            let permutation: Vec<i64> = permutation.iter().map(|&axis| axis as i64).collect();
+            let shape = operands[0]
+                .shape
+                .permute(&permutation)
+                .map_err(shape_error)?;
+            Ok(vec![(operands[0].data_type, shape)])
+        }
+        OpKind::Broadcast { shape } => {
+            expect_operands(op, operands, 1)?;
+            let target = Shape::from_i64(shape);
+            if operands[0].shape.rank() > target.rank() {
+                return Err(invalid(format!(
+                    "can't broadcast rank {} tensor to {}",
+                    operands[0].shape.rank(),
+                    target
+                )));
+            }
+            let shape = operands[0].shape.broadcast(&target).map_err(shape_error)?;
+            Ok(vec![(operands[0].data_type, shape)])
+        }
+        OpKind::Concat { axis } => {
+            expect_some_operands(op, operands)?;
+            same_data_type(op, operands)?;
+            let first = &operands[0].shape;
+            let axis = normalize_axis(*axis, first.rank()).map_err(shape_error)?;
+            let mut dims = first.dims().to_vec();
+            for operand in &operands[1..] {
+                let shape = &operand.shape;
+                if shape.rank() != first.rank() {
+                    return Err(invalid(format!(
+                        "operands have ranks {} and {}",
+                        first.rank(),
+                        shape.rank()
+                    )));
+                }
+                for (i, dim) in dims.iter_mut().enumerate() {
+                    *dim = if i == axis {
+                        match (*dim, shape[i]) {
+                            (Dim::Static(a), Dim::Static(b)) => {
+                                Dim::Static(a.checked_add(b).ok_or_else(|| {
+                                    invalid(format!("concatenating along axis {} overflows", axis))
+                                })?)
+                            }
+                            _ => Dim::Dynamic,
+                        }
+                    } else {
+                        merge_dims(*dim, shape[i]).ok_or_else(|| {
+                            invalid(format!(
+                                "shapes {} and {} differ outside axis {}",
+                                first, shape, axis
+                            ))
+                        })?
+                    };
+                }
+            }
+            Ok(vec![(operands[0].data_type, Shape::new(dims))])
+        }
+        OpKind::Stack { axis } => {
+            expect_some_operands(op, operands)?;
+            same_data_type(op, operands)?;
+            let first = &operands[0].shape;
+            if let Some(other) = operands.iter().find(|operand| operand.shape != *first) {
+                return Err(invalid(format!(
+                    "operands must have the same shape, got {} and {}",
+                    first, other.shape
+                )));
+            }
+            let axis = normalize_axis(*axis, first.rank() + 1).map_err(shape_error)?;
+            let mut dims = first.dims().to_vec();
+            dims.insert(axis, Dim::Static(operands.len()));
+            Ok(vec![(operands[0].data_type, Shape::new(dims))])
+        }
+        OpKind::Split { num_splits, axis } => {
+            expect_operands(op, operands, 1)?;
+            let shape = &operands[0].shape;
+            let axis = shape.normalize_axis(*axis).map_err(shape_error)?;
+            if *num_splits == 0 {
+                return Err(invalid(String::from("num_splits must be positive")));
+            }
+            let mut dims = shape.dims().to_vec();
+            dims[axis] = match dims[axis] {
+                Dim::Static(size) if size % num_splits != 0 => {
+                    return Err(invalid(format!(
+                        "axis {} of size {} can't be split into {} parts",
+                        axis, size, num_splits
+                    )))
+                }
+                Dim::Static(size) => Dim::Static(size / num_splits),
+                Dim::Dynamic => Dim::Dynamic,
+            };
+            let result = (operands[0].data_type, Shape::new(dims));
+            Ok(vec![result; *num_splits])
+        }
+        OpKind::Squeeze { axes } => {
+            expect_operands(op, operands, 1)?;
+            let shape = &operands[0].shape;
+            let axes = if axes.is_empty() {
+                (0..shape.rank())
+                    .filter(|&i| shape[i] == Dim::Static(1))
+                    .collect()
+            } else {
+                shape.normalize_axes(axes).map_err(shape_error)?
+            };
+            if let Some(&axis) = axes
+                .iter()
+                .find(|&&axis| matches!(shape[axis], Dim::Static(size) if size != 1))
+            {
+                return Err(invalid(format!(
+                    "axis {} of {} has size {}, not 1",
+                    axis, shape, shape[axis]
+                )));
+            }
+            let dims = (0..shape.rank())
+                .filter(|i| !axes.contains(i))
+                .map(|i| shape[i])
+                .collect();
+            Ok(vec![(operands[0].data_type, Shape::new(dims))])
+        }
+        OpKind::ExpandDims { axes } => {
+            expect_operands(op, operands, 1)?;
+            let shape = &operands[0].shape;
+            let rank = shape.rank() + axes.len();
+            let mut new_axes = Vec::with_capacity(axes.len());
+            for &axis in axes {
+                let axis = normalize_axis(axis, rank).map_err(shape_error)?;
+                if new_axes.contains(&axis) {
+                    return Err(shape_error(ShapeError::DuplicateAxis { axis }));
+                }
+                new_axes.push(axis);
+            }
+            let mut source = shape.dims().iter();
+            let dims = (0..rank)
+                .map(|i| {
+                    if new_axes.contains(&i) {
+                        Dim::Static(1)
+                    } else {
+                        *source.next().unwrap()
+                    }
+                })
+                .collect();
+            Ok(vec![(operands[0].data_type, Shape::new(dims))])
+        }
+        OpKind::Tile { multiples } => {
+            expect_operands(op, operands, 1)?;
+            let shape = &operands[0].shape;
+            if multiples.len() != shape.rank() || multiples.iter().any(|&m| m < 0) {
+                return Err(invalid(format!(
+                    "multiples {:?} don't fit shape {}",
+                    multiples, shape
+                )));
+            }
+            let dims = shape
+                .dims()
+                .iter()
+                .zip(multiples)
+                .map(|(dim, &m)| match dim {
+                    Dim::Static(size) => {
+                        size.checked_mul(m as usize)
+                            .map(Dim::Static)
+                            .ok_or_else(|| {
+                                invalid(format!("tiling {} by {:?} overflows", shape, multiples))
+                            })
+                    }
+                    Dim::Dynamic => Ok(Dim::Dynamic),
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(vec![(operands[0].data_type, Shape::new(dims))])
+        }
+        OpKind::Reverse { axes } => {
+            expect_operands(op, operands, 1)?;
+            operands[0]
+                .shape
+                .normalize_axes(axes)
+                .map_err(shape_error)?;
+            Ok(vec![(operands[0].data_type, operands[0].shape.clone())])
+        }
+        OpKind::Flatten2d { axis } => {
+            expect_operands(op, operands, 1)?;
+            let shape = &operands[0].shape;
+            let axis = normalize_axis(*axis, shape.rank() + 1).map_err(shape_error)?;
+            let product = |dims: &[Dim]| {
+                dim_product(dims).ok_or_else(|| invalid(format!("flattening {} overflows", shape)))
+            };
+            let dims = vec![
+                product(&shape.dims()[..axis])?,
+                product(&shape.dims()[axis..])?,
+            ];
+            Ok(vec![(operands[0].data_type, Shape::new(dims))])
+        }
+        OpKind::Slice {
+            dimension,
+            start,
+            length,
+        } => {
+            expect_operands(op, operands, 1)?;
+            let shape = &operands[0].shape;
+            if *dimension >= shape.rank() {
+                return Err(shape_error(ShapeError::AxisOutOfRange {
+                    axis: *dimension as i64,
+                    rank: shape.rank(),
+                }));
+            }
+            if *length < 0 {
+                return Err(invalid(format!("length {} is negative", length)));
+            }
+            if let Dim::Static(size) = shape[*dimension] {
+                let begin = if *start < 0 {
+                    *start + size as i64
+                } else {
+                    *start
+                };
+                if begin < 0
+                    || begin
+                        .checked_add(*length)
+                        .is_none_or(|end| end > size as i64)
+                {
+                    return Err(invalid(format!(
+                        "[{}, {}+{}) is out of range for axis {} of size {}",
+                        start, start, length, dimension, size
+                    )));
+                }
+            }
+            let mut dims = shape.dims().to_vec();
+            dims[*dimension] = Dim::Static(*length as usize);
+            Ok(vec![(operands[0].data_type, Shape::new(dims))])
+        }
+        OpKind::StridedSlice {
+            starts,
+            ends,
+            strides,
+            masks,
+        } => {
+            expect_operands(op, operands, 1)?;
+            let shape = &operands[0].shape;
+            if starts.len() != shape.rank()
+                || ends.len() != shape.rank()
+                || strides.len() != shape.rank()
+            {
+                return Err(invalid(format!(
+                    "expected {} entries per slice array, got {}, {} and {}",
+                    shape.rank(),
+                    starts.len(),
+                    ends.len(),
+                    strides.len()
+                )));
+            }
+            let mut dims = Vec::with_capacity(shape.rank());
+            for axis in 0..shape.rank() {
+                let bit = 1 << axis;
+                if strides[axis] == 0 {
+                    return Err(invalid(format!("stride of axis {} is 0", axis)));
+                }
+                let dim = match shape[axis] {
+                    Dim::Static(size) => Dim::Static(
+                        strided_slice_range(
+                            size,
+                            starts[axis],
+                            ends[axis],
+                            strides[axis],
+                            masks.start_mask & bit != 0,
+                            masks.end_mask & bit != 0,
+                        )
+                        .1,
+                    ),
+                    Dim::Dynamic => Dim::Dynamic,
+                };
+                if masks.squeeze_mask & bit != 0 {
+                    if matches!(dim, Dim::Static(size) if size != 1) {
+                        return Err(invalid(format!(
+                            "squeezed axis {} has length {}, not 1",
+                            axis, dim
+                        )));
+                    }
+                } else {
+                    dims.push(dim);
+                }
+            }
+            Ok(vec![(operands[0].data_type, Shape::new(dims))])
+        }
+        OpKind::Gather {
+            axis,
+            batch_dimensions,
+        } => {
+            expect_operands(op, operands, 2)?;
+            let (params, indices) = (&operands[0].shape, &operands[1].shape);
+            expect_integer(op, operands[1].data_type)?;
+            if *axis >= params.rank() || *batch_dimensions > indices.rank() {
+                return Err(invalid(format!(
+                    "axis {} and batch dimensions {} don't fit params {} and indices {}",
+                    axis, batch_dimensions, params, indices
+                )));
+            }
+            if batch_dimensions > axis {
+                return Err(invalid(format!(
+                    "batch dimensions {} must not exceed axis {}",
+                    batch_dimensions, axis
+                )));
+            }
+            let dims = params.dims()[..*axis]
+                .iter()
+                .chain(&indices.dims()[*batch_dimensions..])
+                .chain(&params.dims()[axis + 1..])
+                .copied()
+                .collect();
+            Ok(vec![(operands[0].data_type, Shape::new(dims))])
+        }
+        OpKind::GatherAlongAxis { axis } => {
+            expect_operands(op, operands, 2)?;
+            let (params, indices) = (&operands[0].shape, &operands[1].shape);
+            expect_integer(op, operands[1].data_type)?;
+            params.normalize_axis(*axis as i64).map_err(shape_error)?;
+            if params.rank() != indices.rank() {
+                return Err(invalid(format!(
+                    "params {} and indices {} must have the same rank",
+                    params, indices
+                )));
+            }
+            Ok(vec![(operands[0].data_type, indices.clone())])
+        }
+        OpKind::Scatter { shape, axis, .. } | OpKind::ScatterAlongAxis { shape, axis, .. } => {
+            expect_operands(op, operands, 2)?;
+            expect_integer(op, operands[1].data_type)?;
+            shape.normalize_axis(*axis as i64).map_err(shape_error)?;
+            Ok(vec![(operands[0].data_type, shape.clone())])
+        }
+        OpKind::Softmax { axis } => {
+            expect_operands(op, operands, 1)?;
+            operands[0]
+                .shape
+                .normalize_axis(*axis)
+                .map_err(shape_error)?;
+            Ok(vec![(operands[0].data_type, operands[0].shape.clone())])
+        }
+        OpKind::SoftmaxCrossEntropy { axis, reduction } => {
+            expect_operands(op, operands, 2)?;
+            same_data_type(op, operands)?;
+            let shape = operands[0]
+                .shape
+                .broadcast(&operands[1].shape)
+                .map_err(shape_error)?;
+            let axis = shape.normalize_axis(*axis).map_err(shape_error)?;
+            let dims = (0..shape.rank())
+                .map(|i| {
+                    if i == axis || *reduction != MPSGraphLossReductionType::None {
+                        Dim::Static(1)
+                    } else {
+                        shape[i]
+                    }
+                })
+                .collect();
+            Ok(vec![(operands[0].data_type, Shape::new(dims))])
+        }
+        OpKind::SoftmaxCrossEntropyGradient { axis, .. } => {
+            expect_operands(op, operands, 3)?;
+            same_data_type(op, operands)?;
+            // The incoming gradient has the shape of the loss, which broadcasts to the logits
+            let shape = broadcast_all(operands).map_err(shape_error)?;
+            shape.normalize_axis(*axis).map_err(shape_error)?;
+            Ok(vec![(operands[1].data_type, shape)])
+        }
+        OpKind::Convolution2d(attributes) => {
+            expect_operands(op, operands, 2)?;
+            same_data_type(op, operands)?;
+            let (source, weights) = (&operands[0].shape, &operands[1].shape);
+            expect_rank(op, "source", source, 4)?;
+            expect_rank(op, "weights", weights, 4)?;
+
+            // Axes of the output channels, input channels and kernel in the weights
+            let (out_axis, in_axis, kernel_axes) = match attributes.weights_layout {
+                MPSGraphWeightsLayout::OIHW => (0, 1, [2, 3]),
+                MPSGraphWeightsLayout::HWIO => (3, 2, [0, 1]),
+                layout => {
+                    return Err(invalid(format!(
+                        "weights layout {:?} is not a 2D layout",
+                        layout
+                    )))
+                }
+            };
+            let mut kernel = [0; 2];
+            for (size, axis) in kernel.iter_mut().zip(kernel_axes) {
+                *size = weights[axis].size().ok_or_else(|| {
+                    invalid(format!(
+                        "kernel dimensions of weights {} must be static",
+                        weights
+                    ))
+                })?;
+            }
+            check_window(op, kernel, attributes.strides, attributes.dilations)?;
+            let groups = attributes.groups;
+            if groups == 0 {
+                return Err(invalid(String::from("groups must be positive")));
+            }
+
+            let (channel_axis, spatial) = spatial_axes(attributes.data_layout);
+            if let (Dim::Static(channels), Dim::Static(per_group)) =
+                (source[channel_axis], weights[in_axis])
+            {
+                if per_group.checked_mul(groups) != Some(channels) {
+                    return Err(IrError::Dimension {
+                        op,
+                        operand: "source",
+                        axis: channel_axis,
+                        size: channels,
+                        reason: format!(
+                            "but weights dimension {} has {} input channels per group and \
+                             groups is {}",
+                            in_axis, per_group, groups
+                        ),
+                    });
+                }
+            }
+            if let Dim::Static(outputs) = weights[out_axis] {
+                if outputs % groups != 0 {
+                    return Err(IrError::Dimension {
+                        op,
+                        operand: "weights",
+                        axis: out_axis,
+                        size: outputs,
+                        reason: format!("which is not divisible by groups {}", groups),
+                    });
+                }
+            }
+
+            let mut dims = source.dims().to_vec();
+            dims[channel_axis] = weights[out_axis];
+            for i in 0..2 {
+                let padding = [attributes.padding[2 * i], attributes.padding[2 * i + 1]];
+                dims[spatial[i]] = window_output_size(
+                    op,
+                    spatial[i],
+                    source[spatial[i]],
+                    kernel[i],
+                    attributes.strides[i],
+                    attributes.dilations[i],
+                    padding,
+                    attributes.padding_style,
+                    false,
+                )?;
+            }
+            Ok(vec![(operands[0].data_type, Shape::new(dims))])
+        }
+        OpKind::Pooling2d { window, .. } | OpKind::ImToCol(window) => {
+            expect_operands(op, operands, 1)?;
+            let source = &operands[0].shape;
+            expect_rank(op, "source", source, 4)?;
+            check_window(op, window.kernel, window.strides, window.dilations)?;
+            let is_im_to_col = matches!(kind, OpKind::ImToCol(_));
+            if is_im_to_col && window.padding_style != MPSGraphPaddingStyle::Explicit {
+                return Err(invalid(format!(
+                    "only explicit padding is supported, got {:?}",
+                    window.padding_style
+                )));
+            }
+
+            let (channel_axis, spatial) = spatial_axes(window.data_layout);
+            let mut output = [Dim::Dynamic; 2];
+            for i in 0..2 {
+                output[i] = window_output_size(
+                    op,
+                    spatial[i],
+                    source[spatial[i]],
+                    window.kernel[i],
+                    window.strides[i],
+                    window.dilations[i],
+                    [window.padding[2 * i], window.padding[2 * i + 1]],
+                    window.padding_style,
+                    window.ceil_mode,
+                )?;
+            }
+
+            let dims = if is_im_to_col {
+                let too_large = || invalid(format!("im2col of {} is too large", source));
+                let patch = window.kernel[0]
+                    .checked_mul(window.kernel[1])
+                    .and_then(|area| dim_product(&[source[channel_axis], Dim::Static(area)]))
+                    .ok_or_else(too_large)?;
+                let windows = dim_product(&output).ok_or_else(too_large)?;
+                match window.data_layout {
+                    MPSGraphTensorNamedDataLayout::NCHW => vec![source[0], patch, windows],
+                    MPSGraphTensorNamedDataLayout::NHWC => vec![source[0], windows, patch],
+                }
+            } else {
+                let mut dims = source.dims().to_vec();
+                dims[spatial[0]] = output[0];
+                dims[spatial[1]] = output[1];
+                dims
+            };
+            Ok(vec![(operands[0].data_type, Shape::new(dims))])
+        }
+        OpKind::Resize {
+            size, data_layout, ..
+        } => {
+            expect_operands(op, operands, 1)?;
+            let source = &operands[0].shape;
+            expect_rank(op, "source", source, 4)?;
+            if size.contains(&0) {
+                return Err(invalid(format!("size {:?} must be positive", size)));
+            }
+            let (_, spatial) = spatial_axes(*data_layout);
+            let mut dims = source.dims().to_vec();
+            dims[spatial[0]] = Dim::Static(size[0]);
+            dims[spatial[1]] = Dim::Static(size[1]);
+            Ok(vec![(operands[0].data_type, Shape::new(dims))])
+        }
+        OpKind::TopK { axis, k, .. } => {
+            expect_operands(op, operands, 1)?;
+            let source = &operands[0].shape;
+            if *axis >= source.rank() {
+                return Err(invalid(format!(
+                    "axis {} is out of range for {}",
+                    axis, source
+                )));
+            }
+            if let Dim::Static(size) = source[*axis] {
+                if size < *k {
+                    return Err(IrError::Dimension {
+                        op,
+                        operand: "source",
+                        axis: *axis,
+                        size,
+                        reason: format!("which is smaller than k = {}", k),
+                    });
+                }
+            }
+            let mut dims = source.dims().to_vec();
+            dims[*axis] = Dim::Static(*k);
+            let shape = Shape::new(dims);
+            Ok(vec![
+                (operands[0].data_type, shape.clone()),
+                (MPSDataType::Int32, shape),
+            ])
+        }
+        OpKind::NonMaximumSuppression { .. } => {
+            expect_operands(op, operands, 2)?;
+            let (boxes, scores) = (&operands[0].shape, &operands[1].shape);
+            expect_rank(op, "boxes", boxes, 3)?;
+            expect_rank(op, "scores", scores, 3)?;
+            if let Dim::Static(size) = boxes[2] {
+                if size != 4 {
+                    return Err(IrError::Dimension {
+                        op,
+                        operand: "boxes",
+                        axis: 2,
+                        size,
+                        reason: String::from("expected 4 coordinates per box"),
+                    });
+                }
+            }
+            for axis in 0..2 {
+                if merge_dims(boxes[axis], scores[axis]).is_none() {
+                    return Err(IrError::Dimension {
+                        op,
+                        operand: "scores",
+                        axis,
+                        size: scores[axis].size().unwrap_or(0),
+                        reason: format!("which doesn't match boxes {}", boxes),
+                    });
+                }
+            }
+            for operand in operands {
+                if operand.data_type != MPSDataType::Float32 {
+                    return Err(invalid(format!(
+                        "boxes and scores must be Float32, got {:?}",
+                        operand.data_type
+                    )));
+                }
+            }
+            let batch = merge_dims(boxes[0], scores[0]).unwrap();
+            Ok(vec![(
+                MPSDataType::Int32,
+                Shape::new(vec![batch, Dim::Dynamic]),
+            )])
+        }
+    }
+}
+
+/// Resolves one axis of a strided slice into its first index and result length
+///
+/// Follows the TensorFlow convention that MPSGraph uses: negative indices count from the
+/// end, out-of-range indices are clamped, and masked bounds cover the whole axis in the
+/// direction of the stride.
+pub(crate) fn strided_slice_range(
+    size: usize,
+    start: i64,
+    end: i64,
+    stride: i64,
+    start_masked: bool,
+    end_masked: bool,
+) -> (i64, usize) {
+    let size = size as i64;
+    let resolve = |index: i64, low: i64, high: i64| {
+        let index = if index < 0 { index + size } else { index };
+        index.clamp(low, high)
+    };
+
+    if stride > 0 {
+        let first = if start_masked {
+            0
+        } else {
+            resolve(start, 0, size)
+        };
+        let last = if end_masked {
+            size
+        } else {
+            resolve(end, 0, size)
+        };
+        (first, stepped_length(last - first, stride))
+    } else {
+        let first = if start_masked {
+            size - 1
+        } else {
+            resolve(start, -1, size - 1)
+        };
+        let last = if end_masked {
+            -1
+        } else {
+            resolve(end, -1, size - 1)
+        };
+        (first, stepped_length(first - last, stride))
+    }
+}
+
+/// Counts the steps of `stride` that fit in a span of `distance` elements
+fn stepped_length(distance: i64, stride: i64) -> usize {
+    if distance <= 0 {
+        return 0;
+    }
+    ((distance as u64 - 1) / stride.unsigned_abs() + 1) as usize
+}
+
+/// Computes the result shape of a matrix multiplication
+fn matmul_shape(op: &'static str, lhs: &Shape, rhs: &Shape) -> Result<Shape, IrError> {
+    if lhs.rank() == 0 || rhs.rank() == 0 {
+        return Err(IrError::Invalid {
+            op,
+            reason: String::from("operands must have at least one dimension"),
+        });
+    }
+
+    // Vectors are treated as a single row or column that is dropped from the result
+    let lhs_dims = if lhs.rank() == 1 {
+        vec![Dim::Static(1), lhs[0]]
+    } else {
+        lhs.dims().to_vec()
+    };
+    let rhs_dims = if rhs.rank() == 1 {
+        vec![rhs[0], Dim::Static(1)]
+    } else {
+        rhs.dims().to_vec()
+    };
+
+    let (lhs_batch, lhs_matrix) = lhs_dims.split_at(lhs_dims.len() - 2);
+    let (rhs_batch, rhs_matrix) = rhs_dims.split_at(rhs_dims.len() - 2);
+    if let (Dim::Static(columns), Dim::Static(rows)) = (lhs_matrix[1], rhs_matrix[0]) {
+        if columns != rows {
+            return Err(IrError::Dimension {
+                op,
+                operand: "secondary",
+                axis: rhs.rank().saturating_sub(2),
+                size: rows,
+                reason: format!(
+                    "but primary dimension {} has size {}",
+                    lhs.rank() - 1,
+                    columns
+                ),
+            });
+        }
+    }
+
+    let batch = Shape::new(lhs_batch.to_vec())
+        .broadcast(&Shape::new(rhs_batch.to_vec()))
+        .map_err(|error| IrError::Shape { op, error })?;
+    let mut dims = batch.dims().to_vec();
+    if lhs.rank() > 1 {
+        dims.push(lhs_matrix[0]);
+    }
+    if rhs.rank() > 1 {
+        dims.push(rhs_matrix[1]);
+    }
+    Ok(Shape::new(dims))
+}
+
+/// Returns the channel axis and the `[height, width]` axes of a rank-4 layout
+fn spatial_axes(layout: MPSGraphTensorNamedDataLayout) -> (usize, [usize; 2]) {
+    match layout {
+        MPSGraphTensorNamedDataLayout::NCHW => (1, [2, 3]),
+        MPSGraphTensorNamedDataLayout::NHWC => (3, [1, 2]),
+    }
+}
+
+fn expect_rank(
+    op: &'static str,
+    operand: &'static str,
+    shape: &Shape,
+    rank: usize,
+) -> Result<(), IrError> {
+    if shape.rank() != rank {
+        return Err(IrError::Invalid {
+            op,
+            reason: format!("{} must have rank {}, got {}", operand, rank, shape),
+        });
+    }
+    Ok(())
+}
+
+fn check_window(
+    op: &'static str,
+    kernel: [usize; 2],
+    strides: [usize; 2],
+    dilations: [usize; 2],
+) -> Result<(), IrError> {
+    if kernel.contains(&0) || strides.contains(&0) || dilations.contains(&0) {
+        return Err(IrError::Invalid {
+            op,
+            reason: format!(
+                "kernel {:?}, strides {:?} and dilations {:?} must be positive",
+                kernel, strides, dilations
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Computes the output size of one spatial axis of a sliding window
+///
+/// `padding` holds the padding before and after the axis and is only used with explicit
+/// padding. `TfSame` pads so that the output is the input divided by the stride, rounded
+/// up, and `TfValid` doesn't pad at all.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn window_output_size(
+    op: &'static str,
+    axis: usize,
+    input: Dim,
+    kernel: usize,
+    stride: usize,
+    dilation: usize,
+    padding: [usize; 2],
+    style: MPSGraphPaddingStyle,
+    ceil_mode: bool,
+) -> Result<Dim, IrError> {
+    let Dim::Static(size) = input else {
+        return Ok(Dim::Dynamic);
+    };
+    let (before, after) = match style {
+        MPSGraphPaddingStyle::Explicit => (padding[0], padding[1]),
+        MPSGraphPaddingStyle::TfValid => (0, 0),
+        MPSGraphPaddingStyle::TfSame => return Ok(Dim::Static(size.div_ceil(stride))),
+    };
+    let overflow = || IrError::Dimension {
+        op,
+        operand: "source",
+        axis,
+        size,
+        reason: format!(
+            "and the window or padding around it is too large (kernel {}, dilation {}, \
+             padding {:?})",
+            kernel, dilation, padding
+        ),
+    };
+    let extent = kernel
+        .checked_sub(1)
+        .and_then(|span| span.checked_mul(dilation))
+        .and_then(|span| span.checked_add(1))
+        .ok_or_else(overflow)?;
+    let padded = size
+        .checked_add(before)
+        .and_then(|padded| padded.checked_add(after))
+        .ok_or_else(overflow)?;
+    if padded < extent {
+        return Err(IrError::Dimension {
+            op,
+            operand: "source",
+            axis,
+            size,
+            reason: format!(
+                "but the window spans {} elements with {} elements of padding",
+                extent,
+                before + after
+            ),
+        });
+    }
+
+    let floor = (padded - extent) / stride + 1;
+    let ceil = (padded - extent).div_ceil(stride) + 1;
+    // A partial window only counts if it starts inside the input or the leading padding
+    if ceil_mode
+        && (ceil - 1)
+            .checked_mul(stride)
+            .is_some_and(|start| start < size + before)
+    {
+        return Ok(Dim::Static(ceil));
+    }
+    Ok(Dim::Static(floor))
+}
+
+/// Multiplies dimensions, giving a dynamic result if any is dynamic and `None` on overflow
+fn dim_product(dims: &[Dim]) -> Option<Dim> {
+    let mut product = 1usize;
+    for dim in dims {
+        match dim {
+            Dim::Static(size) => product = product.checked_mul(*size)?,
+            Dim::Dynamic => return Some(Dim::Dynamic),
+        }
+    }
+    Some(Dim::Static(product))
+}
+
+/// Combines two dimensions that must be equal, keeping static information
+fn merge_dims(a: Dim, b: Dim) -> Option<Dim> {
+    match (a, b) {
+        (Dim::Static(x), Dim::Static(y)) if x != y => None,
+        (Dim::Static(_), _) => Some(a),
+        _ => Some(b),
+    }
+}
+
+fn broadcast_all(operands: &[&TensorInfo]) -> Result<Shape, ShapeError> {
+    operands.iter().try_fold(Shape::scalar(), |shape, operand| {
+        shape.broadcast(&operand.shape)
+    })
+}
+
+fn expect_operands(
+    op: &'static str,
+    operands: &[&TensorInfo],
+    count: usize,
+) -> Result<(), IrError> {
+    if operands.len() != count {
+        return Err(IrError::WrongOperandCount {
+            op,
+            expected: count,
+            actual: operands.len(),
+        });
+    }
+    Ok(())
+}
+
+fn expect_some_operands(op: &'static str, operands: &[&TensorInfo]) -> Result<(), IrError> {
+    if operands.is_empty() {
+        return Err(IrError::Invalid {
+            op,
+            reason: String::from("needs at least one operand"),
+        });
+    }
+    Ok(())
+}
+
+fn same_data_type(op: &'static str, operands: &[&TensorInfo]) -> Result<(), IrError> {
+    let expected = operands[0].data_type;
+    match operands
+        .iter()
+        .find(|operand| operand.data_type != expected)
+    {
+        Some(operand) => Err(IrError::DataTypeMismatch {
+            op,
+            expected,
+            actual: operand.data_type,
+        }),
+        None => Ok(()),
+    }
+}
+
+fn expect_bitwise(op: &'static str, data_type: MPSDataType) -> Result<(), IrError> {
+    if data_type.is_integer() {
+        Ok(())
+    } else {
+        Err(IrError::Invalid {
+            op,
+            reason: format!("bitwise operands must be integers, got {:?}", data_type),
+        })
+    }
+}
+
+fn expect_integer(op: &'static str, data_type: MPSDataType) -> Result<(), IrError> {
+    if data_type.is_integer() {
+        Ok(())
+    } else {
+        Err(IrError::Invalid {
+            op,
+            reason: format!("indices must be integers, got {:?}", data_type),
+        })
+    }
+}
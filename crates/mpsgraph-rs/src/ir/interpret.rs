@@ -0,0 +1,1404 @@
+//! Host reference interpreter for recorded graphs.
+//!
+//! [`Graph::interpret`] evaluates a recorded graph on the CPU so results can be checked
+//! without Metal. It favours clarity over speed: elementwise math is done in `f64` and
+//! rounded to the result type, and Int32 arithmetic wraps on overflow. Shifting by a
+//! negative amount or by 32 or more shifts every bit out.
+
+use super::infer::{self, strided_slice_range};
+use super::{
+    BinaryOp, Graph, IrError, OpId, OpKind, Operation, ReductionOp, TensorId, TensorInfo, UnaryOp,
+};
+use crate::core::MPSDataType;
+use crate::dims::{normalize_axis, Shape};
+use crate::loss_ops::MPSGraphLossReductionType;
+use crate::scatter_nd_ops::MPSGraphScatterMode;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Element storage of a [`Value`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueData {
+    Float32(Vec<f32>),
+    Float64(Vec<f64>),
+    Int32(Vec<i32>),
+    Bool(Vec<bool>),
+}
+
+/// Applies the same expression to the vector of whichever variant `$data` holds
+macro_rules! map_data {
+    ($data:expr, $values:ident => $body:expr) => {
+        match $data {
+            ValueData::Float32($values) => ValueData::Float32($body),
+            ValueData::Float64($values) => ValueData::Float64($body),
+            ValueData::Int32($values) => ValueData::Int32($body),
+            ValueData::Bool($values) => ValueData::Bool($body),
+        }
+    };
+}
+
+impl ValueData {
+    /// Returns the data type of the elements
+    pub fn data_type(&self) -> MPSDataType {
+        match self {
+            ValueData::Float32(_) => MPSDataType::Float32,
+            ValueData::Float64(_) => MPSDataType::Float64,
+            ValueData::Int32(_) => MPSDataType::Int32,
+            ValueData::Bool(_) => MPSDataType::Bool,
+        }
+    }
+
+    /// Returns the number of elements
+    pub fn len(&self) -> usize {
+        match self {
+            ValueData::Float32(values) => values.len(),
+            ValueData::Float64(values) => values.len(),
+            ValueData::Int32(values) => values.len(),
+            ValueData::Bool(values) => values.len(),
+        }
+    }
+
+    /// Returns true if there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the elements converted to `f64`, with `true` as 1
+    pub fn to_f64(&self) -> Vec<f64> {
+        match self {
+            ValueData::Float32(values) => values.iter().map(|&v| v as f64).collect(),
+            ValueData::Float64(values) => values.clone(),
+            ValueData::Int32(values) => values.iter().map(|&v| v as f64).collect(),
+            ValueData::Bool(values) => values.iter().map(|&v| v as u8 as f64).collect(),
+        }
+    }
+
+    /// Converts `f64` values to a supported data type, truncating toward zero for Int32
+    fn from_f64(data_type: MPSDataType, values: impl Iterator<Item = f64>) -> Self {
+        match data_type {
+            MPSDataType::Float32 => ValueData::Float32(values.map(|v| v as f32).collect()),
+            MPSDataType::Float64 => ValueData::Float64(values.collect()),
+            MPSDataType::Int32 => ValueData::Int32(values.map(|v| v as i32).collect()),
+            MPSDataType::Bool => ValueData::Bool(values.map(|v| v != 0.0).collect()),
+            _ => unreachable!("data type {:?} is rejected before evaluation", data_type),
+        }
+    }
+
+    /// Decodes native-endian constant bytes
+    fn from_bytes(data_type: MPSDataType, bytes: &[u8]) -> Self {
+        match data_type {
+            MPSDataType::Float32 => ValueData::Float32(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| f32::from_ne_bytes(b.try_into().unwrap()))
+                    .collect(),
+            ),
+            MPSDataType::Float64 => ValueData::Float64(
+                bytes
+                    .chunks_exact(8)
+                    .map(|b| f64::from_ne_bytes(b.try_into().unwrap()))
+                    .collect(),
+            ),
+            MPSDataType::Int32 => ValueData::Int32(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| i32::from_ne_bytes(b.try_into().unwrap()))
+                    .collect(),
+            ),
+            MPSDataType::Bool => ValueData::Bool(bytes.iter().map(|&b| b != 0).collect()),
+            _ => unreachable!("data type {:?} is rejected before evaluation", data_type),
+        }
+    }
+
+    /// Returns the elements at `indices`, in order
+    fn take(&self, indices: &[usize]) -> Self {
+        map_data!(self, values => indices.iter().map(|&i| values[i]).collect())
+    }
+
+    /// Concatenates flat buffers of the same data type
+    fn concat(parts: &[&ValueData]) -> Self {
+        let mut result = parts[0].clone();
+        for part in &parts[1..] {
+            match (&mut result, part) {
+                (ValueData::Float32(a), ValueData::Float32(b)) => a.extend_from_slice(b),
+                (ValueData::Float64(a), ValueData::Float64(b)) => a.extend_from_slice(b),
+                (ValueData::Int32(a), ValueData::Int32(b)) => a.extend_from_slice(b),
+                (ValueData::Bool(a), ValueData::Bool(b)) => a.extend_from_slice(b),
+                _ => unreachable!("operand data types are checked by inference"),
+            }
+        }
+        result
+    }
+}
+
+impl From<Vec<f32>> for ValueData {
+    fn from(values: Vec<f32>) -> Self {
+        ValueData::Float32(values)
+    }
+}
+
+impl From<Vec<f64>> for ValueData {
+    fn from(values: Vec<f64>) -> Self {
+        ValueData::Float64(values)
+    }
+}
+
+impl From<Vec<i32>> for ValueData {
+    fn from(values: Vec<i32>) -> Self {
+        ValueData::Int32(values)
+    }
+}
+
+impl From<Vec<bool>> for ValueData {
+    fn from(values: Vec<bool>) -> Self {
+        ValueData::Bool(values)
+    }
+}
+
+/// A dense host tensor stored in row-major order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value {
+    shape: Vec<usize>,
+    data: ValueData,
+}
+
+impl Value {
+    /// Creates a value from its shape and elements
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of elements doesn't match the shape.
+    pub fn new(shape: &[usize], data: impl Into<ValueData>) -> Self {
+        let data = data.into();
+        let count: usize = shape.iter().product();
+        assert_eq!(
+            data.len(),
+            count,
+            "shape {:?} needs {} elements, got {}",
+            shape,
+            count,
+            data.len()
+        );
+        Value {
+            shape: shape.to_vec(),
+            data,
+        }
+    }
+
+    /// Returns the shape
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    /// Returns the data type of the elements
+    pub fn data_type(&self) -> MPSDataType {
+        self.data.data_type()
+    }
+
+    /// Returns the elements
+    pub fn data(&self) -> &ValueData {
+        &self.data
+    }
+
+    /// Returns the elements if they are Float32
+    pub fn as_f32(&self) -> Option<&[f32]> {
+        match &self.data {
+            ValueData::Float32(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements if they are Float64
+    pub fn as_f64(&self) -> Option<&[f64]> {
+        match &self.data {
+            ValueData::Float64(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements if they are Int32
+    pub fn as_i32(&self) -> Option<&[i32]> {
+        match &self.data {
+            ValueData::Int32(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements if they are Bool
+    pub fn as_bool(&self) -> Option<&[bool]> {
+        match &self.data {
+            ValueData::Bool(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// Errors reported by [`Graph::interpret`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterpretError {
+    /// A target or feed is not part of the graph
+    UnknownTensor(TensorId),
+    /// A placeholder the targets depend on was not fed
+    MissingFeed(TensorId),
+    /// A feed doesn't match its placeholder
+    FeedMismatch {
+        /// The placeholder
+        tensor: TensorId,
+        /// Data type of the placeholder
+        expected_type: MPSDataType,
+        /// Shape of the placeholder
+        expected_shape: Shape,
+        /// Data type of the feed
+        actual_type: MPSDataType,
+        /// Shape of the feed
+        actual_shape: Vec<usize>,
+    },
+    /// The interpreter has no host implementation of the operation
+    UnsupportedOperation(&'static str),
+    /// The interpreter only handles Float32, Float64, Int32 and Bool
+    UnsupportedDataType {
+        /// Operation kind
+        op: &'static str,
+        /// The offending data type
+        data_type: MPSDataType,
+    },
+    /// A gather or scatter index is outside the indexed axis
+    IndexOutOfRange {
+        /// Operation kind
+        op: &'static str,
+        /// The index as stored in the indices tensor
+        index: i64,
+        /// Size of the indexed axis
+        size: usize,
+    },
+    /// Operand values don't fit the operation
+    Invalid {
+        /// Operation kind
+        op: &'static str,
+        /// What is wrong
+        reason: String,
+    },
+    /// Inference failed for the concrete operand shapes
+    Ir(IrError),
+}
+
+impl fmt::Display for InterpretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterpretError::UnknownTensor(id) => {
+                write!(f, "tensor {} is not part of this graph", id)
+            }
+            InterpretError::MissingFeed(id) => write!(f, "placeholder {} was not fed", id),
+            InterpretError::FeedMismatch {
+                tensor,
+                expected_type,
+                expected_shape,
+                actual_type,
+                actual_shape,
+            } => write!(
+                f,
+                "placeholder {} expects {:?}{}, got {:?}{:?}",
+                tensor, expected_type, expected_shape, actual_type, actual_shape
+            ),
+            InterpretError::UnsupportedOperation(op) => {
+                write!(f, "{} is not supported by the interpreter", op)
+            }
+            InterpretError::UnsupportedDataType { op, data_type } => {
+                write!(f, "{}: data type {:?} is not supported", op, data_type)
+            }
+            InterpretError::IndexOutOfRange { op, index, size } => {
+                write!(
+                    f,
+                    "{}: index {} is out of range for size {}",
+                    op, index, size
+                )
+            }
+            InterpretError::Invalid { op, reason } => write!(f, "{}: {}", op, reason),
+            InterpretError::Ir(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for InterpretError {}
+
+impl From<IrError> for InterpretError {
+    fn from(error: IrError) -> Self {
+        InterpretError::Ir(error)
+    }
+}
+
+type Result<T> = std::result::Result<T, InterpretError>;
+
+impl Graph {
+    /// Evaluates `targets` on the host
+    ///
+    /// Only the operations the targets depend on are evaluated, so only the placeholders
+    /// they reach need feeds. Feeds must match the data type of their placeholder and every
+    /// static dimension of its shape.
+    pub fn interpret(
+        &self,
+        feeds: &HashMap<TensorId, Value>,
+        targets: &[TensorId],
+    ) -> Result<HashMap<TensorId, Value>> {
+        if let Some(&id) = targets
+            .iter()
+            .chain(feeds.keys())
+            .find(|id| id.0 >= self.tensors.len())
+        {
+            return Err(InterpretError::UnknownTensor(id));
+        }
+
+        // Mark the operations the targets depend on
+        let mut needed = vec![false; self.operations.len()];
+        let mut pending = targets.to_vec();
+        while let Some(id) = pending.pop() {
+            let op = self.tensor(id).producer;
+            if !needed[op.0] {
+                needed[op.0] = true;
+                pending.extend(&self.operations[op.0].inputs);
+            }
+        }
+
+        let mut values: Vec<Option<Value>> = vec![None; self.tensors.len()];
+        for (op, _) in self.operations.iter().zip(&needed).filter(|(_, &n)| n) {
+            let results = match &op.kind {
+                OpKind::Placeholder { shape, data_type } => {
+                    vec![self.checked_feed(op.outputs[0], shape, *data_type, feeds)?]
+                }
+                _ => {
+                    let operands: Vec<&Value> = op
+                        .inputs
+                        .iter()
+                        .map(|id| values[id.0].as_ref().expect("operands precede their users"))
+                        .collect();
+                    evaluate(op, &operands)?
+                }
+            };
+            for (id, value) in op.outputs.iter().zip(results) {
+                values[id.0] = Some(value);
+            }
+        }
+
+        Ok(targets
+            .iter()
+            .map(|&id| (id, values[id.0].clone().expect("targets are evaluated")))
+            .collect())
+    }
+
+    fn checked_feed(
+        &self,
+        id: TensorId,
+        shape: &Shape,
+        data_type: MPSDataType,
+        feeds: &HashMap<TensorId, Value>,
+    ) -> Result<Value> {
+        let value = feeds.get(&id).ok_or(InterpretError::MissingFeed(id))?;
+        let matches = value.shape.len() == shape.rank()
+            && shape
+                .dims()
+                .iter()
+                .zip(&value.shape)
+                .all(|(dim, &size)| dim.size().is_none_or(|expected| expected == size));
+        if !matches || value.data_type() != data_type {
+            return Err(InterpretError::FeedMismatch {
+                tensor: id,
+                expected_type: data_type,
+                expected_shape: shape.clone(),
+                actual_type: value.data_type(),
+                actual_shape: value.shape.clone(),
+            });
+        }
+        Ok(value.clone())
+    }
+}
+
+/// Evaluates one operation on concrete operands
+fn evaluate(op: &Operation, operands: &[&Value]) -> Result<Vec<Value>> {
+    let name = op.kind.name();
+
+    // Re-run inference on the concrete shapes to get the result shapes
+    let infos: Vec<TensorInfo> = operands
+        .iter()
+        .map(|value| TensorInfo {
+            data_type: value.data_type(),
+            shape: Shape::from_static(&value.shape),
+            name: None,
+            producer: OpId(0),
+        })
+        .collect();
+    let info_refs: Vec<&TensorInfo> = infos.iter().collect();
+    let mut results = Vec::new();
+    for (data_type, shape) in infer::infer_results(&op.kind, &info_refs)? {
+        check_data_type(name, data_type)?;
+        let shape = shape.to_static().ok_or_else(|| InterpretError::Invalid {
+            op: name,
+            reason: format!("result shape {} is not static", shape),
+        })?;
+        results.push((data_type, shape));
+    }
+    let (out_type, out_shape) = results[0].clone();
+
+    let data = match &op.kind {
+        OpKind::Placeholder { .. } => unreachable!("placeholders are fed"),
+        OpKind::Convolution2d(_)
+        | OpKind::Pooling2d { .. }
+        | OpKind::ImToCol(_)
+        | OpKind::Resize { .. }
+        | OpKind::TopK { .. }
+        | OpKind::NonMaximumSuppression { .. } => {
+            return Err(InterpretError::UnsupportedOperation(name))
+        }
+        OpKind::Constant {
+            data, data_type, ..
+        } => ValueData::from_bytes(*data_type, data),
+        OpKind::ConstantScalar {
+            value, data_type, ..
+        } => {
+            let count = out_shape.iter().product();
+            ValueData::from_f64(*data_type, std::iter::repeat_n(*value, count))
+        }
+        OpKind::Unary(unary) => unary_op(*unary, &operands[0].data, out_type),
+        OpKind::Binary(binary) => {
+            let a = broadcast_to(operands[0], &out_shape);
+            let b = broadcast_to(operands[1], &out_shape);
+            binary_op(*binary, &a, &b, out_type)
+        }
+        OpKind::Select => {
+            let predicate = broadcast_to(operands[0], &out_shape).to_f64();
+            let on_true = broadcast_to(operands[1], &out_shape);
+            let on_false = broadcast_to(operands[2], &out_shape);
+            let count = predicate.len();
+            let indices: Vec<usize> = predicate
+                .iter()
+                .enumerate()
+                .map(|(i, &p)| if p != 0.0 { i } else { count + i })
+                .collect();
+            ValueData::concat(&[&on_true, &on_false]).take(&indices)
+        }
+        OpKind::Clamp => {
+            let x = broadcast_to(operands[0], &out_shape).to_f64();
+            let low = broadcast_to(operands[1], &out_shape).to_f64();
+            let high = broadcast_to(operands[2], &out_shape).to_f64();
+            let values = (0..x.len()).map(|i| x[i].max(low[i]).min(high[i]));
+            ValueData::from_f64(out_type, values)
+        }
+        OpKind::Cast { data_type } => {
+            ValueData::from_f64(*data_type, operands[0].data.to_f64().into_iter())
+        }
+        OpKind::MatMul => matmul(name, operands[0], operands[1])?,
+        OpKind::Reduction {
+            op: reduction,
+            axes,
+        } => reduce(name, *reduction, axes, operands[0], &out_shape)?,
+        OpKind::Reshape { .. }
+        | OpKind::Squeeze { .. }
+        | OpKind::ExpandDims { .. }
+        | OpKind::Flatten2d { .. } => operands[0].data.clone(),
+        OpKind::Transpose { permutation } => {
+            let strides = strides(&operands[0].shape);
+            let indices: Vec<usize> = indices(&out_shape)
+                .map(|index| {
+                    permutation
+                        .iter()
+                        .zip(&index)
+                        .map(|(&axis, &i)| i * strides[axis])
+                        .sum()
+                })
+                .collect();
+            operands[0].data.take(&indices)
+        }
+        OpKind::Broadcast { .. } => broadcast_to(operands[0], &out_shape),
+        OpKind::Concat { axis } => {
+            let axis = normalize_axis(*axis, out_shape.len()).unwrap();
+            let mut bases = Vec::with_capacity(operands.len());
+            let mut base = 0;
+            for operand in operands {
+                bases.push(base);
+                base += operand.data.len();
+            }
+            let indices: Vec<usize> = indices(&out_shape)
+                .map(|mut index| {
+                    let mut part = 0;
+                    while index[axis] >= operands[part].shape[axis] {
+                        index[axis] -= operands[part].shape[axis];
+                        part += 1;
+                    }
+                    bases[part] + offset(&index, &strides(&operands[part].shape))
+                })
+                .collect();
+            let parts: Vec<&ValueData> = operands.iter().map(|value| &value.data).collect();
+            ValueData::concat(&parts).take(&indices)
+        }
+        OpKind::Stack { axis } => {
+            // Operands have the same shape, so stacking only interleaves whole slices
+            let axis = normalize_axis(*axis, out_shape.len()).unwrap();
+            let inner: usize = out_shape[axis + 1..].iter().product();
+            let size = operands[0].data.len();
+            let indices: Vec<usize> = (0..out_shape.iter().product::<usize>())
+                .map(|flat| {
+                    let outer = flat / (inner * operands.len());
+                    let part = flat / inner % operands.len();
+                    part * size + outer * inner + flat % inner
+                })
+                .collect();
+            let parts: Vec<&ValueData> = operands.iter().map(|value| &value.data).collect();
+            ValueData::concat(&parts).take(&indices)
+        }
+        OpKind::Split { axis, .. } => {
+            let axis = normalize_axis(*axis, out_shape.len()).unwrap();
+            let strides = strides(&operands[0].shape);
+            return Ok((0..results.len())
+                .map(|part| {
+                    let indices: Vec<usize> = indices(&out_shape)
+                        .map(|mut index| {
+                            index[axis] += part * out_shape[axis];
+                            offset(&index, &strides)
+                        })
+                        .collect();
+                    Value::new(&out_shape, operands[0].data.take(&indices))
+                })
+                .collect());
+        }
+        OpKind::Tile { .. } => {
+            let shape = &operands[0].shape;
+            let strides = strides(shape);
+            let indices: Vec<usize> = indices(&out_shape)
+                .map(|index| {
+                    index
+                        .iter()
+                        .zip(shape)
+                        .zip(&strides)
+                        .map(|((&i, &size), &stride)| i % size * stride)
+                        .sum()
+                })
+                .collect();
+            operands[0].data.take(&indices)
+        }
+        OpKind::Reverse { axes } => {
+            let shape = &operands[0].shape;
+            let axes: Vec<usize> = axes
+                .iter()
+                .map(|&axis| normalize_axis(axis, shape.len()).unwrap())
+                .collect();
+            let strides = strides(shape);
+            let indices: Vec<usize> = indices(shape)
+                .map(|mut index| {
+                    for &axis in &axes {
+                        index[axis] = shape[axis] - 1 - index[axis];
+                    }
+                    offset(&index, &strides)
+                })
+                .collect();
+            operands[0].data.take(&indices)
+        }
+        OpKind::Slice {
+            dimension, start, ..
+        } => {
+            let shape = &operands[0].shape;
+            let start = if *start < 0 {
+                (*start + shape[*dimension] as i64) as usize
+            } else {
+                *start as usize
+            };
+            let strides = strides(shape);
+            let indices: Vec<usize> = indices(&out_shape)
+                .map(|mut index| {
+                    index[*dimension] += start;
+                    offset(&index, &strides)
+                })
+                .collect();
+            operands[0].data.take(&indices)
+        }
+        OpKind::StridedSlice {
+            starts,
+            ends,
+            strides: steps,
+            masks,
+        } => {
+            let shape = &operands[0].shape;
+            let mut firsts = Vec::with_capacity(shape.len());
+            let mut full_shape = Vec::with_capacity(shape.len());
+            for axis in 0..shape.len() {
+                let bit = 1 << axis;
+                let (first, length) = strided_slice_range(
+                    shape[axis],
+                    starts[axis],
+                    ends[axis],
+                    steps[axis],
+                    masks.start_mask & bit != 0,
+                    masks.end_mask & bit != 0,
+                );
+                firsts.push(first);
+                full_shape.push(length);
+            }
+            // Squeezed axes have length 1, so the unsqueezed result has the same element order
+            let strides = strides(shape);
+            let indices: Vec<usize> = indices(&full_shape)
+                .map(|index| {
+                    (0..shape.len())
+                        .map(|axis| {
+                            let i = firsts[axis] + index[axis] as i64 * steps[axis];
+                            i as usize * strides[axis]
+                        })
+                        .sum()
+                })
+                .collect();
+            operands[0].data.take(&indices)
+        }
+        OpKind::Gather {
+            axis,
+            batch_dimensions,
+        } => gather(
+            name,
+            operands[0],
+            operands[1],
+            *axis,
+            *batch_dimensions,
+            &out_shape,
+        )?,
+        OpKind::GatherAlongAxis { axis } => {
+            gather_along_axis(name, operands[0], operands[1], *axis)?
+        }
+        OpKind::Scatter { axis, mode, .. } => scatter(
+            name,
+            operands[0],
+            operands[1],
+            normalize_axis(*axis as i64, out_shape.len()).unwrap(),
+            *mode,
+            &out_shape,
+            false,
+        )?,
+        OpKind::ScatterAlongAxis { axis, mode, .. } => scatter(
+            name,
+            operands[0],
+            operands[1],
+            normalize_axis(*axis as i64, out_shape.len()).unwrap(),
+            *mode,
+            &out_shape,
+            true,
+        )?,
+        OpKind::Softmax { axis } => {
+            check_float(name, out_type)?;
+            let shape = &operands[0].shape;
+            let axis = normalize_axis(*axis, shape.len()).unwrap();
+            let x = operands[0].data.to_f64();
+            let mut result = vec![0.0; x.len()];
+            for lane in lanes(shape, axis) {
+                let max = lane.iter().map(|&i| x[i]).fold(f64::NEG_INFINITY, f64::max);
+                let sum: f64 = lane.iter().map(|&i| (x[i] - max).exp()).sum();
+                for &i in &lane {
+                    result[i] = (x[i] - max).exp() / sum;
+                }
+            }
+            ValueData::from_f64(out_type, result.into_iter())
+        }
+        OpKind::SoftmaxCrossEntropy { axis, reduction } => {
+            check_float(name, out_type)?;
+            let shape = Shape::from_static(&operands[0].shape)
+                .broadcast(&Shape::from_static(&operands[1].shape))
+                .map_err(|error| IrError::Shape { op: name, error })?
+                .to_static()
+                .unwrap();
+            let axis = normalize_axis(*axis, shape.len()).unwrap();
+            let x = broadcast_to(operands[0], &shape).to_f64();
+            let labels = broadcast_to(operands[1], &shape).to_f64();
+            let losses: Vec<f64> = lanes(&shape, axis)
+                .map(|lane| {
+                    let max = lane.iter().map(|&i| x[i]).fold(f64::NEG_INFINITY, f64::max);
+                    let log_sum = lane.iter().map(|&i| (x[i] - max).exp()).sum::<f64>().ln();
+                    -lane
+                        .iter()
+                        .map(|&i| labels[i] * (x[i] - max - log_sum))
+                        .sum::<f64>()
+                })
+                .collect();
+            let total: f64 = losses.iter().sum();
+            match reduction {
+                MPSGraphLossReductionType::None => {
+                    ValueData::from_f64(out_type, losses.into_iter())
+                }
+                MPSGraphLossReductionType::Sum => {
+                    ValueData::from_f64(out_type, std::iter::once(total))
+                }
+                MPSGraphLossReductionType::Mean => {
+                    let mean = total / losses.len().max(1) as f64;
+                    ValueData::from_f64(out_type, std::iter::once(mean))
+                }
+            }
+        }
+        OpKind::SoftmaxCrossEntropyGradient { axis, reduction } => {
+            check_float(name, out_type)?;
+            let axis = normalize_axis(*axis, out_shape.len()).unwrap();
+            // The incoming gradient is constant along each lane, so it broadcasts to the logits
+            let gradient = broadcast_to(operands[0], &out_shape).to_f64();
+            let x = broadcast_to(operands[1], &out_shape).to_f64();
+            let labels = broadcast_to(operands[2], &out_shape).to_f64();
+            let lanes: Vec<Vec<usize>> = lanes(&out_shape, axis).collect();
+            let scale = match reduction {
+                MPSGraphLossReductionType::Mean => 1.0 / lanes.len().max(1) as f64,
+                _ => 1.0,
+            };
+            let mut result = vec![0.0; x.len()];
+            for lane in &lanes {
+                let max = lane.iter().map(|&i| x[i]).fold(f64::NEG_INFINITY, f64::max);
+                let sum: f64 = lane.iter().map(|&i| (x[i] - max).exp()).sum();
+                let label_sum: f64 = lane.iter().map(|&i| labels[i]).sum();
+                for &i in lane {
+                    let softmax = (x[i] - max).exp() / sum;
+                    result[i] = (softmax * label_sum - labels[i]) * gradient[i] * scale;
+                }
+            }
+            ValueData::from_f64(out_type, result.into_iter())
+        }
+    };
+
+    Ok(vec![Value::new(&out_shape, data)])
+}
+
+fn check_data_type(op: &'static str, data_type: MPSDataType) -> Result<()> {
+    match data_type {
+        MPSDataType::Float32 | MPSDataType::Float64 | MPSDataType::Int32 | MPSDataType::Bool => {
+            Ok(())
+        }
+        _ => Err(InterpretError::UnsupportedDataType { op, data_type }),
+    }
+}
+
+fn check_float(op: &'static str, data_type: MPSDataType) -> Result<()> {
+    match data_type {
+        MPSDataType::Float32 | MPSDataType::Float64 => Ok(()),
+        _ => Err(InterpretError::UnsupportedDataType { op, data_type }),
+    }
+}
+
+fn unary_op(op: UnaryOp, x: &ValueData, out_type: MPSDataType) -> ValueData {
+    if let ValueData::Int32(values) = x {
+        let exact: Option<fn(i32) -> i32> = match op {
+            UnaryOp::Identity
+            | UnaryOp::Ceil
+            | UnaryOp::Floor
+            | UnaryOp::Round
+            | UnaryOp::Rint
+            | UnaryOp::Truncate => Some(|v| v),
+            UnaryOp::Abs => Some(i32::wrapping_abs),
+            UnaryOp::Negative => Some(i32::wrapping_neg),
+            UnaryOp::Square | UnaryOp::AbsSquare => Some(|v| v.wrapping_mul(v)),
+            UnaryOp::Sign => Some(i32::signum),
+            UnaryOp::Relu => Some(|v| v.max(0)),
+            UnaryOp::BitwiseNot => Some(|v| !v),
+            UnaryOp::BitwisePopulationCount => Some(|v| v.count_ones() as i32),
+            _ => None,
+        };
+        if let Some(f) = exact {
+            return ValueData::Int32(values.iter().map(|&v| f(v)).collect());
+        }
+    }
+
+    let f: fn(f64) -> f64 = match op {
+        UnaryOp::Identity => |v| v,
+        UnaryOp::Exp => f64::exp,
+        UnaryOp::Exp2 => f64::exp2,
+        UnaryOp::Exp10 => |v| 10f64.powf(v),
+        UnaryOp::Log => f64::ln,
+        UnaryOp::Log2 => f64::log2,
+        UnaryOp::Log10 => f64::log10,
+        UnaryOp::Square => |v| v * v,
+        UnaryOp::Sqrt => f64::sqrt,
+        UnaryOp::Rsqrt => |v| 1.0 / v.sqrt(),
+        UnaryOp::Reciprocal => |v| 1.0 / v,
+        UnaryOp::Abs => f64::abs,
+        UnaryOp::Negative => |v| -v,
+        UnaryOp::Sign => |v| {
+            if v == 0.0 || v.is_nan() {
+                v
+            } else {
+                v.signum()
+            }
+        },
+        UnaryOp::Ceil => f64::ceil,
+        UnaryOp::Floor => f64::floor,
+        UnaryOp::Round => f64::round,
+        UnaryOp::Rint => f64::round_ties_even,
+        UnaryOp::Truncate => f64::trunc,
+        UnaryOp::Sin => f64::sin,
+        UnaryOp::Cos => f64::cos,
+        UnaryOp::Tan => f64::tan,
+        UnaryOp::Sinh => f64::sinh,
+        UnaryOp::Cosh => f64::cosh,
+        UnaryOp::Tanh => f64::tanh,
+        UnaryOp::Asin => f64::asin,
+        UnaryOp::Acos => f64::acos,
+        UnaryOp::Atan => f64::atan,
+        UnaryOp::Asinh => f64::asinh,
+        UnaryOp::Acosh => f64::acosh,
+        UnaryOp::Atanh => f64::atanh,
+        UnaryOp::Erf => erf,
+        UnaryOp::LogicalNot => |v| (v == 0.0) as u8 as f64,
+        UnaryOp::IsNan => |v| v.is_nan() as u8 as f64,
+        UnaryOp::IsInfinite => |v| v.is_infinite() as u8 as f64,
+        UnaryOp::IsFinite => |v| v.is_finite() as u8 as f64,
+        UnaryOp::Relu => |v| if v < 0.0 { 0.0 } else { v },
+        UnaryOp::Sigmoid => |v| 1.0 / (1.0 + (-v).exp()),
+        UnaryOp::AbsSquare => |v| v * v,
+        UnaryOp::Signbit => |v| v.is_sign_negative() as u8 as f64,
+        UnaryOp::BitwiseNot | UnaryOp::BitwisePopulationCount => {
+            unreachable!("bitwise operands are checked by inference")
+        }
+    };
+    ValueData::from_f64(out_type, x.to_f64().into_iter().map(f))
+}
+
+fn binary_op(op: BinaryOp, a: &ValueData, b: &ValueData, out_type: MPSDataType) -> ValueData {
+    if let (ValueData::Int32(a), ValueData::Int32(b)) = (a, b) {
+        let exact: Option<fn(i32, i32) -> i32> = match op {
+            BinaryOp::Add => Some(i32::wrapping_add),
+            BinaryOp::Subtract => Some(i32::wrapping_sub),
+            BinaryOp::Multiply => Some(i32::wrapping_mul),
+            BinaryOp::Divide | BinaryOp::DivisionNoNan => {
+                Some(|x, y| if y == 0 { 0 } else { x.wrapping_div(y) })
+            }
+            BinaryOp::Modulo => Some(|x, y| if y == 0 { 0 } else { x.wrapping_rem(y) }),
+            BinaryOp::FloorModulo => Some(|x, y| {
+                if y == 0 {
+                    return 0;
+                }
+                let r = x.wrapping_rem(y);
+                if r != 0 && (r < 0) != (y < 0) {
+                    r + y
+                } else {
+                    r
+                }
+            }),
+            BinaryOp::Power => Some(|x, y| match (x, y) {
+                (_, 0..) => x.wrapping_pow(y as u32),
+                (1, _) => 1,
+                (-1, _) => {
+                    if y % 2 == 0 {
+                        1
+                    } else {
+                        -1
+                    }
+                }
+                _ => 0,
+            }),
+            BinaryOp::Minimum | BinaryOp::MinimumWithNanPropagation => Some(|x, y| x.min(y)),
+            BinaryOp::Maximum | BinaryOp::MaximumWithNanPropagation => Some(|x, y| x.max(y)),
+            BinaryOp::BitwiseAnd => Some(|x, y| x & y),
+            BinaryOp::BitwiseOr => Some(|x, y| x | y),
+            BinaryOp::BitwiseXor => Some(|x, y| x ^ y),
+            BinaryOp::LeftShift => Some(|x, y| x.checked_shl(y as u32).unwrap_or(0)),
+            BinaryOp::RightShift => Some(|x, y| x.checked_shr(y as u32).unwrap_or(x >> 31)),
+            _ => None,
+        };
+        if let Some(f) = exact {
+            return ValueData::Int32(a.iter().zip(b).map(|(&x, &y)| f(x, y)).collect());
+        }
+    }
+
+    let f: fn(f64, f64) -> f64 = match op {
+        BinaryOp::Add => |x, y| x + y,
+        BinaryOp::Subtract => |x, y| x - y,
+        BinaryOp::Multiply => |x, y| x * y,
+        BinaryOp::Divide => |x, y| x / y,
+        BinaryOp::DivisionNoNan => |x, y| if y == 0.0 { 0.0 } else { x / y },
+        BinaryOp::Modulo => |x, y| x % y,
+        BinaryOp::FloorModulo => |x, y| {
+            let r = x % y;
+            if r != 0.0 && (r < 0.0) != (y < 0.0) {
+                r + y
+            } else {
+                r
+            }
+        },
+        BinaryOp::Power => f64::powf,
+        BinaryOp::Minimum => f64::min,
+        BinaryOp::Maximum => f64::max,
+        BinaryOp::MinimumWithNanPropagation => |x, y| {
+            if x.is_nan() || y.is_nan() {
+                f64::NAN
+            } else {
+                x.min(y)
+            }
+        },
+        BinaryOp::MaximumWithNanPropagation => |x, y| {
+            if x.is_nan() || y.is_nan() {
+                f64::NAN
+            } else {
+                x.max(y)
+            }
+        },
+        BinaryOp::Atan2 => f64::atan2,
+        BinaryOp::Equal => |x, y| (x == y) as u8 as f64,
+        BinaryOp::NotEqual => |x, y| (x != y) as u8 as f64,
+        BinaryOp::LessThan => |x, y| (x < y) as u8 as f64,
+        BinaryOp::LessThanOrEqualTo => |x, y| (x <= y) as u8 as f64,
+        BinaryOp::GreaterThan => |x, y| (x > y) as u8 as f64,
+        BinaryOp::GreaterThanOrEqualTo => |x, y| (x >= y) as u8 as f64,
+        BinaryOp::LogicalAnd => |x, y| (x != 0.0 && y != 0.0) as u8 as f64,
+        BinaryOp::LogicalOr => |x, y| (x != 0.0 || y != 0.0) as u8 as f64,
+        BinaryOp::LogicalXor => |x, y| ((x != 0.0) != (y != 0.0)) as u8 as f64,
+        BinaryOp::LogicalNand => |x, y| !(x != 0.0 && y != 0.0) as u8 as f64,
+        BinaryOp::LogicalNor => |x, y| !(x != 0.0 || y != 0.0) as u8 as f64,
+        BinaryOp::LogicalXnor => |x, y| ((x != 0.0) == (y != 0.0)) as u8 as f64,
+        BinaryOp::BitwiseAnd
+        | BinaryOp::BitwiseOr
+        | BinaryOp::BitwiseXor
+        | BinaryOp::LeftShift
+        | BinaryOp::RightShift => unreachable!("bitwise operands are checked by inference"),
+    };
+    let values = a.to_f64().into_iter().zip(b.to_f64()).map(|(x, y)| f(x, y));
+    ValueData::from_f64(out_type, values)
+}
+
+fn matmul(op: &'static str, lhs: &Value, rhs: &Value) -> Result<ValueData> {
+    if lhs.data_type() == MPSDataType::Bool {
+        return Err(InterpretError::UnsupportedDataType {
+            op,
+            data_type: MPSDataType::Bool,
+        });
+    }
+
+    // Vectors become a single row or column; the dropped dimension has size 1 so the
+    // row-major result is the same
+    let promote = |shape: &[usize], row: bool| match shape.len() {
+        1 if row => vec![1, shape[0]],
+        1 => vec![shape[0], 1],
+        _ => shape.to_vec(),
+    };
+    let lhs_shape = promote(&lhs.shape, true);
+    let rhs_shape = promote(&rhs.shape, false);
+    let (lhs_batch, lhs_matrix) = lhs_shape.split_at(lhs_shape.len() - 2);
+    let (rhs_batch, rhs_matrix) = rhs_shape.split_at(rhs_shape.len() - 2);
+    let (m, k, n) = (lhs_matrix[0], lhs_matrix[1], rhs_matrix[1]);
+    let batch = Shape::from_static(lhs_batch)
+        .broadcast(&Shape::from_static(rhs_batch))
+        .map_err(|error| IrError::Shape { op, error })?
+        .to_static()
+        .unwrap();
+
+    let lhs_strides = strides(lhs_batch);
+    let rhs_strides = strides(rhs_batch);
+    let batch_offsets: Vec<(usize, usize)> = indices(&batch)
+        .map(|index| {
+            (
+                broadcast_offset(&index, lhs_batch, &lhs_strides) * m * k,
+                broadcast_offset(&index, rhs_batch, &rhs_strides) * k * n,
+            )
+        })
+        .collect();
+
+    Ok(match (&lhs.data, &rhs.data) {
+        (ValueData::Int32(a), ValueData::Int32(b)) => {
+            let mut result = Vec::with_capacity(batch_offsets.len() * m * n);
+            for &(a_base, b_base) in &batch_offsets {
+                for i in 0..m {
+                    for j in 0..n {
+                        result.push((0..k).fold(0i32, |sum, p| {
+                            sum.wrapping_add(
+                                a[a_base + i * k + p].wrapping_mul(b[b_base + p * n + j]),
+                            )
+                        }));
+                    }
+                }
+            }
+            ValueData::Int32(result)
+        }
+        _ => {
+            let (a, b) = (lhs.data.to_f64(), rhs.data.to_f64());
+            let mut result = Vec::with_capacity(batch_offsets.len() * m * n);
+            for &(a_base, b_base) in &batch_offsets {
+                for i in 0..m {
+                    for j in 0..n {
+                        result.push(
+                            (0..k)
+                                .map(|p| a[a_base + i * k + p] * b[b_base + p * n + j])
+                                .sum(),
+                        );
+                    }
+                }
+            }
+            ValueData::from_f64(lhs.data_type(), result.into_iter())
+        }
+    })
+}
+
+fn reduce(
+    op: &'static str,
+    reduction: ReductionOp,
+    axes: &[usize],
+    x: &Value,
+    out_shape: &[usize],
+) -> Result<ValueData> {
+    let out_strides = strides(out_shape);
+    let out_count: usize = out_shape.iter().product();
+    let out_offset = |index: &[usize]| {
+        index
+            .iter()
+            .enumerate()
+            .map(|(axis, &i)| {
+                if axes.contains(&axis) {
+                    0
+                } else {
+                    i * out_strides[axis]
+                }
+            })
+            .sum::<usize>()
+    };
+
+    match reduction {
+        ReductionOp::ArgMaximum | ReductionOp::ArgMinimum => {
+            if x.data_type() == MPSDataType::Bool {
+                return Err(InterpretError::UnsupportedDataType {
+                    op,
+                    data_type: MPSDataType::Bool,
+                });
+            }
+            let values = x.data.to_f64();
+            let axis = axes[0];
+            let result = lanes(&x.shape, axis)
+                .map(|lane| {
+                    let mut best = 0;
+                    for (position, &i) in lane.iter().enumerate() {
+                        let candidate = values[i];
+                        let current = values[lane[best]];
+                        let better = match reduction {
+                            ReductionOp::ArgMaximum => candidate > current,
+                            _ => candidate < current,
+                        };
+                        if better || current.is_nan() && !candidate.is_nan() {
+                            best = position;
+                        }
+                    }
+                    best as i32
+                })
+                .collect();
+            Ok(ValueData::Int32(result))
+        }
+        ReductionOp::Sum | ReductionOp::Product if x.data_type() == MPSDataType::Int32 => {
+            let values = x.as_i32().unwrap();
+            let mut result = vec![(reduction == ReductionOp::Product) as i32; out_count];
+            for (index, &value) in indices(&x.shape).zip(values) {
+                let slot = &mut result[out_offset(&index)];
+                *slot = match reduction {
+                    ReductionOp::Sum => slot.wrapping_add(value),
+                    _ => slot.wrapping_mul(value),
+                };
+            }
+            Ok(ValueData::Int32(result))
+        }
+        _ => {
+            let (initial, f): (f64, fn(f64, f64) -> f64) = match reduction {
+                ReductionOp::Sum | ReductionOp::Mean => (0.0, |acc, v| acc + v),
+                ReductionOp::Product => (1.0, |acc, v| acc * v),
+                ReductionOp::Maximum => (f64::NEG_INFINITY, f64::max),
+                ReductionOp::Minimum => (f64::INFINITY, f64::min),
+                ReductionOp::MaximumPropagateNan => (f64::NEG_INFINITY, |acc, v| {
+                    if acc.is_nan() || v.is_nan() {
+                        f64::NAN
+                    } else {
+                        acc.max(v)
+                    }
+                }),
+                ReductionOp::MinimumPropagateNan => (f64::INFINITY, |acc, v| {
+                    if acc.is_nan() || v.is_nan() {
+                        f64::NAN
+                    } else {
+                        acc.min(v)
+                    }
+                }),
+                ReductionOp::And => (1.0, |acc, v| (acc != 0.0 && v != 0.0) as u8 as f64),
+                ReductionOp::Or => (0.0, |acc, v| (acc != 0.0 || v != 0.0) as u8 as f64),
+                ReductionOp::Xor => (0.0, |acc, v| ((acc != 0.0) != (v != 0.0)) as u8 as f64),
+                ReductionOp::ArgMaximum | ReductionOp::ArgMinimum => unreachable!(),
+            };
+            let mut result = vec![initial; out_count];
+            for (index, value) in indices(&x.shape).zip(x.data.to_f64()) {
+                let slot = &mut result[out_offset(&index)];
+                *slot = f(*slot, value);
+            }
+            if reduction == ReductionOp::Mean {
+                let reduced: usize = axes.iter().map(|&axis| x.shape[axis]).product();
+                for value in &mut result {
+                    *value /= reduced as f64;
+                }
+            }
+            Ok(ValueData::from_f64(x.data_type(), result.into_iter()))
+        }
+    }
+}
+
+fn gather(
+    op: &'static str,
+    params: &Value,
+    indices_value: &Value,
+    axis: usize,
+    batch_dimensions: usize,
+    out_shape: &[usize],
+) -> Result<ValueData> {
+    if batch_dimensions > axis {
+        return Err(InterpretError::Invalid {
+            op,
+            reason: format!(
+                "batch dimensions {} must not exceed axis {}",
+                batch_dimensions, axis
+            ),
+        });
+    }
+    let positions = index_values(indices_value);
+    let index_strides = strides(&indices_value.shape);
+    let param_strides = strides(&params.shape);
+    let gathered_rank = indices_value.shape.len() - batch_dimensions;
+
+    let mut offsets = Vec::with_capacity(out_shape.iter().product());
+    for index in indices(out_shape) {
+        let index_position: Vec<usize> = index[..batch_dimensions]
+            .iter()
+            .chain(&index[axis..axis + gathered_rank])
+            .copied()
+            .collect();
+        let position = positions[offset(&index_position, &index_strides)];
+        let position = resolve_index(op, position, params.shape[axis])?;
+        let param_index: Vec<usize> = index[..axis]
+            .iter()
+            .copied()
+            .chain(std::iter::once(position))
+            .chain(index[axis + gathered_rank..].iter().copied())
+            .collect();
+        offsets.push(offset(&param_index, &param_strides));
+    }
+    Ok(params.data.take(&offsets))
+}
+
+fn gather_along_axis(
+    op: &'static str,
+    params: &Value,
+    indices_value: &Value,
+    axis: isize,
+) -> Result<ValueData> {
+    let axis = normalize_axis(axis as i64, params.shape.len()).unwrap();
+    check_along_axis_shapes(op, &params.shape, &indices_value.shape, axis)?;
+    let positions = index_values(indices_value);
+    let param_strides = strides(&params.shape);
+
+    let mut offsets = Vec::with_capacity(positions.len());
+    for (mut index, &position) in indices(&indices_value.shape).zip(&positions) {
+        index[axis] = resolve_index(op, position, params.shape[axis])?;
+        offsets.push(offset(&index, &param_strides));
+    }
+    Ok(params.data.take(&offsets))
+}
+
+/// Scatters `updates` into a zero-initialized tensor of `out_shape`
+///
+/// With `along_axis`, each update element goes to its own position with the `axis`
+/// coordinate replaced by the matching index. Otherwise whole slices are scattered, the
+/// inverse of [`gather`] without batch dimensions.
+fn scatter(
+    op: &'static str,
+    updates: &Value,
+    indices_value: &Value,
+    axis: usize,
+    mode: MPSGraphScatterMode,
+    out_shape: &[usize],
+    along_axis: bool,
+) -> Result<ValueData> {
+    let positions = index_values(indices_value);
+    let index_strides = strides(&indices_value.shape);
+    let out_strides = strides(out_shape);
+    let index_rank = indices_value.shape.len();
+
+    if along_axis {
+        check_along_axis_shapes(op, out_shape, &updates.shape, axis)?;
+        if updates.shape != indices_value.shape {
+            return Err(InterpretError::Invalid {
+                op,
+                reason: format!(
+                    "updates {:?} and indices {:?} must have the same shape",
+                    updates.shape, indices_value.shape
+                ),
+            });
+        }
+    } else {
+        let expected: Vec<usize> = out_shape[..axis]
+            .iter()
+            .chain(&indices_value.shape)
+            .chain(&out_shape[axis + 1..])
+            .copied()
+            .collect();
+        if updates.shape != expected {
+            return Err(InterpretError::Invalid {
+                op,
+                reason: format!(
+                    "updates have shape {:?}, expected {:?}",
+                    updates.shape, expected
+                ),
+            });
+        }
+    }
+
+    let mut result = vec![0.0; out_shape.iter().product()];
+    for (index, value) in indices(&updates.shape).zip(updates.data.to_f64()) {
+        let out_index = if along_axis {
+            let position = positions[offset(&index, &index_strides)];
+            let mut out_index = index.clone();
+            out_index[axis] = resolve_index(op, position, out_shape[axis])?;
+            out_index
+        } else {
+            let position = positions[offset(&index[axis..axis + index_rank], &index_strides)];
+            let position = resolve_index(op, position, out_shape[axis])?;
+            index[..axis]
+                .iter()
+                .copied()
+                .chain(std::iter::once(position))
+                .chain(index[axis + index_rank..].iter().copied())
+                .collect()
+        };
+
+        let slot = &mut result[offset(&out_index, &out_strides)];
+        *slot = match mode {
+            MPSGraphScatterMode::Add => *slot + value,
+            MPSGraphScatterMode::Sub => *slot - value,
+            MPSGraphScatterMode::Mul => *slot * value,
+            MPSGraphScatterMode::Div => *slot / value,
+            MPSGraphScatterMode::Min => slot.min(value),
+            MPSGraphScatterMode::Max => slot.max(value),
+            MPSGraphScatterMode::Set => value,
+        };
+    }
+    Ok(ValueData::from_f64(updates.data_type(), result.into_iter()))
+}
+
+/// Checks that two shapes agree on every axis except `axis`
+fn check_along_axis_shapes(
+    op: &'static str,
+    data: &[usize],
+    indices: &[usize],
+    axis: usize,
+) -> Result<()> {
+    let matches =
+        data.len() == indices.len() && (0..data.len()).all(|a| a == axis || data[a] == indices[a]);
+    if !matches {
+        return Err(InterpretError::Invalid {
+            op,
+            reason: format!(
+                "shapes {:?} and {:?} must match outside axis {}",
+                data, indices, axis
+            ),
+        });
+    }
+    Ok(())
+}
+
+fn index_values(value: &Value) -> Vec<i64> {
+    value.data.to_f64().into_iter().map(|v| v as i64).collect()
+}
+
+/// Resolves a possibly negative index into `0..size`
+fn resolve_index(op: &'static str, index: i64, size: usize) -> Result<usize> {
+    let resolved = if index < 0 {
+        index + size as i64
+    } else {
+        index
+    };
+    if resolved < 0 || resolved >= size as i64 {
+        return Err(InterpretError::IndexOutOfRange { op, index, size });
+    }
+    Ok(resolved as usize)
+}
+
+/// Error function, accurate to about 1e-14
+fn erf(x: f64) -> f64 {
+    if x.is_nan() {
+        return x;
+    }
+    let a = x.abs();
+    let result = if a < 3.0 {
+        // Maclaurin series
+        let mut term = a;
+        let mut sum = a;
+        let mut n = 0.0;
+        while term.abs() > 1e-17 * sum.abs() {
+            n += 1.0;
+            term *= -a * a / n;
+            sum += term / (2.0 * n + 1.0);
+        }
+        sum * 2.0 / std::f64::consts::PI.sqrt()
+    } else {
+        // Continued fraction for erfc
+        let mut fraction = 0.0;
+        for k in (1..=60).rev() {
+            fraction = (k as f64 / 2.0) / (a + fraction);
+        }
+        1.0 - (-a * a).exp() / std::f64::consts::PI.sqrt() / (a + fraction)
+    };
+    result.copysign(x)
+}
+
+fn strides(shape: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1; shape.len()];
+    for axis in (0..shape.len().saturating_sub(1)).rev() {
+        strides[axis] = strides[axis + 1] * shape[axis + 1];
+    }
+    strides
+}
+
+fn offset(index: &[usize], strides: &[usize]) -> usize {
+    index
+        .iter()
+        .zip(strides)
+        .map(|(i, stride)| i * stride)
+        .sum()
+}
+
+/// Iterates over every multi-dimensional index of `shape` in row-major order
+fn indices(shape: &[usize]) -> impl Iterator<Item = Vec<usize>> + '_ {
+    let count: usize = shape.iter().product();
+    (0..count).map(move |mut flat| {
+        let mut index = vec![0; shape.len()];
+        for axis in (0..shape.len()).rev() {
+            index[axis] = flat % shape[axis];
+            flat /= shape[axis];
+        }
+        index
+    })
+}
+
+/// Iterates over the element offsets of every 1-D lane along `axis`
+fn lanes(shape: &[usize], axis: usize) -> impl Iterator<Item = Vec<usize>> + '_ {
+    let strides = strides(shape);
+    let mut outer = shape.to_vec();
+    outer[axis] = 1;
+    let count: usize = outer.iter().product();
+    (0..count).map(move |flat| {
+        let mut index = vec![0; outer.len()];
+        let mut rest = flat;
+        for a in (0..outer.len()).rev() {
+            index[a] = rest % outer[a];
+            rest /= outer[a];
+        }
+        let base = offset(&index, &strides);
+        (0..shape[axis]).map(|i| base + i * strides[axis]).collect()
+    })
+}
+
+/// Offset into a tensor of `shape` for an index of a tensor it broadcasts to
+fn broadcast_offset(index: &[usize], shape: &[usize], strides: &[usize]) -> usize {
+    let skip = index.len() - shape.len();
+    shape
+        .iter()
+        .zip(strides)
+        .zip(&index[skip..])
+        .map(|((&size, &stride), &i)| if size == 1 { 0 } else { i * stride })
+        .sum()
+}
+
+fn broadcast_to(value: &Value, shape: &[usize]) -> ValueData {
+    if value.shape == shape {
+        return value.data.clone();
+    }
+    let strides = strides(&value.shape);
+    let offsets: Vec<usize> = indices(shape)
+        .map(|index| broadcast_offset(&index, &value.shape, &strides))
+        .collect();
+    value.data.take(&offsets)
+}
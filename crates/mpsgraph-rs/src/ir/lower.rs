@@ -0,0 +1,403 @@
+//! Replays a recorded [`Graph`] onto a real `MPSGraph`.
+
+use super::{BinaryOp, Graph, OpKind, Operation, PoolingOp, ReductionOp, TensorId, UnaryOp};
+use crate::convolution_ops::{
+    MPSGraphConvolution2DOpDescriptor, MPSGraphConvolutionDataLayout, MPSGraphPaddingMode,
+};
+use crate::convolution_transpose_ops::TensorNamedDataLayout;
+use crate::graph::MPSGraph;
+use crate::im2col_ops::MPSGraphImToColOpDescriptor;
+use crate::pooling_ops::{
+    MPSGraphPaddingStyle, MPSGraphPooling2DOpDescriptor, MPSGraphTensorNamedDataLayout,
+};
+use crate::shape::MPSShape;
+use crate::tensor::MPSGraphTensor;
+use objc2::runtime::AnyObject;
+use objc2_foundation::NSData;
+
+/// An `MPSGraph` built from a recorded [`Graph`]
+pub struct LoweredGraph {
+    graph: MPSGraph,
+    tensors: Vec<MPSGraphTensor>,
+}
+
+impl LoweredGraph {
+    /// Returns the `MPSGraph` the operations were replayed onto
+    pub fn graph(&self) -> &MPSGraph {
+        &self.graph
+    }
+
+    /// Returns the `MPSGraphTensor` that corresponds to a recorded tensor
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was not recorded in the lowered graph.
+    pub fn tensor(&self, id: TensorId) -> &MPSGraphTensor {
+        &self.tensors[id.0]
+    }
+}
+
+impl Graph {
+    /// Replays every recorded operation onto a new `MPSGraph`
+    pub fn lower_to_mpsgraph(&self) -> LoweredGraph {
+        let graph = MPSGraph::new();
+        let mut tensors: Vec<MPSGraphTensor> = Vec::with_capacity(self.tensors.len());
+
+        for op in &self.operations {
+            let results = lower_operation(&graph, op, &tensors);
+            debug_assert_eq!(results.len(), op.outputs.len());
+            // Tensor ids are assigned in recording order, so results line up with `tensors`
+            tensors.extend(results);
+        }
+
+        LoweredGraph { graph, tensors }
+    }
+}
+
+fn lower_operation(
+    graph: &MPSGraph,
+    op: &Operation,
+    tensors: &[MPSGraphTensor],
+) -> Vec<MPSGraphTensor> {
+    let input = |i: usize| &tensors[op.inputs[i].0];
+    let inputs = || {
+        op.inputs
+            .iter()
+            .map(|id| tensors[id.0].clone())
+            .collect::<Vec<_>>()
+    };
+    let name = op.name.as_deref();
+
+    let result = match &op.kind {
+        OpKind::Placeholder { shape, data_type } => {
+            graph.placeholder(&MPSShape::from_shape(shape), *data_type, name)
+        }
+        OpKind::Constant {
+            data,
+            shape,
+            data_type,
+        } => {
+            let shape = MPSShape::from_shape(shape);
+            unsafe {
+                let data = NSData::with_bytes(data);
+                let data_ptr: *mut AnyObject = data.as_ref() as *const NSData as *mut AnyObject;
+                let tensor: *mut AnyObject = msg_send![
+                    graph.0,
+                    constantWithData: data_ptr,
+                    shape: shape.0,
+                    dataType: *data_type as u32
+                ];
+
+                let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+                MPSGraphTensor::from_retained(tensor)
+            }
+        }
+        OpKind::ConstantScalar {
+            value,
+            shape,
+            data_type,
+        } => {
+            if shape.rank() == 0 {
+                graph.constant_scalar(*value, *data_type)
+            } else {
+                graph.constant_scalar_with_shape(*value, &MPSShape::from_shape(shape), *data_type)
+            }
+        }
+        OpKind::Unary(unary) => lower_unary(graph, *unary, input(0), name),
+        OpKind::Binary(binary) => lower_binary(graph, *binary, input(0), input(1), name),
+        OpKind::Select => graph.select(input(0), input(1), input(2), name),
+        OpKind::Clamp => graph.clamp(input(0), input(1), input(2), name),
+        OpKind::Cast { data_type } => graph.cast(input(0), *data_type, name),
+        OpKind::MatMul => graph.matmul(input(0), input(1), name),
+        OpKind::Reduction {
+            op: reduction,
+            axes,
+        } => {
+            let axes: Vec<i64> = axes.iter().map(|&axis| axis as i64).collect();
+            let x = input(0);
+            match reduction {
+                ReductionOp::Sum => graph.reduction_sum_with_tensor_axes(x, Some(&axes), name),
+                ReductionOp::Mean => graph.mean(x, &axes, name),
+                ReductionOp::Maximum => {
+                    graph.reduction_maximum_with_tensor_axes(x, Some(&axes), name)
+                }
+                ReductionOp::Minimum => {
+                    graph.reduction_minimum_with_tensor_axes(x, Some(&axes), name)
+                }
+                ReductionOp::MaximumPropagateNan => {
+                    graph.reduction_maximum_propagate_nan_with_tensor_axes(x, Some(&axes), name)
+                }
+                ReductionOp::MinimumPropagateNan => {
+                    graph.reduction_minimum_propagate_nan_with_tensor_axes(x, Some(&axes), name)
+                }
+                ReductionOp::Product => {
+                    graph.reduction_product_with_tensor_axes(x, Some(&axes), name)
+                }
+                ReductionOp::And => graph.reduction_and_with_tensor_axes(x, Some(&axes), name),
+                ReductionOp::Or => graph.reduction_or_with_tensor_axes(x, Some(&axes), name),
+                ReductionOp::Xor => graph.reduction_xor_with_tensor_axes(x, Some(&axes), name),
+                ReductionOp::ArgMaximum => {
+                    graph.reduction_arg_maximum_with_tensor_axis(x, axes[0], name)
+                }
+                ReductionOp::ArgMinimum => {
+                    graph.reduction_arg_minimum_with_tensor_axis(x, axes[0], name)
+                }
+            }
+        }
+        OpKind::Reshape { shape } => graph.reshape(input(0), shape, name),
+        OpKind::Transpose { permutation } => graph.transpose(input(0), permutation, name),
+        OpKind::Broadcast { shape } => graph.broadcast(input(0), shape, name),
+        OpKind::Concat { axis } => graph.concatenate(&inputs(), *axis, name),
+        OpKind::Stack { axis } => graph.stack(&inputs(), *axis, name),
+        OpKind::Split { num_splits, axis } => {
+            return graph.split(input(0), *num_splits as i64, *axis, name);
+        }
+        OpKind::Squeeze { axes } => graph.squeeze(input(0), axes, name),
+        OpKind::ExpandDims { axes } => graph.expand_dims(input(0), axes, name),
+        OpKind::Tile { multiples } => graph.tile(input(0), multiples, name),
+        OpKind::Reverse { axes } => graph.reverse(input(0), axes, name),
+        OpKind::Flatten2d { axis } => graph.flatten2d(input(0), *axis, name),
+        OpKind::Slice {
+            dimension,
+            start,
+            length,
+        } => graph.slice(input(0), *dimension, *start, *length, name),
+        OpKind::StridedSlice {
+            starts,
+            ends,
+            strides,
+            masks,
+        } => graph.strided_slice(input(0), starts, ends, strides, masks, name),
+        OpKind::Gather {
+            axis,
+            batch_dimensions,
+        } => graph.gather(input(0), input(1), *axis, *batch_dimensions, name),
+        OpKind::GatherAlongAxis { axis } => {
+            graph.gather_along_axis(*axis, input(0), input(1), name)
+        }
+        OpKind::Scatter { shape, axis, mode } => graph.scatter(
+            input(0),
+            input(1),
+            &MPSShape::from_shape(shape),
+            *axis,
+            *mode,
+            name,
+        ),
+        OpKind::ScatterAlongAxis { shape, axis, mode } => graph.scatter_along_axis(
+            *axis,
+            input(0),
+            input(1),
+            &MPSShape::from_shape(shape),
+            *mode,
+            name,
+        ),
+        OpKind::Softmax { axis } => graph.softmax(input(0), *axis, name),
+        OpKind::SoftmaxCrossEntropy { axis, reduction } => {
+            graph.softmax_cross_entropy(input(0), input(1), *axis, *reduction, name)
+        }
+        OpKind::SoftmaxCrossEntropyGradient { axis, reduction } => graph
+            .softmax_cross_entropy_gradient(input(0), input(1), input(2), *axis, *reduction, name),
+        OpKind::Convolution2d(attributes) => {
+            let descriptor = MPSGraphConvolution2DOpDescriptor::new();
+            descriptor.set_stride_in_y(attributes.strides[0]);
+            descriptor.set_stride_in_x(attributes.strides[1]);
+            descriptor.set_dilation_rate_in_y(attributes.dilations[0]);
+            descriptor.set_dilation_rate_in_x(attributes.dilations[1]);
+            let [top, bottom, left, right] = attributes.padding;
+            descriptor.set_explicit_padding(left, right, top, bottom);
+            descriptor.set_padding_style(match attributes.padding_style {
+                MPSGraphPaddingStyle::Explicit => MPSGraphPaddingMode::Explicit,
+                MPSGraphPaddingStyle::TfValid => MPSGraphPaddingMode::Valid,
+                MPSGraphPaddingStyle::TfSame => MPSGraphPaddingMode::Same,
+            });
+            descriptor.set_data_layout(match attributes.data_layout {
+                MPSGraphTensorNamedDataLayout::NCHW => MPSGraphConvolutionDataLayout::NCHW,
+                MPSGraphTensorNamedDataLayout::NHWC => MPSGraphConvolutionDataLayout::NHWC,
+            });
+            descriptor.set_weights_layout(attributes.weights_layout);
+            descriptor.set_groups(attributes.groups);
+            graph.convolution_2d(input(0), input(1), &descriptor, name)
+        }
+        OpKind::Pooling2d { op, window } => {
+            let descriptor = MPSGraphPooling2DOpDescriptor::new(
+                window.kernel[1],
+                window.kernel[0],
+                window.strides[1],
+                window.strides[0],
+                window.dilations[1],
+                window.dilations[0],
+                window.padding[2],
+                window.padding[3],
+                window.padding[0],
+                window.padding[1],
+                window.padding_style,
+                window.data_layout,
+            );
+            descriptor.set_ceil_mode(window.ceil_mode);
+            match op {
+                PoolingOp::Max => graph.max_pooling_2d(input(0), &descriptor, name),
+                PoolingOp::Average => graph.avg_pooling_2d(input(0), &descriptor, name),
+                PoolingOp::L2Norm => graph.l2_norm_pooling_2d(input(0), &descriptor, name),
+            }
+        }
+        OpKind::ImToCol(window) => {
+            let descriptor = MPSGraphImToColOpDescriptor::descriptor_with_kernel_dimensions(
+                window.kernel[1],
+                window.kernel[0],
+                window.strides[1],
+                window.strides[0],
+                window.dilations[1],
+                window.dilations[0],
+                window.padding[2],
+                window.padding[3],
+                window.padding[0],
+                window.padding[1],
+                named_layout(window.data_layout),
+            );
+            graph.im_to_col(input(0), &descriptor, name)
+        }
+        OpKind::Resize {
+            size,
+            mode,
+            center_result,
+            align_corners,
+            data_layout,
+        } => graph.resize(
+            input(0),
+            &MPSShape::from_slice(size),
+            *mode,
+            *center_result,
+            *align_corners,
+            named_layout(*data_layout),
+            name,
+        ),
+        OpKind::TopK { axis, k, largest } => {
+            let (values, indices) = if *largest {
+                graph.top_k_axis(input(0), *axis as isize, *k, name)
+            } else {
+                graph.bottom_k_axis(input(0), *axis as isize, *k, name)
+            };
+            return vec![values, indices];
+        }
+        OpKind::NonMaximumSuppression {
+            iou_threshold,
+            score_threshold,
+            per_class_suppression,
+            coordinate_mode,
+        } => graph.non_maximum_suppression(
+            input(0),
+            input(1),
+            *iou_threshold,
+            *score_threshold,
+            *per_class_suppression,
+            *coordinate_mode,
+            name,
+        ),
+    };
+
+    vec![result]
+}
+
+fn named_layout(layout: MPSGraphTensorNamedDataLayout) -> TensorNamedDataLayout {
+    match layout {
+        MPSGraphTensorNamedDataLayout::NCHW => TensorNamedDataLayout::NCHW,
+        MPSGraphTensorNamedDataLayout::NHWC => TensorNamedDataLayout::NHWC,
+    }
+}
+
+fn lower_unary(
+    graph: &MPSGraph,
+    op: UnaryOp,
+    x: &MPSGraphTensor,
+    name: Option<&str>,
+) -> MPSGraphTensor {
+    match op {
+        UnaryOp::Identity => graph.identity(x, name),
+        UnaryOp::Exp => graph.exp(x, name),
+        UnaryOp::Exp2 => graph.exp2(x, name),
+        UnaryOp::Exp10 => graph.exp10(x, name),
+        UnaryOp::Log => graph.log(x, name),
+        UnaryOp::Log2 => graph.log2(x, name),
+        UnaryOp::Log10 => graph.log10(x, name),
+        UnaryOp::Square => graph.square(x, name),
+        UnaryOp::Sqrt => graph.sqrt(x, name),
+        UnaryOp::Rsqrt => graph.rsqrt(x, name),
+        UnaryOp::Reciprocal => graph.reciprocal(x, name),
+        UnaryOp::Abs => graph.abs(x, name),
+        UnaryOp::Negative => graph.negative(x, name),
+        UnaryOp::Sign => graph.sign(x, name),
+        UnaryOp::Ceil => graph.ceil(x, name),
+        UnaryOp::Floor => graph.floor(x, name),
+        UnaryOp::Round => graph.round(x, name),
+        UnaryOp::Rint => graph.rint(x, name),
+        UnaryOp::Truncate => graph.truncate(x, name),
+        UnaryOp::Sin => graph.sin(x, name),
+        UnaryOp::Cos => graph.cos(x, name),
+        UnaryOp::Tan => graph.tan(x, name),
+        UnaryOp::Sinh => graph.sinh(x, name),
+        UnaryOp::Cosh => graph.cosh(x, name),
+        UnaryOp::Tanh => graph.tanh(x, name),
+        UnaryOp::Asin => graph.asin(x, name),
+        UnaryOp::Acos => graph.acos(x, name),
+        UnaryOp::Atan => graph.atan(x, name),
+        UnaryOp::Asinh => graph.asinh(x, name),
+        UnaryOp::Acosh => graph.acosh(x, name),
+        UnaryOp::Atanh => graph.atanh(x, name),
+        UnaryOp::Erf => graph.erf(x, name),
+        UnaryOp::LogicalNot => graph.logical_not(x, name),
+        UnaryOp::IsNan => graph.is_nan(x, name),
+        UnaryOp::IsInfinite => graph.is_infinite(x, name),
+        UnaryOp::IsFinite => graph.is_finite(x, name),
+        UnaryOp::Relu => graph.relu(x, name),
+        UnaryOp::Sigmoid => graph.sigmoid(x, name),
+        UnaryOp::AbsSquare => graph.abs_square(x, name),
+        UnaryOp::Signbit => graph.signbit(x, name),
+        UnaryOp::BitwiseNot => graph.bitwise_not(x, name),
+        UnaryOp::BitwisePopulationCount => graph.bitwise_population_count(x, name),
+    }
+}
+
+fn lower_binary(
+    graph: &MPSGraph,
+    op: BinaryOp,
+    primary: &MPSGraphTensor,
+    secondary: &MPSGraphTensor,
+    name: Option<&str>,
+) -> MPSGraphTensor {
+    match op {
+        BinaryOp::Add => graph.add(primary, secondary, name),
+        BinaryOp::Subtract => graph.subtract(primary, secondary, name),
+        BinaryOp::Multiply => graph.multiply(primary, secondary, name),
+        BinaryOp::Divide => graph.divide(primary, secondary, name),
+        BinaryOp::DivisionNoNan => graph.division_no_nan(primary, secondary, name),
+        BinaryOp::Modulo => graph.modulo(primary, secondary, name),
+        BinaryOp::FloorModulo => graph.floor_modulo(primary, secondary, name),
+        BinaryOp::Power => graph.power(primary, secondary, name),
+        BinaryOp::Minimum => graph.minimum(primary, secondary, name),
+        BinaryOp::Maximum => graph.maximum(primary, secondary, name),
+        BinaryOp::MinimumWithNanPropagation => {
+            graph.minimum_with_nan_propagation(primary, secondary, name)
+        }
+        BinaryOp::MaximumWithNanPropagation => {
+            graph.maximum_with_nan_propagation(primary, secondary, name)
+        }
+        BinaryOp::Atan2 => graph.atan2(primary, secondary, name),
+        BinaryOp::Equal => graph.equal(primary, secondary, name),
+        BinaryOp::NotEqual => graph.not_equal(primary, secondary, name),
+        BinaryOp::LessThan => graph.less_than(primary, secondary, name),
+        BinaryOp::LessThanOrEqualTo => graph.less_than_or_equal_to(primary, secondary, name),
+        BinaryOp::GreaterThan => graph.greater_than(primary, secondary, name),
+        BinaryOp::GreaterThanOrEqualTo => graph.greater_than_or_equal_to(primary, secondary, name),
+        BinaryOp::LogicalAnd => graph.logical_and(primary, secondary, name),
+        BinaryOp::LogicalOr => graph.logical_or(primary, secondary, name),
+        BinaryOp::LogicalXor => graph.logical_xor(primary, secondary, name),
+        BinaryOp::LogicalNand => graph.logical_nand(primary, secondary, name),
+        BinaryOp::LogicalNor => graph.logical_nor(primary, secondary, name),
+        BinaryOp::LogicalXnor => graph.logical_xnor(primary, secondary, name),
+        BinaryOp::BitwiseAnd => graph.bitwise_and(primary, secondary, name),
+        BinaryOp::BitwiseOr => graph.bitwise_or(primary, secondary, name),
+        BinaryOp::BitwiseXor => graph.bitwise_xor(primary, secondary, name),
+        BinaryOp::LeftShift => graph.left_shift(primary, secondary, name),
+        BinaryOp::RightShift => graph.right_shift(primary, secondary, name),
+    }
+}
@@ -0,0 +1,677 @@
+//! Backend-agnostic recorded graph IR.
+//!
+//! [`Graph`] records operations through builder methods that mirror the `MPSGraph` op
+//! surface, but refer to tensors by [`TensorId`] instead of sending Objective-C messages.
+//! Every recorded tensor carries its inferred data type and [`Shape`], so graphs can be
+//! built, inspected and transformed on any host. On Apple targets
+//! [`Graph::lower_to_mpsgraph`] replays the recorded operations onto a real `MPSGraph`,
+//! and on every host [`Graph::interpret`] evaluates them on the CPU as a reference.
+//! There, too, `MPSGraph::try_add`, `try_matmul` and the other shape-checked builders run the
+//! same inference rules on `MPSGraphTensor` operands before creating the operation.
+//!
+//! ```
+//! use mpsgraph::ir::Graph;
+//! use mpsgraph::{MPSDataType, Shape};
+//!
+//! let mut graph = Graph::new();
+//! let x = graph.placeholder(&Shape::from_i64(&[-1, 4]), MPSDataType::Float32, Some("x"));
+//! let w = graph.placeholder(&Shape::from_static(&[4, 2]), MPSDataType::Float32, Some("w"));
+//! let y = graph.matmul(x, w, Some("y"));
+//!
+//! assert_eq!(graph.shape(y), &Shape::from_i64(&[-1, 2]));
+//! ```
+
+mod builder;
+#[cfg(target_vendor = "apple")]
+mod checked;
+mod dot;
+mod infer;
+mod interpret;
+#[cfg(target_vendor = "apple")]
+mod lower;
+mod text;
+
+pub use infer::IrError;
+pub use interpret::{InterpretError, Value, ValueData};
+#[cfg(target_vendor = "apple")]
+pub use lower::LoweredGraph;
+pub use text::ParseError;
+
+use crate::convolution_ops::MPSGraphWeightsLayout;
+use crate::core::MPSDataType;
+use crate::dims::Shape;
+use crate::loss_ops::MPSGraphLossReductionType;
+use crate::non_maximum_suppression_ops::MPSGraphNonMaximumSuppressionCoordinateMode;
+use crate::pooling_ops::{MPSGraphPaddingStyle, MPSGraphTensorNamedDataLayout};
+use crate::resize_ops::MPSGraphResizeMode;
+use crate::scatter_nd_ops::MPSGraphScatterMode;
+use crate::tensor_shape_ops::MPSGraphSliceMasks;
+use std::fmt;
+
+/// Identifies a tensor recorded in a [`Graph`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TensorId(pub(crate) usize);
+
+impl TensorId {
+    /// Returns the position of the tensor in [`Graph::tensors`]
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl fmt::Display for TensorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+/// Identifies an operation recorded in a [`Graph`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OpId(pub(crate) usize);
+
+impl OpId {
+    /// Returns the position of the operation in [`Graph::operations`]
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Type information and provenance of a recorded tensor
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorInfo {
+    /// Element type
+    pub data_type: MPSDataType,
+    /// Shape, with dynamic dimensions where the size is only known at run time
+    pub shape: Shape,
+    /// Name of the producing operation, if any
+    pub name: Option<String>,
+    /// The operation that produces this tensor
+    pub producer: OpId,
+}
+
+/// Elementwise operations with one operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOp {
+    Identity,
+    Exp,
+    Exp2,
+    Exp10,
+    Log,
+    Log2,
+    Log10,
+    Square,
+    Sqrt,
+    Rsqrt,
+    Reciprocal,
+    Abs,
+    Negative,
+    Sign,
+    Ceil,
+    Floor,
+    Round,
+    Rint,
+    Truncate,
+    Sin,
+    Cos,
+    Tan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Asin,
+    Acos,
+    Atan,
+    Asinh,
+    Acosh,
+    Atanh,
+    Erf,
+    LogicalNot,
+    IsNan,
+    IsInfinite,
+    IsFinite,
+    Relu,
+    Sigmoid,
+    /// The squared magnitude; `x * x` for real operands
+    AbsSquare,
+    /// Whether the sign bit is set, so `-0.0` gives true
+    Signbit,
+    BitwiseNot,
+    /// Number of set bits in each element
+    BitwisePopulationCount,
+}
+
+impl UnaryOp {
+    /// Returns true if the result is Bool regardless of the operand type
+    pub fn is_predicate(self) -> bool {
+        matches!(
+            self,
+            UnaryOp::LogicalNot
+                | UnaryOp::IsNan
+                | UnaryOp::IsInfinite
+                | UnaryOp::IsFinite
+                | UnaryOp::Signbit
+        )
+    }
+
+    /// Returns true if the operand must be an integer
+    pub fn is_bitwise(self) -> bool {
+        matches!(self, UnaryOp::BitwiseNot | UnaryOp::BitwisePopulationCount)
+    }
+}
+
+/// Elementwise operations with two broadcast operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    DivisionNoNan,
+    Modulo,
+    FloorModulo,
+    Power,
+    Minimum,
+    Maximum,
+    /// Like `Minimum`, but NaN if either operand is NaN
+    MinimumWithNanPropagation,
+    /// Like `Maximum`, but NaN if either operand is NaN
+    MaximumWithNanPropagation,
+    Atan2,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqualTo,
+    GreaterThan,
+    GreaterThanOrEqualTo,
+    LogicalAnd,
+    LogicalOr,
+    LogicalXor,
+    LogicalNand,
+    LogicalNor,
+    LogicalXnor,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    /// Shifts the primary operand left by the secondary operand
+    LeftShift,
+    /// Shifts the primary operand right by the secondary operand, keeping the sign
+    RightShift,
+}
+
+impl BinaryOp {
+    /// Returns true if the result is Bool regardless of the operand type
+    pub fn is_predicate(self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Equal
+                | BinaryOp::NotEqual
+                | BinaryOp::LessThan
+                | BinaryOp::LessThanOrEqualTo
+                | BinaryOp::GreaterThan
+                | BinaryOp::GreaterThanOrEqualTo
+                | BinaryOp::LogicalAnd
+                | BinaryOp::LogicalOr
+                | BinaryOp::LogicalXor
+                | BinaryOp::LogicalNand
+                | BinaryOp::LogicalNor
+                | BinaryOp::LogicalXnor
+        )
+    }
+
+    /// Returns true if the operands must be integers
+    pub fn is_bitwise(self) -> bool {
+        matches!(
+            self,
+            BinaryOp::BitwiseAnd
+                | BinaryOp::BitwiseOr
+                | BinaryOp::BitwiseXor
+                | BinaryOp::LeftShift
+                | BinaryOp::RightShift
+        )
+    }
+}
+
+/// Reductions over one or more axes; reduced axes are kept with size 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReductionOp {
+    Sum,
+    /// Sum divided by the number of reduced elements
+    Mean,
+    Maximum,
+    Minimum,
+    /// Like `Maximum`, but NaN if any reduced element is NaN
+    MaximumPropagateNan,
+    /// Like `Minimum`, but NaN if any reduced element is NaN
+    MinimumPropagateNan,
+    Product,
+    And,
+    Or,
+    Xor,
+    /// Index of the maximum along a single axis, as Int32
+    ArgMaximum,
+    /// Index of the minimum along a single axis, as Int32
+    ArgMinimum,
+}
+
+/// Reduction applied over each pooling window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PoolingOp {
+    Max,
+    Average,
+    L2Norm,
+}
+
+/// Attributes of a 2D convolution
+///
+/// Spatial pairs are `[height, width]` (`[y, x]` in `MPSGraph` terms) and `padding` is
+/// `[top, bottom, left, right]`. Padding values are only used with
+/// [`MPSGraphPaddingStyle::Explicit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Convolution2d {
+    pub strides: [usize; 2],
+    pub dilations: [usize; 2],
+    pub padding: [usize; 4],
+    pub padding_style: MPSGraphPaddingStyle,
+    pub data_layout: MPSGraphTensorNamedDataLayout,
+    pub weights_layout: MPSGraphWeightsLayout,
+    pub groups: usize,
+}
+
+impl Default for Convolution2d {
+    fn default() -> Self {
+        Convolution2d {
+            strides: [1, 1],
+            dilations: [1, 1],
+            padding: [0; 4],
+            padding_style: MPSGraphPaddingStyle::Explicit,
+            data_layout: MPSGraphTensorNamedDataLayout::NCHW,
+            weights_layout: MPSGraphWeightsLayout::OIHW,
+            groups: 1,
+        }
+    }
+}
+
+/// Attributes of a 2D sliding window, used by pooling and im2col
+///
+/// Uses the same conventions as [`Convolution2d`]. Im2col only supports explicit padding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Window2d {
+    pub kernel: [usize; 2],
+    pub strides: [usize; 2],
+    pub dilations: [usize; 2],
+    pub padding: [usize; 4],
+    pub padding_style: MPSGraphPaddingStyle,
+    pub data_layout: MPSGraphTensorNamedDataLayout,
+    /// Rounds the output size up instead of down, as long as the last window starts
+    /// inside the padded input
+    pub ceil_mode: bool,
+}
+
+impl Window2d {
+    /// Creates a window with unit strides and dilations and no padding
+    pub fn new(kernel: [usize; 2]) -> Self {
+        Window2d {
+            kernel,
+            strides: [1, 1],
+            dilations: [1, 1],
+            padding: [0; 4],
+            padding_style: MPSGraphPaddingStyle::Explicit,
+            data_layout: MPSGraphTensorNamedDataLayout::NCHW,
+            ceil_mode: false,
+        }
+    }
+}
+
+/// The kind and attributes of a recorded operation
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpKind {
+    /// A graph input fed at run time
+    Placeholder {
+        shape: Shape,
+        data_type: MPSDataType,
+    },
+    /// A constant with raw element bytes in native byte order
+    Constant {
+        data: Vec<u8>,
+        shape: Shape,
+        data_type: MPSDataType,
+    },
+    /// A constant filled with one value
+    ConstantScalar {
+        value: f64,
+        shape: Shape,
+        data_type: MPSDataType,
+    },
+    Unary(UnaryOp),
+    Binary(BinaryOp),
+    /// Inputs: predicate, true value, false value
+    Select,
+    /// Inputs: tensor, minimum, maximum
+    Clamp,
+    Cast {
+        data_type: MPSDataType,
+    },
+    /// Matrix multiplication over the two innermost dimensions, broadcasting batch dimensions
+    MatMul,
+    Reduction {
+        op: ReductionOp,
+        axes: Vec<usize>,
+    },
+    Reshape {
+        shape: Vec<i64>,
+    },
+    Transpose {
+        permutation: Vec<usize>,
+    },
+    Broadcast {
+        shape: Vec<i64>,
+    },
+    Concat {
+        axis: i64,
+    },
+    Stack {
+        axis: i64,
+    },
+    Split {
+        num_splits: usize,
+        axis: i64,
+    },
+    Squeeze {
+        axes: Vec<i64>,
+    },
+    ExpandDims {
+        axes: Vec<i64>,
+    },
+    Tile {
+        multiples: Vec<i64>,
+    },
+    Reverse {
+        axes: Vec<i64>,
+    },
+    Flatten2d {
+        axis: i64,
+    },
+    Slice {
+        dimension: usize,
+        start: i64,
+        length: i64,
+    },
+    /// A strided slice with one entry per input dimension; the ellipsis is already expanded
+    StridedSlice {
+        starts: Vec<i64>,
+        ends: Vec<i64>,
+        strides: Vec<i64>,
+        masks: MPSGraphSliceMasks,
+    },
+    /// Inputs: params (source), indices
+    Gather {
+        axis: usize,
+        batch_dimensions: usize,
+    },
+    /// Inputs: params (source), indices
+    GatherAlongAxis {
+        axis: isize,
+    },
+    /// Inputs: updates, indices
+    Scatter {
+        shape: Shape,
+        axis: isize,
+        mode: MPSGraphScatterMode,
+    },
+    /// Inputs: updates, indices
+    ScatterAlongAxis {
+        shape: Shape,
+        axis: isize,
+        mode: MPSGraphScatterMode,
+    },
+    Softmax {
+        axis: i64,
+    },
+    /// Inputs: source logits, labels. Reduced axes are kept with size 1.
+    SoftmaxCrossEntropy {
+        axis: i64,
+        reduction: MPSGraphLossReductionType,
+    },
+    /// Inputs: incoming gradient of the loss, source logits, labels. The result is the
+    /// gradient with respect to the logits.
+    SoftmaxCrossEntropyGradient {
+        axis: i64,
+        reduction: MPSGraphLossReductionType,
+    },
+    /// Inputs: source, weights
+    Convolution2d(Convolution2d),
+    Pooling2d {
+        op: PoolingOp,
+        window: Window2d,
+    },
+    /// Extracts every window as a column; see [`Graph::im_to_col`] for the result layout
+    ImToCol(Window2d),
+    /// Resamples the spatial dimensions of a rank-4 tensor to `size` (`[height, width]`)
+    Resize {
+        size: [usize; 2],
+        mode: MPSGraphResizeMode,
+        center_result: bool,
+        align_corners: bool,
+        data_layout: MPSGraphTensorNamedDataLayout,
+    },
+    /// The `k` largest (or smallest) values along `axis` and their Int32 indices
+    TopK {
+        axis: usize,
+        k: usize,
+        largest: bool,
+    },
+    /// Inputs: boxes `[N, B, 4]`, scores `[N, B, K]`. Returns the Int32 indices of the
+    /// selected boxes for each batch.
+    NonMaximumSuppression {
+        iou_threshold: f32,
+        score_threshold: f32,
+        per_class_suppression: bool,
+        coordinate_mode: MPSGraphNonMaximumSuppressionCoordinateMode,
+    },
+}
+
+impl OpKind {
+    /// Returns a short, stable name for the operation kind
+    pub fn name(&self) -> &'static str {
+        match self {
+            OpKind::Placeholder { .. } => "placeholder",
+            OpKind::Constant { .. } => "constant",
+            OpKind::ConstantScalar { .. } => "constant_scalar",
+            OpKind::Unary(_) => "unary",
+            OpKind::Binary(_) => "binary",
+            OpKind::Select => "select",
+            OpKind::Clamp => "clamp",
+            OpKind::Cast { .. } => "cast",
+            OpKind::MatMul => "matmul",
+            OpKind::Reduction { .. } => "reduction",
+            OpKind::Reshape { .. } => "reshape",
+            OpKind::Transpose { .. } => "transpose",
+            OpKind::Broadcast { .. } => "broadcast",
+            OpKind::Concat { .. } => "concatenate",
+            OpKind::Stack { .. } => "stack",
+            OpKind::Split { .. } => "split",
+            OpKind::Squeeze { .. } => "squeeze",
+            OpKind::ExpandDims { .. } => "expand_dims",
+            OpKind::Tile { .. } => "tile",
+            OpKind::Reverse { .. } => "reverse",
+            OpKind::Flatten2d { .. } => "flatten2d",
+            OpKind::Slice { .. } => "slice",
+            OpKind::StridedSlice { .. } => "strided_slice",
+            OpKind::Gather { .. } => "gather",
+            OpKind::GatherAlongAxis { .. } => "gather_along_axis",
+            OpKind::Scatter { .. } => "scatter",
+            OpKind::ScatterAlongAxis { .. } => "scatter_along_axis",
+            OpKind::Softmax { .. } => "softmax",
+            OpKind::SoftmaxCrossEntropy { .. } => "softmax_cross_entropy",
+            OpKind::SoftmaxCrossEntropyGradient { .. } => "softmax_cross_entropy_gradient",
+            OpKind::Convolution2d(_) => "convolution_2d",
+            OpKind::Pooling2d { .. } => "pooling_2d",
+            OpKind::ImToCol(_) => "im_to_col",
+            OpKind::Resize { .. } => "resize",
+            OpKind::TopK { .. } => "top_k",
+            OpKind::NonMaximumSuppression { .. } => "non_maximum_suppression",
+        }
+    }
+}
+
+/// A recorded operation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    /// Kind and attributes
+    pub kind: OpKind,
+    /// Operand tensors, in the order of the corresponding `MPSGraph` method
+    pub inputs: Vec<TensorId>,
+    /// Result tensors
+    pub outputs: Vec<TensorId>,
+    /// Name passed to the builder
+    pub name: Option<String>,
+}
+
+/// A recorded graph of operations
+///
+/// Operations are stored in the order they were recorded, which is always a valid
+/// topological order since operands must exist before they are used.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Graph {
+    tensors: Vec<TensorInfo>,
+    operations: Vec<Operation>,
+}
+
+impl Graph {
+    /// Creates an empty graph
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns all recorded tensors, indexed by [`TensorId::index`]
+    pub fn tensors(&self) -> &[TensorInfo] {
+        &self.tensors
+    }
+
+    /// Returns all recorded operations in recording order
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    /// Returns the type information of a tensor
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was not recorded in this graph.
+    pub fn tensor(&self, id: TensorId) -> &TensorInfo {
+        &self.tensors[id.0]
+    }
+
+    /// Returns the type information of a tensor, or an error if `id` was not recorded in
+    /// this graph
+    pub fn try_tensor(&self, id: TensorId) -> Result<&TensorInfo, IrError> {
+        self.tensors.get(id.0).ok_or(IrError::UnknownTensor(id))
+    }
+
+    /// Returns the shape of a tensor
+    pub fn shape(&self, id: TensorId) -> &Shape {
+        &self.tensor(id).shape
+    }
+
+    /// Returns the data type of a tensor
+    pub fn data_type(&self, id: TensorId) -> MPSDataType {
+        self.tensor(id).data_type
+    }
+
+    /// Returns an operation
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was not recorded in this graph.
+    pub fn operation(&self, id: OpId) -> &Operation {
+        &self.operations[id.0]
+    }
+
+    /// Returns the operation that produces a tensor
+    pub fn producer(&self, id: TensorId) -> &Operation {
+        self.operation(self.tensor(id).producer)
+    }
+
+    /// Returns the placeholder tensors in recording order
+    pub fn placeholders(&self) -> Vec<TensorId> {
+        self.operations
+            .iter()
+            .filter(|op| matches!(op.kind, OpKind::Placeholder { .. }))
+            .flat_map(|op| op.outputs.iter().copied())
+            .collect()
+    }
+
+    /// Finds the first tensor produced by an operation with the given name
+    pub fn tensor_by_name(&self, name: &str) -> Option<TensorId> {
+        self.tensors
+            .iter()
+            .position(|tensor| tensor.name.as_deref() == Some(name))
+            .map(TensorId)
+    }
+
+    /// Records an operation after inferring the types of its results
+    ///
+    /// This is the primitive behind every builder method. It fails without modifying the
+    /// graph if an operand is unknown or the operands don't fit the operation.
+    pub fn try_add_operation(
+        &mut self,
+        kind: OpKind,
+        inputs: &[TensorId],
+        name: Option<&str>,
+    ) -> Result<Vec<TensorId>, IrError> {
+        let operands = inputs
+            .iter()
+            .map(|&id| self.try_tensor(id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let results = infer::infer_results(&kind, &operands)?;
+
+        let producer = OpId(self.operations.len());
+        let outputs = results
+            .into_iter()
+            .map(|(data_type, shape)| {
+                self.tensors.push(TensorInfo {
+                    data_type,
+                    shape,
+                    name: name.map(String::from),
+                    producer,
+                });
+                TensorId(self.tensors.len() - 1)
+            })
+            .collect::<Vec<_>>();
+
+        self.operations.push(Operation {
+            kind,
+            inputs: inputs.to_vec(),
+            outputs: outputs.clone(),
+            name: name.map(String::from),
+        });
+
+        Ok(outputs)
+    }
+
+    /// Records an operation, panicking with a descriptive message if it is invalid
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`Graph::try_add_operation`] fails.
+    pub fn add_operation(
+        &mut self,
+        kind: OpKind,
+        inputs: &[TensorId],
+        name: Option<&str>,
+    ) -> Vec<TensorId> {
+        match self.try_add_operation(kind, inputs, name) {
+            Ok(outputs) => outputs,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Records a single-result operation
+    fn try_record(
+        &mut self,
+        kind: OpKind,
+        inputs: &[TensorId],
+        name: Option<&str>,
+    ) -> Result<TensorId, IrError> {
+        Ok(self.try_add_operation(kind, inputs, name)?[0])
+    }
+}
@@ -0,0 +1,1058 @@
+//! Textual form of recorded graphs.
+//!
+//! Every operation is printed on its own line in a generic syntax modelled on MLIR:
+//!
+//! ```text
+//! %0 = placeholder() {name="x"} : f32[?,3]
+//! %1 = constant() {data=0x0000803f0000803f0000803f0000803f0000803f0000803f} : f32[3,2]
+//! %2 = matmul(%0, %1) {name="y"} : f32[?,2]
+//! %3, %4 = split(%2) {num_splits=2, axis=1} : f32[?,1], f32[?,1]
+//! ```
+//!
+//! Operations are named after [`OpKind::name`], except elementwise operations which use the
+//! name of their function (`relu`, `add`, ...). Every attribute of the kind is printed, in
+//! declaration order, followed by the optional `name`. Placeholders and constants take their
+//! shape and data type from the result type; for every other operation the result types are
+//! inferred again on parse and must match the annotation.
+//!
+//! [`Graph::parse`] reconstructs the same graph from the printed text. Result identifiers
+//! don't have to be contiguous in hand-written text, but each must be defined before use.
+//! Blank lines and lines starting with `//` are ignored.
+
+use super::{
+    BinaryOp, Convolution2d, Graph, IrError, OpKind, PoolingOp, ReductionOp, TensorId, UnaryOp,
+    Window2d,
+};
+use crate::convolution_ops::MPSGraphWeightsLayout;
+use crate::core::MPSDataType;
+use crate::dims::{Dim, Shape};
+use crate::loss_ops::MPSGraphLossReductionType;
+use crate::non_maximum_suppression_ops::MPSGraphNonMaximumSuppressionCoordinateMode;
+use crate::pooling_ops::{MPSGraphPaddingStyle, MPSGraphTensorNamedDataLayout};
+use crate::resize_ops::MPSGraphResizeMode;
+use crate::scatter_nd_ops::MPSGraphScatterMode;
+use crate::tensor_shape_ops::MPSGraphSliceMasks;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Write};
+use std::str::FromStr;
+
+const UNARY_OPS: [UnaryOp; 42] = [
+    UnaryOp::Identity,
+    UnaryOp::Exp,
+    UnaryOp::Exp2,
+    UnaryOp::Exp10,
+    UnaryOp::Log,
+    UnaryOp::Log2,
+    UnaryOp::Log10,
+    UnaryOp::Square,
+    UnaryOp::Sqrt,
+    UnaryOp::Rsqrt,
+    UnaryOp::Reciprocal,
+    UnaryOp::Abs,
+    UnaryOp::Negative,
+    UnaryOp::Sign,
+    UnaryOp::Ceil,
+    UnaryOp::Floor,
+    UnaryOp::Round,
+    UnaryOp::Rint,
+    UnaryOp::Truncate,
+    UnaryOp::Sin,
+    UnaryOp::Cos,
+    UnaryOp::Tan,
+    UnaryOp::Sinh,
+    UnaryOp::Cosh,
+    UnaryOp::Tanh,
+    UnaryOp::Asin,
+    UnaryOp::Acos,
+    UnaryOp::Atan,
+    UnaryOp::Asinh,
+    UnaryOp::Acosh,
+    UnaryOp::Atanh,
+    UnaryOp::Erf,
+    UnaryOp::LogicalNot,
+    UnaryOp::IsNan,
+    UnaryOp::IsInfinite,
+    UnaryOp::IsFinite,
+    UnaryOp::Relu,
+    UnaryOp::Sigmoid,
+    UnaryOp::AbsSquare,
+    UnaryOp::Signbit,
+    UnaryOp::BitwiseNot,
+    UnaryOp::BitwisePopulationCount,
+];
+
+const BINARY_OPS: [BinaryOp; 30] = [
+    BinaryOp::Add,
+    BinaryOp::Subtract,
+    BinaryOp::Multiply,
+    BinaryOp::Divide,
+    BinaryOp::DivisionNoNan,
+    BinaryOp::Modulo,
+    BinaryOp::FloorModulo,
+    BinaryOp::Power,
+    BinaryOp::Minimum,
+    BinaryOp::Maximum,
+    BinaryOp::MinimumWithNanPropagation,
+    BinaryOp::MaximumWithNanPropagation,
+    BinaryOp::Atan2,
+    BinaryOp::Equal,
+    BinaryOp::NotEqual,
+    BinaryOp::LessThan,
+    BinaryOp::LessThanOrEqualTo,
+    BinaryOp::GreaterThan,
+    BinaryOp::GreaterThanOrEqualTo,
+    BinaryOp::LogicalAnd,
+    BinaryOp::LogicalOr,
+    BinaryOp::LogicalXor,
+    BinaryOp::LogicalNand,
+    BinaryOp::LogicalNor,
+    BinaryOp::LogicalXnor,
+    BinaryOp::BitwiseAnd,
+    BinaryOp::BitwiseOr,
+    BinaryOp::BitwiseXor,
+    BinaryOp::LeftShift,
+    BinaryOp::RightShift,
+];
+
+const REDUCTION_OPS: [ReductionOp; 12] = [
+    ReductionOp::Sum,
+    ReductionOp::Mean,
+    ReductionOp::Maximum,
+    ReductionOp::Minimum,
+    ReductionOp::MaximumPropagateNan,
+    ReductionOp::MinimumPropagateNan,
+    ReductionOp::Product,
+    ReductionOp::And,
+    ReductionOp::Or,
+    ReductionOp::Xor,
+    ReductionOp::ArgMaximum,
+    ReductionOp::ArgMinimum,
+];
+
+const POOLING_OPS: [PoolingOp; 3] = [PoolingOp::Max, PoolingOp::Average, PoolingOp::L2Norm];
+
+const PADDING_STYLES: [MPSGraphPaddingStyle; 3] = [
+    MPSGraphPaddingStyle::Explicit,
+    MPSGraphPaddingStyle::TfValid,
+    MPSGraphPaddingStyle::TfSame,
+];
+
+const DATA_LAYOUTS: [MPSGraphTensorNamedDataLayout; 2] = [
+    MPSGraphTensorNamedDataLayout::NCHW,
+    MPSGraphTensorNamedDataLayout::NHWC,
+];
+
+const WEIGHTS_LAYOUTS: [MPSGraphWeightsLayout; 4] = [
+    MPSGraphWeightsLayout::OIHW,
+    MPSGraphWeightsLayout::HWIO,
+    MPSGraphWeightsLayout::OIDHW,
+    MPSGraphWeightsLayout::DHWIO,
+];
+
+const RESIZE_MODES: [MPSGraphResizeMode; 2] =
+    [MPSGraphResizeMode::Nearest, MPSGraphResizeMode::Bilinear];
+
+const SCATTER_MODES: [MPSGraphScatterMode; 7] = [
+    MPSGraphScatterMode::Add,
+    MPSGraphScatterMode::Sub,
+    MPSGraphScatterMode::Mul,
+    MPSGraphScatterMode::Div,
+    MPSGraphScatterMode::Min,
+    MPSGraphScatterMode::Max,
+    MPSGraphScatterMode::Set,
+];
+
+const LOSS_REDUCTIONS: [MPSGraphLossReductionType; 3] = [
+    MPSGraphLossReductionType::None,
+    MPSGraphLossReductionType::Sum,
+    MPSGraphLossReductionType::Mean,
+];
+
+const COORDINATE_MODES: [MPSGraphNonMaximumSuppressionCoordinateMode; 4] = [
+    MPSGraphNonMaximumSuppressionCoordinateMode::CornersHeightFirst,
+    MPSGraphNonMaximumSuppressionCoordinateMode::CornersWidthFirst,
+    MPSGraphNonMaximumSuppressionCoordinateMode::CentersHeightFirst,
+    MPSGraphNonMaximumSuppressionCoordinateMode::CentersWidthFirst,
+];
+
+/// Errors reported by [`Graph::parse`], with the 1-based line they occurred on
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The line doesn't follow the syntax
+    Syntax { line: usize, message: String },
+    /// An operand refers to a result that hasn't been defined
+    UndefinedValue { line: usize, value: String },
+    /// A result identifier is defined twice
+    Redefinition { line: usize, value: String },
+    /// The operation can't be recorded with these operands and attributes
+    Ir { line: usize, error: IrError },
+    /// The annotated result types differ from the inferred ones
+    TypeMismatch {
+        line: usize,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            ParseError::UndefinedValue { line, value } => {
+                write!(f, "line {}: {} is not defined", line, value)
+            }
+            ParseError::Redefinition { line, value } => {
+                write!(f, "line {}: {} is already defined", line, value)
+            }
+            ParseError::Ir { line, error } => write!(f, "line {}: {}", line, error),
+            ParseError::TypeMismatch {
+                line,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "line {}: annotated result type {} differs from inferred type {}",
+                line, expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Returns the short name of a data type, such as `f32` or `bf16`
+fn data_type_name(data_type: MPSDataType) -> &'static str {
+    match data_type {
+        MPSDataType::Float32 => "f32",
+        MPSDataType::Float16 => "f16",
+        MPSDataType::Float64 => "f64",
+        MPSDataType::BFloat16 => "bf16",
+        MPSDataType::Int4 => "i4",
+        MPSDataType::Int8 => "i8",
+        MPSDataType::Int16 => "i16",
+        MPSDataType::Int32 => "i32",
+        MPSDataType::Int64 => "i64",
+        MPSDataType::UInt4 => "u4",
+        MPSDataType::UInt8 => "u8",
+        MPSDataType::UInt16 => "u16",
+        MPSDataType::UInt32 => "u32",
+        MPSDataType::UInt64 => "u64",
+        MPSDataType::Bool => "bool",
+        MPSDataType::Complex16 => "cf16",
+        MPSDataType::Complex32 => "cf32",
+        MPSDataType::Complex64 => "cf64",
+        MPSDataType::Unorm1 => "unorm1",
+        MPSDataType::Unorm8 => "unorm8",
+        MPSDataType::Invalid => "invalid",
+    }
+}
+
+fn parse_data_type(name: &str) -> Option<MPSDataType> {
+    MPSDataType::ALL
+        .into_iter()
+        .find(|&data_type| data_type_name(data_type) == name)
+}
+
+/// Converts a `CamelCase` variant name to `snake_case`
+fn snake_case<T: Debug>(value: T) -> String {
+    let name = format!("{:?}", value);
+    let mut snake = String::with_capacity(name.len() + 4);
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        if c.is_ascii_uppercase()
+            && previous.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit())
+        {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+        previous = Some(c);
+    }
+    snake
+}
+
+fn op_name(kind: &OpKind) -> String {
+    match kind {
+        OpKind::Unary(op) => snake_case(op),
+        OpKind::Binary(op) => snake_case(op),
+        kind => kind.name().to_string(),
+    }
+}
+
+fn format_list<T: fmt::Display>(values: &[T]) -> String {
+    let items: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    format!("[{}]", items.join(","))
+}
+
+fn format_shape(shape: &Shape) -> String {
+    format_list(shape.dims())
+}
+
+fn format_type(data_type: MPSDataType, shape: &Shape) -> String {
+    format!("{}{}", data_type_name(data_type), format_shape(shape))
+}
+
+fn format_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn format_bytes(data: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 + 2 * data.len());
+    hex.push_str("0x");
+    for byte in data {
+        write!(hex, "{:02x}", byte).unwrap();
+    }
+    hex
+}
+
+/// Returns the attributes of an operation kind in declaration order
+fn attributes(kind: &OpKind) -> Vec<(&'static str, String)> {
+    fn window(window: &Window2d) -> Vec<(&'static str, String)> {
+        vec![
+            ("kernel", format_list(&window.kernel)),
+            ("strides", format_list(&window.strides)),
+            ("dilations", format_list(&window.dilations)),
+            ("padding", format_list(&window.padding)),
+            ("padding_style", snake_case(window.padding_style)),
+            ("data_layout", snake_case(window.data_layout)),
+            ("ceil_mode", window.ceil_mode.to_string()),
+        ]
+    }
+
+    match kind {
+        OpKind::Placeholder { .. }
+        | OpKind::Unary(_)
+        | OpKind::Binary(_)
+        | OpKind::Select
+        | OpKind::Clamp
+        | OpKind::MatMul => Vec::new(),
+        OpKind::Constant { data, .. } => vec![("data", format_bytes(data))],
+        OpKind::ConstantScalar { value, .. } => vec![("value", format!("{:?}", value))],
+        OpKind::Cast { data_type } => vec![("data_type", data_type_name(*data_type).into())],
+        OpKind::Reduction { op, axes } => {
+            vec![("op", snake_case(op)), ("axes", format_list(axes))]
+        }
+        OpKind::Reshape { shape } | OpKind::Broadcast { shape } => {
+            vec![("shape", format_list(shape))]
+        }
+        OpKind::Transpose { permutation } => vec![("permutation", format_list(permutation))],
+        OpKind::Concat { axis }
+        | OpKind::Stack { axis }
+        | OpKind::Flatten2d { axis }
+        | OpKind::Softmax { axis } => vec![("axis", axis.to_string())],
+        OpKind::Split { num_splits, axis } => vec![
+            ("num_splits", num_splits.to_string()),
+            ("axis", axis.to_string()),
+        ],
+        OpKind::Squeeze { axes } | OpKind::ExpandDims { axes } | OpKind::Reverse { axes } => {
+            vec![("axes", format_list(axes))]
+        }
+        OpKind::Tile { multiples } => vec![("multiples", format_list(multiples))],
+        OpKind::Slice {
+            dimension,
+            start,
+            length,
+        } => vec![
+            ("dimension", dimension.to_string()),
+            ("start", start.to_string()),
+            ("length", length.to_string()),
+        ],
+        OpKind::StridedSlice {
+            starts,
+            ends,
+            strides,
+            masks,
+        } => vec![
+            ("starts", format_list(starts)),
+            ("ends", format_list(ends)),
+            ("strides", format_list(strides)),
+            ("start_mask", masks.start_mask.to_string()),
+            ("end_mask", masks.end_mask.to_string()),
+            ("squeeze_mask", masks.squeeze_mask.to_string()),
+            ("ellipsis_mask", masks.ellipsis_mask.to_string()),
+        ],
+        OpKind::Gather {
+            axis,
+            batch_dimensions,
+        } => vec![
+            ("axis", axis.to_string()),
+            ("batch_dimensions", batch_dimensions.to_string()),
+        ],
+        OpKind::GatherAlongAxis { axis } => vec![("axis", axis.to_string())],
+        OpKind::Scatter { shape, axis, mode } | OpKind::ScatterAlongAxis { shape, axis, mode } => {
+            vec![
+                ("shape", format_shape(shape)),
+                ("axis", axis.to_string()),
+                ("mode", snake_case(mode)),
+            ]
+        }
+        OpKind::SoftmaxCrossEntropy { axis, reduction }
+        | OpKind::SoftmaxCrossEntropyGradient { axis, reduction } => vec![
+            ("axis", axis.to_string()),
+            ("reduction", snake_case(reduction)),
+        ],
+        OpKind::Convolution2d(convolution) => vec![
+            ("strides", format_list(&convolution.strides)),
+            ("dilations", format_list(&convolution.dilations)),
+            ("padding", format_list(&convolution.padding)),
+            ("padding_style", snake_case(convolution.padding_style)),
+            ("data_layout", snake_case(convolution.data_layout)),
+            ("weights_layout", snake_case(convolution.weights_layout)),
+            ("groups", convolution.groups.to_string()),
+        ],
+        OpKind::Pooling2d { op, window: w } => {
+            let mut attributes = vec![("op", snake_case(op))];
+            attributes.extend(window(w));
+            attributes
+        }
+        OpKind::ImToCol(w) => window(w),
+        OpKind::Resize {
+            size,
+            mode,
+            center_result,
+            align_corners,
+            data_layout,
+        } => vec![
+            ("size", format_list(size)),
+            ("mode", snake_case(mode)),
+            ("center_result", center_result.to_string()),
+            ("align_corners", align_corners.to_string()),
+            ("data_layout", snake_case(data_layout)),
+        ],
+        OpKind::TopK { axis, k, largest } => vec![
+            ("axis", axis.to_string()),
+            ("k", k.to_string()),
+            ("largest", largest.to_string()),
+        ],
+        OpKind::NonMaximumSuppression {
+            iou_threshold,
+            score_threshold,
+            per_class_suppression,
+            coordinate_mode,
+        } => vec![
+            ("iou_threshold", format!("{:?}", iou_threshold)),
+            ("score_threshold", format!("{:?}", score_threshold)),
+            ("per_class_suppression", per_class_suppression.to_string()),
+            ("coordinate_mode", snake_case(coordinate_mode)),
+        ],
+    }
+}
+
+impl fmt::Display for Graph {
+    /// Prints one line per operation in the form read by [`Graph::parse`]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for op in &self.operations {
+            let results: Vec<String> = op.outputs.iter().map(|id| id.to_string()).collect();
+            let operands: Vec<String> = op.inputs.iter().map(|id| id.to_string()).collect();
+            write!(
+                f,
+                "{} = {}({})",
+                results.join(", "),
+                op_name(&op.kind),
+                operands.join(", ")
+            )?;
+
+            let mut attributes: Vec<String> = attributes(&op.kind)
+                .into_iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            if let Some(name) = &op.name {
+                attributes.push(format!("name={}", format_string(name)));
+            }
+            if !attributes.is_empty() {
+                write!(f, " {{{}}}", attributes.join(", "))?;
+            }
+
+            let types: Vec<String> = op
+                .outputs
+                .iter()
+                .map(|&id| format_type(self.data_type(id), self.shape(id)))
+                .collect();
+            writeln!(f, " : {}", types.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// `%` followed by an identifier
+    Value(String),
+    /// A run of letters, digits and `_ . + -`: identifiers, numbers and hex data
+    Word(String),
+    String(String),
+    Punct(char),
+}
+
+fn tokenize(line: &str) -> std::result::Result<Vec<Token>, String> {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '+' | '-');
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '%' => {
+                let mut name = String::from('%');
+                while let Some(&c) = chars.peek().filter(|&&c| is_word(c)) {
+                    name.push(c);
+                    chars.next();
+                }
+                if name.len() == 1 {
+                    return Err("expected a value name after '%'".into());
+                }
+                tokens.push(Token::Value(name));
+            }
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => text.push('\n'),
+                            Some(c @ ('"' | '\\')) => text.push(c),
+                            _ => return Err("invalid escape in string".into()),
+                        },
+                        Some(c) => text.push(c),
+                        None => return Err("unterminated string".into()),
+                    }
+                }
+                tokens.push(Token::String(text));
+            }
+            '=' | '(' | ')' | '{' | '}' | '[' | ']' | ',' | ':' | '?' => {
+                tokens.push(Token::Punct(c))
+            }
+            c if is_word(c) => {
+                let mut word = String::from(c);
+                while let Some(&c) = chars.peek().filter(|&&c| is_word(c)) {
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+            c => return Err(format!("unexpected character '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// An attribute value before it is converted to the type the operation expects
+#[derive(Debug, Clone, PartialEq)]
+enum Attribute {
+    Word(String),
+    String(String),
+    Dynamic,
+    List(Vec<Attribute>),
+}
+
+type Result<T> = std::result::Result<T, String>;
+
+/// Cursor over the tokens of one line
+struct Line {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Line {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| "unexpected end of line".to_string())?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, punct: char) -> bool {
+        let found = self.peek() == Some(&Token::Punct(punct));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, punct: char) -> Result<()> {
+        match self.next()? {
+            Token::Punct(c) if c == punct => Ok(()),
+            token => Err(format!("expected '{}', found {:?}", punct, token)),
+        }
+    }
+
+    fn word(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Word(word) => Ok(word),
+            token => Err(format!("expected a word, found {:?}", token)),
+        }
+    }
+
+    fn value(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Value(name) => Ok(name),
+            token => Err(format!("expected a %value, found {:?}", token)),
+        }
+    }
+
+    /// Parses a comma-separated list until `close`, after the opening delimiter
+    fn list<T>(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        if self.eat(close) {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.eat(close) {
+                return Ok(items);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn attribute(&mut self) -> Result<Attribute> {
+        match self.next()? {
+            Token::Word(word) => Ok(Attribute::Word(word)),
+            Token::String(text) => Ok(Attribute::String(text)),
+            Token::Punct('?') => Ok(Attribute::Dynamic),
+            Token::Punct('[') => Ok(Attribute::List(self.list(']', Self::attribute)?)),
+            token => Err(format!("expected an attribute value, found {:?}", token)),
+        }
+    }
+
+    fn result_type(&mut self) -> Result<(MPSDataType, Shape)> {
+        let name = self.word()?;
+        let data_type =
+            parse_data_type(&name).ok_or_else(|| format!("unknown data type '{}'", name))?;
+        self.expect('[')?;
+        let dims = self.list(']', |line| match line.next()? {
+            Token::Punct('?') => Ok(Dim::Dynamic),
+            Token::Word(word) => word
+                .parse()
+                .map(Dim::Static)
+                .map_err(|_| format!("invalid dimension '{}'", word)),
+            token => Err(format!("expected a dimension, found {:?}", token)),
+        })?;
+        Ok((data_type, Shape::new(dims)))
+    }
+}
+
+/// Attributes of one operation, removed as they are converted
+struct Attributes(Vec<(String, Attribute)>);
+
+impl Attributes {
+    fn take(&mut self, key: &str) -> Result<Attribute> {
+        let index = self
+            .0
+            .iter()
+            .position(|(name, _)| name == key)
+            .ok_or_else(|| format!("missing attribute '{}'", key))?;
+        Ok(self.0.remove(index).1)
+    }
+
+    fn parse<T: FromStr>(key: &str, attribute: &Attribute) -> Result<T> {
+        match attribute {
+            Attribute::Word(word) => word
+                .parse()
+                .map_err(|_| format!("invalid value '{}' for '{}'", word, key)),
+            other => Err(format!("invalid value {:?} for '{}'", other, key)),
+        }
+    }
+
+    fn scalar<T: FromStr>(&mut self, key: &str) -> Result<T> {
+        let attribute = self.take(key)?;
+        Self::parse(key, &attribute)
+    }
+
+    fn list<T: FromStr>(&mut self, key: &str) -> Result<Vec<T>> {
+        match self.take(key)? {
+            Attribute::List(items) => items.iter().map(|item| Self::parse(key, item)).collect(),
+            other => Err(format!("expected a list for '{}', found {:?}", key, other)),
+        }
+    }
+
+    fn array<T: FromStr, const N: usize>(&mut self, key: &str) -> Result<[T; N]> {
+        let values = self.list(key)?;
+        let count = values.len();
+        values
+            .try_into()
+            .map_err(|_| format!("'{}' needs {} values, found {}", key, N, count))
+    }
+
+    fn shape(&mut self, key: &str) -> Result<Shape> {
+        match self.take(key)? {
+            Attribute::List(items) => items
+                .iter()
+                .map(|item| match item {
+                    Attribute::Dynamic => Ok(Dim::Dynamic),
+                    item => Self::parse(key, item).map(Dim::Static),
+                })
+                .collect::<Result<Vec<_>>>()
+                .map(Shape::new),
+            other => Err(format!("expected a shape for '{}', found {:?}", key, other)),
+        }
+    }
+
+    fn keyword<T: Debug + Copy>(&mut self, key: &str, all: &[T]) -> Result<T> {
+        let word: String = self.scalar(key)?;
+        all.iter()
+            .copied()
+            .find(|&value| snake_case(value) == word)
+            .ok_or_else(|| format!("invalid value '{}' for '{}'", word, key))
+    }
+
+    fn data_type(&mut self, key: &str) -> Result<MPSDataType> {
+        let word: String = self.scalar(key)?;
+        parse_data_type(&word).ok_or_else(|| format!("unknown data type '{}'", word))
+    }
+
+    fn bytes(&mut self, key: &str) -> Result<Vec<u8>> {
+        let word: String = self.scalar(key)?;
+        let hex = word
+            .strip_prefix("0x")
+            .filter(|hex| hex.len() % 2 == 0)
+            .ok_or_else(|| format!("'{}' must be 0x followed by pairs of hex digits", key))?;
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                u8::from_str_radix(&hex[i..i + 2], 16)
+                    .map_err(|_| format!("invalid hex digits in '{}'", key))
+            })
+            .collect()
+    }
+
+    fn window(&mut self) -> Result<Window2d> {
+        Ok(Window2d {
+            kernel: self.array("kernel")?,
+            strides: self.array("strides")?,
+            dilations: self.array("dilations")?,
+            padding: self.array("padding")?,
+            padding_style: self.keyword("padding_style", &PADDING_STYLES)?,
+            data_layout: self.keyword("data_layout", &DATA_LAYOUTS)?,
+            ceil_mode: self.scalar("ceil_mode")?,
+        })
+    }
+}
+
+/// Builds the operation kind named `op` from its attributes and annotated result type
+fn op_kind(
+    op: &str,
+    attributes: &mut Attributes,
+    results: &[(MPSDataType, Shape)],
+) -> Result<OpKind> {
+    if let Some(&unary) = UNARY_OPS.iter().find(|&&unary| snake_case(unary) == op) {
+        return Ok(OpKind::Unary(unary));
+    }
+    if let Some(&binary) = BINARY_OPS.iter().find(|&&binary| snake_case(binary) == op) {
+        return Ok(OpKind::Binary(binary));
+    }
+    let result_type = || match results {
+        [(data_type, shape)] => Ok((*data_type, shape.clone())),
+        _ => Err(format!("{} has exactly one result", op)),
+    };
+
+    let a = attributes;
+    Ok(match op {
+        "placeholder" => {
+            let (data_type, shape) = result_type()?;
+            OpKind::Placeholder { shape, data_type }
+        }
+        "constant" => {
+            let (data_type, shape) = result_type()?;
+            OpKind::Constant {
+                data: a.bytes("data")?,
+                shape,
+                data_type,
+            }
+        }
+        "constant_scalar" => {
+            let (data_type, shape) = result_type()?;
+            OpKind::ConstantScalar {
+                value: a.scalar("value")?,
+                shape,
+                data_type,
+            }
+        }
+        "select" => OpKind::Select,
+        "clamp" => OpKind::Clamp,
+        "cast" => OpKind::Cast {
+            data_type: a.data_type("data_type")?,
+        },
+        "matmul" => OpKind::MatMul,
+        "reduction" => OpKind::Reduction {
+            op: a.keyword("op", &REDUCTION_OPS)?,
+            axes: a.list("axes")?,
+        },
+        "reshape" => OpKind::Reshape {
+            shape: a.list("shape")?,
+        },
+        "transpose" => OpKind::Transpose {
+            permutation: a.list("permutation")?,
+        },
+        "broadcast" => OpKind::Broadcast {
+            shape: a.list("shape")?,
+        },
+        "concatenate" => OpKind::Concat {
+            axis: a.scalar("axis")?,
+        },
+        "stack" => OpKind::Stack {
+            axis: a.scalar("axis")?,
+        },
+        "split" => OpKind::Split {
+            num_splits: a.scalar("num_splits")?,
+            axis: a.scalar("axis")?,
+        },
+        "squeeze" => OpKind::Squeeze {
+            axes: a.list("axes")?,
+        },
+        "expand_dims" => OpKind::ExpandDims {
+            axes: a.list("axes")?,
+        },
+        "tile" => OpKind::Tile {
+            multiples: a.list("multiples")?,
+        },
+        "reverse" => OpKind::Reverse {
+            axes: a.list("axes")?,
+        },
+        "flatten_2d" => OpKind::Flatten2d {
+            axis: a.scalar("axis")?,
+        },
+        "slice" => OpKind::Slice {
+            dimension: a.scalar("dimension")?,
+            start: a.scalar("start")?,
+            length: a.scalar("length")?,
+        },
+        "strided_slice" => OpKind::StridedSlice {
+            starts: a.list("starts")?,
+            ends: a.list("ends")?,
+            strides: a.list("strides")?,
+            masks: MPSGraphSliceMasks {
+                start_mask: a.scalar("start_mask")?,
+                end_mask: a.scalar("end_mask")?,
+                squeeze_mask: a.scalar("squeeze_mask")?,
+                ellipsis_mask: a.scalar("ellipsis_mask")?,
+            },
+        },
+        "gather" => OpKind::Gather {
+            axis: a.scalar("axis")?,
+            batch_dimensions: a.scalar("batch_dimensions")?,
+        },
+        "gather_along_axis" => OpKind::GatherAlongAxis {
+            axis: a.scalar("axis")?,
+        },
+        "scatter" => OpKind::Scatter {
+            shape: a.shape("shape")?,
+            axis: a.scalar("axis")?,
+            mode: a.keyword("mode", &SCATTER_MODES)?,
+        },
+        "scatter_along_axis" => OpKind::ScatterAlongAxis {
+            shape: a.shape("shape")?,
+            axis: a.scalar("axis")?,
+            mode: a.keyword("mode", &SCATTER_MODES)?,
+        },
+        "softmax" => OpKind::Softmax {
+            axis: a.scalar("axis")?,
+        },
+        "softmax_cross_entropy" => OpKind::SoftmaxCrossEntropy {
+            axis: a.scalar("axis")?,
+            reduction: a.keyword("reduction", &LOSS_REDUCTIONS)?,
+        },
+        "softmax_cross_entropy_gradient" => OpKind::SoftmaxCrossEntropyGradient {
+            axis: a.scalar("axis")?,
+            reduction: a.keyword("reduction", &LOSS_REDUCTIONS)?,
+        },
+        "convolution_2d" => OpKind::Convolution2d(Convolution2d {
+            strides: a.array("strides")?,
+            dilations: a.array("dilations")?,
+            padding: a.array("padding")?,
+            padding_style: a.keyword("padding_style", &PADDING_STYLES)?,
+            data_layout: a.keyword("data_layout", &DATA_LAYOUTS)?,
+            weights_layout: a.keyword("weights_layout", &WEIGHTS_LAYOUTS)?,
+            groups: a.scalar("groups")?,
+        }),
+        "pooling_2d" => OpKind::Pooling2d {
+            op: a.keyword("op", &POOLING_OPS)?,
+            window: a.window()?,
+        },
+        "im_to_col" => OpKind::ImToCol(a.window()?),
+        "resize" => OpKind::Resize {
+            size: a.array("size")?,
+            mode: a.keyword("mode", &RESIZE_MODES)?,
+            center_result: a.scalar("center_result")?,
+            align_corners: a.scalar("align_corners")?,
+            data_layout: a.keyword("data_layout", &DATA_LAYOUTS)?,
+        },
+        "top_k" => OpKind::TopK {
+            axis: a.scalar("axis")?,
+            k: a.scalar("k")?,
+            largest: a.scalar("largest")?,
+        },
+        "non_maximum_suppression" => OpKind::NonMaximumSuppression {
+            iou_threshold: a.scalar("iou_threshold")?,
+            score_threshold: a.scalar("score_threshold")?,
+            per_class_suppression: a.scalar("per_class_suppression")?,
+            coordinate_mode: a.keyword("coordinate_mode", &COORDINATE_MODES)?,
+        },
+        op => return Err(format!("unknown operation '{}'", op)),
+    })
+}
+
+/// A statement of the text form, before it is recorded
+struct Statement {
+    results: Vec<String>,
+    op: String,
+    operands: Vec<String>,
+    attributes: Attributes,
+    name: Option<String>,
+    types: Vec<(MPSDataType, Shape)>,
+}
+
+fn parse_statement(text: &str) -> Result<Statement> {
+    let mut line = Line {
+        tokens: tokenize(text)?,
+        position: 0,
+    };
+    let mut results = vec![line.value()?];
+    while line.eat(',') {
+        results.push(line.value()?);
+    }
+    line.expect('=')?;
+    let op = line.word()?;
+    line.expect('(')?;
+    let operands = line.list(')', Line::value)?;
+
+    let mut attributes = Vec::new();
+    if line.eat('{') {
+        attributes = line.list('}', |line| {
+            let key = line.word()?;
+            line.expect('=')?;
+            Ok((key, line.attribute()?))
+        })?;
+    }
+    let mut attributes = Attributes(attributes);
+    let name = match attributes.take("name") {
+        Ok(Attribute::String(name)) => Some(name),
+        Ok(other) => return Err(format!("name must be a string, found {:?}", other)),
+        Err(_) => None,
+    };
+
+    line.expect(':')?;
+    let mut types = vec![line.result_type()?];
+    while line.eat(',') {
+        types.push(line.result_type()?);
+    }
+    if let Some(token) = line.peek() {
+        return Err(format!("unexpected {:?} after the result types", token));
+    }
+    if types.len() != results.len() {
+        return Err(format!(
+            "{} results but {} result types",
+            results.len(),
+            types.len()
+        ));
+    }
+    Ok(Statement {
+        results,
+        op,
+        operands,
+        attributes,
+        name,
+        types,
+    })
+}
+
+impl Graph {
+    /// Parses a graph from the textual form printed by its `Display` implementation
+    ///
+    /// Parsing the printed form of a graph reconstructs an equal graph.
+    pub fn parse(text: &str) -> std::result::Result<Graph, ParseError> {
+        let mut graph = Graph::new();
+        let mut values: HashMap<String, TensorId> = HashMap::new();
+
+        for (index, text) in text.lines().enumerate() {
+            let line = index + 1;
+            let text = text.trim();
+            if text.is_empty() || text.starts_with("//") {
+                continue;
+            }
+            let syntax = |message: String| ParseError::Syntax { line, message };
+
+            let mut statement = parse_statement(text).map_err(syntax)?;
+            for (i, value) in statement.results.iter().enumerate() {
+                if values.contains_key(value) || statement.results[..i].contains(value) {
+                    return Err(ParseError::Redefinition {
+                        line,
+                        value: value.clone(),
+                    });
+                }
+            }
+            let operands = statement
+                .operands
+                .iter()
+                .map(|value| {
+                    values
+                        .get(value)
+                        .copied()
+                        .ok_or_else(|| ParseError::UndefinedValue {
+                            line,
+                            value: value.clone(),
+                        })
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let kind = op_kind(&statement.op, &mut statement.attributes, &statement.types)
+                .map_err(syntax)?;
+            if let Some((key, _)) = statement.attributes.0.first() {
+                return Err(syntax(format!(
+                    "unknown attribute '{}' for {}",
+                    key, statement.op
+                )));
+            }
+
+            let outputs = graph
+                .try_add_operation(kind, &operands, statement.name.as_deref())
+                .map_err(|error| ParseError::Ir { line, error })?;
+            let inferred: Vec<String> = outputs
+                .iter()
+                .map(|&id| format_type(graph.data_type(id), graph.shape(id)))
+                .collect();
+            let annotated: Vec<String> = statement
+                .types
+                .iter()
+                .map(|(data_type, shape)| format_type(*data_type, shape))
+                .collect();
+            if inferred != annotated {
+                return Err(ParseError::TypeMismatch {
+                    line,
+                    expected: annotated.join(", "),
+                    actual: inferred.join(", "),
+                });
+            }
+
+            values.extend(statement.results.into_iter().zip(outputs));
+        }
+        Ok(graph)
+    }
+}
+
+impl FromStr for Graph {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> std::result::Result<Self, Self::Err> {
+        Graph::parse(text)
+    }
+}
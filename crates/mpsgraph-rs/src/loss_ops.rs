@@ -1,6 +1,10 @@
+#[cfg(target_vendor = "apple")]
 use crate::core::{AsRawObject, NSString};
+#[cfg(target_vendor = "apple")]
 use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
 use crate::tensor::MPSGraphTensor;
+#[cfg(target_vendor = "apple")]
 use objc2::runtime::AnyObject;
 
 /// The type of reduction applied in loss operations
@@ -20,6 +24,7 @@ pub enum MPSGraphLossReductionType {
 pub const Axis: MPSGraphLossReductionType = MPSGraphLossReductionType::None;
 
 /// Loss operations for MPSGraph
+#[cfg(target_vendor = "apple")]
 impl MPSGraph {
     /// Creates a softmax cross-entropy loss operation and returns the result tensor.
     ///
@@ -81,7 +86,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -147,7 +152,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 }
@@ -29,7 +29,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -58,7 +58,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -90,7 +90,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -134,7 +134,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -165,7 +165,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -192,7 +192,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -226,7 +226,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphOperation(result)
+            MPSGraphOperation::from_retained(result)
         }
     }
 }
@@ -0,0 +1,157 @@
+//! Execution by tensor name.
+//!
+//! [`MPSGraph::run_named`] and [`MPSGraphExecutable::run_named`] take feeds keyed by
+//! placeholder name and return [`DynHostTensor`] results keyed by target name, so callers
+//! don't have to keep tensor handles around. A tensor's name is the one its operation was
+//! created with, e.g. the `name` passed to [`MPSGraph::placeholder`]. Names that match
+//! nothing are reported as [`MPSGraphError::UnknownTensorName`]; names that match several
+//! tensors, or targets requested twice, as [`MPSGraphError::DuplicateTensorName`].
+
+use crate::error::{MPSGraphError, Result};
+use crate::executable::{tensors_from_array, MPSGraphExecutable};
+use crate::graph::{checked_results, operation_name, tensor_names, MPSGraph};
+use crate::host::DynHostTensor;
+use crate::tensor::MPSGraphTensor;
+use crate::tensor_data::MPSGraphTensorData;
+use objc2::msg_send;
+use objc2::runtime::AnyObject;
+use std::collections::{HashMap, HashSet};
+
+/// Returns whether `tensor`'s operation was created with `name`
+fn has_name(tensor: &MPSGraphTensor, name: &str) -> bool {
+    !tensor.0.is_null() && unsafe { operation_name(tensor.0) }.as_deref() == Some(name)
+}
+
+/// Picks the only candidate answering to `name`
+fn unique(name: &str, mut candidates: Vec<MPSGraphTensor>) -> Result<MPSGraphTensor> {
+    match candidates.len() {
+        0 => Err(MPSGraphError::UnknownTensorName(name.to_string())),
+        1 => Ok(candidates.remove(0)),
+        _ => Err(MPSGraphError::DuplicateTensorName(name.to_string())),
+    }
+}
+
+/// Resolves the feed names in a stable order, so the first error reported doesn't depend on
+/// hash map iteration
+fn resolve_feeds(
+    feeds: &HashMap<&str, DynHostTensor>,
+    candidates: &[MPSGraphTensor],
+) -> Result<HashMap<MPSGraphTensor, MPSGraphTensorData>> {
+    let mut names: Vec<&str> = feeds.keys().copied().collect();
+    names.sort_unstable();
+    let mut resolved = HashMap::with_capacity(names.len());
+    for name in names {
+        let matching = candidates
+            .iter()
+            .filter(|tensor| has_name(tensor, name))
+            .cloned()
+            .collect();
+        resolved.insert(unique(name, matching)?, feeds[name].to_tensor_data());
+    }
+    Ok(resolved)
+}
+
+fn resolve_targets(
+    targets: &[&str],
+    mut resolve: impl FnMut(&str) -> Result<MPSGraphTensor>,
+) -> Result<Vec<MPSGraphTensor>> {
+    let mut seen = HashSet::with_capacity(targets.len());
+    targets
+        .iter()
+        .map(|&name| {
+            if !seen.insert(name) {
+                return Err(MPSGraphError::DuplicateTensorName(name.to_string()));
+            }
+            resolve(name)
+        })
+        .collect()
+}
+
+/// Copies the results back to the host, keyed by the requested names
+fn host_results(
+    targets: &[&str],
+    tensors: &[MPSGraphTensor],
+    results: &HashMap<MPSGraphTensor, MPSGraphTensorData>,
+) -> Result<HashMap<String, DynHostTensor>> {
+    targets
+        .iter()
+        .zip(tensors)
+        .map(|(&name, tensor)| {
+            let data = results.get(tensor).ok_or_else(|| {
+                MPSGraphError::ExecutionFailed(format!("no result was produced for '{}'", name))
+            })?;
+            Ok((name.to_string(), DynHostTensor::from_tensor_data(data)?))
+        })
+        .collect()
+}
+
+impl MPSGraph {
+    /// Returns the placeholders of the graph, in creation order
+    pub fn placeholder_tensors(&self) -> Vec<MPSGraphTensor> {
+        unsafe {
+            let tensors: *mut AnyObject = msg_send![self.0, placeholderTensors];
+            tensors_from_array(tensors)
+        }
+    }
+
+    /// Returns the tensor whose operation was created with `name`
+    ///
+    /// Every output of a multi-output operation shares the operation's name, so those
+    /// can't be looked up this way.
+    pub fn tensor_named(&self, name: &str) -> Result<MPSGraphTensor> {
+        let tensors = unsafe { tensor_names::lookup(self.0, name) }
+            .into_iter()
+            .map(|tensor| unsafe {
+                MPSGraphTensor::from_retained(objc2::ffi::objc_retain(tensor as *mut _))
+            })
+            .collect();
+        unique(name, tensors)
+    }
+
+    /// Runs the graph with feeds and targets given by name
+    ///
+    /// Feed names are matched against the graph's placeholders and target names against
+    /// every named tensor. Feeds are checked with [`MPSGraph::validate_feeds`] before
+    /// anything runs.
+    pub fn run_named(
+        &self,
+        feeds: &HashMap<&str, DynHostTensor>,
+        targets: &[&str],
+    ) -> Result<HashMap<String, DynHostTensor>> {
+        let feeds = resolve_feeds(feeds, &self.placeholder_tensors())?;
+        let target_tensors = resolve_targets(targets, |name| self.tensor_named(name))?;
+        self.validate_feeds(&feeds, &target_tensors)?;
+        let results = checked_results(
+            self.run_with_feeds(&feeds, &target_tensors),
+            &target_tensors,
+        )?;
+        host_results(targets, &target_tensors, &results)
+    }
+}
+
+impl MPSGraphExecutable {
+    /// Runs the executable with feeds and targets given by name
+    ///
+    /// Feed names are matched against the executable's [`feed_tensors`](Self::feed_tensors),
+    /// all of which must be fed, and target names against its
+    /// [`target_tensors`](Self::target_tensors). Feeds are checked with
+    /// [`validate_feeds`](Self::validate_feeds) before anything runs.
+    pub fn run_named(
+        &self,
+        feeds: &HashMap<&str, DynHostTensor>,
+        targets: &[&str],
+    ) -> Result<HashMap<String, DynHostTensor>> {
+        let feeds = resolve_feeds(feeds, &self.feed_tensors())?;
+        let outputs = self.target_tensors();
+        let target_tensors = resolve_targets(targets, |name| {
+            let matching = outputs
+                .iter()
+                .filter(|tensor| has_name(tensor, name))
+                .cloned()
+                .collect();
+            unique(name, matching)
+        })?;
+        let results = self.try_run_with_feeds(&feeds, &target_tensors)?;
+        host_results(targets, &target_tensors, &results)
+    }
+}
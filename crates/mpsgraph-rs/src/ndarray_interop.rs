@@ -0,0 +1,101 @@
+//! Conversions between [`ndarray`] arrays and host tensors, tensor data and constants.
+//!
+//! Enabled by the `ndarray` feature. Arrays of any dimensionality and storage convert; those
+//! that aren't in standard (C) order, such as transposed or negatively strided views, are
+//! copied into standard order on the way in. Element types are those implementing
+//! [`HostElement`].
+
+use crate::host::{HostElement, HostTensor};
+use ndarray::{ArrayBase, ArrayD, Data, Dimension, IxDyn};
+
+#[cfg(target_vendor = "apple")]
+use crate::error::Result;
+#[cfg(target_vendor = "apple")]
+use crate::graph::{MPSGraph, MPSTensorDataScalar};
+#[cfg(target_vendor = "apple")]
+use crate::shape::MPSShape;
+#[cfg(target_vendor = "apple")]
+use crate::tensor::MPSGraphTensor;
+#[cfg(target_vendor = "apple")]
+use crate::tensor_data::MPSGraphTensorData;
+
+/// Copies the elements of `array` in standard order
+fn standard_order<T: Clone, S: Data<Elem = T>, D: Dimension>(array: &ArrayBase<S, D>) -> Vec<T> {
+    match array.as_slice() {
+        Some(elements) => elements.to_vec(),
+        None => array.iter().cloned().collect(),
+    }
+}
+
+/// Takes the elements of an owned array in standard order, reusing its buffer when the
+/// layout allows
+fn into_standard_order<T: Clone, D: Dimension>(array: ndarray::Array<T, D>) -> Vec<T> {
+    if !array.is_standard_layout() {
+        return array.iter().cloned().collect();
+    }
+    let len = array.len();
+    let (mut data, offset) = array.into_raw_vec_and_offset();
+    let offset = offset.unwrap_or(0);
+    data.truncate(offset + len);
+    data.drain(..offset);
+    data
+}
+
+impl<T: HostElement> HostTensor<T> {
+    /// Copies an array or view into a C-order host tensor
+    pub fn from_ndarray<S: Data<Elem = T>, D: Dimension>(array: &ArrayBase<S, D>) -> Self {
+        HostTensor::new(array.shape(), standard_order(array))
+            .expect("an array's shape matches its element count")
+    }
+
+    /// Copies the tensor into an array in standard order
+    pub fn to_ndarray(&self) -> ArrayD<T> {
+        ArrayD::from_shape_vec(IxDyn(self.shape()), self.to_vec())
+            .expect("a host tensor's shape matches its element count")
+    }
+}
+
+impl<T: HostElement, D: Dimension> From<ndarray::Array<T, D>> for HostTensor<T> {
+    fn from(array: ndarray::Array<T, D>) -> Self {
+        let shape = array.shape().to_vec();
+        HostTensor::new(&shape, into_standard_order(array))
+            .expect("an array's shape matches its element count")
+    }
+}
+
+impl<T: HostElement> From<HostTensor<T>> for ArrayD<T> {
+    fn from(tensor: HostTensor<T>) -> Self {
+        let shape = tensor.shape().to_vec();
+        ArrayD::from_shape_vec(IxDyn(&shape), tensor.into_vec())
+            .expect("a host tensor's shape matches its element count")
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl MPSGraphTensorData {
+    /// Copies an array or view into new tensor data, in standard order
+    pub fn from_ndarray<T: HostElement, S: Data<Elem = T>, D: Dimension>(
+        array: &ArrayBase<S, D>,
+    ) -> Self {
+        MPSGraphTensorData::new(&standard_order(array), array.shape(), T::DATA_TYPE)
+    }
+
+    /// Copies the data into an array of `T`, which must match [`data_type`](Self::data_type)
+    pub fn to_ndarray<T: HostElement>(&self) -> Result<ArrayD<T>> {
+        self.to_host::<T>().map(ArrayD::from)
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl MPSGraph {
+    /// Creates a constant tensor holding the elements of an array or view
+    pub fn constant_from_ndarray<T, S, D>(&self, array: &ArrayBase<S, D>) -> MPSGraphTensor
+    where
+        T: HostElement + MPSTensorDataScalar,
+        S: Data<Elem = T>,
+        D: Dimension,
+    {
+        let shape = MPSShape::from_slice(array.shape());
+        self.constant(&standard_order(array), &shape, T::DATA_TYPE)
+    }
+}
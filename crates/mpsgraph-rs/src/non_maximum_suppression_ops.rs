@@ -1,18 +1,26 @@
-use objc2::runtime::AnyObject;
-// In objc2, use false as NO and true as YES
-const NO: bool = false;
-const YES: bool = true;
+#[cfg(target_vendor = "apple")]
 use crate::core::{AsRawObject, NSString};
+#[cfg(target_vendor = "apple")]
 use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
 use crate::tensor::MPSGraphTensor;
+#[cfg(target_vendor = "apple")]
 use objc2::msg_send;
+#[cfg(target_vendor = "apple")]
+use objc2::runtime::AnyObject;
+
+// In objc2, use false as NO and true as YES
+#[cfg(target_vendor = "apple")]
+const NO: bool = false;
+#[cfg(target_vendor = "apple")]
+const YES: bool = true;
 
 /// The non-maximum suppression coordinate mode.
 ///
 /// This mode specifies the representation used for the 4 box coordinate values.
 /// Center coordinate modes define a centered box and the box dimensions.
 #[repr(u64)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MPSGraphNonMaximumSuppressionCoordinateMode {
     /// [h_start, w_start, h_end, w_end]
     CornersHeightFirst = 0,
@@ -25,6 +33,7 @@ pub enum MPSGraphNonMaximumSuppressionCoordinateMode {
 }
 
 /// Non-maximum suppression operations for MPSGraph
+#[cfg(target_vendor = "apple")]
 impl MPSGraph {
     /// Creates a nonMaximumumSuppression operation and returns the result tensor.
     ///
@@ -73,7 +82,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -128,7 +137,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 }
@@ -35,7 +35,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 }
@@ -39,7 +39,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -78,7 +78,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -114,7 +114,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -169,7 +169,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -217,7 +217,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -256,7 +256,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -328,7 +328,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 }
@@ -0,0 +1,606 @@
+//! NumPy `.npy` and `.npz` import and export.
+//!
+//! [`NpyArray`] holds a host buffer in C order together with its shape and
+//! [`MPSDataType`]. It reads and writes the `.npy` format (versions 1.0 to 3.0), converts
+//! Fortran-order and big-endian files on load, and on Apple targets converts to and from
+//! [`MPSGraphTensorData`](crate::tensor_data::MPSGraphTensorData). `.npz` archives, as
+//! written by `numpy.savez` and `numpy.savez_compressed`, map entry names onto arrays.
+
+use crate::core::MPSDataType;
+use crate::error::MPSGraphError;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+#[cfg(target_vendor = "apple")]
+use crate::tensor_data::MPSGraphTensorData;
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Errors reported while reading or writing `.npy` and `.npz` files
+#[derive(Debug)]
+pub enum NpyError {
+    /// Reading or writing the underlying file failed
+    Io(io::Error),
+    /// The `.npz` archive could not be read or written
+    Archive(String),
+    /// The file doesn't start with the `.npy` magic string
+    BadMagic,
+    /// The `.npy` format version isn't 1.0, 2.0 or 3.0
+    UnsupportedVersion(u8, u8),
+    /// The header dictionary is malformed
+    InvalidHeader(String),
+    /// The NumPy dtype descriptor has no `MPSDataType` counterpart
+    UnsupportedDtype(String),
+    /// The data type has no NumPy dtype descriptor
+    UnsupportedDataType(MPSDataType),
+    /// The requested element type doesn't match the array's data type
+    DataTypeMismatch {
+        /// Data type of the array
+        expected: MPSDataType,
+        /// Data type of the requested element type
+        actual: MPSDataType,
+    },
+    /// The shape holds more bytes than can be addressed
+    ShapeTooLarge(Vec<usize>),
+    /// The buffer size doesn't match the shape and data type
+    SizeMismatch {
+        /// Bytes the shape and data type require
+        expected: usize,
+        /// Bytes supplied
+        actual: usize,
+    },
+    /// Reading tensor data back failed
+    Graph(MPSGraphError),
+}
+
+impl fmt::Display for NpyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NpyError::Io(error) => write!(f, "npy i/o error: {}", error),
+            NpyError::Archive(reason) => write!(f, "npz archive error: {}", reason),
+            NpyError::BadMagic => write!(f, "not a .npy file: missing magic string"),
+            NpyError::UnsupportedVersion(major, minor) => {
+                write!(f, "unsupported .npy format version {}.{}", major, minor)
+            }
+            NpyError::InvalidHeader(reason) => write!(f, "invalid .npy header: {}", reason),
+            NpyError::UnsupportedDtype(descr) => {
+                write!(f, "NumPy dtype '{}' has no MPSDataType counterpart", descr)
+            }
+            NpyError::UnsupportedDataType(data_type) => {
+                write!(f, "{:?} has no NumPy dtype", data_type)
+            }
+            NpyError::DataTypeMismatch { expected, actual } => {
+                write!(f, "array holds {:?} elements, not {:?}", expected, actual)
+            }
+            NpyError::ShapeTooLarge(shape) => {
+                write!(
+                    f,
+                    "shape {:?} holds more bytes than can be addressed",
+                    shape
+                )
+            }
+            NpyError::SizeMismatch { expected, actual } => write!(
+                f,
+                "buffer holds {} bytes but the shape and data type need {}",
+                actual, expected
+            ),
+            NpyError::Graph(error) => write!(f, "reading tensor data failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for NpyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NpyError::Io(error) => Some(error),
+            NpyError::Graph(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for NpyError {
+    fn from(error: io::Error) -> Self {
+        NpyError::Io(error)
+    }
+}
+
+impl From<zip::result::ZipError> for NpyError {
+    fn from(error: zip::result::ZipError) -> Self {
+        match error {
+            zip::result::ZipError::Io(error) => NpyError::Io(error),
+            error => NpyError::Archive(error.to_string()),
+        }
+    }
+}
+
+impl From<MPSGraphError> for NpyError {
+    fn from(error: MPSGraphError) -> Self {
+        NpyError::Graph(error)
+    }
+}
+
+/// Result type returned by the `.npy` and `.npz` functions
+pub type Result<T> = std::result::Result<T, NpyError>;
+
+/// Returns the little-endian NumPy dtype descriptor for a data type
+///
+/// Returns `None` for types NumPy can't represent, such as `BFloat16`, `Int4` or the
+/// normalized types.
+pub fn descr(data_type: MPSDataType) -> Option<&'static str> {
+    Some(match data_type {
+        MPSDataType::Float16 => "<f2",
+        MPSDataType::Float32 => "<f4",
+        MPSDataType::Float64 => "<f8",
+        MPSDataType::Int8 => "|i1",
+        MPSDataType::Int16 => "<i2",
+        MPSDataType::Int32 => "<i4",
+        MPSDataType::Int64 => "<i8",
+        MPSDataType::UInt8 => "|u1",
+        MPSDataType::UInt16 => "<u2",
+        MPSDataType::UInt32 => "<u4",
+        MPSDataType::UInt64 => "<u8",
+        MPSDataType::Bool => "|b1",
+        MPSDataType::Complex16 => "<c4",
+        MPSDataType::Complex32 => "<c8",
+        MPSDataType::Complex64 => "<c16",
+        _ => return None,
+    })
+}
+
+/// Parses a NumPy dtype descriptor such as `<f4` or `|b1`
+///
+/// Returns the data type and whether the stored values are big-endian.
+pub fn parse_descr(descr: &str) -> Result<(MPSDataType, bool)> {
+    let unsupported = || NpyError::UnsupportedDtype(descr.to_string());
+    let (big_endian, code) = match descr.as_bytes().first() {
+        Some(b'<') | Some(b'|') => (false, &descr[1..]),
+        Some(b'>') => (true, &descr[1..]),
+        Some(b'=') => (cfg!(target_endian = "big"), &descr[1..]),
+        _ => (false, descr),
+    };
+    let data_type = match code {
+        "f2" | "e" => MPSDataType::Float16,
+        "f4" | "f" => MPSDataType::Float32,
+        "f8" | "d" => MPSDataType::Float64,
+        "i1" | "b" => MPSDataType::Int8,
+        "i2" | "h" => MPSDataType::Int16,
+        "i4" | "i" => MPSDataType::Int32,
+        "i8" | "q" => MPSDataType::Int64,
+        "u1" | "B" => MPSDataType::UInt8,
+        "u2" | "H" => MPSDataType::UInt16,
+        "u4" | "I" => MPSDataType::UInt32,
+        "u8" | "Q" => MPSDataType::UInt64,
+        "b1" | "?" => MPSDataType::Bool,
+        "c4" => MPSDataType::Complex16,
+        "c8" | "F" => MPSDataType::Complex32,
+        "c16" | "D" => MPSDataType::Complex64,
+        _ => return Err(unsupported()),
+    };
+    Ok((data_type, big_endian))
+}
+
+/// Element types that can be read from and written to an [`NpyArray`]
+pub trait NpyElement: Copy {
+    /// The data type of the element
+    const DATA_TYPE: MPSDataType;
+
+    /// Reads an element from its little-endian bytes
+    fn from_le_bytes(bytes: &[u8]) -> Self;
+
+    /// Appends the little-endian bytes of the element
+    fn extend_le_bytes(self, out: &mut Vec<u8>);
+}
+
+macro_rules! impl_npy_element {
+    ($($ty:ty => $data_type:ident),* $(,)?) => {
+        $(
+            impl NpyElement for $ty {
+                const DATA_TYPE: MPSDataType = MPSDataType::$data_type;
+
+                fn from_le_bytes(bytes: &[u8]) -> Self {
+                    <$ty>::from_le_bytes(bytes.try_into().unwrap())
+                }
+
+                fn extend_le_bytes(self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_npy_element!(
+    f32 => Float32,
+    f64 => Float64,
+    i8 => Int8,
+    i16 => Int16,
+    i32 => Int32,
+    i64 => Int64,
+    u8 => UInt8,
+    u16 => UInt16,
+    u32 => UInt32,
+    u64 => UInt64,
+);
+
+#[cfg(feature = "half")]
+impl_npy_element!(half::f16 => Float16);
+
+impl NpyElement for bool {
+    const DATA_TYPE: MPSDataType = MPSDataType::Bool;
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+
+    fn extend_le_bytes(self, out: &mut Vec<u8>) {
+        out.push(self as u8);
+    }
+}
+
+/// A host array in C order, as stored in a `.npy` file
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpyArray {
+    shape: Vec<usize>,
+    data_type: MPSDataType,
+    data: Vec<u8>,
+}
+
+/// Returns the bytes an array of `shape` and `data_type` occupies, or `None` on overflow
+fn byte_len(shape: &[usize], data_type: MPSDataType) -> Option<usize> {
+    shape
+        .iter()
+        .try_fold(1usize, |count, &dim| count.checked_mul(dim))
+        .and_then(|count| data_type.packed_size_in_bytes(count))
+}
+
+impl NpyArray {
+    /// Creates an array from little-endian bytes in C order
+    pub fn new(shape: &[usize], data_type: MPSDataType, data: Vec<u8>) -> Result<Self> {
+        let expected =
+            byte_len(shape, data_type).ok_or_else(|| NpyError::ShapeTooLarge(shape.to_vec()))?;
+        if data.len() != expected {
+            return Err(NpyError::SizeMismatch {
+                expected,
+                actual: data.len(),
+            });
+        }
+        Ok(NpyArray {
+            shape: shape.to_vec(),
+            data_type,
+            data,
+        })
+    }
+
+    /// Creates an array from typed values in C order
+    pub fn from_slice<T: NpyElement>(shape: &[usize], values: &[T]) -> Result<Self> {
+        let mut data = Vec::with_capacity(std::mem::size_of_val(values));
+        for &value in values {
+            value.extend_le_bytes(&mut data);
+        }
+        Self::new(shape, T::DATA_TYPE, data)
+    }
+
+    /// Returns the shape of the array
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    /// Returns the data type of the array
+    pub fn data_type(&self) -> MPSDataType {
+        self.data_type
+    }
+
+    /// Returns the little-endian bytes of the array in C order
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the number of elements
+    pub fn element_count(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// Copies the elements out as `T`, which must match the data type
+    pub fn to_vec<T: NpyElement>(&self) -> Result<Vec<T>> {
+        if T::DATA_TYPE != self.data_type {
+            return Err(NpyError::DataTypeMismatch {
+                expected: self.data_type,
+                actual: T::DATA_TYPE,
+            });
+        }
+        Ok(self
+            .data
+            .chunks_exact(self.data_type.size_in_bytes())
+            .map(T::from_le_bytes)
+            .collect())
+    }
+
+    /// Reads a `.npy` stream
+    pub fn read<R: Read>(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic[..6] != MAGIC {
+            return Err(NpyError::BadMagic);
+        }
+        let header_len = match (magic[6], magic[7]) {
+            (1, 0) => {
+                let mut len = [0u8; 2];
+                reader.read_exact(&mut len)?;
+                u16::from_le_bytes(len) as usize
+            }
+            (2, 0) | (3, 0) => {
+                let mut len = [0u8; 4];
+                reader.read_exact(&mut len)?;
+                u32::from_le_bytes(len) as usize
+            }
+            (major, minor) => return Err(NpyError::UnsupportedVersion(major, minor)),
+        };
+        let mut header = vec![0u8; header_len];
+        reader.read_exact(&mut header)?;
+        let header = String::from_utf8(header)
+            .map_err(|_| NpyError::InvalidHeader("header is not valid UTF-8".to_string()))?;
+        let header = parse_header(&header)?;
+
+        let (data_type, big_endian) = parse_descr(&header.descr)?;
+        let element_size = data_type.size_in_bytes();
+        let expected = byte_len(&header.shape, data_type).ok_or_else(|| {
+            NpyError::InvalidHeader(format!(
+                "shape {:?} holds more bytes than can be addressed",
+                header.shape
+            ))
+        })?;
+        // The header is untrusted, so the buffer grows with the bytes actually read
+        let mut data = Vec::new();
+        reader.take(expected as u64).read_to_end(&mut data)?;
+        if data.len() != expected {
+            return Err(NpyError::SizeMismatch {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        if big_endian {
+            let component = if data_type.is_complex() {
+                element_size / 2
+            } else {
+                element_size
+            };
+            data.chunks_exact_mut(component).for_each(|c| c.reverse());
+        }
+        if header.fortran_order {
+            data = fortran_to_c_order(&data, &header.shape, element_size);
+        }
+        Self::new(&header.shape, data_type, data)
+    }
+
+    /// Writes the array as a little-endian, C-order `.npy` stream
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        let descr = descr(self.data_type).ok_or(NpyError::UnsupportedDataType(self.data_type))?;
+        let shape = match self.shape.as_slice() {
+            [size] => format!("({},)", size),
+            shape => format!(
+                "({})",
+                shape
+                    .iter()
+                    .map(|size| size.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+            descr, shape
+        );
+
+        // Pad with spaces and a newline so the data starts on a 64-byte boundary
+        let prefix_len = if header.len() + 64 < u16::MAX as usize {
+            10
+        } else {
+            12
+        };
+        let padding = (64 - (prefix_len + header.len() + 1) % 64) % 64;
+        header.extend(std::iter::repeat_n(' ', padding));
+        header.push('\n');
+
+        writer.write_all(MAGIC)?;
+        if prefix_len == 10 {
+            writer.write_all(&[1, 0])?;
+            writer.write_all(&(header.len() as u16).to_le_bytes())?;
+        } else {
+            writer.write_all(&[2, 0])?;
+            writer.write_all(&(header.len() as u32).to_le_bytes())?;
+        }
+        writer.write_all(header.as_bytes())?;
+        writer.write_all(&self.data)?;
+        Ok(())
+    }
+
+    /// Loads a `.npy` file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Saves the array as a `.npy` file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl NpyArray {
+    /// Copies the array into new tensor data
+    pub fn to_tensor_data(&self) -> MPSGraphTensorData {
+        MPSGraphTensorData::new(&self.data, &self.shape, self.data_type)
+    }
+
+    /// Copies tensor data back to the host
+    pub fn from_tensor_data(tensor_data: &MPSGraphTensorData) -> Result<Self> {
+        let shape = tensor_data.shape().dimensions();
+        let data_type = tensor_data.try_data_type()?;
+        Self::new(&shape, data_type, tensor_data.read_bytes()?)
+    }
+}
+
+/// Reads every array in a `.npz` archive, keyed by entry name without the `.npy` suffix
+pub fn read_npz<R: Read + Seek>(reader: R) -> Result<BTreeMap<String, NpyArray>> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let mut arrays = BTreeMap::new();
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name();
+        let name = name.strip_suffix(".npy").unwrap_or(name).to_string();
+        arrays.insert(name, NpyArray::read(entry)?);
+    }
+    Ok(arrays)
+}
+
+/// Writes arrays as an uncompressed `.npz` archive, like `numpy.savez`
+pub fn write_npz<'a, W, I>(writer: W, arrays: I) -> Result<()>
+where
+    W: Write + Seek,
+    I: IntoIterator<Item = (&'a str, &'a NpyArray)>,
+{
+    let mut archive = zip::ZipWriter::new(writer);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored)
+        .large_file(true);
+    for (name, array) in arrays {
+        archive.start_file(format!("{}.npy", name), options)?;
+        array.write(&mut archive)?;
+    }
+    archive.finish()?;
+    Ok(())
+}
+
+/// Loads every array in a `.npz` file
+pub fn load_npz(path: impl AsRef<Path>) -> Result<BTreeMap<String, NpyArray>> {
+    read_npz(BufReader::new(File::open(path)?))
+}
+
+/// Saves arrays as an uncompressed `.npz` file
+pub fn save_npz<'a, I>(path: impl AsRef<Path>, arrays: I) -> Result<()>
+where
+    I: IntoIterator<Item = (&'a str, &'a NpyArray)>,
+{
+    write_npz(BufWriter::new(File::create(path)?), arrays)
+}
+
+struct Header {
+    descr: String,
+    fortran_order: bool,
+    shape: Vec<usize>,
+}
+
+/// Parses the Python dict literal that makes up a `.npy` header
+fn parse_header(header: &str) -> Result<Header> {
+    let invalid = |reason: &str| NpyError::InvalidHeader(format!("{} in {:?}", reason, header));
+    let body = header
+        .trim()
+        .strip_prefix('{')
+        .and_then(|body| body.strip_suffix('}'))
+        .ok_or_else(|| invalid("expected a dict"))?;
+
+    let mut descr = None;
+    let mut fortran_order = None;
+    let mut shape = None;
+    let mut rest = body.trim_start();
+    while !rest.is_empty() {
+        let (key, after_key) = parse_string(rest).ok_or_else(|| invalid("expected a key"))?;
+        let after_colon = after_key
+            .trim_start()
+            .strip_prefix(':')
+            .ok_or_else(|| invalid("expected ':'"))?
+            .trim_start();
+        rest = match key.as_str() {
+            "descr" => {
+                let (value, rest) =
+                    parse_string(after_colon).ok_or_else(|| invalid("descr must be a string"))?;
+                descr = Some(value);
+                rest
+            }
+            "fortran_order" => {
+                if let Some(rest) = after_colon.strip_prefix("True") {
+                    fortran_order = Some(true);
+                    rest
+                } else if let Some(rest) = after_colon.strip_prefix("False") {
+                    fortran_order = Some(false);
+                    rest
+                } else {
+                    return Err(invalid("fortran_order must be True or False"));
+                }
+            }
+            "shape" => {
+                let inner = after_colon
+                    .strip_prefix('(')
+                    .ok_or_else(|| invalid("shape must be a tuple"))?;
+                let end = inner
+                    .find(')')
+                    .ok_or_else(|| invalid("unterminated shape"))?;
+                let dims = inner[..end]
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|dim| !dim.is_empty())
+                    .map(|dim| dim.trim_end_matches('L').parse::<usize>())
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(|_| invalid("shape must hold non-negative integers"))?;
+                shape = Some(dims);
+                &inner[end + 1..]
+            }
+            _ => return Err(invalid(&format!("unexpected key '{}'", key))),
+        };
+        rest = rest.trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+
+    Ok(Header {
+        descr: descr.ok_or_else(|| invalid("missing descr"))?,
+        fortran_order: fortran_order.ok_or_else(|| invalid("missing fortran_order"))?,
+        shape: shape.ok_or_else(|| invalid("missing shape"))?,
+    })
+}
+
+/// Parses a single- or double-quoted Python string, returning it and the remaining input
+fn parse_string(input: &str) -> Option<(String, &str)> {
+    let quote = input.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    let end = input[1..].find(quote)? + 1;
+    Some((input[1..end].to_string(), &input[end + 1..]))
+}
+
+/// Reorders column-major elements into row-major order
+fn fortran_to_c_order(data: &[u8], shape: &[usize], element_size: usize) -> Vec<u8> {
+    let mut fortran_strides = vec![1; shape.len()];
+    for axis in 1..shape.len() {
+        fortran_strides[axis] = fortran_strides[axis - 1] * shape[axis - 1];
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    let mut index = vec![0; shape.len()];
+    for _ in 0..data.len() / element_size.max(1) {
+        let offset: usize = index
+            .iter()
+            .zip(&fortran_strides)
+            .map(|(i, stride)| i * stride)
+            .sum();
+        out.extend_from_slice(&data[offset * element_size..(offset + 1) * element_size]);
+
+        // Advance the C-order index, last axis fastest
+        for axis in (0..shape.len()).rev() {
+            index[axis] += 1;
+            if index[axis] < shape[axis] {
+                break;
+            }
+            index[axis] = 0;
+        }
+    }
+    out
+}
@@ -0,0 +1,1438 @@
+//! Maps ONNX nodes onto recorded IR operations.
+
+use super::proto::{AttributeProto, GraphProto, NodeProto, TensorProto, ValueInfoProto};
+use super::{data_type, Import, OnnxError, Result};
+use crate::cast;
+use crate::core::MPSDataType;
+use crate::dims::{Dim, Shape};
+use crate::ir::{
+    BinaryOp, Convolution2d, Graph, OpKind, PoolingOp, ReductionOp, TensorId, UnaryOp, Window2d,
+};
+use crate::non_maximum_suppression_ops::MPSGraphNonMaximumSuppressionCoordinateMode;
+use crate::pooling_ops::{MPSGraphPaddingStyle, MPSGraphTensorNamedDataLayout};
+use crate::resize_ops::MPSGraphResizeMode;
+use crate::tensor_shape_ops::MPSGraphSliceMasks;
+use std::collections::HashMap;
+
+/// Operator types with a mapping, sorted so they can be binary searched
+pub(super) const SUPPORTED_OP_TYPES: &[&str] = &[
+    "Abs",
+    "Add",
+    "And",
+    "ArgMax",
+    "ArgMin",
+    "AveragePool",
+    "BatchNormalization",
+    "Cast",
+    "Ceil",
+    "Clip",
+    "Concat",
+    "Constant",
+    "ConstantOfShape",
+    "Conv",
+    "Cos",
+    "Div",
+    "Dropout",
+    "Equal",
+    "Erf",
+    "Exp",
+    "Expand",
+    "Flatten",
+    "Floor",
+    "Gather",
+    "Gemm",
+    "GlobalAveragePool",
+    "GlobalMaxPool",
+    "Greater",
+    "GreaterOrEqual",
+    "Identity",
+    "IsNaN",
+    "LayerNormalization",
+    "LeakyRelu",
+    "Less",
+    "LessOrEqual",
+    "Log",
+    "LogSoftmax",
+    "MatMul",
+    "Max",
+    "MaxPool",
+    "Min",
+    "Mul",
+    "Neg",
+    "NonMaxSuppression",
+    "Not",
+    "Or",
+    "Pow",
+    "Reciprocal",
+    "ReduceMax",
+    "ReduceMean",
+    "ReduceMin",
+    "ReduceProd",
+    "ReduceSum",
+    "Relu",
+    "Reshape",
+    "Resize",
+    "Round",
+    "Shape",
+    "Sigmoid",
+    "Sign",
+    "Sin",
+    "Slice",
+    "Softmax",
+    "Split",
+    "Sqrt",
+    "Squeeze",
+    "Sub",
+    "Sum",
+    "Tanh",
+    "Tile",
+    "TopK",
+    "Transpose",
+    "Unsqueeze",
+    "Where",
+    "Xor",
+];
+
+/// Returns true if the node's operator has a mapping
+pub(super) fn is_supported(node: &NodeProto) -> bool {
+    (node.domain.is_empty() || node.domain == "ai.onnx")
+        && SUPPORTED_OP_TYPES
+            .binary_search(&node.op_type.as_str())
+            .is_ok()
+}
+
+/// Returns the node name, falling back to its first output for unnamed nodes
+pub(super) fn node_name(node: &NodeProto) -> &str {
+    if node.name.is_empty() {
+        node.output.first().map_or("", String::as_str)
+    } else {
+        &node.name
+    }
+}
+
+fn unary_op(op_type: &str) -> Option<UnaryOp> {
+    Some(match op_type {
+        "Abs" => UnaryOp::Abs,
+        "Ceil" => UnaryOp::Ceil,
+        "Cos" => UnaryOp::Cos,
+        // Inference only: the ratio and training mode inputs are ignored
+        "Dropout" | "Identity" => UnaryOp::Identity,
+        "Erf" => UnaryOp::Erf,
+        "Exp" => UnaryOp::Exp,
+        "Floor" => UnaryOp::Floor,
+        "IsNaN" => UnaryOp::IsNan,
+        "Log" => UnaryOp::Log,
+        "Neg" => UnaryOp::Negative,
+        "Not" => UnaryOp::LogicalNot,
+        "Reciprocal" => UnaryOp::Reciprocal,
+        "Relu" => UnaryOp::Relu,
+        // ONNX rounds halves to even
+        "Round" => UnaryOp::Rint,
+        "Sigmoid" => UnaryOp::Sigmoid,
+        "Sign" => UnaryOp::Sign,
+        "Sin" => UnaryOp::Sin,
+        "Sqrt" => UnaryOp::Sqrt,
+        "Tanh" => UnaryOp::Tanh,
+        _ => return None,
+    })
+}
+
+fn binary_op(op_type: &str) -> Option<BinaryOp> {
+    Some(match op_type {
+        "Add" | "Sum" => BinaryOp::Add,
+        "And" => BinaryOp::LogicalAnd,
+        "Div" => BinaryOp::Divide,
+        "Equal" => BinaryOp::Equal,
+        "Greater" => BinaryOp::GreaterThan,
+        "GreaterOrEqual" => BinaryOp::GreaterThanOrEqualTo,
+        "Less" => BinaryOp::LessThan,
+        "LessOrEqual" => BinaryOp::LessThanOrEqualTo,
+        "Max" => BinaryOp::Maximum,
+        "Min" => BinaryOp::Minimum,
+        "Mul" => BinaryOp::Multiply,
+        "Or" => BinaryOp::LogicalOr,
+        "Pow" => BinaryOp::Power,
+        "Sub" => BinaryOp::Subtract,
+        "Xor" => BinaryOp::LogicalXor,
+        _ => return None,
+    })
+}
+
+/// Host copy of an initializer or constant, kept for inputs that must be known at import
+#[derive(Debug, Clone)]
+struct ConstantValue {
+    data_type: MPSDataType,
+    shape: Vec<usize>,
+    /// Little-endian element bytes
+    data: Vec<u8>,
+}
+
+impl ConstantValue {
+    fn from_proto(tensor: &TensorProto) -> Result<Self> {
+        let invalid = |reason: String| OnnxError::InvalidTensor {
+            name: tensor.name.clone(),
+            reason,
+        };
+        // DataLocation::EXTERNAL is 1
+        if tensor.data_location == 1 || !tensor.external_data.is_empty() {
+            return Err(OnnxError::ExternalData(tensor.name.clone()));
+        }
+        let data_type = data_type(tensor.data_type)?;
+        let shape = tensor
+            .dims
+            .iter()
+            .map(|&size| {
+                usize::try_from(size)
+                    .map_err(|_| invalid(format!("dimension {} is negative", size)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let size = data_type.size_in_bytes();
+        let expected = shape
+            .iter()
+            .try_fold(size, |len, &dim| len.checked_mul(dim))
+            .ok_or_else(|| invalid(format!("shape {:?} holds more bytes than fit", shape)))?;
+        let data = if !tensor.raw_data.is_empty() {
+            tensor.raw_data.clone()
+        } else {
+            match data_type {
+                MPSDataType::Float32 | MPSDataType::Complex32 => tensor
+                    .float_data
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes())
+                    .collect(),
+                MPSDataType::Float64 | MPSDataType::Complex64 => tensor
+                    .double_data
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes())
+                    .collect(),
+                MPSDataType::Int64 => tensor
+                    .int64_data
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes())
+                    .collect(),
+                MPSDataType::UInt32 | MPSDataType::UInt64 => tensor
+                    .uint64_data
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes()[..size].to_vec())
+                    .collect(),
+                // The remaining types, including the 16-bit floats, are stored in int32_data
+                _ => tensor
+                    .int32_data
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes()[..size].to_vec())
+                    .collect(),
+            }
+        };
+
+        if data.len() != expected {
+            return Err(invalid(format!(
+                "holds {} bytes but its shape and data type need {}",
+                data.len(),
+                expected
+            )));
+        }
+        Ok(ConstantValue {
+            data_type,
+            shape,
+            data,
+        })
+    }
+
+    fn from_f32s(values: &[f32], shape: Vec<usize>) -> Self {
+        ConstantValue {
+            data_type: MPSDataType::Float32,
+            shape,
+            data: values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        }
+    }
+
+    fn from_i64s(values: &[i64], shape: Vec<usize>) -> Self {
+        ConstantValue {
+            data_type: MPSDataType::Int64,
+            shape,
+            data: values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        }
+    }
+
+    /// Returns the elements of an integer or Bool constant
+    fn ints(&self) -> Option<Vec<i64>> {
+        let size = self.data_type.size_in_bytes();
+        let elements = self.data.chunks_exact(size);
+        Some(match self.data_type {
+            MPSDataType::Int8 => elements.map(|e| e[0] as i8 as i64).collect(),
+            MPSDataType::UInt8 | MPSDataType::Bool => elements.map(|e| e[0] as i64).collect(),
+            MPSDataType::Int16 => elements
+                .map(|e| i16::from_le_bytes([e[0], e[1]]) as i64)
+                .collect(),
+            MPSDataType::UInt16 => elements
+                .map(|e| u16::from_le_bytes([e[0], e[1]]) as i64)
+                .collect(),
+            MPSDataType::Int32 => elements
+                .map(|e| i32::from_le_bytes(e.try_into().unwrap()) as i64)
+                .collect(),
+            MPSDataType::UInt32 => elements
+                .map(|e| u32::from_le_bytes(e.try_into().unwrap()) as i64)
+                .collect(),
+            MPSDataType::Int64 => elements
+                .map(|e| i64::from_le_bytes(e.try_into().unwrap()))
+                .collect(),
+            MPSDataType::UInt64 => elements
+                .map(|e| u64::from_le_bytes(e.try_into().unwrap()) as i64)
+                .collect(),
+            _ => return None,
+        })
+    }
+
+    /// Returns the elements of a real constant as `f64`
+    fn floats(&self) -> Option<Vec<f64>> {
+        match self.data_type {
+            MPSDataType::Float16 | MPSDataType::BFloat16 | MPSDataType::Float32 => {
+                let data = cast::cast(&self.data, self.data_type, MPSDataType::Float64)?;
+                Some(
+                    data.chunks_exact(8)
+                        .map(|e| f64::from_le_bytes(e.try_into().unwrap()))
+                        .collect(),
+                )
+            }
+            MPSDataType::Float64 => Some(
+                self.data
+                    .chunks_exact(8)
+                    .map(|e| f64::from_le_bytes(e.try_into().unwrap()))
+                    .collect(),
+            ),
+            _ => Some(self.ints()?.into_iter().map(|v| v as f64).collect()),
+        }
+    }
+}
+
+/// Returns the data type and shape declared for a graph input
+fn value_type(info: &ValueInfoProto) -> Result<(MPSDataType, Shape)> {
+    let invalid = |reason: &str| OnnxError::InvalidValueInfo {
+        name: info.name.clone(),
+        reason: reason.to_string(),
+    };
+    let tensor = info
+        .r#type
+        .as_ref()
+        .and_then(|r#type| r#type.tensor_type.as_ref())
+        .ok_or_else(|| invalid("not a tensor"))?;
+    let data_type = data_type(tensor.elem_type)?;
+    let shape = tensor
+        .shape
+        .as_ref()
+        .ok_or_else(|| invalid("the rank is unknown"))?;
+    // Symbolic and unknown dimensions are only known at run time
+    let dims = shape
+        .dim
+        .iter()
+        .map(|dim| match dim.dim_value {
+            Some(size) if size >= 0 => Dim::Static(size as usize),
+            _ => Dim::Dynamic,
+        })
+        .collect();
+    Ok((data_type, Shape::new(dims)))
+}
+
+/// A node being imported, with attribute accessors
+struct NodeContext<'a> {
+    node: &'a NodeProto,
+}
+
+impl<'a> NodeContext<'a> {
+    fn op_type(&self) -> &'a str {
+        &self.node.op_type
+    }
+
+    fn error(&self, reason: impl Into<String>) -> OnnxError {
+        OnnxError::Node {
+            node: node_name(self.node).to_string(),
+            op_type: self.node.op_type.clone(),
+            reason: reason.into(),
+        }
+    }
+
+    fn attribute(&self, name: &str) -> Option<&'a AttributeProto> {
+        self.node
+            .attribute
+            .iter()
+            .find(|attribute| attribute.name == name)
+    }
+
+    fn int(&self, name: &str, default: i64) -> i64 {
+        self.attribute(name)
+            .map_or(default, |attribute| attribute.i)
+    }
+
+    fn ints(&self, name: &str) -> Option<&'a [i64]> {
+        self.attribute(name)
+            .map(|attribute| attribute.ints.as_slice())
+    }
+
+    fn float(&self, name: &str, default: f32) -> f32 {
+        self.attribute(name)
+            .map_or(default, |attribute| attribute.f)
+    }
+
+    fn string(&self, name: &str, default: &str) -> String {
+        self.attribute(name)
+            .map_or(default.to_string(), |attribute| {
+                String::from_utf8_lossy(&attribute.s).into_owned()
+            })
+    }
+
+    /// Returns the name of an input, or `None` if it is omitted
+    fn input_name(&self, index: usize) -> Option<&'a str> {
+        self.node
+            .input
+            .get(index)
+            .map(String::as_str)
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Returns the name of an output, or `None` if it is omitted
+    fn output_name(&self, index: usize) -> Option<&'a str> {
+        self.node
+            .output
+            .get(index)
+            .map(String::as_str)
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Reads a `[height, width]` attribute such as `strides`
+    fn pair(&self, name: &str) -> Result<[usize; 2]> {
+        match self.ints(name) {
+            None => Ok([1, 1]),
+            Some(&[height, width]) if height > 0 && width > 0 => {
+                Ok([height as usize, width as usize])
+            }
+            Some(values) => Err(self.error(format!(
+                "{} must be two positive values, got {:?}",
+                name, values
+            ))),
+        }
+    }
+
+    /// Reads `auto_pad` and `pads` as IR padding
+    fn padding(&self) -> Result<([usize; 4], MPSGraphPaddingStyle)> {
+        match self.string("auto_pad", "NOTSET").as_str() {
+            "NOTSET" => match self.ints("pads") {
+                None => Ok(([0; 4], MPSGraphPaddingStyle::Explicit)),
+                // ONNX lists all begins, then all ends
+                Some(&[top, left, bottom, right])
+                    if [top, left, bottom, right].iter().all(|&pad| pad >= 0) =>
+                {
+                    let padding = [top, bottom, left, right].map(|pad| pad as usize);
+                    Ok((padding, MPSGraphPaddingStyle::Explicit))
+                }
+                Some(pads) => Err(self.error(format!(
+                    "pads must be four non-negative values, got {:?}",
+                    pads
+                ))),
+            },
+            "VALID" => Ok(([0; 4], MPSGraphPaddingStyle::TfValid)),
+            // TfSame puts the odd padding element at the end, like SAME_UPPER
+            "SAME_UPPER" => Ok(([0; 4], MPSGraphPaddingStyle::TfSame)),
+            other => Err(self.error(format!("auto_pad {} is not supported", other))),
+        }
+    }
+}
+
+struct Importer {
+    graph: Graph,
+    values: HashMap<String, TensorId>,
+    constants: HashMap<String, ConstantValue>,
+    opset_version: i64,
+}
+
+/// Imports a graph whose nodes are all supported
+pub(super) fn import(graph: &GraphProto, opset_version: i64) -> Result<Import> {
+    let mut importer = Importer {
+        graph: Graph::new(),
+        values: HashMap::new(),
+        constants: HashMap::new(),
+        opset_version,
+    };
+
+    for tensor in &graph.initializer {
+        importer.add_constant(&tensor.name, ConstantValue::from_proto(tensor)?)?;
+    }
+
+    let mut inputs = Vec::new();
+    for info in &graph.input {
+        // Older exporters also list initializers as graph inputs
+        if importer.values.contains_key(&info.name) {
+            continue;
+        }
+        let (data_type, shape) = value_type(info)?;
+        let kind = OpKind::Placeholder { shape, data_type };
+        let id = importer
+            .graph
+            .try_add_operation(kind, &[], Some(&info.name))
+            .map_err(|error| OnnxError::InvalidValueInfo {
+                name: info.name.clone(),
+                reason: error.to_string(),
+            })?[0];
+        importer.values.insert(info.name.clone(), id);
+        inputs.push((info.name.clone(), id));
+    }
+
+    for node in &graph.node {
+        let node = NodeContext { node };
+        let results = importer.import_node(&node)?;
+        importer.bind(&node, &results)?;
+    }
+
+    let outputs = graph
+        .output
+        .iter()
+        .map(|info| match importer.values.get(&info.name) {
+            Some(&id) => Ok((info.name.clone(), id)),
+            None => Err(OnnxError::UnknownValue(info.name.clone())),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Import {
+        graph: importer.graph,
+        inputs,
+        outputs,
+        values: importer.values,
+    })
+}
+
+impl Importer {
+    /// Associates the node's output names with its results
+    fn bind(&mut self, node: &NodeContext, results: &[TensorId]) -> Result<()> {
+        for index in 0..node.node.output.len() {
+            if let Some(name) = node.output_name(index) {
+                let id = results.get(index).ok_or_else(|| {
+                    node.error(format!("output {} ('{}') is not supported", index, name))
+                })?;
+                self.values.insert(name.to_string(), *id);
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&self, node: &NodeContext, index: usize) -> Result<TensorId> {
+        let name = node
+            .input_name(index)
+            .ok_or_else(|| node.error(format!("input {} is required", index)))?;
+        self.values
+            .get(name)
+            .copied()
+            .ok_or_else(|| OnnxError::UnknownValue(name.to_string()))
+    }
+
+    fn optional_value(&self, node: &NodeContext, index: usize) -> Result<Option<TensorId>> {
+        match node.input_name(index) {
+            Some(_) => self.value(node, index).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the host copy of an input that must be an initializer or constant
+    fn constant(&self, node: &NodeContext, index: usize) -> Result<Option<&ConstantValue>> {
+        match node.input_name(index) {
+            Some(name) => self.constants.get(name).map(Some).ok_or_else(|| {
+                node.error(format!(
+                    "input {} ('{}') must be an initializer or constant",
+                    index, name
+                ))
+            }),
+            None => Ok(None),
+        }
+    }
+
+    fn constant_ints(&self, node: &NodeContext, index: usize) -> Result<Option<Vec<i64>>> {
+        self.constant(node, index)?
+            .map(|value| {
+                value
+                    .ints()
+                    .ok_or_else(|| node.error(format!("input {} must hold integers", index)))
+            })
+            .transpose()
+    }
+
+    fn constant_floats(&self, node: &NodeContext, index: usize) -> Result<Option<Vec<f64>>> {
+        self.constant(node, index)?
+            .map(|value| {
+                value
+                    .floats()
+                    .ok_or_else(|| node.error(format!("input {} must hold numbers", index)))
+            })
+            .transpose()
+    }
+
+    fn required_ints(&self, node: &NodeContext, index: usize) -> Result<Vec<i64>> {
+        self.constant_ints(node, index)?
+            .ok_or_else(|| node.error(format!("input {} is required", index)))
+    }
+
+    /// Records a constant and keeps its host copy under `name`
+    fn add_constant(&mut self, name: &str, value: ConstantValue) -> Result<TensorId> {
+        let kind = OpKind::Constant {
+            data: value.data.clone(),
+            shape: Shape::from_static(&value.shape),
+            data_type: value.data_type,
+        };
+        let id = self
+            .graph
+            .try_add_operation(kind, &[], Some(name))
+            .map_err(|error| OnnxError::InvalidTensor {
+                name: name.to_string(),
+                reason: error.to_string(),
+            })?[0];
+        self.values.insert(name.to_string(), id);
+        self.constants.insert(name.to_string(), value);
+        Ok(id)
+    }
+
+    fn record(
+        &mut self,
+        node: &NodeContext,
+        kind: OpKind,
+        inputs: &[TensorId],
+        name: Option<&str>,
+    ) -> Result<Vec<TensorId>> {
+        self.graph
+            .try_add_operation(kind, inputs, name)
+            .map_err(|error| node.error(error.to_string()))
+    }
+
+    fn record_one(
+        &mut self,
+        node: &NodeContext,
+        kind: OpKind,
+        inputs: &[TensorId],
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        Ok(self.record(node, kind, inputs, name)?[0])
+    }
+
+    fn unary(
+        &mut self,
+        node: &NodeContext,
+        op: UnaryOp,
+        x: TensorId,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.record_one(node, OpKind::Unary(op), &[x], name)
+    }
+
+    fn binary(
+        &mut self,
+        node: &NodeContext,
+        op: BinaryOp,
+        lhs: TensorId,
+        rhs: TensorId,
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        self.record_one(node, OpKind::Binary(op), &[lhs, rhs], name)
+    }
+
+    fn scalar(
+        &mut self,
+        node: &NodeContext,
+        value: f64,
+        data_type: MPSDataType,
+    ) -> Result<TensorId> {
+        let kind = OpKind::ConstantScalar {
+            value,
+            shape: Shape::scalar(),
+            data_type,
+        };
+        self.record_one(node, kind, &[], None)
+    }
+
+    fn reshape(&mut self, node: &NodeContext, x: TensorId, shape: Vec<i64>) -> Result<TensorId> {
+        self.record_one(node, OpKind::Reshape { shape }, &[x], None)
+    }
+
+    fn axis(&self, node: &NodeContext, x: TensorId, axis: i64) -> Result<usize> {
+        self.graph
+            .shape(x)
+            .normalize_axis(axis)
+            .map_err(|error| node.error(error.to_string()))
+    }
+
+    /// Averages over `axes`, which must have static sizes; reduced axes are kept
+    fn mean(
+        &mut self,
+        node: &NodeContext,
+        x: TensorId,
+        axes: &[usize],
+        name: Option<&str>,
+    ) -> Result<TensorId> {
+        let shape = self.graph.shape(x);
+        let mut count = 1;
+        for &axis in axes {
+            match shape.dims().get(axis) {
+                Some(Dim::Static(size)) => count *= size,
+                _ => {
+                    return Err(node.error(format!(
+                        "averaging over axis {} of {} needs a static size",
+                        axis, shape
+                    )))
+                }
+            }
+        }
+        let data_type = self.graph.data_type(x);
+        let kind = OpKind::Reduction {
+            op: ReductionOp::Sum,
+            axes: axes.to_vec(),
+        };
+        let sum = self.record_one(node, kind, &[x], None)?;
+        let count = self.scalar(node, count as f64, data_type)?;
+        self.binary(node, BinaryOp::Divide, sum, count, name)
+    }
+
+    fn import_node(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let name = node.output_name(0);
+        if let Some(op) = unary_op(node.op_type()) {
+            let x = self.value(node, 0)?;
+            return Ok(vec![self.unary(node, op, x, name)?]);
+        }
+        if let Some(op) = binary_op(node.op_type()) {
+            // Max, Min and Sum take any number of operands
+            let count = node.node.input.len();
+            let mut result = self.value(node, 0)?;
+            if count == 1 {
+                result = self.unary(node, UnaryOp::Identity, result, name)?;
+            }
+            for index in 1..count {
+                let rhs = self.value(node, index)?;
+                let last = index + 1 == count;
+                result = self.binary(node, op, result, rhs, name.filter(|_| last))?;
+            }
+            return Ok(vec![result]);
+        }
+
+        match node.op_type() {
+            "Where" => {
+                let inputs = [
+                    self.value(node, 0)?,
+                    self.value(node, 1)?,
+                    self.value(node, 2)?,
+                ];
+                self.record(node, OpKind::Select, &inputs, name)
+            }
+            "Clip" => self.clip(node),
+            "Cast" => {
+                let x = self.value(node, 0)?;
+                let data_type = data_type(node.int("to", 0) as i32)?;
+                self.record(node, OpKind::Cast { data_type }, &[x], name)
+            }
+            "LeakyRelu" => {
+                let x = self.value(node, 0)?;
+                let alpha = node.float("alpha", 0.01);
+                let scale = self.scalar(node, alpha as f64, self.graph.data_type(x))?;
+                let scaled = self.binary(node, BinaryOp::Multiply, x, scale, None)?;
+                // max(x, alpha * x) for alpha <= 1, min(x, alpha * x) otherwise
+                let op = if alpha <= 1.0 {
+                    BinaryOp::Maximum
+                } else {
+                    BinaryOp::Minimum
+                };
+                Ok(vec![self.binary(node, op, x, scaled, name)?])
+            }
+            "MatMul" => {
+                let inputs = [self.value(node, 0)?, self.value(node, 1)?];
+                self.record(node, OpKind::MatMul, &inputs, name)
+            }
+            "Gemm" => self.gemm(node),
+            "Softmax" | "LogSoftmax" => {
+                let x = self.value(node, 0)?;
+                let axis = node.int("axis", -1);
+                if node.op_type() == "Softmax" {
+                    return self.record(node, OpKind::Softmax { axis }, &[x], name);
+                }
+                let softmax = self.record_one(node, OpKind::Softmax { axis }, &[x], None)?;
+                Ok(vec![self.unary(node, UnaryOp::Log, softmax, name)?])
+            }
+            "Conv" => self.conv(node),
+            "MaxPool" => self.pool(node, PoolingOp::Max),
+            "AveragePool" => self.pool(node, PoolingOp::Average),
+            "GlobalAveragePool" | "GlobalMaxPool" => {
+                let x = self.value(node, 0)?;
+                if self.graph.shape(x).rank() != 4 {
+                    return Err(node.error("only rank-4 NCHW inputs are supported"));
+                }
+                if node.op_type() == "GlobalAveragePool" {
+                    return Ok(vec![self.mean(node, x, &[2, 3], name)?]);
+                }
+                let kind = OpKind::Reduction {
+                    op: ReductionOp::Maximum,
+                    axes: vec![2, 3],
+                };
+                self.record(node, kind, &[x], name)
+            }
+            "BatchNormalization" => self.batch_normalization(node),
+            "LayerNormalization" => self.layer_normalization(node),
+            "Reshape" => self.reshape_node(node),
+            "Flatten" => {
+                let x = self.value(node, 0)?;
+                let axis = node.int("axis", 1);
+                self.record(node, OpKind::Flatten2d { axis }, &[x], name)
+            }
+            "Transpose" => {
+                let x = self.value(node, 0)?;
+                let rank = self.graph.shape(x).rank();
+                let permutation = match node.ints("perm") {
+                    Some(perm) => perm
+                        .iter()
+                        .map(|&axis| {
+                            usize::try_from(axis).map_err(|_| {
+                                node.error(format!("perm {:?} has a negative axis", perm))
+                            })
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                    None => (0..rank).rev().collect(),
+                };
+                self.record(node, OpKind::Transpose { permutation }, &[x], name)
+            }
+            "Concat" => {
+                let inputs = (0..node.node.input.len())
+                    .map(|index| self.value(node, index))
+                    .collect::<Result<Vec<_>>>()?;
+                let axis = node
+                    .attribute("axis")
+                    .ok_or_else(|| node.error("the axis attribute is required"))?
+                    .i;
+                self.record(node, OpKind::Concat { axis }, &inputs, name)
+            }
+            "Squeeze" => {
+                let x = self.value(node, 0)?;
+                let axes = self.constant_ints(node, 1)?.unwrap_or_default();
+                self.record(node, OpKind::Squeeze { axes }, &[x], name)
+            }
+            "Unsqueeze" => {
+                let x = self.value(node, 0)?;
+                let axes = self.required_ints(node, 1)?;
+                self.record(node, OpKind::ExpandDims { axes }, &[x], name)
+            }
+            "Expand" => {
+                let x = self.value(node, 0)?;
+                let target = Shape::from_i64(&self.required_ints(node, 1)?);
+                // ONNX broadcasts in both directions, so the result may exceed the target
+                let shape = self
+                    .graph
+                    .shape(x)
+                    .broadcast(&target)
+                    .map_err(|error| node.error(error.to_string()))?;
+                let kind = OpKind::Broadcast {
+                    shape: shape.to_i64(),
+                };
+                self.record(node, kind, &[x], name)
+            }
+            "Tile" => {
+                let x = self.value(node, 0)?;
+                let multiples = self.required_ints(node, 1)?;
+                self.record(node, OpKind::Tile { multiples }, &[x], name)
+            }
+            "Slice" => self.slice(node),
+            "Split" => self.split(node),
+            "Gather" => {
+                let inputs = [self.value(node, 0)?, self.value(node, 1)?];
+                let axis = self.axis(node, inputs[0], node.int("axis", 0))?;
+                let kind = OpKind::Gather {
+                    axis,
+                    batch_dimensions: 0,
+                };
+                self.record(node, kind, &inputs, name)
+            }
+            "ReduceSum" | "ReduceMean" | "ReduceMax" | "ReduceMin" | "ReduceProd" => {
+                self.reduce(node)
+            }
+            "ArgMax" | "ArgMin" => self.arg_reduce(node),
+            "TopK" => self.top_k(node),
+            "Resize" => self.resize(node),
+            "NonMaxSuppression" => self.non_max_suppression(node),
+            "Constant" => self.constant_node(node),
+            "ConstantOfShape" => {
+                let shape = self
+                    .required_ints(node, 0)?
+                    .iter()
+                    .map(|&size| {
+                        usize::try_from(size)
+                            .map_err(|_| node.error(format!("size {} is negative", size)))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let (data_type, value) = match node.attribute("value").and_then(|a| a.t.as_ref()) {
+                    Some(tensor) => {
+                        let value = ConstantValue::from_proto(tensor)?;
+                        let fill = value.floats().and_then(|values| values.first().copied());
+                        let fill = fill.ok_or_else(|| node.error("value must hold one number"))?;
+                        (value.data_type, fill)
+                    }
+                    None => (MPSDataType::Float32, 0.0),
+                };
+                let kind = OpKind::ConstantScalar {
+                    value,
+                    shape: Shape::from_static(&shape),
+                    data_type,
+                };
+                self.record(node, kind, &[], name)
+            }
+            "Shape" => self.shape_node(node),
+            _ => Err(node.error("operator is not supported")),
+        }
+    }
+
+    fn clip(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let x = self.value(node, 0)?;
+        let data_type = self.graph.data_type(x);
+        let bounds = [self.optional_value(node, 1)?, self.optional_value(node, 2)?];
+        if !data_type.is_float() && bounds.contains(&None) {
+            return Err(node.error("clipping integers needs both min and max"));
+        }
+        let min = match bounds[0] {
+            Some(min) => min,
+            None => self.scalar(node, f64::NEG_INFINITY, data_type)?,
+        };
+        let max = match bounds[1] {
+            Some(max) => max,
+            None => self.scalar(node, f64::INFINITY, data_type)?,
+        };
+        self.record(node, OpKind::Clamp, &[x, min, max], node.output_name(0))
+    }
+
+    fn gemm(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let name = node.output_name(0);
+        let mut a = self.value(node, 0)?;
+        let mut b = self.value(node, 1)?;
+        let c = self.optional_value(node, 2)?;
+        let alpha = node.float("alpha", 1.0);
+        let beta = node.float("beta", 1.0);
+
+        let transpose = OpKind::Transpose {
+            permutation: vec![1, 0],
+        };
+        if node.int("transA", 0) != 0 {
+            a = self.record_one(node, transpose.clone(), &[a], None)?;
+        }
+        if node.int("transB", 0) != 0 {
+            b = self.record_one(node, transpose, &[b], None)?;
+        }
+
+        let product_name = name.filter(|_| alpha == 1.0 && c.is_none());
+        let mut y = self.record_one(node, OpKind::MatMul, &[a, b], product_name)?;
+        if alpha != 1.0 {
+            let alpha = self.scalar(node, alpha as f64, self.graph.data_type(y))?;
+            y = self.binary(
+                node,
+                BinaryOp::Multiply,
+                y,
+                alpha,
+                name.filter(|_| c.is_none()),
+            )?;
+        }
+        if let Some(mut c) = c {
+            if beta != 1.0 {
+                let beta = self.scalar(node, beta as f64, self.graph.data_type(c))?;
+                c = self.binary(node, BinaryOp::Multiply, c, beta, None)?;
+            }
+            y = self.binary(node, BinaryOp::Add, y, c, name)?;
+        }
+        Ok(vec![y])
+    }
+
+    fn conv(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let name = node.output_name(0);
+        let x = self.value(node, 0)?;
+        let weights = self.value(node, 1)?;
+        let bias = self.optional_value(node, 2)?;
+        if self.graph.shape(weights).rank() != 4 {
+            return Err(node.error("only 2D convolutions are supported"));
+        }
+
+        let (padding, padding_style) = node.padding()?;
+        let groups = usize::try_from(node.int("group", 1))
+            .ok()
+            .filter(|&groups| groups > 0)
+            .ok_or_else(|| node.error("group must be positive"))?;
+        // ONNX uses NCHW sources and OIHW weights, the IR defaults
+        let descriptor = Convolution2d {
+            strides: node.pair("strides")?,
+            dilations: node.pair("dilations")?,
+            padding,
+            padding_style,
+            groups,
+            ..Convolution2d::default()
+        };
+        let kind = OpKind::Convolution2d(descriptor);
+        let output = self.record_one(node, kind, &[x, weights], name.filter(|_| bias.is_none()))?;
+
+        match bias {
+            Some(bias) => {
+                let bias = self.reshape(node, bias, vec![1, -1, 1, 1])?;
+                Ok(vec![self.binary(
+                    node,
+                    BinaryOp::Add,
+                    output,
+                    bias,
+                    name,
+                )?])
+            }
+            None => Ok(vec![output]),
+        }
+    }
+
+    fn pool(&mut self, node: &NodeContext, op: PoolingOp) -> Result<Vec<TensorId>> {
+        let x = self.value(node, 0)?;
+        let kernel = match node.ints("kernel_shape") {
+            Some(&[height, width]) if height > 0 && width > 0 => [height as usize, width as usize],
+            other => {
+                return Err(node.error(format!(
+                    "kernel_shape must be two positive values, got {:?}",
+                    other
+                )))
+            }
+        };
+        let (padding, padding_style) = node.padding()?;
+        let padded = padding_style == MPSGraphPaddingStyle::TfSame || padding != [0; 4];
+        if op == PoolingOp::Average && node.int("count_include_pad", 0) != 0 && padded {
+            return Err(node.error("count_include_pad is not supported with padding"));
+        }
+        let window = Window2d {
+            strides: node.pair("strides")?,
+            dilations: node.pair("dilations")?,
+            padding,
+            padding_style,
+            ceil_mode: node.int("ceil_mode", 0) != 0,
+            ..Window2d::new(kernel)
+        };
+        self.record(
+            node,
+            OpKind::Pooling2d { op, window },
+            &[x],
+            node.output_name(0),
+        )
+    }
+
+    /// Inference-mode batch normalization over axis 1
+    fn batch_normalization(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        if node.int("training_mode", 0) != 0 {
+            return Err(node.error("training mode is not supported"));
+        }
+        let x = self.value(node, 0)?;
+        let [scale, bias, mean, variance] = [
+            self.value(node, 1)?,
+            self.value(node, 2)?,
+            self.value(node, 3)?,
+            self.value(node, 4)?,
+        ];
+        let rank = self.graph.shape(x).rank();
+        if rank < 2 {
+            return Err(node.error("the input needs a channel axis"));
+        }
+        let mut parameter_shape = vec![1, -1];
+        parameter_shape.resize(rank, 1);
+
+        let epsilon = node.float("epsilon", 1e-5) as f64;
+        let epsilon = self.scalar(node, epsilon, self.graph.data_type(variance))?;
+        let variance = self.binary(node, BinaryOp::Add, variance, epsilon, None)?;
+        let inverse = self.unary(node, UnaryOp::Rsqrt, variance, None)?;
+        let factor = self.binary(node, BinaryOp::Multiply, scale, inverse, None)?;
+
+        let factor = self.reshape(node, factor, parameter_shape.clone())?;
+        let mean = self.reshape(node, mean, parameter_shape.clone())?;
+        let bias = self.reshape(node, bias, parameter_shape)?;
+        let centered = self.binary(node, BinaryOp::Subtract, x, mean, None)?;
+        let scaled = self.binary(node, BinaryOp::Multiply, centered, factor, None)?;
+        Ok(vec![self.binary(
+            node,
+            BinaryOp::Add,
+            scaled,
+            bias,
+            node.output_name(0),
+        )?])
+    }
+
+    /// Normalizes over `axis` and every later axis, which must have static sizes
+    fn layer_normalization(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let name = node.output_name(0);
+        let x = self.value(node, 0)?;
+        let scale = self.value(node, 1)?;
+        let bias = self.optional_value(node, 2)?;
+        let axis = self.axis(node, x, node.int("axis", -1))?;
+        let axes = (axis..self.graph.shape(x).rank()).collect::<Vec<_>>();
+
+        let mean = self.mean(node, x, &axes, None)?;
+        let centered = self.binary(node, BinaryOp::Subtract, x, mean, None)?;
+        let squared = self.unary(node, UnaryOp::Square, centered, None)?;
+        let variance = self.mean(node, squared, &axes, None)?;
+        let epsilon = node.float("epsilon", 1e-5) as f64;
+        let epsilon = self.scalar(node, epsilon, self.graph.data_type(variance))?;
+        let variance = self.binary(node, BinaryOp::Add, variance, epsilon, None)?;
+        let inverse = self.unary(node, UnaryOp::Rsqrt, variance, None)?;
+        let normalized = self.binary(node, BinaryOp::Multiply, centered, inverse, None)?;
+
+        let scaled_name = name.filter(|_| bias.is_none());
+        let scaled = self.binary(node, BinaryOp::Multiply, normalized, scale, scaled_name)?;
+        match bias {
+            Some(bias) => Ok(vec![self.binary(
+                node,
+                BinaryOp::Add,
+                scaled,
+                bias,
+                name,
+            )?]),
+            None => Ok(vec![scaled]),
+        }
+    }
+
+    fn reshape_node(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let x = self.value(node, 0)?;
+        let allow_zero = node.int("allowzero", 0) != 0;
+        let source = self.graph.shape(x).clone();
+        // Unless allowzero is set, 0 copies the source dimension at the same position
+        let shape = self
+            .required_ints(node, 1)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, size)| match (size, source.dims().get(index)) {
+                (0, Some(Dim::Static(size))) if !allow_zero => Ok(*size as i64),
+                (0, Some(Dim::Dynamic)) if !allow_zero => Ok(-1),
+                (0, None) if !allow_zero => Err(node.error(format!(
+                    "0 at position {} copies a dimension {} doesn't have",
+                    index, source
+                ))),
+                (size, _) => Ok(size),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.record(node, OpKind::Reshape { shape }, &[x], node.output_name(0))
+    }
+
+    fn slice(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let x = self.value(node, 0)?;
+        let rank = self.graph.shape(x).rank();
+        let slice_starts = self.required_ints(node, 1)?;
+        let slice_ends = self.required_ints(node, 2)?;
+        let count = slice_starts.len();
+        let axes = self
+            .constant_ints(node, 3)?
+            .unwrap_or_else(|| (0..count as i64).collect());
+        let steps = self
+            .constant_ints(node, 4)?
+            .unwrap_or_else(|| vec![1; count]);
+        if slice_ends.len() != count || axes.len() != count || steps.len() != count {
+            return Err(node.error("starts, ends, axes and steps must have the same length"));
+        }
+
+        // Axes that aren't sliced keep their full range through the masks
+        let mut starts = vec![0; rank];
+        let mut ends = vec![0; rank];
+        let mut strides = vec![1; rank];
+        let full = ((1u64 << rank) - 1) as u32;
+        let mut masks = MPSGraphSliceMasks {
+            start_mask: full,
+            end_mask: full,
+            ..MPSGraphSliceMasks::default()
+        };
+        for index in 0..count {
+            let axis = self.axis(node, x, axes[index])?;
+            starts[axis] = slice_starts[index];
+            ends[axis] = slice_ends[index];
+            strides[axis] = steps[index];
+            masks.start_mask &= !(1 << axis);
+            masks.end_mask &= !(1 << axis);
+        }
+        let kind = OpKind::StridedSlice {
+            starts,
+            ends,
+            strides,
+            masks,
+        };
+        self.record(node, kind, &[x], node.output_name(0))
+    }
+
+    fn split(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let x = self.value(node, 0)?;
+        let axis = node.int("axis", 0);
+        let count = node.node.output.len();
+        match self.constant_ints(node, 1)? {
+            Some(lengths) => {
+                if lengths.len() != count {
+                    return Err(node.error(format!(
+                        "{} split lengths for {} outputs",
+                        lengths.len(),
+                        count
+                    )));
+                }
+                let dimension = self.axis(node, x, axis)?;
+                let mut start = 0;
+                let mut results = Vec::with_capacity(count);
+                for (index, length) in lengths.into_iter().enumerate() {
+                    let kind = OpKind::Slice {
+                        dimension,
+                        start,
+                        length,
+                    };
+                    results.push(self.record_one(node, kind, &[x], node.output_name(index))?);
+                    start += length;
+                }
+                Ok(results)
+            }
+            None => {
+                let num_splits = if self.opset_version >= 18 {
+                    node.int("num_outputs", count as i64) as usize
+                } else {
+                    count
+                };
+                self.record(
+                    node,
+                    OpKind::Split { num_splits, axis },
+                    &[x],
+                    node.output_name(0),
+                )
+            }
+        }
+    }
+
+    fn reduce(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let name = node.output_name(0);
+        let x = self.value(node, 0)?;
+        let shape = self.graph.shape(x).clone();
+        // Opset 18 moved the axes of every reduction from an attribute to an input
+        let axes = match node.ints("axes") {
+            Some(axes) => Some(axes.to_vec()),
+            None => self.constant_ints(node, 1)?,
+        };
+        let axes = match axes {
+            Some(axes) if !axes.is_empty() => shape
+                .normalize_axes(&axes)
+                .map_err(|error| node.error(error.to_string()))?,
+            _ if node.int("noop_with_empty_axes", 0) != 0 => {
+                return Ok(vec![self.unary(node, UnaryOp::Identity, x, name)?]);
+            }
+            _ => (0..shape.rank()).collect(),
+        };
+
+        let keep_dims = node.int("keepdims", 1) != 0;
+        let reduced_name = name.filter(|_| keep_dims);
+        let op = match node.op_type() {
+            "ReduceMean" => None,
+            "ReduceMax" => Some(ReductionOp::Maximum),
+            "ReduceMin" => Some(ReductionOp::Minimum),
+            "ReduceProd" => Some(ReductionOp::Product),
+            _ => Some(ReductionOp::Sum),
+        };
+        let reduced = match op {
+            Some(op) => {
+                let kind = OpKind::Reduction {
+                    op,
+                    axes: axes.clone(),
+                };
+                self.record_one(node, kind, &[x], reduced_name)?
+            }
+            None => self.mean(node, x, &axes, reduced_name)?,
+        };
+        if keep_dims {
+            return Ok(vec![reduced]);
+        }
+        let axes = axes.into_iter().map(|axis| axis as i64).collect();
+        self.record(node, OpKind::Squeeze { axes }, &[reduced], name)
+    }
+
+    fn arg_reduce(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        if node.int("select_last_index", 0) != 0 {
+            return Err(node.error("select_last_index is not supported"));
+        }
+        let x = self.value(node, 0)?;
+        let axis = self.axis(node, x, node.int("axis", 0))?;
+        let op = if node.op_type() == "ArgMax" {
+            ReductionOp::ArgMaximum
+        } else {
+            ReductionOp::ArgMinimum
+        };
+        let kind = OpKind::Reduction {
+            op,
+            axes: vec![axis],
+        };
+        let mut indices = self.record_one(node, kind, &[x], None)?;
+        if node.int("keepdims", 1) == 0 {
+            let kind = OpKind::Squeeze {
+                axes: vec![axis as i64],
+            };
+            indices = self.record_one(node, kind, &[indices], None)?;
+        }
+        // ONNX returns Int64 indices
+        let kind = OpKind::Cast {
+            data_type: MPSDataType::Int64,
+        };
+        self.record(node, kind, &[indices], node.output_name(0))
+    }
+
+    fn top_k(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let x = self.value(node, 0)?;
+        let k = match self.required_ints(node, 1)?.as_slice() {
+            &[k] if k >= 0 => k as usize,
+            k => return Err(node.error(format!("K must be one non-negative value, got {:?}", k))),
+        };
+        let kind = OpKind::TopK {
+            axis: self.axis(node, x, node.int("axis", -1))?,
+            k,
+            largest: node.int("largest", 1) != 0,
+        };
+        let results = self.record(node, kind, &[x], node.output_name(0))?;
+        let kind = OpKind::Cast {
+            data_type: MPSDataType::Int64,
+        };
+        let indices = self.record_one(node, kind, &[results[1]], node.output_name(1))?;
+        Ok(vec![results[0], indices])
+    }
+
+    fn resize(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let x = self.value(node, 0)?;
+        let shape = self.graph.shape(x).clone();
+        if shape.rank() != 4 {
+            return Err(node.error("only rank-4 NCHW inputs are supported"));
+        }
+        if node.int("antialias", 0) != 0 || node.attribute("axes").is_some() {
+            return Err(node.error("antialias and axes are not supported"));
+        }
+
+        let mode = match node.string("mode", "nearest").as_str() {
+            "nearest" => MPSGraphResizeMode::Nearest,
+            "linear" => MPSGraphResizeMode::Bilinear,
+            other => return Err(node.error(format!("mode {} is not supported", other))),
+        };
+        let (center_result, align_corners) = match node
+            .string("coordinate_transformation_mode", "half_pixel")
+            .as_str()
+        {
+            "half_pixel" | "pytorch_half_pixel" => (true, false),
+            "align_corners" => (false, true),
+            "asymmetric" => (false, false),
+            other => {
+                return Err(node.error(format!(
+                    "coordinate_transformation_mode {} is not supported",
+                    other
+                )))
+            }
+        };
+
+        // Either input may be present but empty when the other one is used
+        let sizes = self
+            .constant_ints(node, 3)?
+            .filter(|sizes| !sizes.is_empty());
+        let scales = self
+            .constant_floats(node, 2)?
+            .filter(|scales| !scales.is_empty());
+        let size = match (sizes, scales) {
+            (Some(sizes), _) => {
+                let unchanged = sizes.len() == 4
+                    && (0..2).all(|axis| match shape[axis] {
+                        Dim::Static(size) => size as i64 == sizes[axis],
+                        Dim::Dynamic => true,
+                    });
+                if !unchanged || sizes[2] <= 0 || sizes[3] <= 0 {
+                    return Err(node.error(format!(
+                        "sizes {:?} must keep N and C of {} and have positive spatial sizes",
+                        sizes, shape
+                    )));
+                }
+                [sizes[2] as usize, sizes[3] as usize]
+            }
+            (None, Some(scales)) => {
+                if scales.len() != 4 || scales[0] != 1.0 || scales[1] != 1.0 {
+                    return Err(node.error(format!("scales {:?} must be 1 for N and C", scales)));
+                }
+                let (Dim::Static(height), Dim::Static(width)) = (shape[2], shape[3]) else {
+                    return Err(node.error("scales need static spatial dimensions"));
+                };
+                [
+                    (height as f64 * scales[2]).floor() as usize,
+                    (width as f64 * scales[3]).floor() as usize,
+                ]
+            }
+            (None, None) => return Err(node.error("scales or sizes is required")),
+        };
+
+        let kind = OpKind::Resize {
+            size,
+            mode,
+            center_result,
+            align_corners,
+            data_layout: MPSGraphTensorNamedDataLayout::NCHW,
+        };
+        self.record(node, kind, &[x], node.output_name(0))
+    }
+
+    fn non_max_suppression(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let boxes = self.value(node, 0)?;
+        let scores = self.value(node, 1)?;
+        let first = |values: Option<Vec<f64>>| values.and_then(|values| values.first().copied());
+        let iou_threshold = first(self.constant_floats(node, 3)?).unwrap_or(0.0);
+        let score_threshold = first(self.constant_floats(node, 4)?).unwrap_or(f32::MIN as f64);
+        let coordinate_mode = match node.int("center_point_box", 0) {
+            0 => MPSGraphNonMaximumSuppressionCoordinateMode::CornersHeightFirst,
+            1 => MPSGraphNonMaximumSuppressionCoordinateMode::CentersWidthFirst,
+            other => return Err(node.error(format!("center_point_box {} is not supported", other))),
+        };
+
+        // ONNX scores are [N, classes, boxes], MPSGraph expects [N, boxes, classes]
+        let transpose = OpKind::Transpose {
+            permutation: vec![0, 2, 1],
+        };
+        let scores = self.record_one(node, transpose, &[scores], None)?;
+        let kind = OpKind::NonMaximumSuppression {
+            iou_threshold: iou_threshold as f32,
+            score_threshold: score_threshold as f32,
+            per_class_suppression: true,
+            coordinate_mode,
+        };
+        self.record(node, kind, &[boxes, scores], node.output_name(0))
+    }
+
+    fn constant_node(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let output = node
+            .output_name(0)
+            .ok_or_else(|| node.error("the output is unnamed"))?;
+        let attribute = node
+            .node
+            .attribute
+            .first()
+            .ok_or_else(|| node.error("the value is missing"))?;
+        let value = match attribute.name.as_str() {
+            "value" => match &attribute.t {
+                Some(tensor) => ConstantValue::from_proto(tensor)?,
+                None => return Err(node.error("the value tensor is missing")),
+            },
+            "value_float" => ConstantValue::from_f32s(&[attribute.f], vec![]),
+            "value_floats" => {
+                ConstantValue::from_f32s(&attribute.floats, vec![attribute.floats.len()])
+            }
+            "value_int" => ConstantValue::from_i64s(&[attribute.i], vec![]),
+            "value_ints" => ConstantValue::from_i64s(&attribute.ints, vec![attribute.ints.len()]),
+            other => return Err(node.error(format!("{} constants are not supported", other))),
+        };
+        Ok(vec![self.add_constant(output, value)?])
+    }
+
+    /// Folds the shape of the input into an Int64 constant
+    fn shape_node(&mut self, node: &NodeContext) -> Result<Vec<TensorId>> {
+        let output = node
+            .output_name(0)
+            .ok_or_else(|| node.error("the output is unnamed"))?;
+        let x = self.value(node, 0)?;
+        let shape = self.graph.shape(x).clone();
+        let rank = shape.rank() as i64;
+        let clamp = |index: i64| {
+            let index = if index < 0 { index + rank } else { index };
+            index.clamp(0, rank) as usize
+        };
+        let start = clamp(node.int("start", 0));
+        let end = clamp(node.int("end", rank)).max(start);
+        let dims = shape.dims()[start..end]
+            .iter()
+            .map(|dim| match dim {
+                Dim::Static(size) => Ok(*size as i64),
+                Dim::Dynamic => Err(node.error(format!("{} has dynamic dimensions", shape))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let value = ConstantValue::from_i64s(&dims, vec![dims.len()]);
+        Ok(vec![self.add_constant(output, value)?])
+    }
+}
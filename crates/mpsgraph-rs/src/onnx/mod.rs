@@ -0,0 +1,379 @@
+//! ONNX model import.
+//!
+//! [`OnnxModel`] parses an ONNX protobuf that uses the default operator set at version 13
+//! or later, and [`OnnxModel::import`] maps its nodes onto a recorded
+//! [`ir::Graph`](crate::ir::Graph): graph inputs become placeholders, initializers and
+//! `Constant` nodes become constants, and each node becomes one or more IR operations.
+//! Since the result is the portable IR, parsing, mapping and shape checking run on any
+//! host; on Apple targets [`Graph::lower_to_mpsgraph`](crate::ir::Graph::lower_to_mpsgraph)
+//! then builds the `MPSGraph`.
+//!
+//! A model that uses operators without a mapping is rejected with an [`UnsupportedReport`]
+//! listing every such node, and [`OnnxModel::unsupported_ops`] produces the same report
+//! without importing. [`supported_op_types`] lists the operators that are mapped.
+//!
+//! A few mappings differ from the ONNX definition:
+//!
+//! - `NonMaxSuppression` returns `MPSGraph`'s Int32 indices of the selected boxes per
+//!   batch, not ONNX's `[selected, 3]` Int64 triplets, and ignores
+//!   `max_output_boxes_per_class`.
+//! - `Resize` ignores `nearest_mode` and uses `MPSGraph`'s rounding.
+//! - Inputs that ONNX allows to be computed, such as the target of `Reshape` or the `K` of
+//!   `TopK`, must be initializers or `Constant` outputs.
+//!
+//! ```
+//! use mpsgraph::onnx::OnnxModel;
+//!
+//! # fn import(bytes: &[u8]) -> mpsgraph::onnx::Result<()> {
+//! let model = OnnxModel::from_bytes(bytes)?;
+//! let report = model.unsupported_ops();
+//! if !report.is_empty() {
+//!     eprintln!("{}", report);
+//! }
+//! let import = model.import()?;
+//! for (name, id) in import.outputs() {
+//!     println!("{}: {}", name, import.graph().shape(*id));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod import;
+pub mod proto;
+
+use crate::core::MPSDataType;
+use crate::ir::{Graph, TensorId};
+use prost::Message;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Oldest version of the default operator set the importer accepts
+pub const MIN_OPSET_VERSION: i64 = 13;
+
+/// Errors reported while parsing or importing an ONNX model
+#[derive(Debug)]
+pub enum OnnxError {
+    /// Reading the model file failed
+    Io(io::Error),
+    /// The protobuf is malformed
+    Decode(String),
+    /// The model has no graph
+    MissingGraph,
+    /// The default operator set is missing or older than [`MIN_OPSET_VERSION`]
+    UnsupportedOpset(i64),
+    /// The graph uses operators without a mapping
+    Unsupported(UnsupportedReport),
+    /// The ONNX element type has no `MPSDataType` counterpart
+    UnsupportedDataType(i32),
+    /// An initializer or constant keeps its data outside the model file
+    ExternalData(String),
+    /// An initializer or constant is malformed
+    InvalidTensor {
+        /// Name of the tensor
+        name: String,
+        /// What is wrong with it
+        reason: String,
+    },
+    /// A graph input or output has no usable tensor type
+    InvalidValueInfo {
+        /// Name of the value
+        name: String,
+        /// What is wrong with it
+        reason: String,
+    },
+    /// A node or graph output refers to a value that nothing produces
+    UnknownValue(String),
+    /// A node can't be mapped, for example because of an attribute value or operand shape
+    Node {
+        /// Name of the node, or of its first output if it is unnamed
+        node: String,
+        /// ONNX operator type
+        op_type: String,
+        /// Why the node was rejected
+        reason: String,
+    },
+}
+
+impl fmt::Display for OnnxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OnnxError::Io(error) => write!(f, "onnx i/o error: {}", error),
+            OnnxError::Decode(reason) => write!(f, "invalid onnx protobuf: {}", reason),
+            OnnxError::MissingGraph => write!(f, "onnx model has no graph"),
+            OnnxError::UnsupportedOpset(version) => write!(
+                f,
+                "onnx opset {} is not supported, the importer needs {} or later",
+                version, MIN_OPSET_VERSION
+            ),
+            OnnxError::Unsupported(report) => write!(f, "{}", report),
+            OnnxError::UnsupportedDataType(code) => {
+                write!(
+                    f,
+                    "onnx element type {} has no MPSDataType counterpart",
+                    code
+                )
+            }
+            OnnxError::ExternalData(name) => {
+                write!(
+                    f,
+                    "tensor '{}' uses external data, which is not supported",
+                    name
+                )
+            }
+            OnnxError::InvalidTensor { name, reason } => {
+                write!(f, "invalid tensor '{}': {}", name, reason)
+            }
+            OnnxError::InvalidValueInfo { name, reason } => {
+                write!(f, "invalid type for '{}': {}", name, reason)
+            }
+            OnnxError::UnknownValue(name) => write!(f, "no node produces '{}'", name),
+            OnnxError::Node {
+                node,
+                op_type,
+                reason,
+            } => write!(f, "{} node '{}': {}", op_type, node, reason),
+        }
+    }
+}
+
+impl std::error::Error for OnnxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OnnxError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for OnnxError {
+    fn from(error: io::Error) -> Self {
+        OnnxError::Io(error)
+    }
+}
+
+impl From<prost::DecodeError> for OnnxError {
+    fn from(error: prost::DecodeError) -> Self {
+        OnnxError::Decode(error.to_string())
+    }
+}
+
+/// Result type returned by the ONNX functions
+pub type Result<T> = std::result::Result<T, OnnxError>;
+
+/// Returns the data type for an ONNX `TensorProto.DataType` code
+pub fn data_type(code: i32) -> Result<MPSDataType> {
+    use proto::data_type::*;
+    Ok(match code {
+        FLOAT => MPSDataType::Float32,
+        UINT8 => MPSDataType::UInt8,
+        INT8 => MPSDataType::Int8,
+        UINT16 => MPSDataType::UInt16,
+        INT16 => MPSDataType::Int16,
+        INT32 => MPSDataType::Int32,
+        INT64 => MPSDataType::Int64,
+        BOOL => MPSDataType::Bool,
+        FLOAT16 => MPSDataType::Float16,
+        DOUBLE => MPSDataType::Float64,
+        UINT32 => MPSDataType::UInt32,
+        UINT64 => MPSDataType::UInt64,
+        COMPLEX64 => MPSDataType::Complex32,
+        COMPLEX128 => MPSDataType::Complex64,
+        BFLOAT16 => MPSDataType::BFloat16,
+        code => return Err(OnnxError::UnsupportedDataType(code)),
+    })
+}
+
+/// Returns the ONNX operator types the importer maps, in alphabetical order
+pub fn supported_op_types() -> &'static [&'static str] {
+    import::SUPPORTED_OP_TYPES
+}
+
+/// A node whose operator has no mapping
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedNode {
+    /// Name of the node, or of its first output if it is unnamed
+    pub name: String,
+    /// ONNX operator type
+    pub op_type: String,
+    /// Operator domain; empty for the default domain
+    pub domain: String,
+}
+
+impl UnsupportedNode {
+    /// Returns the operator type, qualified with its domain outside the default domain
+    pub fn qualified_op_type(&self) -> String {
+        if self.domain.is_empty() || self.domain == "ai.onnx" {
+            self.op_type.clone()
+        } else {
+            format!("{}.{}", self.domain, self.op_type)
+        }
+    }
+}
+
+/// Every node of a model whose operator has no mapping
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnsupportedReport {
+    /// The unsupported nodes, in graph order
+    pub nodes: Vec<UnsupportedNode>,
+}
+
+impl UnsupportedReport {
+    /// Returns true if every node can be mapped
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns how many nodes use each unsupported operator, keyed by qualified type
+    pub fn op_types(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for node in &self.nodes {
+            *counts.entry(node.qualified_op_type()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+impl fmt::Display for UnsupportedReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} unsupported onnx node(s):", self.nodes.len())?;
+        for (op_type, count) in self.op_types() {
+            let names = self
+                .nodes
+                .iter()
+                .filter(|node| node.qualified_op_type() == op_type)
+                .map(|node| node.name.as_str())
+                .collect::<Vec<_>>();
+            write!(f, "\n  {} x{}: {}", op_type, count, names.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A parsed ONNX model
+#[derive(Debug, Clone)]
+pub struct OnnxModel {
+    model: proto::ModelProto,
+    opset_version: i64,
+}
+
+impl OnnxModel {
+    /// Parses a serialized `ModelProto`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::from_proto(proto::ModelProto::decode(bytes)?)
+    }
+
+    /// Reads and parses an `.onnx` file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Wraps a decoded `ModelProto` after checking its graph and operator set
+    pub fn from_proto(model: proto::ModelProto) -> Result<Self> {
+        if model.graph.is_none() {
+            return Err(OnnxError::MissingGraph);
+        }
+        let opset_version = model
+            .opset_import
+            .iter()
+            .find(|opset| opset.domain.is_empty() || opset.domain == "ai.onnx")
+            .map_or(0, |opset| opset.version);
+        if opset_version < MIN_OPSET_VERSION {
+            return Err(OnnxError::UnsupportedOpset(opset_version));
+        }
+        Ok(OnnxModel {
+            model,
+            opset_version,
+        })
+    }
+
+    /// Returns the decoded protobuf
+    pub fn proto(&self) -> &proto::ModelProto {
+        &self.model
+    }
+
+    /// Returns the version of the default operator set
+    pub fn opset_version(&self) -> i64 {
+        self.opset_version
+    }
+
+    /// Returns the ONNX IR version of the file format
+    pub fn ir_version(&self) -> i64 {
+        self.model.ir_version
+    }
+
+    /// Returns the name of the tool that produced the model
+    pub fn producer_name(&self) -> &str {
+        &self.model.producer_name
+    }
+
+    fn graph(&self) -> &proto::GraphProto {
+        self.model.graph.as_ref().expect("checked in from_proto")
+    }
+
+    /// Lists the nodes whose operators have no mapping
+    pub fn unsupported_ops(&self) -> UnsupportedReport {
+        UnsupportedReport {
+            nodes: self
+                .graph()
+                .node
+                .iter()
+                .filter(|node| !import::is_supported(node))
+                .map(|node| UnsupportedNode {
+                    name: import::node_name(node).to_string(),
+                    op_type: node.op_type.clone(),
+                    domain: node.domain.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Maps the model onto a recorded graph
+    ///
+    /// Fails with [`OnnxError::Unsupported`] before recording anything if any node has no
+    /// mapping.
+    pub fn import(&self) -> Result<Import> {
+        let report = self.unsupported_ops();
+        if !report.is_empty() {
+            return Err(OnnxError::Unsupported(report));
+        }
+        import::import(self.graph(), self.opset_version)
+    }
+}
+
+/// The result of importing an ONNX model
+#[derive(Debug, Clone)]
+pub struct Import {
+    graph: Graph,
+    inputs: Vec<(String, TensorId)>,
+    outputs: Vec<(String, TensorId)>,
+    values: HashMap<String, TensorId>,
+}
+
+impl Import {
+    /// Returns the recorded graph
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    /// Consumes the import and returns the recorded graph
+    pub fn into_graph(self) -> Graph {
+        self.graph
+    }
+
+    /// Returns the placeholders for the model inputs, in model order
+    pub fn inputs(&self) -> &[(String, TensorId)] {
+        &self.inputs
+    }
+
+    /// Returns the tensors for the model outputs, in model order
+    pub fn outputs(&self) -> &[(String, TensorId)] {
+        &self.outputs
+    }
+
+    /// Returns the tensor recorded for an ONNX value name
+    pub fn tensor(&self, name: &str) -> Option<TensorId> {
+        self.values.get(name).copied()
+    }
+}
@@ -1,14 +1,24 @@
-use crate::graph::MPSGraph;
+use crate::graph::{GraphOwner, MPSGraph};
 use crate::tensor::MPSGraphTensor;
 use objc2::msg_send;
 use objc2::runtime::AnyObject;
 use std::fmt;
-use std::ptr;
 
 /// A wrapper for MPSGraphOperation objects
-pub struct MPSGraphOperation(pub(crate) *mut AnyObject);
+///
+/// Like [`MPSGraphTensor`], the wrapper owns one retain on the operation and one on its graph.
+pub struct MPSGraphOperation(pub(crate) *mut AnyObject, GraphOwner);
 
 impl MPSGraphOperation {
+    /// Wraps an operation the caller already holds a +1 retain on, taking over that retain
+    ///
+    /// # Safety
+    ///
+    /// `operation` must be null or point to a live `MPSGraphOperation` retained by the caller.
+    pub(crate) unsafe fn from_retained(operation: *mut AnyObject) -> Self {
+        MPSGraphOperation(operation, GraphOwner::of_operation(operation))
+    }
+
     /// Returns the input tensors of this operation
     pub fn input_tensors(&self) -> Vec<MPSGraphTensor> {
         unsafe {
@@ -19,7 +29,7 @@ impl MPSGraphOperation {
             for i in 0..count {
                 let tensor: *mut AnyObject = msg_send![input_tensors, objectAtIndex: i,];
                 let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-                result.push(MPSGraphTensor(tensor));
+                result.push(MPSGraphTensor::from_retained(tensor));
             }
 
             result
@@ -36,7 +46,7 @@ impl MPSGraphOperation {
             for i in 0..count {
                 let tensor: *mut AnyObject = msg_send![output_tensors, objectAtIndex: i,];
                 let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-                result.push(MPSGraphTensor(tensor));
+                result.push(MPSGraphTensor::from_retained(tensor));
             }
 
             result
@@ -48,7 +58,7 @@ impl MPSGraphOperation {
         unsafe {
             let graph: *mut AnyObject = msg_send![self.0, graph];
             let graph = objc2::ffi::objc_retain(graph as *mut _);
-            MPSGraph(graph)
+            MPSGraph::from_retained(graph)
         }
     }
 
@@ -71,7 +81,7 @@ impl MPSGraphOperation {
             for i in 0..count {
                 let op: *mut AnyObject = msg_send![dependencies, objectAtIndex: i,];
                 let op = objc2::ffi::objc_retain(op as *mut _);
-                result.push(MPSGraphOperation(op));
+                result.push(MPSGraphOperation::from_retained(op));
             }
 
             result
@@ -93,12 +103,10 @@ impl Clone for MPSGraphOperation {
     fn clone(&self) -> Self {
         unsafe {
             if !self.0.is_null() {
-                let obj = objc2::ffi::objc_retain(self.0 as *mut _);
-                MPSGraphOperation(obj)
-            } else {
-                MPSGraphOperation(ptr::null_mut())
+                objc2::ffi::objc_retain(self.0 as *mut _);
             }
         }
+        MPSGraphOperation(self.0, self.1.clone())
     }
 }
 
@@ -19,7 +19,7 @@ impl MPSGraphVariableOp {
         unsafe {
             let op: *mut AnyObject = msg_send![self.0, operation];
             let op = objc2::ffi::objc_retain(op as *mut _);
-            MPSGraphOperation(op)
+            MPSGraphOperation::from_retained(op)
         }
     }
 
@@ -28,7 +28,7 @@ impl MPSGraphVariableOp {
         unsafe {
             let tensor: *mut AnyObject = msg_send![self.0, tensor];
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 }
@@ -108,7 +108,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -235,7 +235,7 @@ impl MPSGraph {
             for i in 0..count {
                 let tensor: *mut AnyObject = msg_send![result_array, objectAtIndex: i];
                 let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-                result.push(MPSGraphTensor(tensor));
+                result.push(MPSGraphTensor::from_retained(tensor));
             }
 
             result
@@ -319,7 +319,7 @@ impl MPSGraph {
             for i in 0..count {
                 let tensor: *mut AnyObject = msg_send![result_array, objectAtIndex: i];
                 let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-                result.push(MPSGraphTensor(tensor));
+                result.push(MPSGraphTensor::from_retained(tensor));
             }
 
             result
@@ -417,7 +417,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 }
@@ -1,14 +1,21 @@
+#[cfg(target_vendor = "apple")]
 use crate::core::{create_ns_array_from_i64_slice, AsRawObject, MPSDataType};
+#[cfg(target_vendor = "apple")]
 use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
 use crate::tensor::MPSGraphTensor;
+#[cfg(target_vendor = "apple")]
 use objc2::msg_send;
+#[cfg(target_vendor = "apple")]
 use objc2::runtime::AnyObject;
+#[cfg(target_vendor = "apple")]
 use objc2_foundation::NSString;
+#[cfg(target_vendor = "apple")]
 use std::ptr;
 
 /// Return indices mode for max pooling operations
 #[repr(u64)]
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum MPSGraphPoolingReturnIndicesMode {
     /// No indices returned
     None = 0,
@@ -32,7 +39,7 @@ pub enum MPSGraphPoolingReturnIndicesMode {
 
 /// Data layout for 2D tensor operations
 #[repr(u64)]
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum MPSGraphTensorNamedDataLayout {
     /// NCHW layout (batch, channels, height, width)
     NCHW = 0,
@@ -42,29 +49,36 @@ pub enum MPSGraphTensorNamedDataLayout {
 
 /// Padding style for tensor operations
 #[repr(u64)]
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum MPSGraphPaddingStyle {
     /// Explicit padding with specified values
     Explicit = 0,
-    /// Same padding (input and output have same dimensions)
-    TfSame = 1,
     /// Valid padding (no padding)
-    TfValid = 2,
+    TfValid = 1,
+    /// Same padding (the output size is the input size divided by the stride, rounded up)
+    TfSame = 2,
 }
 
 /// The descriptor for 2D pooling operations
+#[cfg(target_vendor = "apple")]
 pub struct MPSGraphPooling2DOpDescriptor(pub(crate) *mut AnyObject);
 
 /// The descriptor for 4D pooling operations
+#[cfg(target_vendor = "apple")]
 pub struct MPSGraphPooling4DOpDescriptor(pub(crate) *mut AnyObject);
 
 // Implement Send + Sync for thread safety
+#[cfg(target_vendor = "apple")]
 unsafe impl Send for MPSGraphPooling2DOpDescriptor {}
+#[cfg(target_vendor = "apple")]
 unsafe impl Sync for MPSGraphPooling2DOpDescriptor {}
 
+#[cfg(target_vendor = "apple")]
 unsafe impl Send for MPSGraphPooling4DOpDescriptor {}
+#[cfg(target_vendor = "apple")]
 unsafe impl Sync for MPSGraphPooling4DOpDescriptor {}
 
+#[cfg(target_vendor = "apple")]
 impl Drop for MPSGraphPooling2DOpDescriptor {
     fn drop(&mut self) {
         unsafe {
@@ -75,6 +89,7 @@ impl Drop for MPSGraphPooling2DOpDescriptor {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl Clone for MPSGraphPooling2DOpDescriptor {
     fn clone(&self) -> Self {
         unsafe {
@@ -88,6 +103,7 @@ impl Clone for MPSGraphPooling2DOpDescriptor {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl Drop for MPSGraphPooling4DOpDescriptor {
     fn drop(&mut self) {
         unsafe {
@@ -98,6 +114,7 @@ impl Drop for MPSGraphPooling4DOpDescriptor {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl Clone for MPSGraphPooling4DOpDescriptor {
     fn clone(&self) -> Self {
         unsafe {
@@ -111,6 +128,7 @@ impl Clone for MPSGraphPooling4DOpDescriptor {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl MPSGraphPooling2DOpDescriptor {
     /// Creates a new 2D pooling descriptor with the given parameters
     pub fn new(
@@ -221,6 +239,7 @@ impl MPSGraphPooling2DOpDescriptor {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl MPSGraphPooling4DOpDescriptor {
     /// Creates a new 4D pooling descriptor with the given parameters
     pub fn new(
@@ -333,6 +352,7 @@ impl MPSGraphPooling4DOpDescriptor {
 }
 
 /// 2D and 4D pooling operations for MPSGraph
+#[cfg(target_vendor = "apple")]
 impl MPSGraph {
     /// Creates a 2D max pooling operation
     pub fn max_pooling_2d(
@@ -355,7 +375,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -397,8 +417,8 @@ impl MPSGraph {
             objc2::ffi::objc_release(result as *mut _);
 
             (
-                MPSGraphTensor(pooling_tensor),
-                MPSGraphTensor(indices_tensor),
+                MPSGraphTensor::from_retained(pooling_tensor),
+                MPSGraphTensor::from_retained(indices_tensor),
             )
         }
     }
@@ -426,7 +446,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -460,7 +480,7 @@ impl MPSGraph {
             objc2::ffi::objc_release(shape_array as *mut _);
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -489,7 +509,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -514,7 +534,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -541,7 +561,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -566,7 +586,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -593,7 +613,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -618,7 +638,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -660,8 +680,8 @@ impl MPSGraph {
             objc2::ffi::objc_release(result as *mut _);
 
             (
-                MPSGraphTensor(pooling_tensor),
-                MPSGraphTensor(indices_tensor),
+                MPSGraphTensor::from_retained(pooling_tensor),
+                MPSGraphTensor::from_retained(indices_tensor),
             )
         }
     }
@@ -689,7 +709,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -723,7 +743,7 @@ impl MPSGraph {
             objc2::ffi::objc_release(shape_array as *mut _);
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -752,7 +772,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -777,7 +797,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -804,7 +824,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -829,7 +849,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -856,7 +876,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 }
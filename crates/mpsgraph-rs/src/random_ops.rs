@@ -133,7 +133,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -158,7 +158,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -183,7 +183,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -210,7 +210,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -250,7 +250,7 @@ impl MPSGraph {
             let random_tensor = objc2::ffi::objc_retain(random_tensor as *mut _);
             let updated_state = objc2::ffi::objc_retain(updated_state as *mut _);
 
-            (MPSGraphTensor(random_tensor), MPSGraphTensor(updated_state))
+            (MPSGraphTensor::from_retained(random_tensor), MPSGraphTensor::from_retained(updated_state))
         }
     }
 
@@ -269,7 +269,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -294,7 +294,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -332,7 +332,7 @@ impl MPSGraph {
             let random_tensor = objc2::ffi::objc_retain(random_tensor as *mut _);
             let updated_state = objc2::ffi::objc_retain(updated_state as *mut _);
 
-            (MPSGraphTensor(random_tensor), MPSGraphTensor(updated_state))
+            (MPSGraphTensor::from_retained(random_tensor), MPSGraphTensor::from_retained(updated_state))
         }
     }
 
@@ -355,7 +355,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -378,7 +378,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 }
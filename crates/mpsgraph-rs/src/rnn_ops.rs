@@ -608,7 +608,7 @@ impl MPSGraph {
             for i in 0..count {
                 let tensor: *mut AnyObject = msg_send![result, objectAtIndex: i];
                 let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-                tensors.push(MPSGraphTensor(tensor));
+                tensors.push(MPSGraphTensor::from_retained(tensor));
             }
 
             objc2::ffi::objc_release(result as *mut _);
@@ -693,7 +693,7 @@ impl MPSGraph {
             for i in 0..count {
                 let tensor: *mut AnyObject = msg_send![result, objectAtIndex: i];
                 let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-                tensors.push(MPSGraphTensor(tensor));
+                tensors.push(MPSGraphTensor::from_retained(tensor));
             }
 
             objc2::ffi::objc_release(result as *mut _);
@@ -759,7 +759,7 @@ impl MPSGraph {
             for i in 0..count {
                 let tensor: *mut AnyObject = msg_send![result, objectAtIndex: i];
                 let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-                tensors.push(MPSGraphTensor(tensor));
+                tensors.push(MPSGraphTensor::from_retained(tensor));
             }
 
             objc2::ffi::objc_release(result as *mut _);
@@ -855,7 +855,7 @@ impl MPSGraph {
             for i in 0..count {
                 let tensor: *mut AnyObject = msg_send![result, objectAtIndex: i];
                 let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-                tensors.push(MPSGraphTensor(tensor));
+                tensors.push(MPSGraphTensor::from_retained(tensor));
             }
 
             objc2::ffi::objc_release(result as *mut _);
@@ -928,9 +928,9 @@ impl MPSGraph {
                 objc2::ffi::objc_retain(output_cell_state_tensor as *mut _);
 
             (
-                MPSGraphTensor(output_tensor),
-                MPSGraphTensor(output_hidden_state_tensor),
-                MPSGraphTensor(output_cell_state_tensor),
+                MPSGraphTensor::from_retained(output_tensor),
+                MPSGraphTensor::from_retained(output_hidden_state_tensor),
+                MPSGraphTensor::from_retained(output_cell_state_tensor),
             )
         }
     }
@@ -993,8 +993,8 @@ impl MPSGraph {
             let output_state_tensor = objc2::ffi::objc_retain(output_state_tensor as *mut _);
 
             (
-                MPSGraphTensor(output_tensor),
-                MPSGraphTensor(output_state_tensor),
+                MPSGraphTensor::from_retained(output_tensor),
+                MPSGraphTensor::from_retained(output_state_tensor),
             )
         }
     }
@@ -0,0 +1,618 @@
+//! Safetensors weight loading and checkpoint writing.
+//!
+//! [`SafetensorsFile`] memory-maps a `.safetensors` file (or takes ownership of its bytes),
+//! parses the header and maps each entry's dtype onto an [`MPSDataType`].
+//! [`SafetensorsFile::resolve`] applies the renames and casts of [`LoadOptions`] on the
+//! host; on Apple targets [`SafetensorsFile::load_into`] then materializes the entries as
+//! graph constants or variables, keyed by name, and [`save_variables`] reads variables back
+//! out of a graph to write a checkpoint.
+
+use crate::cast;
+use crate::core::MPSDataType;
+use crate::error::MPSGraphError;
+use ::safetensors::tensor::{Metadata, TensorInfo};
+use ::safetensors::{SafeTensorError, SafeTensors, View};
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+pub use ::safetensors::Dtype;
+
+#[cfg(target_vendor = "apple")]
+use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
+use crate::shape::MPSShape;
+#[cfg(target_vendor = "apple")]
+use crate::tensor::MPSGraphTensor;
+#[cfg(target_vendor = "apple")]
+use crate::tensor_data::MPSGraphTensorData;
+
+/// Errors reported while reading or writing safetensors files
+#[derive(Debug)]
+pub enum SafetensorsError {
+    /// Reading or writing the underlying file failed
+    Io(io::Error),
+    /// The header or the data layout is malformed
+    Format(String),
+    /// The safetensors dtype has no `MPSDataType` counterpart
+    UnsupportedDtype(Dtype),
+    /// The data type has no safetensors dtype
+    UnsupportedDataType(MPSDataType),
+    /// A cast on load isn't between floating-point types
+    UnsupportedCast {
+        /// Data type stored in the file
+        from: MPSDataType,
+        /// Data type requested in the graph
+        to: MPSDataType,
+    },
+    /// The file has no tensor with this name
+    MissingTensor(String),
+    /// Two tensors were renamed to the same name
+    DuplicateName(String),
+    /// The buffer size doesn't match the shape and data type
+    SizeMismatch {
+        /// Bytes the shape and data type require
+        expected: usize,
+        /// Bytes supplied
+        actual: usize,
+    },
+    /// Running the graph to read variables failed
+    Graph(MPSGraphError),
+}
+
+impl fmt::Display for SafetensorsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SafetensorsError::Io(error) => write!(f, "safetensors i/o error: {}", error),
+            SafetensorsError::Format(reason) => write!(f, "invalid safetensors file: {}", reason),
+            SafetensorsError::UnsupportedDtype(dtype) => {
+                write!(
+                    f,
+                    "safetensors dtype {:?} has no MPSDataType counterpart",
+                    dtype
+                )
+            }
+            SafetensorsError::UnsupportedDataType(data_type) => {
+                write!(f, "{:?} has no safetensors dtype", data_type)
+            }
+            SafetensorsError::UnsupportedCast { from, to } => write!(
+                f,
+                "cannot cast {:?} to {:?}: only floating-point casts are supported",
+                from, to
+            ),
+            SafetensorsError::MissingTensor(name) => write!(f, "no tensor named '{}'", name),
+            SafetensorsError::DuplicateName(name) => {
+                write!(f, "more than one tensor is named '{}'", name)
+            }
+            SafetensorsError::SizeMismatch { expected, actual } => write!(
+                f,
+                "buffer holds {} bytes but the shape and data type need {}",
+                actual, expected
+            ),
+            SafetensorsError::Graph(error) => write!(f, "reading variables failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for SafetensorsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SafetensorsError::Io(error) => Some(error),
+            SafetensorsError::Graph(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SafetensorsError {
+    fn from(error: io::Error) -> Self {
+        SafetensorsError::Io(error)
+    }
+}
+
+impl From<SafeTensorError> for SafetensorsError {
+    fn from(error: SafeTensorError) -> Self {
+        match error {
+            SafeTensorError::IoError(error) => SafetensorsError::Io(error),
+            error => SafetensorsError::Format(error.to_string()),
+        }
+    }
+}
+
+impl From<MPSGraphError> for SafetensorsError {
+    fn from(error: MPSGraphError) -> Self {
+        SafetensorsError::Graph(error)
+    }
+}
+
+/// Result type returned by the safetensors functions
+pub type Result<T> = std::result::Result<T, SafetensorsError>;
+
+/// Returns the safetensors dtype for a data type
+///
+/// Returns `None` for types safetensors can't represent, such as the complex, 4-bit and
+/// normalized types.
+pub fn dtype(data_type: MPSDataType) -> Option<Dtype> {
+    Some(match data_type {
+        MPSDataType::Bool => Dtype::BOOL,
+        MPSDataType::UInt8 => Dtype::U8,
+        MPSDataType::Int8 => Dtype::I8,
+        MPSDataType::Int16 => Dtype::I16,
+        MPSDataType::UInt16 => Dtype::U16,
+        MPSDataType::Float16 => Dtype::F16,
+        MPSDataType::BFloat16 => Dtype::BF16,
+        MPSDataType::Int32 => Dtype::I32,
+        MPSDataType::UInt32 => Dtype::U32,
+        MPSDataType::Float32 => Dtype::F32,
+        MPSDataType::Float64 => Dtype::F64,
+        MPSDataType::Int64 => Dtype::I64,
+        MPSDataType::UInt64 => Dtype::U64,
+        _ => return None,
+    })
+}
+
+/// Returns the data type for a safetensors dtype
+///
+/// The 8-bit float formats have no `MPSDataType` counterpart.
+pub fn data_type(dtype: Dtype) -> Result<MPSDataType> {
+    Ok(match dtype {
+        Dtype::BOOL => MPSDataType::Bool,
+        Dtype::U8 => MPSDataType::UInt8,
+        Dtype::I8 => MPSDataType::Int8,
+        Dtype::I16 => MPSDataType::Int16,
+        Dtype::U16 => MPSDataType::UInt16,
+        Dtype::F16 => MPSDataType::Float16,
+        Dtype::BF16 => MPSDataType::BFloat16,
+        Dtype::I32 => MPSDataType::Int32,
+        Dtype::U32 => MPSDataType::UInt32,
+        Dtype::F32 => MPSDataType::Float32,
+        Dtype::F64 => MPSDataType::Float64,
+        Dtype::I64 => MPSDataType::Int64,
+        Dtype::U64 => MPSDataType::UInt64,
+        dtype => return Err(SafetensorsError::UnsupportedDtype(dtype)),
+    })
+}
+
+/// A borrowed tensor: little-endian bytes in C order with a shape and data type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TensorView<'a> {
+    data_type: MPSDataType,
+    shape: &'a [usize],
+    data: &'a [u8],
+}
+
+impl<'a> TensorView<'a> {
+    /// Creates a view, checking that the buffer matches the shape and data type
+    pub fn new(data_type: MPSDataType, shape: &'a [usize], data: &'a [u8]) -> Result<Self> {
+        let expected = shape.iter().product::<usize>() * data_type.size_in_bytes();
+        if data.len() != expected {
+            return Err(SafetensorsError::SizeMismatch {
+                expected,
+                actual: data.len(),
+            });
+        }
+        Ok(TensorView {
+            data_type,
+            shape,
+            data,
+        })
+    }
+
+    /// Returns the data type of the tensor
+    pub fn data_type(&self) -> MPSDataType {
+        self.data_type
+    }
+
+    /// Returns the shape of the tensor
+    pub fn shape(&self) -> &'a [usize] {
+        self.shape
+    }
+
+    /// Returns the little-endian bytes of the tensor in C order
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+/// How loaded tensors enter the graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WeightKind {
+    /// Immutable constants created with `MPSGraph::constant`
+    #[default]
+    Constant,
+    /// Named variables created with `MPSGraph::variable`, which can be assigned and read back
+    Variable,
+}
+
+/// Maps a name in the file to a name in the graph, or `None` to skip the tensor
+type Rename = Box<dyn Fn(&str) -> Option<String>>;
+
+/// Renames and casts applied while loading a safetensors file
+#[derive(Default)]
+pub struct LoadOptions {
+    kind: WeightKind,
+    rename: Option<Rename>,
+    casts: Vec<(MPSDataType, MPSDataType)>,
+}
+
+impl LoadOptions {
+    /// Loads every tensor as a constant under its own name, without casts
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether tensors become constants or variables
+    pub fn with_kind(mut self, kind: WeightKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Maps file names to graph names; tensors for which `rename` returns `None` are skipped
+    pub fn with_rename(mut self, rename: impl Fn(&str) -> Option<String> + 'static) -> Self {
+        self.rename = Some(Box::new(rename));
+        self
+    }
+
+    /// Converts tensors stored as `from` to `to`, such as `Float32` to `Float16`
+    pub fn with_cast(mut self, from: MPSDataType, to: MPSDataType) -> Self {
+        self.casts.retain(|(existing, _)| *existing != from);
+        self.casts.push((from, to));
+        self
+    }
+
+    /// Returns whether tensors become constants or variables
+    pub fn kind(&self) -> WeightKind {
+        self.kind
+    }
+}
+
+impl fmt::Debug for LoadOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadOptions")
+            .field("kind", &self.kind)
+            .field("rename", &self.rename.is_some())
+            .field("casts", &self.casts)
+            .finish()
+    }
+}
+
+/// A tensor after renaming and casting, ready to be added to a graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedTensor<'a> {
+    /// Name of the tensor in the graph
+    pub name: String,
+    /// Data type of the tensor in the graph
+    pub data_type: MPSDataType,
+    /// Shape of the tensor
+    pub shape: Vec<usize>,
+    /// Little-endian bytes in C order, borrowed from the file unless a cast applied
+    pub data: Cow<'a, [u8]>,
+}
+
+enum Storage {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Storage {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Storage::Mapped(map) => map,
+            Storage::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// A parsed `.safetensors` file
+pub struct SafetensorsFile {
+    storage: Storage,
+    data_start: usize,
+    entries: Vec<(String, TensorInfo)>,
+    metadata: BTreeMap<String, String>,
+}
+
+impl SafetensorsFile {
+    /// Memory-maps and parses a safetensors file
+    ///
+    /// Tensor data is read lazily from the mapping. The file must not be modified while the
+    /// returned value is alive.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is read-only and callers must not truncate or rewrite the
+        // file while it is mapped, as documented above
+        let map = unsafe { Mmap::map(&file)? };
+        Self::parse(Storage::Mapped(map))
+    }
+
+    /// Parses a safetensors file held in memory
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        Self::parse(Storage::Owned(bytes))
+    }
+
+    fn parse(storage: Storage) -> Result<Self> {
+        let (header_len, metadata): (usize, Metadata) =
+            SafeTensors::read_metadata(storage.bytes())?;
+        let mut entries: Vec<(String, TensorInfo)> = metadata
+            .tensors()
+            .into_iter()
+            .map(|(name, info)| (name, info.clone()))
+            .collect();
+        entries.sort_by_key(|(_, info)| info.data_offsets);
+        let metadata = metadata
+            .metadata()
+            .iter()
+            .flatten()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        Ok(SafetensorsFile {
+            storage,
+            data_start: 8 + header_len,
+            entries,
+            metadata,
+        })
+    }
+
+    /// Returns whether the tensor data is read from a memory mapping
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.storage, Storage::Mapped(_))
+    }
+
+    /// Returns the free-form `__metadata__` entries of the header
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Returns the number of tensors
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the file holds no tensors
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the tensor names in file order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Returns a view of the named tensor
+    pub fn tensor(&self, name: &str) -> Result<TensorView<'_>> {
+        let (_, info) = self
+            .entries
+            .iter()
+            .find(|(entry, _)| entry == name)
+            .ok_or_else(|| SafetensorsError::MissingTensor(name.to_string()))?;
+        self.view(info)
+    }
+
+    fn view<'a>(&'a self, info: &'a TensorInfo) -> Result<TensorView<'a>> {
+        let (start, end) = info.data_offsets;
+        let data = &self.storage.bytes()[self.data_start + start..self.data_start + end];
+        TensorView::new(data_type(info.dtype)?, &info.shape, data)
+    }
+
+    /// Applies the renames and casts of `options` to every tensor, in file order
+    ///
+    /// Tensors whose dtype has no `MPSDataType` counterpart are an error unless the rename
+    /// skips them.
+    pub fn resolve(&self, options: &LoadOptions) -> Result<Vec<ResolvedTensor<'_>>> {
+        for &(from, to) in &options.casts {
+            if !from.is_float() || !to.is_float() || from.is_complex() || to.is_complex() {
+                return Err(SafetensorsError::UnsupportedCast { from, to });
+            }
+        }
+
+        let mut names = HashSet::new();
+        let mut resolved = Vec::with_capacity(self.entries.len());
+        for (name, info) in &self.entries {
+            let name = match &options.rename {
+                Some(rename) => match rename(name) {
+                    Some(name) => name,
+                    None => continue,
+                },
+                None => name.clone(),
+            };
+            if !names.insert(name.clone()) {
+                return Err(SafetensorsError::DuplicateName(name));
+            }
+
+            let view = self.view(info)?;
+            let cast_to = options
+                .casts
+                .iter()
+                .find(|(from, _)| *from == view.data_type)
+                .map(|&(_, to)| to);
+            let (data_type, data) = match cast_to {
+                Some(to) if to != view.data_type => {
+                    let data = cast::cast(view.data, view.data_type, to).ok_or(
+                        SafetensorsError::UnsupportedCast {
+                            from: view.data_type,
+                            to,
+                        },
+                    )?;
+                    (to, Cow::Owned(data))
+                }
+                _ => (view.data_type, Cow::Borrowed(view.data)),
+            };
+            resolved.push(ResolvedTensor {
+                name,
+                data_type,
+                shape: view.shape.to_vec(),
+                data,
+            });
+        }
+        Ok(resolved)
+    }
+}
+
+impl fmt::Debug for SafetensorsFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SafetensorsFile")
+            .field("mapped", &self.is_mapped())
+            .field("tensors", &self.entries.len())
+            .field("metadata", &self.metadata)
+            .finish()
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl SafetensorsFile {
+    /// Adds every tensor to `graph` as a constant or variable, keyed by its graph name
+    ///
+    /// Variables are also given their graph name as operation name.
+    pub fn load_into(
+        &self,
+        graph: &MPSGraph,
+        options: &LoadOptions,
+    ) -> Result<HashMap<String, MPSGraphTensor>> {
+        let mut tensors = HashMap::new();
+        for resolved in self.resolve(options)? {
+            let shape = MPSShape::from_slice(&resolved.shape);
+            let tensor = match options.kind {
+                WeightKind::Constant => graph.constant(&resolved.data, &shape, resolved.data_type),
+                WeightKind::Variable => graph.variable(
+                    &resolved.data,
+                    &shape,
+                    resolved.data_type,
+                    Some(&resolved.name),
+                ),
+            };
+            tensors.insert(resolved.name, tensor);
+        }
+        Ok(tensors)
+    }
+}
+
+/// Adapts a [`TensorView`] to the writer in the `safetensors` crate
+struct Entry<'a> {
+    dtype: Dtype,
+    view: TensorView<'a>,
+}
+
+impl View for Entry<'_> {
+    fn dtype(&self) -> Dtype {
+        self.dtype
+    }
+
+    fn shape(&self) -> &[usize] {
+        self.view.shape
+    }
+
+    fn data(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.view.data)
+    }
+
+    fn data_len(&self) -> usize {
+        self.view.data.len()
+    }
+}
+
+fn entries<'a, I>(tensors: I) -> Result<Vec<(&'a str, Entry<'a>)>>
+where
+    I: IntoIterator<Item = (&'a str, TensorView<'a>)>,
+{
+    tensors
+        .into_iter()
+        .map(|(name, view)| {
+            let dtype = dtype(view.data_type)
+                .ok_or(SafetensorsError::UnsupportedDataType(view.data_type))?;
+            Ok((name, Entry { dtype, view }))
+        })
+        .collect()
+}
+
+fn header_metadata(metadata: Option<&BTreeMap<String, String>>) -> Option<HashMap<String, String>> {
+    metadata.map(|metadata| {
+        metadata
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    })
+}
+
+/// Serializes tensors as a safetensors file, with optional `__metadata__` entries
+pub fn serialize<'a, I>(tensors: I, metadata: Option<&BTreeMap<String, String>>) -> Result<Vec<u8>>
+where
+    I: IntoIterator<Item = (&'a str, TensorView<'a>)>,
+{
+    Ok(::safetensors::serialize(
+        entries(tensors)?,
+        &header_metadata(metadata),
+    )?)
+}
+
+/// Saves tensors as a safetensors file, with optional `__metadata__` entries
+pub fn save<'a, I>(
+    path: impl AsRef<Path>,
+    tensors: I,
+    metadata: Option<&BTreeMap<String, String>>,
+) -> Result<()>
+where
+    I: IntoIterator<Item = (&'a str, TensorView<'a>)>,
+{
+    Ok(::safetensors::serialize_to_file(
+        entries(tensors)?,
+        &header_metadata(metadata),
+        path.as_ref(),
+    )?)
+}
+
+/// Copies tensor data back to the host and saves it as a safetensors file
+#[cfg(target_vendor = "apple")]
+pub fn save_tensor_data<'a, I>(
+    path: impl AsRef<Path>,
+    tensors: I,
+    metadata: Option<&BTreeMap<String, String>>,
+) -> Result<()>
+where
+    I: IntoIterator<Item = (&'a str, &'a MPSGraphTensorData)>,
+{
+    let mut host = Vec::new();
+    for (name, tensor_data) in tensors {
+        let shape = tensor_data.shape().dimensions();
+        let data_type = tensor_data.try_data_type()?;
+        host.push((name, shape, data_type, tensor_data.read_bytes()?));
+    }
+
+    let views = host
+        .iter()
+        .map(|(name, shape, data_type, data)| {
+            Ok((*name, TensorView::new(*data_type, shape, data)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    save(path, views, metadata)
+}
+
+/// Reads the current value of each variable and saves them as a checkpoint
+///
+/// The variables are keyed by the given names, so the checkpoint can be loaded back with
+/// [`WeightKind::Variable`]. Each call adds `read_variable` operations to `graph`.
+#[cfg(target_vendor = "apple")]
+pub fn save_variables<'a, I>(
+    path: impl AsRef<Path>,
+    graph: &MPSGraph,
+    variables: I,
+    metadata: Option<&BTreeMap<String, String>>,
+) -> Result<()>
+where
+    I: IntoIterator<Item = (&'a str, &'a MPSGraphTensor)>,
+{
+    let (names, reads): (Vec<&str>, Vec<MPSGraphTensor>) = variables
+        .into_iter()
+        .map(|(name, variable)| (name, graph.read_variable(variable, None)))
+        .unzip();
+    let results = graph.try_run_with_feeds(&HashMap::new(), &reads)?;
+    save_tensor_data(
+        path,
+        names
+            .into_iter()
+            .zip(reads.iter().map(|read| &results[read])),
+        metadata,
+    )
+}
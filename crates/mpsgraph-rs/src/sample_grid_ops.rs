@@ -1,17 +1,26 @@
-use objc2::runtime::AnyObject;
-// In objc2, use false as NO and true as YES
-const NO: bool = false;
-const YES: bool = true;
+#[cfg(target_vendor = "apple")]
 use crate::convolution_transpose_ops::TensorNamedDataLayout;
+#[cfg(target_vendor = "apple")]
 use crate::core::{AsRawObject, NSString};
+#[cfg(target_vendor = "apple")]
 use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
 use crate::resize_ops::{MPSGraphResizeMode, MPSGraphResizeNearestRoundingMode};
+#[cfg(target_vendor = "apple")]
 use crate::tensor::MPSGraphTensor;
+#[cfg(target_vendor = "apple")]
 use objc2::msg_send;
+#[cfg(target_vendor = "apple")]
+use objc2::runtime::AnyObject;
+// In objc2, use false as NO and true as YES
+#[cfg(target_vendor = "apple")]
+const NO: bool = false;
+#[cfg(target_vendor = "apple")]
+const YES: bool = true;
 
 /// Padding modes for MPSGraph operations
 #[repr(i64)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MPSGraphPaddingMode {
     /// Constant padding
     Constant = 0,
@@ -30,6 +39,7 @@ pub enum MPSGraphPaddingMode {
 }
 
 /// Sample Grid operations for MPSGraph
+#[cfg(target_vendor = "apple")]
 impl MPSGraph {
     /// Samples a tensor using the coordinates provided.
     ///
@@ -85,7 +95,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -139,7 +149,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 }
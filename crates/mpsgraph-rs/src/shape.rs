@@ -8,7 +8,8 @@ pub use objc2_foundation::{NSArray, NSNumber};
 use std::fmt;
 use std::ptr;
 
-use crate::core::AsRawObject;
+use crate::core::{create_ns_array_from_i64_slice, AsRawObject};
+use crate::dims::Shape;
 
 /// Type for NSArray objects that represent shape vectors
 pub struct MPSShape(pub(crate) *mut AnyObject);
@@ -122,6 +123,32 @@ impl MPSShape {
     pub fn element_count(&self) -> usize {
         self.dimensions().iter().product()
     }
+
+    /// Create an MPSShape from a [`Shape`], encoding dynamic dimensions as `-1`
+    pub fn from_shape(shape: &Shape) -> Self {
+        MPSShape(create_ns_array_from_i64_slice(&shape.to_i64()))
+    }
+
+    /// Convert to a [`Shape`], decoding negative dimensions as dynamic
+    pub fn to_shape(&self) -> Shape {
+        unsafe {
+            let ns_array: &NSArray<NSNumber> =
+                &*(self.0 as *const objc2_foundation::NSArray<objc2_foundation::NSNumber>);
+            let dims: Vec<i64> = (0..ns_array.len())
+                .map(|i| {
+                    let num_ptr: *mut NSNumber = msg_send![ns_array, objectAtIndex:i];
+                    (*num_ptr).longLongValue()
+                })
+                .collect();
+            Shape::from_i64(&dims)
+        }
+    }
+}
+
+impl From<&Shape> for MPSShape {
+    fn from(shape: &Shape) -> Self {
+        MPSShape::from_shape(shape)
+    }
 }
 
 impl Drop for MPSShape {
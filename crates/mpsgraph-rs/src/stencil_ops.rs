@@ -1,13 +1,19 @@
+#[cfg(target_vendor = "apple")]
 use crate::core::{AsRawObject, NSString};
+#[cfg(target_vendor = "apple")]
 use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
 use crate::shape::MPSShape;
+#[cfg(target_vendor = "apple")]
 use crate::tensor::MPSGraphTensor;
+#[cfg(target_vendor = "apple")]
 use objc2::msg_send;
+#[cfg(target_vendor = "apple")]
 use objc2::runtime::AnyObject;
 
 /// The reduction mode for stencil operations.
 #[repr(u64)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MPSGraphReductionMode {
     /// Min reduction
     Min = 0,
@@ -24,14 +30,17 @@ pub enum MPSGraphReductionMode {
 }
 
 /// Descriptor for stencil operations
+#[cfg(target_vendor = "apple")]
 pub struct MPSGraphStencilOpDescriptor(pub(crate) *mut AnyObject);
 
+#[cfg(target_vendor = "apple")]
 impl Default for MPSGraphStencilOpDescriptor {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl MPSGraphStencilOpDescriptor {
     /// Creates a new stencil operation descriptor with default values
     pub fn new() -> Self {
@@ -189,6 +198,7 @@ impl MPSGraphStencilOpDescriptor {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl Drop for MPSGraphStencilOpDescriptor {
     fn drop(&mut self) {
         unsafe {
@@ -197,6 +207,7 @@ impl Drop for MPSGraphStencilOpDescriptor {
     }
 }
 
+#[cfg(target_vendor = "apple")]
 impl Clone for MPSGraphStencilOpDescriptor {
     fn clone(&self) -> Self {
         unsafe {
@@ -207,6 +218,7 @@ impl Clone for MPSGraphStencilOpDescriptor {
 }
 
 /// Stencil operations for MPSGraph
+#[cfg(target_vendor = "apple")]
 impl MPSGraph {
     /// Creates a stencil operation and returns the result tensor.
     ///
@@ -247,7 +259,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 }
@@ -1,4 +1,6 @@
 use crate::core::MPSDataType;
+use crate::error::MPSGraphError;
+use crate::graph::GraphOwner;
 use crate::operation::MPSGraphOperation;
 use crate::shape::MPSShape;
 use objc2::msg_send;
@@ -6,22 +8,43 @@ use objc2::runtime::AnyObject;
 use std::convert::AsRef;
 use std::fmt;
 use std::hash::{Hash, Hasher};
-use std::ptr;
 
 /// A wrapper for MPSGraphTensor objects
-pub struct MPSGraphTensor(pub(crate) *mut AnyObject);
+///
+/// The wrapper owns one retain on the tensor and one on its graph, so clones are cheap
+/// and the graph stays alive for as long as any tensor built from it.
+pub struct MPSGraphTensor(pub(crate) *mut AnyObject, GraphOwner);
 
 // Implement Send + Sync for the wrapper type
 unsafe impl Send for MPSGraphTensor {}
 unsafe impl Sync for MPSGraphTensor {}
 
 impl MPSGraphTensor {
+    /// Wraps a tensor the caller already holds a +1 retain on, taking over that retain
+    ///
+    /// # Safety
+    ///
+    /// `tensor` must be null or point to a live `MPSGraphTensor` retained by the caller.
+    pub(crate) unsafe fn from_retained(tensor: *mut AnyObject) -> Self {
+        let owner = GraphOwner::of_tensor(tensor);
+        owner.record_tensor(tensor);
+        MPSGraphTensor(tensor, owner)
+    }
+
     /// Returns the data type of this tensor
+    ///
+    /// Panics if MPSGraph reports a data type this crate doesn't know; see
+    /// [`try_data_type`](Self::try_data_type).
     pub fn data_type(&self) -> MPSDataType {
-        unsafe {
-            let data_type_val: u32 = msg_send![self.0, dataType];
-            std::mem::transmute(data_type_val)
-        }
+        self.try_data_type()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Returns the data type of this tensor, or [`MPSGraphError::UnknownDataType`] for a code
+    /// this crate doesn't know
+    pub fn try_data_type(&self) -> Result<MPSDataType, MPSGraphError> {
+        let data_type_val: u32 = unsafe { msg_send![self.0, dataType] };
+        MPSDataType::try_from(data_type_val)
     }
 
     /// Returns the shape of this tensor
@@ -42,7 +65,7 @@ impl MPSGraphTensor {
         unsafe {
             let operation: *mut AnyObject = msg_send![self.0, operation];
             let operation = objc2::ffi::objc_retain(operation as *mut _);
-            MPSGraphOperation(operation)
+            MPSGraphOperation::from_retained(operation)
         }
     }
 
@@ -79,22 +102,22 @@ impl MPSGraphTensor {
 
 impl Drop for MPSGraphTensor {
     fn drop(&mut self) {
-        if !self.0.is_null() {
-            // We need to skip object release to avoid crashes
-            self.0 = std::ptr::null_mut();
+        unsafe {
+            if !self.0.is_null() {
+                objc2::ffi::objc_release(self.0 as *mut _);
+            }
         }
     }
 }
 
 impl Clone for MPSGraphTensor {
     fn clone(&self) -> Self {
-        if !self.0.is_null() {
-            // We need to skip object retain to avoid memory management issues
-            let obj = self.0;
-            MPSGraphTensor(obj)
-        } else {
-            MPSGraphTensor(ptr::null_mut())
+        unsafe {
+            if !self.0.is_null() {
+                objc2::ffi::objc_retain(self.0 as *mut _);
+            }
         }
+        MPSGraphTensor(self.0, self.1.clone())
     }
 }
 
@@ -1,4 +1,5 @@
 use crate::core::MPSDataType;
+use crate::error::MPSGraphError;
 use crate::shape::MPSShape;
 use metal::foreign_types::ForeignType;
 use metal::Buffer;
@@ -10,6 +11,10 @@ use std::fmt;
 /// A wrapper for MPSGraphTensorData objects
 pub struct MPSGraphTensorData(pub(crate) *mut AnyObject);
 
+// Implement Send + Sync for the wrapper type
+unsafe impl Send for MPSGraphTensorData {}
+unsafe impl Sync for MPSGraphTensorData {}
+
 impl MPSGraphTensorData {
     /// Creates a new MPSGraphTensorData from a slice of data and a shape dimensions
     /// This is a convenience method that converts shape dimensions to an MPSShape
@@ -284,12 +289,20 @@ impl MPSGraphTensorData {
     }
 
     /// Returns the data type of this tensor data
+    ///
+    /// Panics if MPSGraph reports a data type this crate doesn't know; see
+    /// [`try_data_type`](Self::try_data_type).
     pub fn data_type(&self) -> MPSDataType {
-        unsafe {
-            // Use u32 for dataType since that matches NSUInteger on most platforms
-            let data_type_val: u32 = msg_send![self.0, dataType];
-            std::mem::transmute(data_type_val)
-        }
+        self.try_data_type()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Returns the data type of this tensor data, or [`MPSGraphError::UnknownDataType`] for a
+    /// code this crate doesn't know
+    pub fn try_data_type(&self) -> Result<MPSDataType, MPSGraphError> {
+        // Use u32 for dataType since that matches NSUInteger on most platforms
+        let data_type_val: u32 = unsafe { msg_send![self.0, dataType] };
+        MPSDataType::try_from(data_type_val)
     }
 
     /// Get the MPSNDArray from this tensor data
@@ -494,13 +507,54 @@ impl MPSGraphTensorData {
         }
     }
 
+    /// Copies the elements to the host as native-endian bytes in C order
+    ///
+    /// The NDArray stays retained while MPSGraph copies out of it with
+    /// `readBytes:strideBytes:`, so views with an offset or padded strides read correctly and
+    /// the result doesn't alias GPU memory.
+    pub fn read_bytes(&self) -> Result<Vec<u8>, MPSGraphError> {
+        let shape = self.shape().dimensions();
+        let data_type = self.try_data_type()?;
+        let expected = shape
+            .iter()
+            .try_fold(1usize, |count, &size| count.checked_mul(size))
+            .and_then(|count| data_type.packed_size_in_bytes(count))
+            .ok_or(MPSGraphError::SizeMismatch {
+                expected: usize::MAX,
+                actual: 0,
+            })?;
+        let ndarray = self.mpsndarray();
+        if ndarray.is_null() {
+            return Err(MPSGraphError::SizeMismatch {
+                expected,
+                actual: 0,
+            });
+        }
+        let mut bytes = vec![0u8; expected];
+        unsafe {
+            let _: () = msg_send![
+                ndarray,
+                readBytes: bytes.as_mut_ptr() as *mut std::ffi::c_void,
+                strideBytes: std::ptr::null_mut::<isize>()
+            ];
+            objc2::ffi::objc_release(ndarray as *mut _);
+        }
+        Ok(bytes)
+    }
+
     /// Synchronize and access the tensor data as a slice of a specific type
     ///
     /// This method synchronizes the tensor data to CPU and then provides access
     /// to the data as a slice of the specified type. The type must match the
-    /// tensor's data type for correct results.
+    /// tensor's data type for correct results, and the slice spans the whole underlying
+    /// buffer, which may be longer than the tensor.
+    ///
+    /// The NDArray owning the buffer is released before the slice is returned, and MPSGraph
+    /// may have created it just for this call, so the slice can dangle. Use
+    /// [`to_host`](Self::to_host) or [`read_bytes`](Self::read_bytes), which copy.
     ///
     /// - Returns: Option containing a slice reference to the data, or None if access fails
+    #[deprecated(note = "the slice can outlive its buffer; use `to_host` or `read_bytes`")]
     pub fn synchronized_data<T>(&self) -> Option<&[T]> {
         unsafe {
             // Synchronize the data to CPU first
@@ -558,6 +612,22 @@ impl MPSGraphTensorData {
     }
 }
 
+// Half precision conversions for host data stored as f32
+#[cfg(feature = "half")]
+impl MPSGraphTensorData {
+    /// Creates Float16 tensor data by rounding f32 values to half precision
+    pub fn from_f32_as_f16(values: &[f32], shape_dims: &[usize]) -> Self {
+        let values: Vec<half::f16> = values.iter().copied().map(half::f16::from_f32).collect();
+        Self::new(&values, shape_dims, MPSDataType::Float16)
+    }
+
+    /// Creates BFloat16 tensor data by rounding f32 values to bfloat16
+    pub fn from_f32_as_bf16(values: &[f32], shape_dims: &[usize]) -> Self {
+        let values: Vec<half::bf16> = values.iter().copied().map(half::bf16::from_f32).collect();
+        Self::new(&values, shape_dims, MPSDataType::BFloat16)
+    }
+}
+
 impl Drop for MPSGraphTensorData {
     fn drop(&mut self) {
         if !self.0.is_null() {
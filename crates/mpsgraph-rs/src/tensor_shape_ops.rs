@@ -1,13 +1,195 @@
+#[cfg(target_vendor = "apple")]
 use crate::core::create_ns_array_from_i64_slice;
+#[cfg(target_vendor = "apple")]
 use crate::core::AsRawObject;
+#[cfg(target_vendor = "apple")]
 use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
 use crate::tensor::MPSGraphTensor;
+#[cfg(target_vendor = "apple")]
 use objc2::msg_send;
+#[cfg(target_vendor = "apple")]
 use objc2::runtime::AnyObject;
+#[cfg(target_vendor = "apple")]
 use objc2_foundation::NSString;
+#[cfg(target_vendor = "apple")]
 use std::ptr;
 
+/// Masks for strided slice operations
+///
+/// Bit `i` of each mask refers to the `i`-th entry of the `starts`, `ends` and `strides` arrays.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MPSGraphSliceMasks {
+    /// Ignore `starts[i]` and slice from the beginning of the dimension
+    pub start_mask: u32,
+    /// Ignore `ends[i]` and slice to the end of the dimension
+    pub end_mask: u32,
+    /// Remove dimension `i` from the result; the slice must have length 1
+    pub squeeze_mask: u32,
+    /// Entry `i` stands for as many full dimensions as needed to cover the input rank.
+    /// At most one bit may be set.
+    pub ellipsis_mask: u32,
+}
+
+/// A strided slice with one entry per input dimension and no ellipsis
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ExpandedSlice {
+    pub starts: Vec<i64>,
+    pub ends: Vec<i64>,
+    pub strides: Vec<i64>,
+    pub masks: MPSGraphSliceMasks,
+}
+
+/// Expands the ellipsis of a strided slice for an input of the given rank.
+///
+/// MPSGraph has no ellipsis mask, so the ellipsis entry is replaced by full-range entries
+/// (start and end masks set, stride 1). Without an ellipsis, missing trailing dimensions
+/// are filled in the same way.
+///
+/// # Panics
+///
+/// Panics if the arrays have different lengths, more than one ellipsis bit is set, or there
+/// are more entries than the input has dimensions.
+#[cfg(any(target_vendor = "apple", test))]
+pub(crate) fn expand_slice_ellipsis(
+    rank: usize,
+    starts: &[i64],
+    ends: &[i64],
+    strides: &[i64],
+    masks: &MPSGraphSliceMasks,
+) -> ExpandedSlice {
+    try_expand_slice_ellipsis(rank, starts, ends, strides, masks)
+        .unwrap_or_else(|reason| panic!("{}", reason))
+}
+
+/// Prepares a strided slice for MPSGraph on an input of the given rank.
+///
+/// Only an ellipsis needs the rank, which is 0 for unranked tensors. Without one the arrays
+/// are passed through unchanged and MPSGraph fills missing trailing dimensions itself.
+///
+/// # Panics
+///
+/// Panics if the arrays have different lengths, or if an ellipsis can't be expanded as in
+/// [`expand_slice_ellipsis`].
+#[cfg(any(target_vendor = "apple", test))]
+pub(crate) fn slice_for_graph(
+    rank: usize,
+    starts: &[i64],
+    ends: &[i64],
+    strides: &[i64],
+    masks: &MPSGraphSliceMasks,
+) -> ExpandedSlice {
+    if masks.ellipsis_mask != 0 {
+        return expand_slice_ellipsis(rank, starts, ends, strides, masks);
+    }
+    assert!(
+        ends.len() == starts.len() && strides.len() == starts.len(),
+        "starts, ends and strides must have the same length ({}, {}, {})",
+        starts.len(),
+        ends.len(),
+        strides.len()
+    );
+    ExpandedSlice {
+        starts: starts.to_vec(),
+        ends: ends.to_vec(),
+        strides: strides.to_vec(),
+        masks: *masks,
+    }
+}
+
+/// Like [`expand_slice_ellipsis`], but describes a malformed slice instead of panicking
+pub(crate) fn try_expand_slice_ellipsis(
+    rank: usize,
+    starts: &[i64],
+    ends: &[i64],
+    strides: &[i64],
+    masks: &MPSGraphSliceMasks,
+) -> Result<ExpandedSlice, String> {
+    let count = starts.len();
+    if ends.len() != count || strides.len() != count {
+        return Err(format!(
+            "starts, ends and strides must have the same length ({}, {}, {})",
+            count,
+            ends.len(),
+            strides.len()
+        ));
+    }
+    if rank > 32 {
+        return Err(format!(
+            "strided slice masks support at most 32 dimensions, got rank {}",
+            rank
+        ));
+    }
+    if masks.ellipsis_mask.count_ones() > 1 {
+        return Err(format!(
+            "at most one ellipsis bit may be set, got mask {:#b}",
+            masks.ellipsis_mask
+        ));
+    }
+
+    // Masks only have bits for the first 32 entries
+    let bit = |i: usize| 1u32.checked_shl(i as u32).unwrap_or(0);
+    let ellipsis = (0..count).find(|&i| masks.ellipsis_mask & bit(i) != 0);
+    let explicit = count - ellipsis.map_or(0, |_| 1);
+    if explicit > rank {
+        return Err(format!(
+            "strided slice has {} entries but the input has rank {}",
+            explicit, rank
+        ));
+    }
+    let fill = rank - explicit;
+    // Dimensions covered by the ellipsis (or the trailing dimensions) start here
+    let fill_at = ellipsis.unwrap_or(count);
+
+    let mut expanded = ExpandedSlice {
+        starts: Vec::with_capacity(rank),
+        ends: Vec::with_capacity(rank),
+        strides: Vec::with_capacity(rank),
+        masks: MPSGraphSliceMasks::default(),
+    };
+    fn push(expanded: &mut ExpandedSlice, start: i64, end: i64, stride: i64, bits: [bool; 3]) {
+        let bit = 1u32 << expanded.starts.len();
+        expanded.starts.push(start);
+        expanded.ends.push(end);
+        expanded.strides.push(stride);
+        if bits[0] {
+            expanded.masks.start_mask |= bit;
+        }
+        if bits[1] {
+            expanded.masks.end_mask |= bit;
+        }
+        if bits[2] {
+            expanded.masks.squeeze_mask |= bit;
+        }
+    }
+
+    for i in 0..=count {
+        if i == fill_at {
+            for _ in 0..fill {
+                push(&mut expanded, 0, 0, 1, [true, true, false]);
+            }
+        }
+        if i < count && Some(i) != ellipsis {
+            let bit = bit(i);
+            push(
+                &mut expanded,
+                starts[i],
+                ends[i],
+                strides[i],
+                [
+                    masks.start_mask & bit != 0,
+                    masks.end_mask & bit != 0,
+                    masks.squeeze_mask & bit != 0,
+                ],
+            );
+        }
+    }
+
+    Ok(expanded)
+}
+
 /// Tensor shape operations for MPSGraph
+#[cfg(target_vendor = "apple")]
 impl MPSGraph {
     /// Creates a reshape operation
     pub fn reshape(&self, x: &MPSGraphTensor, shape: &[i64], name: Option<&str>) -> MPSGraphTensor {
@@ -27,7 +209,7 @@ impl MPSGraph {
             objc2::ffi::objc_release(shape_array as *mut _);
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -45,7 +227,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -72,7 +254,7 @@ impl MPSGraph {
             objc2::ffi::objc_release(shape_array as *mut _);
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -89,7 +271,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -112,7 +294,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -143,7 +325,7 @@ impl MPSGraph {
             objc2::ffi::objc_release(tensor_array as *mut _);
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -174,7 +356,7 @@ impl MPSGraph {
             for i in 0..count {
                 let tensor_obj: *mut AnyObject = msg_send![result, objectAtIndex: i];
                 objc2::ffi::objc_retain(tensor_obj as *mut _);
-                tensors.push(MPSGraphTensor(tensor_obj));
+                tensors.push(MPSGraphTensor::from_retained(tensor_obj));
             }
 
             objc2::ffi::objc_release(result as *mut _);
@@ -201,7 +383,7 @@ impl MPSGraph {
             objc2::ffi::objc_release(axes_array as *mut _);
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -228,7 +410,7 @@ impl MPSGraph {
             objc2::ffi::objc_release(axes_array as *mut _);
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -255,7 +437,7 @@ impl MPSGraph {
             objc2::ffi::objc_release(multiples_array as *mut _);
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -284,7 +466,7 @@ impl MPSGraph {
             objc2::ffi::objc_release(padding_array as *mut _);
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -307,7 +489,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -330,7 +512,7 @@ impl MPSGraph {
             ];
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 
@@ -352,7 +534,287 @@ impl MPSGraph {
             objc2::ffi::objc_release(axes_array as *mut _);
 
             let tensor = objc2::ffi::objc_retain(tensor as *mut _);
-            MPSGraphTensor(tensor)
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a slice operation that takes `length` elements of `dimension` starting at `start`
+    pub fn slice(
+        &self,
+        x: &MPSGraphTensor,
+        dimension: usize,
+        start: i64,
+        length: i64,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        unsafe {
+            let name_obj = match name {
+                Some(s) => NSString::from_str(s).as_raw_object(),
+                None => ptr::null_mut(),
+            };
+
+            let tensor: *mut AnyObject = msg_send![self.0, sliceTensor: x.0,
+                dimension: dimension,
+                start: start,
+                length: length,
+                name: name_obj
+            ];
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a strided slice operation
+    ///
+    /// Negative starts and ends count from the end of the dimension. An ellipsis entry is
+    /// expanded to full-range entries using the rank of `x`; without one, MPSGraph fills
+    /// missing trailing dimensions, so `x` may be unranked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice arrays have different lengths, or an ellipsis is set and can't be
+    /// expanded for the rank of `x`.
+    pub fn strided_slice(
+        &self,
+        x: &MPSGraphTensor,
+        starts: &[i64],
+        ends: &[i64],
+        strides: &[i64],
+        masks: &MPSGraphSliceMasks,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        let slice = slice_for_graph(x.rank(), starts, ends, strides, masks);
+
+        unsafe {
+            let name_obj = match name {
+                Some(s) => NSString::from_str(s).as_raw_object(),
+                None => ptr::null_mut(),
+            };
+
+            let starts_array = create_ns_array_from_i64_slice(&slice.starts);
+            let ends_array = create_ns_array_from_i64_slice(&slice.ends);
+            let strides_array = create_ns_array_from_i64_slice(&slice.strides);
+
+            let tensor: *mut AnyObject = msg_send![self.0, sliceTensor: x.0,
+                starts: starts_array,
+                ends: ends_array,
+                strides: strides_array,
+                startMask: slice.masks.start_mask,
+                endMask: slice.masks.end_mask,
+                squeezeMask: slice.masks.squeeze_mask,
+                name: name_obj
+            ];
+
+            objc2::ffi::objc_release(starts_array as *mut _);
+            objc2::ffi::objc_release(ends_array as *mut _);
+            objc2::ffi::objc_release(strides_array as *mut _);
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a strided slice operation with starts, ends and strides given as 1D Int32 tensors
+    ///
+    /// # Panics
+    ///
+    /// Panics if `masks.ellipsis_mask` is set; the number of entries of tensor-valued indices is
+    /// not known when the graph is built, so an ellipsis cannot be expanded.
+    pub fn strided_slice_with_tensors(
+        &self,
+        x: &MPSGraphTensor,
+        start_tensor: &MPSGraphTensor,
+        end_tensor: &MPSGraphTensor,
+        stride_tensor: &MPSGraphTensor,
+        masks: &MPSGraphSliceMasks,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        assert_eq!(
+            masks.ellipsis_mask, 0,
+            "ellipsis_mask is not supported with tensor-valued slice indices"
+        );
+
+        unsafe {
+            let name_obj = match name {
+                Some(s) => NSString::from_str(s).as_raw_object(),
+                None => ptr::null_mut(),
+            };
+
+            let tensor: *mut AnyObject = msg_send![self.0, sliceTensor: x.0,
+                startTensor: start_tensor.0,
+                endTensor: end_tensor.0,
+                strideTensor: stride_tensor.0,
+                startMask: masks.start_mask,
+                endMask: masks.end_mask,
+                squeezeMask: masks.squeeze_mask,
+                name: name_obj
+            ];
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a slice update operation
+    ///
+    /// Returns a copy of `data` where the strided slice is replaced by `update`. The shape of
+    /// `update` must match the shape of the slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice arrays have different lengths, or an ellipsis is set and can't be
+    /// expanded for the rank of `data`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn slice_update(
+        &self,
+        data: &MPSGraphTensor,
+        update: &MPSGraphTensor,
+        starts: &[i64],
+        ends: &[i64],
+        strides: &[i64],
+        masks: &MPSGraphSliceMasks,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        let slice = slice_for_graph(data.rank(), starts, ends, strides, masks);
+
+        unsafe {
+            let name_obj = match name {
+                Some(s) => NSString::from_str(s).as_raw_object(),
+                None => ptr::null_mut(),
+            };
+
+            let starts_array = create_ns_array_from_i64_slice(&slice.starts);
+            let ends_array = create_ns_array_from_i64_slice(&slice.ends);
+            let strides_array = create_ns_array_from_i64_slice(&slice.strides);
+
+            let tensor: *mut AnyObject = msg_send![self.0, sliceUpdateDataTensor: data.0,
+                updateTensor: update.0,
+                starts: starts_array,
+                ends: ends_array,
+                strides: strides_array,
+                startMask: slice.masks.start_mask,
+                endMask: slice.masks.end_mask,
+                squeezeMask: slice.masks.squeeze_mask,
+                name: name_obj
+            ];
+
+            objc2::ffi::objc_release(starts_array as *mut _);
+            objc2::ffi::objc_release(ends_array as *mut _);
+            objc2::ffi::objc_release(strides_array as *mut _);
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates a slice update operation with starts, ends and strides given as 1D Int32 tensors
+    ///
+    /// # Panics
+    ///
+    /// Panics if `masks.ellipsis_mask` is set.
+    #[allow(clippy::too_many_arguments)]
+    pub fn slice_update_with_tensors(
+        &self,
+        data: &MPSGraphTensor,
+        update: &MPSGraphTensor,
+        starts_tensor: &MPSGraphTensor,
+        ends_tensor: &MPSGraphTensor,
+        strides_tensor: &MPSGraphTensor,
+        masks: &MPSGraphSliceMasks,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        assert_eq!(
+            masks.ellipsis_mask, 0,
+            "ellipsis_mask is not supported with tensor-valued slice indices"
+        );
+
+        unsafe {
+            let name_obj = match name {
+                Some(s) => NSString::from_str(s).as_raw_object(),
+                None => ptr::null_mut(),
+            };
+
+            let tensor: *mut AnyObject = msg_send![self.0, sliceUpdateDataTensor: data.0,
+                updateTensor: update.0,
+                startsTensor: starts_tensor.0,
+                endsTensor: ends_tensor.0,
+                stridesTensor: strides_tensor.0,
+                startMask: masks.start_mask,
+                endMask: masks.end_mask,
+                squeezeMask: masks.squeeze_mask,
+                name: name_obj
+            ];
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
+        }
+    }
+
+    /// Creates the gradient of a strided slice operation
+    ///
+    /// Scatters `input_gradient` into a zero tensor of the forward input's shape.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_gradient` - Gradient with respect to the result of the forward slice
+    /// * `fwd_in_shape_tensor` - 1D tensor holding the shape of the forward slice's input
+    /// * `starts`, `ends`, `strides`, `masks` - The parameters of the forward slice
+    /// * `name` - Name for the operation
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice arrays are inconsistent, or an ellipsis is used and the length of
+    /// `fwd_in_shape_tensor` is not known when the graph is built.
+    #[allow(clippy::too_many_arguments)]
+    pub fn slice_gradient(
+        &self,
+        input_gradient: &MPSGraphTensor,
+        fwd_in_shape_tensor: &MPSGraphTensor,
+        starts: &[i64],
+        ends: &[i64],
+        strides: &[i64],
+        masks: &MPSGraphSliceMasks,
+        name: Option<&str>,
+    ) -> MPSGraphTensor {
+        // The forward input's rank is the length of its shape tensor
+        let rank = if masks.ellipsis_mask != 0 {
+            match fwd_in_shape_tensor.dimensions().as_slice() {
+                [length] => *length,
+                _ => panic!("slice_gradient with an ellipsis needs a statically shaped 1D fwd_in_shape_tensor"),
+            }
+        } else {
+            starts.len()
+        };
+        let slice = expand_slice_ellipsis(rank, starts, ends, strides, masks);
+
+        unsafe {
+            let name_obj = match name {
+                Some(s) => NSString::from_str(s).as_raw_object(),
+                None => ptr::null_mut(),
+            };
+
+            let starts_array = create_ns_array_from_i64_slice(&slice.starts);
+            let ends_array = create_ns_array_from_i64_slice(&slice.ends);
+            let strides_array = create_ns_array_from_i64_slice(&slice.strides);
+
+            let tensor: *mut AnyObject = msg_send![self.0, sliceGradientTensor: input_gradient.0,
+                fwdInShapeTensor: fwd_in_shape_tensor.0,
+                starts: starts_array,
+                ends: ends_array,
+                strides: strides_array,
+                startMask: slice.masks.start_mask,
+                endMask: slice.masks.end_mask,
+                squeezeMask: slice.masks.squeeze_mask,
+                name: name_obj
+            ];
+
+            objc2::ffi::objc_release(starts_array as *mut _);
+            objc2::ffi::objc_release(ends_array as *mut _);
+            objc2::ffi::objc_release(strides_array as *mut _);
+
+            let tensor = objc2::ffi::objc_retain(tensor as *mut _);
+            MPSGraphTensor::from_retained(tensor)
         }
     }
 }
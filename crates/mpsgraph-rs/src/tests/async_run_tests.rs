@@ -0,0 +1,103 @@
+use crate::core::MPSDataType;
+use crate::error::{FeedProblem, MPSGraphError};
+use crate::graph::MPSGraph;
+use crate::shape::MPSShape;
+use crate::tensor_data::MPSGraphTensorData;
+use metal::Device;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+
+/// Minimal executor: parks the thread until the future is woken
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Arc::new(Unpark(thread::current())).into();
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+fn assert_send<T: Send>(value: T) -> T {
+    value
+}
+
+#[test]
+fn test_graph_run_async() {
+    let device = Device::system_default().expect("No Metal device found");
+    let queue = device.new_command_queue();
+    let graph = MPSGraph::new();
+    let x = graph.placeholder(&MPSShape::from_slice(&[3]), MPSDataType::Float32, Some("x"));
+    let y = graph.add(&x, &x, None);
+    let targets = [y.clone()];
+
+    let mut feeds = HashMap::new();
+    feeds.insert(
+        x.clone(),
+        MPSGraphTensorData::new(&[1.0f32, 2.0, 3.0], &[3], MPSDataType::Float32),
+    );
+    let results = block_on(assert_send(graph.run(&queue, &feeds, &targets))).unwrap();
+    assert_eq!(
+        results[&y].to_host::<f32>().unwrap().into_vec()[..3],
+        [2.0, 4.0, 6.0]
+    );
+
+    // Dropping a submitted run doesn't disturb later ones
+    let mut abandoned = Box::pin(graph.run(&queue, &feeds, &targets));
+    let waker = std::task::Waker::noop();
+    let _ = abandoned.as_mut().poll(&mut Context::from_waker(waker));
+    drop(abandoned);
+    assert!(block_on(graph.run(&queue, &feeds, &targets)).is_ok());
+}
+
+#[test]
+fn test_executable_run_async() {
+    let device = Device::system_default().expect("No Metal device found");
+    let queue = device.new_command_queue();
+    let graph = MPSGraph::new();
+    let x = graph.placeholder(&MPSShape::from_slice(&[2]), MPSDataType::Float32, Some("x"));
+    let y = graph.multiply(&x, &x, None);
+    let targets = [y.clone()];
+
+    let mut feeds = HashMap::new();
+    feeds.insert(
+        x.clone(),
+        MPSGraphTensorData::new(&[3.0f32, 4.0], &[2], MPSDataType::Float32),
+    );
+    let executable = graph
+        .try_compile(
+            &crate::device::MPSGraphDevice::new(),
+            &feeds,
+            &targets,
+            None,
+        )
+        .unwrap();
+    assert_eq!(executable.feed_tensors(), vec![x.clone()]);
+
+    let results = block_on(executable.run(&queue, &feeds)).unwrap();
+    assert_eq!(
+        results[&y].to_host::<f32>().unwrap().into_vec()[..2],
+        [9.0, 16.0]
+    );
+
+    let error = block_on(executable.run(&queue, &HashMap::new())).unwrap_err();
+    assert!(matches!(
+        error,
+        MPSGraphError::InvalidFeeds(problems)
+            if problems == [FeedProblem::Missing { tensor: "x".to_string() }]
+    ));
+}
@@ -0,0 +1,66 @@
+use crate::cast::{self, bf16_to_f32, f16_to_f32, f32_to_bf16, f32_to_f16};
+use crate::core::MPSDataType;
+
+#[test]
+fn test_f16_conversions() {
+    assert_eq!(f32_to_f16(1.0), 0x3c00);
+    assert_eq!(f32_to_f16(-2.0), 0xc000);
+    assert_eq!(f32_to_f16(65504.0), 0x7bff);
+    assert_eq!(f32_to_f16(65520.0), 0x7c00);
+    assert_eq!(f32_to_f16(f32::NEG_INFINITY), 0xfc00);
+    assert_eq!(f32_to_f16(-0.0), 0x8000);
+    assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+
+    // Smallest subnormal, and values that round to it or to zero
+    assert_eq!(f32_to_f16(2.0f32.powi(-24)), 0x0001);
+    assert_eq!(f32_to_f16(2.0f32.powi(-25)), 0x0000);
+    assert_eq!(f32_to_f16(1.5 * 2.0f32.powi(-25)), 0x0001);
+
+    // Ties round to even: 1 + 2^-11 is halfway between 1 and the next half
+    assert_eq!(f32_to_f16(1.0 + 2.0f32.powi(-11)), 0x3c00);
+    assert_eq!(f32_to_f16(1.0 + 3.0 * 2.0f32.powi(-11)), 0x3c02);
+
+    // Every non-NaN half value survives a round trip through f32
+    for bits in 0..=u16::MAX {
+        let value = f16_to_f32(bits);
+        if !value.is_nan() {
+            assert_eq!(f32_to_f16(value), bits, "{:#06x}", bits);
+        }
+    }
+}
+
+#[test]
+fn test_bf16_conversions() {
+    assert_eq!(f32_to_bf16(1.0), 0x3f80);
+    assert_eq!(bf16_to_f32(0x3f80), 1.0);
+    assert_eq!(f32_to_bf16(1.0 + 2.0f32.powi(-8)), 0x3f80);
+    assert_eq!(f32_to_bf16(1.0 + 3.0 * 2.0f32.powi(-8)), 0x3f82);
+    assert!(bf16_to_f32(f32_to_bf16(f32::NAN)).is_nan());
+    assert_eq!(f32_to_bf16(f32::INFINITY), 0x7f80);
+}
+
+#[test]
+fn test_cast_buffers() {
+    let data: Vec<u8> = [1.0f32, -0.5, 3.0]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let half = cast::cast(&data, MPSDataType::Float32, MPSDataType::Float16).unwrap();
+    assert_eq!(half, [0x00, 0x3c, 0x00, 0xb8, 0x00, 0x42]);
+
+    let double = cast::cast(&half, MPSDataType::Float16, MPSDataType::Float64).unwrap();
+    let values: Vec<f64> = double
+        .chunks_exact(8)
+        .map(|c| f64::from_le_bytes(c.try_into().unwrap()))
+        .collect();
+    assert_eq!(values, [1.0, -0.5, 3.0]);
+
+    assert_eq!(
+        cast::cast(&data, MPSDataType::Float32, MPSDataType::Int32),
+        None
+    );
+    assert_eq!(
+        cast::cast(&data[..5], MPSDataType::Float32, MPSDataType::Float16),
+        None
+    );
+}
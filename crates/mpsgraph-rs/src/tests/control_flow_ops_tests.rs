@@ -0,0 +1,134 @@
+use crate::core::MPSDataType;
+use crate::graph::MPSGraph;
+use crate::shape::MPSShape;
+use crate::tensor::MPSGraphTensor;
+use crate::tensor_data::MPSGraphTensorData;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+
+fn run_scalar(graph: &MPSGraph, tensor: &MPSGraphTensor) -> f32 {
+    let results = graph.run_with_feeds(&HashMap::new(), std::slice::from_ref(tensor));
+    results[tensor].to_host::<f32>().unwrap().into_vec()[0]
+}
+
+#[test]
+fn test_if_op() {
+    let graph = MPSGraph::new();
+    let then_value = graph.constant_scalar(42.0, MPSDataType::Float32);
+    let else_value = graph.constant_scalar(-42.0, MPSDataType::Float32);
+
+    for (predicate, expected) in [(true, 42.0), (false, -42.0)] {
+        let predicate = graph.constant_scalar(predicate, MPSDataType::Bool);
+        let result = graph.if_op(
+            &predicate,
+            || vec![graph.add(&then_value, &then_value, None)],
+            Some(|| vec![graph.add(&else_value, &else_value, None)]),
+            Some("if_op"),
+        );
+        assert_eq!(result.len(), 1);
+        assert_eq!(run_scalar(&graph, &result[0]), expected * 2.0);
+    }
+}
+
+#[test]
+fn test_for_loop() {
+    let graph = MPSGraph::new();
+    let lower_bound = graph.constant_scalar(0, MPSDataType::Int32);
+    let upper_bound = graph.constant_scalar(5, MPSDataType::Int32);
+    let step = graph.constant_scalar(1, MPSDataType::Int32);
+    let initial = graph.constant_scalar(0.0, MPSDataType::Float32);
+
+    // Sums the indices 0 to 4
+    let result = graph.for_loop(
+        &lower_bound,
+        &upper_bound,
+        &step,
+        &[&initial],
+        |index, args| {
+            let index = graph.cast(index, MPSDataType::Float32, None);
+            vec![graph.add(&args[0], &index, None)]
+        },
+        Some("for_loop"),
+    );
+    assert_eq!(result.len(), 1);
+    assert_eq!(run_scalar(&graph, &result[0]), 10.0);
+
+    let iterations = graph.constant_scalar(3, MPSDataType::Int32);
+    let two = graph.constant_scalar(2.0, MPSDataType::Float32);
+    let one = graph.constant_scalar(1.0, MPSDataType::Float32);
+    let result = graph.for_loop_with_iterations(
+        &iterations,
+        &[&one],
+        |_, args| vec![graph.multiply(&args[0], &two, None)],
+        None,
+    );
+    assert_eq!(run_scalar(&graph, &result[0]), 8.0);
+}
+
+#[test]
+fn test_while_loop() {
+    let graph = MPSGraph::new();
+    let initial = graph.placeholder(&MPSShape::from_slice(&[]), MPSDataType::Float32, None);
+    let limit = graph.constant_scalar(100.0, MPSDataType::Float32);
+    let two = graph.constant_scalar(2.0, MPSDataType::Float32);
+
+    // Doubles the value while it is below 100
+    let result = graph.while_loop(
+        &[&initial],
+        |inputs, passed| {
+            passed.extend_from_slice(inputs);
+            graph.less_than(&inputs[0], &limit, None)
+        },
+        |args| vec![graph.multiply(&args[0], &two, None)],
+        Some("while_loop"),
+    );
+    assert_eq!(result.len(), 1);
+
+    let feed = MPSGraphTensorData::new(&[3.0f32], &[], MPSDataType::Float32);
+    let feeds = HashMap::from([(initial, feed)]);
+    let results = graph.run_with_feeds(&feeds, &result);
+    assert_eq!(
+        results[&result[0]].to_host::<f32>().unwrap().into_vec(),
+        &[192.0]
+    );
+}
+
+#[test]
+fn test_panic_in_block_resumes_after_call() {
+    let graph = MPSGraph::new();
+    let predicate = graph.constant_scalar(true, MPSDataType::Bool);
+
+    let caught = panic::catch_unwind(AssertUnwindSafe(|| {
+        graph.if_op(
+            &predicate,
+            || panic!("then branch failed"),
+            Some(Vec::new),
+            None,
+        )
+    }));
+    let payload = caught.unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"then branch failed"));
+
+    // The graph is still usable
+    let value = graph.constant_scalar(1.5, MPSDataType::Float32);
+    let doubled = graph.add(&value, &value, None);
+    assert_eq!(run_scalar(&graph, &doubled), 3.0);
+}
+
+#[test]
+fn test_panic_in_else_block_after_then_results() {
+    let graph = MPSGraph::new();
+    let predicate = graph.constant_scalar(true, MPSDataType::Bool);
+
+    // MPSGraph would raise on branches returning different counts
+    let caught = panic::catch_unwind(AssertUnwindSafe(|| {
+        graph.if_op(
+            &predicate,
+            || vec![graph.constant_scalar(1.0, MPSDataType::Float32)],
+            Some(|| -> Vec<MPSGraphTensor> { panic!("else branch failed") }),
+            None,
+        )
+    }));
+    let payload = caught.unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"else branch failed"));
+}
@@ -0,0 +1,207 @@
+use crate::{
+    convolution_ops::{
+        MPSGraphConvolution2DOpDescriptor, MPSGraphConvolution3DOpDescriptor,
+        MPSGraphConvolutionDataLayout, MPSGraphPaddingMode, MPSGraphWeightsLayout,
+    },
+    core::MPSDataType,
+    graph::MPSGraph,
+    shape::MPSShape,
+    tensor_data::MPSGraphTensorData,
+};
+use std::collections::HashMap;
+
+// Reads the first `count` values of a Float32 result
+fn read_f32(data: &MPSGraphTensorData, count: usize) -> Vec<f32> {
+    let values = data
+        .to_host::<f32>()
+        .expect("result data should be readable")
+        .into_vec();
+    values[..count].to_vec()
+}
+
+#[test]
+fn test_convolution_descriptors() {
+    let descriptor = MPSGraphConvolution2DOpDescriptor::new();
+    descriptor.set_stride_in_x(2);
+    descriptor.set_stride_in_y(2);
+    descriptor.set_dilation_rate_in_x(1);
+    descriptor.set_dilation_rate_in_y(1);
+    descriptor.set_explicit_padding(1, 1, 1, 1);
+    descriptor.set_padding_style(MPSGraphPaddingMode::Explicit);
+    descriptor.set_data_layout(MPSGraphConvolutionDataLayout::NHWC);
+    descriptor.set_weights_layout(MPSGraphWeightsLayout::HWIO);
+    descriptor.set_groups(1);
+
+    let descriptor = MPSGraphConvolution3DOpDescriptor::new();
+    descriptor.set_stride_in_x(1);
+    descriptor.set_stride_in_y(1);
+    descriptor.set_stride_in_z(1);
+    descriptor.set_dilation_rate_in_x(1);
+    descriptor.set_dilation_rate_in_y(1);
+    descriptor.set_dilation_rate_in_z(1);
+    descriptor.set_padding_left(0);
+    descriptor.set_padding_right(0);
+    descriptor.set_padding_top(0);
+    descriptor.set_padding_bottom(0);
+    descriptor.set_padding_front(0);
+    descriptor.set_padding_back(0);
+    descriptor.set_explicit_padding(0, 0, 0, 0, 0, 0);
+    descriptor.set_padding_style(MPSGraphPaddingMode::Same);
+    descriptor.set_data_layout(MPSGraphConvolutionDataLayout::NCDHW);
+    descriptor.set_weights_layout(MPSGraphWeightsLayout::OIDHW);
+    descriptor.set_groups(1);
+}
+
+#[test]
+fn test_convolution_2d() {
+    let graph = MPSGraph::new();
+
+    // NCHW input (1, 1, 3, 3) and OIHW weights (1, 1, 2, 2)
+    let input = graph.placeholder(
+        &MPSShape::from_slice(&[1, 1, 3, 3]),
+        MPSDataType::Float32,
+        Some("Input"),
+    );
+    let weights = graph.placeholder(
+        &MPSShape::from_slice(&[1, 1, 2, 2]),
+        MPSDataType::Float32,
+        Some("Weights"),
+    );
+
+    let descriptor = MPSGraphConvolution2DOpDescriptor::new();
+    descriptor.set_padding_style(MPSGraphPaddingMode::Valid);
+    descriptor.set_data_layout(MPSGraphConvolutionDataLayout::NCHW);
+    descriptor.set_weights_layout(MPSGraphWeightsLayout::OIHW);
+
+    let conv = graph.convolution_2d(&input, &weights, &descriptor, Some("Conv"));
+
+    let input_data = MPSGraphTensorData::new(
+        &[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0],
+        &[1, 1, 3, 3],
+        MPSDataType::Float32,
+    );
+    let weights_data = MPSGraphTensorData::new(
+        &[1.0f32, 0.0, 0.0, 1.0],
+        &[1, 1, 2, 2],
+        MPSDataType::Float32,
+    );
+
+    let mut feeds = HashMap::new();
+    feeds.insert(input.clone(), input_data);
+    feeds.insert(weights.clone(), weights_data);
+
+    let results = graph.run_with_feeds(&feeds, std::slice::from_ref(&conv));
+    let values = read_f32(&results[&conv], 4);
+
+    // Each output is the sum of the top-left and bottom-right input of its window
+    assert_eq!(values, vec![6.0, 8.0, 12.0, 14.0]);
+}
+
+#[test]
+fn test_convolution_2d_gradients() {
+    let graph = MPSGraph::new();
+
+    let input_shape = MPSShape::from_slice(&[1, 1, 3, 3]);
+    let weights_shape = MPSShape::from_slice(&[1, 1, 2, 2]);
+    let input = graph.placeholder(&input_shape, MPSDataType::Float32, Some("Input"));
+    let weights = graph.placeholder(&weights_shape, MPSDataType::Float32, Some("Weights"));
+    let gradient = graph.placeholder(
+        &MPSShape::from_slice(&[1, 1, 2, 2]),
+        MPSDataType::Float32,
+        Some("Gradient"),
+    );
+
+    let descriptor = MPSGraphConvolution2DOpDescriptor::new();
+    descriptor.set_padding_style(MPSGraphPaddingMode::Valid);
+    descriptor.set_data_layout(MPSGraphConvolutionDataLayout::NCHW);
+    descriptor.set_weights_layout(MPSGraphWeightsLayout::OIHW);
+
+    let data_gradient = graph.convolution_2d_data_gradient(
+        &gradient,
+        &weights,
+        &input_shape,
+        &descriptor,
+        Some("DataGradient"),
+    );
+    let weights_gradient = graph.convolution_2d_weights_gradient(
+        &gradient,
+        &input,
+        &weights_shape,
+        &descriptor,
+        Some("WeightsGradient"),
+    );
+
+    let mut feeds = HashMap::new();
+    feeds.insert(
+        input.clone(),
+        MPSGraphTensorData::new(&[1.0f32; 9], &[1, 1, 3, 3], MPSDataType::Float32),
+    );
+    feeds.insert(
+        weights.clone(),
+        MPSGraphTensorData::new(&[1.0f32; 4], &[1, 1, 2, 2], MPSDataType::Float32),
+    );
+    feeds.insert(
+        gradient.clone(),
+        MPSGraphTensorData::new(&[1.0f32; 4], &[1, 1, 2, 2], MPSDataType::Float32),
+    );
+
+    let results = graph.run_with_feeds(&feeds, &[data_gradient.clone(), weights_gradient.clone()]);
+
+    // Every input pixel is counted once per window that covers it
+    assert_eq!(
+        read_f32(&results[&data_gradient], 9),
+        vec![1.0, 2.0, 1.0, 2.0, 4.0, 2.0, 1.0, 2.0, 1.0]
+    );
+    assert_eq!(
+        read_f32(&results[&weights_gradient], 4),
+        vec![4.0, 4.0, 4.0, 4.0]
+    );
+}
+
+#[test]
+fn test_convolution_3d() {
+    let graph = MPSGraph::new();
+
+    // NCDHW input (1, 1, 2, 2, 2) and OIDHW weights (1, 1, 1, 1, 1)
+    let input = graph.placeholder(
+        &MPSShape::from_slice(&[1, 1, 2, 2, 2]),
+        MPSDataType::Float32,
+        Some("Input"),
+    );
+    let weights = graph.placeholder(
+        &MPSShape::from_slice(&[1, 1, 1, 1, 1]),
+        MPSDataType::Float32,
+        Some("Weights"),
+    );
+
+    let descriptor = MPSGraphConvolution3DOpDescriptor::new();
+    descriptor.set_stride_in_x(2);
+    descriptor.set_explicit_padding(0, 0, 0, 0, 1, 0);
+    descriptor.set_padding_style(MPSGraphPaddingMode::Explicit);
+    descriptor.set_data_layout(MPSGraphConvolutionDataLayout::NCDHW);
+    descriptor.set_weights_layout(MPSGraphWeightsLayout::OIDHW);
+
+    // One slice of padding in front, and every other column
+    let conv = graph.convolution_3d(&input, &weights, &descriptor, Some("Conv3D"));
+    assert_eq!(conv.dimensions(), vec![1, 1, 3, 2, 1]);
+
+    let mut feeds = HashMap::new();
+    feeds.insert(
+        input.clone(),
+        MPSGraphTensorData::new(
+            &[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0],
+            &[1, 1, 2, 2, 2],
+            MPSDataType::Float32,
+        ),
+    );
+    feeds.insert(
+        weights.clone(),
+        MPSGraphTensorData::new(&[2.0f32], &[1, 1, 1, 1, 1], MPSDataType::Float32),
+    );
+
+    let results = graph.run_with_feeds(&feeds, std::slice::from_ref(&conv));
+    assert_eq!(
+        read_f32(&results[&conv], 6),
+        vec![0.0, 0.0, 2.0, 6.0, 10.0, 14.0]
+    );
+}
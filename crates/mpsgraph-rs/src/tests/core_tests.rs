@@ -2,6 +2,7 @@ use crate::core::{
     MPSDataType, MPSGraphExecutionStage, MPSGraphOptimization, MPSGraphOptimizationProfile,
     MPSGraphOptions,
 };
+use crate::error::MPSGraphError;
 
 #[test]
 fn test_mps_data_type() {
@@ -26,6 +27,94 @@ fn test_mps_data_type() {
     }
 }
 
+#[test]
+fn test_mps_data_type_codes() {
+    // Raw values from MPSCoreTypes.h
+    assert_eq!(MPSDataType::Float32.as_u32(), 0x1000_0020);
+    assert_eq!(MPSDataType::BFloat16.as_u32(), 0x9000_0010);
+    assert_eq!(MPSDataType::Int4.as_u32(), 0x2000_0004);
+    assert_eq!(MPSDataType::UInt4.as_u32(), 4);
+    assert_eq!(MPSDataType::Bool.as_u32(), 0x8000_0008);
+    assert_eq!(MPSDataType::Complex16.as_u32(), 0x1100_0020);
+    assert_eq!(MPSDataType::Complex32.as_u32(), 0x1100_0040);
+    assert_eq!(MPSDataType::Unorm1.as_u32(), 0x4000_0001);
+    assert_eq!(MPSDataType::Unorm8.as_u32(), 0x4000_0008);
+
+    for data_type in MPSDataType::ALL {
+        assert_eq!(MPSDataType::try_from(data_type.as_u32()), Ok(data_type));
+    }
+    assert_eq!(MPSDataType::try_from(0), Ok(MPSDataType::Invalid));
+    assert_eq!(
+        MPSDataType::try_from(0x1000_0011),
+        Err(MPSGraphError::UnknownDataType(0x1000_0011))
+    );
+}
+
+#[test]
+fn test_mps_data_type_properties() {
+    use MPSDataType::*;
+
+    for data_type in [Float16, Float32, Float64, BFloat16] {
+        assert!(data_type.is_float() && data_type.is_signed());
+        assert!(!data_type.is_integer() && !data_type.is_complex());
+    }
+    for data_type in [Int4, Int8, Int16, Int32, Int64] {
+        assert!(data_type.is_integer() && data_type.is_signed());
+    }
+    for data_type in [UInt4, UInt8, UInt16, UInt32, UInt64] {
+        assert!(data_type.is_integer() && !data_type.is_signed());
+    }
+    for data_type in [Complex16, Complex32, Complex64] {
+        assert!(data_type.is_complex() && !data_type.is_float());
+    }
+    for data_type in [Bool, Unorm1, Unorm8, Invalid] {
+        assert!(!data_type.is_float() && !data_type.is_integer() && !data_type.is_complex());
+    }
+
+    assert_eq!(Int4.bit_width(), 4);
+    assert_eq!(Int4.size_in_bytes(), 1);
+    assert_eq!(BFloat16.bit_width(), 16);
+    assert_eq!(Bool.size_in_bytes(), 1);
+    assert_eq!(Complex16.size_in_bytes(), 4);
+    assert_eq!(Complex32.size_in_bytes(), 8);
+    assert_eq!(Unorm1.bit_width(), 1);
+    assert_eq!(Invalid.size_in_bytes(), 0);
+
+    assert_eq!(Int4.packed_size_in_bytes(5), Some(3));
+    assert_eq!(UInt4.packed_size_in_bytes(4), Some(2));
+    assert_eq!(Unorm1.packed_size_in_bytes(9), Some(2));
+    assert_eq!(Float32.packed_size_in_bytes(3), Some(12));
+    assert_eq!(Int8.packed_size_in_bytes(0), Some(0));
+    assert_eq!(Float64.packed_size_in_bytes(usize::MAX), None);
+}
+
+#[test]
+fn test_mps_data_type_promotion() {
+    use MPSDataType::*;
+
+    let cases = [
+        (Float32, Float32, Some(Float32)),
+        (Bool, Int8, Some(Int8)),
+        (Int8, Int32, Some(Int32)),
+        (UInt8, UInt16, Some(UInt16)),
+        (UInt8, Int8, Some(Int16)),
+        (Int64, UInt32, Some(Int64)),
+        (Int64, UInt64, None),
+        (Int32, Float16, Some(Float16)),
+        (Float16, BFloat16, Some(Float32)),
+        (BFloat16, Float64, Some(Float64)),
+        (Float32, Complex16, Some(Complex32)),
+        (Int32, Complex32, Some(Complex32)),
+        (Complex32, Float64, Some(Complex64)),
+        (Unorm8, Float32, None),
+        (Invalid, Invalid, None),
+    ];
+    for (a, b, expected) in cases {
+        assert_eq!(MPSDataType::promote(a, b), expected, "{:?} + {:?}", a, b);
+        assert_eq!(MPSDataType::promote(b, a), expected, "{:?} + {:?}", b, a);
+    }
+}
+
 #[test]
 fn test_graph_options() {
     // Initialize options with default value
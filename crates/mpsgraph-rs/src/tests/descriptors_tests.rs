@@ -0,0 +1,187 @@
+use crate::convolution_ops::{MPSGraphConvolutionDataLayout, MPSGraphWeightsLayout};
+use crate::descriptors::{
+    Convolution2dDescriptor, Convolution3dDescriptor, DepthwiseConvolution3dDescriptor,
+    DescriptorError, ImToColDescriptor, Pooling2dDescriptor, Pooling4dDescriptor,
+    StencilDescriptor,
+};
+use crate::pooling_ops::MPSGraphPaddingStyle;
+use crate::sample_grid_ops::MPSGraphPaddingMode;
+use std::collections::HashSet;
+
+#[cfg(target_vendor = "apple")]
+use crate::core::MPSDataType;
+#[cfg(target_vendor = "apple")]
+use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
+use crate::shape::MPSShape;
+
+#[test]
+fn test_descriptor_values() {
+    let window = Pooling2dDescriptor::new([3, 3])
+        .with_strides([2, 2])
+        .with_padding([1, 1, 1, 1]);
+    assert_eq!(window.clone(), window);
+    assert_ne!(window.clone().with_ceil_mode(true), window);
+
+    let stencils: HashSet<StencilDescriptor> = [
+        StencilDescriptor::new().with_padding_constant(0.0),
+        StencilDescriptor::new().with_padding_constant(-0.0),
+        StencilDescriptor::new().with_boundary_mode(MPSGraphPaddingMode::Reflect),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(stencils.len(), 2);
+
+    assert!(format!("{:?}", Convolution2dDescriptor::new().with_groups(4)).contains("groups: 4"));
+}
+
+#[test]
+fn test_validate_accepts_defaults() {
+    assert_eq!(
+        Convolution2dDescriptor::new().validate_kernel([3, 3]),
+        Ok(())
+    );
+    assert_eq!(Convolution3dDescriptor::new().validate(), Ok(()));
+    assert_eq!(DepthwiseConvolution3dDescriptor::new().validate(), Ok(()));
+    assert_eq!(Pooling4dDescriptor::new([1, 1, 2, 2]).validate(), Ok(()));
+    assert_eq!(
+        StencilDescriptor::new().validate_kernel([1, 1, 3, 3]),
+        Ok(())
+    );
+    assert_eq!(
+        ImToColDescriptor::new([3, 3])
+            .with_dilations([2, 2])
+            .with_padding([5, 5, 5, 5])
+            .validate(),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_validate_rejects_invalid() {
+    assert_eq!(
+        Convolution2dDescriptor::new()
+            .with_strides([1, 0])
+            .validate(),
+        Err(DescriptorError::Zero { field: "stride" })
+    );
+    assert_eq!(
+        Convolution2dDescriptor::new().with_groups(0).validate(),
+        Err(DescriptorError::Zero { field: "groups" })
+    );
+    assert_eq!(
+        Pooling2dDescriptor::new([0, 2]).validate(),
+        Err(DescriptorError::Zero {
+            field: "kernel size"
+        })
+    );
+    assert_eq!(
+        Pooling2dDescriptor::new([2, 2])
+            .with_padding([0, 0, 0, 3])
+            .validate(),
+        Err(DescriptorError::PaddingExceedsWindow {
+            axis: 1,
+            padding: 3,
+            window: 2
+        })
+    );
+    assert!(matches!(
+        Convolution2dDescriptor::new()
+            .with_padding([2, 2, 2, 2])
+            .validate_kernel([3, 3]),
+        Ok(())
+    ));
+    assert_eq!(
+        Convolution2dDescriptor::new()
+            .with_padding([4, 0, 0, 0])
+            .validate_kernel([3, 3]),
+        Err(DescriptorError::PaddingExceedsWindow {
+            axis: 0,
+            padding: 4,
+            window: 3
+        })
+    );
+    assert_eq!(
+        Convolution2dDescriptor::new()
+            .with_padding([1, 1, 1, 1])
+            .with_padding_style(MPSGraphPaddingStyle::TfSame)
+            .validate(),
+        Err(DescriptorError::PaddingWithStyle(
+            MPSGraphPaddingStyle::TfSame
+        ))
+    );
+    assert_eq!(
+        StencilDescriptor::new()
+            .with_padding([0, 0, 0, 0, 1, 1, 1, 1])
+            .with_padding_style(MPSGraphPaddingStyle::TfValid)
+            .validate(),
+        Err(DescriptorError::PaddingWithStyle(
+            MPSGraphPaddingStyle::TfValid
+        ))
+    );
+    assert_eq!(
+        Convolution3dDescriptor::new()
+            .with_data_layout(MPSGraphConvolutionDataLayout::NCHW)
+            .validate(),
+        Err(DescriptorError::UnsupportedLayout {
+            field: "data layout",
+            layout: "NCHW".to_string()
+        })
+    );
+    assert_eq!(
+        Convolution2dDescriptor::new()
+            .with_weights_layout(MPSGraphWeightsLayout::OIDHW)
+            .validate()
+            .unwrap_err()
+            .to_string(),
+        "weights layout OIDHW is not supported here"
+    );
+}
+
+#[cfg(target_vendor = "apple")]
+#[test]
+fn test_try_pooling_and_convolution() {
+    let graph = MPSGraph::new();
+    let x = graph.placeholder(
+        &MPSShape::from_slice(&[1, 1, 4, 4]),
+        MPSDataType::Float32,
+        None,
+    );
+    let window = Pooling2dDescriptor::new([2, 2]).with_strides([2, 2]);
+    let pooled = graph.try_max_pooling_2d(&x, &window, None).unwrap();
+    assert_eq!(pooled.dimensions(), vec![1, 1, 2, 2]);
+
+    let weights = graph.placeholder(
+        &MPSShape::from_slice(&[1, 1, 3, 3]),
+        MPSDataType::Float32,
+        None,
+    );
+    let convolution = Convolution2dDescriptor::new().with_padding([4, 4, 1, 1]);
+    assert!(matches!(
+        graph.try_convolution_2d(&x, &weights, &convolution, None),
+        Err(DescriptorError::PaddingExceedsWindow { axis: 0, .. })
+    ));
+    let convolution = convolution.with_padding([1, 1, 1, 1]);
+    let features = graph
+        .try_convolution_2d(&x, &weights, &convolution, None)
+        .unwrap();
+    assert_eq!(features.dimensions(), vec![1, 1, 4, 4]);
+
+    let volume = graph.placeholder(
+        &MPSShape::from_slice(&[1, 1, 2, 4, 4]),
+        MPSDataType::Float32,
+        None,
+    );
+    let weights = graph.placeholder(
+        &MPSShape::from_slice(&[1, 1, 1, 3, 3]),
+        MPSDataType::Float32,
+        None,
+    );
+    let convolution = Convolution3dDescriptor::new()
+        .with_strides([1, 2, 2])
+        .with_padding([0, 0, 1, 1, 1, 1]);
+    let features = graph
+        .try_convolution_3d(&volume, &weights, &convolution, None)
+        .unwrap();
+    assert_eq!(features.dimensions(), vec![1, 1, 2, 2, 2]);
+}
@@ -0,0 +1,122 @@
+use crate::dims::{normalize_axis, Dim, Shape, ShapeError};
+#[cfg(target_vendor = "apple")]
+use crate::shape::MPSShape;
+
+#[test]
+fn test_dim_conversions() {
+    assert_eq!(Dim::from_i64(3), Dim::Static(3));
+    assert_eq!(Dim::from_i64(-1), Dim::Dynamic);
+    assert_eq!(Dim::Dynamic.to_i64(), -1);
+    assert_eq!(Dim::Static(0).size(), Some(0));
+    assert_eq!(Dim::Dynamic.size(), None);
+
+    let shape = Shape::from_i64(&[2, -1, 4]);
+    assert_eq!(shape.to_string(), "[2, ?, 4]");
+    assert_eq!(shape.to_i64(), vec![2, -1, 4]);
+    assert_eq!(shape.rank(), 3);
+    assert!(!shape.is_static());
+    assert_eq!(shape.element_count(), None);
+    assert_eq!(shape.to_static(), None);
+
+    let shape = Shape::from_static(&[2, 3]);
+    assert_eq!(shape.element_count(), Some(6));
+    assert_eq!(shape.to_static(), Some(vec![2, 3]));
+    assert_eq!(Shape::scalar().element_count(), Some(1));
+    assert_eq!(Shape::from_static(&[1 << 62, 8]).element_count(), None);
+}
+
+#[test]
+fn test_normalize_axes() {
+    let shape = Shape::from_static(&[2, 3, 4]);
+    assert_eq!(shape.normalize_axis(-1), Ok(2));
+    assert_eq!(shape.normalize_axis(0), Ok(0));
+    assert_eq!(
+        shape.normalize_axis(3),
+        Err(ShapeError::AxisOutOfRange { axis: 3, rank: 3 })
+    );
+    assert_eq!(
+        normalize_axis(-4, 3),
+        Err(ShapeError::AxisOutOfRange { axis: -4, rank: 3 })
+    );
+    assert_eq!(shape.normalize_axes(&[-1, 0]), Ok(vec![2, 0]));
+    assert_eq!(
+        shape.normalize_axes(&[2, -1]),
+        Err(ShapeError::DuplicateAxis { axis: 2 })
+    );
+}
+
+#[test]
+fn test_broadcast() {
+    let a = Shape::from_static(&[8, 1, 6, 1]);
+    let b = Shape::from_static(&[7, 1, 5]);
+    assert_eq!(a.broadcast(&b), Ok(Shape::from_static(&[8, 7, 6, 5])));
+
+    // Scalars broadcast to anything
+    assert_eq!(Shape::scalar().broadcast(&b), Ok(b.clone()));
+
+    // Dynamic dimensions take the static size, or stay dynamic
+    let c = Shape::from_i64(&[-1, 3]);
+    let d = Shape::from_i64(&[4, -1]);
+    assert_eq!(c.broadcast(&d), Ok(Shape::from_static(&[4, 3])));
+    assert_eq!(
+        c.broadcast(&Shape::from_i64(&[-1, 1])),
+        Ok(Shape::from_i64(&[-1, 3]))
+    );
+
+    let error = Shape::from_static(&[2, 3])
+        .broadcast(&Shape::from_static(&[4, 3]))
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        ShapeError::IncompatibleBroadcast { axis: 0, .. }
+    ));
+    assert_eq!(
+        error.to_string(),
+        "shapes [2, 3] and [4, 3] can't be broadcast: dimensions differ at axis 0"
+    );
+}
+
+#[test]
+fn test_reshape() {
+    let shape = Shape::from_static(&[2, 3, 4]);
+    assert_eq!(shape.reshape(&[6, -1]), Ok(Shape::from_static(&[6, 4])));
+    assert_eq!(shape.reshape(&[24]), Ok(Shape::from_static(&[24])));
+    assert!(shape.reshape(&[5, -1]).is_err());
+    assert!(shape.reshape(&[-1, -1]).is_err());
+    assert!(shape.reshape(&[2, -2, 12]).is_err());
+    assert!(shape.reshape(&[25]).is_err());
+    assert!(matches!(
+        shape.reshape(&[1 << 62, 8]),
+        Err(ShapeError::InvalidReshape { reason, .. }) if reason.contains("overflows")
+    ));
+    assert!(matches!(
+        Shape::from_static(&[1 << 62, 8]).reshape(&[-1]),
+        Err(ShapeError::InvalidReshape { reason, .. }) if reason.contains("overflows")
+    ));
+
+    // Unknown element counts can't be checked, and the inferred dimension stays dynamic
+    let dynamic = Shape::from_i64(&[-1, 4]);
+    assert_eq!(dynamic.reshape(&[-1, 2]), Ok(Shape::from_i64(&[-1, 2])));
+    assert_eq!(dynamic.reshape(&[8, 2]), Ok(Shape::from_static(&[8, 2])));
+}
+
+#[test]
+fn test_permute() {
+    let shape = Shape::from_i64(&[2, -1, 4]);
+    assert_eq!(shape.permute(&[2, 0, 1]), Ok(Shape::from_i64(&[4, 2, -1])));
+    assert_eq!(shape.permute(&[-1, 1, 0]), Ok(Shape::from_i64(&[4, -1, 2])));
+    assert!(matches!(
+        shape.permute(&[0, 0, 1]),
+        Err(ShapeError::InvalidPermutation { .. })
+    ));
+    assert!(shape.permute(&[0, 1]).is_err());
+}
+
+#[test]
+#[cfg(target_vendor = "apple")]
+fn test_mpsshape_round_trip() {
+    let shape = Shape::from_i64(&[2, -1, 4]);
+    let mps_shape = MPSShape::from(&shape);
+    assert_eq!(mps_shape.rank(), 3);
+    assert_eq!(mps_shape.to_shape(), shape);
+}
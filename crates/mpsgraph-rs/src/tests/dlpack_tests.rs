@@ -0,0 +1,184 @@
+use crate::core::MPSDataType;
+use crate::dlpack::{
+    DLDataType, DLDataTypeCode, DLDevice, DLDeviceType, DLManagedTensor, DLTensor, DlpackError,
+    ManagedTensor,
+};
+use crate::host::{DynHostTensor, HostTensor};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[test]
+fn test_dlpack_data_types() {
+    for data_type in MPSDataType::ALL {
+        if let Ok(dtype) = DLDataType::try_from(data_type) {
+            assert_eq!(MPSDataType::try_from(dtype), Ok(data_type));
+            assert_eq!(dtype.bits as usize, data_type.bit_width());
+        }
+    }
+    let dtype = |code, bits| DLDataType {
+        code,
+        bits,
+        lanes: 1,
+    };
+    assert_eq!(
+        DLDataType::try_from(MPSDataType::Bool),
+        Ok(dtype(DLDataTypeCode::BOOL, 8))
+    );
+    assert_eq!(
+        DLDataType::try_from(MPSDataType::BFloat16),
+        Ok(dtype(DLDataTypeCode::BFLOAT, 16))
+    );
+    assert_eq!(
+        DLDataType::try_from(MPSDataType::Complex32),
+        Ok(dtype(DLDataTypeCode::COMPLEX, 64))
+    );
+    assert_eq!(
+        DLDataType::try_from(MPSDataType::Int4),
+        Err(DlpackError::UnsupportedDataType(MPSDataType::Int4))
+    );
+
+    let vector = DLDataType {
+        lanes: 4,
+        ..dtype(DLDataTypeCode::FLOAT, 32)
+    };
+    assert_eq!(
+        MPSDataType::try_from(vector),
+        Err(DlpackError::UnsupportedDlDataType(vector))
+    );
+    assert!(MPSDataType::try_from(dtype(DLDataTypeCode::OPAQUE_HANDLE, 64)).is_err());
+}
+
+#[test]
+fn test_dlpack_export() {
+    let tensor = HostTensor::new(&[2, 3], vec![0i32, 1, 2, 3, 4, 5])
+        .unwrap()
+        .permute(&[1, 0])
+        .unwrap();
+    let exported = tensor.clone();
+    let storage = exported.storage().as_ptr();
+    let managed = ManagedTensor::from_host(exported);
+    assert_eq!(managed.device(), DLDevice::CPU);
+    assert_eq!(managed.data_type(), Ok(MPSDataType::Int32));
+    assert_eq!(managed.shape(), &[3, 2]);
+    assert_eq!(managed.strides(), Some(&[1i64, 3][..]));
+    assert_eq!(managed.dl_tensor().data as *const i32, storage);
+    assert!(!managed.is_compact());
+    assert_eq!(managed.as_bytes(), Err(DlpackError::NotCompact));
+    assert_eq!(managed.to_host::<i32>().unwrap(), tensor);
+    assert!(matches!(
+        managed.to_host::<f32>(),
+        Err(DlpackError::DataTypeMismatch { .. })
+    ));
+
+    // A consumer takes the raw tensor and eventually calls its deleter
+    let raw = managed.into_raw();
+    let managed = unsafe { ManagedTensor::from_raw(raw) }.unwrap();
+    assert_eq!(managed.to_dyn_host().unwrap().shape(), &[3, 2]);
+    drop(managed);
+
+    let flags = DynHostTensor::from_slice(&[3], &[true, false, true]).unwrap();
+    let managed = ManagedTensor::from_dyn_host(flags.clone()).unwrap();
+    assert!(managed.is_compact());
+    assert_eq!(managed.as_bytes().unwrap(), &[1, 0, 1]);
+    assert_eq!(managed.to_dyn_host().unwrap(), flags);
+
+    let nibbles = DynHostTensor::new(&[2], MPSDataType::Int4, vec![0; 1]).unwrap();
+    assert!(ManagedTensor::from_dyn_host(nibbles).is_err());
+}
+
+static DELETED: AtomicUsize = AtomicUsize::new(0);
+
+/// A tensor as another framework would hand it over, freed through its deleter
+struct Foreign {
+    managed: DLManagedTensor,
+    shape: [i64; 2],
+    strides: [i64; 2],
+    data: Vec<f32>,
+}
+
+unsafe extern "C" fn delete_foreign(tensor: *mut DLManagedTensor) {
+    drop(Box::from_raw((*tensor).manager_ctx as *mut Foreign));
+    DELETED.fetch_add(1, Ordering::SeqCst);
+}
+
+fn foreign(device: DLDevice) -> *mut DLManagedTensor {
+    let foreign = Box::into_raw(Box::new(Foreign {
+        managed: DLManagedTensor {
+            dl_tensor: DLTensor {
+                data: std::ptr::null_mut(),
+                device,
+                ndim: 2,
+                dtype: DLDataType::try_from(MPSDataType::Float32).unwrap(),
+                shape: std::ptr::null_mut(),
+                strides: std::ptr::null_mut(),
+                byte_offset: 0,
+            },
+            manager_ctx: std::ptr::null_mut(),
+            deleter: Some(delete_foreign),
+        },
+        // A 2x2 view of a 3x3 matrix, starting at [0, 2] with its columns reversed
+        shape: [2, 2],
+        strides: [3, -1],
+        data: (0..9).map(|i| i as f32).collect(),
+    }));
+    unsafe {
+        (*foreign).managed.manager_ctx = foreign as *mut _;
+        let tensor = &mut (*foreign).managed.dl_tensor;
+        tensor.data = (*foreign).data.as_mut_ptr() as *mut _;
+        tensor.byte_offset = 2 * 4;
+        tensor.shape = (*foreign).shape.as_mut_ptr();
+        tensor.strides = (*foreign).strides.as_mut_ptr();
+        &mut (*foreign).managed
+    }
+}
+
+#[test]
+fn test_dlpack_import() {
+    let before = DELETED.load(Ordering::SeqCst);
+    let managed = unsafe { ManagedTensor::from_raw(foreign(DLDevice::CPU)) }.unwrap();
+    assert!(!managed.is_compact());
+    let host = managed.to_host::<f32>().unwrap();
+    assert_eq!(host.shape(), &[2, 2]);
+    assert_eq!(host.to_vec(), vec![2.0, 1.0, 5.0, 4.0]);
+    drop(managed);
+    assert_eq!(DELETED.load(Ordering::SeqCst), before + 1);
+
+    let metal = DLDevice {
+        device_type: DLDeviceType::METAL,
+        device_id: 0,
+    };
+    let managed = unsafe { ManagedTensor::from_raw(foreign(metal)) }.unwrap();
+    assert_eq!(
+        managed.to_dyn_host(),
+        Err(DlpackError::UnsupportedDevice(metal))
+    );
+    drop(managed);
+    assert_eq!(DELETED.load(Ordering::SeqCst), before + 2);
+
+    assert!(matches!(
+        unsafe { ManagedTensor::from_raw(std::ptr::null_mut()) },
+        Err(DlpackError::InvalidTensor(_))
+    ));
+}
+
+#[cfg(target_vendor = "apple")]
+#[test]
+fn test_dlpack_tensor_data() {
+    use crate::tensor_data::MPSGraphTensorData;
+
+    let tensor_data =
+        MPSGraphTensorData::new(&[1.0f32, 2.0, 3.0, 4.0], &[2, 2], MPSDataType::Float32);
+    let managed = tensor_data.to_dlpack().unwrap();
+    assert_eq!(managed.device(), DLDevice::CPU);
+    assert_eq!(managed.shape(), &[2, 2]);
+    assert_eq!(
+        managed.to_host::<f32>().unwrap().to_vec(),
+        vec![1.0, 2.0, 3.0, 4.0]
+    );
+
+    let imported = managed.to_tensor_data().unwrap();
+    assert_eq!(imported.shape().dimensions(), vec![2, 2]);
+    assert_eq!(
+        imported.to_host::<f32>().unwrap().to_vec(),
+        vec![1.0, 2.0, 3.0, 4.0]
+    );
+}
@@ -0,0 +1,92 @@
+use crate::core::MPSDataType;
+use crate::dims::Shape;
+use crate::dot::DotOptions;
+use crate::ir::Graph;
+
+#[cfg(target_vendor = "apple")]
+use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
+use crate::shape::MPSShape;
+
+#[test]
+fn test_ir_to_dot() {
+    let mut graph = Graph::new();
+    let x = graph.placeholder(&Shape::from_i64(&[-1, 3]), MPSDataType::Float32, Some("x"));
+    let w = graph.constant(&[1.0f32; 6], &[3, 2], MPSDataType::Float32);
+    let y = graph.matmul(x, w, Some("y"));
+    let z = graph.relu(y, None);
+    // Not reachable from the target, so not exported
+    graph.sigmoid(x, Some("unused"));
+
+    let dot = graph.to_dot(&[z], &DotOptions::new());
+    assert_eq!(
+        dot,
+        r#"digraph {
+    node [shape=box];
+    n0 [label="x\nplaceholder"];
+    n1 [label="constant"];
+    n2 [label="y\nmatmul"];
+    n3 [label="Relu"];
+    n4 [label="%3", shape=ellipse];
+    n0 -> n2 [label="[?, 3] Float32"];
+    n1 -> n2 [label="[3, 2] Float32"];
+    n2 -> n3 [label="[?, 2] Float32"];
+    n3 -> n4 [label="[?, 2] Float32"];
+}
+"#
+    );
+}
+
+#[test]
+fn test_ir_to_dot_clusters() {
+    let mut graph = Graph::new();
+    let x = graph.placeholder(&Shape::from_static(&[2]), MPSDataType::Float32, Some("x"));
+    let a = graph.relu(x, Some("encoder/layer0/relu"));
+    let b = graph.sigmoid(a, Some("encoder/layer1/sigmoid"));
+    let c = graph.add(a, b, Some("encoder/add \"sum\""));
+
+    let dot = graph.to_dot(&[c], &DotOptions::new().with_clusters("/"));
+    assert_eq!(
+        dot,
+        r#"digraph {
+    node [shape=box];
+    n0 [label="x\nplaceholder"];
+    n4 [label="encoder/add \"sum\"", shape=ellipse];
+    subgraph cluster_0 {
+        label="encoder";
+        n3 [label="encoder/add \"sum\"\nAdd"];
+        subgraph cluster_1 {
+            label="layer0";
+            n1 [label="encoder/layer0/relu\nRelu"];
+        }
+        subgraph cluster_2 {
+            label="layer1";
+            n2 [label="encoder/layer1/sigmoid\nSigmoid"];
+        }
+    }
+    n0 -> n1 [label="[2] Float32"];
+    n1 -> n2 [label="[2] Float32"];
+    n1 -> n3 [label="[2] Float32"];
+    n2 -> n3 [label="[2] Float32"];
+    n3 -> n4 [label="[2] Float32"];
+}
+"#
+    );
+}
+
+#[cfg(target_vendor = "apple")]
+#[test]
+fn test_mpsgraph_to_dot() {
+    let graph = MPSGraph::new();
+    let shape = MPSShape::from_slice(&[2, 3]);
+    let x = graph.placeholder(&shape, MPSDataType::Float32, Some("block/x"));
+    let y = graph.placeholder(&shape, MPSDataType::Float32, Some("block/y"));
+    let sum = graph.add(&x, &y, Some("block/sum"));
+
+    let dot = graph.to_dot(&[sum], &DotOptions::new().with_clusters("/"));
+    assert!(dot.starts_with("digraph {\n"));
+    assert!(dot.contains("label=\"block\";"));
+    assert!(dot.contains("[label=\"block/sum\"]"));
+    assert_eq!(dot.matches("[label=\"[2, 3] Float32\"]").count(), 3);
+    assert!(!dot.contains("style=dashed"));
+}
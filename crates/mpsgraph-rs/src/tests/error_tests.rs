@@ -0,0 +1,187 @@
+use crate::core::MPSDataType;
+use crate::error::{FeedProblem, MPSGraphError};
+#[cfg(target_vendor = "apple")]
+use crate::{
+    executable::MPSGraphExecutable, graph::MPSGraph, shape::MPSShape,
+    tensor_data::MPSGraphTensorData,
+};
+#[cfg(target_vendor = "apple")]
+use std::collections::HashMap;
+
+#[test]
+fn test_error_display() {
+    let error = MPSGraphError::ShapeMismatch {
+        tensor: String::from("input"),
+        expected: vec![-1, 4],
+        actual: vec![2, 3],
+    };
+    assert_eq!(
+        error.to_string(),
+        "shape mismatch for tensor 'input': placeholder expects [-1, 4], feed has [2, 3]"
+    );
+
+    let error = MPSGraphError::CommandBuffer {
+        domain: String::from("MTLCommandBufferErrorDomain"),
+        code: 2,
+        description: String::from("Timeout"),
+    };
+    assert_eq!(
+        error.to_string(),
+        "command buffer failed (MTLCommandBufferErrorDomain code 2): Timeout"
+    );
+}
+
+#[test]
+fn test_feed_problems() {
+    let check = |expected: Option<&[i64]>, actual: &[usize], actual_type| {
+        FeedProblem::check("x", expected, MPSDataType::Float32, actual, actual_type)
+    };
+    assert!(check(Some(&[-1, 3]), &[5, 3], MPSDataType::Float32).is_empty());
+    assert!(check(None, &[2, 2, 2], MPSDataType::Float32).is_empty());
+    assert_eq!(
+        check(Some(&[]), &[1], MPSDataType::Int32),
+        vec![
+            FeedProblem::DataType {
+                tensor: String::from("x"),
+                expected: MPSDataType::Float32,
+                actual: MPSDataType::Int32,
+            },
+            FeedProblem::Shape {
+                tensor: String::from("x"),
+                expected: vec![],
+                actual: vec![1],
+            },
+        ]
+    );
+    assert_eq!(
+        check(Some(&[-1, 3]), &[5, 4], MPSDataType::Float32).len(),
+        1
+    );
+
+    let error = MPSGraphError::InvalidFeeds(vec![
+        FeedProblem::Missing {
+            tensor: String::from("y"),
+        },
+        FeedProblem::Unused {
+            tensor: String::from("z"),
+        },
+    ]);
+    assert_eq!(
+        error.to_string(),
+        "2 invalid feed(s): placeholder 'y' is not fed; 'z' is fed but the targets don't use it"
+    );
+}
+
+#[test]
+#[cfg(target_vendor = "apple")]
+fn test_validate_feeds_reports_every_problem() {
+    let graph = MPSGraph::new();
+    let a = graph.placeholder(
+        &MPSShape::from_shape(&crate::dims::Shape::from_i64(&[-1, 2])),
+        MPSDataType::Float32,
+        Some("a"),
+    );
+    let b = graph.placeholder(&MPSShape::from_slice(&[2]), MPSDataType::Float32, Some("b"));
+    let c = graph.placeholder(&MPSShape::from_slice(&[2]), MPSDataType::Int32, Some("c"));
+    let unused = graph.placeholder(&MPSShape::from_slice(&[1]), MPSDataType::Float32, Some("u"));
+    let sum = graph.add(&a, &b, None);
+    let result = graph.add(&sum, &graph.cast(&c, MPSDataType::Float32, None), None);
+
+    let mut feeds = HashMap::new();
+    feeds.insert(
+        a.clone(),
+        MPSGraphTensorData::new(&[0.0f32; 6], &[3, 2], MPSDataType::Float32),
+    );
+    assert!(graph
+        .validate_feeds(&feeds, std::slice::from_ref(&sum))
+        .is_err());
+    feeds.insert(
+        b.clone(),
+        MPSGraphTensorData::new(&[0.0f32; 2], &[2], MPSDataType::Float32),
+    );
+    assert_eq!(
+        graph.validate_feeds(&feeds, std::slice::from_ref(&sum)),
+        Ok(())
+    );
+
+    feeds.insert(
+        b,
+        MPSGraphTensorData::new(&[0i32; 3], &[3], MPSDataType::Int32),
+    );
+    feeds.insert(
+        unused,
+        MPSGraphTensorData::new(&[0.0f32], &[1], MPSDataType::Float32),
+    );
+    match graph.validate_feeds(&feeds, &[result]) {
+        Err(MPSGraphError::InvalidFeeds(problems)) => {
+            let names: Vec<&str> = problems.iter().map(|p| p.tensor()).collect();
+            assert_eq!(names, ["c", "b", "b", "u"]);
+            assert!(matches!(problems[0], FeedProblem::Missing { .. }));
+            assert!(matches!(problems[3], FeedProblem::Unused { .. }));
+        }
+        other => panic!("expected InvalidFeeds, got {:?}", other),
+    }
+}
+
+#[test]
+#[cfg(target_vendor = "apple")]
+fn test_try_run_rejects_wrong_data_type() {
+    let graph = MPSGraph::new();
+    let shape = MPSShape::from_slice(&[2, 2]);
+    let a = graph.placeholder(&shape, MPSDataType::Float32, Some("A"));
+    let result = graph.add(&a, &a, None);
+
+    let data = MPSGraphTensorData::new(&[1i32, 2, 3, 4], &[2, 2], MPSDataType::Int32);
+    let mut feeds = HashMap::new();
+    feeds.insert(a, data);
+
+    match graph.try_run_with_feeds(&feeds, &[result]) {
+        Err(MPSGraphError::InvalidFeeds(problems)) => assert_eq!(
+            problems,
+            [FeedProblem::DataType {
+                tensor: "A".to_string(),
+                expected: MPSDataType::Float32,
+                actual: MPSDataType::Int32,
+            }]
+        ),
+        other => panic!("expected InvalidFeeds, got {:?}", other.map(|r| r.len())),
+    }
+}
+
+#[test]
+#[cfg(target_vendor = "apple")]
+fn test_try_run_rejects_wrong_shape() {
+    let graph = MPSGraph::new();
+    let shape = MPSShape::from_slice(&[2, 2]);
+    let a = graph.placeholder(&shape, MPSDataType::Float32, Some("A"));
+    let result = graph.add(&a, &a, None);
+
+    let data = MPSGraphTensorData::new(&[1.0f32, 2.0, 3.0], &[3], MPSDataType::Float32);
+    let mut feeds = HashMap::new();
+    feeds.insert(a, data);
+
+    match graph.try_run_with_feeds(&feeds, &[result]) {
+        Err(MPSGraphError::InvalidFeeds(problems)) => assert_eq!(
+            problems,
+            [FeedProblem::Shape {
+                tensor: "A".to_string(),
+                expected: vec![2, 2],
+                actual: vec![3],
+            }]
+        ),
+        other => panic!("expected InvalidFeeds, got {:?}", other.map(|r| r.len())),
+    }
+}
+
+#[test]
+#[cfg(target_vendor = "apple")]
+fn test_try_load_missing_package() {
+    let result = MPSGraphExecutable::try_from_serialized_package(
+        "file:///nonexistent/model.mpsgraphpackage",
+        None,
+    );
+    assert!(matches!(
+        result,
+        Err(MPSGraphError::PackageLoadFailed { .. })
+    ));
+}
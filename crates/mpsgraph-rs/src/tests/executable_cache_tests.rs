@@ -0,0 +1,243 @@
+use crate::core::{MPSDataType, MPSGraphOptimization};
+use crate::dims::Shape;
+use crate::executable_cache::{
+    CacheError, CacheKey, CompilationOptions, FeedSignature, Fingerprint, Fingerprinter, Manifest,
+};
+use crate::ir::Graph;
+
+fn key(graph: u64, feeds: u64) -> CacheKey {
+    CacheKey {
+        graph: Fingerprint::from_u64(graph),
+        feeds: Fingerprint::from_u64(feeds),
+    }
+}
+
+fn model(weight: f32) -> Graph {
+    let mut graph = Graph::new();
+    let x = graph.placeholder(&Shape::from_i64(&[-1, 2]), MPSDataType::Float32, Some("x"));
+    let w = graph.constant(&[weight; 4], &[2, 2], MPSDataType::Float32);
+    graph.matmul(x, w, Some("y"));
+    graph
+}
+
+#[test]
+fn test_fingerprints() {
+    // FNV-1a test vectors
+    assert_eq!(Fingerprinter::new().finish().to_u64(), 0xcbf29ce484222325);
+    let mut fingerprinter = Fingerprinter::new();
+    fingerprinter.write_bytes(b"a");
+    assert_eq!(fingerprinter.finish().to_u64(), 0xaf63dc4c8601ec8c);
+
+    let fingerprint = |parts: &[&str]| {
+        let mut fingerprinter = Fingerprinter::new();
+        parts.iter().for_each(|part| fingerprinter.write_str(part));
+        fingerprinter.finish()
+    };
+    assert_ne!(fingerprint(&["ab", "c"]), fingerprint(&["a", "bc"]));
+
+    assert_eq!(model(1.0).fingerprint(), model(1.0).fingerprint());
+    assert_ne!(model(1.0).fingerprint(), model(2.0).fingerprint());
+
+    let text = Fingerprint::from_u64(0xabc).to_string();
+    assert_eq!(text, "0000000000000abc");
+    assert_eq!(
+        text.parse::<Fingerprint>(),
+        Ok(Fingerprint::from_u64(0xabc))
+    );
+    assert!("abc".parse::<Fingerprint>().is_err());
+
+    let options = CompilationOptions::default();
+    assert_eq!(
+        options.fingerprint(),
+        CompilationOptions::default().fingerprint()
+    );
+    assert_ne!(
+        options.fingerprint(),
+        options
+            .with_optimization_level(MPSGraphOptimization::Level0)
+            .fingerprint()
+    );
+}
+
+#[test]
+fn test_feed_signature() {
+    let a = FeedSignature::new()
+        .with(0, &[4, 2], MPSDataType::Float32)
+        .with(3, &[4], MPSDataType::Int32);
+    let b = FeedSignature::new().with(3, &[4], MPSDataType::Int32).with(
+        0,
+        &[4, 2],
+        MPSDataType::Float32,
+    );
+    assert_eq!(a, b);
+    assert_eq!(a.fingerprint(), b.fingerprint());
+    assert_eq!(a.len(), 2);
+
+    let batched = FeedSignature::new()
+        .with(0, &[8, 2], MPSDataType::Float32)
+        .with(3, &[4], MPSDataType::Int32);
+    assert_ne!(a.fingerprint(), batched.fingerprint());
+    let half = FeedSignature::new()
+        .with(0, &[4, 2], MPSDataType::Float16)
+        .with(3, &[4], MPSDataType::Int32);
+    assert_ne!(a.fingerprint(), half.fingerprint());
+
+    let cache_key = CacheKey::new(model(1.0).fingerprint(), &a);
+    assert_eq!(cache_key.to_string().parse(), Ok(cache_key));
+    assert_eq!(
+        CacheKey::from_file_name(&cache_key.file_name()),
+        Some(cache_key)
+    );
+    assert_eq!(CacheKey::from_file_name("manifest.txt"), None);
+}
+
+#[test]
+fn test_manifest_lru_and_round_trip() {
+    let options = Fingerprint::from_u64(7);
+    let mut manifest = Manifest::new(options);
+    assert!(manifest.insert(key(1, 1), 2).is_empty());
+    assert!(manifest.insert(key(2, 1), 2).is_empty());
+    assert!(manifest.touch(&key(1, 1)));
+    assert_eq!(manifest.insert(key(3, 1), 2), vec![key(2, 1)]);
+    assert_eq!(manifest.keys(), vec![key(1, 1), key(3, 1)]);
+    assert!(!manifest.touch(&key(2, 1)));
+
+    let text = manifest.to_string();
+    assert_eq!(
+        text,
+        "mpsgraph-executable-cache 1\n\
+         options 0000000000000007\n\
+         entry 0000000000000001-0000000000000001 3\n\
+         entry 0000000000000003-0000000000000001 4\n"
+    );
+    let mut parsed = Manifest::parse(&text).unwrap();
+    assert_eq!(parsed, manifest);
+    assert_eq!(parsed.insert(key(4, 1), 2), vec![key(1, 1)]);
+    assert_eq!(parsed.shrink_to(0), vec![key(3, 1), key(4, 1)]);
+
+    let mut disabled = Manifest::new(options);
+    assert_eq!(disabled.insert(key(5, 5), 0), vec![key(5, 5)]);
+}
+
+#[test]
+fn test_manifest_invalidation() {
+    let mut manifest = Manifest::new(CompilationOptions::default().fingerprint());
+    manifest.insert(key(1, 1), 4);
+    manifest.insert(key(2, 2), 4);
+
+    assert!(manifest
+        .retarget(CompilationOptions::default().fingerprint())
+        .is_empty());
+    let debug = CompilationOptions::default().with_debug_compile(true);
+    assert_eq!(
+        manifest.retarget(debug.fingerprint()),
+        vec![key(1, 1), key(2, 2)]
+    );
+    assert!(manifest.is_empty());
+    assert_eq!(manifest.options(), debug.fingerprint());
+
+    let error = |text: &str| Manifest::parse(text).unwrap_err();
+    assert!(matches!(error(""), CacheError::Manifest { line: 1, .. }));
+    assert!(matches!(
+        error("mpsgraph-executable-cache 2\noptions 0000000000000000"),
+        CacheError::Manifest { line: 1, message } if message.contains("version")
+    ));
+    assert!(matches!(
+        error("mpsgraph-executable-cache 1\nentry 0000000000000001-0000000000000001 1"),
+        CacheError::Manifest { message, .. } if message.contains("options")
+    ));
+    assert!(matches!(
+        error(
+            "mpsgraph-executable-cache 1\noptions 0000000000000000\n\
+             entry 0000000000000001-0000000000000001 1\n\
+             entry 0000000000000001-0000000000000001 2"
+        ),
+        CacheError::Manifest { line: 4, .. }
+    ));
+    assert!(matches!(
+        error("mpsgraph-executable-cache 1\noptions 0000000000000000\nentry 12 1"),
+        CacheError::Manifest { line: 3, .. }
+    ));
+}
+
+#[cfg(target_vendor = "apple")]
+#[test]
+fn test_executable_cache() {
+    use crate::device::MPSGraphDevice;
+    use crate::executable_cache::ExecutableCache;
+    use crate::tensor_data::MPSGraphTensorData;
+    use std::collections::HashMap;
+
+    let build = |weight: f32| {
+        let mut graph = Graph::new();
+        let x = graph.placeholder(&Shape::from_i64(&[2]), MPSDataType::Float32, Some("x"));
+        let w = graph.constant(&[weight; 2], &[2], MPSDataType::Float32);
+        let y = graph.add(x, w, Some("y"));
+        let lowered = graph.lower_to_mpsgraph();
+        let targets = [lowered.tensor(y).clone()];
+        let mut feeds = HashMap::new();
+        feeds.insert(
+            lowered.tensor(x).clone(),
+            MPSGraphTensorData::new(&[1.0f32, 2.0], &[2], MPSDataType::Float32),
+        );
+        let key = CacheKey::new(
+            graph.fingerprint(),
+            &lowered.graph().feed_signature(&feeds, &targets),
+        );
+        (lowered, feeds, targets, key)
+    };
+    let (lowered, feeds, targets, key) = build(1.0);
+    let (rebuilt, rebuilt_feeds, rebuilt_targets, rebuilt_key) = build(1.0);
+    let (heavier, heavier_feeds, heavier_targets, heavier_key) = build(2.0);
+    assert_eq!(key, rebuilt_key);
+    // The weights are part of the key even though MPSGraph doesn't expose them
+    assert_ne!(key, heavier_key);
+
+    let directory =
+        std::env::temp_dir().join(format!("mpsgraph-executable-cache-{}", std::process::id()));
+    let device = MPSGraphDevice::new();
+    let options = CompilationOptions::default();
+    let mut cache = ExecutableCache::new(options, 4)
+        .with_directory(&directory, 4)
+        .unwrap();
+    cache
+        .get_or_compile_with_key(key, lowered.graph(), &device, &feeds, &targets)
+        .unwrap();
+    cache
+        .get_or_compile_with_key(
+            rebuilt_key,
+            rebuilt.graph(),
+            &device,
+            &rebuilt_feeds,
+            &rebuilt_targets,
+        )
+        .unwrap();
+    assert_eq!(cache.len(), 1);
+    cache
+        .get_or_compile_with_key(
+            heavier_key,
+            heavier.graph(),
+            &device,
+            &heavier_feeds,
+            &heavier_targets,
+        )
+        .unwrap();
+    assert_eq!(cache.len(), 2);
+
+    let manifest =
+        Manifest::parse(&std::fs::read_to_string(directory.join("manifest.txt")).unwrap()).unwrap();
+    if manifest.contains(&key) {
+        // Reopening the directory finds the serialized package
+        let mut reopened = ExecutableCache::new(options, 4)
+            .with_directory(&directory, 4)
+            .unwrap();
+        assert!(reopened.get(&key).unwrap().is_some());
+        reopened
+            .set_options(options.with_debug_compile(true))
+            .unwrap();
+        assert!(reopened.get(&key).unwrap().is_none());
+        assert!(!directory.join(key.file_name()).exists());
+    }
+
+    let _ = std::fs::remove_dir_all(&directory);
+}
@@ -0,0 +1,391 @@
+use crate::cast::f32_to_f16;
+use crate::core::MPSDataType;
+use crate::gguf::{
+    self, GgmlType, GgufError, GgufFile, GgufTensor, GgufValue, Unpacked, LUT_ENTRIES,
+};
+use std::path::PathBuf;
+
+#[cfg(target_vendor = "apple")]
+use crate::graph::MPSGraph;
+#[cfg(target_vendor = "apple")]
+use std::collections::HashMap;
+
+const QUANTIZED: [GgmlType; 6] = [
+    GgmlType::Q4_0,
+    GgmlType::Q4_1,
+    GgmlType::Q5_0,
+    GgmlType::Q8_0,
+    GgmlType::Q4_K,
+    GgmlType::Q6_K,
+];
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("mpsgraph-{}-{}.gguf", name, std::process::id()))
+}
+
+fn f16_bytes(value: f32) -> [u8; 2] {
+    f32_to_f16(value).to_le_bytes()
+}
+
+/// Byte ranges of the f16 scales and minimums in a block
+fn f16_fields(ggml_type: GgmlType) -> &'static [usize] {
+    match ggml_type {
+        GgmlType::Q4_1 | GgmlType::Q4_K => &[0, 2],
+        GgmlType::Q6_K => &[208],
+        _ => &[0],
+    }
+}
+
+/// Deterministic pseudo-random blocks with finite f16 scales
+fn random_blocks(ggml_type: GgmlType, count: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) as u32
+    };
+    let mut data: Vec<u8> = (0..count * ggml_type.block_bytes())
+        .map(|_| next() as u8)
+        .collect();
+    for block in data.chunks_exact_mut(ggml_type.block_bytes()) {
+        for &field in f16_fields(ggml_type) {
+            let value = (next() % 2000) as f32 / 1000.0 - 1.0;
+            block[field..field + 2].copy_from_slice(&f16_bytes(value));
+        }
+    }
+    data
+}
+
+#[test]
+fn test_ggml_type_codes() {
+    for code in 0..=30 {
+        match GgmlType::try_from(code) {
+            Ok(ggml_type) => assert_eq!(ggml_type as u32, code),
+            Err(error) => {
+                assert!(matches!(error, GgufError::UnknownType(c) if c == code));
+                assert!(matches!(code, 4 | 5));
+            }
+        }
+    }
+    assert!(GgmlType::try_from(31).is_err());
+
+    assert_eq!(GgmlType::Q4_K.size_in_bytes(512), Some(288));
+    assert_eq!(GgmlType::Q8_0.size_in_bytes(33), None);
+    assert_eq!(GgmlType::F32.size_in_bytes(usize::MAX), None);
+    assert_eq!(GgmlType::BF16.data_type(), Some(MPSDataType::BFloat16));
+    assert_eq!(GgmlType::Q6_K.data_type(), None);
+}
+
+#[test]
+fn test_gguf_round_trip() {
+    let weight = random_blocks(GgmlType::Q4_0, 4, 1);
+    let norm: Vec<u8> = [1.0f32, 2.0, 3.0]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let metadata = vec![
+        (
+            "general.architecture".to_string(),
+            GgufValue::String("llama".into()),
+        ),
+        ("general.alignment".to_string(), GgufValue::U32(64)),
+        ("llama.context_length".to_string(), GgufValue::U64(4096)),
+        ("llama.rope.freq_base".to_string(), GgufValue::F32(10000.0)),
+        ("tokenizer.add_bos".to_string(), GgufValue::Bool(true)),
+        (
+            "tokenizer.tokens".to_string(),
+            GgufValue::Array(vec![
+                GgufValue::String("<s>".into()),
+                GgufValue::String("</s>".into()),
+            ]),
+        ),
+    ];
+    let tensors = [
+        (
+            "norm.weight",
+            GgufTensor::new(GgmlType::F32, &[3], &norm).unwrap(),
+        ),
+        (
+            "blk.0.attn_q.weight",
+            GgufTensor::new(GgmlType::Q4_0, &[2, 64], &weight).unwrap(),
+        ),
+    ];
+
+    let path = temp_path("round-trip");
+    gguf::save(&path, &metadata, &tensors).unwrap();
+    let file = GgufFile::open(&path).unwrap();
+    assert!(file.is_mapped());
+    assert_eq!(file.version(), 3);
+    assert_eq!(file.metadata(), metadata.as_slice());
+    assert_eq!(file.alignment(), 64);
+    assert_eq!(
+        file.get("llama.context_length").unwrap().as_i64(),
+        Some(4096)
+    );
+    assert_eq!(
+        file.get("general.architecture").unwrap().as_str(),
+        Some("llama")
+    );
+    let tokens = file.get("tokenizer.tokens").unwrap().as_array().unwrap();
+    assert_eq!(tokens[1].as_str(), Some("</s>"));
+
+    let infos = file.tensors();
+    assert_eq!(infos[0].name, "norm.weight");
+    assert_eq!(infos[1].shape, vec![2, 64]);
+    assert_eq!(infos[1].ggml_type, GgmlType::Q4_0);
+    assert_eq!(infos[1].offset, 64);
+
+    let tensor = file.tensor("blk.0.attn_q.weight").unwrap();
+    assert_eq!(tensor.data(), weight.as_slice());
+    assert_eq!(
+        file.tensor("norm.weight").unwrap().dequantize().unwrap(),
+        vec![1.0, 2.0, 3.0]
+    );
+    assert!(matches!(
+        file.tensor("missing"),
+        Err(GgufError::MissingTensor(name)) if name == "missing"
+    ));
+    drop(file);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_dequantize_reference_blocks() {
+    // Q4_0: (nibble - 8) * d, low nibbles first
+    let mut block = f16_bytes(2.0).to_vec();
+    block.extend((0..16u8).map(|j| j | ((15 - j) << 4)));
+    let expected: Vec<f32> = (0..16)
+        .map(|j| (j - 8) as f32 * 2.0)
+        .chain((0..16).map(|j| (7 - j) as f32 * 2.0))
+        .collect();
+    assert_eq!(gguf::dequantize(GgmlType::Q4_0, &block).unwrap(), expected);
+
+    // Q4_1: nibble * d + m
+    let mut block = f16_bytes(1.0).to_vec();
+    block.extend(f16_bytes(-2.0));
+    block.extend((0..16u8).map(|j| j | (j << 4)));
+    let expected: Vec<f32> = (0..32).map(|j| (j % 16) as f32 - 2.0).collect();
+    assert_eq!(gguf::dequantize(GgmlType::Q4_1, &block).unwrap(), expected);
+
+    // Q5_0: the fifth bit of elements 0, 16 and 31 comes from qh
+    let mut block = f16_bytes(0.5).to_vec();
+    block.extend((1u32 | (1 << 16) | (1 << 31)).to_le_bytes());
+    block.extend([0u8; 16]);
+    let mut expected = vec![-8.0f32; 32];
+    expected[0] = 0.0;
+    expected[16] = 0.0;
+    expected[31] = 0.0;
+    assert_eq!(gguf::dequantize(GgmlType::Q5_0, &block).unwrap(), expected);
+
+    // Q8_0: q * d
+    let mut block = f16_bytes(0.5).to_vec();
+    block.extend((-16i8..16).map(|q| q as u8));
+    let expected: Vec<f32> = (-16..16).map(|q| q as f32 * 0.5).collect();
+    assert_eq!(gguf::dequantize(GgmlType::Q8_0, &block).unwrap(), expected);
+
+    // Q4_K: eight 32-element sub-blocks with 6-bit scales and minimums
+    let mut block = f16_bytes(1.0).to_vec();
+    block.extend(f16_bytes(1.0));
+    block.extend([1, 2 | 0x40, 3, 4, 0, 1, 0, 1, 0x12, 0, 0, 0]);
+    block.extend([0x31u8; 128]);
+    let expected: Vec<f32> = [1.0f32, 5.0, 3.0, 11.0, 1.0, 48.0, 0.0, 0.0]
+        .iter()
+        .flat_map(|&value| [value; 32])
+        .collect();
+    assert_eq!(gguf::dequantize(GgmlType::Q4_K, &block).unwrap(), expected);
+
+    // Q6_K: d * scale * (q - 32) over sixteen 16-element sub-blocks
+    let mut block = vec![0u8; 210];
+    block[0] = 0x21;
+    block[128] = 0b11_10_01_00;
+    let scales: Vec<u8> = (0..16)
+        .map(|i| match i {
+            0 => 2,
+            15 => -1i8 as u8,
+            _ => 1,
+        })
+        .collect();
+    block[192..208].copy_from_slice(&scales);
+    block[208..].copy_from_slice(&f16_bytes(0.5));
+    let mut expected = vec![-16.0f32; 256];
+    expected[1..16].fill(-32.0);
+    expected[240..].fill(16.0);
+    expected[0] = -31.0;
+    expected[32] = -8.0;
+    expected[64] = 1.0;
+    expected[96] = 8.0;
+    assert_eq!(gguf::dequantize(GgmlType::Q6_K, &block).unwrap(), expected);
+}
+
+#[test]
+fn test_unpack_matches_reference() {
+    for ggml_type in QUANTIZED {
+        let data = random_blocks(ggml_type, 8, ggml_type as u64);
+        let reference = gguf::dequantize(ggml_type, &data).unwrap();
+        let unpacked = gguf::unpack(ggml_type, &data).unwrap();
+        let values = unpacked.dequantize();
+        assert_eq!(values.len(), reference.len());
+        for (index, (value, expected)) in values.iter().zip(&reference).enumerate() {
+            assert_eq!(
+                value.to_bits(),
+                expected.to_bits(),
+                "{:?} element {}",
+                ggml_type,
+                index
+            );
+        }
+
+        match (ggml_type, unpacked) {
+            (
+                GgmlType::Q4_1 | GgmlType::Q4_K,
+                Unpacked::Lut {
+                    indices,
+                    lut,
+                    block_size,
+                },
+            ) => {
+                assert_eq!(block_size, 32);
+                assert_eq!(indices.len(), reference.len() / 2);
+                assert_eq!(lut.len(), reference.len() / 32 * LUT_ENTRIES);
+            }
+            (
+                _,
+                Unpacked::Affine {
+                    values,
+                    data_type,
+                    scales,
+                    zero_point,
+                    block_size,
+                },
+            ) => {
+                assert_eq!(values.len(), reference.len());
+                assert_eq!(scales.len(), reference.len() / block_size);
+                let expected = match ggml_type {
+                    GgmlType::Q4_0 => (MPSDataType::UInt8, 8, 32),
+                    GgmlType::Q5_0 => (MPSDataType::UInt8, 16, 32),
+                    GgmlType::Q8_0 => (MPSDataType::Int8, 0, 32),
+                    _ => (MPSDataType::UInt8, 32, 16),
+                };
+                assert_eq!((data_type, zero_point, block_size), expected);
+            }
+            (ggml_type, unpacked) => panic!("{:?} unpacked to {:?}", ggml_type, unpacked),
+        }
+    }
+
+    let half: Vec<u8> = [1.5f32, -2.0].iter().flat_map(|&v| f16_bytes(v)).collect();
+    assert_eq!(
+        gguf::unpack(GgmlType::F16, &half).unwrap(),
+        Unpacked::Plain {
+            data_type: MPSDataType::Float16,
+            data: half.clone()
+        }
+    );
+    assert_eq!(
+        gguf::dequantize(GgmlType::F16, &half).unwrap(),
+        vec![1.5, -2.0]
+    );
+}
+
+#[test]
+fn test_gguf_errors() {
+    let valid = gguf::serialize(&[], &[]).unwrap();
+    assert_eq!(
+        GgufFile::from_bytes(valid.clone()).unwrap().tensors().len(),
+        0
+    );
+
+    let mut bad_magic = valid.clone();
+    bad_magic[0] = b'X';
+    assert!(matches!(
+        GgufFile::from_bytes(bad_magic),
+        Err(GgufError::Format(_))
+    ));
+
+    let mut old = valid.clone();
+    old[4..8].copy_from_slice(&1u32.to_le_bytes());
+    assert!(matches!(
+        GgufFile::from_bytes(old),
+        Err(GgufError::UnsupportedVersion(1))
+    ));
+
+    // A huge tensor count must not allocate before running out of bytes
+    let mut huge = valid.clone();
+    huge[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(matches!(
+        GgufFile::from_bytes(huge),
+        Err(GgufError::Format(_))
+    ));
+
+    let data = random_blocks(GgmlType::Q8_0, 2, 7);
+    let tensor = GgufTensor::new(GgmlType::Q8_0, &[64], &data).unwrap();
+    let bytes = gguf::serialize(&[], &[("w", tensor)]).unwrap();
+    assert!(matches!(
+        GgufFile::from_bytes(bytes[..bytes.len() - 1].to_vec()),
+        Err(GgufError::Format(_))
+    ));
+
+    // The ggml type code follows the name, rank and single dimension of the tensor info
+    let mut unknown = bytes.clone();
+    let type_offset = 24 + 8 + 1 + 4 + 8;
+    unknown[type_offset..type_offset + 4].copy_from_slice(&4u32.to_le_bytes());
+    assert!(matches!(
+        GgufFile::from_bytes(unknown),
+        Err(GgufError::UnknownType(4))
+    ));
+
+    assert!(matches!(
+        GgufTensor::new(GgmlType::Q8_0, &[2, 32], &data[1..]),
+        Err(GgufError::SizeMismatch {
+            expected: 68,
+            actual: 67
+        })
+    ));
+    assert!(matches!(
+        GgufTensor::new(GgmlType::Q8_0, &[16], &data),
+        Err(GgufError::Format(_))
+    ));
+    assert!(matches!(
+        GgufTensor::new(GgmlType::F32, &[usize::MAX, 2], &data),
+        Err(GgufError::Format(ref reason)) if reason.contains("more bytes")
+    ));
+    assert!(matches!(
+        gguf::dequantize(GgmlType::Q5_K, &[0; 176]),
+        Err(GgufError::UnsupportedType(GgmlType::Q5_K))
+    ));
+    assert!(matches!(
+        gguf::unpack(GgmlType::IQ4_NL, &[0; 18]),
+        Err(GgufError::UnsupportedType(GgmlType::IQ4_NL))
+    ));
+}
+
+#[cfg(target_vendor = "apple")]
+#[test]
+fn test_gguf_load_into_graph() {
+    let q4_0 = random_blocks(GgmlType::Q4_0, 4, 3);
+    let q4_k = random_blocks(GgmlType::Q4_K, 1, 4);
+    let tensors = [
+        (
+            "q4_0",
+            GgufTensor::new(GgmlType::Q4_0, &[2, 64], &q4_0).unwrap(),
+        ),
+        (
+            "q4_k",
+            GgufTensor::new(GgmlType::Q4_K, &[256], &q4_k).unwrap(),
+        ),
+    ];
+    let file = GgufFile::from_bytes(gguf::serialize(&[], &tensors).unwrap()).unwrap();
+
+    let graph = MPSGraph::new();
+    let loaded = file.load_into(&graph, MPSDataType::Float32).unwrap();
+    for (name, tensor) in &tensors {
+        let result = &loaded[*name];
+        assert_eq!(result.data_type(), MPSDataType::Float32);
+        let results = graph.run_with_feeds(&HashMap::new(), std::slice::from_ref(result));
+        let values = results[result].to_host::<f32>().unwrap().into_vec();
+        let expected = tensor.dequantize().unwrap();
+        for (value, expected) in values.iter().zip(&expected) {
+            assert!((value - expected).abs() <= 1e-6 * expected.abs().max(1.0));
+        }
+    }
+}
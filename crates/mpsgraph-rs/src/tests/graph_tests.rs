@@ -66,6 +66,37 @@ fn test_graph_constant() {
     assert_eq!(shape_f32.dimensions(), vec![2, 2]);
 }
 
+#[test]
+fn test_graph_constant_narrow_types() {
+    let graph = MPSGraph::new();
+    let shape = MPSShape::from_slice(&[4]);
+
+    let constant_i8 = graph.constant(&[1i8, -2, 3, -4], &shape, MPSDataType::Int8);
+    let constant_u16 = graph.constant(&[1u16, 2, 3, 4], &shape, MPSDataType::UInt16);
+    let constant_bool = graph.constant(&[true, false, true, true], &shape, MPSDataType::Bool);
+    let scalar_bool = graph.constant_scalar(true, MPSDataType::Bool);
+
+    assert_eq!(constant_i8.data_type(), MPSDataType::Int8);
+    assert_eq!(constant_u16.data_type(), MPSDataType::UInt16);
+    assert_eq!(constant_bool.data_type(), MPSDataType::Bool);
+    assert_eq!(scalar_bool.data_type(), MPSDataType::Bool);
+}
+
+#[cfg(feature = "half")]
+#[test]
+fn test_graph_constant_half() {
+    let graph = MPSGraph::new();
+    let shape = MPSShape::from_slice(&[2]);
+
+    let weights = [half::f16::from_f32(0.5), half::f16::from_f32(-1.5)];
+    let constant_f16 = graph.constant(&weights, &shape, MPSDataType::Float16);
+    let scalar_bf16 = graph.constant_scalar(half::bf16::from_f32(2.0), MPSDataType::BFloat16);
+
+    assert_eq!(constant_f16.data_type(), MPSDataType::Float16);
+    assert_eq!(constant_f16.dimensions(), vec![2]);
+    assert_eq!(scalar_bf16.data_type(), MPSDataType::BFloat16);
+}
+
 #[test]
 fn test_graph_run_with_feeds() {
     let graph = MPSGraph::new();
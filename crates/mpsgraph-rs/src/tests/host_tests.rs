@@ -0,0 +1,233 @@
+use crate::core::MPSDataType;
+use crate::dims::ShapeError;
+use crate::error::MPSGraphError;
+use crate::host::{DynHostTensor, HostTensor};
+
+#[test]
+fn test_dyn_host_tensor() {
+    let tensor = DynHostTensor::from_slice(&[2, 2], &[1.0f32, 2.0, 3.0, 4.0]).unwrap();
+    assert_eq!(tensor.shape(), &[2, 2]);
+    assert_eq!(tensor.data_type(), MPSDataType::Float32);
+    assert_eq!(tensor.element_count(), 4);
+    assert_eq!(tensor.bytes().len(), 16);
+    assert_eq!(tensor.to_vec::<f32>().unwrap(), vec![1.0, 2.0, 3.0, 4.0]);
+    assert_eq!(
+        tensor.to_vec::<i32>(),
+        Err(MPSGraphError::DataTypeMismatch {
+            expected: MPSDataType::Float32,
+            actual: MPSDataType::Int32,
+        })
+    );
+
+    let flags = DynHostTensor::from_slice(&[3], &[true, false, true]).unwrap();
+    assert_eq!(flags.bytes(), &[1, 0, 1]);
+    assert_eq!(flags.to_vec::<bool>().unwrap(), vec![true, false, true]);
+
+    let scalar = DynHostTensor::scalar(7i64);
+    assert!(scalar.shape().is_empty());
+    assert_eq!(scalar.element_count(), 1);
+    assert_eq!(scalar.to_vec::<i64>().unwrap(), vec![7]);
+
+    let error = DynHostTensor::new(&[3], MPSDataType::Int16, vec![0; 4]).unwrap_err();
+    assert_eq!(
+        error,
+        MPSGraphError::SizeMismatch {
+            expected: 6,
+            actual: 4
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "buffer holds 4 bytes but the shape and data type need 6"
+    );
+    assert!(DynHostTensor::from_slice(&[3], &[1u8, 2]).is_err());
+    assert_eq!(
+        DynHostTensor::new(&[usize::MAX, 2], MPSDataType::Float32, vec![0; 8]).unwrap_err(),
+        MPSGraphError::SizeMismatch {
+            expected: usize::MAX,
+            actual: 8
+        }
+    );
+
+    // 4-bit elements are packed two to a byte
+    let packed = DynHostTensor::new(&[2, 3], MPSDataType::Int4, vec![0; 3]).unwrap();
+    assert_eq!(packed.element_count(), 6);
+    assert_eq!(
+        DynHostTensor::new(&[3], MPSDataType::UInt4, vec![0; 3]).unwrap_err(),
+        MPSGraphError::SizeMismatch {
+            expected: 2,
+            actual: 3
+        }
+    );
+}
+
+#[test]
+fn test_host_tensor_layout() {
+    let tensor = HostTensor::new(&[2, 3], vec![0i32, 1, 2, 3, 4, 5]).unwrap();
+    assert_eq!(tensor.strides(), &[3, 1]);
+    assert_eq!(tensor.data_type(), MPSDataType::Int32);
+    assert!(tensor.is_contiguous());
+    assert_eq!(tensor[&[1, 2][..]], 5);
+    assert_eq!(tensor.get(&[2, 0]), None);
+    assert_eq!(tensor.get(&[1]), None);
+
+    let transposed = tensor.clone().permute(&[1, 0]).unwrap();
+    assert_eq!(transposed.shape(), &[3, 2]);
+    assert_eq!(transposed.strides(), &[1, 3]);
+    assert!(!transposed.is_contiguous());
+    assert_eq!(transposed.storage(), tensor.storage());
+    assert_eq!(transposed[&[2, 1][..]], 5);
+    assert_eq!(transposed.to_vec(), vec![0, 3, 1, 4, 2, 5]);
+    assert_eq!(transposed, transposed.to_contiguous());
+    assert_ne!(transposed, tensor);
+
+    // Reshaping a permuted tensor copies it into C order first
+    let flat = transposed.clone().reshape(&[-1]).unwrap();
+    assert_eq!(flat.shape(), &[6]);
+    assert_eq!(flat.storage(), &[0, 3, 1, 4, 2, 5]);
+    assert!(matches!(
+        tensor.clone().reshape(&[4, -1]),
+        Err(MPSGraphError::Shape(ShapeError::InvalidReshape { .. }))
+    ));
+    assert!(matches!(
+        tensor.clone().permute(&[0, 0]),
+        Err(MPSGraphError::Shape(ShapeError::InvalidPermutation { .. }))
+    ));
+    assert_eq!(tensor.clone().permute(&[-1, 0]).unwrap(), transposed);
+
+    let mut rows = HostTensor::from_strided(&[2, 2], &[4, 1], vec![1.0f32; 6]).unwrap();
+    rows[&[1, 1][..]] = 7.0;
+    assert_eq!(rows.into_vec(), vec![1.0, 1.0, 1.0, 7.0]);
+    assert_eq!(
+        HostTensor::from_strided(&[2, 2], &[4, 1], vec![0u8; 5]).unwrap_err(),
+        MPSGraphError::SizeMismatch {
+            expected: 6,
+            actual: 5
+        }
+    );
+    assert!(matches!(
+        HostTensor::from_strided(&[2, 2], &[1], vec![0u8; 4]),
+        Err(MPSGraphError::InvalidStrides { .. })
+    ));
+    assert!(HostTensor::new(&[2], vec![0u8; 3]).is_err());
+    assert_eq!(
+        HostTensor::from_strided(&[3, 2], &[usize::MAX, 1], vec![0u8; 4]).unwrap_err(),
+        MPSGraphError::SizeMismatch {
+            expected: usize::MAX,
+            actual: 4
+        }
+    );
+    assert!(HostTensor::new(&[usize::MAX, 3], vec![0u8; 4]).is_err());
+
+    let dynamic = DynHostTensor::from(transposed.clone());
+    assert_eq!(dynamic.to_vec::<i32>().unwrap(), vec![0, 3, 1, 4, 2, 5]);
+    assert_eq!(dynamic.to_typed::<i32>().unwrap(), transposed);
+    assert!(dynamic.to_typed::<f32>().is_err());
+    assert_eq!(
+        HostTensor::scalar(true).iter().copied().collect::<Vec<_>>(),
+        vec![true]
+    );
+}
+
+#[cfg(target_vendor = "apple")]
+#[test]
+fn test_host_tensor_data_round_trip() {
+    use crate::tensor_data::MPSGraphTensorData;
+
+    let tensor = HostTensor::new(&[2, 3], vec![0i32, 1, 2, 3, 4, 5])
+        .unwrap()
+        .permute(&[1, 0])
+        .unwrap();
+    let tensor_data = MPSGraphTensorData::from_host(&tensor);
+    assert_eq!(tensor_data.shape().dimensions(), vec![3, 2]);
+    assert_eq!(tensor_data.data_type(), MPSDataType::Int32);
+
+    let read = tensor_data.to_host::<i32>().unwrap();
+    assert!(read.is_contiguous());
+    assert_eq!(read, tensor);
+    assert_eq!(
+        tensor_data.to_host::<f32>().unwrap_err(),
+        MPSGraphError::DataTypeMismatch {
+            expected: MPSDataType::Int32,
+            actual: MPSDataType::Float32,
+        }
+    );
+}
+
+#[cfg(target_vendor = "apple")]
+#[test]
+fn test_run_named() {
+    use crate::device::MPSGraphDevice;
+    use crate::error::FeedProblem;
+    use crate::graph::MPSGraph;
+    use crate::shape::MPSShape;
+    use std::collections::HashMap;
+
+    let graph = MPSGraph::new();
+    let shape = MPSShape::from_slice(&[2]);
+    let x = graph.placeholder(&shape, MPSDataType::Float32, Some("x"));
+    let y = graph.placeholder(&shape, MPSDataType::Float32, Some("y"));
+    let sum = graph.add(&x, &y, Some("sum"));
+    let product = graph.multiply(&x, &y, Some("product"));
+    let _twin = graph.placeholder(&shape, MPSDataType::Float32, Some("twin"));
+    let _twin = graph.placeholder(&shape, MPSDataType::Float32, Some("twin"));
+    // Names stay indexed while the graph is held, even without tensor handles
+    drop(graph.exp(&x, Some("exp")));
+    assert!(graph.tensor_named("exp").is_ok());
+
+    assert_eq!(graph.placeholder_tensors().len(), 4);
+    assert_eq!(graph.tensor_named("sum").unwrap(), sum);
+    assert_eq!(
+        graph.tensor_named("missing"),
+        Err(MPSGraphError::UnknownTensorName(String::from("missing")))
+    );
+
+    let mut feeds = HashMap::new();
+    feeds.insert(
+        "x",
+        DynHostTensor::from_slice(&[2], &[1.0f32, 2.0]).unwrap(),
+    );
+    feeds.insert(
+        "y",
+        DynHostTensor::from_slice(&[2], &[3.0f32, 4.0]).unwrap(),
+    );
+    let results = graph.run_named(&feeds, &["sum", "product"]).unwrap();
+    assert_eq!(results["sum"].to_vec::<f32>().unwrap(), vec![4.0, 6.0]);
+    assert_eq!(results["product"].to_vec::<f32>().unwrap(), vec![3.0, 8.0]);
+
+    assert_eq!(
+        graph.run_named(&feeds, &["sum", "sum"]),
+        Err(MPSGraphError::DuplicateTensorName(String::from("sum")))
+    );
+    assert_eq!(
+        graph.run_named(&feeds, &["total"]),
+        Err(MPSGraphError::UnknownTensorName(String::from("total")))
+    );
+    let mut twin_feeds = feeds.clone();
+    twin_feeds.insert(
+        "twin",
+        DynHostTensor::from_slice(&[2], &[0.0f32; 2]).unwrap(),
+    );
+    assert_eq!(
+        graph.run_named(&twin_feeds, &["sum"]),
+        Err(MPSGraphError::DuplicateTensorName(String::from("twin")))
+    );
+
+    let tensor_feeds = HashMap::from([
+        (x, feeds["x"].to_tensor_data()),
+        (y, feeds["y"].to_tensor_data()),
+    ]);
+    let executable = graph
+        .try_compile(&MPSGraphDevice::new(), &tensor_feeds, &[sum, product], None)
+        .unwrap();
+    let results = executable.run_named(&feeds, &["product"]).unwrap();
+    assert_eq!(results["product"].to_vec::<f32>().unwrap(), vec![3.0, 8.0]);
+
+    let mut partial = feeds.clone();
+    partial.remove("y");
+    assert!(matches!(
+        executable.run_named(&partial, &["sum"]),
+        Err(MPSGraphError::InvalidFeeds(ref problems))
+            if problems == &[FeedProblem::Missing { tensor: "y".to_string() }]
+    ));
+}
@@ -0,0 +1,386 @@
+use crate::core::MPSDataType;
+use crate::dims::Shape;
+use crate::ir::{Graph, InterpretError, TensorId, Value};
+use crate::loss_ops::MPSGraphLossReductionType;
+use crate::scatter_nd_ops::MPSGraphScatterMode;
+use crate::tensor_shape_ops::MPSGraphSliceMasks;
+use std::collections::HashMap;
+
+fn run(graph: &Graph, feeds: &[(TensorId, Value)], target: TensorId) -> Value {
+    let feeds: HashMap<TensorId, Value> = feeds.iter().cloned().collect();
+    let mut results = graph
+        .interpret(&feeds, &[target])
+        .expect("interpretation should succeed");
+    results.remove(&target).unwrap()
+}
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn test_elementwise() {
+    let mut graph = Graph::new();
+    let a = graph.placeholder(&Shape::from_static(&[2, 1]), MPSDataType::Float32, None);
+    let b = graph.constant(&[1.0f32, -2.0, 3.0], &[3], MPSDataType::Float32);
+    let sum = graph.add(a, b, None);
+    let low = graph.constant_scalar(0.0, MPSDataType::Float32);
+    let high = graph.constant_scalar(4.0, MPSDataType::Float32);
+    let clamped = graph.clamp(sum, low, high, None);
+    let less = graph.less_than(a, b, None);
+    let selected = graph.select(less, a, b, None);
+    let erf = graph.erf(b, None);
+
+    let feeds = [(a, Value::new(&[2, 1], vec![0.5f32, 2.0]))];
+    let result = run(&graph, &feeds, clamped);
+    assert_eq!(result.shape(), &[2, 3]);
+    assert_eq!(result.as_f32().unwrap(), &[1.5, 0.0, 3.5, 3.0, 0.0, 4.0]);
+
+    let result = run(&graph, &feeds, less);
+    assert_eq!(
+        result.as_bool().unwrap(),
+        &[true, false, true, false, false, true]
+    );
+    let result = run(&graph, &feeds, selected);
+    assert_eq!(result.as_f32().unwrap(), &[0.5, -2.0, 0.5, 1.0, -2.0, 2.0]);
+
+    let result = run(&graph, &feeds, erf);
+    assert_close(
+        result.as_f32().unwrap(),
+        &[0.8427008, -0.9953223, 0.9999779],
+    );
+}
+
+#[test]
+fn test_int32_arithmetic() {
+    let mut graph = Graph::new();
+    let a = graph.constant(&[7i32, -7, i32::MAX, 5], &[4], MPSDataType::Int32);
+    let b = graph.constant(&[2i32, 2, 1, 0], &[4], MPSDataType::Int32);
+    let sum = graph.add(a, b, None);
+    let quotient = graph.divide(a, b, None);
+    let modulo = graph.floor_modulo(a, b, None);
+    let sign = graph.sign(b, None);
+    let cast = graph.cast(a, MPSDataType::Float32, None);
+
+    let result = run(&graph, &[], sum);
+    assert_eq!(result.as_i32().unwrap(), &[9, -5, i32::MIN, 5]);
+    let result = run(&graph, &[], quotient);
+    assert_eq!(result.as_i32().unwrap(), &[3, -3, i32::MAX, 0]);
+    let result = run(&graph, &[], modulo);
+    assert_eq!(result.as_i32().unwrap(), &[1, 1, 0, 0]);
+    let result = run(&graph, &[], sign);
+    assert_eq!(result.as_i32().unwrap(), &[1, 1, 1, 0]);
+    let result = run(&graph, &[], cast);
+    assert_eq!(result.data_type(), MPSDataType::Float32);
+}
+
+#[test]
+fn test_bitwise_and_nan_propagation() {
+    let mut graph = Graph::new();
+    let a = graph.constant(&[12i32, -1, 5, i32::MIN], &[4], MPSDataType::Int32);
+    let b = graph.constant(&[10i32, 1, 32, 31], &[4], MPSDataType::Int32);
+    let and = graph.bitwise_and(a, b, None);
+    let xor = graph.bitwise_xor(a, b, None);
+    let not = graph.bitwise_not(a, None);
+    let count = graph.bitwise_population_count(a, None);
+    let left = graph.left_shift(a, b, None);
+    let right = graph.right_shift(a, b, None);
+
+    assert_eq!(run(&graph, &[], and).as_i32().unwrap(), &[8, 1, 0, 0]);
+    assert_eq!(
+        run(&graph, &[], xor).as_i32().unwrap(),
+        &[6, -2, 37, i32::MIN + 31]
+    );
+    assert_eq!(
+        run(&graph, &[], not).as_i32().unwrap(),
+        &[-13, 0, -6, i32::MAX]
+    );
+    assert_eq!(run(&graph, &[], count).as_i32().unwrap(), &[2, 32, 2, 1]);
+    assert_eq!(run(&graph, &[], left).as_i32().unwrap(), &[12288, -2, 0, 0]);
+    assert_eq!(run(&graph, &[], right).as_i32().unwrap(), &[0, -1, 0, -1]);
+
+    let x = graph.constant(&[1.0f32, f32::NAN, -0.0, 3.0], &[4], MPSDataType::Float32);
+    let y = graph.constant(&[2.0f32, 0.0, 1.0, f32::NAN], &[4], MPSDataType::Float32);
+    let minimum = graph.minimum(x, y, None);
+    let propagated = graph.minimum_with_nan_propagation(x, y, None);
+    let signbit = graph.signbit(x, None);
+    let square = graph.abs_square(y, None);
+    let nans = graph.is_nan(x, None);
+    let xnor = graph.logical_xnor(nans, signbit, None);
+
+    assert_eq!(
+        run(&graph, &[], minimum).as_f32().unwrap(),
+        &[1.0, 0.0, -0.0, 3.0]
+    );
+    let result = run(&graph, &[], propagated);
+    let values = result.as_f32().unwrap();
+    assert_eq!(values[0], 1.0);
+    assert!(values[1].is_nan() && values[3].is_nan());
+    assert_eq!(
+        run(&graph, &[], signbit).as_bool().unwrap(),
+        &[false, false, true, false]
+    );
+    assert_eq!(
+        run(&graph, &[], square).as_f32().unwrap()[..3],
+        [4.0, 0.0, 1.0]
+    );
+    assert_eq!(
+        run(&graph, &[], xnor).as_bool().unwrap(),
+        &[true, false, false, true]
+    );
+}
+
+#[test]
+fn test_matmul_and_reductions() {
+    let mut graph = Graph::new();
+    let x = graph.constant(
+        &[1.0f64, 2.0, 3.0, 4.0, 5.0, 6.0],
+        &[2, 1, 3],
+        MPSDataType::Float64,
+    );
+    let w = graph.constant(
+        &[1.0f64, 0.0, 0.0, 1.0, 1.0, 1.0],
+        &[3, 2],
+        MPSDataType::Float64,
+    );
+    let v = graph.constant(&[1.0f64, 1.0, 1.0], &[3], MPSDataType::Float64);
+    let product = graph.batch_matmul(x, w, None);
+    let dot = graph.matmul(x, v, None);
+    let sum = graph.reduction_sum_with_tensor_axes(x, Some(&[0, 2]), None);
+    let arg_max = graph.reduction_arg_maximum_with_tensor_axis(x, -1, None);
+    let mean = graph.mean(x, &[-1], None);
+    let flags = graph.constant(&[true, true, false, true], &[2, 2], MPSDataType::Bool);
+    let xor = graph.reduction_xor_with_tensor_axis(flags, 1, None);
+    let holes = graph.constant(&[1.0f32, f32::NAN, 3.0, 2.0], &[2, 2], MPSDataType::Float32);
+    let maximum = graph.reduction_maximum_with_tensor_axis(holes, 1, None);
+    let propagated = graph.reduction_maximum_propagate_nan_with_tensor_axis(holes, 1, None);
+
+    let result = run(&graph, &[], product);
+    assert_eq!(result.shape(), &[2, 1, 2]);
+    assert_eq!(result.as_f64().unwrap(), &[4.0, 5.0, 10.0, 11.0]);
+
+    let result = run(&graph, &[], dot);
+    assert_eq!(result.shape(), &[2, 1]);
+    assert_eq!(result.as_f64().unwrap(), &[6.0, 15.0]);
+
+    let result = run(&graph, &[], sum);
+    assert_eq!(result.shape(), &[1, 1, 1]);
+    assert_eq!(result.as_f64().unwrap(), &[21.0]);
+
+    let result = run(&graph, &[], arg_max);
+    assert_eq!(result.shape(), &[2, 1, 1]);
+    assert_eq!(result.as_i32().unwrap(), &[2, 2]);
+
+    let result = run(&graph, &[], mean);
+    assert_eq!(result.shape(), &[2, 1, 1]);
+    assert_eq!(result.as_f64().unwrap(), &[2.0, 5.0]);
+
+    let result = run(&graph, &[], xor);
+    assert_eq!(result.as_bool().unwrap(), &[false, true]);
+
+    let result = run(&graph, &[], maximum);
+    assert_eq!(result.as_f32().unwrap(), &[1.0, 3.0]);
+    let result = run(&graph, &[], propagated);
+    assert!(result.as_f32().unwrap()[0].is_nan());
+    assert_eq!(result.as_f32().unwrap()[1], 3.0);
+}
+
+#[test]
+fn test_shape_ops() {
+    let mut graph = Graph::new();
+    let values: Vec<i32> = (0..6).collect();
+    let x = graph.constant(&values, &[2, 3], MPSDataType::Int32);
+    let transposed = graph.transpose(x, &[1, 0], None);
+    let concatenated = graph.concatenate(&[x, x], 1, None);
+    let stacked = graph.stack(&[x, x], 1, None);
+    let parts = graph.split(x, 3, 1, None);
+    let tiled = graph.tile(x, &[1, 2], None);
+    let reversed = graph.reverse(x, &[-1], None);
+    let sliced = graph.slice(x, 1, -2, 2, None);
+    // x[::-1, ::2]
+    let masks = MPSGraphSliceMasks {
+        start_mask: 0b11,
+        end_mask: 0b11,
+        ..Default::default()
+    };
+    let strided = graph.strided_slice(x, &[0, 0], &[0, 0], &[-1, 2], &masks, None);
+
+    let result = run(&graph, &[], transposed);
+    assert_eq!(result.as_i32().unwrap(), &[0, 3, 1, 4, 2, 5]);
+    let result = run(&graph, &[], concatenated);
+    assert_eq!(
+        result.as_i32().unwrap(),
+        &[0, 1, 2, 0, 1, 2, 3, 4, 5, 3, 4, 5]
+    );
+    let result = run(&graph, &[], stacked);
+    assert_eq!(result.shape(), &[2, 2, 3]);
+    assert_eq!(
+        result.as_i32().unwrap(),
+        &[0, 1, 2, 0, 1, 2, 3, 4, 5, 3, 4, 5]
+    );
+    let result = run(&graph, &[], parts[2]);
+    assert_eq!(result.as_i32().unwrap(), &[2, 5]);
+    let result = run(&graph, &[], tiled);
+    assert_eq!(
+        result.as_i32().unwrap(),
+        &[0, 1, 2, 0, 1, 2, 3, 4, 5, 3, 4, 5]
+    );
+    let result = run(&graph, &[], reversed);
+    assert_eq!(result.as_i32().unwrap(), &[2, 1, 0, 5, 4, 3]);
+    let result = run(&graph, &[], sliced);
+    assert_eq!(result.as_i32().unwrap(), &[1, 2, 4, 5]);
+    let result = run(&graph, &[], strided);
+    assert_eq!(result.shape(), &[2, 2]);
+    assert_eq!(result.as_i32().unwrap(), &[3, 5, 0, 2]);
+}
+
+#[test]
+fn test_gather_and_scatter() {
+    let mut graph = Graph::new();
+    let table = graph.constant(
+        &[0.0f32, 1.0, 2.0, 3.0, 4.0, 5.0],
+        &[3, 2],
+        MPSDataType::Float32,
+    );
+    let indices = graph.constant(&[2i32, -3], &[2], MPSDataType::Int32);
+    let gathered = graph.gather(table, indices, 0, 0, None);
+    let along = graph.constant(&[1i32, 0, 0, 0, 1, 1], &[3, 2], MPSDataType::Int32);
+    let gathered_along = graph.gather_along_axis(1, table, along, None);
+    let updates = graph.constant(&[1.0f32, 2.0, 3.0, 4.0], &[2, 2], MPSDataType::Float32);
+    let same = graph.constant(&[1i32, 1], &[2], MPSDataType::Int32);
+    let scattered = graph.scatter(
+        updates,
+        same,
+        &Shape::from_static(&[3, 2]),
+        0,
+        MPSGraphScatterMode::Add,
+        None,
+    );
+
+    let result = run(&graph, &[], gathered);
+    assert_eq!(result.shape(), &[2, 2]);
+    assert_eq!(result.as_f32().unwrap(), &[4.0, 5.0, 0.0, 1.0]);
+    let result = run(&graph, &[], gathered_along);
+    assert_eq!(result.as_f32().unwrap(), &[1.0, 0.0, 2.0, 2.0, 5.0, 5.0]);
+    let result = run(&graph, &[], scattered);
+    assert_eq!(result.as_f32().unwrap(), &[0.0, 0.0, 4.0, 6.0, 0.0, 0.0]);
+
+    let out_of_range = graph.constant(&[3i32], &[1], MPSDataType::Int32);
+    let bad = graph.gather(table, out_of_range, 0, 0, None);
+    let error = graph.interpret(&HashMap::new(), &[bad]).unwrap_err();
+    assert_eq!(
+        error,
+        InterpretError::IndexOutOfRange {
+            op: "gather",
+            index: 3,
+            size: 3,
+        }
+    );
+}
+
+#[test]
+fn test_softmax_and_loss() {
+    let mut graph = Graph::new();
+    let logits = graph.constant(&[0.0f32, 0.0, 1.0, 3.0], &[2, 2], MPSDataType::Float32);
+    let labels = graph.constant(&[1.0f32, 0.0, 0.0, 1.0], &[2, 2], MPSDataType::Float32);
+    let softmax = graph.softmax(logits, -1, None);
+    let loss =
+        graph.softmax_cross_entropy(logits, labels, -1, MPSGraphLossReductionType::None, None);
+    let mean =
+        graph.softmax_cross_entropy(logits, labels, -1, MPSGraphLossReductionType::Mean, None);
+    let one =
+        graph.constant_scalar_with_shape(1.0, &Shape::from_static(&[1, 1]), MPSDataType::Float32);
+    let gradient = graph.softmax_cross_entropy_gradient(
+        one,
+        logits,
+        labels,
+        -1,
+        MPSGraphLossReductionType::Mean,
+        None,
+    );
+
+    let e = 1.0f32 / (1.0 + 2.0f32.exp());
+    let result = run(&graph, &[], softmax);
+    assert_close(result.as_f32().unwrap(), &[0.5, 0.5, e, 1.0 - e]);
+
+    let second = -(1.0 - e).ln();
+    let result = run(&graph, &[], loss);
+    assert_eq!(result.shape(), &[2, 1]);
+    assert_close(result.as_f32().unwrap(), &[2.0f32.ln(), second]);
+
+    let result = run(&graph, &[], mean);
+    assert_eq!(result.shape(), &[1, 1]);
+    assert_close(result.as_f32().unwrap(), &[(2.0f32.ln() + second) / 2.0]);
+
+    // d(mean loss)/d(logits) = (softmax - labels) / rows for one-hot labels
+    let result = run(&graph, &[], gradient);
+    assert_eq!(result.shape(), &[2, 2]);
+    assert_close(result.as_f32().unwrap(), &[-0.25, 0.25, e / 2.0, -e / 2.0]);
+}
+
+#[test]
+fn test_interpret_errors() {
+    let mut graph = Graph::new();
+    let x = graph.placeholder(&Shape::from_i64(&[-1, 2]), MPSDataType::Float32, Some("x"));
+    let y = graph.exp(x, None);
+    let h = graph.placeholder(&Shape::from_static(&[2]), MPSDataType::Float16, None);
+    let z = graph.exp(h, None);
+
+    // Only the placeholders the targets reach need feeds
+    let feeds = [(x, Value::new(&[3, 2], vec![0.0f32; 6]))];
+    assert_eq!(run(&graph, &feeds, y).shape(), &[3, 2]);
+
+    let error = graph.interpret(&HashMap::new(), &[y]).unwrap_err();
+    assert_eq!(error, InterpretError::MissingFeed(x));
+
+    let mut feeds = HashMap::new();
+    feeds.insert(x, Value::new(&[3], vec![0.0f32; 3]));
+    let error = graph.interpret(&feeds, &[y]).unwrap_err();
+    assert!(matches!(error, InterpretError::FeedMismatch { tensor, .. } if tensor == x));
+
+    let mut feeds = HashMap::new();
+    feeds.insert(h, Value::new(&[2], vec![0.0f32; 2]));
+    let error = graph.interpret(&feeds, &[z]).unwrap_err();
+    assert!(matches!(error, InterpretError::FeedMismatch { .. }));
+
+    let unknown = TensorId(42);
+    let error = graph.interpret(&HashMap::new(), &[unknown]).unwrap_err();
+    assert_eq!(error, InterpretError::UnknownTensor(unknown));
+}
+
+#[test]
+#[cfg(target_vendor = "apple")]
+fn test_interpreter_matches_mpsgraph() {
+    let mut graph = Graph::new();
+    let x = graph.placeholder(&Shape::from_static(&[2, 3]), MPSDataType::Float32, None);
+    let w = graph.constant(
+        &[0.5f32, -1.0, 2.0, 0.0, 1.0, 1.0],
+        &[3, 2],
+        MPSDataType::Float32,
+    );
+    let product = graph.matmul(x, w, None);
+    let activated = graph.tanh(product, None);
+    let y = graph.softmax(activated, 1, None);
+
+    let input = [1.0f32, 2.0, 3.0, -1.0, 0.5, 0.25];
+    let expected = run(&graph, &[(x, Value::new(&[2, 3], input.to_vec()))], y);
+
+    let lowered = graph.lower_to_mpsgraph();
+    let mut feeds = HashMap::new();
+    feeds.insert(
+        lowered.tensor(x).clone(),
+        crate::tensor_data::MPSGraphTensorData::new(&input, &[2, 3], MPSDataType::Float32),
+    );
+    let target = lowered.tensor(y).clone();
+    let results = lowered
+        .graph()
+        .run_with_feeds(&feeds, std::slice::from_ref(&target));
+    let actual = results[&target]
+        .to_host::<f32>()
+        .expect("result data should be readable")
+        .into_vec();
+    assert_close(&actual[..4], expected.as_f32().unwrap());
+}
@@ -0,0 +1,597 @@
+use crate::convolution_ops::MPSGraphWeightsLayout;
+use crate::core::MPSDataType;
+use crate::dims::{Shape, ShapeError};
+use crate::ir::{Convolution2d, Graph, IrError, OpKind, PoolingOp, ReductionOp, Window2d};
+use crate::loss_ops::MPSGraphLossReductionType;
+use crate::pooling_ops::{MPSGraphPaddingStyle, MPSGraphTensorNamedDataLayout};
+use crate::resize_ops::MPSGraphResizeMode;
+#[cfg(target_vendor = "apple")]
+use crate::tensor_data::MPSGraphTensorData;
+use crate::tensor_shape_ops::MPSGraphSliceMasks;
+#[cfg(target_vendor = "apple")]
+use std::collections::HashMap;
+
+#[test]
+fn test_record_and_inspect() {
+    let mut graph = Graph::new();
+    let x = graph.placeholder(&Shape::from_i64(&[-1, 3]), MPSDataType::Float32, Some("x"));
+    let w = graph.constant(&[1.0f32; 6], &[3, 2], MPSDataType::Float32);
+    let y = graph.matmul(x, w, Some("y"));
+    let z = graph.relu(y, None);
+
+    assert_eq!(graph.operations().len(), 4);
+    assert_eq!(graph.tensors().len(), 4);
+    assert_eq!(graph.placeholders(), vec![x]);
+    assert_eq!(graph.tensor_by_name("y"), Some(y));
+    assert_eq!(graph.shape(z), &Shape::from_i64(&[-1, 2]));
+    assert_eq!(graph.data_type(z), MPSDataType::Float32);
+
+    let producer = graph.producer(y);
+    assert_eq!(producer.kind, OpKind::MatMul);
+    assert_eq!(producer.inputs, vec![x, w]);
+    assert_eq!(producer.outputs, vec![y]);
+    assert_eq!(y.to_string(), "%2");
+}
+
+#[test]
+fn test_elementwise_inference() {
+    let mut graph = Graph::new();
+    let a = graph.placeholder(&Shape::from_static(&[4, 1]), MPSDataType::Float32, None);
+    let b = graph.placeholder(&Shape::from_static(&[3]), MPSDataType::Float32, None);
+
+    let sum = graph.add(a, b, None);
+    assert_eq!(graph.shape(sum), &Shape::from_static(&[4, 3]));
+
+    let less = graph.less_than(a, b, None);
+    assert_eq!(graph.data_type(less), MPSDataType::Bool);
+
+    let nan = graph.is_nan(a, None);
+    assert_eq!(graph.data_type(nan), MPSDataType::Bool);
+
+    let selected = graph.select(less, a, b, None);
+    assert_eq!(graph.shape(selected), &Shape::from_static(&[4, 3]));
+
+    let cast = graph.cast(a, MPSDataType::Int32, None);
+    assert_eq!(graph.data_type(cast), MPSDataType::Int32);
+
+    let signbit = graph.signbit(a, None);
+    assert_eq!(graph.data_type(signbit), MPSDataType::Bool);
+    let nand = graph.logical_nand(less, less, None);
+    assert_eq!(graph.data_type(nand), MPSDataType::Bool);
+
+    let bits = graph.bitwise_and(cast, cast, None);
+    assert_eq!(graph.data_type(bits), MPSDataType::Int32);
+    let error = graph.try_bitwise_xor(a, b, None).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "binary: bitwise operands must be integers, got Float32"
+    );
+    assert!(graph.try_bitwise_not(a, None).is_err());
+}
+
+#[test]
+fn test_matmul_inference() {
+    let mut graph = Graph::new();
+    let batched = graph.placeholder(
+        &Shape::from_static(&[5, 1, 2, 3]),
+        MPSDataType::Float32,
+        None,
+    );
+    let matrix = graph.placeholder(&Shape::from_static(&[4, 3, 6]), MPSDataType::Float32, None);
+    let vector = graph.placeholder(&Shape::from_static(&[6]), MPSDataType::Float32, None);
+
+    let product = graph.matmul(batched, matrix, None);
+    assert_eq!(graph.shape(product), &Shape::from_static(&[5, 4, 2, 6]));
+
+    let reduced = graph.matmul(product, vector, None);
+    assert_eq!(graph.shape(reduced), &Shape::from_static(&[5, 4, 2]));
+}
+
+#[test]
+fn test_shape_op_inference() {
+    let mut graph = Graph::new();
+    let x = graph.placeholder(&Shape::from_static(&[2, 3, 4]), MPSDataType::Float32, None);
+
+    let reshaped = graph.reshape(x, &[6, -1], None);
+    assert_eq!(graph.shape(reshaped), &Shape::from_static(&[6, 4]));
+
+    let transposed = graph.transpose(x, &[2, 0, 1], None);
+    assert_eq!(graph.shape(transposed), &Shape::from_static(&[4, 2, 3]));
+
+    let concatenated = graph.concatenate(&[x, x], 1, None);
+    assert_eq!(graph.shape(concatenated), &Shape::from_static(&[2, 6, 4]));
+
+    let stacked = graph.stack(&[x, x, x], -1, None);
+    assert_eq!(graph.shape(stacked), &Shape::from_static(&[2, 3, 4, 3]));
+
+    let parts = graph.split(x, 2, 2, None);
+    assert_eq!(parts.len(), 2);
+    assert_eq!(graph.shape(parts[1]), &Shape::from_static(&[2, 3, 2]));
+    assert_eq!(graph.producer(parts[0]).outputs, parts);
+
+    let expanded = graph.expand_dims(x, &[0, -1], None);
+    assert_eq!(graph.shape(expanded), &Shape::from_static(&[1, 2, 3, 4, 1]));
+
+    let squeezed = graph.squeeze(expanded, &[], None);
+    assert_eq!(graph.shape(squeezed), &Shape::from_static(&[2, 3, 4]));
+
+    let tiled = graph.tile(x, &[1, 2, 3], None);
+    assert_eq!(graph.shape(tiled), &Shape::from_static(&[2, 6, 12]));
+
+    let flattened = graph.flatten2d(x, 1, None);
+    assert_eq!(graph.shape(flattened), &Shape::from_static(&[2, 12]));
+
+    let sum = graph.reduction_sum_with_tensor_axes(x, Some(&[0, -1]), None);
+    assert_eq!(graph.shape(sum), &Shape::from_static(&[1, 3, 1]));
+    assert_eq!(
+        graph.producer(sum).kind,
+        OpKind::Reduction {
+            op: ReductionOp::Sum,
+            axes: vec![0, 2],
+        }
+    );
+
+    let arg_max = graph.reduction_arg_maximum_with_tensor_axis(x, 1, None);
+    assert_eq!(graph.data_type(arg_max), MPSDataType::Int32);
+    assert_eq!(graph.shape(arg_max), &Shape::from_static(&[2, 1, 4]));
+}
+
+#[test]
+fn test_slice_inference() {
+    let mut graph = Graph::new();
+    let x = graph.placeholder(&Shape::from_static(&[4, 5, 6]), MPSDataType::Float32, None);
+
+    let sliced = graph.slice(x, 1, -3, 2, None);
+    assert_eq!(graph.shape(sliced), &Shape::from_static(&[4, 2, 6]));
+
+    // x[1, ..., ::-2]
+    let masks = MPSGraphSliceMasks {
+        squeeze_mask: 0b001,
+        ellipsis_mask: 0b010,
+        end_mask: 0b100,
+        ..Default::default()
+    };
+    let strided = graph.strided_slice(x, &[1, 0, -1], &[2, 0, 0], &[1, 1, -2], &masks, None);
+    assert_eq!(graph.shape(strided), &Shape::from_static(&[5, 3]));
+}
+
+#[test]
+fn test_gather_scatter_loss_inference() {
+    let mut graph = Graph::new();
+    let table = graph.placeholder(&Shape::from_static(&[10, 8]), MPSDataType::Float32, None);
+    let indices = graph.placeholder(&Shape::from_static(&[2, 3]), MPSDataType::Int32, None);
+
+    let gathered = graph.gather(table, indices, 0, 0, None);
+    assert_eq!(graph.shape(gathered), &Shape::from_static(&[2, 3, 8]));
+
+    let labels = graph.placeholder(&Shape::from_static(&[4, 10]), MPSDataType::Float32, None);
+    let logits = graph.placeholder(&Shape::from_static(&[4, 10]), MPSDataType::Float32, None);
+    let loss =
+        graph.softmax_cross_entropy(logits, labels, -1, MPSGraphLossReductionType::None, None);
+    assert_eq!(graph.shape(loss), &Shape::from_static(&[4, 1]));
+    let mean =
+        graph.softmax_cross_entropy(logits, labels, -1, MPSGraphLossReductionType::Mean, None);
+    assert_eq!(graph.shape(mean), &Shape::from_static(&[1, 1]));
+
+    let gradient = graph.softmax_cross_entropy_gradient(
+        mean,
+        logits,
+        labels,
+        -1,
+        MPSGraphLossReductionType::Mean,
+        None,
+    );
+    assert_eq!(graph.shape(gradient), &Shape::from_static(&[4, 10]));
+}
+
+#[test]
+fn test_invalid_operations() {
+    let mut graph = Graph::new();
+    let a = graph.placeholder(&Shape::from_static(&[2, 3]), MPSDataType::Float32, None);
+    let b = graph.placeholder(&Shape::from_static(&[4, 3]), MPSDataType::Float32, None);
+    let c = graph.placeholder(&Shape::from_static(&[2, 3]), MPSDataType::Int32, None);
+    let indices = graph.placeholder(&Shape::from_static(&[2, 3]), MPSDataType::Int32, None);
+    let before = graph.clone();
+
+    let error = graph
+        .try_add_operation(OpKind::MatMul, &[a, b], None)
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "matmul: secondary dimension 0 has size 4, but primary dimension 1 has size 3"
+    );
+
+    let error = graph
+        .try_add_operation(OpKind::Concat { axis: 0 }, &[a, c], None)
+        .unwrap_err();
+    assert_eq!(
+        error,
+        IrError::DataTypeMismatch {
+            op: "concatenate",
+            expected: MPSDataType::Float32,
+            actual: MPSDataType::Int32,
+        }
+    );
+
+    let error = graph
+        .try_add_operation(OpKind::Softmax { axis: 2 }, &[a], None)
+        .unwrap_err();
+    assert_eq!(
+        error,
+        IrError::Shape {
+            op: "softmax",
+            error: ShapeError::AxisOutOfRange { axis: 2, rank: 2 },
+        }
+    );
+
+    let error = graph
+        .try_add_operation(OpKind::Select, &[a, a, a], None)
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "select: predicate must be Bool, got Float32"
+    );
+
+    let gather = OpKind::Gather {
+        axis: 0,
+        batch_dimensions: 1,
+    };
+    let error = graph
+        .try_add_operation(gather, &[a, indices], None)
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "gather: batch dimensions 1 must not exceed axis 0"
+    );
+
+    let unknown = crate::ir::TensorId(99);
+    let error = graph
+        .try_add_operation(OpKind::Softmax { axis: 0 }, &[unknown], None)
+        .unwrap_err();
+    assert_eq!(error, IrError::UnknownTensor(unknown));
+    assert_eq!(error.to_string(), "tensor %99 is not part of this graph");
+
+    // Failed operations leave the graph untouched
+    assert_eq!(graph, before);
+}
+
+#[test]
+fn test_window_inference() {
+    let mut graph = Graph::new();
+    let image = graph.placeholder(
+        &Shape::from_i64(&[-1, 3, -1, 32]),
+        MPSDataType::Float32,
+        None,
+    );
+    let weights = graph.placeholder(
+        &Shape::from_static(&[8, 3, 3, 3]),
+        MPSDataType::Float32,
+        None,
+    );
+    let descriptor = Convolution2d {
+        strides: [2, 2],
+        padding: [1, 1, 1, 1],
+        ..Default::default()
+    };
+    let conv = graph.convolution_2d(image, weights, &descriptor, None);
+    assert_eq!(graph.shape(conv), &Shape::from_i64(&[-1, 8, -1, 16]));
+
+    // Grouped, dilated NHWC convolution with HWIO weights
+    let image = graph.placeholder(
+        &Shape::from_static(&[2, 10, 10, 6]),
+        MPSDataType::Float32,
+        None,
+    );
+    let weights = graph.placeholder(
+        &Shape::from_static(&[3, 3, 2, 6]),
+        MPSDataType::Float32,
+        None,
+    );
+    let descriptor = Convolution2d {
+        dilations: [2, 2],
+        padding_style: MPSGraphPaddingStyle::TfValid,
+        data_layout: MPSGraphTensorNamedDataLayout::NHWC,
+        weights_layout: MPSGraphWeightsLayout::HWIO,
+        groups: 3,
+        ..Default::default()
+    };
+    let conv = graph.convolution_2d(image, weights, &descriptor, None);
+    assert_eq!(graph.shape(conv), &Shape::from_static(&[2, 6, 6, 6]));
+
+    let image = graph.placeholder(
+        &Shape::from_static(&[1, 2, 5, 7]),
+        MPSDataType::Float32,
+        None,
+    );
+    let window = Window2d {
+        strides: [2, 2],
+        ..Window2d::new([2, 2])
+    };
+    let pooled = graph.max_pooling_2d(image, &window, None);
+    assert_eq!(graph.shape(pooled), &Shape::from_static(&[1, 2, 2, 3]));
+    let ceil = Window2d {
+        ceil_mode: true,
+        ..window
+    };
+    let pooled = graph.avg_pooling_2d(image, &ceil, None);
+    assert_eq!(graph.shape(pooled), &Shape::from_static(&[1, 2, 3, 4]));
+    let same = Window2d {
+        padding_style: MPSGraphPaddingStyle::TfSame,
+        ..Window2d::new([3, 3])
+    };
+    let pooled = graph.l2_norm_pooling_2d(image, &same, None);
+    assert_eq!(graph.shape(pooled), &Shape::from_static(&[1, 2, 5, 7]));
+
+    let image = graph.placeholder(
+        &Shape::from_static(&[2, 3, 4, 4]),
+        MPSDataType::Float32,
+        None,
+    );
+    let columns = graph.im_to_col(image, &window, None);
+    assert_eq!(graph.shape(columns), &Shape::from_static(&[2, 12, 4]));
+    let nhwc = Window2d {
+        data_layout: MPSGraphTensorNamedDataLayout::NHWC,
+        ..window
+    };
+    let image = graph.placeholder(
+        &Shape::from_static(&[2, 4, 4, 3]),
+        MPSDataType::Float32,
+        None,
+    );
+    let columns = graph.im_to_col(image, &nhwc, None);
+    assert_eq!(graph.shape(columns), &Shape::from_static(&[2, 4, 12]));
+
+    let resized = graph.resize(
+        image,
+        [16, 2],
+        MPSGraphResizeMode::Bilinear,
+        true,
+        false,
+        MPSGraphTensorNamedDataLayout::NHWC,
+        None,
+    );
+    assert_eq!(graph.shape(resized), &Shape::from_static(&[2, 16, 2, 3]));
+}
+
+#[test]
+fn test_window_errors() {
+    let mut graph = Graph::new();
+    let image = graph.placeholder(
+        &Shape::from_static(&[1, 4, 3, 3]),
+        MPSDataType::Float32,
+        None,
+    );
+    let weights = graph.placeholder(
+        &Shape::from_static(&[8, 3, 3, 3]),
+        MPSDataType::Float32,
+        None,
+    );
+
+    let kind = OpKind::Convolution2d(Convolution2d::default());
+    let error = graph
+        .try_add_operation(kind, &[image, weights], None)
+        .unwrap_err();
+    assert_eq!(
+        error,
+        IrError::Dimension {
+            op: "convolution_2d",
+            operand: "source",
+            axis: 1,
+            size: 4,
+            reason: String::from(
+                "but weights dimension 1 has 3 input channels per group and groups is 1"
+            ),
+        }
+    );
+
+    let kind = OpKind::Pooling2d {
+        op: PoolingOp::Max,
+        window: Window2d::new([5, 3]),
+    };
+    let error = graph.try_add_operation(kind, &[image], None).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "pooling_2d: source dimension 2 has size 3, but the window spans 5 elements with 0 \
+         elements of padding"
+    );
+
+    let zero_stride = Window2d {
+        strides: [1, 0],
+        ..Window2d::new([2, 2])
+    };
+    let error = graph
+        .try_add_operation(OpKind::ImToCol(zero_stride), &[image], None)
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        IrError::Invalid {
+            op: "im_to_col",
+            ..
+        }
+    ));
+
+    let same = Window2d {
+        padding_style: MPSGraphPaddingStyle::TfSame,
+        ..Window2d::new([2, 2])
+    };
+    let error = graph
+        .try_add_operation(OpKind::ImToCol(same), &[image], None)
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        IrError::Invalid {
+            op: "im_to_col",
+            ..
+        }
+    ));
+
+    let weights = graph.placeholder(
+        &Shape::from_static(&[6, 1, 3, 3]),
+        MPSDataType::Float32,
+        None,
+    );
+    let grouped = Convolution2d {
+        groups: 4,
+        ..Default::default()
+    };
+    let error = graph
+        .try_add_operation(OpKind::Convolution2d(grouped), &[image, weights], None)
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "convolution_2d: weights dimension 0 has size 6, which is not divisible by groups 4"
+    );
+}
+
+#[test]
+fn test_try_builders_return_errors() {
+    let mut graph = Graph::new();
+    let a = graph
+        .try_placeholder(&Shape::from_static(&[2, 3]), MPSDataType::Float32, None)
+        .unwrap();
+    let b = graph.placeholder(&Shape::from_static(&[4]), MPSDataType::Float32, None);
+    let operations = graph.operations().len();
+
+    assert!(matches!(
+        graph.try_add(a, b, None),
+        Err(IrError::Shape {
+            op: "binary",
+            error: ShapeError::IncompatibleBroadcast { .. }
+        })
+    ));
+    assert!(matches!(
+        graph.try_matmul(a, a, None),
+        Err(IrError::Dimension { op: "matmul", .. })
+    ));
+    assert!(matches!(
+        graph.try_reduction_sum_with_tensor_axis(a, 2, None),
+        Err(IrError::Shape {
+            op: "reduction",
+            ..
+        })
+    ));
+    assert!(matches!(
+        graph.try_split(a, 0, 1, None),
+        Err(IrError::Invalid { op: "split", .. })
+    ));
+    assert!(matches!(
+        graph.try_top_k_axis(a, -3, 1, None),
+        Err(IrError::Shape { op: "top_k", .. })
+    ));
+    let masks = MPSGraphSliceMasks::default();
+    assert!(matches!(
+        graph.try_strided_slice(a, &[0, 0, 0], &[1, 1, 1], &[1, 1, 1], &masks, None),
+        Err(IrError::Invalid {
+            op: "strided_slice",
+            ..
+        })
+    ));
+    assert!(matches!(
+        graph.try_tile(a, &[1, i64::MAX], None),
+        Err(IrError::Invalid { op: "tile", .. })
+    ));
+    let unknown = graph.try_relu(b, None).unwrap();
+    let mut other = Graph::new();
+    assert_eq!(
+        other.try_relu(unknown, None),
+        Err(IrError::UnknownTensor(unknown))
+    );
+
+    // Failed builders record nothing
+    assert_eq!(graph.operations().len(), operations + 1);
+    assert_eq!(
+        graph
+            .try_add(a, a, None)
+            .map(|sum| graph.shape(sum).clone()),
+        Ok(Shape::from_static(&[2, 3]))
+    );
+}
+
+#[test]
+#[should_panic(expected = "can't be broadcast")]
+fn test_builder_panics_on_invalid_operands() {
+    let mut graph = Graph::new();
+    let a = graph.placeholder(&Shape::from_static(&[2, 3]), MPSDataType::Float32, None);
+    let b = graph.placeholder(&Shape::from_static(&[4]), MPSDataType::Float32, None);
+    graph.add(a, b, None);
+}
+
+#[test]
+#[cfg(target_vendor = "apple")]
+fn test_checked_mpsgraph_builders() {
+    use crate::graph::MPSGraph;
+    use crate::shape::MPSShape;
+
+    let graph = MPSGraph::new();
+    let a = graph.placeholder(&MPSShape::from_slice(&[2, 3]), MPSDataType::Float32, None);
+    let b = graph.placeholder(&MPSShape::from_slice(&[4]), MPSDataType::Float32, None);
+
+    assert!(matches!(
+        graph.try_add(&a, &b, None),
+        Err(IrError::Shape {
+            op: "binary",
+            error: ShapeError::IncompatibleBroadcast { .. }
+        })
+    ));
+    assert!(matches!(
+        graph.try_matmul(&a, &a, None),
+        Err(IrError::Dimension { op: "matmul", .. })
+    ));
+    assert!(matches!(
+        graph.try_reduction_sum_with_tensor_axes(&a, Some(&[2]), None),
+        Err(IrError::Shape {
+            op: "reduction",
+            ..
+        })
+    ));
+    assert!(matches!(
+        graph.try_select(&a, &a, &a, None),
+        Err(IrError::Invalid { op: "select", .. })
+    ));
+
+    let sum = graph.try_add(&a, &a, None).unwrap();
+    assert_eq!(sum.shape().dimensions(), vec![2, 3]);
+    let reduced = graph
+        .try_reduction_sum_with_tensor_axis(&a, -1, None)
+        .unwrap();
+    assert_eq!(reduced.shape().dimensions(), vec![2, 1]);
+}
+
+#[test]
+#[cfg(target_vendor = "apple")]
+fn test_lower_to_mpsgraph() {
+    let mut graph = Graph::new();
+    let x = graph.placeholder(
+        &Shape::from_static(&[2, 2]),
+        MPSDataType::Float32,
+        Some("x"),
+    );
+    let w = graph.constant(&[1.0f32, 2.0, 3.0, 4.0], &[2, 2], MPSDataType::Float32);
+    let bias = graph.constant_scalar(1.0, MPSDataType::Float32);
+    let product = graph.matmul(x, w, None);
+    let y = graph.add(product, bias, Some("y"));
+    let parts = graph.split(y, 2, 1, None);
+
+    let lowered = graph.lower_to_mpsgraph();
+    let mpsgraph = lowered.graph();
+    assert_eq!(lowered.tensor(y).shape().dimensions(), vec![2, 2]);
+
+    let mut feeds = HashMap::new();
+    feeds.insert(
+        lowered.tensor(x).clone(),
+        MPSGraphTensorData::new(&[1.0f32, 0.0, 0.0, 1.0], &[2, 2], MPSDataType::Float32),
+    );
+    let targets = [lowered.tensor(y).clone(), lowered.tensor(parts[1]).clone()];
+    let results = mpsgraph.run_with_feeds(&feeds, &targets);
+
+    let values = results[&targets[0]]
+        .to_host::<f32>()
+        .expect("result data should be readable")
+        .into_vec();
+    assert_eq!(&values[..4], &[2.0, 3.0, 4.0, 5.0]);
+    let values = results[&targets[1]]
+        .to_host::<f32>()
+        .expect("result data should be readable")
+        .into_vec();
+    assert_eq!(&values[..2], &[3.0, 5.0]);
+}
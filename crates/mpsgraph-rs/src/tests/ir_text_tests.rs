@@ -0,0 +1,192 @@
+use crate::core::MPSDataType;
+use crate::dims::Shape;
+use crate::ir::{Convolution2d, Graph, IrError, ParseError, Window2d};
+use crate::loss_ops::MPSGraphLossReductionType;
+use crate::non_maximum_suppression_ops::MPSGraphNonMaximumSuppressionCoordinateMode;
+use crate::pooling_ops::{MPSGraphPaddingStyle, MPSGraphTensorNamedDataLayout};
+use crate::resize_ops::MPSGraphResizeMode;
+use crate::tensor_shape_ops::MPSGraphSliceMasks;
+
+#[test]
+fn test_print_graph() {
+    let mut graph = Graph::new();
+    let x = graph.placeholder(&Shape::from_i64(&[-1, 3]), MPSDataType::Float32, Some("x"));
+    let w = graph.constant(&[1.0f32; 6], &[3, 2], MPSDataType::Float32);
+    let y = graph.matmul(x, w, Some("y"));
+    let halves = graph.split(y, 2, 1, None);
+    let sum = graph.add(halves[0], halves[1], Some("say \"hi\""));
+    graph.reduction_sum_with_tensor_axis(sum, 0, None);
+
+    assert_eq!(
+        graph.to_string(),
+        r#"%0 = placeholder() {name="x"} : f32[?,3]
+%1 = constant() {data=0x0000803f0000803f0000803f0000803f0000803f0000803f} : f32[3,2]
+%2 = matmul(%0, %1) {name="y"} : f32[?,2]
+%3, %4 = split(%2) {num_splits=2, axis=1} : f32[?,1], f32[?,1]
+%5 = add(%3, %4) {name="say \"hi\""} : f32[?,1]
+%6 = reduction(%5) {op=sum, axes=[0]} : f32[1,1]
+"#
+    );
+}
+
+#[test]
+fn test_text_round_trip() {
+    let mut graph = Graph::new();
+    let images = graph.placeholder(
+        &Shape::from_static(&[1, 3, 8, 8]),
+        MPSDataType::Float16,
+        Some("images"),
+    );
+    let weights = graph.constant(&[0u16; 4 * 3 * 9], &[4, 3, 3, 3], MPSDataType::Float16);
+    let convolution = Convolution2d {
+        padding: [1, 1, 1, 1],
+        ..Default::default()
+    };
+    let features = graph.convolution_2d(images, weights, &convolution, Some("block/conv"));
+    let window = Window2d {
+        strides: [2, 2],
+        padding_style: MPSGraphPaddingStyle::TfSame,
+        ceil_mode: true,
+        ..Window2d::new([2, 2])
+    };
+    let pooled = graph.max_pooling_2d(features, &window, None);
+    let resized = graph.resize(
+        pooled,
+        [8, 8],
+        MPSGraphResizeMode::Bilinear,
+        true,
+        false,
+        MPSGraphTensorNamedDataLayout::NCHW,
+        None,
+    );
+    let wide = graph.cast(resized, MPSDataType::Float32, None);
+    let flat = graph.reshape(wide, &[4, -1], None);
+    let probabilities = graph.softmax(flat, -1, Some("probs"));
+    let (_, indices) = graph.top_k_axis(probabilities, 1, 3, None);
+    let scale = graph.constant_scalar(f64::NEG_INFINITY, MPSDataType::Float32);
+    graph.multiply(probabilities, scale, None);
+    graph.transpose(probabilities, &[1, 0], None);
+    graph.reduction_arg_maximum_with_tensor_axis(indices, 1, None);
+    graph.mean(probabilities, &[0], None);
+    graph.reduction_maximum_propagate_nan_with_tensor_axis(probabilities, 1, None);
+    graph.left_shift(indices, indices, None);
+    graph.bitwise_population_count(indices, None);
+    graph.minimum_with_nan_propagation(probabilities, scale, None);
+    let labels =
+        graph.constant_scalar_with_shape(0.25, &Shape::from_static(&[4, 1]), MPSDataType::Float32);
+    let loss = graph.softmax_cross_entropy(flat, labels, -1, MPSGraphLossReductionType::Sum, None);
+    graph.softmax_cross_entropy_gradient(
+        loss,
+        flat,
+        labels,
+        -1,
+        MPSGraphLossReductionType::Sum,
+        Some("grad"),
+    );
+    let masks = MPSGraphSliceMasks {
+        end_mask: 2,
+        ..Default::default()
+    };
+    graph.strided_slice(flat, &[1, 0], &[3, 0], &[1, 2], &masks, None);
+
+    let boxes = graph.placeholder(&Shape::from_static(&[1, 5, 4]), MPSDataType::Float32, None);
+    let scores = graph.placeholder(&Shape::from_static(&[1, 5, 2]), MPSDataType::Float32, None);
+    graph.non_maximum_suppression(
+        boxes,
+        scores,
+        0.45,
+        0.1,
+        true,
+        MPSGraphNonMaximumSuppressionCoordinateMode::CentersWidthFirst,
+        Some("nms"),
+    );
+
+    let text = graph.to_string();
+    assert!(text.contains("padding_style=tf_same"));
+    assert!(text.contains("value=-inf"));
+    assert!(text.contains("coordinate_mode=centers_width_first"));
+    assert!(text.contains("{op=maximum_propagate_nan, axes=[1]}"));
+    assert!(text.contains("= softmax_cross_entropy_gradient("));
+    let parsed = Graph::parse(&text).unwrap();
+    assert_eq!(parsed, graph);
+    assert_eq!(parsed.to_string(), text);
+}
+
+#[test]
+fn test_parse_hand_written() {
+    let graph: Graph = "
+        // Identifiers only need to be unique
+        %x = placeholder() {name=\"x\"} : f32[2,2]
+
+        %doubled = add(%x, %x) : f32[2,2]
+        %out = relu(%doubled) : f32[2,2]
+    "
+    .parse()
+    .unwrap();
+    assert_eq!(graph.operations().len(), 3);
+    assert_eq!(
+        graph.to_string(),
+        "%0 = placeholder() {name=\"x\"} : f32[2,2]\n\
+         %1 = add(%0, %0) : f32[2,2]\n\
+         %2 = relu(%1) : f32[2,2]\n"
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = |text: &str| Graph::parse(text).unwrap_err();
+
+    assert!(matches!(
+        error("%0 = placeholder() : f32[2]\n%1 = frobnicate(%0) : f32[2]"),
+        ParseError::Syntax { line: 2, message } if message.contains("frobnicate")
+    ));
+    assert!(matches!(
+        error("%0 = relu(%7) : f32[2]"),
+        ParseError::UndefinedValue { line: 1, value } if value == "%7"
+    ));
+    assert!(matches!(
+        error("%0 = placeholder() : f32[2]\n%0 = relu(%0) : f32[2]"),
+        ParseError::Redefinition { line: 2, .. }
+    ));
+    assert!(matches!(
+        error("%0 = placeholder() : f32[2]\n%1 = relu(%0) : f32[3]"),
+        ParseError::TypeMismatch { line: 2, expected, actual }
+            if expected == "f32[3]" && actual == "f32[2]"
+    ));
+    assert!(matches!(
+        error("%0 = placeholder() : f32[2]\n%1 = add(%0) : f32[2]"),
+        ParseError::Ir {
+            line: 2,
+            error: IrError::WrongOperandCount { .. }
+        }
+    ));
+    assert!(matches!(
+        error("%0 = placeholder() : f32[2]\n%1 = softmax(%0) {axis=0, beta=2} : f32[2]"),
+        ParseError::Syntax { message, .. } if message.contains("beta")
+    ));
+    assert!(matches!(
+        error("%0 = placeholder() : f32[2]\n%1 = softmax(%0) : f32[2]"),
+        ParseError::Syntax { message, .. } if message.contains("missing attribute 'axis'")
+    ));
+    assert!(matches!(
+        error("%0 = placeholder() : q8[2]"),
+        ParseError::Syntax { message, .. } if message.contains("q8")
+    ));
+    assert!(matches!(
+        error("%0 = constant() {data=0x0} : u8[1]"),
+        ParseError::Syntax { .. }
+    ));
+    // Element counts that overflow are reported rather than panicking
+    assert!(matches!(
+        error("%0 = constant() {data=0x00} : f32[4611686018427387904,8]"),
+        ParseError::Ir { line: 1, .. }
+    ));
+    assert!(matches!(
+        error("%0 = placeholder() : f32[2]\n%1 = reshape(%0) {shape=[4611686018427387904,8]} : f32[4611686018427387904,8]"),
+        ParseError::Ir { line: 2, .. }
+    ));
+    assert_eq!(
+        error("%0 = relu(%7) : f32[2]").to_string(),
+        "line 1: %7 is not defined"
+    );
+}
@@ -157,6 +157,9 @@ fn test_large_number_of_tensors() {
         // Let them all drop at the end of this scope
     }
 
+    // Every tensor handle released its retain
+    assert_eq!(graph.live_handles(), 0);
+
     // Graph should still be valid
     let new_tensor = graph.placeholder(&shape, MPSDataType::Float32, Some("after_drop"));
     assert_eq!(new_tensor.name(), "after_drop");
@@ -209,3 +212,44 @@ fn test_retain_release_cycle() {
     assert_eq!(a_plus_b.name(), "A+B");
     assert_eq!(c.name(), "A*B");
 }
+
+#[test]
+fn test_tensor_and_operation_handles_are_released() {
+    let graph = MPSGraph::new();
+    let shape = MPSShape::from_slice(&[2, 2]);
+
+    {
+        let a = graph.placeholder(&shape, MPSDataType::Float32, Some("A"));
+        let b = graph.placeholder(&shape, MPSDataType::Float32, Some("B"));
+        let sum = graph.add(&a, &b, None);
+        let clones: Vec<_> = (0..10).map(|_| sum.clone()).collect();
+        let operation = sum.operation();
+        let inputs = operation.input_tensors();
+
+        assert_eq!(graph.live_handles(), 3 + clones.len() + 1 + inputs.len());
+    }
+
+    assert_eq!(graph.live_handles(), 0);
+}
+
+#[test]
+fn test_tensor_keeps_graph_alive() {
+    let shape = MPSShape::from_slice(&[2, 2]);
+    let tensor = {
+        let graph = MPSGraph::new();
+        let a = graph.placeholder(&shape, MPSDataType::Float32, Some("A"));
+        graph.negative(&a, Some("negated"))
+    };
+
+    // The graph wrapper is gone, but the tensor still holds the graph
+    let graph = tensor.operation().graph();
+    assert_eq!(tensor.name(), "negated");
+    assert_eq!(graph.live_handles(), 1);
+
+    let doubled = graph.add(&tensor, &tensor, None);
+    assert_eq!(doubled.dimensions(), vec![2, 2]);
+
+    drop(doubled);
+    drop(tensor);
+    assert_eq!(graph.live_handles(), 0);
+}
@@ -2,14 +2,42 @@
 // This module contains comprehensive tests for all Objective-C calls
 // to ensure they work without crashes and behave as expected.
 
-mod activation_ops_tests;
-mod arithmetic_ops_tests;
-mod command_buffer_tests;
+mod cast_tests;
 mod core_tests;
-mod graph_tests;
-mod matrix_ops_tests;
-mod memory_management_tests;
-mod pooling_ops_tests;
-mod resize_ops_tests;
-mod tensor_data_tests;
-mod tensor_tests;
+mod descriptors_tests;
+mod dims_tests;
+mod dlpack_tests;
+mod dot_tests;
+mod error_tests;
+mod executable_cache_tests;
+#[cfg(feature = "gguf")]
+mod gguf_tests;
+mod host_tests;
+mod interpret_tests;
+mod ir_tests;
+mod ir_text_tests;
+#[cfg(feature = "ndarray")]
+mod ndarray_interop_tests;
+#[cfg(feature = "npy")]
+mod npy_tests;
+#[cfg(feature = "onnx")]
+mod onnx_tests;
+#[cfg(feature = "safetensors")]
+mod safetensors_tests;
+mod tensor_shape_ops_tests;
+
+cfg_apple! {
+    mod activation_ops_tests;
+    mod arithmetic_ops_tests;
+    mod async_run_tests;
+    mod command_buffer_tests;
+    mod control_flow_ops_tests;
+    mod convolution_ops_tests;
+    mod graph_tests;
+    mod matrix_ops_tests;
+    mod memory_management_tests;
+    mod pooling_ops_tests;
+    mod resize_ops_tests;
+    mod tensor_data_tests;
+    mod tensor_tests;
+}
@@ -0,0 +1,54 @@
+use crate::host::HostTensor;
+use ndarray::{s, ArrayD, IxDyn};
+
+#[test]
+fn test_ndarray_host_tensor_conversions() {
+    let array = ArrayD::from_shape_vec(IxDyn(&[2, 3]), vec![0i32, 1, 2, 3, 4, 5]).unwrap();
+    let tensor = HostTensor::from(array.clone());
+    assert_eq!(tensor.shape(), &[2, 3]);
+    assert_eq!(tensor.storage(), &[0, 1, 2, 3, 4, 5]);
+    assert_eq!(ArrayD::from(tensor.clone()), array);
+    assert_eq!(tensor.to_ndarray(), array);
+
+    // Transposed, reversed and sliced layouts are copied into standard order
+    let transposed = HostTensor::from_ndarray(&array.t());
+    assert_eq!(transposed.shape(), &[3, 2]);
+    assert_eq!(transposed.storage(), &[0, 3, 1, 4, 2, 5]);
+    let reversed = HostTensor::from_ndarray(&array.slice(s![.., ..;-1]));
+    assert_eq!(reversed.storage(), &[2, 1, 0, 5, 4, 3]);
+    let row = HostTensor::from(array.clone().slice_move(s![1.., ..]));
+    assert_eq!(row.shape(), &[1, 3]);
+    assert_eq!(row.storage(), &[3, 4, 5]);
+
+    // A permuted host tensor comes back in standard order
+    let permuted = tensor.permute(&[1, 0]).unwrap();
+    assert_eq!(ArrayD::from(permuted), array.t());
+
+    let scalar = HostTensor::from(ndarray::arr0(1.5f32).into_dyn());
+    assert!(scalar.shape().is_empty());
+    assert_eq!(scalar.storage(), &[1.5]);
+}
+
+#[cfg(target_vendor = "apple")]
+#[test]
+fn test_ndarray_tensor_data_and_constants() {
+    use crate::core::MPSDataType;
+    use crate::graph::MPSGraph;
+    use crate::tensor_data::MPSGraphTensorData;
+    use std::collections::HashMap;
+
+    let array = ArrayD::from_shape_vec(IxDyn(&[2, 2]), vec![1.0f32, 2.0, 3.0, 4.0]).unwrap();
+    let tensor_data = MPSGraphTensorData::from_ndarray(&array.t());
+    assert_eq!(tensor_data.shape().dimensions(), vec![2, 2]);
+    assert_eq!(tensor_data.data_type(), MPSDataType::Float32);
+    assert_eq!(tensor_data.to_ndarray::<f32>().unwrap(), array.t());
+    assert!(tensor_data.to_ndarray::<i32>().is_err());
+
+    let graph = MPSGraph::new();
+    let constant = graph.constant_from_ndarray(&array);
+    let doubled = graph.add(&constant, &constant, None);
+    let results = graph
+        .try_run_with_feeds(&HashMap::new(), std::slice::from_ref(&doubled))
+        .unwrap();
+    assert_eq!(results[&doubled].to_ndarray::<f32>().unwrap(), &array * 2.0);
+}
@@ -0,0 +1,222 @@
+use crate::core::MPSDataType;
+use crate::npy::{self, descr, parse_descr, NpyArray, NpyError};
+#[cfg(target_vendor = "apple")]
+use crate::tensor_data::MPSGraphTensorData;
+use std::io::{Cursor, Write};
+
+/// Builds a version 1.0 `.npy` file from a raw header dict and data bytes
+fn npy_file(header: &str, data: &[u8]) -> Vec<u8> {
+    let mut file = b"\x93NUMPY\x01\x00".to_vec();
+    file.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    file.extend_from_slice(header.as_bytes());
+    file.extend_from_slice(data);
+    file
+}
+
+#[test]
+fn test_dtype_mapping() {
+    for data_type in MPSDataType::ALL {
+        if let Some(descr) = descr(data_type) {
+            assert_eq!(parse_descr(descr).unwrap(), (data_type, false));
+        }
+    }
+    assert_eq!(descr(MPSDataType::BFloat16), None);
+    assert_eq!(descr(MPSDataType::Int4), None);
+
+    assert_eq!(parse_descr("<f4").unwrap(), (MPSDataType::Float32, false));
+    assert_eq!(parse_descr(">i8").unwrap(), (MPSDataType::Int64, true));
+    assert_eq!(parse_descr("|b1").unwrap(), (MPSDataType::Bool, false));
+    assert_eq!(parse_descr("<c8").unwrap(), (MPSDataType::Complex32, false));
+    assert_eq!(parse_descr("f2").unwrap(), (MPSDataType::Float16, false));
+
+    let error = parse_descr("<U4").unwrap_err();
+    assert!(matches!(error, NpyError::UnsupportedDtype(ref d) if d == "<U4"));
+    assert_eq!(
+        error.to_string(),
+        "NumPy dtype '<U4' has no MPSDataType counterpart"
+    );
+}
+
+#[test]
+fn test_npy_round_trip() {
+    let values = [1.0f32, -2.5, 3.25, 4.0, 5.5, -6.0];
+    let array = NpyArray::from_slice(&[2, 3], &values).unwrap();
+
+    let mut file = Vec::new();
+    array.write(&mut file).unwrap();
+    let header_len = u16::from_le_bytes([file[8], file[9]]) as usize;
+    assert_eq!((10 + header_len) % 64, 0);
+    assert_eq!(file[10 + header_len - 1], b'\n');
+    assert!(String::from_utf8_lossy(&file[10..10 + header_len])
+        .starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }"));
+
+    let read = NpyArray::read(file.as_slice()).unwrap();
+    assert_eq!(read, array);
+    assert_eq!(read.to_vec::<f32>().unwrap(), values);
+    assert!(matches!(
+        read.to_vec::<i32>(),
+        Err(NpyError::DataTypeMismatch {
+            expected: MPSDataType::Float32,
+            actual: MPSDataType::Int32,
+        })
+    ));
+
+    // One-dimensional shapes need the trailing comma, scalars are an empty tuple
+    let vector = NpyArray::from_slice(&[3], &[true, false, true]).unwrap();
+    let mut file = Vec::new();
+    vector.write(&mut file).unwrap();
+    assert!(String::from_utf8_lossy(&file).contains("'shape': (3,)"));
+    assert_eq!(NpyArray::read(file.as_slice()).unwrap(), vector);
+
+    let scalar = NpyArray::from_slice(&[], &[7i64]).unwrap();
+    let mut file = Vec::new();
+    scalar.write(&mut file).unwrap();
+    assert!(String::from_utf8_lossy(&file).contains("'shape': ()"));
+    assert_eq!(
+        NpyArray::read(file.as_slice())
+            .unwrap()
+            .to_vec::<i64>()
+            .unwrap(),
+        [7]
+    );
+}
+
+#[test]
+fn test_npy_fortran_order_and_endianness() {
+    // [[1, 2, 3], [4, 5, 6]] stored column by column
+    let data: Vec<u8> = [1i16, 4, 2, 5, 3, 6]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let file = npy_file(
+        "{'descr': '<i2', 'fortran_order': True, 'shape': (2, 3), }\n",
+        &data,
+    );
+    let array = NpyArray::read(file.as_slice()).unwrap();
+    assert_eq!(array.shape(), &[2, 3]);
+    assert_eq!(array.to_vec::<i16>().unwrap(), [1, 2, 3, 4, 5, 6]);
+
+    let data: Vec<u8> = [1.5f64, -2.0]
+        .iter()
+        .flat_map(|v| v.to_be_bytes())
+        .collect();
+    let file = npy_file(
+        "{\"descr\": \">f8\", \"fortran_order\": False, \"shape\": (2,)}",
+        &data,
+    );
+    let array = NpyArray::read(file.as_slice()).unwrap();
+    assert_eq!(array.to_vec::<f64>().unwrap(), [1.5, -2.0]);
+}
+
+#[test]
+fn test_npy_errors() {
+    assert!(matches!(
+        NpyArray::read(&b"NOTNUMPY\x00\x00"[..]),
+        Err(NpyError::BadMagic)
+    ));
+    assert!(matches!(
+        NpyArray::read(&b"\x93NUMPY\x04\x00\x00\x00"[..]),
+        Err(NpyError::UnsupportedVersion(4, 0))
+    ));
+
+    let file = npy_file("{'descr': '<f4', 'shape': (2,), }", &[0; 8]);
+    assert!(matches!(
+        NpyArray::read(file.as_slice()),
+        Err(NpyError::InvalidHeader(ref reason)) if reason.contains("missing fortran_order")
+    ));
+
+    let file = npy_file(
+        "{'descr': '<M8[ns]', 'fortran_order': False, 'shape': (1,), }",
+        &[0; 8],
+    );
+    assert!(matches!(
+        NpyArray::read(file.as_slice()),
+        Err(NpyError::UnsupportedDtype(_))
+    ));
+
+    let file = npy_file(
+        "{'descr': '<f4', 'fortran_order': False, 'shape': (4,), }",
+        &[0; 12],
+    );
+    assert!(matches!(
+        NpyArray::read(file.as_slice()),
+        Err(NpyError::SizeMismatch {
+            expected: 16,
+            actual: 12
+        })
+    ));
+
+    // Shapes from the header are untrusted: overflowing ones are rejected and huge ones
+    // fail on the short read instead of allocating up front
+    let file = npy_file(
+        "{'descr': '<f4', 'fortran_order': False, 'shape': (4294967296, 4294967296), }",
+        &[0; 8],
+    );
+    assert!(matches!(
+        NpyArray::read(file.as_slice()),
+        Err(NpyError::InvalidHeader(ref reason)) if reason.contains("more bytes")
+    ));
+    let file = npy_file(
+        "{'descr': '<f4', 'fortran_order': False, 'shape': (1099511627776,), }",
+        &[0; 8],
+    );
+    assert!(matches!(
+        NpyArray::read(file.as_slice()),
+        Err(NpyError::SizeMismatch { actual: 8, .. })
+    ));
+    assert!(matches!(
+        NpyArray::new(&[usize::MAX, 2], MPSDataType::Float32, Vec::new()),
+        Err(NpyError::ShapeTooLarge(_))
+    ));
+
+    let bfloat = NpyArray::new(&[1], MPSDataType::BFloat16, vec![0; 2]).unwrap();
+    assert!(matches!(
+        bfloat.write(Vec::new()),
+        Err(NpyError::UnsupportedDataType(MPSDataType::BFloat16))
+    ));
+}
+
+#[test]
+fn test_npz_round_trip() {
+    let weights = NpyArray::from_slice(&[2, 2], &[1.0f32, 2.0, 3.0, 4.0]).unwrap();
+    let labels = NpyArray::from_slice(&[3], &[0u8, 1, 2]).unwrap();
+
+    let mut archive = Cursor::new(Vec::new());
+    npy::write_npz(&mut archive, [("weights", &weights), ("labels", &labels)]).unwrap();
+    archive.set_position(0);
+
+    let arrays = npy::read_npz(archive).unwrap();
+    assert_eq!(arrays.len(), 2);
+    assert_eq!(arrays["weights"], weights);
+    assert_eq!(arrays["labels"], labels);
+}
+
+#[test]
+fn test_npz_compressed_entries() {
+    // numpy.savez_compressed deflates each entry
+    let bias = NpyArray::from_slice(&[4], &[0.5f64; 4]).unwrap();
+    let mut entry = Vec::new();
+    bias.write(&mut entry).unwrap();
+
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    writer.start_file("bias.npy", options).unwrap();
+    writer.write_all(&entry).unwrap();
+    let archive = writer.finish().unwrap();
+
+    let arrays = npy::read_npz(Cursor::new(archive.into_inner())).unwrap();
+    assert_eq!(arrays["bias"], bias);
+}
+
+#[cfg(target_vendor = "apple")]
+#[test]
+fn test_npy_tensor_data_round_trip() {
+    let array = NpyArray::from_slice(&[2, 2], &[1i32, 2, 3, 4]).unwrap();
+    let tensor_data: MPSGraphTensorData = array.to_tensor_data();
+    assert_eq!(tensor_data.data_type(), MPSDataType::Int32);
+    assert_eq!(tensor_data.shape().dimensions(), vec![2, 2]);
+
+    let read = NpyArray::from_tensor_data(&tensor_data).unwrap();
+    assert_eq!(read, array);
+}
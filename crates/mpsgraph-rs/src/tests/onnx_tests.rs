@@ -0,0 +1,469 @@
+use crate::core::MPSDataType;
+use crate::dims::Shape;
+use crate::ir::{OpKind, Value};
+use crate::onnx::proto::{
+    attribute_type, data_type, AttributeProto, Dimension, GraphProto, ModelProto, NodeProto,
+    OperatorSetIdProto, TensorProto, TensorShapeProto, TypeProto, TypeProtoTensor, ValueInfoProto,
+};
+use crate::onnx::{self, supported_op_types, OnnxError, OnnxModel};
+use prost::Message;
+use std::collections::HashMap;
+
+/// Declares a graph input or output; negative sizes become symbolic dimensions
+fn value_info(name: &str, elem_type: i32, dims: &[i64]) -> ValueInfoProto {
+    let dim = dims
+        .iter()
+        .map(|&size| Dimension {
+            dim_value: (size >= 0).then_some(size),
+            dim_param: (size < 0).then(|| "N".to_string()),
+        })
+        .collect();
+    ValueInfoProto {
+        name: name.to_string(),
+        r#type: Some(TypeProto {
+            tensor_type: Some(TypeProtoTensor {
+                elem_type,
+                shape: Some(TensorShapeProto { dim }),
+            }),
+        }),
+    }
+}
+
+fn node(
+    op_type: &str,
+    inputs: &[&str],
+    outputs: &[&str],
+    attributes: Vec<AttributeProto>,
+) -> NodeProto {
+    NodeProto {
+        input: inputs.iter().map(|s| s.to_string()).collect(),
+        output: outputs.iter().map(|s| s.to_string()).collect(),
+        op_type: op_type.to_string(),
+        attribute: attributes,
+        ..Default::default()
+    }
+}
+
+fn int(name: &str, i: i64) -> AttributeProto {
+    AttributeProto {
+        name: name.to_string(),
+        i,
+        r#type: attribute_type::INT,
+        ..Default::default()
+    }
+}
+
+fn ints(name: &str, ints: &[i64]) -> AttributeProto {
+    AttributeProto {
+        name: name.to_string(),
+        ints: ints.to_vec(),
+        r#type: attribute_type::INTS,
+        ..Default::default()
+    }
+}
+
+fn string(name: &str, s: &str) -> AttributeProto {
+    AttributeProto {
+        name: name.to_string(),
+        s: s.as_bytes().to_vec(),
+        r#type: attribute_type::STRING,
+        ..Default::default()
+    }
+}
+
+fn floats(name: &str, dims: &[i64], values: &[f32]) -> TensorProto {
+    TensorProto {
+        name: name.to_string(),
+        dims: dims.to_vec(),
+        data_type: data_type::FLOAT,
+        float_data: values.to_vec(),
+        ..Default::default()
+    }
+}
+
+fn int64s(name: &str, values: &[i64]) -> TensorProto {
+    TensorProto {
+        name: name.to_string(),
+        dims: vec![values.len() as i64],
+        data_type: data_type::INT64,
+        raw_data: values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        ..Default::default()
+    }
+}
+
+fn model_bytes(
+    opset: i64,
+    nodes: Vec<NodeProto>,
+    initializer: Vec<TensorProto>,
+    input: Vec<ValueInfoProto>,
+    output: Vec<ValueInfoProto>,
+) -> Vec<u8> {
+    ModelProto {
+        ir_version: 8,
+        producer_name: "fixture".to_string(),
+        graph: Some(GraphProto {
+            node: nodes,
+            name: "test".to_string(),
+            initializer,
+            input,
+            output,
+            ..Default::default()
+        }),
+        opset_import: vec![OperatorSetIdProto {
+            domain: String::new(),
+            version: opset,
+        }],
+        ..Default::default()
+    }
+    .encode_to_vec()
+}
+
+#[test]
+fn test_onnx_supported_op_types() {
+    let ops = supported_op_types();
+    assert!(ops.windows(2).all(|pair| pair[0] < pair[1]));
+    for op in [
+        "Conv",
+        "Gemm",
+        "MatMul",
+        "Softmax",
+        "LayerNormalization",
+        "Gather",
+    ] {
+        assert!(ops.contains(&op), "{}", op);
+    }
+    for op in ["Resize", "TopK", "NonMaxSuppression"] {
+        assert!(ops.contains(&op), "{}", op);
+    }
+    assert_eq!(
+        onnx::data_type(data_type::FLOAT16).unwrap(),
+        MPSDataType::Float16
+    );
+    assert!(matches!(
+        onnx::data_type(data_type::STRING),
+        Err(OnnxError::UnsupportedDataType(8))
+    ));
+}
+
+#[test]
+fn test_onnx_import_conv_classifier() {
+    let weights: Vec<f32> = (0..4 * 3 * 3 * 3).map(|i| i as f32 / 100.0).collect();
+    let bytes = model_bytes(
+        13,
+        vec![
+            node(
+                "Conv",
+                &["image", "conv.weight", "conv.bias"],
+                &["conv"],
+                vec![ints("pads", &[1, 1, 1, 1]), ints("kernel_shape", &[3, 3])],
+            ),
+            node("Relu", &["conv"], &["relu"], vec![]),
+            node(
+                "MaxPool",
+                &["relu"],
+                &["pool"],
+                vec![ints("kernel_shape", &[2, 2]), ints("strides", &[2, 2])],
+            ),
+            node("GlobalAveragePool", &["pool"], &["gap"], vec![]),
+            node("Flatten", &["gap"], &["flat"], vec![]),
+            node(
+                "Gemm",
+                &["flat", "fc.weight", "fc.bias"],
+                &["logits"],
+                vec![int("transB", 1)],
+            ),
+            node("Softmax", &["logits"], &["probs"], vec![]),
+        ],
+        vec![
+            floats("conv.weight", &[4, 3, 3, 3], &weights),
+            floats("conv.bias", &[4], &[0.0; 4]),
+            floats("fc.weight", &[10, 4], &[0.5; 40]),
+            floats("fc.bias", &[10], &[0.0; 10]),
+        ],
+        vec![value_info("image", data_type::FLOAT, &[-1, 3, 8, 8])],
+        vec![value_info("probs", data_type::FLOAT, &[-1, 10])],
+    );
+
+    let model = OnnxModel::from_bytes(&bytes).unwrap();
+    assert_eq!(model.opset_version(), 13);
+    assert_eq!(model.ir_version(), 8);
+    assert_eq!(model.producer_name(), "fixture");
+    assert!(model.unsupported_ops().is_empty());
+
+    let import = model.import().unwrap();
+    let graph = import.graph();
+    let (name, image) = &import.inputs()[0];
+    assert_eq!(name, "image");
+    assert_eq!(graph.placeholders(), [*image]);
+    assert_eq!(graph.shape(*image), &Shape::from_i64(&[-1, 3, 8, 8]));
+
+    let conv = import.tensor("conv").unwrap();
+    assert_eq!(graph.shape(conv), &Shape::from_i64(&[-1, 4, 8, 8]));
+    assert_eq!(
+        graph.shape(import.tensor("pool").unwrap()),
+        &Shape::from_i64(&[-1, 4, 4, 4])
+    );
+    let (name, probs) = &import.outputs()[0];
+    assert_eq!(name, "probs");
+    assert_eq!(graph.shape(*probs), &Shape::from_i64(&[-1, 10]));
+    assert_eq!(graph.data_type(*probs), MPSDataType::Float32);
+    assert_eq!(graph.tensor_by_name("probs"), Some(*probs));
+
+    // Initializers are recorded as constants under their own names
+    let weight = graph.producer(import.tensor("conv.weight").unwrap());
+    assert!(matches!(weight.kind, OpKind::Constant { ref data, .. } if data.len() == 4 * 108));
+    assert_eq!(weight.name.as_deref(), Some("conv.weight"));
+}
+
+#[test]
+fn test_onnx_import_evaluates_on_host() {
+    let bytes = model_bytes(
+        17,
+        vec![
+            node("Gemm", &["x", "w", "c"], &["gemm"], vec![int("transB", 1)]),
+            node("Relu", &["gemm"], &["relu"], vec![]),
+            node(
+                "ReduceMean",
+                &["relu"],
+                &["mean"],
+                vec![ints("axes", &[1]), int("keepdims", 0)],
+            ),
+            node(
+                "LayerNormalization",
+                &["relu", "gamma", "beta"],
+                &["norm"],
+                vec![],
+            ),
+            node("Reshape", &["norm", "flat_shape"], &["flat"], vec![]),
+        ],
+        vec![
+            floats("w", &[2, 3], &[1.0, 0.0, 1.0, 0.0, 1.0, 0.0]),
+            floats("c", &[2], &[-5.0, 1.0]),
+            floats("gamma", &[2], &[1.0, 1.0]),
+            floats("beta", &[2], &[0.0, 0.0]),
+            int64s("flat_shape", &[-1]),
+        ],
+        vec![value_info("x", data_type::FLOAT, &[2, 3])],
+        vec![
+            value_info("mean", data_type::FLOAT, &[2]),
+            value_info("flat", data_type::FLOAT, &[4]),
+        ],
+    );
+    let import = OnnxModel::from_bytes(&bytes).unwrap().import().unwrap();
+    let graph = import.graph();
+    let x = import.inputs()[0].1;
+    let mean = import.tensor("mean").unwrap();
+    let flat = import.tensor("flat").unwrap();
+    assert_eq!(graph.shape(mean), &Shape::from_static(&[2]));
+    assert_eq!(graph.shape(flat), &Shape::from_static(&[4]));
+
+    let feeds = HashMap::from([(
+        x,
+        Value::new(&[2, 3], vec![1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]),
+    )]);
+    let results = graph.interpret(&feeds, &[mean, flat]).unwrap();
+    // x * w^T + c = [[-1, 3], [5, 6]], then relu
+    assert_eq!(results[&mean].as_f32().unwrap(), &[1.5, 5.5]);
+    let normalized = results[&flat].as_f32().unwrap();
+    for (actual, expected) in normalized.iter().zip([-1.0, 1.0, -1.0, 1.0]) {
+        assert!((actual - expected).abs() < 1e-3, "{:?}", normalized);
+    }
+}
+
+#[test]
+fn test_onnx_import_detection_ops() {
+    let bytes = model_bytes(
+        13,
+        vec![
+            node(
+                "Resize",
+                &["features", "", "scales"],
+                &["upsampled"],
+                vec![string("mode", "linear")],
+            ),
+            node("TopK", &["logits", "k"], &["values", "indices"], vec![]),
+            node(
+                "Gather",
+                &["table", "indices"],
+                &["embedded"],
+                vec![int("axis", 0)],
+            ),
+            node(
+                "NonMaxSuppression",
+                &["boxes", "scores", "max_boxes", "iou"],
+                &["selected"],
+                vec![int("center_point_box", 1)],
+            ),
+        ],
+        vec![
+            floats("scales", &[4], &[1.0, 1.0, 2.0, 2.0]),
+            int64s("k", &[3]),
+            floats("table", &[10, 16], &[0.0; 160]),
+            int64s("max_boxes", &[5]),
+            floats("iou", &[1], &[0.5]),
+        ],
+        vec![
+            value_info("features", data_type::FLOAT, &[1, 8, 7, 5]),
+            value_info("logits", data_type::FLOAT, &[-1, 10]),
+            value_info("boxes", data_type::FLOAT, &[1, 20, 4]),
+            value_info("scores", data_type::FLOAT, &[1, 3, 20]),
+        ],
+        vec![
+            value_info("upsampled", data_type::FLOAT, &[1, 8, 14, 10]),
+            value_info("values", data_type::FLOAT, &[-1, 3]),
+            value_info("embedded", data_type::FLOAT, &[-1, 3, 16]),
+            value_info("selected", data_type::INT32, &[1, -1]),
+        ],
+    );
+    let import = OnnxModel::from_bytes(&bytes).unwrap().import().unwrap();
+    let graph = import.graph();
+    let shape = |name: &str| graph.shape(import.tensor(name).unwrap()).clone();
+
+    assert_eq!(shape("upsampled"), Shape::from_static(&[1, 8, 14, 10]));
+    let resize = graph.producer(import.tensor("upsampled").unwrap());
+    assert!(matches!(
+        resize.kind,
+        OpKind::Resize {
+            size: [14, 10],
+            center_result: true,
+            align_corners: false,
+            ..
+        }
+    ));
+
+    assert_eq!(shape("values"), Shape::from_i64(&[-1, 3]));
+    let indices = import.tensor("indices").unwrap();
+    assert_eq!(graph.data_type(indices), MPSDataType::Int64);
+    assert_eq!(shape("embedded"), Shape::from_i64(&[-1, 3, 16]));
+
+    let selected = import.tensor("selected").unwrap();
+    assert_eq!(graph.data_type(selected), MPSDataType::Int32);
+    assert_eq!(shape("selected"), Shape::from_i64(&[1, -1]));
+    assert!(matches!(
+        graph.producer(selected).kind,
+        OpKind::NonMaximumSuppression {
+            iou_threshold: 0.5,
+            per_class_suppression: true,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_onnx_unsupported_report() {
+    let mut custom = node("FusedGelu", &["x"], &["gelu"], vec![]);
+    custom.domain = "com.microsoft".to_string();
+    custom.name = "gelu_0".to_string();
+    let bytes = model_bytes(
+        14,
+        vec![
+            node("Relu", &["x"], &["relu"], vec![]),
+            node("Einsum", &["relu", "relu"], &["a"], vec![]),
+            custom,
+            node("Einsum", &["a", "gelu"], &["b"], vec![]),
+        ],
+        vec![],
+        vec![value_info("x", data_type::FLOAT, &[2, 2])],
+        vec![value_info("b", data_type::FLOAT, &[2, 2])],
+    );
+    let model = OnnxModel::from_bytes(&bytes).unwrap();
+    let report = model.unsupported_ops();
+    assert_eq!(report.nodes.len(), 3);
+    assert_eq!(report.nodes[1].name, "gelu_0");
+    assert_eq!(
+        report.nodes[1].qualified_op_type(),
+        "com.microsoft.FusedGelu"
+    );
+    assert_eq!(
+        report.op_types().into_iter().collect::<Vec<_>>(),
+        [
+            ("Einsum".to_string(), 2),
+            ("com.microsoft.FusedGelu".to_string(), 1)
+        ]
+    );
+    let message = report.to_string();
+    assert!(
+        message.starts_with("3 unsupported onnx node(s):"),
+        "{}",
+        message
+    );
+    assert!(message.contains("Einsum x2: a, b"), "{}", message);
+
+    match model.import() {
+        Err(OnnxError::Unsupported(error)) => assert_eq!(error, report),
+        other => panic!(
+            "expected an unsupported report, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+}
+
+#[test]
+fn test_onnx_errors() {
+    let relu = || vec![node("Relu", &["x"], &["y"], vec![])];
+    let input = || vec![value_info("x", data_type::FLOAT, &[2])];
+    let output = |name: &str| vec![value_info(name, data_type::FLOAT, &[2])];
+
+    let bytes = model_bytes(11, relu(), vec![], input(), output("y"));
+    assert!(matches!(
+        OnnxModel::from_bytes(&bytes),
+        Err(OnnxError::UnsupportedOpset(11))
+    ));
+    assert!(matches!(
+        OnnxModel::from_bytes(&[0xff, 0xff, 0xff]),
+        Err(OnnxError::Decode(_))
+    ));
+    assert!(matches!(
+        OnnxModel::from_bytes(&ModelProto::default().encode_to_vec()),
+        Err(OnnxError::MissingGraph)
+    ));
+
+    let bytes = model_bytes(13, relu(), vec![], input(), output("z"));
+    let model = OnnxModel::from_bytes(&bytes).unwrap();
+    assert!(matches!(model.import(), Err(OnnxError::UnknownValue(ref name)) if name == "z"));
+
+    // Reshape targets must be known at import time
+    let nodes = vec![node("Reshape", &["x", "shape"], &["y"], vec![])];
+    let inputs = vec![
+        value_info("x", data_type::FLOAT, &[2]),
+        value_info("shape", data_type::INT64, &[1]),
+    ];
+    let bytes = model_bytes(13, nodes, vec![], inputs, output("y"));
+    match OnnxModel::from_bytes(&bytes).unwrap().import() {
+        Err(OnnxError::Node {
+            op_type, reason, ..
+        }) => {
+            assert_eq!(op_type, "Reshape");
+            assert!(reason.contains("initializer or constant"), "{}", reason);
+        }
+        other => panic!("expected a node error, got {:?}", other.map(|_| ())),
+    }
+
+    // Shape errors from the IR name the node
+    let nodes = vec![node("Add", &["x", "w"], &["y"], vec![])];
+    let weights = vec![floats("w", &[3], &[1.0; 3])];
+    let bytes = model_bytes(13, nodes, weights, input(), output("y"));
+    assert!(matches!(
+        OnnxModel::from_bytes(&bytes).unwrap().import(),
+        Err(OnnxError::Node { ref node, .. }) if node == "y"
+    ));
+
+    let mut weights = floats("w", &[2], &[]);
+    weights.data_location = 1;
+    let nodes = vec![node("Add", &["x", "w"], &["y"], vec![])];
+    let bytes = model_bytes(13, nodes, vec![weights], input(), output("y"));
+    assert!(matches!(
+        OnnxModel::from_bytes(&bytes).unwrap().import(),
+        Err(OnnxError::ExternalData(ref name)) if name == "w"
+    ));
+
+    // Initializer shapes are untrusted and must not overflow the byte count
+    let weights = floats("w", &[1 << 40, 1 << 40], &[]);
+    let nodes = vec![node("Add", &["x", "w"], &["y"], vec![])];
+    let bytes = model_bytes(13, nodes, vec![weights], input(), output("y"));
+    assert!(matches!(
+        OnnxModel::from_bytes(&bytes).unwrap().import(),
+        Err(OnnxError::InvalidTensor { ref name, ref reason })
+            if name == "w" && reason.contains("more bytes")
+    ));
+}
@@ -0,0 +1,252 @@
+use crate::core::MPSDataType;
+use crate::safetensors::{
+    self, data_type, dtype, Dtype, LoadOptions, SafetensorsError, SafetensorsFile, TensorView,
+    WeightKind,
+};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[cfg(target_vendor = "apple")]
+use crate::graph::MPSGraph;
+
+/// Builds a safetensors file from a raw JSON header and data bytes
+fn safetensors_file(header: &str, data: &[u8]) -> Vec<u8> {
+    let mut file = (header.len() as u64).to_le_bytes().to_vec();
+    file.extend_from_slice(header.as_bytes());
+    file.extend_from_slice(data);
+    file
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "mpsgraph-{}-{}.safetensors",
+        name,
+        std::process::id()
+    ))
+}
+
+fn f32_bytes(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+#[test]
+fn test_dtype_mapping() {
+    for data_type in MPSDataType::ALL {
+        if let Some(dtype) = dtype(data_type) {
+            assert_eq!(safetensors::data_type(dtype).unwrap(), data_type);
+        }
+    }
+    assert_eq!(dtype(MPSDataType::Complex32), None);
+    assert_eq!(dtype(MPSDataType::Int4), None);
+    assert!(matches!(
+        data_type(Dtype::F8_E4M3),
+        Err(SafetensorsError::UnsupportedDtype(Dtype::F8_E4M3))
+    ));
+}
+
+#[test]
+fn test_safetensors_round_trip() {
+    let weight = f32_bytes(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    let bias = [1u8, 0, 1];
+    let metadata = BTreeMap::from([("format".to_string(), "pt".to_string())]);
+
+    let bytes = safetensors::serialize(
+        [
+            (
+                "layer.weight",
+                TensorView::new(MPSDataType::Float32, &[2, 3], &weight).unwrap(),
+            ),
+            (
+                "layer.mask",
+                TensorView::new(MPSDataType::Bool, &[3], &bias).unwrap(),
+            ),
+        ],
+        Some(&metadata),
+    )
+    .unwrap();
+
+    let file = SafetensorsFile::from_bytes(bytes).unwrap();
+    assert!(!file.is_mapped());
+    assert_eq!(file.len(), 2);
+    assert_eq!(file.metadata(), &metadata);
+
+    let mut names: Vec<&str> = file.names().collect();
+    names.sort();
+    assert_eq!(names, ["layer.mask", "layer.weight"]);
+
+    let view = file.tensor("layer.weight").unwrap();
+    assert_eq!(view.data_type(), MPSDataType::Float32);
+    assert_eq!(view.shape(), &[2, 3]);
+    assert_eq!(view.data(), weight.as_slice());
+    assert_eq!(file.tensor("layer.mask").unwrap().data(), &bias);
+
+    assert!(matches!(
+        file.tensor("layer.missing"),
+        Err(SafetensorsError::MissingTensor(ref name)) if name == "layer.missing"
+    ));
+    assert!(matches!(
+        TensorView::new(MPSDataType::Float32, &[4], &weight),
+        Err(SafetensorsError::SizeMismatch {
+            expected: 16,
+            actual: 24
+        })
+    ));
+}
+
+#[test]
+fn test_safetensors_memory_mapped() {
+    let path = temp_path("mapped");
+    let data = f32_bytes(&[0.5, -1.5]);
+    safetensors::save(
+        &path,
+        [(
+            "scale",
+            TensorView::new(MPSDataType::Float32, &[2], &data).unwrap(),
+        )],
+        None,
+    )
+    .unwrap();
+
+    let file = SafetensorsFile::open(&path).unwrap();
+    assert!(file.is_mapped());
+    assert!(file.metadata().is_empty());
+    assert_eq!(file.tensor("scale").unwrap().data(), data.as_slice());
+    drop(file);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_safetensors_resolve_renames_and_casts() {
+    let weight = f32_bytes(&[1.0, -0.5]);
+    let steps = 7i64.to_le_bytes();
+    let bytes = safetensors::serialize(
+        [
+            (
+                "model.weight",
+                TensorView::new(MPSDataType::Float32, &[2], &weight).unwrap(),
+            ),
+            (
+                "model.steps",
+                TensorView::new(MPSDataType::Int64, &[1], &steps).unwrap(),
+            ),
+            (
+                "optimizer.momentum",
+                TensorView::new(MPSDataType::Float32, &[2], &weight).unwrap(),
+            ),
+        ],
+        None,
+    )
+    .unwrap();
+    let file = SafetensorsFile::from_bytes(bytes).unwrap();
+
+    let options = LoadOptions::new()
+        .with_rename(|name| name.strip_prefix("model.").map(str::to_string))
+        .with_cast(MPSDataType::Float32, MPSDataType::Float16);
+    assert_eq!(options.kind(), WeightKind::Constant);
+
+    let mut resolved = file.resolve(&options).unwrap();
+    resolved.sort_by(|a, b| a.name.cmp(&b.name));
+    assert_eq!(resolved.len(), 2);
+
+    assert_eq!(resolved[0].name, "steps");
+    assert_eq!(resolved[0].data_type, MPSDataType::Int64);
+    assert!(matches!(resolved[0].data, Cow::Borrowed(_)));
+
+    assert_eq!(resolved[1].name, "weight");
+    assert_eq!(resolved[1].data_type, MPSDataType::Float16);
+    assert_eq!(resolved[1].shape, [2]);
+    assert_eq!(resolved[1].data.as_ref(), &[0x00, 0x3c, 0x00, 0xb8]);
+
+    let options = LoadOptions::new().with_cast(MPSDataType::Float32, MPSDataType::Int32);
+    assert!(matches!(
+        file.resolve(&options),
+        Err(SafetensorsError::UnsupportedCast {
+            from: MPSDataType::Float32,
+            to: MPSDataType::Int32,
+        })
+    ));
+
+    let options = LoadOptions::new()
+        .with_rename(|name| Some(name.replace("optimizer.momentum", "model.weight")));
+    assert!(matches!(
+        file.resolve(&options),
+        Err(SafetensorsError::DuplicateName(ref name)) if name == "model.weight"
+    ));
+}
+
+#[test]
+fn test_safetensors_errors() {
+    let header = r#"{"scale":{"dtype":"F8_E4M3","shape":[2],"data_offsets":[0,2]}}"#;
+    let file = SafetensorsFile::from_bytes(safetensors_file(header, &[0x38, 0x40])).unwrap();
+    assert!(matches!(
+        file.tensor("scale"),
+        Err(SafetensorsError::UnsupportedDtype(Dtype::F8_E4M3))
+    ));
+    // Skipping the tensor by name avoids the error
+    let options = LoadOptions::new().with_rename(|_| None);
+    assert!(file.resolve(&options).unwrap().is_empty());
+
+    let header = r#"{"scale":{"dtype":"F32","shape":[2],"data_offsets":[0,8]}}"#;
+    assert!(matches!(
+        SafetensorsFile::from_bytes(safetensors_file(header, &[0; 4])),
+        Err(SafetensorsError::Format(_))
+    ));
+    assert!(matches!(
+        SafetensorsFile::from_bytes(vec![1, 2, 3]),
+        Err(SafetensorsError::Format(_))
+    ));
+
+    let data = [0u8; 8];
+    assert!(matches!(
+        safetensors::serialize(
+            [(
+                "z",
+                TensorView::new(MPSDataType::Complex32, &[1], &data).unwrap()
+            )],
+            None
+        ),
+        Err(SafetensorsError::UnsupportedDataType(
+            MPSDataType::Complex32
+        ))
+    ));
+}
+
+#[cfg(target_vendor = "apple")]
+#[test]
+fn test_safetensors_load_into_graph() {
+    let weight = f32_bytes(&[1.0, 2.0, 3.0, 4.0]);
+    let bytes = safetensors::serialize(
+        [(
+            "weight",
+            TensorView::new(MPSDataType::Float32, &[2, 2], &weight).unwrap(),
+        )],
+        None,
+    )
+    .unwrap();
+    let file = SafetensorsFile::from_bytes(bytes).unwrap();
+
+    let graph = MPSGraph::new();
+    let options = LoadOptions::new().with_cast(MPSDataType::Float32, MPSDataType::Float16);
+    let tensors = file.load_into(&graph, &options).unwrap();
+    assert_eq!(tensors["weight"].data_type(), MPSDataType::Float16);
+
+    let options = LoadOptions::new().with_kind(WeightKind::Variable);
+    let variables = file.load_into(&graph, &options).unwrap();
+    let variable = &variables["weight"];
+    assert_eq!(variable.data_type(), MPSDataType::Float32);
+
+    // Double the variable and write a checkpoint of the new value
+    let doubled = graph.add(variable, variable, None);
+    let assign = graph.assign_variable(variable, &doubled, None);
+    graph.run_with_feeds_and_ops(&Default::default(), &[], &[assign]);
+
+    let path = temp_path("checkpoint");
+    safetensors::save_variables(&path, &graph, [("weight", variable)], None).unwrap();
+    let checkpoint = SafetensorsFile::open(&path).unwrap();
+    let view = checkpoint.tensor("weight").unwrap();
+    assert_eq!(view.shape(), &[2, 2]);
+    assert_eq!(view.data(), f32_bytes(&[2.0, 4.0, 6.0, 8.0]).as_slice());
+    drop(checkpoint);
+    std::fs::remove_file(&path).unwrap();
+}
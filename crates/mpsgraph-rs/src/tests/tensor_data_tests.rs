@@ -29,6 +29,7 @@ fn test_tensor_data_creation() {
     assert_eq!(tensor_data_i32.data_type(), MPSDataType::Int32);
     assert_eq!(tensor_data_f16.data_type(), MPSDataType::Float16);
     assert_eq!(tensor_data_i8.data_type(), MPSDataType::Int8);
+    assert_eq!(tensor_data_i8.try_data_type().unwrap(), MPSDataType::Int8);
 
     // Check shape
     let shape_obj_f32 = tensor_data_f32.shape();
@@ -112,3 +113,24 @@ fn test_tensor_data_different_shapes() {
     let shape_obj_4d = tensor_data_4d.shape();
     assert_eq!(shape_obj_4d.dimensions(), vec![2, 2, 2, 2]);
 }
+
+#[cfg(feature = "half")]
+#[test]
+fn test_tensor_data_from_f32_as_half() {
+    let values = [0.5f32, 1.0, -2.25, 65504.0];
+
+    let f16_data = MPSGraphTensorData::from_f32_as_f16(&values, &[2, 2]);
+    assert_eq!(f16_data.data_type(), MPSDataType::Float16);
+    assert_eq!(f16_data.shape().dimensions(), vec![2, 2]);
+    let stored = f16_data.to_host::<half::f16>().unwrap();
+    let stored: Vec<f32> = stored.iter().map(|v| v.to_f32()).collect();
+    assert_eq!(stored, values);
+
+    // 65504 is the largest f16 but isn't representable in bfloat16
+    let bf16_data = MPSGraphTensorData::from_f32_as_bf16(&values[..3], &[3]);
+    assert_eq!(bf16_data.data_type(), MPSDataType::BFloat16);
+    assert_eq!(bf16_data.shape().dimensions(), vec![3]);
+    let stored = bf16_data.to_host::<half::bf16>().unwrap();
+    let stored: Vec<f32> = stored.iter().map(|v| v.to_f32()).collect();
+    assert_eq!(stored, values[..3]);
+}
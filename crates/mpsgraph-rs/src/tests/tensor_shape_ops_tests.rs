@@ -0,0 +1,213 @@
+use crate::tensor_shape_ops::{expand_slice_ellipsis, slice_for_graph, MPSGraphSliceMasks};
+#[cfg(target_vendor = "apple")]
+use crate::{core::MPSDataType, graph::MPSGraph, shape::MPSShape, tensor_data::MPSGraphTensorData};
+#[cfg(target_vendor = "apple")]
+use std::collections::HashMap;
+
+// Reads the first `count` values of a Float32 result
+#[cfg(target_vendor = "apple")]
+fn read_f32(data: &MPSGraphTensorData, count: usize) -> Vec<f32> {
+    let values = data
+        .to_host::<f32>()
+        .expect("result data should be readable")
+        .into_vec();
+    values[..count].to_vec()
+}
+
+#[test]
+fn test_expand_slice_ellipsis() {
+    // x[1:3:1, ..., ::2] on a rank 4 input, with the first dimension squeezed
+    let masks = MPSGraphSliceMasks {
+        start_mask: 0b100,
+        squeeze_mask: 0b001,
+        ellipsis_mask: 0b010,
+        ..Default::default()
+    };
+    let expanded = expand_slice_ellipsis(4, &[1, 0, 2], &[3, 0, 4], &[1, 1, 2], &masks);
+
+    assert_eq!(expanded.starts, vec![1, 0, 0, 2]);
+    assert_eq!(expanded.ends, vec![3, 0, 0, 4]);
+    assert_eq!(expanded.strides, vec![1, 1, 1, 2]);
+    assert_eq!(
+        expanded.masks,
+        MPSGraphSliceMasks {
+            start_mask: 0b1110,
+            end_mask: 0b0110,
+            squeeze_mask: 0b0001,
+            ellipsis_mask: 0,
+        }
+    );
+}
+
+#[test]
+fn test_expand_slice_fills_trailing_dimensions() {
+    let expanded = expand_slice_ellipsis(3, &[1], &[2], &[1], &MPSGraphSliceMasks::default());
+
+    assert_eq!(expanded.starts, vec![1, 0, 0]);
+    assert_eq!(expanded.ends, vec![2, 0, 0]);
+    assert_eq!(expanded.strides, vec![1, 1, 1]);
+    assert_eq!(expanded.masks.start_mask, 0b110);
+    assert_eq!(expanded.masks.end_mask, 0b110);
+}
+
+#[test]
+fn test_expand_slice_empty_ellipsis() {
+    // The ellipsis covers no dimensions when every dimension is given explicitly
+    let masks = MPSGraphSliceMasks {
+        end_mask: 0b100,
+        ellipsis_mask: 0b001,
+        ..Default::default()
+    };
+    let expanded = expand_slice_ellipsis(2, &[0, 1, 2], &[0, 2, 0], &[1, 1, 1], &masks);
+
+    assert_eq!(expanded.starts, vec![1, 2]);
+    assert_eq!(expanded.ends, vec![2, 0]);
+    assert_eq!(expanded.masks.start_mask, 0);
+    assert_eq!(expanded.masks.end_mask, 0b10);
+}
+
+#[test]
+#[should_panic(expected = "at most one ellipsis bit")]
+fn test_expand_slice_rejects_two_ellipses() {
+    let masks = MPSGraphSliceMasks {
+        ellipsis_mask: 0b11,
+        ..Default::default()
+    };
+    expand_slice_ellipsis(3, &[0, 0], &[0, 0], &[1, 1], &masks);
+}
+
+#[test]
+#[should_panic(expected = "but the input has rank")]
+fn test_expand_slice_rejects_too_many_entries() {
+    expand_slice_ellipsis(1, &[0, 0], &[1, 1], &[1, 1], &MPSGraphSliceMasks::default());
+}
+
+#[test]
+fn test_slice_for_graph_without_ellipsis_ignores_rank() {
+    // Unranked tensors report rank 0, and MPSGraph fills the trailing dimensions itself
+    let masks = MPSGraphSliceMasks {
+        end_mask: 0b10,
+        ..Default::default()
+    };
+    let slice = slice_for_graph(0, &[1, 0], &[2, 0], &[1, 2], &masks);
+
+    assert_eq!(slice.starts, vec![1, 0]);
+    assert_eq!(slice.ends, vec![2, 0]);
+    assert_eq!(slice.strides, vec![1, 2]);
+    assert_eq!(slice.masks, masks);
+}
+
+#[test]
+fn test_slice_for_graph_expands_ellipsis() {
+    let masks = MPSGraphSliceMasks {
+        ellipsis_mask: 0b1,
+        ..Default::default()
+    };
+    let slice = slice_for_graph(2, &[0, 1], &[0, 2], &[1, 1], &masks);
+
+    assert_eq!(slice.starts, vec![0, 1]);
+    assert_eq!(slice.masks.start_mask, 0b01);
+    assert_eq!(slice.masks.ellipsis_mask, 0);
+}
+
+#[test]
+#[cfg(target_vendor = "apple")]
+fn test_slice_ops() {
+    let graph = MPSGraph::new();
+    let shape = MPSShape::from_slice(&[2, 3]);
+    let x = graph.placeholder(&shape, MPSDataType::Float32, Some("X"));
+    let update = graph.placeholder(
+        &MPSShape::from_slice(&[2, 1]),
+        MPSDataType::Float32,
+        Some("Update"),
+    );
+
+    // x[:, 1:3]
+    let sliced = graph.slice(&x, 1, 1, 2, Some("Slice"));
+
+    // x[..., ::2]
+    let masks = MPSGraphSliceMasks {
+        ellipsis_mask: 0b01,
+        end_mask: 0b10,
+        ..Default::default()
+    };
+    let strided = graph.strided_slice(&x, &[0, 0], &[0, 0], &[1, 2], &masks, Some("Strided"));
+
+    // x[:, 1:2] = update
+    let updated = graph.slice_update(
+        &x,
+        &update,
+        &[0, 1],
+        &[2, 2],
+        &[1, 1],
+        &MPSGraphSliceMasks::default(),
+        Some("SliceUpdate"),
+    );
+
+    let mut feeds = HashMap::new();
+    feeds.insert(
+        x.clone(),
+        MPSGraphTensorData::new(
+            &[0.0f32, 1.0, 2.0, 3.0, 4.0, 5.0],
+            &[2, 3],
+            MPSDataType::Float32,
+        ),
+    );
+    feeds.insert(
+        update.clone(),
+        MPSGraphTensorData::new(&[-1.0f32, -2.0], &[2, 1], MPSDataType::Float32),
+    );
+
+    let results = graph.run_with_feeds(&feeds, &[sliced.clone(), strided.clone(), updated.clone()]);
+
+    assert_eq!(read_f32(&results[&sliced], 4), vec![1.0, 2.0, 4.0, 5.0]);
+    assert_eq!(read_f32(&results[&strided], 4), vec![0.0, 2.0, 3.0, 5.0]);
+    assert_eq!(
+        read_f32(&results[&updated], 6),
+        vec![0.0, -1.0, 2.0, 3.0, -2.0, 5.0]
+    );
+}
+
+#[test]
+#[cfg(target_vendor = "apple")]
+fn test_slice_gradient() {
+    let graph = MPSGraph::new();
+    let x = graph.placeholder(
+        &MPSShape::from_slice(&[2, 3]),
+        MPSDataType::Float32,
+        Some("X"),
+    );
+    let gradient = graph.placeholder(
+        &MPSShape::from_slice(&[2, 2]),
+        MPSDataType::Float32,
+        Some("Gradient"),
+    );
+
+    // Gradient of x[:, 1:3]
+    let shape = graph.shape_of(&x, None);
+    let x_gradient = graph.slice_gradient(
+        &gradient,
+        &shape,
+        &[0, 1],
+        &[2, 3],
+        &[1, 1],
+        &MPSGraphSliceMasks::default(),
+        Some("SliceGradient"),
+    );
+
+    let mut feeds = HashMap::new();
+    feeds.insert(
+        x.clone(),
+        MPSGraphTensorData::new(&[0.0f32; 6], &[2, 3], MPSDataType::Float32),
+    );
+    feeds.insert(
+        gradient.clone(),
+        MPSGraphTensorData::new(&[1.0f32, 2.0, 3.0, 4.0], &[2, 2], MPSDataType::Float32),
+    );
+
+    let results = graph.run_with_feeds(&feeds, std::slice::from_ref(&x_gradient));
+    assert_eq!(
+        read_f32(&results[&x_gradient], 6),
+        vec![0.0, 1.0, 2.0, 0.0, 3.0, 4.0]
+    );
+}
@@ -14,6 +14,7 @@ fn test_tensor_properties() {
     // Test data type retrieval
     assert_eq!(tensor1.data_type(), MPSDataType::Float32);
     assert_eq!(tensor2.data_type(), MPSDataType::Int32);
+    assert_eq!(tensor2.try_data_type().unwrap(), MPSDataType::Int32);
 
     // Test shape retrieval
     let shape1_retrieved = tensor1.shape();
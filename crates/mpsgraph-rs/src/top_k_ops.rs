@@ -43,7 +43,7 @@ impl MPSGraph {
             let values = objc2::ffi::objc_retain(values as *mut _);
             let indices = objc2::ffi::objc_retain(indices as *mut _);
 
-            (MPSGraphTensor(values), MPSGraphTensor(indices))
+            (MPSGraphTensor::from_retained(values), MPSGraphTensor::from_retained(indices))
         }
     }
 
@@ -84,7 +84,7 @@ impl MPSGraph {
             let values = objc2::ffi::objc_retain(values as *mut _);
             let indices = objc2::ffi::objc_retain(indices as *mut _);
 
-            (MPSGraphTensor(values), MPSGraphTensor(indices))
+            (MPSGraphTensor::from_retained(values), MPSGraphTensor::from_retained(indices))
         }
     }
 
@@ -125,7 +125,7 @@ impl MPSGraph {
             let values = objc2::ffi::objc_retain(values as *mut _);
             let indices = objc2::ffi::objc_retain(indices as *mut _);
 
-            (MPSGraphTensor(values), MPSGraphTensor(indices))
+            (MPSGraphTensor::from_retained(values), MPSGraphTensor::from_retained(indices))
         }
     }
 
@@ -161,7 +161,7 @@ impl MPSGraph {
             let values = objc2::ffi::objc_retain(values as *mut _);
             let indices = objc2::ffi::objc_retain(indices as *mut _);
 
-            (MPSGraphTensor(values), MPSGraphTensor(indices))
+            (MPSGraphTensor::from_retained(values), MPSGraphTensor::from_retained(indices))
         }
     }
 
@@ -200,7 +200,7 @@ impl MPSGraph {
             let values = objc2::ffi::objc_retain(values as *mut _);
             let indices = objc2::ffi::objc_retain(indices as *mut _);
 
-            (MPSGraphTensor(values), MPSGraphTensor(indices))
+            (MPSGraphTensor::from_retained(values), MPSGraphTensor::from_retained(indices))
         }
     }
 
@@ -239,7 +239,7 @@ impl MPSGraph {
             let values = objc2::ffi::objc_retain(values as *mut _);
             let indices = objc2::ffi::objc_retain(indices as *mut _);
 
-            (MPSGraphTensor(values), MPSGraphTensor(indices))
+            (MPSGraphTensor::from_retained(values), MPSGraphTensor::from_retained(indices))
         }
     }
 
@@ -271,7 +271,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -306,7 +306,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 
@@ -341,7 +341,7 @@ impl MPSGraph {
             ];
 
             let result = objc2::ffi::objc_retain(result as *mut _);
-            MPSGraphTensor(result)
+            MPSGraphTensor::from_retained(result)
         }
     }
 }
@@ -1,19 +1,18 @@
 fn main() {
-    // Only compile on Apple platforms
-    #[cfg(not(any(
-        target_os = "macos",
-        target_os = "ios",
-        target_os = "tvos",
-        target_os = "watchos",
-        target_os = "visionos"
-    )))]
-    {
+    // The build script runs on the host, so check the target through Cargo's environment.
+    // On other platforms only the portable parts of mpsgraph (shapes, IR) are available.
+    let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let apple = matches!(
+        target_os.as_str(),
+        "macos" | "ios" | "tvos" | "watchos" | "visionos"
+    );
+
+    if apple {
+        println!("cargo:rustc-link-lib=framework=MetalPerformanceShadersGraph");
+    } else {
         // Disable doctests when not on Apple platforms (for CI)
         println!("cargo:rustc-cfg=disable_doctests");
-        panic!("This crate only supports Apple platforms (macOS, iOS, tvOS, watchOS, visionOS)");
     }
 
-    // Print cargo metadata
-    println!("cargo:rustc-link-lib=framework=MetalPerformanceShadersGraph");
     println!("cargo:rerun-if-changed=build.rs");
 }
@@ -11,9 +11,12 @@
 //!
 //! Run with: `cargo run --example tensor_ops_example`
 
+#[cfg(target_vendor = "apple")]
 use mpsgraph_tools::prelude::*;
+#[cfg(target_vendor = "apple")]
 use mpsgraph_tools::tensor_ops::{GraphExt, Tensor};
 
+#[cfg(target_vendor = "apple")]
 fn main() {
     // Create graph and input tensors
     let graph = MPSGraph::new();
@@ -76,3 +79,8 @@ fn main() {
     println!("- Sequence generation: arange");
     println!("- Range operations: clip(&a, &min, &max)");
 }
+
+#[cfg(not(target_vendor = "apple"))]
+fn main() {
+    eprintln!("This example needs Metal and only runs on Apple platforms");
+}
@@ -14,6 +14,7 @@
 pub use mpsgraph::*;
 
 // Tensor operations module (additional functionality beyond vanilla mpsgraph)
+#[cfg(target_vendor = "apple")]
 pub mod tensor_ops;
 
 /// Convenience prelude module with most commonly used items
@@ -22,11 +23,13 @@ pub mod prelude {
     pub use mpsgraph::prelude::*;
 
     // Tensor operations (our additional functionality)
+    #[cfg(target_vendor = "apple")]
     pub use crate::tensor_ops;
+    #[cfg(target_vendor = "apple")]
     pub use crate::tensor_ops::{
         abs, clip, exp, gelu, log, pow, relu, sigmoid, silu, sqrt, square, tanh, GraphExt, Tensor,
     };
 }
 
-#[cfg(test)]
+#[cfg(all(test, target_vendor = "apple"))]
 mod tests;